@@ -0,0 +1,608 @@
+//! Agents as actors
+//!
+//! A started agent runs as a tokio task that reads instructions from its
+//! mailbox and reports through the session's event stream. The run loop is
+//! owned by the parent's scope (see [`crate::scope`]), so terminating the
+//! parent stops it. Instructions are sent through the [`AgentHandle`]:
+//! assign a task, deliver a message, interrupt, or shut down.
+//!
+//! The actual work is done by the agent's [`AgentRuntime`]. While a task
+//! is running the mailbox is still read, so an interrupt or shutdown drops
+//! the task at once; other mail waits until the task is finished. A result
+//! a reviewer bounces back is worked on again with the feedback. A paused
+//! agent neither reads mail nor advances its task until it is resumed.
+//! With heartbeats enabled the loop also beats at their interval, so a
+//! loop that died or is blocked shows up as unresponsive.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use warhorn::SubmissionId;
+
+use crate::agent::AgentHandle;
+use crate::error::GoblinError;
+use crate::heartbeat::tick;
+use crate::mailbox::Mail;
+use crate::result::TaskResult;
+use crate::runtime::{AgentRuntime, TaskAssignment};
+use crate::session::SessionHandle;
+
+/// Run an agent's mailbox loop until it is told to stop or its mailbox closes
+pub async fn run(
+    session: SessionHandle,
+    agent: AgentHandle,
+    runtime: Arc<dyn AgentRuntime>,
+    mut mailbox: mpsc::UnboundedReceiver<Mail>,
+    sub_id: SubmissionId,
+) -> Result<(), GoblinError> {
+    agent.initialize(&sub_id).await?;
+    debug!(agent_id = %agent.id(), runtime = runtime.name(), "Agent actor started");
+
+    let mut beats = session.heartbeat_policy().map(|policy| policy.ticker());
+    if beats.is_some() {
+        agent.beat();
+    }
+    let mut deferred = VecDeque::new();
+    // The last task handed in, which a reviewer may still bounce
+    let mut finished: Option<TaskAssignment> = None;
+    loop {
+        agent.wait_resumed().await;
+        let mail = match deferred.pop_front() {
+            Some(mail) => mail,
+            None => tokio::select! {
+                biased;
+                _ = agent.wait_paused() => continue,
+                _ = tick(&mut beats) => {
+                    agent.beat();
+                    continue;
+                }
+                mail = mailbox.recv() => match mail {
+                    Some(mail) => mail,
+                    None => break,
+                },
+            },
+        };
+
+        match mail {
+            Mail::AssignTask { task_id, instructions } => {
+                agent.assign_task(task_id);
+                let assignment = TaskAssignment {
+                    task_id,
+                    instructions,
+                    sub_id: sub_id.clone(),
+                };
+                agent.checkpoint(Some(assignment.clone()));
+                let work = runtime.run_task(&agent, &assignment);
+                tokio::pin!(work);
+
+                let outcome = loop {
+                    // While paused the task is not polled, so it resumes where it stopped
+                    agent.wait_resumed().await;
+                    tokio::select! {
+                        biased;
+                        _ = agent.wait_paused() => continue,
+                        _ = tick(&mut beats) => {
+                            agent.beat();
+                            continue;
+                        }
+                        outcome = &mut work => break Some(outcome),
+                        mail = mailbox.recv() => match mail {
+                            Some(Mail::Interrupt) => break None,
+                            Some(Mail::Shutdown { reason }) => {
+                                info!(agent_id = %agent.id(), reason = %reason, "Agent shutting down mid-task");
+                                return Ok(());
+                            }
+                            Some(other) => deferred.push_back(other),
+                            None => return Ok(()),
+                        },
+                    }
+                };
+
+                let Some(outcome) = outcome else {
+                    debug!(agent_id = %agent.id(), task_id = %task_id, "Agent interrupted");
+                    agent.clear_task();
+                    agent.checkpoint(None);
+                    continue;
+                };
+                // Errors that would fail again, like a refused tool, are not retried
+                let retryable = !matches!(&outcome, Err(e) if !e.is_retryable());
+                let mut result = outcome.unwrap_or_else(|e| TaskResult::failure(task_id, e.to_string()));
+                result.task_id = task_id;
+                let result = result.from_agent(agent.id());
+                if retryable && session.retry_task(&agent, &assignment, &result, &sub_id) {
+                    continue;
+                }
+                if let Err(e) = session.record_result(result, &sub_id).await {
+                    warn!(agent_id = %agent.id(), task_id = %task_id, error = %e, "Failed to record result");
+                }
+                agent.checkpoint(None);
+                finished = Some(assignment.clone());
+            }
+            Mail::Message { from, content } => {
+                if let Err(e) = runtime.on_message(&agent, from, &content).await {
+                    warn!(agent_id = %agent.id(), error = %e, "Failed to handle message");
+                }
+            }
+            Mail::Interrupt => {
+                agent.clear_task();
+            }
+            Mail::Shutdown { reason } => {
+                info!(agent_id = %agent.id(), reason = %reason, "Agent shutting down");
+                break;
+            }
+            Mail::ChildrenCompleted { task_id, results } => {
+                debug!(agent_id = %agent.id(), task_id = %task_id, children = results.len(), "Children completed");
+                if let Err(e) = runtime.on_children_completed(&agent, task_id, &results).await {
+                    warn!(agent_id = %agent.id(), task_id = %task_id, error = %e, "Failed to handle completed children");
+                }
+            }
+            Mail::Handoff { handoff } => {
+                debug!(agent_id = %agent.id(), from = %handoff.from, "Received handoff");
+                agent.record(handoff.to_entry());
+            }
+            Mail::ResultBounced { task_id, feedback } => {
+                debug!(agent_id = %agent.id(), task_id = %task_id, "Result bounced back");
+                // The task is still ours: take it up again with the feedback
+                match finished.take().filter(|a| a.task_id == task_id) {
+                    Some(assignment) => deferred.push_front(Mail::AssignTask {
+                        task_id,
+                        instructions: format!("{}\n\nA reviewer sent your result back: {}", assignment.instructions, feedback),
+                    }),
+                    None => warn!(agent_id = %agent.id(), task_id = %task_id, "Bounced result of a task the agent no longer has"),
+                }
+            }
+            Mail::FailureReport { report } => {
+                debug!(agent_id = %agent.id(), from = %report.agent_id, task_id = %report.task_id, "Received failure report");
+                if let Err(e) = runtime.on_failure_report(&agent, &report).await {
+                    warn!(agent_id = %agent.id(), error = %e, "Failed to handle failure report");
+                }
+            }
+            Mail::ApprovalRequest { request } => {
+                match runtime.decide_approval(&agent, &request).await {
+                    Some(approved) => agent.decide_approval(&request.call_id, approved),
+                    None => agent.decline_approval(&request.call_id),
+                };
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use async_trait::async_trait;
+    use trinkets::ToolRegistry;
+    use warhorn::{AgentConfig, AgentRole, AgentStatus, SessionConfig, TaskId};
+
+    use crate::approval::{ApprovalPolicy, ExecRequest, TimeoutAction};
+    use crate::config::SessionOptions;
+    use crate::protocol::GoblinEvent;
+    use crate::runtime::Runtimes;
+    use crate::scope::JoinOutcome;
+    use crate::session::Session;
+
+    /// Works forever when told to, otherwise finishes at once
+    struct Stubborn;
+
+    #[async_trait]
+    impl AgentRuntime for Stubborn {
+        fn name(&self) -> &str {
+            "stubborn"
+        }
+
+        async fn run_task(&self, _agent: &AgentHandle, a: &TaskAssignment) -> Result<TaskResult, GoblinError> {
+            if a.instructions == "forever" {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            Ok(TaskResult::success(a.task_id, "done"))
+        }
+
+        async fn on_message(
+            &self,
+            _agent: &AgentHandle,
+            _from: Option<warhorn::AgentId>,
+            content: &str,
+        ) -> Result<(), GoblinError> {
+            match content {
+                "crash" => Err(GoblinError::TaskError("crashed".into())),
+                "panic" => panic!("stubborn panicked"),
+                _ => Ok(()),
+            }
+        }
+
+        async fn decide_approval(&self, _agent: &AgentHandle, request: &ExecRequest) -> Option<bool> {
+            match request.command.first().map(String::as_str) {
+                Some("sudo") => None,
+                program => Some(program.is_some_and(|program| program != "rm")),
+            }
+        }
+    }
+
+    async fn eventually(check: impl Fn() -> bool) {
+        for _ in 0..100 {
+            if check() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition not reached");
+    }
+
+    async fn next_matching<T>(
+        rx: &mut mpsc::UnboundedReceiver<GoblinEvent>,
+        f: impl Fn(GoblinEvent) -> Option<T>,
+    ) -> T {
+        loop {
+            let event = rx.recv().await.expect("event stream closed");
+            if let Some(found) = f(event) {
+                return found;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_actor_follows_instructions() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let session = SessionHandle::new(
+            Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx)
+                .with_runtimes(Runtimes::new(Arc::new(Stubborn))),
+        );
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = session.spawn_agent(root, None, &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+
+        // Mail sent before the actor starts is queued
+        let endless = TaskId::new();
+        worker.assign(endless, "forever").unwrap();
+        session.start_actor(&worker.id(), &sub_id).unwrap();
+        assert!(session.start_actor(&worker.id(), &sub_id).is_err());
+
+        eventually(|| worker.current_task() == Some(endless)).await;
+        assert_eq!(worker.status(), AgentStatus::Running);
+
+        // An interrupt drops the running task
+        worker.interrupt().unwrap();
+        eventually(|| worker.current_task().is_none()).await;
+
+        let quick = TaskId::new();
+        worker.assign(quick, "now").unwrap();
+        let result = next_matching(&mut rx, |e| match e {
+            GoblinEvent::TaskResult { result, .. } => Some(result),
+            _ => None,
+        })
+        .await;
+        assert_eq!((result.task_id, result.agent_id), (quick, Some(worker.id())));
+        assert_eq!(session.result(&quick), Some(result));
+
+        worker.shutdown("done").unwrap();
+        let joined = next_matching(&mut rx, |e| match e {
+            GoblinEvent::AgentJoined { agent_id, outcome, .. } => Some((agent_id, outcome)),
+            _ => None,
+        })
+        .await;
+        assert_eq!(joined, (worker.id(), JoinOutcome::Completed));
+        assert!(worker.message(None, "anyone there?").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_completed_children_reach_the_runtime() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = SessionHandle::new(
+            Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx)
+                .with_runtimes(Runtimes::new(Arc::new(Stubborn))),
+        );
+        let sub_id = SubmissionId::new();
+        let lead = session.spawn_agent(AgentConfig::default(), None, &sub_id).unwrap();
+        session.start_actor(&lead.id(), &sub_id).unwrap();
+
+        // The default hook records the results for the lead's next turn
+        let before = lead.history().len();
+        let task_id = TaskId::new();
+        lead.deliver(Mail::ChildrenCompleted {
+            task_id,
+            results: vec![TaskResult::success(TaskId::new(), "half"), TaskResult::success(TaskId::new(), "other half")],
+        });
+        eventually(|| lead.history().len() == before + 1).await;
+    }
+
+    #[tokio::test]
+    async fn test_bounced_result_is_worked_on_again() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let session = SessionHandle::new(
+            Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx)
+                .with_runtimes(Runtimes::new(Arc::new(Stubborn))),
+        );
+        let sub_id = SubmissionId::new();
+        let worker = session.spawn_agent(AgentConfig::default(), None, &sub_id).unwrap();
+        session.start_actor(&worker.id(), &sub_id).unwrap();
+
+        let task_id = TaskId::new();
+        worker.assign(task_id, "now").unwrap();
+        let results = |e: GoblinEvent| match e {
+            GoblinEvent::TaskResult { result, .. } => Some(result.task_id),
+            _ => None,
+        };
+        assert_eq!(next_matching(&mut rx, results).await, task_id);
+
+        worker.deliver(Mail::ResultBounced {
+            task_id,
+            feedback: "Try harder".into(),
+        });
+        assert_eq!(next_matching(&mut rx, results).await, task_id);
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_restarts_then_gives_up() {
+        use crate::supervise::{Backoff, SupervisionPolicies, SupervisionPolicy};
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let policy = SupervisionPolicy::OneForOne {
+            max_restarts: 1,
+            backoff: Backoff::new(1, 1),
+        };
+        let session = SessionHandle::new(
+            Session::with_options(
+                SessionConfig::default(),
+                SessionOptions::new().with_supervision(SupervisionPolicies::default().with_role("worker", policy)),
+                Arc::new(ToolRegistry::new()),
+                tx,
+            )
+            .with_runtimes(Runtimes::new(Arc::new(Stubborn))),
+        );
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = session.spawn_agent(root, None, &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+        session.start_actor(&worker.id(), &sub_id).unwrap();
+
+        // A message the runtime fails on is logged; the agent keeps working
+        worker.message(None, "crash").unwrap();
+        let task_id = TaskId::new();
+        worker.assign(task_id, "now").unwrap();
+        let restarted = next_matching(&mut rx, |e| match e {
+            GoblinEvent::AgentRestarting { .. } => Some(true),
+            GoblinEvent::TaskResult { .. } => Some(false),
+            _ => None,
+        })
+        .await;
+        assert!(!restarted);
+
+        worker.message(None, "panic").unwrap();
+        let attempt = next_matching(&mut rx, |e| match e {
+            GoblinEvent::AgentRestarting { agent_id, attempt, .. } if agent_id == worker.id() => Some(attempt),
+            _ => None,
+        })
+        .await;
+        assert_eq!(attempt, 1);
+        next_matching(&mut rx, |e| matches!(e, GoblinEvent::AgentRestarted { .. }).then_some(())).await;
+
+        // A panic after the last restart terminates the agent
+        worker.message(None, "panic").unwrap();
+        let outcome = next_matching(&mut rx, |e| match e {
+            GoblinEvent::AgentJoined { outcome, .. } => Some(outcome),
+            _ => None,
+        })
+        .await;
+        assert_eq!(outcome, JoinOutcome::Panicked);
+        eventually(|| session.get_agent(&worker.id()).is_none()).await;
+    }
+
+    #[tokio::test]
+    async fn test_failed_task_is_retried_until_it_succeeds() {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use crate::retry::RetryPolicy;
+        use crate::supervise::Backoff;
+
+        /// Fails its first two tasks
+        #[derive(Default)]
+        struct Flaky(AtomicU32);
+
+        #[async_trait]
+        impl AgentRuntime for Flaky {
+            fn name(&self) -> &str {
+                "flaky"
+            }
+
+            async fn run_task(&self, _agent: &AgentHandle, a: &TaskAssignment) -> Result<TaskResult, GoblinError> {
+                match self.0.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err(GoblinError::TaskError("flaked".into())),
+                    _ => Ok(TaskResult::success(a.task_id, "done")),
+                }
+            }
+        }
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let policy = RetryPolicy::new(3).with_backoff(Backoff::new(1, 5)).with_jitter(0.5);
+        let session = SessionHandle::new(
+            Session::with_options(
+                SessionConfig::default(),
+                SessionOptions::new().with_retry(policy),
+                Arc::new(ToolRegistry::new()),
+                tx,
+            )
+            .with_runtimes(Runtimes::new(Arc::new(Flaky::default()))),
+        );
+        let sub_id = SubmissionId::new();
+        let worker = session.spawn_agent(AgentConfig::default(), None, &sub_id).unwrap();
+        session.start_actor(&worker.id(), &sub_id).unwrap();
+        let task_id = TaskId::new();
+        worker.assign(task_id, "try").unwrap();
+
+        let mut retried = Vec::new();
+        let result = loop {
+            match rx.recv().await.expect("event stream closed") {
+                GoblinEvent::TaskRetried { attempt, .. } => retried.push(attempt),
+                GoblinEvent::TaskResult { result, .. } => break result,
+                _ => {}
+            }
+        };
+        assert_eq!(retried, vec![2, 3]);
+        assert!(result.is_success());
+        assert_eq!(result.attempts.iter().map(|a| a.attempt).collect::<Vec<_>>(), vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_unanswered_approval_escalates_to_parent() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let policy = ApprovalPolicy::new().with_timeout(Duration::from_millis(10), TimeoutAction::EscalateToParent);
+        let session = SessionHandle::new(
+            Session::with_options(
+                SessionConfig::default(),
+                SessionOptions::new().with_approval_policy(policy),
+                Arc::new(ToolRegistry::new()),
+                tx,
+            )
+            .with_runtimes(Runtimes::new(Arc::new(Stubborn))),
+        );
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = session.spawn_agent(root, None, &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+        session.start_actor(&root.id(), &sub_id).unwrap();
+
+        let command = |line: &str| line.split_whitespace().map(String::from).collect();
+        let ask = |line: &'static str| worker.request_exec_approval(command(line), &sub_id);
+        assert!(ask("make test").await.unwrap());
+        assert!(!ask("rm -rf /").await.unwrap());
+
+        let decided_by = next_matching(&mut rx, |e| match e {
+            GoblinEvent::ExecApprovalDecided { decided_by, .. } => Some(decided_by),
+            _ => None,
+        })
+        .await;
+        assert_eq!(decided_by, Some(root.id()));
+    }
+
+    #[tokio::test]
+    async fn test_delegate_lead_decides_before_the_client() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let policy = ApprovalPolicy::new().with_delegate("domain_lead");
+        let session = SessionHandle::new(
+            Session::with_options(
+                SessionConfig::default(),
+                SessionOptions::new().with_approval_policy(policy),
+                Arc::new(ToolRegistry::new()),
+                tx,
+            )
+            .with_runtimes(Runtimes::new(Arc::new(Stubborn))),
+        );
+        let sub_id = SubmissionId::new();
+        let lead = AgentConfig {
+            role: AgentRole::DomainLead { domain: "build".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead, None, &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id).unwrap();
+        session.start_actor(&lead.id(), &sub_id).unwrap();
+
+        let command = |line: &str| line.split_whitespace().map(String::from).collect();
+        let ask = |line: &'static str| worker.request_exec_approval(command(line), &sub_id);
+        assert!(ask("make test").await.unwrap());
+        let delegate = next_matching(&mut rx, |e| match e {
+            GoblinEvent::ApprovalDelegated { delegate, .. } => Some(delegate),
+            _ => None,
+        })
+        .await;
+        assert_eq!(delegate, lead.id());
+
+        // The lead declines, so the client is asked
+        let waiting = tokio::spawn({
+            let (worker, sub_id) = (worker.clone(), sub_id.clone());
+            async move { worker.request_exec_approval(command("sudo make install"), &sub_id).await }
+        });
+        let call_id = next_matching(&mut rx, |e| match e {
+            GoblinEvent::ExecApprovalRequested { request, .. } => Some(request.call_id),
+            _ => None,
+        })
+        .await;
+        assert!(session.approvals().resolve(&call_id, true));
+        assert!(waiting.await.unwrap().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_paused_actor_holds_its_mail() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let session = SessionHandle::new(
+            Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx)
+                .with_runtimes(Runtimes::new(Arc::new(Stubborn))),
+        );
+        let sub_id = SubmissionId::new();
+        let worker = session.spawn_agent(AgentConfig::default(), None, &sub_id).unwrap();
+        session.start_actor(&worker.id(), &sub_id).unwrap();
+        eventually(|| worker.status() == AgentStatus::Running).await;
+
+        session.pause_agent(&worker.id(), &sub_id).unwrap();
+        assert!(worker.summary().paused);
+        let task_id = TaskId::new();
+        worker.assign(task_id, "now").unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(worker.current_task(), None);
+        assert_eq!(session.result(&task_id), None);
+
+        session.resume_agent(&worker.id(), &sub_id).unwrap();
+        let result = next_matching(&mut rx, |e| match e {
+            GoblinEvent::TaskResult { result, .. } => Some(result),
+            _ => None,
+        })
+        .await;
+        assert_eq!(result.task_id, task_id);
+    }
+
+    #[tokio::test]
+    async fn test_restart_keeps_identity_and_checkpoint() {
+        use crate::history::HistoryEntry;
+        use crate::model::ChatRole;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let session = SessionHandle::new(
+            Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx)
+                .with_runtimes(Runtimes::new(Arc::new(Stubborn))),
+        );
+        let sub_id = SubmissionId::new();
+        let worker = session.spawn_agent(AgentConfig::default(), None, &sub_id).unwrap();
+        worker.record(HistoryEntry::message(ChatRole::User, "Remember this"));
+        session.start_actor(&worker.id(), &sub_id).unwrap();
+
+        let task_id = TaskId::new();
+        worker.assign(task_id, "forever").unwrap();
+        eventually(|| worker.current_task() == Some(task_id)).await;
+        // Progress made after the checkpoint is lost on restart
+        worker.record(HistoryEntry::message(ChatRole::Assistant, "Half done"));
+        while rx.try_recv().is_ok() {}
+
+        session.restart_agent(&worker.id(), &sub_id).await.unwrap();
+        eventually(|| worker.current_task() == Some(task_id)).await;
+        assert_eq!(session.get_agent(&worker.id()).unwrap().history_len(), 1);
+        assert_eq!(worker.status(), AgentStatus::Running);
+
+        let events: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert!(!events.iter().any(|e| matches!(e, GoblinEvent::Protocol(warhorn::Event::AgentSpawned { .. }))));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            GoblinEvent::AgentRestarted { restored_entries: 1, resumed_task: Some(t), .. } if *t == task_id
+        )));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            GoblinEvent::AgentJoined { outcome: JoinOutcome::Cancelled, .. }
+        )));
+    }
+}
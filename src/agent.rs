@@ -1,17 +1,44 @@
 //! Agent implementation - a single AI worker
 
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::Instant;
 use parking_lot::RwLock;
-use tokio::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, watch};
 use tracing::{debug, info, warn, instrument};
 
 use warhorn::{
-    AgentId, AgentRole, AgentStatus, AgentConfig, TaskId,
+    AgentId, AgentRole, AgentStatus, AgentConfig, CallId, TaskId,
     Event, SubmissionId, TokenUsage,
 };
 use trinkets::{ToolRegistry, ToolContext};
 
+use crate::approval::{Approvals, ExecRequest, SandboxLevel, TrustLevel};
+use crate::artifact::{Artifact, ArtifactId, ArtifactStore};
+use crate::audit::{AuditLog, AuditRecord};
+use crate::breaker::{counts_as_failure, CircuitBreakers};
+use crate::budget::{BudgetLedger, TokenBudget};
+use crate::coalesce::InFlightCalls;
+use crate::compaction::{Compaction, Compactor};
+use crate::config::UsageUpdates;
 use crate::error::GoblinError;
+use crate::fairshare::TaskSlots;
+use crate::history::{History, HistoryEntry};
+use crate::mailbox::{Mail, Mailbox};
+use crate::model::{ChatMessage, ChatRequest, ChatResponse, DeltaSink, ModelBinding};
+use crate::modelslots::ModelSlots;
+use crate::pricing::ModelPrice;
+use crate::protocol::GoblinEvent;
+use crate::runtime::TaskAssignment;
+use crate::scope::AgentScope;
+use crate::sandbox::{SandboxEscalation, SandboxEscalations, SandboxException, SandboxGrant, SandboxProfile, SANDBOX_PROFILE_ENV};
+use crate::store::{SessionStore, StoreRequest, STORE_TOOL};
+use crate::toolcache::{CallContext, ToolCache};
+use crate::toollimit::ToolLimits;
+use crate::mcp::{McpTool, McpTools};
+use crate::toolscope::ToolView;
+use crate::workspace::{ScratchDir, SCRATCH_DIR_ENV};
 
 /// A single AI agent worker
 pub struct Agent {
@@ -24,17 +51,181 @@ pub struct Agent {
     /// Configuration
     pub config: AgentConfig,
     /// Parent agent (None for orchestrator)
-    pub parent_id: Option<AgentId>,
+    parent_id: RwLock<Option<AgentId>>,
     /// Children agents (if can spawn)
     children: RwLock<Vec<AgentId>>,
-    /// Tool registry available to this agent
-    tools: Arc<ToolRegistry>,
+    /// Limit on children, initially from the configuration
+    max_children: RwLock<Option<usize>>,
+    /// Tools available to this agent
+    tools: ToolView,
     /// Current task being worked on
     current_task: RwLock<Option<TaskId>>,
     /// Token usage
     usage: RwLock<TokenUsage>,
+    /// Price of the agent's model, free if None
+    price: Option<ModelPrice>,
+    /// Dollars spent so far
+    cost_usd: RwLock<f64>,
+    /// How often usage is reported
+    usage_updates: UsageUpdates,
+    /// When usage was last reported, and the tokens spent by then
+    last_usage_report: RwLock<(Instant, u64)>,
     /// Event sender for reporting back
-    event_tx: mpsc::UnboundedSender<Event>,
+    event_tx: mpsc::UnboundedSender<GoblinEvent>,
+    /// Private scratch directory, removed on termination
+    scratch: RwLock<Option<ScratchDir>>,
+    /// Run loops of the agent's children
+    scope: AgentScope,
+    /// Incoming mail
+    mailbox: Mailbox,
+    /// Model the agent thinks with, if a provider serves it
+    model: Option<ModelBinding>,
+    /// Sequence number of the next message event
+    message_seq: AtomicU64,
+    /// Conversation so far
+    history: RwLock<History>,
+    /// Compacts the history when it outgrows the context window
+    compactor: Option<Compactor>,
+    /// Whether the agent is paused
+    paused: watch::Sender<bool>,
+    /// State to restore if the agent is restarted
+    checkpoint: RwLock<Option<Checkpoint>>,
+    /// Blackboard shared with the other agents of the session
+    store: Option<Arc<SessionStore>>,
+    /// Session artifact store for files and large outputs
+    artifacts: Option<Arc<ArtifactStore>>,
+    /// Session approvals the agent asks before running commands
+    approvals: Option<Arc<Approvals>>,
+    /// How far the agent is trusted to act without oversight
+    trust: RwLock<TrustLevel>,
+    /// How the agent's tool executions are confined
+    sandbox_profile: SandboxProfile,
+    /// Access granted beyond the profile, until each expires
+    sandbox_exceptions: RwLock<Vec<SandboxException>>,
+    /// Session sandbox escalations the agent asks for more access
+    escalations: Option<Arc<SandboxEscalations>>,
+    /// Session audit log the agent's tool calls are recorded in
+    audit: Option<Arc<AuditLog>>,
+    /// Tokens the agent may spend, unlimited if None
+    budget: Option<TokenBudget>,
+    /// Whether the agent has spent its budget
+    budget_exceeded: AtomicBool,
+    /// Whether the agent is kept out of the worker pool after failing
+    quarantined: AtomicBool,
+    /// When the agent's run loop last beat, None before it started
+    last_beat: RwLock<Option<Instant>>,
+    /// Whether the watchdog found the agent's run loop silent
+    unresponsive: AtomicBool,
+    /// When the agent last made progress
+    last_progress: RwLock<chrono::DateTime<chrono::Utc>>,
+    /// Whether the agent's current stall was reported
+    stalled: AtomicBool,
+    /// Session ledger the agent's spending is rolled up in
+    ledger: Option<Arc<BudgetLedger>>,
+    /// Session slots the agent's model calls wait for
+    model_slots: Option<Arc<ModelSlots>>,
+    /// Session slots the agent's tool calls wait for
+    tool_slots: Option<TaskSlots>,
+    /// Session breakers the agent's model calls go through
+    breakers: Option<Arc<CircuitBreakers>>,
+    /// Session cache the agent's read-only tool calls are served from
+    tool_cache: Option<Arc<ToolCache>>,
+    /// Session calls in flight identical calls of the agent wait for
+    in_flight: Option<Arc<InFlightCalls>>,
+    /// Session limits the agent's tool calls wait for
+    tool_limits: Option<Arc<ToolLimits>>,
+    /// Tool calls the agent may run at once, unlimited if None
+    tool_calls: Option<tokio::sync::Semaphore>,
+    /// Tool calls of the agent running now
+    tool_calls_running: AtomicUsize,
+    /// Tool calls of the agent waiting for its cap
+    tool_calls_queued: AtomicUsize,
+    /// Tools of the session's MCP servers
+    mcp: Option<Arc<McpTools>>,
+}
+
+/// State an agent is restored to when restarted
+#[derive(Debug, Clone, Default)]
+pub struct Checkpoint {
+    pub history: History,
+    /// Task the agent was working on, assigned again after a restart
+    pub assignment: Option<TaskAssignment>,
+}
+
+/// Changes to an agent's configuration, e.g. for a fork
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgentOverrides {
+    pub role: Option<AgentRole>,
+    pub model: Option<String>,
+    pub cwd: Option<std::path::PathBuf>,
+    pub can_spawn: Option<bool>,
+    pub max_children: Option<usize>,
+}
+
+impl AgentOverrides {
+    /// Use a different model
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Apply the overrides to a configuration
+    pub fn apply(&self, config: &mut AgentConfig) {
+        if let Some(role) = &self.role {
+            config.role = role.clone();
+        }
+        if let Some(model) = &self.model {
+            config.model = model.clone();
+        }
+        if let Some(cwd) = &self.cwd {
+            config.cwd = Some(cwd.clone());
+        }
+        if let Some(can_spawn) = self.can_spawn {
+            config.can_spawn = can_spawn;
+        }
+        if let Some(max_children) = self.max_children {
+            config.max_children = Some(max_children);
+        }
+    }
+}
+
+/// Snapshot of an agent's state, for status queries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSummary {
+    pub agent_id: AgentId,
+    pub role: AgentRole,
+    pub status: AgentStatus,
+    pub parent_id: Option<AgentId>,
+    pub current_task: Option<TaskId>,
+    /// Paused agents keep their state but do no work
+    #[serde(default)]
+    pub paused: bool,
+    pub usage: TokenUsage,
+    /// Dollars spent, by the price of the agent's model
+    #[serde(default)]
+    pub cost_usd: f64,
+    /// Entries in the agent's history
+    pub history_len: usize,
+    /// Estimated tokens taken by the agent's history
+    pub history_tokens: u64,
+    #[serde(default)]
+    pub trust: TrustLevel,
+    /// Agents over their token budget make no more model calls
+    #[serde(default)]
+    pub budget_exceeded: bool,
+    /// Quarantined agents are kept for diagnosis and get no new tasks
+    #[serde(default)]
+    pub quarantined: bool,
+    /// Unresponsive agents missed their heartbeats
+    #[serde(default)]
+    pub unresponsive: bool,
+    /// Tool calls running now, past every wait for a slot or limit
+    #[serde(default)]
+    pub tool_calls_running: usize,
+    /// Tool calls waiting for the agent's cap on calls at once; calls
+    /// waiting for the session's tool slots or limits are in neither count
+    #[serde(default)]
+    pub tool_calls_queued: usize,
 }
 
 impl Agent {
@@ -43,7 +234,7 @@ impl Agent {
         config: AgentConfig,
         parent_id: Option<AgentId>,
         tools: Arc<ToolRegistry>,
-        event_tx: mpsc::UnboundedSender<Event>,
+        event_tx: mpsc::UnboundedSender<GoblinEvent>,
     ) -> Self {
         let id = AgentId::new();
         
@@ -58,16 +249,216 @@ impl Agent {
             id,
             role: config.role.clone(),
             status: RwLock::new(AgentStatus::Spawning),
+            max_children: RwLock::new(config.max_children),
             config,
-            parent_id,
+            parent_id: RwLock::new(parent_id),
             children: RwLock::new(Vec::new()),
-            tools,
+            tools: ToolView::all(tools),
             current_task: RwLock::new(None),
             usage: RwLock::new(TokenUsage::default()),
+            price: None,
+            cost_usd: RwLock::new(0.0),
+            usage_updates: UsageUpdates::default(),
+            last_usage_report: RwLock::new((Instant::now(), 0)),
             event_tx,
+            scratch: RwLock::new(None),
+            scope: AgentScope::new(),
+            mailbox: Mailbox::new(),
+            model: None,
+            message_seq: AtomicU64::new(0),
+            history: RwLock::new(History::new()),
+            compactor: None,
+            paused: watch::channel(false).0,
+            checkpoint: RwLock::new(None),
+            store: None,
+            artifacts: None,
+            approvals: None,
+            trust: RwLock::new(TrustLevel::default()),
+            sandbox_profile: SandboxProfile::default(),
+            sandbox_exceptions: RwLock::new(Vec::new()),
+            escalations: None,
+            audit: None,
+            budget: None,
+            budget_exceeded: AtomicBool::new(false),
+            quarantined: AtomicBool::new(false),
+            last_beat: RwLock::new(None),
+            unresponsive: AtomicBool::new(false),
+            last_progress: RwLock::new(chrono::Utc::now()),
+            stalled: AtomicBool::new(false),
+            ledger: None,
+            model_slots: None,
+            tool_slots: None,
+            breakers: None,
+            tool_cache: None,
+            in_flight: None,
+            tool_limits: None,
+            tool_calls: None,
+            tool_calls_running: AtomicUsize::new(0),
+            tool_calls_queued: AtomicUsize::new(0),
+            mcp: None,
         }
     }
 
+    /// Give this agent a scratch directory
+    pub fn with_scratch(self, scratch: ScratchDir) -> Self {
+        *self.scratch.write() = Some(scratch);
+        self
+    }
+
+    /// Keep an existing identity, e.g. when restoring from a checkpoint
+    pub(crate) fn with_id(mut self, id: AgentId) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Bind this agent to a model provider
+    pub fn with_model(mut self, model: ModelBinding) -> Self {
+        self.model = Some(model);
+        self
+    }
+
+    /// Compact the agent's history automatically
+    pub fn with_compactor(mut self, compactor: Compactor) -> Self {
+        self.compactor = Some(compactor);
+        self
+    }
+
+    /// Share a session store with this agent
+    pub fn with_store(mut self, store: Arc<SessionStore>) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// Let this agent register artifacts in a session's store
+    pub fn with_artifacts(mut self, artifacts: Arc<ArtifactStore>) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
+    /// Have this agent's commands approved through a session's approvals
+    pub fn with_approvals(mut self, approvals: Arc<Approvals>) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    /// Narrow the tools this agent may use
+    pub fn with_tool_view(mut self, tools: ToolView) -> Self {
+        self.tools = tools;
+        self
+    }
+
+    /// Confine this agent's tool executions with a profile
+    pub fn with_sandbox_profile(mut self, profile: SandboxProfile) -> Self {
+        self.sandbox_profile = profile;
+        self
+    }
+
+    /// Let this agent ask a session's escalations for more sandbox access
+    pub fn with_escalations(mut self, escalations: Arc<SandboxEscalations>) -> Self {
+        self.escalations = Some(escalations);
+        self
+    }
+
+    /// Record this agent's tool calls in a session's audit log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Limit the tokens this agent may spend
+    pub fn with_budget(mut self, budget: Option<TokenBudget>) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Price this agent's tokens
+    pub fn with_price(mut self, price: Option<ModelPrice>) -> Self {
+        self.price = price;
+        self
+    }
+
+    /// Roll this agent's spending up a session's ledger
+    pub fn with_ledger(mut self, ledger: Arc<BudgetLedger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+
+    /// Report usage this often
+    pub fn with_usage_updates(mut self, updates: UsageUpdates) -> Self {
+        self.usage_updates = updates;
+        self
+    }
+
+    /// Take a session slot before each model call
+    pub fn with_model_slots(mut self, slots: Arc<ModelSlots>) -> Self {
+        self.model_slots = Some(slots);
+        self
+    }
+
+    /// Take a session slot before each tool call
+    pub fn with_tool_slots(mut self, slots: TaskSlots) -> Self {
+        self.tool_slots = Some(slots);
+        self
+    }
+
+    /// Fail model calls fast while the session's breaker of the model is open
+    pub fn with_circuit_breakers(mut self, breakers: Arc<CircuitBreakers>) -> Self {
+        self.breakers = Some(breakers);
+        self
+    }
+
+    /// Share read-only tool results through a session's cache
+    pub fn with_tool_cache(mut self, cache: Arc<ToolCache>) -> Self {
+        self.tool_cache = Some(cache);
+        self
+    }
+
+    /// Wait for identical calls in flight in a session instead of running again
+    pub fn with_in_flight_calls(mut self, calls: Arc<InFlightCalls>) -> Self {
+        self.in_flight = Some(calls);
+        self
+    }
+
+    /// Hold the agent's tool calls to a session's rate and concurrency limits
+    pub fn with_tool_limits(mut self, limits: Arc<ToolLimits>) -> Self {
+        self.tool_limits = Some(limits);
+        self
+    }
+
+    /// Offer the tools of a session's MCP servers
+    pub fn with_mcp_tools(mut self, mcp: Arc<McpTools>) -> Self {
+        self.mcp = Some(mcp);
+        self
+    }
+
+    /// Run at most this many of the agent's tool calls at once, queueing the rest
+    pub fn with_max_tool_calls(mut self, calls: usize) -> Self {
+        self.tool_calls = Some(tokio::sync::Semaphore::new(calls));
+        self
+    }
+
+    /// Trust this agent this much
+    pub fn with_trust(self, trust: TrustLevel) -> Self {
+        *self.trust.write() = trust;
+        self
+    }
+
+    /// Context window of the agent's model, if the provider reports one
+    pub fn context_window(&self) -> Option<u64> {
+        let binding = self.model.as_ref()?;
+        binding.provider.context_window(&binding.model)
+    }
+
+    /// Check if the agent has a model to think with
+    pub fn has_model(&self) -> bool {
+        self.model.is_some()
+    }
+
+    /// Path of the agent's scratch directory, if provisioned
+    pub fn scratch_dir(&self) -> Option<std::path::PathBuf> {
+        self.scratch.read().as_ref().map(|s| s.path().to_path_buf())
+    }
+
     /// Get current status
     pub fn status(&self) -> AgentStatus {
         self.status.read().clone()
@@ -75,15 +466,27 @@ impl Agent {
 
     /// Set status and emit event
     pub fn set_status(&self, status: AgentStatus, sub_id: &SubmissionId) {
+        self.store_status(status.clone(), sub_id);
+        self.emit(GoblinEvent::TreeDelta {
+            sub_id: sub_id.clone(),
+            added: Vec::new(),
+            removed: Vec::new(),
+            status_changes: vec![(self.id, status)],
+        });
+    }
+
+    /// Set status, emitting only the protocol event
+    fn store_status(&self, status: AgentStatus, sub_id: &SubmissionId) {
         let mut guard = self.status.write();
         *guard = status.clone();
         drop(guard);
+        self.mark_progress();
 
         let _ = self.event_tx.send(Event::AgentStatusChanged {
             sub_id: sub_id.clone(),
             agent_id: self.id,
             status,
-        });
+        }.into());
     }
 
     /// Initialize the agent (load context, etc.)
@@ -94,18 +497,54 @@ impl Agent {
         self.set_status(AgentStatus::Initializing, sub_id);
         
         // TODO: Load context from Grimoire
-        // TODO: Initialize model connection
-        
+
+        if let Some(binding) = &self.model {
+            if let Err(e) = binding.provider.connect(&binding.model).await {
+                warn!(agent_id = %self.id, provider = binding.provider.name(), error = %e, "Model connection failed");
+                return Err(e);
+            }
+            debug!(agent_id = %self.id, provider = binding.provider.name(), model = %binding.model, "Model connected");
+        }
+
         self.set_status(AgentStatus::Running, sub_id);
         
         info!(agent_id = %self.id, "Agent initialized");
         Ok(())
     }
 
+    /// Pause the agent, returning false if it already was
+    ///
+    /// A paused agent stops reading its mailbox and making model calls;
+    /// whatever it was doing continues from the same point on resume.
+    pub fn pause(&self) -> bool {
+        self.paused.send_if_modified(|paused| !std::mem::replace(paused, true))
+    }
+
+    /// Resume a paused agent, returning false if it was not paused
+    pub fn resume(&self) -> bool {
+        self.paused.send_if_modified(|paused| std::mem::replace(paused, false))
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// Wait until the agent is not paused
+    pub async fn wait_resumed(&self) {
+        let _ = self.paused.subscribe().wait_for(|paused| !*paused).await;
+    }
+
+    /// Wait until the agent is paused
+    pub async fn wait_paused(&self) {
+        let _ = self.paused.subscribe().wait_for(|paused| *paused).await;
+    }
+
     /// Assign a task to this agent
     pub fn assign_task(&self, task_id: TaskId) {
         let mut guard = self.current_task.write();
         *guard = Some(task_id);
+        drop(guard);
+        self.mark_progress();
     }
 
     /// Get current task
@@ -113,6 +552,30 @@ impl Agent {
         self.current_task.read().clone()
     }
 
+    /// Clear the current task, returning it
+    ///
+    /// Usage the task spent since the last `UsageUpdated` event is
+    /// reported first.
+    pub fn clear_task(&self) -> Option<TaskId> {
+        self.flush_usage();
+        self.current_task.write().take()
+    }
+
+    /// Check if the agent is alive and has no task assigned
+    pub fn is_idle(&self) -> bool {
+        self.current_task.read().is_none() && self.status() != AgentStatus::Terminated
+    }
+
+    /// Parent agent (None for root agents)
+    pub fn parent_id(&self) -> Option<AgentId> {
+        *self.parent_id.read()
+    }
+
+    /// Move the agent under another parent
+    pub(crate) fn set_parent(&self, parent_id: AgentId) {
+        *self.parent_id.write() = Some(parent_id);
+    }
+
     /// Add a child agent
     pub fn add_child(&self, child_id: AgentId) {
         self.children.write().push(child_id);
@@ -134,45 +597,525 @@ impl Agent {
         self.children.read().clone()
     }
 
+    /// Scope owning the run loops of this agent's children
+    pub fn scope(&self) -> &AgentScope {
+        &self.scope
+    }
+
+    /// Deliver mail to this agent
+    pub fn deliver(&self, mail: Mail) -> bool {
+        self.mailbox.deliver(mail)
+    }
+
+    /// Take the receiving end of the mailbox (only once)
+    pub fn take_mailbox(&self) -> Option<mpsc::UnboundedReceiver<Mail>> {
+        self.mailbox.take_receiver()
+    }
+
     /// Check if agent can spawn children
     pub fn can_spawn(&self) -> bool {
         if !self.config.can_spawn {
             return false;
         }
         
-        if let Some(max) = self.config.max_children {
+        if let Some(max) = *self.max_children.read() {
             return self.children.read().len() < max;
         }
         
         true
     }
 
+    /// Change the limit on children; existing children are kept
+    pub fn set_max_children(&self, max_children: Option<usize>) {
+        *self.max_children.write() = max_children;
+    }
+
+    /// How far this agent is trusted to act without oversight
+    pub fn trust(&self) -> TrustLevel {
+        *self.trust.read()
+    }
+
+    /// Change how far this agent is trusted
+    pub fn set_trust(&self, trust: TrustLevel) {
+        *self.trust.write() = trust;
+    }
+
+    /// Sandbox this agent's commands run in: the profile's level, or the
+    /// most permissive one its trust allows without the client, widened by
+    /// any active exception
+    pub fn sandbox(&self) -> SandboxLevel {
+        self.sandbox_profile().level.unwrap_or_else(|| self.trust().max_sandbox())
+    }
+
+    /// How this agent's tool executions are confined, with the level
+    /// resolved and active exceptions applied
+    pub fn sandbox_profile(&self) -> SandboxProfile {
+        let base = SandboxProfile {
+            level: Some(self.sandbox_profile.level.unwrap_or_else(|| self.trust().max_sandbox())),
+            ..self.sandbox_profile.clone()
+        };
+        let now = chrono::Utc::now();
+        let mut exceptions = self.sandbox_exceptions.write();
+        exceptions.retain(|e| e.is_active(now));
+        exceptions.iter().fold(base, |profile, e| profile.widened(&e.grant))
+    }
+
+    /// Ask for access beyond this agent's sandbox, after a tool call it
+    /// blocked
+    ///
+    /// Returns whether it was granted; if so, the agent's tool context
+    /// carries the widened profile until the exception expires, and the
+    /// call can be retried.
+    pub async fn request_sandbox_escalation(
+        &self,
+        needs: SandboxGrant,
+        reason: impl Into<String>,
+        sub_id: &SubmissionId,
+    ) -> Result<bool, GoblinError> {
+        let escalations = self.escalations.as_ref().ok_or_else(|| {
+            GoblinError::NotConfigured(format!("Agent {} has no sandbox escalations to ask", self.id))
+        })?;
+        let request = SandboxEscalation {
+            call_id: CallId::new(),
+            agent_id: self.id,
+            role: self.role.clone(),
+            needs,
+            reason: reason.into(),
+            requested_at: chrono::Utc::now(),
+        };
+        let Some(exception) = escalations.request(request, sub_id).await else {
+            return Ok(false);
+        };
+        self.sandbox_exceptions.write().push(exception);
+        Ok(true)
+    }
+
     /// Get tool registry
     pub fn tools(&self) -> &ToolRegistry {
+        self.tools.registry()
+    }
+
+    /// Tools this agent may use
+    pub fn tool_view(&self) -> &ToolView {
         &self.tools
     }
 
+    /// Directory the agent's tools run in
+    fn cwd(&self) -> std::path::PathBuf {
+        self.config.cwd.clone().unwrap_or_else(|| std::env::current_dir().unwrap_or_default())
+    }
+
     /// Create tool context for this agent
     pub fn tool_context(&self) -> ToolContext {
-        let mut ctx = ToolContext::new(
-            self.config.cwd.clone().unwrap_or_else(|| std::env::current_dir().unwrap_or_default())
-        );
+        let mut ctx = ToolContext::new(self.cwd());
         
         ctx = ctx.with_agent(self.id);
         
         if let Some(task_id) = self.current_task() {
             ctx = ctx.with_task(task_id);
         }
+
+        if let Some(dir) = self.scratch_dir() {
+            ctx = ctx.with_env(SCRATCH_DIR_ENV, dir.display().to_string());
+        }
+
+        if let Ok(profile) = serde_json::to_string(&self.sandbox_profile()) {
+            ctx = ctx.with_env(SANDBOX_PROFILE_ENV, profile);
+        }
         
         ctx
     }
 
+    /// Check that this agent may invoke a tool, recording the call in the
+    /// session's audit log
+    ///
+    /// Runtimes call this before running any tool; calls the agent's tool
+    /// view refuses are recorded too.
+    pub fn audit_tool_call(&self, tool: &str, params: &serde_json::Value) -> Result<(), GoblinError> {
+        self.mark_progress();
+        let allowed = self.tools.allows(tool);
+        if let Some(audit) = &self.audit {
+            audit.record(self.id, AuditRecord::ToolCall {
+                tool: tool.to_string(),
+                params: params.clone(),
+                sandbox: self.sandbox(),
+                allowed,
+            });
+        }
+        if !allowed {
+            return Err(GoblinError::ToolDenied {
+                agent_id: self.id,
+                tool: tool.to_string(),
+            });
+        }
+        if let Some(cache) = &self.tool_cache {
+            cache.observe_call(tool);
+        }
+        Ok(())
+    }
+
+    /// Result of an earlier identical call from the session's cache
+    ///
+    /// Runtimes look calls up after [`Agent::audit_tool_call`] and run the
+    /// tool only on a miss, storing its result with
+    /// [`Agent::cache_tool_result`].
+    pub fn cached_tool_result(&self, tool: &str, params: &serde_json::Value) -> Option<serde_json::Value> {
+        self.tool_cache.as_ref()?.get(tool, params, &self.call_context())
+    }
+
+    /// Store the result of a call in the session's cache, if the tool is cached
+    pub fn cache_tool_result(&self, tool: &str, params: &serde_json::Value, result: serde_json::Value) {
+        if let Some(cache) = &self.tool_cache {
+            cache.put(tool, params, &self.call_context(), result);
+        }
+    }
+
+    /// Directory and sandbox this agent's tool calls run in
+    pub fn call_context(&self) -> CallContext {
+        CallContext {
+            cwd: self.cwd(),
+            sandbox: self.sandbox_profile(),
+        }
+    }
+
+    /// Tools of the session's MCP servers this agent may use
+    pub fn mcp_tools(&self) -> Vec<McpTool> {
+        let Some(mcp) = &self.mcp else { return Vec::new() };
+        mcp.tools().into_iter().filter(|t| self.tools.allows(&t.name)).collect()
+    }
+
+    /// Call a tool of one of the session's MCP servers, by its prefixed name
+    ///
+    /// The call is audited and goes through [`Agent::run_tool_call`], with
+    /// the agent's settings of the tool applied.
+    pub async fn call_mcp_tool(&self, tool: &str, params: serde_json::Value) -> Result<serde_json::Value, GoblinError> {
+        self.audit_tool_call(tool, &params)?;
+        let mcp = self.mcp.as_ref().ok_or_else(|| {
+            GoblinError::NotConfigured(format!("Agent {} has no MCP tools", self.id))
+        })?;
+        let mut params = params;
+        self.tools.configure(tool, &mut params);
+        let timeout = self.tools.settings(tool).timeout();
+        let call = || async {
+            match timeout {
+                Some(timeout) => match tokio::time::timeout(timeout, mcp.call(tool, params.clone())).await {
+                    Ok(result) => result,
+                    Err(_) => Err(GoblinError::ToolTimeout {
+                        tool: tool.to_string(),
+                        timeout_ms: timeout.as_millis() as u64,
+                    }),
+                },
+                None => mcp.call(tool, params.clone()).await,
+            }
+        };
+        self.run_tool_call(tool, &params, call).await
+    }
+
+    /// Run a tool call through the session's cache, the calls in flight and
+    /// the tool's limits
+    ///
+    /// `call` runs only if the result is not cached and no identical call
+    /// is running, once the agent's cap and the tool's limits allow it, and
+    /// is reported in `ToolCallStarted` and `ToolCallFinished` events.
+    /// Runtimes call this after [`Agent::audit_tool_call`], and may run
+    /// several calls at once.
+    pub async fn run_tool_call<F, Fut>(
+        &self,
+        tool: &str,
+        params: &serde_json::Value,
+        call: F,
+    ) -> Result<serde_json::Value, GoblinError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<serde_json::Value, GoblinError>>,
+    {
+        if let Some(result) = self.cached_tool_result(tool, params) {
+            return Ok(result);
+        }
+        let limits = self.tool_limits.clone();
+        let limited = || async move {
+            let queued = Counted::new(&self.tool_calls_queued);
+            let _slot = match &self.tool_calls {
+                Some(cap) => cap.acquire().await.ok(),
+                None => None,
+            };
+            drop(queued);
+            let _share = match &self.tool_slots {
+                Some(slots) => Some(slots.acquire(self.current_task()).await),
+                None => None,
+            };
+            let _permit = match &limits {
+                Some(limits) => Some(limits.acquire(tool).await),
+                None => None,
+            };
+            let _running = Counted::new(&self.tool_calls_running);
+            let mut started = StartedCall::new(self, tool);
+            let result = call().await;
+            started.success = result.is_ok();
+            result
+        };
+        let result = match &self.in_flight {
+            Some(calls) => calls.run(tool, params, &self.call_context(), limited).await,
+            None => limited().await,
+        };
+        if let Some(cache) = &self.tool_cache {
+            cache.observe_call(tool);
+        }
+        let result = result?;
+        self.cache_tool_result(tool, params, result.clone());
+        Ok(result)
+    }
+
+    /// Run a [`STORE_TOOL`] call on this agent's behalf
+    pub fn use_store(&self, arguments: serde_json::Value, sub_id: &SubmissionId) -> Result<serde_json::Value, GoblinError> {
+        self.audit_tool_call(STORE_TOOL, &arguments)?;
+        let store = self.store.as_ref().ok_or_else(|| {
+            GoblinError::NotConfigured(format!("Agent {} has no session store", self.id))
+        })?;
+        let request: StoreRequest = serde_json::from_value(arguments)
+            .map_err(|e| GoblinError::InvalidInput(format!("Invalid {} call: {}", STORE_TOOL, e)))?;
+        Ok(store.handle(request, Some(self.id), sub_id))
+    }
+
+    /// Ask whether a command may run in the agent's working directory and
+    /// sandbox
+    ///
+    /// Waits for the client if the session's policy escalates the command.
+    pub async fn request_exec_approval(
+        &self,
+        command: Vec<String>,
+        sub_id: &SubmissionId,
+    ) -> Result<bool, GoblinError> {
+        let approvals = self.approvals.as_ref().ok_or_else(|| {
+            GoblinError::NotConfigured(format!("Agent {} has no approvals to ask", self.id))
+        })?;
+        let request = ExecRequest {
+            call_id: CallId::new(),
+            agent_id: self.id,
+            role: self.role.clone(),
+            command,
+            cwd: self.cwd(),
+            sandbox: self.sandbox(),
+            trust: self.trust(),
+            requested_at: chrono::Utc::now(),
+        };
+        Ok(approvals.request(request, sub_id).await)
+    }
+
+    /// Answer a command escalated to this agent
+    pub fn decide_approval(&self, call_id: &CallId, approved: bool) -> bool {
+        self.approvals
+            .as_ref()
+            .is_some_and(|approvals| approvals.resolve_by(call_id, approved, self.id))
+    }
+
+    /// Leave a command escalated to this agent to the client
+    pub fn decline_approval(&self, call_id: &CallId) -> bool {
+        self.approvals.as_ref().is_some_and(|approvals| approvals.decline(call_id))
+    }
+
+    /// Register a file or blob this agent produced for its current task
+    pub fn produce_artifact(
+        &self,
+        name: impl Into<String>,
+        media_type: Option<String>,
+        data: Vec<u8>,
+        sub_id: &SubmissionId,
+    ) -> Result<ArtifactId, GoblinError> {
+        let artifacts = self.artifacts.as_ref().ok_or_else(|| {
+            GoblinError::NotConfigured(format!("Agent {} has no artifact store", self.id))
+        })?;
+        let mut artifact = Artifact::new(name, media_type, data).with_producer(self.id);
+        if let Some(task_id) = self.current_task() {
+            artifact = artifact.with_task(task_id);
+        }
+        let info = artifact.info();
+        let id = artifacts.insert(artifact);
+        self.emit(GoblinEvent::ArtifactRegistered {
+            sub_id: sub_id.clone(),
+            artifact: info,
+        });
+        Ok(id)
+    }
+
     /// Update token usage
+    ///
+    /// Thresholds of the agent's budget crossed on the way are reported,
+    /// and reaching its limit marks the agent over budget. The tokens and
+    /// their cost are also charged to the session's ledger. The new usage,
+    /// running cost and session total are reported in a `UsageUpdated`
+    /// event when the agent's usage updates setting says one is due.
     pub fn add_usage(&self, input: u64, output: u64) {
-        let mut guard = self.usage.write();
-        guard.input_tokens += input;
-        guard.output_tokens += output;
-        guard.total_tokens = guard.input_tokens + guard.output_tokens;
+        if input == 0 && output == 0 {
+            return;
+        }
+        self.mark_progress();
+        let (before, usage) = {
+            let mut guard = self.usage.write();
+            let before = guard.total_tokens;
+            guard.input_tokens += input;
+            guard.output_tokens += output;
+            guard.total_tokens = guard.input_tokens + guard.output_tokens;
+            (before, guard.clone())
+        };
+        let cost = self.price.map_or(0.0, |p| p.cost(input, output));
+        let cost_usd = {
+            let mut total = self.cost_usd.write();
+            *total += cost;
+            *total
+        };
+        let sub_id = self.submission();
+        let after = usage.total_tokens;
+        if let Some(budget) = &self.budget {
+            self.check_budget(budget, before, after, &sub_id);
+        }
+        let session_total = match &self.ledger {
+            Some(ledger) => ledger.charge(self, after - before, cost, &sub_id),
+            None => after,
+        };
+
+        let due = {
+            let mut last = self.last_usage_report.write();
+            let due = self.usage_updates.due(last.0.elapsed(), after - last.1);
+            if due {
+                *last = (Instant::now(), after);
+            }
+            due
+        };
+        if due {
+            self.emit(GoblinEvent::UsageUpdated {
+                sub_id,
+                agent_id: self.id,
+                task_id: self.current_task(),
+                usage,
+                cost_usd,
+                session_total,
+            });
+        }
+    }
+
+    /// Report usage spent since the last `UsageUpdated` event, whether or
+    /// not the usage updates setting says one is due
+    fn flush_usage(&self) {
+        let usage = self.usage.read().clone();
+        {
+            let mut last = self.last_usage_report.write();
+            if usage.total_tokens == last.1 {
+                return;
+            }
+            *last = (Instant::now(), usage.total_tokens);
+        }
+        let session_total = self.ledger.as_ref().map_or(usage.total_tokens, |ledger| ledger.session_spent());
+        self.emit(GoblinEvent::UsageUpdated {
+            sub_id: self.submission(),
+            agent_id: self.id,
+            task_id: self.current_task(),
+            usage,
+            cost_usd: *self.cost_usd.read(),
+            session_total,
+        });
+    }
+
+    fn check_budget(&self, budget: &TokenBudget, before: u64, after: u64, sub_id: &SubmissionId) {
+        let crossed = budget.crossed(before, after);
+        let exceeded = after >= budget.max_tokens && !self.budget_exceeded.swap(true, Ordering::SeqCst);
+        for threshold in crossed {
+            self.emit(GoblinEvent::BudgetWarning {
+                sub_id: sub_id.clone(),
+                agent_id: self.id,
+                used: after,
+                limit: budget.max_tokens,
+                threshold,
+            });
+        }
+        if exceeded {
+            warn!(agent_id = %self.id, used = after, limit = budget.max_tokens, "Agent exceeded its token budget");
+            self.emit(GoblinEvent::BudgetExceeded {
+                sub_id: sub_id.clone(),
+                agent_id: self.id,
+                used: after,
+                limit: budget.max_tokens,
+            });
+        }
+    }
+
+    /// Submission of the task being worked on, or a fresh one between tasks
+    fn submission(&self) -> SubmissionId {
+        self.checkpoint
+            .read()
+            .as_ref()
+            .and_then(|c| c.assignment.as_ref())
+            .map(|a| a.sub_id.clone())
+            .unwrap_or_else(SubmissionId::new)
+    }
+
+    /// The agent's token budget, if it has one
+    pub fn budget(&self) -> Option<&TokenBudget> {
+        self.budget.as_ref()
+    }
+
+    /// Whether the agent has spent its token budget
+    pub fn is_budget_exceeded(&self) -> bool {
+        self.budget_exceeded.load(Ordering::SeqCst)
+    }
+
+    /// Keep the agent out of the worker pool, returning false if it already was
+    pub fn quarantine(&self) -> bool {
+        !self.quarantined.swap(true, Ordering::SeqCst)
+    }
+
+    /// Whether the agent is kept out of the worker pool
+    pub fn is_quarantined(&self) -> bool {
+        self.quarantined.load(Ordering::SeqCst)
+    }
+
+    /// Signal that the agent's run loop is alive
+    pub fn beat(&self) {
+        *self.last_beat.write() = Some(Instant::now());
+    }
+
+    /// Time since the agent's run loop last beat, None if it never did
+    pub fn since_beat(&self) -> Option<std::time::Duration> {
+        self.last_beat.read().map(|beat| beat.elapsed())
+    }
+
+    /// Tool calls of the agent running now
+    pub fn tool_calls_running(&self) -> usize {
+        self.tool_calls_running.load(Ordering::SeqCst)
+    }
+
+    /// Tool calls of the agent waiting for its cap
+    pub fn tool_calls_queued(&self) -> usize {
+        self.tool_calls_queued.load(Ordering::SeqCst)
+    }
+
+    /// Whether the agent missed its heartbeats
+    pub fn is_unresponsive(&self) -> bool {
+        self.unresponsive.load(Ordering::SeqCst)
+    }
+
+    /// Mark the agent unresponsive or not, returning whether that changed
+    pub(crate) fn set_unresponsive(&self, unresponsive: bool) -> bool {
+        self.unresponsive.swap(unresponsive, Ordering::SeqCst) != unresponsive
+    }
+
+    /// Signal that the agent made progress on its task
+    ///
+    /// Task assignments, status changes, model tokens and tool calls count
+    /// on their own; runtimes call this for other progress.
+    pub fn mark_progress(&self) {
+        *self.last_progress.write() = chrono::Utc::now();
+        self.stalled.store(false, Ordering::SeqCst);
+    }
+
+    /// When the agent last made progress
+    pub fn last_progress(&self) -> chrono::DateTime<chrono::Utc> {
+        *self.last_progress.read()
+    }
+
+    /// Mark the agent's current stall reported, returning false if it already was
+    pub(crate) fn set_stalled(&self) -> bool {
+        !self.stalled.swap(true, Ordering::SeqCst)
     }
 
     /// Get token usage
@@ -180,26 +1123,149 @@ impl Agent {
         self.usage.read().clone()
     }
 
-    /// Emit a message event
-    pub fn emit_message(&self, sub_id: &SubmissionId, content: String, streaming: bool) {
-        let _ = self.event_tx.send(Event::AgentMessage {
+    /// Dollars spent so far, by the price of the agent's model
+    pub fn cost_usd(&self) -> f64 {
+        *self.cost_usd.read()
+    }
+
+    /// Ask the agent's model to continue a conversation
+    ///
+    /// Pieces of the reply are passed to `on_delta` as they arrive; the
+    /// tokens spent are added to the agent's usage. The call waits for a
+    /// session model slot first. Agents over their token budget get a
+    /// `BudgetExceeded` error instead, and calls to a model whose circuit
+    /// breaker is open a `CircuitOpen` error.
+    pub async fn complete(
+        &self,
+        messages: Vec<ChatMessage>,
+        on_delta: DeltaSink<'_>,
+    ) -> Result<ChatResponse, GoblinError> {
+        let binding = self.model.as_ref().ok_or_else(|| {
+            GoblinError::ModelError(format!("Agent {} has no model provider for '{}'", self.id, self.config.model))
+        })?;
+        let request = ChatRequest {
+            model: binding.model.clone(),
+            messages,
+        };
+        self.wait_resumed().await;
+        if self.is_budget_exceeded() {
+            return Err(GoblinError::BudgetExceeded(self.id));
+        }
+        let breaker = format!("{}/{}", binding.provider.name(), binding.model);
+        if let Some(breakers) = &self.breakers {
+            breakers.check(&breaker, &self.submission())?;
+        }
+        let _permit = match &self.model_slots {
+            Some(slots) => Some(slots.acquire(&self.config.model, self.current_task()).await),
+            None => None,
+        };
+        let on_delta = |delta: &str| {
+            self.mark_progress();
+            on_delta(delta);
+        };
+        let result = binding.provider.complete(&request, &on_delta).await;
+        if let Some(breakers) = &self.breakers {
+            let failed = result.as_ref().is_err_and(counts_as_failure);
+            breakers.record(&breaker, !failed, &self.submission());
+        }
+        let response = result?;
+        self.add_usage(response.usage.input_tokens, response.usage.output_tokens);
+        Ok(response)
+    }
+
+    /// Ask the agent's model to continue a conversation, streaming the reply
+    ///
+    /// Every piece of the reply is emitted as a streaming message, followed
+    /// by a non-streaming message with the complete content.
+    pub async fn stream(
+        &self,
+        sub_id: &SubmissionId,
+        messages: Vec<ChatMessage>,
+    ) -> Result<ChatResponse, GoblinError> {
+        let on_delta = |delta: &str| {
+            if !delta.is_empty() {
+                self.emit_message(sub_id, delta.to_string(), true);
+            }
+        };
+        let response = self.complete(messages, &on_delta).await?;
+        self.emit_message(sub_id, response.content.clone(), false);
+        Ok(response)
+    }
+
+    /// Send an event to the session's event stream
+    pub(crate) fn emit(&self, event: impl Into<GoblinEvent>) {
+        let _ = self.event_tx.send(event.into());
+    }
+
+    /// Emit a message event, returning its sequence number
+    pub fn emit_message(&self, sub_id: &SubmissionId, content: String, streaming: bool) -> u64 {
+        let seq = self.message_seq.fetch_add(1, Ordering::Relaxed);
+        let _ = self.event_tx.send(GoblinEvent::AgentMessage {
             sub_id: sub_id.clone(),
             agent_id: self.id,
+            seq,
             content,
             streaming,
             message_type: warhorn::MessageType::Text,
         });
+        seq
+    }
+
+    /// Snapshot of the agent's state
+    pub fn summary(&self) -> AgentSummary {
+        let history = self.history.read();
+        AgentSummary {
+            agent_id: self.id,
+            role: self.role.clone(),
+            status: self.status(),
+            parent_id: self.parent_id(),
+            current_task: self.current_task(),
+            paused: self.is_paused(),
+            usage: self.usage(),
+            cost_usd: self.cost_usd(),
+            history_len: history.len(),
+            history_tokens: history.tokens(),
+            trust: self.trust(),
+            budget_exceeded: self.is_budget_exceeded(),
+            quarantined: self.is_quarantined(),
+            unresponsive: self.is_unresponsive(),
+            tool_calls_running: self.tool_calls_running(),
+            tool_calls_queued: self.tool_calls_queued(),
+        }
+    }
+
+    /// Prepare the agent to be started again after its run loop died
+    ///
+    /// The agent gets a fresh mailbox and goes back to `Spawning`; its
+    /// history is restored from the last checkpoint, which is returned.
+    pub(crate) fn reopen(&self, sub_id: &SubmissionId) -> Option<Checkpoint> {
+        self.set_status(AgentStatus::Spawning, sub_id);
+        self.mailbox.reopen();
+        self.clear_task();
+
+        let checkpoint = self.checkpoint.read().clone()?;
+        *self.history.write() = checkpoint.history.clone();
+        Some(checkpoint)
     }
 
     /// Terminate this agent
+    ///
+    /// The session reports the agent's removal from the hierarchy, so no
+    /// status change is added to the tree deltas.
     pub fn terminate(&self, sub_id: &SubmissionId, reason: String) {
-        self.set_status(AgentStatus::Terminated, sub_id);
+        self.store_status(AgentStatus::Terminated, sub_id);
+
+        if let Some(scratch) = self.scratch.write().take() {
+            if let Err(e) = scratch.cleanup() {
+                warn!(agent_id = %self.id, error = %e, "Failed to clean up scratch directory");
+            }
+        }
         
         let _ = self.event_tx.send(Event::AgentTerminated {
             sub_id: sub_id.clone(),
             agent_id: self.id,
             reason,
-        });
+        }.into());
     }
 }
 
@@ -231,6 +1297,96 @@ impl AgentHandle {
     pub fn inner(&self) -> &Agent {
         &self.inner
     }
+
+    pub(crate) fn downgrade(&self) -> std::sync::Weak<Agent> {
+        Arc::downgrade(&self.inner)
+    }
+
+    /// The agent's conversation so far
+    pub fn history(&self) -> History {
+        self.inner.history.read().clone()
+    }
+
+    /// Number of entries in the agent's history
+    pub fn history_len(&self) -> usize {
+        self.inner.history.read().len()
+    }
+
+    /// Estimated tokens taken by the agent's history
+    pub fn history_tokens(&self) -> u64 {
+        self.inner.history.read().tokens()
+    }
+
+    /// Append to the agent's history
+    pub fn record(&self, entry: HistoryEntry) {
+        self.inner.history.write().push(entry);
+    }
+
+    /// Save the agent's history, and the task it is starting, for a restart
+    pub fn checkpoint(&self, assignment: Option<TaskAssignment>) {
+        let history = self.history();
+        *self.inner.checkpoint.write() = Some(Checkpoint { history, assignment });
+    }
+
+    /// The last saved checkpoint
+    pub fn last_checkpoint(&self) -> Option<Checkpoint> {
+        self.inner.checkpoint.read().clone()
+    }
+
+    /// Replace the agent's history
+    pub fn restore_history(&self, history: History) {
+        *self.inner.history.write() = history;
+    }
+
+    /// Replace the oldest `count` history entries, returning the new token estimate
+    pub fn replace_history_prefix(&self, count: usize, entry: HistoryEntry) -> u64 {
+        let mut history = self.inner.history.write();
+        history.replace_prefix(count, entry);
+        history.tokens()
+    }
+
+    /// Compact the agent's history if it is close to the context window
+    pub async fn compact_history(&self, sub_id: &SubmissionId) -> Result<Option<Compaction>, GoblinError> {
+        match &self.inner.compactor {
+            Some(compactor) => compactor.compact_if_needed(self, sub_id).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Send mail to the agent, failing if its run loop has stopped
+    pub fn send(&self, mail: Mail) -> Result<(), GoblinError> {
+        if self.inner.deliver(mail) {
+            Ok(())
+        } else {
+            Err(GoblinError::ChannelError(format!("Mailbox of agent {} is closed", self.id())))
+        }
+    }
+
+    /// Tell the agent to work on a task
+    pub fn assign(&self, task_id: TaskId, instructions: impl Into<String>) -> Result<(), GoblinError> {
+        self.send(Mail::AssignTask {
+            task_id,
+            instructions: instructions.into(),
+        })
+    }
+
+    /// Deliver a message to the agent
+    pub fn message(&self, from: Option<AgentId>, content: impl Into<String>) -> Result<(), GoblinError> {
+        self.send(Mail::Message {
+            from,
+            content: content.into(),
+        })
+    }
+
+    /// Tell the agent to stop working on its current task
+    pub fn interrupt(&self) -> Result<(), GoblinError> {
+        self.send(Mail::Interrupt)
+    }
+
+    /// Tell the agent's run loop to stop
+    pub fn shutdown(&self, reason: impl Into<String>) -> Result<(), GoblinError> {
+        self.send(Mail::Shutdown { reason: reason.into() })
+    }
 }
 
 impl std::ops::Deref for AgentHandle {
@@ -241,11 +1397,62 @@ impl std::ops::Deref for AgentHandle {
     }
 }
 
+/// Counts something in progress until dropped
+struct Counted<'a>(&'a AtomicUsize);
+
+impl<'a> Counted<'a> {
+    fn new(count: &'a AtomicUsize) -> Self {
+        count.fetch_add(1, Ordering::SeqCst);
+        Self(count)
+    }
+}
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Reports a running tool call, started when created and finished when
+/// dropped, as failed unless marked a success
+struct StartedCall<'a> {
+    agent: &'a Agent,
+    call_id: CallId,
+    tool: &'a str,
+    success: bool,
+}
+
+impl<'a> StartedCall<'a> {
+    fn new(agent: &'a Agent, tool: &'a str) -> Self {
+        let call_id = CallId::new();
+        agent.emit(GoblinEvent::ToolCallStarted {
+            sub_id: agent.submission(),
+            call_id: call_id.clone(),
+            agent_id: agent.id,
+            task_id: agent.current_task(),
+            tool: tool.to_string(),
+        });
+        Self { agent, call_id, tool, success: false }
+    }
+}
+
+impl Drop for StartedCall<'_> {
+    fn drop(&mut self) {
+        self.agent.emit(GoblinEvent::ToolCallFinished {
+            sub_id: self.agent.submission(),
+            call_id: self.call_id.clone(),
+            agent_id: self.agent.id,
+            tool: self.tool.to_string(),
+            success: self.success,
+        });
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    fn create_test_agent() -> (Agent, mpsc::UnboundedReceiver<Event>) {
+    fn create_test_agent() -> (Agent, mpsc::UnboundedReceiver<GoblinEvent>) {
         let (tx, rx) = mpsc::unbounded_channel();
         let tools = Arc::new(ToolRegistry::new());
         let config = AgentConfig {
@@ -260,7 +1467,101 @@ mod tests {
     fn test_agent_creation() {
         let (agent, _rx) = create_test_agent();
         assert_eq!(agent.status(), AgentStatus::Spawning);
-        assert!(agent.parent_id.is_none());
+        assert!(agent.parent_id().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_over_the_cap_are_queued() {
+        let (agent, _rx) = create_test_agent();
+        let agent = agent.with_max_tool_calls(1);
+        let (release, released) = tokio::sync::watch::channel(false);
+        let call = || async {
+            let mut released = released.clone();
+            while !*released.borrow() {
+                released.changed().await.unwrap();
+            }
+            Ok::<_, GoblinError>(serde_json::Value::Null)
+        };
+        let params = serde_json::json!({});
+        let calls = async {
+            tokio::join!(agent.run_tool_call("grep", &params, call), agent.run_tool_call("ls", &params, call))
+        };
+        let status = async {
+            tokio::task::yield_now().await;
+            let summary = agent.summary();
+            release.send(true).unwrap();
+            (summary.tool_calls_running, summary.tool_calls_queued)
+        };
+        let ((first, second), status) = tokio::join!(calls, status);
+        assert!(first.is_ok() && second.is_ok());
+        assert_eq!(status, (1, 1));
+        assert_eq!((agent.tool_calls_running(), agent.tool_calls_queued()), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_waits_for_session_slots_are_not_queued() {
+        let (agent, _rx) = create_test_agent();
+        let slots = TaskSlots::new(Some(1), Arc::default());
+        let agent = agent.with_tool_slots(slots.clone()).with_max_tool_calls(2);
+        let held = slots.acquire(None).await;
+        let call = agent.run_tool_call("grep", &serde_json::json!({}), || async {
+            Ok::<_, GoblinError>(serde_json::Value::Null)
+        });
+        let status = async {
+            tokio::task::yield_now().await;
+            let counts = (agent.tool_calls_running(), agent.tool_calls_queued());
+            drop(held);
+            counts
+        };
+        let (result, counts) = tokio::join!(call, status);
+        assert!(result.is_ok());
+        assert_eq!(counts, (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_writes_clear_reads_cached_while_they_ran() {
+        use crate::toolcache::ToolCachePolicy;
+
+        let (agent, _rx) = create_test_agent();
+        let policy = ToolCachePolicy::new(std::time::Duration::from_secs(60))
+            .with_tools(["grep"])
+            .with_writes(["write_file"]);
+        let agent = agent.with_tool_cache(Arc::new(ToolCache::new(policy)));
+        let params = serde_json::json!({"pattern": "todo"});
+        agent
+            .run_tool_call("write_file", &serde_json::json!({}), || async {
+                let stale = || async { Ok::<_, GoblinError>(serde_json::json!(["old"])) };
+                agent.run_tool_call("grep", &params, stale).await
+            })
+            .await
+            .unwrap();
+        assert_eq!(agent.cached_tool_result("grep", &params), None);
+    }
+
+    #[tokio::test]
+    async fn test_tool_calls_take_session_tool_slots() {
+        let (agent, mut rx) = create_test_agent();
+        let slots = TaskSlots::new(Some(1), Arc::default());
+        let agent = agent.with_tool_slots(slots.clone());
+        let in_use = agent
+            .run_tool_call("grep", &serde_json::json!({}), || async {
+                Ok::<_, GoblinError>(serde_json::json!(slots.pool().total_in_use()))
+            })
+            .await
+            .unwrap();
+        assert_eq!(in_use, serde_json::json!(1));
+        assert_eq!(slots.pool().total_in_use(), 0);
+
+        let events: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|e| matches!(e, GoblinEvent::ToolCallStarted { .. } | GoblinEvent::ToolCallFinished { .. }))
+            .collect();
+        assert!(matches!(
+            events.as_slice(),
+            [
+                GoblinEvent::ToolCallStarted { call_id: started, .. },
+                GoblinEvent::ToolCallFinished { call_id: finished, success: true, .. },
+            ] if started == finished
+        ));
     }
 
     #[test]
@@ -274,4 +1575,185 @@ mod tests {
         assert!(agent.remove_child(&child_id));
         assert_eq!(agent.children().len(), 0);
     }
+
+    #[test]
+    fn test_agent_scratch_cleanup_on_terminate() {
+        use crate::workspace::ScratchConfig;
+
+        let root = tempfile::tempdir().unwrap();
+        let config = ScratchConfig {
+            root: Some(root.path().to_path_buf()),
+            ..Default::default()
+        };
+
+        let (agent, _rx) = create_test_agent();
+        let scratch = ScratchDir::provision(&config, agent.id).unwrap();
+        let agent = agent.with_scratch(scratch);
+
+        let path = agent.scratch_dir().unwrap();
+        assert!(path.is_dir());
+
+        agent.terminate(&SubmissionId::new(), "done".into());
+        assert!(!path.exists());
+        assert!(agent.scratch_dir().is_none());
+    }
+
+    struct Chunked;
+
+    #[async_trait::async_trait]
+    impl crate::model::ModelProvider for Chunked {
+        fn name(&self) -> &str {
+            "chunked"
+        }
+
+        async fn complete(&self, _request: &ChatRequest, on_delta: DeltaSink<'_>) -> Result<ChatResponse, GoblinError> {
+            for piece in ["Hel", "", "lo"] {
+                on_delta(piece);
+            }
+            Ok(ChatResponse {
+                content: "Hello".into(),
+                usage: TokenUsage::default(),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_emits_sequenced_deltas() {
+        let (agent, mut rx) = create_test_agent();
+        let agent = agent.with_model(ModelBinding {
+            provider: Arc::new(Chunked),
+            model: "any".into(),
+        });
+
+        let sub_id = SubmissionId::new();
+        let response = agent.stream(&sub_id, vec![ChatMessage::user("Greet me")]).await.unwrap();
+        assert_eq!(response.content, "Hello");
+
+        let messages: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                GoblinEvent::AgentMessage { seq, content, streaming, .. } => Some((seq, content, streaming)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (0, "Hel".to_string(), true),
+                (1, "lo".to_string(), true),
+                (2, "Hello".to_string(), false),
+            ]
+        );
+        assert_eq!(agent.emit_message(&sub_id, "again".into(), false), 3);
+    }
+
+    /// Fails every call, as unavailable or as a bad request
+    struct Failing {
+        unavailable: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl crate::model::ModelProvider for Failing {
+        fn name(&self) -> &str {
+            "failing"
+        }
+
+        async fn complete(&self, _request: &ChatRequest, _on_delta: DeltaSink<'_>) -> Result<ChatResponse, GoblinError> {
+            if self.unavailable {
+                Err(GoblinError::ModelUnavailable("503".into()))
+            } else {
+                Err(GoblinError::ModelError("bad request".into()))
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_unavailable_providers_open_the_breaker() {
+        use crate::breaker::{BreakerPolicy, BreakerState, CircuitBreakers};
+
+        let breakers = Arc::new(CircuitBreakers::new(
+            BreakerPolicy::new(2, std::time::Duration::from_secs(60)),
+            mpsc::unbounded_channel().0,
+        ));
+        let agent = |unavailable| {
+            let (agent, _rx) = create_test_agent();
+            agent
+                .with_model(ModelBinding {
+                    provider: Arc::new(Failing { unavailable }),
+                    model: if unavailable { "down" } else { "up" }.into(),
+                })
+                .with_circuit_breakers(Arc::clone(&breakers))
+        };
+
+        let (refusing, down) = (agent(false), agent(true));
+        for _ in 0..3 {
+            assert!(matches!(refusing.complete(Vec::new(), &|_| {}).await, Err(GoblinError::ModelError(_))));
+            let _ = down.complete(Vec::new(), &|_| {}).await;
+        }
+        assert!(matches!(down.complete(Vec::new(), &|_| {}).await, Err(GoblinError::CircuitOpen { .. })));
+        assert_eq!(
+            breakers.states(),
+            vec![("failing/down".to_string(), BreakerState::Open), ("failing/up".to_string(), BreakerState::Closed)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_budget_warns_then_stops_model_calls() {
+        let (agent, mut rx) = create_test_agent();
+        let agent = agent
+            .with_model(ModelBinding {
+                provider: Arc::new(Chunked),
+                model: "any".into(),
+            })
+            .with_budget(Some(TokenBudget::new(100).with_warn_at([0.5, 0.8])));
+
+        agent.add_usage(40, 20);
+        agent.add_usage(15, 5);
+        assert!(!agent.is_budget_exceeded());
+        agent.add_usage(30, 0);
+        assert!(agent.is_budget_exceeded() && agent.summary().budget_exceeded);
+        agent.add_usage(10, 0);
+
+        let events: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                GoblinEvent::BudgetWarning { used, threshold, .. } => Some((used, Some(threshold))),
+                GoblinEvent::BudgetExceeded { used, .. } => Some((used, None)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(events, vec![(60, Some(0.5)), (80, Some(0.8)), (110, None)]);
+
+        let result = agent.complete(vec![ChatMessage::user("More")], &|_| {}).await;
+        assert!(matches!(result, Err(GoblinError::BudgetExceeded(id)) if id == agent.id));
+    }
+
+    #[test]
+    fn test_usage_reported_every_n_tokens() {
+        let (agent, mut rx) = create_test_agent();
+        let agent = agent.with_usage_updates(UsageUpdates {
+            interval_ms: None,
+            every_tokens: Some(100),
+        });
+        for _ in 0..5 {
+            agent.add_usage(30, 10);
+        }
+
+        let reported: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                GoblinEvent::UsageUpdated { usage, session_total, .. } => Some((usage.total_tokens, session_total)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reported, vec![(120, 120)]);
+
+        // What is left is reported when the task ends
+        agent.clear_task();
+        agent.clear_task();
+        let flushed: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                GoblinEvent::UsageUpdated { usage, .. } => Some(usage.total_tokens),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(flushed, vec![200]);
+    }
 }
@@ -0,0 +1,876 @@
+//! Approval of tool executions
+//!
+//! Agents ask before running a command. Asking the client about every call
+//! does not scale past a handful of workers, so an [`ApprovalPolicy`]
+//! settles the clear cases with rules on the command, working directory,
+//! sandbox level and agent role. Only calls the policy escalates reach the
+//! client, as `ExecApprovalRequested` events answered with
+//! `Op::ExecApproval`.
+//!
+//! Every agent has a [`TrustLevel`], by default falling with its depth in
+//! the hierarchy. Rules can require a minimum trust, and no rule approves
+//! a command sandboxed more permissively than the agent's trust allows;
+//! such commands go to the client.
+//!
+//! Agents with a delegate role, typically domain leads, see the escalated
+//! requests of their descendants first: the nearest one's runtime decides
+//! or declines, and only declined requests reach the client.
+//!
+//! An [`ApprovalTimeout`] keeps an unanswered request from blocking its
+//! agent forever: when it runs out, the request is approved, denied, or
+//! handed up the hierarchy one agent at a time, each parent's runtime
+//! getting a chance to decide before the next timeout.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+use warhorn::{AgentId, AgentRole, AgentStatus, CallId, SubmissionId};
+
+use crate::agent::{Agent, AgentHandle};
+use crate::audit::{AuditLog, AuditRecord};
+use crate::mailbox::Mail;
+use crate::protocol::GoblinEvent;
+use crate::runtime::{by_role, role_keys};
+
+/// How much a command's sandbox lets it do, least first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SandboxLevel {
+    ReadOnly,
+    WorkspaceWrite,
+    FullAccess,
+}
+
+/// How far an agent is trusted to act without oversight, least first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustLevel {
+    Untrusted,
+    #[default]
+    Standard,
+    Trusted,
+}
+
+impl TrustLevel {
+    /// Most permissive sandbox an agent's commands may get without the client
+    pub fn max_sandbox(self) -> SandboxLevel {
+        match self {
+            Self::Untrusted => SandboxLevel::ReadOnly,
+            Self::Standard => SandboxLevel::WorkspaceWrite,
+            Self::Trusted => SandboxLevel::FullAccess,
+        }
+    }
+}
+
+/// Trust levels given to agents as they are spawned
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrustPolicy {
+    /// Trust by role key, like `worker` or `specialist:reviewer`, taking
+    /// precedence over depth
+    #[serde(default)]
+    pub roles: HashMap<String, TrustLevel>,
+    /// Trust by depth in the hierarchy, the root first; the last entry
+    /// applies to everything deeper
+    #[serde(default)]
+    pub by_depth: Vec<TrustLevel>,
+}
+
+impl Default for TrustPolicy {
+    fn default() -> Self {
+        Self {
+            roles: HashMap::new(),
+            by_depth: vec![TrustLevel::Trusted, TrustLevel::Standard, TrustLevel::Untrusted],
+        }
+    }
+}
+
+impl TrustPolicy {
+    /// Give agents with a role this trust, wherever they are
+    pub fn with_role(mut self, role: impl Into<String>, trust: TrustLevel) -> Self {
+        self.roles.insert(role.into(), trust);
+        self
+    }
+
+    /// Set the trust by depth, the root first
+    pub fn with_depths(mut self, by_depth: impl IntoIterator<Item = TrustLevel>) -> Self {
+        self.by_depth = by_depth.into_iter().collect();
+        self
+    }
+
+    /// Trust of an agent with `role` at `depth`
+    pub fn trust_for(&self, role: &AgentRole, depth: usize) -> TrustLevel {
+        if let Some(trust) = by_role(&self.roles, role) {
+            return *trust;
+        }
+        self.by_depth
+            .get(depth)
+            .or(self.by_depth.last())
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// A command an agent wants to run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExecRequest {
+    pub call_id: CallId,
+    pub agent_id: AgentId,
+    pub role: AgentRole,
+    /// Program and arguments
+    pub command: Vec<String>,
+    pub cwd: PathBuf,
+    pub sandbox: SandboxLevel,
+    /// Trust of the requesting agent
+    #[serde(default)]
+    pub trust: TrustLevel,
+    pub requested_at: DateTime<Utc>,
+}
+
+impl ExecRequest {
+    /// Whether the command's sandbox is beyond what the agent's trust
+    /// allows without the client
+    pub fn exceeds_trust(&self) -> bool {
+        self.sandbox > self.trust.max_sandbox()
+    }
+}
+
+/// An escalated request still waiting for an answer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingApproval {
+    pub call_id: CallId,
+    pub agent_id: AgentId,
+    /// Program and arguments
+    pub command: Vec<String>,
+    pub requested_at: DateTime<Utc>,
+    /// Milliseconds since the request was made
+    pub age_ms: u64,
+    /// Whether the client was asked, rather than only a delegate
+    pub awaiting_client: bool,
+}
+
+/// What to do with an execution request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    Approve,
+    Deny,
+    /// Ask the client
+    #[default]
+    Escalate,
+}
+
+/// A rule deciding the requests it matches
+///
+/// Every criterion that is set must match; empty lists match anything.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalRule {
+    /// Name reported with the decisions the rule makes
+    pub name: String,
+    pub decision: ApprovalDecision,
+    /// Programs (`cargo`) or command prefixes (`git status`)
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Directories the command must run under, compared after resolving
+    /// `.` and `..`
+    #[serde(default)]
+    pub path_prefixes: Vec<PathBuf>,
+    /// Most permissive sandbox the command may run in
+    #[serde(default)]
+    pub max_sandbox: Option<SandboxLevel>,
+    /// Roles of the requesting agent, as keys like `worker` or `specialist:reviewer`
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Least trust the requesting agent must have
+    #[serde(default)]
+    pub min_trust: Option<TrustLevel>,
+}
+
+impl ApprovalRule {
+    pub fn new(name: impl Into<String>, decision: ApprovalDecision) -> Self {
+        Self {
+            name: name.into(),
+            decision,
+            commands: Vec::new(),
+            path_prefixes: Vec::new(),
+            max_sandbox: None,
+            roles: Vec::new(),
+            min_trust: None,
+        }
+    }
+
+    /// Match only these programs or command prefixes
+    pub fn with_commands<S: Into<String>>(mut self, commands: impl IntoIterator<Item = S>) -> Self {
+        self.commands.extend(commands.into_iter().map(Into::into));
+        self
+    }
+
+    /// Match only commands running under a directory
+    pub fn with_path_prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.path_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Match only commands sandboxed at most this permissively
+    pub fn with_max_sandbox(mut self, level: SandboxLevel) -> Self {
+        self.max_sandbox = Some(level);
+        self
+    }
+
+    /// Match only agents with one of these roles
+    pub fn with_roles<S: Into<String>>(mut self, roles: impl IntoIterator<Item = S>) -> Self {
+        self.roles.extend(roles.into_iter().map(Into::into));
+        self
+    }
+
+    /// Match only agents trusted at least this much
+    pub fn with_min_trust(mut self, trust: TrustLevel) -> Self {
+        self.min_trust = Some(trust);
+        self
+    }
+
+    pub fn matches(&self, request: &ExecRequest) -> bool {
+        if !self.commands.is_empty() && !self.commands.iter().any(|c| command_matches(c, &request.command)) {
+            return false;
+        }
+        if !self.path_prefixes.is_empty() {
+            let Some(cwd) = normalize_path(&request.cwd) else {
+                return false;
+            };
+            if !self.path_prefixes.iter().filter_map(|p| normalize_path(p)).any(|p| cwd.starts_with(p)) {
+                return false;
+            }
+        }
+        if self.max_sandbox.is_some_and(|max| request.sandbox > max) {
+            return false;
+        }
+        if self.min_trust.is_some_and(|min| request.trust < min) {
+            return false;
+        }
+        if !self.roles.is_empty() {
+            let keys = role_keys(&request.role);
+            if !self.roles.iter().any(|r| keys.contains(r)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Directories a program named without a path may also be run from
+const TRUSTED_BIN_DIRS: &[&str] = &["/bin", "/sbin", "/usr/bin", "/usr/sbin", "/usr/local/bin"];
+
+/// Whether a command runs `pattern`: a program, or the leading words of
+/// the command line
+///
+/// The program must be spelled as in the pattern, or be the pattern's
+/// program in a [trusted directory](TRUSTED_BIN_DIRS): `git` matches
+/// `/usr/bin/git` but not `./git` or `/tmp/git`.
+fn command_matches(pattern: &str, command: &[String]) -> bool {
+    let words: Vec<&str> = pattern.split_whitespace().collect();
+    let Some((program, args)) = words.split_first() else {
+        return false;
+    };
+    let Some(first) = command.first() else {
+        return false;
+    };
+    let path = Path::new(first);
+    let trusted = path.file_name().is_some_and(|name| name == *program)
+        && path.parent().is_some_and(|dir| TRUSTED_BIN_DIRS.iter().any(|trusted| dir == Path::new(trusted)));
+    (first == program || trusted)
+        && command.len() > args.len()
+        && args.iter().zip(&command[1..]).all(|(a, c)| a == c)
+}
+
+/// Resolve `.` and `..` in an absolute path without touching the file
+/// system
+///
+/// Returns None for relative paths and paths climbing above the root.
+pub(crate) fn normalize_path(path: &Path) -> Option<PathBuf> {
+    if !path.is_absolute() {
+        return None;
+    }
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    Some(normalized)
+}
+
+/// What happens to a request nobody answered in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutAction {
+    Deny,
+    /// Approve, unless the command is beyond the agent's trust
+    Approve,
+    /// Ask the parent of the agent last asked, denying once there is none
+    EscalateToParent,
+}
+
+/// How long an escalated request may wait for an answer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApprovalTimeout {
+    pub timeout_ms: u64,
+    pub action: TimeoutAction,
+}
+
+impl ApprovalTimeout {
+    pub fn new(timeout: Duration, action: TimeoutAction) -> Self {
+        Self {
+            timeout_ms: timeout.as_millis() as u64,
+            action,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+/// Rules applied to every execution request of a session
+///
+/// The first matching rule decides; requests no rule matches get the
+/// default decision.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    #[serde(default)]
+    pub rules: Vec<ApprovalRule>,
+    #[serde(default)]
+    pub default: ApprovalDecision,
+    /// Limit on waiting for escalated requests; they wait forever if None
+    #[serde(default)]
+    pub timeout: Option<ApprovalTimeout>,
+    /// Roles whose agents decide their descendants' escalated requests
+    /// before the client, as keys like `domain_lead`
+    #[serde(default)]
+    pub delegates: Vec<String>,
+    /// Trust levels of the session's agents
+    #[serde(default)]
+    pub trust: TrustPolicy,
+}
+
+impl ApprovalPolicy {
+    /// A policy escalating every request
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rule(mut self, rule: ApprovalRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    pub fn with_default(mut self, decision: ApprovalDecision) -> Self {
+        self.default = decision;
+        self
+    }
+
+    /// Let agents with a role decide their descendants' escalated requests
+    pub fn with_delegate(mut self, role: impl Into<String>) -> Self {
+        self.delegates.push(role.into());
+        self
+    }
+
+    /// Whether agents with a role decide escalated requests of their descendants
+    pub fn is_delegate(&self, role: &AgentRole) -> bool {
+        let keys = role_keys(role);
+        self.delegates.iter().any(|d| keys.contains(d))
+    }
+
+    /// Set how agents are trusted
+    pub fn with_trust(mut self, trust: TrustPolicy) -> Self {
+        self.trust = trust;
+        self
+    }
+
+    /// Stop waiting for an answer after `timeout`, then apply `action`
+    pub fn with_timeout(mut self, timeout: Duration, action: TimeoutAction) -> Self {
+        self.timeout = Some(ApprovalTimeout::new(timeout, action));
+        self
+    }
+
+    /// Decide a request, with the name of the rule that decided it
+    ///
+    /// Approvals of commands sandboxed beyond the agent's trust are
+    /// escalated instead.
+    pub fn evaluate(&self, request: &ExecRequest) -> (ApprovalDecision, Option<&str>) {
+        let (decision, rule) = match self.rules.iter().find(|r| r.matches(request)) {
+            Some(rule) => (rule.decision, Some(rule.name.as_str())),
+            None => (self.default, None),
+        };
+        if decision == ApprovalDecision::Approve && request.exceeds_trust() {
+            return (ApprovalDecision::Escalate, None);
+        }
+        (decision, rule)
+    }
+}
+
+#[derive(Debug)]
+struct Pending {
+    request: ExecRequest,
+    sub_id: SubmissionId,
+    /// Whether the client was asked yet
+    escalated: bool,
+    answer: oneshot::Sender<bool>,
+}
+
+/// Withdraws a pending request when its requester stops waiting, e.g.
+/// because the task was cancelled, so it is not left for the client
+struct PendingGuard<'a> {
+    pending: &'a Mutex<HashMap<CallId, Pending>>,
+    call_id: CallId,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.pending.lock().remove(&self.call_id);
+    }
+}
+
+/// Approval requests of a session's agents
+#[derive(Debug)]
+pub struct Approvals {
+    policy: RwLock<ApprovalPolicy>,
+    /// Requests waiting for a delegate or the client, by call
+    pending: Mutex<HashMap<CallId, Pending>>,
+    /// Agents requests can be escalated to
+    agents: Mutex<HashMap<AgentId, Weak<Agent>>>,
+    event_tx: mpsc::UnboundedSender<GoblinEvent>,
+    /// Where decisions are recorded
+    audit: Option<Arc<AuditLog>>,
+}
+
+impl Approvals {
+    /// Decide requests with `policy`, reporting on `event_tx`
+    pub fn new(policy: ApprovalPolicy, event_tx: mpsc::UnboundedSender<GoblinEvent>) -> Self {
+        Self {
+            policy: RwLock::new(policy),
+            pending: Mutex::new(HashMap::new()),
+            agents: Mutex::new(HashMap::new()),
+            event_tx,
+            audit: None,
+        }
+    }
+
+    /// Record every decision in an audit log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    pub fn policy(&self) -> ApprovalPolicy {
+        self.policy.read().clone()
+    }
+
+    /// Decide requests made from now on with `policy`
+    ///
+    /// Requests already escalated keep waiting under the old policy's timeout.
+    pub fn set_policy(&self, policy: ApprovalPolicy) {
+        *self.policy.write() = policy;
+    }
+
+    /// Make an agent reachable for requests escalated to it
+    pub fn register(&self, agent: &AgentHandle) {
+        let mut agents = self.agents.lock();
+        agents.retain(|_, agent| agent.strong_count() > 0);
+        agents.insert(agent.id(), agent.downgrade());
+    }
+
+    /// Decide a request, waiting for a delegate or the client if the
+    /// policy escalates it
+    ///
+    /// Returns whether the command may run. A request whose answer can
+    /// never arrive, because the session went away, is denied.
+    pub async fn request(&self, request: ExecRequest, sub_id: &SubmissionId) -> bool {
+        let (decision, rule) = {
+            let policy = self.policy.read();
+            let (decision, rule) = policy.evaluate(&request);
+            (decision, rule.map(String::from))
+        };
+        let approved = match decision {
+            ApprovalDecision::Approve => true,
+            ApprovalDecision::Deny => false,
+            ApprovalDecision::Escalate => {
+                let (answer, answered) = oneshot::channel();
+                self.pending.lock().insert(
+                    request.call_id.clone(),
+                    Pending {
+                        request: request.clone(),
+                        sub_id: sub_id.clone(),
+                        escalated: false,
+                        answer,
+                    },
+                );
+                let _pending = PendingGuard {
+                    pending: &self.pending,
+                    call_id: request.call_id.clone(),
+                };
+                let asked = match self.delegate_for(&request) {
+                    Some(delegate) => {
+                        let _ = self.event_tx.send(GoblinEvent::ApprovalDelegated {
+                            sub_id: sub_id.clone(),
+                            call_id: request.call_id.clone(),
+                            agent_id: request.agent_id,
+                            delegate,
+                        });
+                        delegate
+                    }
+                    None => {
+                        self.decline(&request.call_id);
+                        request.agent_id
+                    }
+                };
+                return self.wait(request, sub_id, asked, answered).await;
+            }
+        };
+
+        debug!(call_id = %request.call_id, rule = ?rule, approved, "Execution decided by policy");
+        self.decided(sub_id.clone(), &request, approved, rule.as_deref(), None);
+        approved
+    }
+
+    /// Wait for the answer to an escalated request, applying the timeout
+    ///
+    /// `asked` is the agent that last got the request, if not the client.
+    async fn wait(
+        &self,
+        request: ExecRequest,
+        sub_id: &SubmissionId,
+        mut asked: AgentId,
+        mut answered: oneshot::Receiver<bool>,
+    ) -> bool {
+        let Some(timeout) = self.policy.read().timeout else {
+            return answered.await.unwrap_or(false);
+        };
+        // A timeout must not approve what the agent's trust leaves to the client
+        let action = match timeout.action {
+            TimeoutAction::Approve if request.exceeds_trust() => TimeoutAction::Deny,
+            action => action,
+        };
+        loop {
+            if let Ok(answer) = tokio::time::timeout(timeout.duration(), &mut answered).await {
+                return answer.unwrap_or(false);
+            }
+
+            let parent = match action {
+                TimeoutAction::EscalateToParent => self.escalate_above(&asked, &request),
+                _ => None,
+            };
+            let _ = self.event_tx.send(GoblinEvent::ApprovalTimedOut {
+                sub_id: sub_id.clone(),
+                call_id: request.call_id.clone(),
+                agent_id: request.agent_id,
+                action,
+                escalated_to: parent,
+            });
+            if let Some(parent) = parent {
+                debug!(call_id = %request.call_id, parent = %parent, "Approval escalated to parent");
+                asked = parent;
+                continue;
+            }
+
+            let approved = action == TimeoutAction::Approve;
+            if !self.settle(&request.call_id, approved, Some("timeout"), None) {
+                // Answered just as the time ran out
+                return answered.await.unwrap_or(false);
+            }
+            return approved;
+        }
+    }
+
+    /// Hand a request to the nearest delegate above the requesting agent,
+    /// returning the delegate
+    fn delegate_for(&self, request: &ExecRequest) -> Option<AgentId> {
+        let policy = self.policy.read().clone();
+        if policy.delegates.is_empty() {
+            return None;
+        }
+        let mut agent = self.find(&request.agent_id)?;
+        loop {
+            agent = self.find(&agent.parent_id()?)?;
+            if policy.is_delegate(&agent.role) {
+                return agent
+                    .deliver(Mail::ApprovalRequest { request: request.clone() })
+                    .then_some(agent.id);
+            }
+        }
+    }
+
+    /// Hand a request to the parent of `agent_id`, returning the parent
+    fn escalate_above(&self, agent_id: &AgentId, request: &ExecRequest) -> Option<AgentId> {
+        let parent = self.find(&self.find(agent_id)?.parent_id()?)?;
+        parent
+            .deliver(Mail::ApprovalRequest { request: request.clone() })
+            .then_some(parent.id)
+    }
+
+    /// A live agent of the session
+    fn find(&self, agent_id: &AgentId) -> Option<std::sync::Arc<Agent>> {
+        let agent = self.agents.lock().get(agent_id)?.upgrade()?;
+        (agent.status() != AgentStatus::Terminated).then_some(agent)
+    }
+
+    /// Pass a pending request on to the client, e.g. because the agent it
+    /// was escalated to declined to decide
+    ///
+    /// Returns false if the request is not pending or the client already has it.
+    pub fn decline(&self, call_id: &CallId) -> bool {
+        let request = {
+            let mut pending = self.pending.lock();
+            match pending.get_mut(call_id) {
+                Some(p) if !p.escalated => {
+                    p.escalated = true;
+                    (p.sub_id.clone(), p.request.clone())
+                }
+                _ => return false,
+            }
+        };
+        let (sub_id, request) = request;
+        let _ = self.event_tx.send(GoblinEvent::ExecApprovalRequested { sub_id, request });
+        true
+    }
+
+    /// Answer a pending request, returning false if there is none for the call
+    pub fn resolve(&self, call_id: &CallId, approved: bool) -> bool {
+        self.settle(call_id, approved, None, None)
+    }
+
+    /// Answer a pending request on behalf of an agent it was escalated to
+    pub fn resolve_by(&self, call_id: &CallId, approved: bool, agent_id: AgentId) -> bool {
+        self.settle(call_id, approved, None, Some(agent_id))
+    }
+
+    fn settle(&self, call_id: &CallId, approved: bool, rule: Option<&str>, decided_by: Option<AgentId>) -> bool {
+        let Some(pending) = self.pending.lock().remove(call_id) else {
+            return false;
+        };
+        self.decided(pending.sub_id, &pending.request, approved, rule, decided_by);
+        let _ = pending.answer.send(approved);
+        true
+    }
+
+    /// Report and record a decision
+    fn decided(
+        &self,
+        sub_id: SubmissionId,
+        request: &ExecRequest,
+        approved: bool,
+        rule: Option<&str>,
+        decided_by: Option<AgentId>,
+    ) {
+        if let Some(audit) = &self.audit {
+            audit.record(request.agent_id, AuditRecord::ExecApproval {
+                call_id: request.call_id.clone(),
+                command: request.command.clone(),
+                approved,
+                rule: rule.map(String::from),
+                decided_by,
+            });
+        }
+        let _ = self.event_tx.send(GoblinEvent::ExecApprovalDecided {
+            sub_id,
+            call_id: request.call_id.clone(),
+            agent_id: request.agent_id,
+            approved,
+            rule: rule.map(String::from),
+            decided_by,
+        });
+    }
+
+    /// Requests still waiting for an answer, oldest first
+    pub fn list(&self) -> Vec<PendingApproval> {
+        let now = Utc::now();
+        let mut pending: Vec<PendingApproval> = self
+            .pending
+            .lock()
+            .values()
+            .map(|p| PendingApproval {
+                call_id: p.request.call_id.clone(),
+                agent_id: p.request.agent_id,
+                command: p.request.command.clone(),
+                requested_at: p.request.requested_at,
+                age_ms: (now - p.request.requested_at).num_milliseconds().max(0) as u64,
+                awaiting_client: p.escalated,
+            })
+            .collect();
+        pending.sort_by_key(|p| p.requested_at);
+        pending
+    }
+
+    /// Whether a call is waiting for the client
+    pub fn is_pending(&self, call_id: &CallId) -> bool {
+        self.pending.lock().contains_key(call_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(command: &str, cwd: &str, sandbox: SandboxLevel, role: AgentRole) -> ExecRequest {
+        ExecRequest {
+            call_id: CallId::new(),
+            agent_id: AgentId::new(),
+            role,
+            command: command.split_whitespace().map(String::from).collect(),
+            cwd: cwd.into(),
+            sandbox,
+            trust: TrustLevel::Standard,
+            requested_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_decides() {
+        let policy = ApprovalPolicy::new()
+            .with_rule(ApprovalRule::new("no-push", ApprovalDecision::Deny).with_commands(["git push"]))
+            .with_rule(
+                ApprovalRule::new("workspace-tools", ApprovalDecision::Approve)
+                    .with_commands(["git", "cargo"])
+                    .with_path_prefix("/work")
+                    .with_max_sandbox(SandboxLevel::WorkspaceWrite)
+                    .with_roles(["worker"]),
+            );
+        let decide = |command, cwd, sandbox, role| policy.evaluate(&request(command, cwd, sandbox, role));
+        let write = SandboxLevel::WorkspaceWrite;
+
+        assert_eq!(decide("git status", "/work/repo", write, AgentRole::Worker), (ApprovalDecision::Approve, Some("workspace-tools")));
+        assert_eq!(decide("/usr/bin/git push origin", "/work/repo", write, AgentRole::Worker).0, ApprovalDecision::Deny);
+        assert_eq!(decide("cargo test", "/etc", write, AgentRole::Worker), (ApprovalDecision::Escalate, None));
+        assert_eq!(decide("cargo test", "/work", SandboxLevel::FullAccess, AgentRole::Worker).0, ApprovalDecision::Escalate);
+        assert_eq!(decide("cargo test", "/work", write, AgentRole::Orchestrator).0, ApprovalDecision::Escalate);
+        assert_eq!(decide("rm -rf target", "/work", write, AgentRole::Worker).0, ApprovalDecision::Escalate);
+        assert_eq!(decide("/tmp/bin/git status", "/work", write, AgentRole::Worker).0, ApprovalDecision::Escalate);
+        assert_eq!(decide("./cargo test", "/work", write, AgentRole::Worker).0, ApprovalDecision::Escalate);
+        assert_eq!(decide("git status", "/work/../etc", write, AgentRole::Worker).0, ApprovalDecision::Escalate);
+        assert_eq!(decide("git status", "/work/./repo", write, AgentRole::Worker).0, ApprovalDecision::Approve);
+        assert_eq!(decide("git status", "work", write, AgentRole::Worker).0, ApprovalDecision::Escalate);
+    }
+
+    #[test]
+    fn test_trust_limits_what_rules_approve() {
+        let trust = TrustPolicy::default().with_role("specialist:auditor", TrustLevel::Trusted);
+        assert_eq!(trust.trust_for(&AgentRole::Orchestrator, 0), TrustLevel::Trusted);
+        assert_eq!(trust.trust_for(&AgentRole::Worker, 1), TrustLevel::Standard);
+        assert_eq!(trust.trust_for(&AgentRole::Worker, 5), TrustLevel::Untrusted);
+        let auditor = AgentRole::Specialist { specialty: "auditor".into() };
+        assert_eq!(trust.trust_for(&auditor, 5), TrustLevel::Trusted);
+
+        let policy = ApprovalPolicy::new()
+            .with_trust(trust)
+            .with_rule(ApprovalRule::new("deploy", ApprovalDecision::Approve).with_commands(["deploy"]).with_min_trust(TrustLevel::Trusted))
+            .with_rule(ApprovalRule::new("make", ApprovalDecision::Approve).with_commands(["make"]));
+        let decide = |command, sandbox, trust| {
+            let mut request = request(command, "/", sandbox, AgentRole::Worker);
+            request.trust = trust;
+            policy.evaluate(&request)
+        };
+        let write = SandboxLevel::WorkspaceWrite;
+
+        assert_eq!(decide("deploy", write, TrustLevel::Trusted), (ApprovalDecision::Approve, Some("deploy")));
+        assert_eq!(decide("deploy", write, TrustLevel::Standard).0, ApprovalDecision::Escalate);
+        assert_eq!(decide("make", write, TrustLevel::Standard), (ApprovalDecision::Approve, Some("make")));
+        assert_eq!(decide("make", write, TrustLevel::Untrusted), (ApprovalDecision::Escalate, None));
+        assert_eq!(decide("make", SandboxLevel::ReadOnly, TrustLevel::Untrusted).0, ApprovalDecision::Approve);
+    }
+
+    #[tokio::test]
+    async fn test_escalated_requests_wait_for_the_client() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let approvals = std::sync::Arc::new(Approvals::new(
+            ApprovalPolicy::new().with_rule(ApprovalRule::new("ls", ApprovalDecision::Approve).with_commands(["ls"])),
+            tx,
+        ));
+        let sub_id = SubmissionId::new();
+
+        assert!(approvals.request(request("ls", "/", SandboxLevel::ReadOnly, AgentRole::Worker), &sub_id).await);
+        assert!(matches!(rx.recv().await, Some(GoblinEvent::ExecApprovalDecided { approved: true, .. })));
+
+        let asked = request("make", "/", SandboxLevel::ReadOnly, AgentRole::Worker);
+        let call_id = asked.call_id.clone();
+        let waiting = tokio::spawn({
+            let approvals = std::sync::Arc::clone(&approvals);
+            let sub_id = sub_id.clone();
+            async move { approvals.request(asked, &sub_id).await }
+        });
+        assert!(matches!(rx.recv().await, Some(GoblinEvent::ExecApprovalRequested { .. })));
+        assert!(approvals.is_pending(&call_id));
+        let listed = approvals.list();
+        assert_eq!(listed.len(), 1);
+        assert_eq!((&listed[0].call_id, listed[0].command.as_slice()), (&call_id, ["make".to_string()].as_slice()));
+        assert!(listed[0].awaiting_client);
+        assert!(approvals.resolve(&call_id, true));
+        assert!(approvals.list().is_empty());
+        assert!(waiting.await.unwrap());
+        assert!(!approvals.resolve(&call_id, false));
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_request_is_withdrawn() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let approvals = std::sync::Arc::new(Approvals::new(ApprovalPolicy::new(), tx));
+
+        let asked = request("make", "/", SandboxLevel::ReadOnly, AgentRole::Worker);
+        let waiting = tokio::spawn({
+            let (approvals, asked) = (std::sync::Arc::clone(&approvals), asked.clone());
+            async move { approvals.request(asked, &SubmissionId::new()).await }
+        });
+        assert!(matches!(rx.recv().await, Some(GoblinEvent::ExecApprovalRequested { .. })));
+        assert!(approvals.is_pending(&asked.call_id));
+
+        waiting.abort();
+        let _ = waiting.await;
+        assert!(!approvals.is_pending(&asked.call_id));
+        assert!(approvals.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_timeout_applies_default_action() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let policy = ApprovalPolicy::new().with_timeout(Duration::from_millis(10), TimeoutAction::Approve);
+        let approvals = Approvals::new(policy, tx);
+
+        let asked = request("make", "/", SandboxLevel::ReadOnly, AgentRole::Worker);
+        assert!(approvals.request(asked.clone(), &SubmissionId::new()).await);
+        assert!(!approvals.is_pending(&asked.call_id));
+
+        let kinds: Vec<String> = std::iter::from_fn(|| rx.try_recv().ok())
+            .map(|e| e.describe().unwrap().0)
+            .collect();
+        assert_eq!(kinds, vec!["ExecApprovalRequested", "ApprovalTimedOut", "ExecApprovalDecided"]);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_does_not_approve_beyond_trust() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let policy = ApprovalPolicy::new()
+            .with_default(ApprovalDecision::Approve)
+            .with_timeout(Duration::from_millis(10), TimeoutAction::Approve);
+        let approvals = Approvals::new(policy, tx);
+
+        let mut asked = request("make", "/", SandboxLevel::WorkspaceWrite, AgentRole::Worker);
+        asked.trust = TrustLevel::Untrusted;
+        assert!(!approvals.request(asked, &SubmissionId::new()).await);
+
+        let action = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|e| match e {
+            GoblinEvent::ApprovalTimedOut { action, .. } => Some(action),
+            _ => None,
+        });
+        assert_eq!(action, Some(TimeoutAction::Deny));
+    }
+}
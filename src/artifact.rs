@@ -0,0 +1,349 @@
+//! Session artifact storage for files and context blobs
+//!
+//! Attachments submitted with a task and files or blobs produced by agents
+//! are registered in the session's [`ArtifactStore`] with their producer,
+//! task and content hash. Results and events refer to artifacts by ID, so
+//! large outputs are not copied into every event; clients fetch the
+//! content they need with the `FetchArtifact` op.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use warhorn::{AgentId, TaskId};
+
+use crate::error::GoblinError;
+
+/// Largest inline attachment a result carries in events; larger ones are stored as artifacts
+pub const INLINE_ATTACHMENT_LIMIT: usize = 64 * 1024;
+
+/// Unique identifier of a stored artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArtifactId(Uuid);
+
+impl ArtifactId {
+    /// Create a new random artifact ID
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for ArtifactId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ArtifactId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A file or context blob attached to a task submission
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// Display name (usually the file name)
+    pub name: String,
+    /// MIME type, if known
+    pub media_type: Option<String>,
+    /// The attached content
+    pub content: AttachmentContent,
+}
+
+/// Content of an attachment
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttachmentContent {
+    /// Inline text
+    Text(String),
+    /// Inline binary data
+    Bytes(Vec<u8>),
+    /// A file under the session's working directory, read at submission
+    /// time; relative paths are resolved against that directory
+    Path(PathBuf),
+}
+
+impl Attachment {
+    /// Attach inline text
+    pub fn text(name: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            media_type: Some("text/plain".into()),
+            content: AttachmentContent::Text(text.into()),
+        }
+    }
+
+    /// Attach a file by path, absolute or relative to the session directory
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        Self {
+            name,
+            media_type: None,
+            content: AttachmentContent::Path(path),
+        }
+    }
+
+    /// Size of the content carried inline, 0 for files referenced by path
+    pub fn inline_size(&self) -> usize {
+        match &self.content {
+            AttachmentContent::Text(text) => text.len(),
+            AttachmentContent::Bytes(bytes) => bytes.len(),
+            AttachmentContent::Path(_) => 0,
+        }
+    }
+
+    /// Resolve the attachment content into bytes
+    ///
+    /// Files are read only if they resolve, links followed, to a path
+    /// under `root`, so a client cannot attach any file of the host.
+    pub fn read(&self, root: &Path) -> Result<Vec<u8>, GoblinError> {
+        match &self.content {
+            AttachmentContent::Text(text) => Ok(text.clone().into_bytes()),
+            AttachmentContent::Bytes(bytes) => Ok(bytes.clone()),
+            AttachmentContent::Path(path) => {
+                let unreadable = |e: std::io::Error| {
+                    GoblinError::InvalidInput(format!("Failed to read attachment {}: {}", path.display(), e))
+                };
+                let root = root.canonicalize().map_err(unreadable)?;
+                let resolved = root.join(path).canonicalize().map_err(unreadable)?;
+                if !resolved.starts_with(&root) {
+                    return Err(GoblinError::InvalidInput(format!(
+                        "Attachment {} is outside the session directory {}",
+                        path.display(),
+                        root.display()
+                    )));
+                }
+                std::fs::read(&resolved).map_err(unreadable)
+            }
+        }
+    }
+}
+
+/// A stored artifact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artifact {
+    /// Artifact ID
+    pub id: ArtifactId,
+    /// Display name
+    pub name: String,
+    /// MIME type, if known
+    pub media_type: Option<String>,
+    /// Agent that produced the artifact (None for client submissions)
+    pub producer: Option<AgentId>,
+    /// Task the artifact belongs to
+    pub task_id: Option<TaskId>,
+    /// SHA-256 of the content, as `sha256:<hex>`
+    pub hash: String,
+    /// Raw content
+    pub data: Vec<u8>,
+}
+
+impl Artifact {
+    /// Create an artifact with a new ID
+    pub fn new(name: impl Into<String>, media_type: Option<String>, data: Vec<u8>) -> Self {
+        Self {
+            id: ArtifactId::new(),
+            name: name.into(),
+            media_type,
+            producer: None,
+            task_id: None,
+            hash: content_hash(&data),
+            data,
+        }
+    }
+
+    /// Attribute the artifact to the agent that produced it
+    pub fn with_producer(mut self, agent_id: AgentId) -> Self {
+        self.producer = Some(agent_id);
+        self
+    }
+
+    /// Attach the artifact to a task
+    pub fn with_task(mut self, task_id: TaskId) -> Self {
+        self.task_id = Some(task_id);
+        self
+    }
+
+    /// Content interpreted as UTF-8 text, if valid
+    pub fn as_text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.data).ok()
+    }
+
+    /// Content size in bytes
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Metadata of the artifact, without its content
+    pub fn info(&self) -> ArtifactInfo {
+        ArtifactInfo {
+            id: self.id,
+            name: self.name.clone(),
+            media_type: self.media_type.clone(),
+            producer: self.producer,
+            task_id: self.task_id,
+            hash: self.hash.clone(),
+            size: self.size(),
+        }
+    }
+}
+
+/// Metadata of a stored artifact, as reported in events
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactInfo {
+    pub id: ArtifactId,
+    pub name: String,
+    pub media_type: Option<String>,
+    pub producer: Option<AgentId>,
+    pub task_id: Option<TaskId>,
+    pub hash: String,
+    /// Content size in bytes
+    pub size: usize,
+}
+
+/// SHA-256 of some content, as `sha256:<hex>`
+pub fn content_hash(data: &[u8]) -> String {
+    let digest = Sha256::digest(data);
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{}", hex)
+}
+
+/// Registry of artifacts held by a session
+#[derive(Default)]
+pub struct ArtifactStore {
+    artifacts: RwLock<HashMap<ArtifactId, Artifact>>,
+}
+
+impl ArtifactStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store an attachment submitted with a task
+    pub fn store_attachment(
+        &self,
+        attachment: &Attachment,
+        root: &Path,
+        task_id: Option<TaskId>,
+    ) -> Result<ArtifactId, GoblinError> {
+        let data = attachment.read(root)?;
+        let mut artifact = Artifact::new(attachment.name.clone(), attachment.media_type.clone(), data);
+        artifact.task_id = task_id;
+        Ok(self.insert(artifact))
+    }
+
+    /// Insert an artifact, returning its ID
+    pub fn insert(&self, artifact: Artifact) -> ArtifactId {
+        let id = artifact.id;
+        self.artifacts.write().insert(id, artifact);
+        id
+    }
+
+    /// Get an artifact by ID
+    pub fn get(&self, id: &ArtifactId) -> Option<Artifact> {
+        self.artifacts.read().get(id).cloned()
+    }
+
+    /// Get all artifacts belonging to a task
+    pub fn for_task(&self, task_id: &TaskId) -> Vec<Artifact> {
+        self.artifacts
+            .read()
+            .values()
+            .filter(|a| a.task_id.as_ref() == Some(task_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Metadata of every artifact, in no particular order
+    pub fn list(&self) -> Vec<ArtifactInfo> {
+        self.artifacts.read().values().map(Artifact::info).collect()
+    }
+
+    /// Get artifact count
+    pub fn len(&self) -> usize {
+        self.artifacts.read().len()
+    }
+
+    /// Check if the store is empty
+    pub fn is_empty(&self) -> bool {
+        self.artifacts.read().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_store_text_attachment() {
+        let store = ArtifactStore::new();
+        let task_id = TaskId::new();
+
+        let id = store
+            .store_attachment(&Attachment::text("spec.md", "# Spec"), Path::new("/"), Some(task_id))
+            .unwrap();
+
+        let artifact = store.get(&id).unwrap();
+        assert_eq!(artifact.name, "spec.md");
+        assert_eq!(artifact.as_text(), Some("# Spec"));
+        assert_eq!(artifact.hash, content_hash(b"# Spec"));
+        assert_eq!(store.for_task(&task_id).len(), 1);
+    }
+
+    #[test]
+    fn test_store_file_attachment() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut file = std::fs::File::create(dir.path().join("build.log")).unwrap();
+        write!(file, "error: boom").unwrap();
+
+        let store = ArtifactStore::new();
+        let id = store.store_attachment(&Attachment::file("build.log"), dir.path(), None).unwrap();
+        assert_eq!(store.get(&id).unwrap().as_text(), Some("error: boom"));
+
+        let absolute = Attachment::file(dir.path().join("build.log"));
+        assert!(store.store_attachment(&absolute, dir.path(), None).is_ok());
+    }
+
+    #[test]
+    fn test_missing_file_attachment() {
+        let store = ArtifactStore::new();
+        let attachment = Attachment::file("/nonexistent/cabal/attachment.log");
+
+        assert!(store.store_attachment(&attachment, Path::new("/"), None).is_err());
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_file_attachments_stay_in_the_session_directory() {
+        let outside = tempfile::NamedTempFile::new().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let store = ArtifactStore::new();
+
+        for attachment in [Attachment::file(outside.path()), Attachment::file("../../../../etc/hostname")] {
+            assert!(store.store_attachment(&attachment, dir.path(), None).is_err());
+        }
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_content_hash() {
+        assert_eq!(
+            content_hash(b""),
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        let artifact = Artifact::new("out.bin", None, vec![1, 2, 3]).with_producer(AgentId::new());
+        let info = artifact.info();
+        assert_eq!((info.size, info.hash), (3, content_hash(&[1, 2, 3])));
+        assert_eq!(info.producer, artifact.producer);
+    }
+}
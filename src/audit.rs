@@ -0,0 +1,375 @@
+//! Per-session audit trail of tool calls and approvals
+//!
+//! Every session keeps an append-only [`AuditLog`] for compliance review:
+//! each tool invocation with its parameters and the sandbox it ran under,
+//! each command approval, and each sandbox escalation, with the agent
+//! that acted. Parameters, and secrets passed on approved command lines,
+//! are redacted before they are recorded, so they never reach the log.
+//!
+//! Clients read the log with `QueryAudit` and write it out as JSON lines
+//! with `ExportAudit`, which only writes into the directory the session
+//! was configured with.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use warhorn::{AgentId, CallId};
+
+use crate::approval::SandboxLevel;
+use crate::error::GoblinError;
+use crate::sandbox::SandboxGrant;
+
+/// Parameter keys redacted by default, matched case-insensitively as substrings
+pub const DEFAULT_REDACTED_KEYS: &[&str] = &["password", "secret", "token", "api_key", "authorization", "credential"];
+
+/// Replacement for redacted values
+pub const REDACTED: &str = "[redacted]";
+
+/// What an audit entry records
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditRecord {
+    /// A tool was invoked, or refused by the agent's tool view
+    ToolCall {
+        tool: String,
+        /// Parameters, redacted
+        params: Value,
+        sandbox: SandboxLevel,
+        allowed: bool,
+    },
+    /// A command's approval was decided
+    ExecApproval {
+        call_id: CallId,
+        command: Vec<String>,
+        approved: bool,
+        /// Policy rule that decided, if any
+        rule: Option<String>,
+        /// Agent that decided it for the client, if any
+        decided_by: Option<AgentId>,
+    },
+    /// A sandbox escalation was granted or refused
+    SandboxEscalation {
+        call_id: CallId,
+        needs: SandboxGrant,
+        granted: bool,
+        rule: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+    },
+}
+
+impl AuditRecord {
+    /// Snake-case kind, as used in queries
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ToolCall { .. } => "tool_call",
+            Self::ExecApproval { .. } => "exec_approval",
+            Self::SandboxEscalation { .. } => "sandbox_escalation",
+        }
+    }
+}
+
+/// One entry of an audit log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// Position in the log, starting at 1
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    /// Agent that acted
+    pub agent_id: AgentId,
+    #[serde(flatten)]
+    pub record: AuditRecord,
+}
+
+/// Which entries a query returns; unset fields match everything
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditQuery {
+    #[serde(default)]
+    pub agent_id: Option<AgentId>,
+    /// Record kind, like `tool_call`
+    #[serde(default)]
+    pub kind: Option<String>,
+    /// Only entries after this sequence number
+    #[serde(default)]
+    pub after_seq: Option<u64>,
+    /// Most entries returned, oldest first
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        if self.agent_id.is_some_and(|id| id != entry.agent_id) {
+            return false;
+        }
+        if self.kind.as_deref().is_some_and(|kind| kind != entry.record.kind()) {
+            return false;
+        }
+        !self.after_seq.is_some_and(|seq| entry.seq <= seq)
+    }
+}
+
+/// Append-only audit trail of a session
+#[derive(Debug)]
+pub struct AuditLog {
+    entries: Mutex<Vec<AuditEntry>>,
+    /// Parameter keys whose values are redacted, lowercase
+    redacted_keys: Vec<String>,
+    /// Where [`AuditLog::export`] may write
+    export_dir: Option<PathBuf>,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AuditLog {
+    /// A log redacting the [`DEFAULT_REDACTED_KEYS`]
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(Vec::new()),
+            redacted_keys: DEFAULT_REDACTED_KEYS.iter().map(|k| k.to_string()).collect(),
+            export_dir: None,
+        }
+    }
+
+    /// Also redact parameters whose keys contain one of these
+    pub fn with_redacted_keys<S: Into<String>>(mut self, keys: impl IntoIterator<Item = S>) -> Self {
+        self.redacted_keys
+            .extend(keys.into_iter().map(|k| k.into().to_lowercase()));
+        self
+    }
+
+    /// Let [`AuditLog::export`] write into a directory
+    pub fn with_export_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.export_dir = Some(dir.into());
+        self
+    }
+
+    /// Append a record, returning its sequence number
+    pub fn record(&self, agent_id: AgentId, record: AuditRecord) -> u64 {
+        let record = match record {
+            AuditRecord::ToolCall { tool, params, sandbox, allowed } => AuditRecord::ToolCall {
+                tool,
+                params: self.redact(params),
+                sandbox,
+                allowed,
+            },
+            AuditRecord::ExecApproval { call_id, command, approved, rule, decided_by } => AuditRecord::ExecApproval {
+                call_id,
+                command: self.redact_command(command),
+                approved,
+                rule,
+                decided_by,
+            },
+            other => other,
+        };
+        let mut entries = self.entries.lock();
+        let seq = entries.len() as u64 + 1;
+        entries.push(AuditEntry { seq, at: Utc::now(), agent_id, record });
+        seq
+    }
+
+    /// Entries matching a query, oldest first
+    pub fn query(&self, query: &AuditQuery) -> Vec<AuditEntry> {
+        self.entries
+            .lock()
+            .iter()
+            .filter(|e| query.matches(e))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Write every entry to a file as JSON lines, returning how many
+    pub fn export_jsonl(&self, path: impl AsRef<Path>) -> Result<usize, GoblinError> {
+        let path = path.as_ref();
+        let entries = self.entries.lock().clone();
+        let file = File::create(path).map_err(|e| export_error(path, e))?;
+        let mut out = BufWriter::new(file);
+        for entry in &entries {
+            serde_json::to_writer(&mut out, entry)
+                .map_err(|e| GoblinError::PersistenceError(format!("Failed to encode audit entry: {}", e)))?;
+            out.write_all(b"\n").map_err(|e| export_error(path, e))?;
+        }
+        out.flush().map_err(|e| export_error(path, e))?;
+        Ok(entries.len())
+    }
+
+    /// Write every entry as JSON lines to a file in the export directory,
+    /// returning the file's path and how many entries were written
+    ///
+    /// `name` must be a plain file name, so exports cannot land outside
+    /// the directory.
+    pub fn export(&self, name: &Path) -> Result<(PathBuf, usize), GoblinError> {
+        let dir = self.export_dir.as_ref().ok_or_else(|| {
+            GoblinError::ConfigError("Audit export is not enabled for this session".into())
+        })?;
+        let mut components = name.components();
+        let (Some(Component::Normal(file)), None) = (components.next(), components.next()) else {
+            return Err(GoblinError::ConfigError(format!(
+                "Audit export name must be a plain file name: {}",
+                name.display()
+            )));
+        };
+        let path = dir.join(file);
+        let entries = self.export_jsonl(&path)?;
+        Ok((path, entries))
+    }
+
+    fn is_sensitive(&self, key: &str) -> bool {
+        let lower = key.to_lowercase();
+        self.redacted_keys.iter().any(|k| lower.contains(k.as_str()))
+    }
+
+    /// Redact secrets on a command line: values of `--token=...`,
+    /// `API_KEY=...` and `Authorization: ...` arguments, and the argument
+    /// after a flag like `--password`
+    fn redact_command(&self, command: Vec<String>) -> Vec<String> {
+        let mut redact_next = false;
+        command
+            .into_iter()
+            .map(|arg| {
+                if std::mem::take(&mut redact_next) {
+                    return REDACTED.into();
+                }
+                match arg.find(['=', ':']) {
+                    Some(at) if self.is_sensitive(&arg[..at]) => format!("{}{}", &arg[..=at], REDACTED),
+                    Some(_) => arg,
+                    None => {
+                        redact_next = arg.starts_with('-') && self.is_sensitive(&arg);
+                        arg
+                    }
+                }
+            })
+            .collect()
+    }
+
+    fn redact(&self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        if self.is_sensitive(&key) {
+                            (key, Value::String(REDACTED.into()))
+                        } else {
+                            (key, self.redact(value))
+                        }
+                    })
+                    .collect(),
+            ),
+            Value::Array(items) => Value::Array(items.into_iter().map(|v| self.redact(v)).collect()),
+            other => other,
+        }
+    }
+}
+
+fn export_error(path: &Path, e: std::io::Error) -> GoblinError {
+    GoblinError::PersistenceError(format!("Audit export to {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_records_are_redacted_and_queryable() {
+        let log = AuditLog::new().with_redacted_keys(["Cookie"]);
+        let (agent, other) = (AgentId::new(), AgentId::new());
+        let params = json!({ "url": "https://example.com", "headers": { "Authorization": "Bearer x", "cookie": "y" } });
+        log.record(agent, AuditRecord::ToolCall {
+            tool: "http_get".into(),
+            params,
+            sandbox: SandboxLevel::ReadOnly,
+            allowed: true,
+        });
+        log.record(other, AuditRecord::ExecApproval {
+            call_id: CallId::new(),
+            command: vec!["make".into()],
+            approved: false,
+            rule: Some("no-make".into()),
+            decided_by: None,
+        });
+
+        let calls = log.query(&AuditQuery { kind: Some("tool_call".into()), ..AuditQuery::default() });
+        assert_eq!(calls.len(), 1);
+        match &calls[0].record {
+            AuditRecord::ToolCall { params, .. } => {
+                assert_eq!(params["url"], "https://example.com");
+                assert_eq!(params["headers"]["Authorization"], REDACTED);
+                assert_eq!(params["headers"]["cookie"], REDACTED);
+            }
+            other => panic!("unexpected record: {:?}", other),
+        }
+        let mine = AuditQuery { agent_id: Some(other), ..AuditQuery::default() };
+        assert_eq!(log.query(&mine)[0].seq, 2);
+        assert!(log.query(&AuditQuery { after_seq: Some(2), ..AuditQuery::default() }).is_empty());
+    }
+
+    #[test]
+    fn test_command_secrets_are_redacted() {
+        let log = AuditLog::new();
+        let command = [
+            "curl", "-H", "Authorization: Bearer x", "--token=y", "--password", "z", "https://example.com",
+        ];
+        log.record(AgentId::new(), AuditRecord::ExecApproval {
+            call_id: CallId::new(),
+            command: command.iter().map(|a| a.to_string()).collect(),
+            approved: true,
+            rule: None,
+            decided_by: None,
+        });
+
+        match &log.query(&AuditQuery::default())[0].record {
+            AuditRecord::ExecApproval { command, .. } => assert_eq!(
+                command,
+                &["curl", "-H", "Authorization:[redacted]", "--token=[redacted]", "--password", "[redacted]", "https://example.com"]
+            ),
+            other => panic!("unexpected record: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_export_writes_json_lines() {
+        let log = AuditLog::new();
+        let agent = AgentId::new();
+        for tool in ["read_file", "grep"] {
+            log.record(agent, AuditRecord::ToolCall {
+                tool: tool.into(),
+                params: json!({}),
+                sandbox: SandboxLevel::ReadOnly,
+                allowed: true,
+            });
+        }
+        assert!(log.export(Path::new("audit.jsonl")).is_err());
+
+        let log = log.with_export_dir(std::env::temp_dir());
+        assert!(log.export(Path::new("../audit.jsonl")).is_err());
+        assert!(log.export(Path::new("/etc/audit.jsonl")).is_err());
+        let name = format!("cabal-audit-{}.jsonl", agent);
+        let (path, entries) = log.export(Path::new(&name)).unwrap();
+        assert_eq!((path.clone(), entries), (std::env::temp_dir().join(&name), 2));
+        let lines: Vec<AuditEntry> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(lines, log.query(&AuditQuery::default()));
+    }
+}
@@ -0,0 +1,230 @@
+//! Circuit breakers around model providers
+//!
+//! Every model a session's agents call gets a breaker, keyed by provider
+//! and model. Only failures that say the provider is in trouble count:
+//! rate limits, errors on its side and unreachable endpoints, reported as
+//! `ModelUnavailable`; a call the provider refused, like a malformed
+//! request, shows it is up.
+//!
+//! After `failures` calls in a row fail, the breaker opens: calls fail at
+//! once with `CircuitOpen` instead of reaching the provider. Once the
+//! cool-down has passed it half-opens and lets a single trial call through;
+//! the trial closes the breaker if it succeeds and opens it again if not.
+//! Every change of state is reported in a `CircuitStateChanged` event.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::warn;
+use warhorn::SubmissionId;
+
+use crate::error::GoblinError;
+use crate::protocol::GoblinEvent;
+
+/// When breakers open and for how long
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BreakerPolicy {
+    /// Failed calls in a row that open a breaker
+    pub failures: u32,
+    /// Time an open breaker waits before letting a trial call through
+    pub cooldown_ms: u64,
+}
+
+impl Default for BreakerPolicy {
+    fn default() -> Self {
+        Self {
+            failures: 5,
+            cooldown_ms: 30_000,
+        }
+    }
+}
+
+impl BreakerPolicy {
+    pub fn new(failures: u32, cooldown: Duration) -> Self {
+        Self {
+            failures,
+            cooldown_ms: cooldown.as_millis() as u64,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.failures == 0 {
+            return Err(GoblinError::ConfigError("circuit breaker failures must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
+
+/// State of a breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through
+    Closed,
+    /// Calls fail at once
+    Open,
+    /// A single trial call goes through
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: BreakerState,
+    failures: u32,
+    /// When the breaker opened, or let its trial call through
+    since: Instant,
+    trial_in_flight: bool,
+}
+
+impl Breaker {
+    fn new(now: Instant) -> Self {
+        Self {
+            state: BreakerState::Closed,
+            failures: 0,
+            since: now,
+            trial_in_flight: false,
+        }
+    }
+}
+
+/// Whether a failed call counts against its model's breaker
+pub fn counts_as_failure(error: &GoblinError) -> bool {
+    matches!(error, GoblinError::ModelUnavailable(_))
+}
+
+/// Breakers of a session's models, by `provider/model`
+#[derive(Debug)]
+pub struct CircuitBreakers {
+    policy: BreakerPolicy,
+    breakers: Mutex<HashMap<String, Breaker>>,
+    event_tx: mpsc::UnboundedSender<GoblinEvent>,
+}
+
+impl CircuitBreakers {
+    pub fn new(policy: BreakerPolicy, event_tx: mpsc::UnboundedSender<GoblinEvent>) -> Self {
+        Self {
+            policy,
+            breakers: Mutex::new(HashMap::new()),
+            event_tx,
+        }
+    }
+
+    /// Let a call to `model` through, or fail with `CircuitOpen`
+    pub fn check(&self, model: &str, sub_id: &SubmissionId) -> Result<(), GoblinError> {
+        self.check_at(model, Instant::now(), sub_id)
+    }
+
+    pub fn check_at(&self, model: &str, now: Instant, sub_id: &SubmissionId) -> Result<(), GoblinError> {
+        let cooldown = Duration::from_millis(self.policy.cooldown_ms);
+        let mut breakers = self.breakers.lock();
+        let breaker = breakers.entry(model.to_string()).or_insert_with(|| Breaker::new(now));
+        // A trial call that never reported back is replaced after another cool-down
+        let wait = match breaker.state {
+            BreakerState::Closed => return Ok(()),
+            BreakerState::HalfOpen if !breaker.trial_in_flight => Duration::ZERO,
+            _ => cooldown.saturating_sub(now.saturating_duration_since(breaker.since)),
+        };
+        if !wait.is_zero() {
+            return Err(GoblinError::CircuitOpen {
+                model: model.to_string(),
+                retry_after_ms: (wait.as_millis() as u64).max(1),
+            });
+        }
+        breaker.trial_in_flight = true;
+        breaker.since = now;
+        if breaker.state == BreakerState::Open {
+            breaker.state = BreakerState::HalfOpen;
+            self.emit(model, breaker, sub_id);
+        }
+        Ok(())
+    }
+
+    /// Record how a call to `model` that was let through went
+    pub fn record(&self, model: &str, success: bool, sub_id: &SubmissionId) {
+        self.record_at(model, success, Instant::now(), sub_id)
+    }
+
+    pub fn record_at(&self, model: &str, success: bool, now: Instant, sub_id: &SubmissionId) {
+        let mut breakers = self.breakers.lock();
+        let breaker = breakers.entry(model.to_string()).or_insert_with(|| Breaker::new(now));
+        breaker.trial_in_flight = false;
+        let before = breaker.state;
+        if success {
+            breaker.failures = 0;
+            breaker.state = BreakerState::Closed;
+        } else {
+            breaker.failures += 1;
+            if before == BreakerState::HalfOpen || breaker.failures >= self.policy.failures {
+                breaker.state = BreakerState::Open;
+                breaker.since = now;
+            }
+        }
+        if breaker.state != before {
+            if breaker.state == BreakerState::Open {
+                warn!(model, failures = breaker.failures, "Circuit breaker opened");
+            }
+            self.emit(model, breaker, sub_id);
+        }
+    }
+
+    /// State of every model's breaker
+    pub fn states(&self) -> Vec<(String, BreakerState)> {
+        let mut states: Vec<_> = self.breakers.lock().iter().map(|(model, b)| (model.clone(), b.state)).collect();
+        states.sort();
+        states
+    }
+
+    fn emit(&self, model: &str, breaker: &Breaker, sub_id: &SubmissionId) {
+        let _ = self.event_tx.send(GoblinEvent::CircuitStateChanged {
+            sub_id: sub_id.clone(),
+            model: model.to_string(),
+            state: breaker.state,
+            failures: breaker.failures,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_fails_fast_then_half_opens() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let breakers = CircuitBreakers::new(BreakerPolicy::new(2, Duration::from_secs(10)), tx);
+        let sub_id = SubmissionId::new();
+        let start = Instant::now();
+        let model = "cloud/large";
+
+        for _ in 0..2 {
+            breakers.check_at(model, start, &sub_id).unwrap();
+            breakers.record_at(model, false, start, &sub_id);
+        }
+        let err = breakers.check_at(model, start + Duration::from_secs(4), &sub_id).unwrap_err();
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(6)));
+
+        // One trial after the cool-down; it fails and the breaker opens again
+        let later = start + Duration::from_secs(10);
+        breakers.check_at(model, later, &sub_id).unwrap();
+        assert!(breakers.check_at(model, later, &sub_id).is_err());
+        breakers.record_at(model, false, later, &sub_id);
+        assert!(breakers.check_at(model, later + Duration::from_secs(1), &sub_id).is_err());
+
+        let latest = later + Duration::from_secs(10);
+        breakers.check_at(model, latest, &sub_id).unwrap();
+        breakers.record_at(model, true, latest, &sub_id);
+        assert_eq!(breakers.states(), vec![(model.to_string(), BreakerState::Closed)]);
+
+        let states: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                GoblinEvent::CircuitStateChanged { state, .. } => Some(state),
+                _ => None,
+            })
+            .collect();
+        use BreakerState::*;
+        assert_eq!(states, vec![Open, HalfOpen, Open, HalfOpen, Closed]);
+    }
+}
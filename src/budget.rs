@@ -0,0 +1,478 @@
+//! Per-agent token budgets
+//!
+//! `warhorn::AgentConfig` has no room for a token limit, so budgets are
+//! set in the session options by role, like sandbox profiles, and each
+//! agent gets its [`TokenBudget`] when it is spawned. As the agent's usage
+//! crosses the budget's warning thresholds a `BudgetWarning` is emitted;
+//! once it reaches `max_tokens` the agent is marked over budget, a
+//! `BudgetExceeded` is emitted, and its model calls fail from then on.
+//!
+//! A session's [`BudgetLedger`] also rolls every agent's spending up the
+//! hierarchy, worker to lead to orchestrator. Ceilings on a subtree, by
+//! the role of the agent at its top, throttle a runaway lead and its
+//! workers by pausing them while the rest of the session carries on;
+//! a ceiling on the session pauses every agent. Raising the ceiling with
+//! `SetBudgetCeiling` resumes the agents it paused. The ledger also keeps
+//! the dollar cost of the spending, by subtree, by task and in total.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Weak};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::warn;
+use warhorn::{AgentId, AgentRole, SubmissionId, TaskId};
+
+use crate::agent::{Agent, AgentHandle};
+use crate::error::GoblinError;
+use crate::protocol::GoblinEvent;
+use crate::runtime::by_role;
+
+/// Fractions of the budget at which warnings are emitted by default
+pub const DEFAULT_WARN_AT: &[f64] = &[0.8, 0.9];
+
+/// Tokens one agent may spend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TokenBudget {
+    /// Hard limit on the agent's total tokens
+    pub max_tokens: u64,
+    /// Fractions of `max_tokens` at which a warning is emitted
+    #[serde(default = "default_warn_at")]
+    pub warn_at: Vec<f64>,
+}
+
+fn default_warn_at() -> Vec<f64> {
+    DEFAULT_WARN_AT.to_vec()
+}
+
+impl TokenBudget {
+    /// A budget warning at the [`DEFAULT_WARN_AT`] fractions
+    pub fn new(max_tokens: u64) -> Self {
+        Self {
+            max_tokens,
+            warn_at: default_warn_at(),
+        }
+    }
+
+    /// Warn at these fractions of the budget instead
+    pub fn with_warn_at(mut self, fractions: impl IntoIterator<Item = f64>) -> Self {
+        self.warn_at = fractions.into_iter().collect();
+        self
+    }
+
+    /// Warning thresholds passed when usage grows from `before` to `after`,
+    /// as fractions in ascending order
+    pub fn crossed(&self, before: u64, after: u64) -> Vec<f64> {
+        let mut crossed: Vec<f64> = self
+            .warn_at
+            .iter()
+            .copied()
+            .filter(|&f| {
+                let threshold = (self.max_tokens as f64 * f).ceil() as u64;
+                before < threshold && after >= threshold
+            })
+            .collect();
+        crossed.sort_by(f64::total_cmp);
+        crossed
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.max_tokens == 0 {
+            return Err(GoblinError::ConfigError("token budget max_tokens must be at least 1".into()));
+        }
+        if self.warn_at.iter().any(|f| !(*f > 0.0 && *f < 1.0)) {
+            return Err(GoblinError::ConfigError("token budget warnings must be between 0 and 1".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Token budgets of a session's agents, by role
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TokenBudgets {
+    /// Budget of agents no role entry matches; unlimited if None
+    #[serde(default)]
+    pub default: Option<TokenBudget>,
+    /// Budgets by role key, like `worker` or `specialist:reviewer`
+    #[serde(default)]
+    pub roles: HashMap<String, TokenBudget>,
+    /// Ceilings on the tokens spent by an agent and its descendants, by
+    /// the agent's role key
+    #[serde(default)]
+    pub subtrees: HashMap<String, u64>,
+    /// Ceiling on the tokens spent by the whole session
+    #[serde(default)]
+    pub session: Option<u64>,
+}
+
+impl TokenBudgets {
+    /// Give every agent this budget unless its role has its own
+    pub fn with_default(mut self, budget: TokenBudget) -> Self {
+        self.default = Some(budget);
+        self
+    }
+
+    /// Give agents with a role their own budget
+    pub fn with_role(mut self, role: impl Into<String>, budget: TokenBudget) -> Self {
+        self.roles.insert(role.into(), budget);
+        self
+    }
+
+    /// Cap what agents with a role and their descendants spend together
+    pub fn with_subtree_ceiling(mut self, role: impl Into<String>, max_tokens: u64) -> Self {
+        self.subtrees.insert(role.into(), max_tokens);
+        self
+    }
+
+    /// Cap what the whole session spends
+    pub fn with_session_ceiling(mut self, max_tokens: u64) -> Self {
+        self.session = Some(max_tokens);
+        self
+    }
+
+    /// Budget of an agent with `role`
+    pub fn budget_for(&self, role: &AgentRole) -> Option<&TokenBudget> {
+        by_role(&self.roles, role).or(self.default.as_ref())
+    }
+
+    /// Ceiling of the subtree below an agent with `role`
+    pub fn ceiling_for(&self, role: &AgentRole) -> Option<u64> {
+        by_role(&self.subtrees, role).copied()
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.subtrees.values().chain(&self.session).any(|max| *max == 0) {
+            return Err(GoblinError::ConfigError("token ceilings must be at least 1".into()));
+        }
+        self.default
+            .iter()
+            .chain(self.roles.values())
+            .try_for_each(TokenBudget::validate)
+    }
+}
+
+/// Spending of one agent's subtree, or of the session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubtreeBudget {
+    /// Agent at the top of the subtree; None for the whole session
+    pub agent_id: Option<AgentId>,
+    /// Tokens spent in the subtree, by terminated agents too
+    pub spent: u64,
+    /// Dollar cost of the tokens spent
+    #[serde(default)]
+    pub cost_usd: f64,
+    pub max_tokens: Option<u64>,
+    /// Whether the subtree is paused for reaching its ceiling
+    pub throttled: bool,
+}
+
+#[derive(Debug, Default)]
+struct Tally {
+    spent: u64,
+    cost_usd: f64,
+    max_tokens: Option<u64>,
+    /// Agents paused for reaching the ceiling, while throttled
+    paused: Option<Vec<AgentId>>,
+}
+
+impl Tally {
+    fn over(&self) -> bool {
+        self.max_tokens.is_some_and(|max| self.spent >= max)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Ledger {
+    subtrees: HashMap<AgentId, Tally>,
+    session: Tally,
+    /// Dollars spent on each task
+    tasks: HashMap<TaskId, f64>,
+}
+
+/// Paused list of the nearest throttled subtree in `chain`, or of the
+/// session if it is throttled and no subtree is
+fn nearest_paused<'a>(ledger: &'a mut Ledger, chain: &[AgentId]) -> Option<&'a mut Vec<AgentId>> {
+    let root = chain
+        .iter()
+        .find(|id| ledger.subtrees.get(id).is_some_and(|t| t.paused.is_some()))
+        .copied();
+    match root {
+        Some(id) => ledger.subtrees.get_mut(&id).and_then(|t| t.paused.as_mut()),
+        None => ledger.session.paused.as_mut(),
+    }
+}
+
+/// Token spending of a session's agents, rolled up the hierarchy
+#[derive(Debug)]
+pub struct BudgetLedger {
+    agents: Mutex<HashMap<AgentId, Weak<Agent>>>,
+    ledger: Mutex<Ledger>,
+    event_tx: mpsc::UnboundedSender<GoblinEvent>,
+}
+
+impl BudgetLedger {
+    /// A ledger capping the session at `session_max`, if set
+    pub fn new(session_max: Option<u64>, event_tx: mpsc::UnboundedSender<GoblinEvent>) -> Self {
+        let session = Tally {
+            max_tokens: session_max,
+            ..Tally::default()
+        };
+        Self {
+            agents: Mutex::new(HashMap::new()),
+            ledger: Mutex::new(Ledger { subtrees: HashMap::new(), session, tasks: HashMap::new() }),
+            event_tx,
+        }
+    }
+
+    /// Track an agent, capping its subtree at `max_tokens` if set
+    ///
+    /// Agents spawned into a throttled subtree are paused with it; returns
+    /// whether the agent was.
+    pub fn register(&self, agent: &AgentHandle, max_tokens: Option<u64>) -> bool {
+        {
+            let mut agents = self.agents.lock();
+            agents.retain(|_, agent| agent.strong_count() > 0);
+            agents.insert(agent.id(), agent.downgrade());
+        }
+        let chain = self.chain(agent);
+        let mut ledger = self.ledger.lock();
+        ledger.subtrees.insert(agent.id(), Tally { max_tokens, ..Tally::default() });
+        match nearest_paused(&mut ledger, &chain) {
+            Some(paused) if agent.pause() => {
+                paused.push(agent.id());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Add tokens spent by an agent, and their cost, to its subtree, every
+    /// subtree above and its current task
+    ///
+    /// Subtrees reaching their ceiling are throttled. Returns the tokens
+    /// spent by the whole session.
+    pub fn charge(&self, agent: &Agent, tokens: u64, cost_usd: f64, sub_id: &SubmissionId) -> u64 {
+        if tokens == 0 {
+            return self.session_spent();
+        }
+        let chain = self.chain(agent);
+        let (reached, session_reached, session_spent) = {
+            let mut ledger = self.ledger.lock();
+            if let Some(task_id) = agent.current_task() {
+                *ledger.tasks.entry(task_id).or_default() += cost_usd;
+            }
+            let mut reached = Vec::new();
+            for id in &chain {
+                if let Some(tally) = ledger.subtrees.get_mut(id) {
+                    tally.spent += tokens;
+                    tally.cost_usd += cost_usd;
+                    if tally.over() && tally.paused.is_none() {
+                        tally.paused = Some(Vec::new());
+                        reached.push((*id, tally.spent, tally.max_tokens.unwrap_or_default()));
+                    }
+                }
+            }
+            let session = &mut ledger.session;
+            session.spent += tokens;
+            session.cost_usd += cost_usd;
+            let session_reached = session.over() && session.paused.is_none();
+            if session_reached {
+                session.paused = Some(Vec::new());
+            }
+            let spent = session.spent;
+            (reached, session_reached.then(|| (spent, session.max_tokens.unwrap_or_default())), spent)
+        };
+
+        for (root, used, limit) in reached {
+            warn!(agent_id = %root, used, limit, "Subtree reached its token ceiling");
+            self.emit(GoblinEvent::BudgetCeilingReached { sub_id: sub_id.clone(), agent_id: Some(root), used, limit });
+            let paused = self.pause_where(|id| self.chain_of(id).contains(&root), sub_id);
+            self.throttle(Some(root), paused);
+        }
+        if let Some((used, limit)) = session_reached {
+            warn!(used, limit, "Session reached its token ceiling");
+            self.emit(GoblinEvent::BudgetCeilingReached { sub_id: sub_id.clone(), agent_id: None, used, limit });
+            let paused = self.pause_where(|_| true, sub_id);
+            self.throttle(None, paused);
+        }
+        session_spent
+    }
+
+    /// Change the ceiling of an agent's subtree, or of the session if no
+    /// agent is given
+    ///
+    /// Lifting a subtree over its new ceiling resumes the agents it paused,
+    /// unless a ceiling above still throttles them; those stay paused
+    /// until that one lifts too.
+    pub fn set_ceiling(
+        &self,
+        agent_id: Option<AgentId>,
+        max_tokens: Option<u64>,
+        sub_id: &SubmissionId,
+    ) -> Result<(), GoblinError> {
+        if max_tokens == Some(0) {
+            return Err(GoblinError::ConfigError("token ceilings must be at least 1".into()));
+        }
+        let released = {
+            let mut ledger = self.ledger.lock();
+            let tally = match agent_id {
+                Some(id) => ledger.subtrees.get_mut(&id).ok_or(GoblinError::AgentNotFound(id))?,
+                None => &mut ledger.session,
+            };
+            tally.max_tokens = max_tokens;
+            if tally.over() {
+                Vec::new()
+            } else {
+                tally.paused.take().unwrap_or_default()
+            }
+        };
+        for id in released {
+            let Some(agent) = self.find(&id) else {
+                continue;
+            };
+            let chain = self.chain(&agent);
+            if let Some(paused) = nearest_paused(&mut self.ledger.lock(), &chain) {
+                paused.push(id);
+                continue;
+            }
+            if agent.resume() {
+                self.emit(GoblinEvent::AgentResumed { sub_id: sub_id.clone(), agent_id: id });
+            }
+        }
+        Ok(())
+    }
+
+    /// Spending of the session followed by every subtree with spending or
+    /// a ceiling
+    pub fn rollup(&self) -> Vec<SubtreeBudget> {
+        let ledger = self.ledger.lock();
+        let summary = |agent_id, tally: &Tally| SubtreeBudget {
+            agent_id,
+            spent: tally.spent,
+            cost_usd: tally.cost_usd,
+            max_tokens: tally.max_tokens,
+            throttled: tally.paused.is_some(),
+        };
+        let mut subtrees: Vec<SubtreeBudget> = ledger
+            .subtrees
+            .iter()
+            .filter(|(_, t)| t.spent > 0 || t.max_tokens.is_some())
+            .map(|(id, t)| summary(Some(*id), t))
+            .collect();
+        subtrees.sort_by(|a, b| b.spent.cmp(&a.spent));
+        subtrees.insert(0, summary(None, &ledger.session));
+        subtrees
+    }
+
+    /// Tokens spent by an agent and its descendants
+    pub fn spent_below(&self, agent_id: &AgentId) -> u64 {
+        self.ledger.lock().subtrees.get(agent_id).map_or(0, |t| t.spent)
+    }
+
+    /// Dollars spent by an agent and its descendants
+    pub fn cost_below(&self, agent_id: &AgentId) -> f64 {
+        self.ledger.lock().subtrees.get(agent_id).map_or(0.0, |t| t.cost_usd)
+    }
+
+    /// Tokens spent by the whole session
+    pub fn session_spent(&self) -> u64 {
+        self.ledger.lock().session.spent
+    }
+
+    /// Dollars spent by the whole session
+    pub fn session_cost(&self) -> f64 {
+        self.ledger.lock().session.cost_usd
+    }
+
+    /// Dollars spent on each task, most expensive first
+    pub fn task_costs(&self) -> Vec<(TaskId, f64)> {
+        let mut costs: Vec<(TaskId, f64)> = self.ledger.lock().tasks.iter().map(|(id, c)| (*id, *c)).collect();
+        costs.sort_by(|a, b| b.1.total_cmp(&a.1));
+        costs
+    }
+
+    /// Whether an agent's subtree, or one above it, is throttled
+    pub fn is_throttled(&self, agent: &Agent) -> bool {
+        let chain = self.chain(agent);
+        let ledger = self.ledger.lock();
+        ledger.session.paused.is_some()
+            || chain
+                .iter()
+                .any(|id| ledger.subtrees.get(id).is_some_and(|t| t.paused.is_some()))
+    }
+
+    /// The agent followed by its ancestors
+    fn chain(&self, agent: &Agent) -> Vec<AgentId> {
+        let mut chain = vec![agent.id];
+        let mut parent = agent.parent_id();
+        while let Some(id) = parent {
+            chain.push(id);
+            parent = self.find(&id).and_then(|a| a.parent_id());
+        }
+        chain
+    }
+
+    fn chain_of(&self, agent_id: &AgentId) -> Vec<AgentId> {
+        self.find(agent_id).map(|a| self.chain(&a)).unwrap_or_default()
+    }
+
+    fn find(&self, agent_id: &AgentId) -> Option<Arc<Agent>> {
+        self.agents.lock().get(agent_id)?.upgrade()
+    }
+
+    /// Pause the live agents matching `filter`, returning those that were
+    /// running
+    fn pause_where(&self, filter: impl Fn(&AgentId) -> bool, sub_id: &SubmissionId) -> Vec<AgentId> {
+        let ids: Vec<AgentId> = self.agents.lock().keys().copied().collect();
+        let mut paused = Vec::new();
+        for id in ids.into_iter().filter(|id| filter(id)) {
+            if self.find(&id).is_some_and(|agent| agent.pause()) {
+                self.emit(GoblinEvent::AgentPaused { sub_id: sub_id.clone(), agent_id: id });
+                paused.push(id);
+            }
+        }
+        paused
+    }
+
+    fn throttle(&self, root: Option<AgentId>, paused: Vec<AgentId>) {
+        let mut ledger = self.ledger.lock();
+        let tally = match root {
+            Some(id) => ledger.subtrees.get_mut(&id),
+            None => Some(&mut ledger.session),
+        };
+        if let Some(list) = tally.and_then(|t| t.paused.as_mut()) {
+            list.extend(paused);
+        }
+    }
+
+    fn emit(&self, event: GoblinEvent) {
+        let _ = self.event_tx.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thresholds_are_crossed_once() {
+        let budget = TokenBudget::new(1000).with_warn_at([0.9, 0.5]);
+        assert!(budget.crossed(0, 499).is_empty());
+        assert_eq!(budget.crossed(0, 950), vec![0.5, 0.9]);
+        assert_eq!(budget.crossed(500, 950), vec![0.9]);
+        assert!(budget.crossed(950, 1200).is_empty());
+    }
+
+    #[test]
+    fn test_role_budgets_fall_back_to_the_default() {
+        let budgets = TokenBudgets::default()
+            .with_default(TokenBudget::new(1000))
+            .with_role("specialist", TokenBudget::new(50));
+        let reviewer = AgentRole::Specialist { specialty: "reviewer".into() };
+        assert_eq!(budgets.budget_for(&reviewer).unwrap().max_tokens, 50);
+        assert_eq!(budgets.budget_for(&AgentRole::Worker).unwrap().max_tokens, 1000);
+        assert!(TokenBudgets::default().budget_for(&AgentRole::Worker).is_none());
+        assert!(TokenBudget::new(10).with_warn_at([1.5]).validate().is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Portable session bundles
+//!
+//! A [`SessionBundle`] packs a finished session into one JSON file: its
+//! configuration, the hierarchy with final agent statuses, every agent's
+//! transcript, the plans and results, and the tokens spent. Bundles are
+//! meant for sharing runs between machines; loading one gives a read-only
+//! view for inspection and starts nothing.
+
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentId, TaskId, TokenUsage};
+
+use crate::checkpoint::{self, SessionCheckpoint, CHECKPOINT_VERSION};
+use crate::error::GoblinError;
+use crate::hierarchy::HierarchySnapshot;
+use crate::history::History;
+use crate::result::TaskResult;
+
+/// Version of the bundle format written by this crate
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// A finished session, packed for sharing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionBundle {
+    /// Format version, see [`BUNDLE_VERSION`]
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Hierarchy with the agents' final statuses
+    pub hierarchy: HierarchySnapshot,
+    /// Tokens spent by the whole session
+    pub usage: TokenUsage,
+    /// Configuration, agent transcripts, plans and results
+    pub session: SessionCheckpoint,
+}
+
+impl SessionBundle {
+    /// Transcript of an agent
+    pub fn transcript(&self, agent_id: &AgentId) -> Option<&History> {
+        self.session
+            .agents
+            .iter()
+            .find(|a| a.agent_id == *agent_id)
+            .map(|a| &a.history)
+    }
+
+    /// Result of a task or subtask
+    pub fn result(&self, task_id: &TaskId) -> Option<&TaskResult> {
+        self.session.results.iter().find(|r| r.task_id == *task_id)
+    }
+
+    /// Write the bundle to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GoblinError> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| GoblinError::PersistenceError(format!("Failed to encode bundle: {}", e)))?;
+        checkpoint::write_atomic(path.as_ref(), &json)
+    }
+
+    /// Read a bundle written by [`save`](Self::save)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GoblinError> {
+        let path = path.as_ref();
+        let json = std::fs::read(path).map_err(|e| checkpoint::persistence_error(path, e))?;
+        let bundle: Self = serde_json::from_slice(&json).map_err(|e| {
+            GoblinError::PersistenceError(format!("Invalid bundle {}: {}", path.display(), e))
+        })?;
+        if bundle.version != BUNDLE_VERSION || bundle.session.version != CHECKPOINT_VERSION {
+            return Err(GoblinError::PersistenceError(format!(
+                "Bundle {} has version {}.{}, expected {}.{}",
+                path.display(),
+                bundle.version,
+                bundle.session.version,
+                BUNDLE_VERSION,
+                CHECKPOINT_VERSION
+            )));
+        }
+        Ok(bundle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+    use trinkets::ToolRegistry;
+    use warhorn::{AgentConfig, AgentRole, SessionConfig, SubmissionId};
+
+    use crate::history::HistoryEntry;
+    use crate::model::ChatRole;
+    use crate::session::Session;
+
+    #[test]
+    fn test_export_and_load_bundle() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = session.spawn_agent(root, None, &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+        worker.record(HistoryEntry::message(ChatRole::User, "Fix the parser"));
+        worker.add_usage(100, 20);
+
+        let task_id = TaskId::new();
+        session.start_task(task_id, "Fix the parser");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.json");
+        assert!(session.export(&path).is_err());
+
+        session.finish_task(&task_id);
+        session.export(&path).unwrap();
+        let bundle = SessionBundle::load(&path).unwrap();
+        assert_eq!(bundle.session.session_id, session.id);
+        assert_eq!(bundle.hierarchy, session.hierarchy_snapshot());
+        assert_eq!(bundle.usage, session.total_usage());
+        assert_eq!(bundle.transcript(&worker.id()).unwrap().len(), 1);
+        assert!(bundle.result(&task_id).is_none());
+
+        let mut newer = bundle;
+        newer.version = BUNDLE_VERSION + 1;
+        newer.save(&path).unwrap();
+        assert!(matches!(SessionBundle::load(&path), Err(GoblinError::PersistenceError(_))));
+    }
+}
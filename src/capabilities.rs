@@ -0,0 +1,134 @@
+//! Capability introspection for clients
+//!
+//! UIs ask the orchestrator what it supports before offering controls, so
+//! they don't present actions this build or configuration will reject.
+
+use serde::{Deserialize, Serialize};
+
+/// What this orchestrator supports
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// Crate version
+    pub version: String,
+    /// Registered tools
+    pub tools: Vec<String>,
+    /// Models used by active sessions
+    pub models: Vec<String>,
+    /// Agent roles that can be spawned
+    pub roles: Vec<String>,
+    /// Available planning strategies
+    pub planners: Vec<String>,
+    /// Available result merging strategies
+    pub mergers: Vec<String>,
+    /// Registered agent runtimes
+    #[serde(default)]
+    pub runtimes: Vec<String>,
+    /// Registered model providers
+    #[serde(default)]
+    pub model_providers: Vec<String>,
+    /// Orchestration and policy features
+    pub features: Vec<String>,
+    /// Enabled cargo features
+    pub cargo_features: Vec<String>,
+}
+
+/// Agent roles every build can spawn
+pub const ROLES: &[&str] = &["orchestrator", "domain_lead", "worker", "specialist"];
+
+/// Orchestration and policy features every build supports
+pub const FEATURES: &[&str] = &[
+    "attachments",
+    "artifacts",
+    "workflows",
+    "workflow_approval_gates",
+    "priority_scheduling",
+    "fair_share_slots",
+    "cross_session_delegation",
+    "task_deadlines",
+    "self_test",
+    "recurring_tasks",
+    "result_merging",
+    "result_review",
+    "map_reduce",
+    "pipelines",
+    "structured_concurrency",
+    "agent_actors",
+    "agent_runtimes",
+    "model_providers",
+    "message_streaming",
+    "agent_history",
+    "history_compaction",
+    "context_handoff",
+    "agent_forking",
+    "agent_pausing",
+    "agent_restart",
+    "agent_reparenting",
+    "tree_deltas",
+    "session_checkpoints",
+    "session_restore",
+    "session_journal",
+    "session_forking",
+    "session_bundles",
+    "speculative_execution",
+    "debates",
+    "automation_rules",
+    "session_store",
+    "live_config_updates",
+    "concurrent_tasks",
+    "session_metrics",
+    "bounded_channels",
+    "event_broadcast",
+    "filtered_subscriptions",
+    "event_replay",
+    "channel_health",
+    "stdio_transport",
+    "approval_policy",
+    "approval_timeouts",
+    "approval_delegation",
+    "pending_approvals",
+    "trust_levels",
+    "tool_scopes",
+    "sandbox_profiles",
+    "sandbox_escalation",
+    "audit_log",
+    "policy_files",
+    "spawn_limits",
+    "agent_limit",
+    "spawn_rate_limits",
+    "model_call_limits",
+    "token_budgets",
+    "budget_rollups",
+    "cost_tracking",
+    "usage_updates",
+    "supervision",
+    "task_retries",
+    "failure_reports",
+    "circuit_breakers",
+    "failure_propagation",
+    "heartbeats",
+    "stall_detection",
+    "error_codes",
+    "orphan_adoption",
+    "crash_recovery",
+    "tool_overrides",
+    "tool_result_cache",
+    "tool_call_coalescing",
+    "tool_rate_limits",
+    "agent_tool_concurrency",
+    "mcp_servers",
+    "cost_estimates",
+    "unix_daemon",
+];
+
+/// Cargo features this build was compiled with
+pub fn cargo_features() -> Vec<String> {
+    // Optional transports and integrations register themselves here
+    let mut features = Vec::new();
+    if cfg!(feature = "websocket") {
+        features.push("websocket".to_string());
+    }
+    if cfg!(feature = "grpc") {
+        features.push("grpc".to_string());
+    }
+    features
+}
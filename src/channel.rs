@@ -1,23 +1,574 @@
 //! Communication channels for the orchestrator
+//!
+//! Channels are unbounded by default. A [`ChannelBuilder`] with a buffer size
+//! builds bounded channels instead, which apply an [`OverflowPolicy`] when a
+//! send finds them full, so an event storm cannot grow the queues without
+//! limit.
 
-use tokio::sync::mpsc;
-use warhorn::{Op, Event};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc::error::TryRecvError;
+
+use crate::filter::EventFilter;
+use crate::protocol::{GoblinEvent, GoblinOp};
+use crate::replay::ReplayBuffer;
+
+/// What a bounded channel does when a send finds it full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Wait for the receiver to make room; non-async sends fail instead
+    #[default]
+    Block,
+    /// Discard the oldest queued message to make room
+    ///
+    /// The ring holding the messages has a power-of-two size, so the
+    /// capacity is rounded up to the next power of two.
+    DropOldest,
+    /// Fail the send with [`ChannelError::Full`]
+    Error,
+}
+
+/// What a broadcast subscriber does when it falls behind and events are
+/// overwritten before it reads them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// Skip the lost events and continue with the oldest one still buffered
+    #[default]
+    Skip,
+    /// End the subscription, so the subscriber can resync from a snapshot
+    Disconnect,
+}
+
+/// Health of one channel, for spotting backpressure
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ChannelStats {
+    /// Messages waiting to be received
+    pub depth: usize,
+    pub received: u64,
+    /// Messages lost to a full channel or a lagging subscriber
+    pub dropped: u64,
+    /// Messages received per second, over the last second or so
+    pub per_sec: f64,
+    /// Age of the oldest message waiting, if send times are known
+    pub oldest_pending_ms: Option<u64>,
+}
+
+/// Interval over which the receive rate is measured
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Counters shared by the two halves of a channel
+#[derive(Debug)]
+struct Gauge {
+    /// Whether send times are recorded; only single-receiver channels are
+    tracked: bool,
+    /// Send times of messages not yet received, oldest first
+    pending: parking_lot::Mutex<VecDeque<Instant>>,
+    received: AtomicU64,
+    dropped: AtomicU64,
+    rate: parking_lot::Mutex<Rate>,
+}
+
+#[derive(Debug)]
+struct Rate {
+    since: Instant,
+    count: u64,
+    per_sec: f64,
+}
+
+impl Gauge {
+    fn new(tracked: bool) -> Arc<Self> {
+        Arc::new(Self {
+            tracked,
+            pending: parking_lot::Mutex::new(VecDeque::new()),
+            received: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            rate: parking_lot::Mutex::new(Rate {
+                since: Instant::now(),
+                count: 0,
+                per_sec: 0.0,
+            }),
+        })
+    }
+
+    /// Record a send; call before the message can be received
+    fn sent(&self) {
+        if self.tracked {
+            self.pending.lock().push_back(Instant::now());
+        }
+    }
+
+    /// Take back a send that failed
+    fn unsent(&self) {
+        if self.tracked {
+            self.pending.lock().pop_back();
+        }
+    }
+
+    fn received(&self) {
+        if self.tracked {
+            self.pending.lock().pop_front();
+        }
+        self.received.fetch_add(1, Ordering::Relaxed);
+
+        let mut rate = self.rate.lock();
+        rate.count += 1;
+        let elapsed = rate.since.elapsed();
+        if elapsed >= RATE_WINDOW {
+            rate.per_sec = rate.count as f64 / elapsed.as_secs_f64();
+            rate.since = Instant::now();
+            rate.count = 0;
+        }
+    }
+
+    fn dropped(&self, n: u64) {
+        self.dropped.fetch_add(n, Ordering::Relaxed);
+    }
+
+    fn stats(&self, depth: Option<usize>) -> ChannelStats {
+        let per_sec = {
+            // A window that ran long without closing means the flow slowed down
+            let rate = self.rate.lock();
+            let elapsed = rate.since.elapsed();
+            if elapsed >= RATE_WINDOW {
+                rate.count as f64 / elapsed.as_secs_f64()
+            } else {
+                rate.per_sec
+            }
+        };
+        let pending = self.pending.lock();
+        ChannelStats {
+            depth: depth.unwrap_or(pending.len()),
+            received: self.received.load(Ordering::Relaxed),
+            dropped: self.dropped.load(Ordering::Relaxed),
+            per_sec,
+            oldest_pending_ms: pending.front().map(|t| t.elapsed().as_millis() as u64),
+        }
+    }
+}
+
+/// Sending half of an unbounded or bounded channel
+#[derive(Debug)]
+pub struct ChannelSender<T> {
+    inner: SenderInner<T>,
+    gauge: Arc<Gauge>,
+}
+
+#[derive(Debug)]
+enum SenderInner<T> {
+    Unbounded(mpsc::UnboundedSender<T>),
+    Bounded(mpsc::Sender<T>, OverflowPolicy),
+    /// Bounded with [`OverflowPolicy::DropOldest`], which a broadcast ring provides
+    Ring(broadcast::Sender<T>),
+}
+
+impl<T> Clone for ChannelSender<T> {
+    fn clone(&self) -> Self {
+        let inner = match &self.inner {
+            SenderInner::Unbounded(tx) => SenderInner::Unbounded(tx.clone()),
+            SenderInner::Bounded(tx, policy) => SenderInner::Bounded(tx.clone(), *policy),
+            SenderInner::Ring(tx) => SenderInner::Ring(tx.clone()),
+        };
+        Self {
+            inner,
+            gauge: Arc::clone(&self.gauge),
+        }
+    }
+}
+
+impl<T> ChannelSender<T> {
+    /// Send without waiting
+    ///
+    /// A full bounded channel fails with [`ChannelError::Full`] unless its
+    /// policy is [`OverflowPolicy::DropOldest`].
+    pub fn send(&self, msg: T) -> Result<(), ChannelError> {
+        match &self.inner {
+            SenderInner::Unbounded(tx) => {
+                self.gauge.sent();
+                tx.send(msg).map_err(|_| {
+                    self.gauge.unsent();
+                    ChannelError::Closed
+                })
+            }
+            SenderInner::Bounded(tx, _) => match tx.try_reserve() {
+                Ok(permit) => {
+                    self.gauge.sent();
+                    permit.send(msg);
+                    Ok(())
+                }
+                Err(mpsc::error::TrySendError::Full(())) => {
+                    self.gauge.dropped(1);
+                    Err(ChannelError::Full)
+                }
+                Err(mpsc::error::TrySendError::Closed(())) => Err(ChannelError::Closed),
+            },
+            SenderInner::Ring(tx) => tx.send(msg).map(|_| ()).map_err(|_| ChannelError::Closed),
+        }
+    }
+
+    /// Send, waiting for room if the channel is bounded with [`OverflowPolicy::Block`]
+    pub async fn send_async(&self, msg: T) -> Result<(), ChannelError> {
+        match &self.inner {
+            SenderInner::Bounded(tx, OverflowPolicy::Block) => {
+                let permit = tx.reserve().await.map_err(|_| ChannelError::Closed)?;
+                self.gauge.sent();
+                permit.send(msg);
+                Ok(())
+            }
+            _ => self.send(msg),
+        }
+    }
+
+    /// Whether the receiving half is gone
+    pub fn is_closed(&self) -> bool {
+        match &self.inner {
+            SenderInner::Unbounded(tx) => tx.is_closed(),
+            SenderInner::Bounded(tx, _) => tx.is_closed(),
+            SenderInner::Ring(tx) => tx.receiver_count() == 0,
+        }
+    }
+
+    /// Health of the channel as seen from the sending side
+    pub fn stats(&self) -> ChannelStats {
+        let depth = match &self.inner {
+            SenderInner::Bounded(tx, _) => Some(tx.max_capacity() - tx.capacity()),
+            SenderInner::Ring(tx) => Some(tx.len()),
+            SenderInner::Unbounded(_) => None,
+        };
+        self.gauge.stats(depth)
+    }
+
+    /// The underlying sender, if the channel is unbounded
+    pub(crate) fn unbounded(&self) -> Option<&mpsc::UnboundedSender<T>> {
+        match &self.inner {
+            SenderInner::Unbounded(tx) => Some(tx),
+            _ => None,
+        }
+    }
+}
+
+impl<T> From<mpsc::UnboundedSender<T>> for ChannelSender<T> {
+    fn from(tx: mpsc::UnboundedSender<T>) -> Self {
+        Self {
+            inner: SenderInner::Unbounded(tx),
+            gauge: Gauge::new(false),
+        }
+    }
+}
+
+/// Receiving half of an unbounded or bounded channel
+#[derive(Debug)]
+pub struct ChannelReceiver<T> {
+    inner: ReceiverInner<T>,
+    gauge: Arc<Gauge>,
+}
+
+#[derive(Debug)]
+enum ReceiverInner<T> {
+    Unbounded(mpsc::UnboundedReceiver<T>),
+    Bounded(mpsc::Receiver<T>),
+    Ring(RingReceiver<T>),
+}
+
+type RingRecv<T> = Pin<Box<dyn Future<Output = (Result<T, RecvError>, broadcast::Receiver<T>)> + Send>>;
+
+/// Broadcast receiver that can be polled; while a receive is in flight the
+/// receiver lives inside the pending future
+struct RingReceiver<T> {
+    rx: Option<broadcast::Receiver<T>>,
+    pending: Option<RingRecv<T>>,
+    lag: LagPolicy,
+}
+
+impl<T> std::fmt::Debug for RingReceiver<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RingReceiver").field("pending", &self.pending.is_some()).finish()
+    }
+}
+
+impl<T: Clone + Send + 'static> ChannelReceiver<T> {
+    fn ring(rx: broadcast::Receiver<T>, lag: LagPolicy) -> Self {
+        Self {
+            inner: ReceiverInner::Ring(RingReceiver { rx: Some(rx), pending: None, lag }),
+            gauge: Gauge::new(false),
+        }
+    }
+
+    /// Receive the next message, or None once every sender is gone
+    pub async fn recv(&mut self) -> Option<T> {
+        std::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Poll for the next message, or None once every sender is gone
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let msg = match &mut self.inner {
+            ReceiverInner::Unbounded(rx) => ready!(rx.poll_recv(cx)),
+            ReceiverInner::Bounded(rx) => ready!(rx.poll_recv(cx)),
+            ReceiverInner::Ring(ring) => ready!(Self::poll_ring(ring, &self.gauge, cx)),
+        };
+        if msg.is_some() {
+            self.gauge.received();
+        }
+        Poll::Ready(msg)
+    }
+
+    fn poll_ring(ring: &mut RingReceiver<T>, gauge: &Gauge, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        loop {
+            if ring.pending.is_none() {
+                // A subscriber disconnected for lagging has no receiver left
+                let Some(mut rx) = ring.rx.take() else {
+                    return Poll::Ready(None);
+                };
+                ring.pending = Some(Box::pin(async move { (rx.recv().await, rx) }));
+            }
+            let pending = ring.pending.as_mut().expect("receive in flight");
+            let (result, rx) = ready!(pending.as_mut().poll(cx));
+            ring.pending = None;
+            match result {
+                Ok(msg) => {
+                    ring.rx = Some(rx);
+                    return Poll::Ready(Some(msg));
+                }
+                Err(RecvError::Lagged(n)) => {
+                    gauge.dropped(n);
+                    if ring.lag == LagPolicy::Disconnect {
+                        return Poll::Ready(None);
+                    }
+                    ring.rx = Some(rx);
+                }
+                Err(RecvError::Closed) => return Poll::Ready(None),
+            }
+        }
+    }
+
+    /// Receive a message if one is queued
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let msg = match &mut self.inner {
+            ReceiverInner::Unbounded(rx) => rx.try_recv()?,
+            ReceiverInner::Bounded(rx) => rx.try_recv()?,
+            ReceiverInner::Ring(_) => {
+                // A receive left in flight by a dropped `recv` owns the receiver
+                let waker = futures::task::noop_waker();
+                return match self.poll_recv(&mut Context::from_waker(&waker)) {
+                    Poll::Ready(Some(msg)) => Ok(msg),
+                    Poll::Ready(None) => Err(TryRecvError::Disconnected),
+                    Poll::Pending => Err(TryRecvError::Empty),
+                };
+            }
+        };
+        self.gauge.received();
+        Ok(msg)
+    }
+
+    /// Messages discarded to make room so far
+    pub fn dropped(&self) -> u64 {
+        self.gauge.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Health of the channel as seen from the receiving side
+    pub fn stats(&self) -> ChannelStats {
+        let depth = match &self.inner {
+            ReceiverInner::Unbounded(rx) => rx.len(),
+            ReceiverInner::Bounded(rx) => rx.len(),
+            ReceiverInner::Ring(ring) => ring.rx.as_ref().map_or(0, |rx| rx.len()),
+        };
+        self.gauge.stats(Some(depth))
+    }
+}
+
+impl<T: Clone + Send + 'static> Stream for ChannelReceiver<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        self.get_mut().poll_recv(cx)
+    }
+}
+
+impl<T: Clone + Send + 'static> From<mpsc::UnboundedReceiver<T>> for ChannelReceiver<T> {
+    fn from(rx: mpsc::UnboundedReceiver<T>) -> Self {
+        Self {
+            inner: ReceiverInner::Unbounded(rx),
+            gauge: Gauge::new(false),
+        }
+    }
+}
+
+/// Create a channel, bounded to `capacity` messages if given
+pub fn channel<T: Clone + Send + 'static>(capacity: Option<usize>, policy: OverflowPolicy) -> (ChannelSender<T>, ChannelReceiver<T>) {
+    let (tx, rx) = match capacity.map(|c| c.max(1)) {
+        None => {
+            let (tx, rx) = mpsc::unbounded_channel();
+            (SenderInner::Unbounded(tx), ReceiverInner::Unbounded(rx))
+        }
+        Some(capacity) if policy == OverflowPolicy::DropOldest => {
+            let (tx, rx) = broadcast::channel(capacity);
+            let tx = ChannelSender {
+                inner: SenderInner::Ring(tx),
+                gauge: Gauge::new(false),
+            };
+            return (tx, ChannelReceiver::ring(rx, LagPolicy::Skip));
+        }
+        Some(capacity) => {
+            let (tx, rx) = mpsc::channel(capacity);
+            (SenderInner::Bounded(tx, policy), ReceiverInner::Bounded(rx))
+        }
+    };
+    let gauge = Gauge::new(true);
+    (
+        ChannelSender { inner: tx, gauge: Arc::clone(&gauge) },
+        ChannelReceiver { inner: rx, gauge },
+    )
+}
 
 /// Channel pair for orchestrator communication
 pub struct ChannelPair {
     /// Receiver for operations
-    pub op_rx: mpsc::UnboundedReceiver<Op>,
+    pub op_rx: ChannelReceiver<GoblinOp>,
     /// Sender for events
-    pub event_tx: mpsc::UnboundedSender<Event>,
+    pub event_tx: ChannelSender<GoblinEvent>,
+    /// Recent events replayed to new subscribers, in broadcast mode
+    pub replay: Option<Arc<ReplayBuffer>>,
+}
+
+/// Client-side half for sending operations to the orchestrator
+#[derive(Debug, Clone)]
+pub struct GoblinSender {
+    op_tx: ChannelSender<GoblinOp>,
+}
+
+impl GoblinSender {
+    /// Send an operation to the orchestrator
+    pub fn send(&self, op: impl Into<GoblinOp>) -> Result<(), ChannelError> {
+        self.op_tx.send(op.into())
+    }
+
+    /// Send an operation, waiting for room in a full bounded channel
+    pub async fn send_async(&self, op: impl Into<GoblinOp>) -> Result<(), ChannelError> {
+        self.op_tx.send_async(op.into()).await
+    }
+
+    /// Check if the orchestrator stopped receiving operations
+    pub fn is_closed(&self) -> bool {
+        self.op_tx.is_closed()
+    }
+
+    /// Health of the operation channel
+    pub fn stats(&self) -> ChannelStats {
+        self.op_tx.stats()
+    }
+}
+
+/// Client-side half for receiving events from the orchestrator
+#[derive(Debug)]
+pub struct GoblinReceiver {
+    event_rx: ChannelReceiver<GoblinEvent>,
+    /// Replayed events, delivered before live ones
+    backlog: VecDeque<GoblinEvent>,
+    /// Events to deliver; the others are skipped
+    filter: Option<EventFilter>,
+}
+
+impl GoblinReceiver {
+    fn new(event_rx: ChannelReceiver<GoblinEvent>) -> Self {
+        Self {
+            event_rx,
+            backlog: VecDeque::new(),
+            filter: None,
+        }
+    }
+
+    /// Only deliver events that match a filter
+    pub fn filtered(mut self, filter: EventFilter) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// Try to receive an event (non-blocking)
+    pub fn try_recv(&mut self) -> Option<GoblinEvent> {
+        loop {
+            let event = match self.backlog.pop_front() {
+                Some(event) => event,
+                None => self.event_rx.try_recv().ok()?,
+            };
+            if self.wanted(&event) {
+                return Some(event);
+            }
+        }
+    }
+
+    /// Receive an event, or None once the orchestrator is gone
+    pub async fn recv(&mut self) -> Option<GoblinEvent> {
+        std::future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<GoblinEvent>> {
+        loop {
+            let event = match self.backlog.pop_front() {
+                Some(event) => Some(event),
+                None => ready!(self.event_rx.poll_recv(cx)),
+            };
+            match event {
+                Some(event) if !self.wanted(&event) => continue,
+                event => return Poll::Ready(event),
+            }
+        }
+    }
+
+    fn wanted(&mut self, event: &GoblinEvent) -> bool {
+        match &mut self.filter {
+            Some(filter) => filter.matches(event),
+            None => true,
+        }
+    }
+
+    /// Events discarded by a full channel so far
+    pub fn dropped(&self) -> u64 {
+        self.event_rx.dropped()
+    }
+
+    /// Health of the event channel; replayed events count as waiting
+    pub fn stats(&self) -> ChannelStats {
+        let mut stats = self.event_rx.stats();
+        stats.depth += self.backlog.len();
+        stats
+    }
+}
+
+impl Stream for GoblinReceiver {
+    type Item = GoblinEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<GoblinEvent>> {
+        self.get_mut().poll_recv(cx)
+    }
 }
 
 /// Client-side channel for communicating with the orchestrator
-#[derive(Clone)]
+///
+/// Combines a [`GoblinSender`] and a [`GoblinReceiver`]; clones share the
+/// receiver. Use [`split`](Self::split) to send and receive from separate
+/// tasks, and a channel built with [`ChannelBuilder::broadcast`] to give
+/// several consumers their own receivers.
+#[derive(Debug, Clone)]
 pub struct GoblinChannel {
-    /// Sender for operations
-    op_tx: mpsc::UnboundedSender<Op>,
-    /// Receiver for events
-    event_rx: std::sync::Arc<parking_lot::Mutex<mpsc::UnboundedReceiver<Event>>>,
+    sender: GoblinSender,
+    receiver: Arc<tokio::sync::Mutex<GoblinReceiver>>,
+    /// Counters of the shared receiver, readable while it waits for events
+    event_gauge: Arc<Gauge>,
+    /// Idle receiver new subscribers are cloned from, in broadcast mode;
+    /// unlike a sender, it lets the channel close when the orchestrator stops
+    events: Option<Arc<broadcast::Receiver<GoblinEvent>>>,
+    lag: LagPolicy,
+    /// Recent events for new subscribers
+    replay: Option<Arc<ReplayBuffer>>,
 }
 
 impl GoblinChannel {
@@ -25,40 +576,86 @@ impl GoblinChannel {
     ///
     /// Returns the client channel and the orchestrator channel pair
     pub fn new() -> (Self, ChannelPair) {
-        let (op_tx, op_rx) = mpsc::unbounded_channel();
-        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        ChannelBuilder::new().build()
+    }
 
-        let channel = Self {
-            op_tx,
-            event_rx: std::sync::Arc::new(parking_lot::Mutex::new(event_rx)),
+    /// A new receiver of every event sent from now on, independent of the
+    /// channel's own receiver
+    ///
+    /// With a replay buffer, the receiver first gets the recent critical
+    /// events of every session. Returns None unless the channel was built
+    /// in broadcast mode.
+    pub fn subscribe(&self) -> Option<GoblinReceiver> {
+        let events = self.events.as_ref()?;
+        let (backlog, rx) = match &self.replay {
+            Some(replay) => replay.subscribe(|| events.resubscribe()),
+            None => (Vec::new(), events.resubscribe()),
         };
+        let mut receiver = GoblinReceiver::new(ChannelReceiver::ring(rx, self.lag));
+        receiver.backlog = backlog.into();
+        Some(receiver)
+    }
 
-        let pair = ChannelPair { op_rx, event_tx };
+    /// A new receiver of the events sent from now on that match a filter
+    ///
+    /// Returns None unless the channel was built in broadcast mode.
+    pub fn subscribe_filtered(&self, filter: EventFilter) -> Option<GoblinReceiver> {
+        Some(self.subscribe()?.filtered(filter))
+    }
 
-        (channel, pair)
+    /// Split into sender and receiver halves
+    ///
+    /// Fails, returning the channel, while clones still share its receiver.
+    pub fn split(self) -> Result<(GoblinSender, GoblinReceiver), Self> {
+        match Arc::try_unwrap(self.receiver) {
+            Ok(receiver) => Ok((self.sender, receiver.into_inner())),
+            Err(receiver) => Err(Self { receiver, ..self }),
+        }
+    }
+
+    /// A sender for this channel's operations
+    pub fn sender(&self) -> GoblinSender {
+        self.sender.clone()
     }
 
     /// Send an operation to the orchestrator
-    pub fn send(&self, op: Op) -> Result<(), ChannelError> {
-        self.op_tx.send(op).map_err(|_| ChannelError::Closed)
+    pub fn send(&self, op: impl Into<GoblinOp>) -> Result<(), ChannelError> {
+        self.sender.send(op)
+    }
+
+    /// Send an operation, waiting for room in a full bounded channel
+    pub async fn send_async(&self, op: impl Into<GoblinOp>) -> Result<(), ChannelError> {
+        self.sender.send_async(op).await
     }
 
     /// Try to receive an event (non-blocking)
-    pub fn try_recv(&self) -> Option<Event> {
-        self.event_rx.lock().try_recv().ok()
+    ///
+    /// Returns None while another task is waiting in [`recv`](Self::recv).
+    pub fn try_recv(&self) -> Option<GoblinEvent> {
+        self.receiver.try_lock().ok()?.try_recv()
     }
 
-    /// Receive an event (blocking)
-    pub async fn recv(&self) -> Option<Event> {
-        // Note: This requires careful handling since we're holding the mutex
-        // In practice, you'd want a different design for async recv
-        let mut guard = self.event_rx.lock();
-        guard.recv().await
+    /// Receive an event, waiting for one to arrive
+    pub async fn recv(&self) -> Option<GoblinEvent> {
+        self.receiver.lock().await.recv().await
     }
 
     /// Check if the channel is closed
     pub fn is_closed(&self) -> bool {
-        self.op_tx.is_closed()
+        self.sender.is_closed()
+    }
+
+    /// Health of the operation channel
+    pub fn op_stats(&self) -> ChannelStats {
+        self.sender.stats()
+    }
+
+    /// Health of the event channel as seen by this channel's receiver
+    ///
+    /// In broadcast mode the depth is not tracked; subscribers report
+    /// their own with [`GoblinReceiver::stats`].
+    pub fn event_stats(&self) -> ChannelStats {
+        self.event_gauge.stats(None)
     }
 }
 
@@ -73,29 +670,104 @@ impl Default for GoblinChannel {
 pub enum ChannelError {
     #[error("Channel is closed")]
     Closed,
+    #[error("Channel is full")]
+    Full,
 }
 
 /// Builder for creating configured channels
 pub struct ChannelBuilder {
     buffer_size: Option<usize>,
+    overflow: OverflowPolicy,
+    broadcast: Option<usize>,
+    lag: LagPolicy,
+    replay: Option<usize>,
 }
 
 impl ChannelBuilder {
     pub fn new() -> Self {
-        Self { buffer_size: None }
+        Self {
+            buffer_size: None,
+            overflow: OverflowPolicy::default(),
+            broadcast: None,
+            lag: LagPolicy::default(),
+            replay: None,
+        }
     }
 
     /// Set buffer size (bounded channel)
+    ///
+    /// With [`OverflowPolicy::DropOldest`] the size is rounded up to the
+    /// next power of two: a size of 3 keeps 4 messages.
     pub fn buffer_size(mut self, size: usize) -> Self {
         self.buffer_size = Some(size);
         self
     }
 
+    /// Set what a bounded channel does when full, in both directions
+    pub fn overflow(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow = policy;
+        self
+    }
+
+    /// Fan events out to any number of subscribers, each buffering up to
+    /// `capacity` events it has not read yet
+    ///
+    /// Replaces the overflow policy for events: a subscriber that falls
+    /// behind loses its oldest events and handles that per its
+    /// [`LagPolicy`].
+    pub fn broadcast(mut self, capacity: usize) -> Self {
+        self.broadcast = Some(capacity);
+        self
+    }
+
+    /// Set what broadcast subscribers do when they fall behind
+    pub fn lag(mut self, policy: LagPolicy) -> Self {
+        self.lag = policy;
+        self
+    }
+
+    /// Replay up to `capacity` recent critical events per session to new
+    /// broadcast subscribers
+    pub fn replay(mut self, capacity: usize) -> Self {
+        self.replay = Some(capacity);
+        self
+    }
+
     /// Build the channel pair
     pub fn build(self) -> (GoblinChannel, ChannelPair) {
-        // For now, always use unbounded
-        // Could add bounded channel support based on buffer_size
-        GoblinChannel::new()
+        let (op_tx, op_rx) = channel(self.buffer_size, self.overflow);
+        let (event_tx, event_rx, events) = match self.broadcast {
+            Some(capacity) => {
+                let (tx, rx) = broadcast::channel(capacity.max(1));
+                let template = Arc::new(rx.resubscribe());
+                let event_tx = ChannelSender {
+                    inner: SenderInner::Ring(tx),
+                    gauge: Gauge::new(false),
+                };
+                (event_tx, ChannelReceiver::ring(rx, self.lag), Some(template))
+            }
+            None => {
+                let (event_tx, event_rx) = channel(self.buffer_size, self.overflow);
+                (event_tx, event_rx, None)
+            }
+        };
+
+        let replay = match (&events, self.replay) {
+            (Some(_), Some(capacity)) => Some(Arc::new(ReplayBuffer::new(capacity))),
+            _ => None,
+        };
+
+        let channel = GoblinChannel {
+            sender: GoblinSender { op_tx },
+            event_gauge: Arc::clone(&event_rx.gauge),
+            receiver: Arc::new(tokio::sync::Mutex::new(GoblinReceiver::new(event_rx))),
+            events,
+            lag: self.lag,
+            replay: replay.clone(),
+        };
+        let pair = ChannelPair { op_rx, event_tx, replay };
+
+        (channel, pair)
     }
 }
 
@@ -108,7 +780,7 @@ impl Default for ChannelBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use warhorn::SubmissionId;
+    use warhorn::{Event, Op, SubmissionId};
 
     #[test]
     fn test_channel_creation() {
@@ -138,10 +810,168 @@ mod tests {
             message: "test".to_string(),
             details: None,
         };
-        pair.event_tx.send(event).unwrap();
+        pair.event_tx.send(event.into()).unwrap();
         
         // Receive it
         let received = channel.try_recv();
         assert!(received.is_some());
     }
+
+    #[tokio::test]
+    async fn test_split_halves_work_concurrently() {
+        let (channel, mut pair) = GoblinChannel::new();
+        let copy = channel.clone();
+        let channel = channel.split().unwrap_err();
+        drop(copy);
+        let (sender, mut receiver) = channel.split().unwrap();
+
+        let waiting = tokio::spawn(async move { receiver.recv().await });
+        tokio::task::yield_now().await;
+        sender.send(Op::interrupt()).unwrap();
+        assert!(pair.op_rx.recv().await.is_some());
+
+        pair.event_tx.send(warning("done")).unwrap();
+        assert_eq!(message(waiting.await.unwrap().unwrap()), "done");
+    }
+
+    #[tokio::test]
+    async fn test_receiver_is_a_stream() {
+        use futures::StreamExt;
+
+        let (channel, pair) = ChannelBuilder::new().buffer_size(2).overflow(OverflowPolicy::DropOldest).build();
+        let (_sender, receiver) = channel.split().unwrap();
+        for text in ["spawned", "working", "done"] {
+            pair.event_tx.send(warning(text)).unwrap();
+        }
+        drop(pair);
+
+        let messages: Vec<String> = receiver.map(message).collect().await;
+        assert_eq!(messages, vec!["working", "done"]);
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_subscribers_read_independently() {
+        assert!(GoblinChannel::new().0.subscribe().is_none());
+
+        let (channel, pair) = ChannelBuilder::new().broadcast(2).build();
+        let mut tui = channel.subscribe().unwrap();
+        pair.event_tx.send(warning("spawned")).unwrap();
+        let mut logger = channel.subscribe().unwrap();
+        pair.event_tx.send(warning("done")).unwrap();
+
+        assert_eq!(message(channel.recv().await.unwrap()), "spawned");
+        assert_eq!(message(tui.recv().await.unwrap()), "spawned");
+        assert_eq!(message(tui.recv().await.unwrap()), "done");
+        assert_eq!(message(logger.recv().await.unwrap()), "done");
+
+        // A lagging subscriber either skips ahead or is disconnected
+        let (channel, pair) = ChannelBuilder::new().broadcast(1).build();
+        pair.event_tx.send(warning("first")).unwrap();
+        pair.event_tx.send(warning("second")).unwrap();
+        assert_eq!(message(channel.try_recv().unwrap()), "second");
+
+        let (channel, pair) = ChannelBuilder::new().broadcast(1).lag(LagPolicy::Disconnect).build();
+        let mut slow = channel.subscribe().unwrap();
+        pair.event_tx.send(warning("first")).unwrap();
+        pair.event_tx.send(warning("second")).unwrap();
+        assert!(slow.recv().await.is_none());
+        assert!(slow.recv().await.is_none());
+        assert_eq!(slow.dropped(), 1);
+
+        // Subscribers see the end of the stream once the orchestrator is gone
+        let (channel, pair) = ChannelBuilder::new().broadcast(1).build();
+        let mut late = channel.subscribe().unwrap();
+        drop(pair);
+        assert!(late.recv().await.is_none());
+        assert!(channel.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_filtered_subscription_skips_other_events() {
+        let (channel, pair) = ChannelBuilder::new().broadcast(8).build();
+        let mut tree = channel.subscribe_filtered(EventFilter::status_only()).unwrap();
+        let agent_id = warhorn::AgentId::new();
+        pair.event_tx.send(warning("noise")).unwrap();
+        pair.event_tx
+            .send(GoblinEvent::AgentPaused { sub_id: SubmissionId::new(), agent_id })
+            .unwrap();
+
+        assert!(matches!(tree.recv().await, Some(GoblinEvent::AgentPaused { .. })));
+        assert!(tree.try_recv().is_none());
+        assert_eq!(message(channel.try_recv().unwrap()), "noise");
+    }
+
+    #[tokio::test]
+    async fn test_channel_stats() {
+        let (channel, mut pair) = ChannelBuilder::new().buffer_size(2).overflow(OverflowPolicy::Error).build();
+        channel.send(Op::interrupt()).unwrap();
+        channel.send(Op::interrupt()).unwrap();
+        assert!(channel.send(Op::interrupt()).is_err());
+
+        let stats = channel.op_stats();
+        assert_eq!((stats.depth, stats.received, stats.dropped), (2, 0, 1));
+        assert!(stats.oldest_pending_ms.is_some());
+        pair.op_rx.recv().await.unwrap();
+        let stats = pair.op_rx.stats();
+        assert_eq!((stats.depth, stats.received), (1, 1));
+
+        pair.event_tx.send(warning("queued")).unwrap();
+        assert_eq!(channel.event_stats().depth, 1);
+        channel.try_recv().unwrap();
+        let stats = channel.event_stats();
+        assert_eq!((stats.depth, stats.received, stats.oldest_pending_ms), (0, 1, None));
+    }
+
+    fn warning(message: &str) -> GoblinEvent {
+        Event::Warning {
+            sub_id: SubmissionId::new(),
+            message: message.to_string(),
+            details: None,
+        }
+        .into()
+    }
+
+    fn message(event: GoblinEvent) -> String {
+        match event {
+            GoblinEvent::Protocol(Event::Warning { message, .. }) => message,
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_bounded_overflow_policies() {
+        let (channel, pair) = ChannelBuilder::new().buffer_size(1).overflow(OverflowPolicy::Error).build();
+        pair.event_tx.send(warning("first")).unwrap();
+        assert!(matches!(pair.event_tx.send(warning("second")), Err(ChannelError::Full)));
+        assert_eq!(message(channel.try_recv().unwrap()), "first");
+
+        let (channel, pair) = ChannelBuilder::new().buffer_size(2).overflow(OverflowPolicy::DropOldest).build();
+        for text in ["first", "second", "third"] {
+            pair.event_tx.send(warning(text)).unwrap();
+        }
+        assert_eq!(message(channel.try_recv().unwrap()), "second");
+        assert_eq!(message(channel.try_recv().unwrap()), "third");
+        assert_eq!(channel.receiver.lock().await.dropped(), 1);
+
+        // The ring is rounded up to a power of two
+        let (channel, pair) = ChannelBuilder::new().buffer_size(3).overflow(OverflowPolicy::DropOldest).build();
+        for text in ["first", "second", "third", "fourth", "fifth"] {
+            pair.event_tx.send(warning(text)).unwrap();
+        }
+        let kept: Vec<String> = std::iter::from_fn(|| channel.try_recv()).map(message).collect();
+        assert_eq!(kept, vec!["second", "third", "fourth", "fifth"]);
+        assert_eq!(channel.receiver.lock().await.dropped(), 1);
+
+        // Blocking sends wait for the receiver to make room
+        let (channel, mut pair) = ChannelBuilder::new().buffer_size(1).build();
+        channel.send(Op::interrupt()).unwrap();
+        assert!(matches!(channel.send(Op::interrupt()), Err(ChannelError::Full)));
+        let sender = channel.clone();
+        let blocked = tokio::spawn(async move { sender.send_async(Op::interrupt()).await });
+        tokio::task::yield_now().await;
+        assert!(!blocked.is_finished());
+        assert!(pair.op_rx.recv().await.is_some());
+        blocked.await.unwrap().unwrap();
+        assert!(pair.op_rx.try_recv().is_ok());
+    }
 }
@@ -0,0 +1,273 @@
+//! Session checkpoints on disk
+//!
+//! A [`SessionCheckpoint`] captures what a long-running orchestration needs
+//! to survive a process restart: the session configuration, the hierarchy
+//! with every agent's configuration, history and task, and the plans and
+//! results so far. Checkpoints are written as versioned JSON; files written
+//! by a different version are refused rather than misread.
+//!
+//! A checkpoint can also be forked into a new session, to try a different
+//! approach from a known-good point without disturbing the original run.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentConfig, AgentId, SessionConfig, SessionId, TaskId, TokenUsage};
+
+use crate::agent::AgentHandle;
+use crate::config::SessionOptions;
+use crate::error::GoblinError;
+use crate::history::History;
+use crate::plan::TaskPlan;
+use crate::result::TaskResult;
+use crate::runtime::TaskAssignment;
+use crate::tasks::ActiveTask;
+use crate::toolscope::ToolScope;
+
+/// Version of the checkpoint format written by this crate
+pub const CHECKPOINT_VERSION: u32 = 2;
+
+/// Saved state of one agent
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentCheckpoint {
+    pub agent_id: AgentId,
+    pub parent_id: Option<AgentId>,
+    pub config: AgentConfig,
+    pub history: History,
+    pub current_task: Option<TaskId>,
+    /// Assignment the agent was working on, with its instructions
+    pub assignment: Option<TaskAssignment>,
+    pub usage: TokenUsage,
+    pub paused: bool,
+    /// Tool scope given to the agent alone, see [`crate::ToolView::own_scope`]
+    #[serde(default)]
+    pub tool_scope: Option<ToolScope>,
+}
+
+impl AgentCheckpoint {
+    /// Capture an agent's current state
+    pub fn capture(agent: &AgentHandle) -> Self {
+        let assignment = agent
+            .last_checkpoint()
+            .and_then(|c| c.assignment)
+            .filter(|a| agent.current_task() == Some(a.task_id));
+        Self {
+            agent_id: agent.id(),
+            parent_id: agent.parent_id(),
+            config: agent.config.clone(),
+            history: agent.history(),
+            current_task: agent.current_task(),
+            assignment,
+            usage: agent.usage(),
+            paused: agent.is_paused(),
+            tool_scope: agent.tool_view().own_scope().cloned(),
+        }
+    }
+}
+
+/// Saved state of a session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCheckpoint {
+    /// Format version, see [`CHECKPOINT_VERSION`]
+    pub version: u32,
+    pub saved_at: DateTime<Utc>,
+    pub session_id: SessionId,
+    pub config: SessionConfig,
+    pub options: SessionOptions,
+    /// Tasks in progress, oldest first
+    pub tasks: Vec<ActiveTask>,
+    /// Agents in hierarchy order, each after its parent
+    pub agents: Vec<AgentCheckpoint>,
+    pub plans: Vec<TaskPlan>,
+    pub results: Vec<TaskResult>,
+    /// Tokens spent by agents terminated before the checkpoint
+    pub retired_usage: TokenUsage,
+}
+
+impl SessionCheckpoint {
+    /// Write the checkpoint to a file
+    ///
+    /// The file is replaced atomically, so a crash while saving leaves the
+    /// previous checkpoint intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), GoblinError> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| GoblinError::PersistenceError(format!("Failed to encode checkpoint: {}", e)))?;
+        write_atomic(path.as_ref(), &json)
+    }
+
+    /// Read a checkpoint written by [`save`](Self::save)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GoblinError> {
+        let path = path.as_ref();
+        let json = std::fs::read(path).map_err(|e| persistence_error(path, e))?;
+        let checkpoint: Self = serde_json::from_slice(&json).map_err(|e| {
+            GoblinError::PersistenceError(format!("Invalid checkpoint {}: {}", path.display(), e))
+        })?;
+        if checkpoint.version != CHECKPOINT_VERSION {
+            return Err(GoblinError::PersistenceError(format!(
+                "Checkpoint {} has version {}, expected {}",
+                path.display(),
+                checkpoint.version,
+                CHECKPOINT_VERSION
+            )));
+        }
+        Ok(checkpoint)
+    }
+
+    /// A copy of the checkpoint for a new session
+    ///
+    /// Agents get fresh ids; parents, plan assignees and result authors
+    /// follow them. With `at_task`, that task is the only one in progress in
+    /// the fork and agents busy with anything outside its plan are left idle.
+    /// Forked agents keep their working directories, which they share with
+    /// the original agents.
+    pub fn fork(&self, at_task: Option<TaskId>) -> Result<Self, GoblinError> {
+        let mut fork = self.clone();
+        fork.session_id = SessionId::new();
+        fork.saved_at = Utc::now();
+
+        let ids: HashMap<AgentId, AgentId> = self.agents.iter().map(|a| (a.agent_id, AgentId::new())).collect();
+        let remap = |id: AgentId| ids.get(&id).copied().unwrap_or(id);
+        for agent in &mut fork.agents {
+            agent.agent_id = remap(agent.agent_id);
+            agent.parent_id = agent.parent_id.map(remap);
+        }
+        for task in fork.plans.iter_mut().flat_map(|p| p.tasks_mut()) {
+            task.assignee = task.assignee.map(remap);
+        }
+        for result in &mut fork.results {
+            result.agent_id = result.agent_id.map(remap);
+        }
+
+        if let Some(task_id) = at_task {
+            let plan = self
+                .plans
+                .iter()
+                .find(|p| p.task_id == task_id)
+                .ok_or_else(|| GoblinError::InvalidState(format!("Session {} has no task {}", self.session_id, task_id)))?;
+            let mut kept: HashSet<TaskId> = plan.tasks().iter().map(|t| t.id).collect();
+            kept.insert(task_id);
+            for agent in &mut fork.agents {
+                if agent.current_task.is_some_and(|t| !kept.contains(&t)) {
+                    agent.current_task = None;
+                    agent.assignment = None;
+                }
+            }
+            let task = self
+                .tasks
+                .iter()
+                .find(|t| t.task_id == task_id)
+                .cloned()
+                .unwrap_or_else(|| ActiveTask::new(task_id, plan.prompt.clone()));
+            fork.tasks = vec![task];
+        }
+        Ok(fork)
+    }
+}
+
+/// Write a file through a temporary sibling, so readers never see it half written
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), GoblinError> {
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, contents).map_err(|e| persistence_error(&partial, e))?;
+    std::fs::rename(&partial, path).map_err(|e| persistence_error(path, e))
+}
+
+pub(crate) fn persistence_error(path: &Path, e: std::io::Error) -> GoblinError {
+    GoblinError::PersistenceError(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::mpsc;
+    use trinkets::ToolRegistry;
+    use warhorn::{AgentRole, SubmissionId};
+
+    use crate::history::HistoryEntry;
+    use crate::model::ChatRole;
+    use crate::session::Session;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = session.spawn_agent(root, None, &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+        let task_id = TaskId::new();
+        worker.assign_task(task_id);
+        worker.checkpoint(Some(TaskAssignment {
+            task_id,
+            instructions: "Fix the parser".into(),
+            sub_id: sub_id.clone(),
+        }));
+        worker.record(HistoryEntry::message(ChatRole::User, "Fix the parser"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        session.checkpoint(&path).unwrap();
+
+        let loaded = SessionCheckpoint::load(&path).unwrap();
+        assert_eq!(loaded.session_id, session.id);
+        let ids: Vec<_> = loaded.agents.iter().map(|a| a.agent_id).collect();
+        assert_eq!(ids, vec![root.id(), worker.id()]);
+        assert_eq!(loaded.agents[1], AgentCheckpoint::capture(&worker));
+        assert_eq!(loaded.agents[1].assignment.as_ref().unwrap().instructions, "Fix the parser");
+        assert!(!dir.path().join("session.partial").exists());
+
+        let mut stale = loaded;
+        stale.version = CHECKPOINT_VERSION + 1;
+        stale.save(&path).unwrap();
+        assert!(matches!(SessionCheckpoint::load(&path), Err(GoblinError::PersistenceError(_))));
+    }
+
+    #[test]
+    fn test_fork_remaps_agents_and_drops_other_work() {
+        use crate::plan::PlannedTask;
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = session.spawn_agent(root, None, &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+        let other = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+
+        let mut plan = TaskPlan::new(TaskId::new(), "Fix the parser");
+        let mut step = PlannedTask::new("Fix the lexer", AgentRole::Worker);
+        step.assignee = Some(worker.id());
+        let step = plan.add(step);
+        worker.assign_task(step);
+        other.assign_task(TaskId::new());
+
+        let mut checkpoint = session.to_checkpoint();
+        checkpoint.plans.push(plan.clone());
+        checkpoint.results.push(TaskResult::success(step, "lexer fixed").from_agent(worker.id()));
+
+        let fork = checkpoint.fork(Some(plan.task_id)).unwrap();
+        assert_ne!(fork.session_id, checkpoint.session_id);
+        assert_eq!(fork.tasks.len(), 1);
+        assert_eq!(fork.tasks[0].task_id, plan.task_id);
+        assert_eq!(fork.tasks[0].prompt, "Fix the parser");
+        let new_worker = fork.agents[1].agent_id;
+        assert!(!checkpoint.agents.iter().any(|a| a.agent_id == new_worker));
+        assert_eq!(fork.agents[1].parent_id, Some(fork.agents[0].agent_id));
+        assert_eq!(fork.agents[1].current_task, Some(step));
+        assert_eq!(fork.agents[2].current_task, None);
+        assert_eq!(fork.plans[0].get(&step).unwrap().assignee, Some(new_worker));
+        assert_eq!(fork.results[0].agent_id, Some(new_worker));
+
+        assert!(checkpoint.fork(Some(TaskId::new())).is_err());
+    }
+}
@@ -0,0 +1,260 @@
+//! Coalescing of identical tool calls in flight
+//!
+//! Sibling agents often make the same call at the same moment, before any
+//! of them has a result to cache. With a [`CoalescePolicy`] in the session
+//! options, runtimes run tool calls through the session's [`InFlightCalls`]:
+//! a call identical to one still running, same tool, parameters and
+//! [`CallContext`], waits for that call's result instead of running again.
+//! Only the tools the policy names are coalesced, by default the read-only
+//! tools of the session's tool cache. Tools the policy names as not
+//! idempotent, and the tool cache's writing tools, are always run, since
+//! running them once is not the same as running them for every caller.
+//!
+//! If the running call fails, the calls waiting for it fail with
+//! `SharedCallFailed`; if it is cancelled, they run on their own.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::watch;
+
+use crate::error::GoblinError;
+use crate::toolcache::{key, CallContext, ToolCachePolicy};
+use crate::toolscope::pattern_matches;
+
+/// Which tool calls are coalesced
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoalescePolicy {
+    /// Tools whose calls are coalesced, by name or pattern; those the
+    /// session's tool cache caches if empty
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Tools that are always run, by name or pattern
+    #[serde(default)]
+    pub non_idempotent: Vec<String>,
+}
+
+impl CoalescePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Coalesce calls of these tools
+    pub fn with_tools<S: Into<String>>(mut self, tools: impl IntoIterator<Item = S>) -> Self {
+        self.tools.extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    /// Never coalesce calls of these tools
+    pub fn with_non_idempotent<S: Into<String>>(mut self, tools: impl IntoIterator<Item = S>) -> Self {
+        self.non_idempotent.extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    /// Policy with the read-only tools of a session's tool cache coalesced
+    /// if no tools are named, and its writing tools never coalesced
+    pub fn resolved(mut self, cache: Option<&ToolCachePolicy>) -> Self {
+        if let Some(cache) = cache {
+            if self.tools.is_empty() {
+                self.tools = cache.tools.clone();
+            }
+            self.non_idempotent.extend(cache.writes.iter().cloned());
+        }
+        self
+    }
+
+    /// Whether calls of a tool are coalesced
+    pub fn coalesces(&self, tool: &str) -> bool {
+        self.tools.iter().any(|p| pattern_matches(p, tool))
+            && !self.non_idempotent.iter().any(|p| pattern_matches(p, tool))
+    }
+}
+
+type Outcome = Option<Result<Value, String>>;
+
+/// A session's tool calls in flight, by tool, parameters and context
+pub struct InFlightCalls {
+    policy: CoalescePolicy,
+    calls: Mutex<HashMap<String, watch::Receiver<Outcome>>>,
+    coalesced: AtomicU64,
+}
+
+impl InFlightCalls {
+    pub fn new(policy: CoalescePolicy) -> Self {
+        Self {
+            policy,
+            calls: Mutex::new(HashMap::new()),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    pub fn policy(&self) -> &CoalescePolicy {
+        &self.policy
+    }
+
+    /// Run a call, or wait for the result of an identical one in flight
+    pub async fn run<F, Fut>(
+        &self,
+        tool: &str,
+        params: &Value,
+        context: &CallContext,
+        call: F,
+    ) -> Result<Value, GoblinError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Value, GoblinError>>,
+    {
+        if !self.policy.coalesces(tool) {
+            return call().await;
+        }
+
+        let key = key(tool, params, context);
+        let leader = {
+            let mut calls = self.calls.lock();
+            match calls.get(&key) {
+                Some(rx) => Err(rx.clone()),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    calls.insert(key.clone(), rx);
+                    Ok(tx)
+                }
+            }
+        };
+
+        match leader {
+            Ok(tx) => {
+                let _running = Running { calls: &self.calls, key };
+                let result = call().await;
+                let shared = match &result {
+                    Ok(value) => Ok(value.clone()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let _ = tx.send(Some(shared));
+                result
+            }
+            Err(mut rx) => {
+                self.coalesced.fetch_add(1, Ordering::Relaxed);
+                loop {
+                    let outcome = rx.borrow().clone();
+                    if let Some(outcome) = outcome {
+                        return outcome.map_err(|message| GoblinError::SharedCallFailed {
+                            tool: tool.to_string(),
+                            message,
+                        });
+                    }
+                    if rx.changed().await.is_err() {
+                        break;
+                    }
+                }
+                // The running call was cancelled before it had a result
+                call().await
+            }
+        }
+    }
+
+    /// Identical calls running now
+    pub fn running(&self) -> usize {
+        self.calls.lock().len()
+    }
+
+    /// Calls that waited for an identical call instead of running
+    pub fn coalesced(&self) -> u64 {
+        self.coalesced.load(Ordering::Relaxed)
+    }
+}
+
+impl std::fmt::Debug for InFlightCalls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("InFlightCalls")
+            .field("policy", &self.policy)
+            .field("running", &self.running())
+            .field("coalesced", &self.coalesced())
+            .finish()
+    }
+}
+
+/// Removes a call from those in flight when it ends or is cancelled
+struct Running<'a> {
+    calls: &'a Mutex<HashMap<String, watch::Receiver<Outcome>>>,
+    key: String,
+}
+
+impl Drop for Running<'_> {
+    fn drop(&mut self) {
+        self.calls.lock().remove(&self.key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::AtomicUsize;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_identical_calls_run_once() {
+        let policy = CoalescePolicy::new().with_tools(["grep", "shell_*"]).with_non_idempotent(["shell_*"]);
+        let calls = InFlightCalls::new(policy);
+        let ctx = CallContext::default();
+        let runs = AtomicUsize::new(0);
+        let grep = || async {
+            runs.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok::<_, GoblinError>(json!(["src/lib.rs:3"]))
+        };
+        let params = json!({"pattern": "todo"});
+
+        let (a, b) = tokio::join!(calls.run("grep", &params, &ctx, grep), calls.run("grep", &params, &ctx, grep));
+        assert_eq!(a.unwrap(), b.unwrap());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!((calls.coalesced(), calls.running()), (1, 0));
+
+        let shell = || async {
+            runs.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok::<_, GoblinError>(Value::Null)
+        };
+        let command = json!({"command": "make"});
+        let _ = tokio::join!(
+            calls.run("shell_exec", &command, &ctx, shell),
+            calls.run("shell_exec", &command, &ctx, shell)
+        );
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+
+        // The same call in another directory runs on its own
+        let elsewhere = CallContext { cwd: "/work/fork".into(), ..CallContext::default() };
+        let _ = tokio::join!(calls.run("grep", &params, &ctx, grep), calls.run("grep", &params, &elsewhere, grep));
+        assert_eq!(runs.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_calls_share_the_failure() {
+        let calls = InFlightCalls::new(CoalescePolicy::new().with_tools(["read_file"]));
+        let ctx = CallContext::default();
+        let failing = || async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Err::<Value, _>(GoblinError::TaskError("disk full".into()))
+        };
+        let params = json!({"path": "a.rs"});
+        let (first, second) =
+            tokio::join!(calls.run("read_file", &params, &ctx, failing), calls.run("read_file", &params, &ctx, failing));
+        assert_eq!(first.unwrap_err().code(), "task_error");
+        assert_eq!(second.unwrap_err().code(), "shared_call_failed");
+    }
+
+    #[test]
+    fn test_default_policy_coalesces_the_cached_tools() {
+        let cache = ToolCachePolicy::new(Duration::from_secs(60))
+            .with_tools(["read_*", "grep"])
+            .with_writes(["write_*"]);
+        let policy = CoalescePolicy::default().resolved(Some(&cache));
+        assert!(policy.coalesces("read_file") && policy.coalesces("grep"));
+        assert!(!policy.coalesces("write_file") && !policy.coalesces("shell_exec"));
+        assert!(!CoalescePolicy::default().resolved(None).coalesces("grep"));
+    }
+}
@@ -0,0 +1,196 @@
+//! Automatic compaction of agent histories
+//!
+//! With a [`CompactionPolicy`] in the session options, an agent whose
+//! history approaches its model's context window has the older entries
+//! summarized and replaced by the summary before its next model turn. The
+//! most recent entries are kept verbatim. Summaries come from a
+//! [`Summarizer`], by default the agent's own model.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use warhorn::SubmissionId;
+
+use crate::agent::AgentHandle;
+use crate::error::GoblinError;
+use crate::history::HistoryEntry;
+use crate::model::{ChatMessage, ChatRole};
+use crate::protocol::GoblinEvent;
+
+/// When and how histories are compacted
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CompactionPolicy {
+    /// Fraction of the context window at which to compact
+    pub threshold: f64,
+    /// Most recent entries kept verbatim
+    pub keep_recent: usize,
+    /// Context window assumed when the model provider does not report one
+    pub context_window: u64,
+}
+
+impl Default for CompactionPolicy {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            keep_recent: 6,
+            context_window: 128_000,
+        }
+    }
+}
+
+impl CompactionPolicy {
+    /// Validate the policy
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if !(self.threshold > 0.0 && self.threshold <= 1.0) {
+            return Err(GoblinError::ConfigError("compaction threshold must be in (0, 1]".into()));
+        }
+        if self.context_window == 0 {
+            return Err(GoblinError::ConfigError("compaction context_window must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
+
+/// What a compaction replaced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Compaction {
+    /// History entries replaced by the summary
+    pub entries: usize,
+    pub tokens_before: u64,
+    pub tokens_after: u64,
+    pub summary: String,
+}
+
+/// Summarizes history entries on behalf of an agent
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    /// Summarize the given entries of the agent's history
+    async fn summarize(&self, agent: &AgentHandle, entries: &[HistoryEntry]) -> Result<String, GoblinError>;
+}
+
+/// Summarizes with the agent's own model
+#[derive(Debug, Default)]
+pub struct ModelSummarizer;
+
+#[async_trait]
+impl Summarizer for ModelSummarizer {
+    async fn summarize(&self, agent: &AgentHandle, entries: &[HistoryEntry]) -> Result<String, GoblinError> {
+        let mut messages = vec![ChatMessage::system(
+            "Summarize the conversation below for your own later reference. \
+             Keep every finding, decision and open question; drop pleasantries and repetition.",
+        )];
+        messages.extend(entries.iter().map(HistoryEntry::to_message));
+        let response = agent.complete(messages, &|_| {}).await?;
+        Ok(response.content)
+    }
+}
+
+/// Compacts an agent's history according to a policy
+#[derive(Clone)]
+pub struct Compactor {
+    pub policy: CompactionPolicy,
+    pub summarizer: Arc<dyn Summarizer>,
+}
+
+impl std::fmt::Debug for Compactor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Compactor").field("policy", &self.policy).finish()
+    }
+}
+
+impl Compactor {
+    /// Compact the agent's history if it is close to the context window
+    pub async fn compact_if_needed(
+        &self,
+        agent: &AgentHandle,
+        sub_id: &SubmissionId,
+    ) -> Result<Option<Compaction>, GoblinError> {
+        let window = agent.context_window().unwrap_or(self.policy.context_window);
+        let limit = (window as f64 * self.policy.threshold) as u64;
+        let history = agent.history();
+        if history.tokens() < limit || history.len() <= self.policy.keep_recent {
+            return Ok(None);
+        }
+
+        let count = history.len() - self.policy.keep_recent;
+        let summary = self.summarizer.summarize(agent, &history.entries()[..count]).await?;
+        let entry = HistoryEntry::message(
+            ChatRole::System,
+            format!("Summary of the earlier conversation:\n{}", summary),
+        );
+        let tokens_after = agent.replace_history_prefix(count, entry);
+
+        let compaction = Compaction {
+            entries: count,
+            tokens_before: history.tokens(),
+            tokens_after,
+            summary,
+        };
+        info!(
+            agent_id = %agent.id(),
+            entries = count,
+            tokens_before = compaction.tokens_before,
+            tokens_after,
+            "Compacted agent history"
+        );
+        agent.emit(GoblinEvent::HistoryCompacted {
+            sub_id: sub_id.clone(),
+            agent_id: agent.id(),
+            compaction: compaction.clone(),
+        });
+        Ok(Some(compaction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use trinkets::ToolRegistry;
+    use warhorn::AgentConfig;
+
+    use crate::agent::Agent;
+
+    struct Gist;
+
+    #[async_trait]
+    impl Summarizer for Gist {
+        async fn summarize(&self, _agent: &AgentHandle, entries: &[HistoryEntry]) -> Result<String, GoblinError> {
+            Ok(format!("{} entries", entries.len()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compacts_older_entries() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let agent = AgentHandle::new(Agent::new(AgentConfig::default(), None, Arc::new(ToolRegistry::new()), tx));
+        let compactor = Compactor {
+            policy: CompactionPolicy {
+                threshold: 0.5,
+                keep_recent: 2,
+                context_window: 40,
+            },
+            summarizer: Arc::new(Gist),
+        };
+        let sub_id = SubmissionId::new();
+
+        agent.record(HistoryEntry::message(ChatRole::User, "a".repeat(40)));
+        assert_eq!(compactor.compact_if_needed(&agent, &sub_id).await.unwrap(), None);
+
+        for turn in ["b", "c", "d"] {
+            agent.record(HistoryEntry::message(ChatRole::Assistant, turn.repeat(40)));
+        }
+        let compaction = compactor.compact_if_needed(&agent, &sub_id).await.unwrap().unwrap();
+        assert_eq!(compaction.entries, 2);
+        assert_eq!(compaction.summary, "2 entries");
+        assert!(compaction.tokens_after < compaction.tokens_before);
+
+        let history = agent.history();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.tokens(), compaction.tokens_after);
+        assert!(matches!(&history.entries()[0], HistoryEntry::Message { role: ChatRole::System, .. }));
+        assert!(matches!(rx.try_recv(), Ok(GoblinEvent::HistoryCompacted { .. })));
+    }
+}
@@ -0,0 +1,577 @@
+//! Cabal-specific session options
+//!
+//! `warhorn::SessionConfig` carries the protocol-level session settings.
+//! Orchestration behavior that only cabal understands is configured here and
+//! applied to every session the orchestrator creates.
+//!
+//! Parts of the `SessionConfig` of a running session, and its approval
+//! policy, can be changed with a [`SessionConfigPatch`].
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use warhorn::SessionConfig;
+
+use crate::approval::ApprovalPolicy;
+use crate::budget::TokenBudgets;
+use crate::coalesce::CoalescePolicy;
+use crate::compaction::CompactionPolicy;
+use crate::deadline::DeadlineAction;
+use crate::breaker::BreakerPolicy;
+use crate::delegation::DelegationPolicy;
+use crate::error::GoblinError;
+use crate::failure::FailureReports;
+use crate::heartbeat::HeartbeatPolicy;
+use crate::stall::StallPolicy;
+use crate::orphan::OrphanPolicy;
+use crate::estimate::EstimatePolicy;
+use crate::mcp::McpServer;
+use crate::merger::MergerKind;
+use crate::planner::PlannerKind;
+use crate::propagation::FailurePolicies;
+use crate::pricing::PricingTable;
+use crate::ratelimit::RateLimit;
+use crate::retry::RetryPolicy;
+use crate::review::ReviewPolicy;
+use crate::sandbox::SandboxProfiles;
+use crate::selftest::SelfTestConfig;
+use crate::supervise::SupervisionPolicies;
+use crate::toolcache::ToolCachePolicy;
+use crate::toollimit::ToolLimit;
+use crate::toolscope::ToolScope;
+use crate::workspace::ScratchConfig;
+
+/// Orchestration options applied to a session
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionOptions {
+    /// Provision a scratch directory for every spawned agent
+    pub scratch: Option<ScratchConfig>,
+    /// Planning strategy used when no planner is injected into the orchestrator
+    pub planner: PlannerKind,
+    /// Strategy for combining child results when no merger is injected
+    pub merger: MergerKind,
+    /// Review subtask results before merging them (needs an injected reviewer)
+    pub review: Option<ReviewPolicy>,
+    /// Summarize older history entries as agents approach their context window
+    pub compaction: Option<CompactionPolicy>,
+    /// Maximum number of subtasks waiting for a worker (unbounded if None)
+    pub max_queued_tasks: Option<usize>,
+    /// Concurrent model calls shared fairly between tasks (unlimited if None)
+    pub model_slots: Option<usize>,
+    /// Concurrent calls of particular models, within `model_slots`
+    #[serde(default)]
+    pub model_slots_per_model: HashMap<String, usize>,
+    /// Concurrent tool executions shared fairly between tasks (unlimited if None)
+    pub tool_slots: Option<usize>,
+    /// Sessions allowed to delegate subtasks into this session
+    pub delegation: DelegationPolicy,
+    /// Action applied when a task misses its deadline, unless overridden per task
+    pub deadline_action: DeadlineAction,
+    /// Verification pass run while the session is configured
+    pub self_test: Option<SelfTestConfig>,
+    /// Rules approving or denying agents' commands without asking the client
+    #[serde(default)]
+    pub approval: ApprovalPolicy,
+    /// Tools allowed or denied to agents by role and depth
+    #[serde(default)]
+    pub tool_scopes: Vec<ToolScope>,
+    /// How agents' tool executions are confined, by role
+    #[serde(default)]
+    pub sandbox: SandboxProfiles,
+    /// Tool parameter keys redacted in the audit log, besides the defaults
+    #[serde(default)]
+    pub audit_redacted_keys: Vec<String>,
+    /// Directory `ExportAudit` writes into; exports are refused if None
+    #[serde(default)]
+    pub audit_export_dir: Option<PathBuf>,
+    /// Limits on how many agents a session spawns, and how deep
+    #[serde(default)]
+    pub spawn: SpawnLimits,
+    /// Tokens agents may spend, by role
+    #[serde(default)]
+    pub token_budgets: TokenBudgets,
+    /// Prices of models, for the running cost of agents, tasks and sessions
+    #[serde(default)]
+    pub pricing: PricingTable,
+    /// Estimate the cost of planned tasks before they start
+    #[serde(default)]
+    pub estimate: Option<EstimatePolicy>,
+    /// How often agents report their token usage
+    #[serde(default)]
+    pub usage_updates: UsageUpdates,
+    /// What is done when agents' run loops fail, by role
+    #[serde(default)]
+    pub supervision: SupervisionPolicies,
+    /// Assign failed tasks again, with backoff
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// When agents that keep failing are reported to their lead
+    #[serde(default)]
+    pub failure_reports: FailureReports,
+    /// Fail model calls fast after a model keeps failing
+    #[serde(default)]
+    pub circuit_breaker: Option<BreakerPolicy>,
+    /// What a failed subtask does to its siblings, by the role of their lead
+    #[serde(default)]
+    pub failure_propagation: FailurePolicies,
+    /// Have agents beat and mark those that miss beats unresponsive
+    #[serde(default)]
+    pub heartbeat: Option<HeartbeatPolicy>,
+    /// Report agents that make no progress on their task, and nudge or interrupt them
+    #[serde(default)]
+    pub stall: Option<StallPolicy>,
+    /// Adopt or terminate the children of agents that died, instead of
+    /// leaving them running
+    #[serde(default)]
+    pub orphans: Option<OrphanPolicy>,
+    /// Share the results of read-only tool calls between agents
+    #[serde(default)]
+    pub tool_cache: Option<ToolCachePolicy>,
+    /// Run identical tool calls in flight once, sharing the result
+    #[serde(default)]
+    pub coalesce: Option<CoalescePolicy>,
+    /// Rate and concurrency limits of tools across all agents, by tool name or pattern
+    #[serde(default)]
+    pub tool_limits: HashMap<String, ToolLimit>,
+    /// Tool calls each agent may run at once, queueing the rest (unlimited if None)
+    #[serde(default)]
+    pub max_tool_calls_per_agent: Option<usize>,
+    /// MCP servers started for the session, whose tools are offered to agents
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServer>,
+}
+
+/// Share of `max_agents` at which a session warns that it is near the limit
+pub const AGENT_LIMIT_WARN_AT: f64 = 0.8;
+
+/// Limits on a session's agent tree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpawnLimits {
+    /// Cap on the root agent's children, lowering the session's own setting
+    #[serde(default)]
+    pub max_parallel_agents: Option<usize>,
+    /// Deepest an agent may be spawned or moved, the root being 0
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Most agents alive in the session at once
+    #[serde(default)]
+    pub max_agents: Option<usize>,
+    /// How often any one agent may spawn children
+    #[serde(default)]
+    pub rate_per_parent: Option<RateLimit>,
+    /// How often agents may be spawned in the session
+    #[serde(default)]
+    pub rate_per_session: Option<RateLimit>,
+}
+
+impl SpawnLimits {
+    /// Apply the limits to a session's configuration
+    pub fn apply(&self, config: &mut SessionConfig) {
+        if let Some(max) = self.max_parallel_agents {
+            config.max_parallel_agents = config.max_parallel_agents.min(max);
+        }
+    }
+
+    /// Live agents at which the session warns it is near `max_agents`
+    pub fn warn_at(&self) -> Option<usize> {
+        self.max_agents.map(|max| (max as f64 * AGENT_LIMIT_WARN_AT).ceil() as usize)
+    }
+
+    /// Check that the limits leave room for at least one agent
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.max_parallel_agents == Some(0) || self.max_agents == Some(0) {
+            return Err(GoblinError::ConfigError(
+                "spawn max_parallel_agents and max_agents must be at least 1".into(),
+            ));
+        }
+        self.rate_per_parent.iter().chain(&self.rate_per_session).try_for_each(RateLimit::validate)
+    }
+}
+
+/// How often agents report their token usage
+///
+/// With neither setting, every change is reported. Usage left unreported
+/// when an agent's task ends is reported then.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsageUpdates {
+    /// Report once this many milliseconds passed since the last report
+    #[serde(default)]
+    pub interval_ms: Option<u64>,
+    /// Report once this many tokens were spent since the last report
+    #[serde(default)]
+    pub every_tokens: Option<u64>,
+}
+
+impl UsageUpdates {
+    /// Whether a report is due `elapsed` and `tokens` after the last one
+    pub fn due(&self, elapsed: Duration, tokens: u64) -> bool {
+        match (self.interval_ms, self.every_tokens) {
+            (None, None) => true,
+            (interval, every) => {
+                interval.is_some_and(|ms| elapsed >= Duration::from_millis(ms)) || every.is_some_and(|n| tokens >= n)
+            }
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.interval_ms == Some(0) || self.every_tokens == Some(0) {
+            return Err(GoblinError::ConfigError(
+                "usage update interval_ms and every_tokens must be at least 1".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl SessionOptions {
+    /// Create default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enable per-agent scratch directories
+    pub fn with_scratch(mut self, scratch: ScratchConfig) -> Self {
+        self.scratch = Some(scratch);
+        self
+    }
+
+    /// Select the built-in planning strategy
+    pub fn with_planner(mut self, planner: PlannerKind) -> Self {
+        self.planner = planner;
+        self
+    }
+
+    /// Select the built-in result merging strategy
+    pub fn with_merger(mut self, merger: MergerKind) -> Self {
+        self.merger = merger;
+        self
+    }
+
+    /// Have subtask results reviewed, bouncing them back at most `max_bounces` times
+    pub fn with_review(mut self, max_bounces: u32) -> Self {
+        self.review = Some(ReviewPolicy { max_bounces });
+        self
+    }
+
+    /// Compact agent histories automatically
+    pub fn with_compaction(mut self, policy: CompactionPolicy) -> Self {
+        self.compaction = Some(policy);
+        self
+    }
+
+    /// Bound the queue of subtasks waiting for a worker
+    pub fn with_max_queued_tasks(mut self, max: usize) -> Self {
+        self.max_queued_tasks = Some(max);
+        self
+    }
+
+    /// Limit concurrent model calls and tool executions across tasks
+    pub fn with_slots(mut self, model_slots: usize, tool_slots: usize) -> Self {
+        self.model_slots = Some(model_slots);
+        self.tool_slots = Some(tool_slots);
+        self
+    }
+
+    /// Limit concurrent calls of one model
+    pub fn with_model_slots_for(mut self, model: impl Into<String>, slots: usize) -> Self {
+        self.model_slots_per_model.insert(model.into(), slots);
+        self
+    }
+
+    /// Set which sessions may delegate work into this session
+    pub fn with_delegation(mut self, policy: DelegationPolicy) -> Self {
+        self.delegation = policy;
+        self
+    }
+
+    /// Set the default action for missed deadlines
+    pub fn with_deadline_action(mut self, action: DeadlineAction) -> Self {
+        self.deadline_action = action;
+        self
+    }
+
+    /// Run a startup self-test when sessions are configured
+    pub fn with_self_test(mut self, self_test: SelfTestConfig) -> Self {
+        self.self_test = Some(self_test);
+        self
+    }
+
+    /// Decide agents' commands by policy, escalating only what it leaves open
+    pub fn with_approval_policy(mut self, policy: ApprovalPolicy) -> Self {
+        self.approval = policy;
+        self
+    }
+
+    /// Narrow the tools of the agents a scope matches
+    pub fn with_tool_scope(mut self, scope: ToolScope) -> Self {
+        self.tool_scopes.push(scope);
+        self
+    }
+
+    /// Confine agents' tool executions with profiles picked by role
+    pub fn with_sandbox_profiles(mut self, profiles: SandboxProfiles) -> Self {
+        self.sandbox = profiles;
+        self
+    }
+
+    /// Redact tool parameters with these keys from the audit log too
+    pub fn with_audit_redacted_keys<S: Into<String>>(mut self, keys: impl IntoIterator<Item = S>) -> Self {
+        self.audit_redacted_keys.extend(keys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Let clients export audit logs into a directory
+    pub fn with_audit_export_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.audit_export_dir = Some(dir.into());
+        self
+    }
+
+    /// Limit how many agents sessions spawn, and how deep
+    pub fn with_spawn_limits(mut self, limits: SpawnLimits) -> Self {
+        self.spawn = limits;
+        self
+    }
+
+    /// Limit the tokens agents spend, by role
+    pub fn with_token_budgets(mut self, budgets: TokenBudgets) -> Self {
+        self.token_budgets = budgets;
+        self
+    }
+
+    /// Price agents' tokens by their models
+    pub fn with_pricing(mut self, pricing: PricingTable) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Estimate planned tasks, holding costly ones for confirmation
+    pub fn with_estimate(mut self, policy: EstimatePolicy) -> Self {
+        self.estimate = Some(policy);
+        self
+    }
+
+    /// Throttle agents' usage reports
+    pub fn with_usage_updates(mut self, updates: UsageUpdates) -> Self {
+        self.usage_updates = updates;
+        self
+    }
+
+    /// Restart, escalate or give up on failed agents by role
+    pub fn with_supervision(mut self, policies: SupervisionPolicies) -> Self {
+        self.supervision = policies;
+        self
+    }
+
+    /// Retry failed tasks
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Report agents to their lead after this many failures in a row
+    pub fn with_failure_reports(mut self, after_failures: u32) -> Self {
+        self.failure_reports = FailureReports { after_failures };
+        self
+    }
+
+    /// Put a circuit breaker around every model agents call
+    pub fn with_circuit_breaker(mut self, policy: BreakerPolicy) -> Self {
+        self.circuit_breaker = Some(policy);
+        self
+    }
+
+    /// Fail fast, continue or quarantine on child failures by role
+    pub fn with_failure_propagation(mut self, policies: FailurePolicies) -> Self {
+        self.failure_propagation = policies;
+        self
+    }
+
+    /// Watch agents' heartbeats
+    pub fn with_heartbeat(mut self, policy: HeartbeatPolicy) -> Self {
+        self.heartbeat = Some(policy);
+        self
+    }
+
+    /// Watch agents' progress
+    pub fn with_stall_detection(mut self, policy: StallPolicy) -> Self {
+        self.stall = Some(policy);
+        self
+    }
+
+    /// Adopt or terminate the children of agents that died
+    pub fn with_orphans(mut self, policy: OrphanPolicy) -> Self {
+        self.orphans = Some(policy);
+        self
+    }
+
+    /// Cache the results of read-only tool calls for the whole session
+    pub fn with_tool_cache(mut self, policy: ToolCachePolicy) -> Self {
+        self.tool_cache = Some(policy);
+        self
+    }
+
+    /// Coalesce identical tool calls of the session's agents in flight, of
+    /// the tool cache's read-only tools unless the policy names others
+    pub fn with_tool_coalescing(mut self, policy: CoalescePolicy) -> Self {
+        self.coalesce = Some(policy);
+        self
+    }
+
+    /// Limit the calls of a tool, or of the tools a pattern names, across all agents
+    pub fn with_tool_limit(mut self, tool: impl Into<String>, limit: ToolLimit) -> Self {
+        self.tool_limits.insert(tool.into(), limit);
+        self
+    }
+
+    /// Let each agent run at most this many tool calls at once
+    pub fn with_max_tool_calls_per_agent(mut self, calls: usize) -> Self {
+        self.max_tool_calls_per_agent = Some(calls);
+        self
+    }
+
+    /// Start an MCP server for the session and offer its tools to agents
+    pub fn with_mcp_server(mut self, server: McpServer) -> Self {
+        self.mcp_servers.push(server);
+        self
+    }
+
+    /// Validate the options
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if let Some(scratch) = &self.scratch {
+            scratch.validate()?;
+        }
+        if let Some(compaction) = &self.compaction {
+            compaction.validate()?;
+        }
+        self.sandbox.validate()?;
+        if let Some(dir) = self.audit_export_dir.as_ref().filter(|dir| !dir.is_absolute()) {
+            return Err(GoblinError::ConfigError(format!(
+                "Audit export directory must be absolute: {}",
+                dir.display()
+            )));
+        }
+        self.spawn.validate()?;
+        self.token_budgets.validate()?;
+        self.pricing.validate()?;
+        self.usage_updates.validate()?;
+        self.supervision.validate()?;
+        self.failure_reports.validate()?;
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.validate()?;
+        }
+        if let Some(heartbeat) = &self.heartbeat {
+            heartbeat.validate()?;
+        }
+        if let Some(stall) = &self.stall {
+            stall.validate()?;
+        }
+        if let Some(cache) = &self.tool_cache {
+            cache.validate()?;
+        }
+        self.tool_limits.values().try_for_each(ToolLimit::validate)?;
+        if self.max_tool_calls_per_agent == Some(0) {
+            return Err(GoblinError::ConfigError("max_tool_calls_per_agent must be at least 1".into()));
+        }
+        for (i, server) in self.mcp_servers.iter().enumerate() {
+            server.validate()?;
+            if self.mcp_servers[..i].iter().any(|s| s.name == server.name) {
+                return Err(GoblinError::ConfigError(format!("MCP server {} is configured twice", server.name)));
+            }
+        }
+        if let Some(estimate) = &self.estimate {
+            estimate.validate()?;
+        }
+        if self.max_queued_tasks == Some(0) {
+            return Err(GoblinError::ConfigError("max_queued_tasks must be at least 1".into()));
+        }
+        if self.model_slots == Some(0) || self.tool_slots == Some(0) {
+            return Err(GoblinError::ConfigError("model_slots and tool_slots must be at least 1".into()));
+        }
+        if self.model_slots_per_model.values().any(|slots| *slots == 0) {
+            return Err(GoblinError::ConfigError("model_slots_per_model entries must be at least 1".into()));
+        }
+        if self
+            .tool_scopes
+            .iter()
+            .any(|s| matches!((s.min_depth, s.max_depth), (Some(min), Some(max)) if min > max))
+        {
+            return Err(GoblinError::ConfigError("tool scope min_depth exceeds its max_depth".into()));
+        }
+        if self.tool_scopes.iter().flat_map(|s| s.overrides.values()).any(|o| o.timeout_ms == Some(0)) {
+            return Err(GoblinError::ConfigError("tool override timeout_ms must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Changes to the configuration of a running session
+///
+/// Only settings that can change safely while agents are working are
+/// covered; unset fields are left as they are.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionConfigPatch {
+    /// Model for agents spawned from now on
+    pub model: Option<String>,
+    /// Limit on the root agent's children
+    pub max_parallel_agents: Option<usize>,
+    /// Rules for agents' commands requested from now on
+    #[serde(default)]
+    pub approval: Option<ApprovalPolicy>,
+}
+
+impl SessionConfigPatch {
+    /// Use a different model for new agents
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Change the limit on the root agent's children
+    pub fn with_max_parallel_agents(mut self, max: usize) -> Self {
+        self.max_parallel_agents = Some(max);
+        self
+    }
+
+    /// Replace the rules approving or denying agents' commands
+    pub fn with_approval_policy(mut self, policy: ApprovalPolicy) -> Self {
+        self.approval = Some(policy);
+        self
+    }
+
+    /// Validate the patch on its own, before checking it against a session
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.model.as_deref().is_some_and(|m| m.trim().is_empty()) {
+            return Err(GoblinError::ConfigError("model must not be empty".into()));
+        }
+        if self.max_parallel_agents == Some(0) {
+            return Err(GoblinError::ConfigError("max_parallel_agents must be at least 1".into()));
+        }
+        let mut rules = self.approval.iter().flat_map(|a| &a.rules);
+        if let Some(rule) = rules.find(|r| r.name.trim().is_empty()) {
+            return Err(GoblinError::ConfigError(format!(
+                "Approval rule for {:?} needs a name",
+                rule.commands
+            )));
+        }
+        Ok(())
+    }
+
+    /// Apply the patch to a configuration
+    ///
+    /// The approval policy is not part of the configuration; the session
+    /// applies it to its approvals.
+    pub fn apply(&self, config: &mut SessionConfig) {
+        if let Some(model) = &self.model {
+            config.model = model.clone();
+        }
+        if let Some(max) = self.max_parallel_agents {
+            config.max_parallel_agents = max;
+        }
+    }
+}
+
@@ -0,0 +1,514 @@
+//! Long-running daemon serving an orchestrator over a unix socket
+//!
+//! Clients connect to the socket and speak newline-delimited JSON. The
+//! first line is a [`ClientMessage::Hello`]; the daemon answers with a
+//! [`ServerMessage::Welcome`] carrying the client's identity. After that
+//! every line is an op, and the daemon streams back the events answering
+//! the client's own submissions plus any unsolicited events (scheduled
+//! tasks, rules), which go to everyone.
+//!
+//! A client that reconnects with its previous `client_id` gets the events
+//! that arrived while it was away, up to the backlog limit. Its ops stop
+//! being routed to it when it disconnects, so their later events reach
+//! every client, the disconnected one through its backlog.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+use trinkets::ToolRegistry;
+use uuid::Uuid;
+use warhorn::SubmissionId;
+
+use crate::channel::ChannelPair;
+use crate::error::GoblinError;
+use crate::orchestrator::Orchestrator;
+use crate::protocol::{GoblinEvent, GoblinOp};
+
+/// Events kept per disconnected client by default
+pub const DEFAULT_BACKLOG: usize = 1024;
+
+/// Identity of a daemon client, stable across reconnects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ClientId(Uuid);
+
+impl ClientId {
+    /// Create a new random client ID
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for ClientId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// A line sent by a client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// Open the conversation, resuming an earlier identity if given
+    Hello { client_id: Option<ClientId> },
+    /// Submit an operation
+    Op(GoblinOp),
+}
+
+/// A line sent by the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// The client's identity
+    Welcome {
+        client_id: ClientId,
+        /// Whether an earlier identity was resumed
+        resumed: bool,
+    },
+    /// An orchestrator event
+    Event(GoblinEvent),
+    /// A line could not be handled
+    Error { message: String },
+}
+
+#[derive(Debug, Default)]
+struct Client {
+    /// Writer of the live connection, if connected
+    tx: Option<mpsc::UnboundedSender<ServerMessage>>,
+    /// Events that arrived while disconnected
+    backlog: VecDeque<GoblinEvent>,
+}
+
+#[derive(Debug, Default)]
+struct Clients {
+    clients: HashMap<ClientId, Client>,
+    /// Which client submitted which op
+    routes: HashMap<SubmissionId, ClientId>,
+}
+
+impl Clients {
+    fn deliver(&mut self, client_id: ClientId, event: GoblinEvent, backlog_limit: usize) {
+        let Some(client) = self.clients.get_mut(&client_id) else {
+            return;
+        };
+        if let Some(tx) = &client.tx {
+            if tx.send(ServerMessage::Event(event.clone())).is_ok() {
+                return;
+            }
+            client.tx = None;
+        }
+        if client.backlog.len() >= backlog_limit {
+            client.backlog.pop_front();
+        }
+        client.backlog.push_back(event);
+    }
+
+    fn route(&mut self, event: GoblinEvent, backlog_limit: usize) {
+        let owner = event.sub_id().and_then(|sub_id| self.routes.get(&sub_id).copied());
+        match owner {
+            Some(client_id) => self.deliver(client_id, event, backlog_limit),
+            None => {
+                let ids: Vec<ClientId> = self.clients.keys().copied().collect();
+                for client_id in ids {
+                    self.deliver(client_id, event.clone(), backlog_limit);
+                }
+            }
+        }
+    }
+}
+
+/// Daemon runner binding a unix socket
+pub struct Daemon {
+    path: PathBuf,
+    backlog: usize,
+}
+
+impl Daemon {
+    /// Create a daemon serving on the socket at `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            backlog: DEFAULT_BACKLOG,
+        }
+    }
+
+    /// Set how many events are kept for each disconnected client
+    pub fn with_backlog(mut self, backlog: usize) -> Self {
+        self.backlog = backlog;
+        self
+    }
+
+    /// Socket path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run an orchestrator behind the socket until it stops
+    ///
+    /// `setup` receives the orchestrator wired to the socket's clients, to
+    /// add options, rules or a planner before the daemon starts listening.
+    pub async fn run(
+        self,
+        tools: ToolRegistry,
+        setup: impl FnOnce(Orchestrator) -> Orchestrator,
+    ) -> Result<(), GoblinError> {
+        let (op_tx, op_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let pair = ChannelPair {
+            op_rx: op_rx.into(),
+            event_tx: event_tx.into(),
+            replay: None,
+        };
+        let orchestrator = setup(Orchestrator::new(tools, pair));
+
+        tokio::select! {
+            result = orchestrator.run() => result,
+            result = self.serve(op_tx, event_rx) => result,
+        }
+    }
+
+    /// Serve clients, forwarding their ops to `op_tx` and routing events from `event_rx`
+    pub async fn serve(
+        self,
+        op_tx: mpsc::UnboundedSender<GoblinOp>,
+        mut event_rx: mpsc::UnboundedReceiver<GoblinEvent>,
+    ) -> Result<(), GoblinError> {
+        remove_stale_socket(&self.path)?;
+        let listener = UnixListener::bind(&self.path).map_err(|e| {
+            GoblinError::TransportError(format!("{}: {}", self.path.display(), e))
+        })?;
+        info!(path = %self.path.display(), "Daemon listening");
+
+        let clients = Arc::new(Mutex::new(Clients::default()));
+        let backlog = self.backlog;
+
+        let router = {
+            let clients = Arc::clone(&clients);
+            tokio::spawn(async move {
+                while let Some(event) = event_rx.recv().await {
+                    clients.lock().route(event, backlog);
+                }
+            })
+        };
+
+        let result = loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => break Err(GoblinError::TransportError(e.to_string())),
+            };
+            if op_tx.is_closed() {
+                break Ok(());
+            }
+            let clients = Arc::clone(&clients);
+            let op_tx = op_tx.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, clients, op_tx).await {
+                    debug!(error = %e, "Daemon connection closed with error");
+                }
+            });
+        };
+
+        router.abort();
+        let _ = std::fs::remove_file(&self.path);
+        result
+    }
+}
+
+/// Remove a socket left behind by a previous run, which would make bind fail
+///
+/// Refuses to remove anything but a socket no daemon is listening on.
+fn remove_stale_socket(path: &Path) -> Result<(), GoblinError> {
+    use std::os::unix::fs::FileTypeExt;
+
+    let failed = |e: std::io::Error| GoblinError::TransportError(format!("{}: {}", path.display(), e));
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(failed(e)),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(GoblinError::TransportError(format!("{} exists and is not a socket", path.display())));
+    }
+    if std::os::unix::net::UnixStream::connect(path).is_ok() {
+        return Err(GoblinError::TransportError(format!(
+            "{} is in use by another daemon",
+            path.display()
+        )));
+    }
+    std::fs::remove_file(path).map_err(failed)
+}
+
+async fn handle_connection(
+    stream: UnixStream,
+    clients: Arc<Mutex<Clients>>,
+    op_tx: mpsc::UnboundedSender<GoblinOp>,
+) -> Result<(), GoblinError> {
+    let io = |e: std::io::Error| GoblinError::TransportError(e.to_string());
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<ServerMessage>();
+    let writer_task = tokio::spawn(async move {
+        while let Some(message) = rx.recv().await {
+            let Ok(mut line) = serde_json::to_string(&message) else { continue };
+            line.push('\n');
+            if writer.write_all(line.as_bytes()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    // Handshake
+    let hello = lines.next_line().await.map_err(io)?;
+    let requested = match hello.as_deref().map(serde_json::from_str::<ClientMessage>) {
+        Some(Ok(ClientMessage::Hello { client_id })) => client_id,
+        _ => {
+            let _ = tx.send(ServerMessage::Error {
+                message: "Expected Hello as the first message".into(),
+            });
+            drop(tx);
+            let _ = writer_task.await;
+            return Err(GoblinError::TransportError("Missing handshake".into()));
+        }
+    };
+
+    let client_id = {
+        let mut clients = clients.lock();
+        let resumed = requested.filter(|id| clients.clients.contains_key(id));
+        let client_id = resumed.unwrap_or_else(|| requested.unwrap_or_default());
+        let client = clients.clients.entry(client_id).or_default();
+
+        let _ = tx.send(ServerMessage::Welcome {
+            client_id,
+            resumed: resumed.is_some(),
+        });
+        for event in client.backlog.drain(..) {
+            let _ = tx.send(ServerMessage::Event(event));
+        }
+        client.tx = Some(tx.clone());
+        client_id
+    };
+    info!(client_id = %client_id, "Daemon client connected");
+
+    while let Some(line) = lines.next_line().await.map_err(io)? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<ClientMessage>(&line) {
+            Ok(ClientMessage::Op(op)) => {
+                clients.lock().routes.insert(op.sub_id().clone(), client_id);
+                if op_tx.send(op).is_err() {
+                    break;
+                }
+            }
+            Ok(ClientMessage::Hello { .. }) => {
+                let _ = tx.send(ServerMessage::Error {
+                    message: "Already connected".into(),
+                });
+            }
+            Err(e) => {
+                warn!(client_id = %client_id, error = %e, "Malformed daemon message");
+                let _ = tx.send(ServerMessage::Error { message: e.to_string() });
+            }
+        }
+    }
+
+    // Keep the identity and start buffering until the client reconnects
+    {
+        let mut clients = clients.lock();
+        if let Some(client) = clients.clients.get_mut(&client_id) {
+            client.tx = None;
+        }
+        clients.routes.retain(|_, owner| *owner != client_id);
+    }
+    drop(tx);
+    let _ = writer_task.await;
+    info!(client_id = %client_id, "Daemon client disconnected");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use warhorn::{Event, Op};
+
+    struct TestClient {
+        lines: tokio::io::Lines<BufReader<tokio::net::unix::OwnedReadHalf>>,
+        writer: tokio::net::unix::OwnedWriteHalf,
+    }
+
+    impl TestClient {
+        async fn connect(path: &Path, client_id: Option<ClientId>) -> (Self, ClientId, bool) {
+            let stream = UnixStream::connect(path).await.unwrap();
+            let (reader, writer) = stream.into_split();
+            let mut client = Self {
+                lines: BufReader::new(reader).lines(),
+                writer,
+            };
+            client.send(&ClientMessage::Hello { client_id }).await;
+            match client.recv().await {
+                ServerMessage::Welcome { client_id, resumed } => (client, client_id, resumed),
+                other => panic!("expected welcome, got {:?}", other),
+            }
+        }
+
+        async fn send(&mut self, message: &ClientMessage) {
+            let mut line = serde_json::to_string(message).unwrap();
+            line.push('\n');
+            self.writer.write_all(line.as_bytes()).await.unwrap();
+        }
+
+        async fn recv(&mut self) -> ServerMessage {
+            let line = tokio::time::timeout(Duration::from_secs(1), self.lines.next_line())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            serde_json::from_str(&line).unwrap()
+        }
+    }
+
+    fn warning(sub_id: SubmissionId) -> GoblinEvent {
+        Event::Warning {
+            sub_id,
+            message: "heads up".into(),
+            details: None,
+        }
+        .into()
+    }
+
+    async fn wait_for_socket(path: &Path) {
+        for _ in 0..100 {
+            if path.exists() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("daemon did not bind");
+    }
+
+    #[tokio::test]
+    async fn test_routes_events_to_submitting_client() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cabal.sock");
+        let (op_tx, mut op_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        tokio::spawn(Daemon::new(&path).serve(op_tx, event_rx));
+        wait_for_socket(&path).await;
+
+        let (mut alice, _, _) = TestClient::connect(&path, None).await;
+        let (mut bob, _, _) = TestClient::connect(&path, None).await;
+
+        let op: GoblinOp = Op::interrupt().into();
+        let sub_id = op.sub_id().clone();
+        alice.send(&ClientMessage::Op(op)).await;
+        let received = tokio::time::timeout(Duration::from_secs(1), op_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.sub_id(), &sub_id);
+
+        // Alice gets the answer to her op; unsolicited events reach everyone
+        event_tx.send(warning(sub_id.clone())).unwrap();
+        event_tx.send(warning(SubmissionId::new())).unwrap();
+
+        match alice.recv().await {
+            ServerMessage::Event(event) => assert_eq!(event.sub_id(), Some(sub_id)),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        assert!(matches!(alice.recv().await, ServerMessage::Event(_)));
+        match bob.recv().await {
+            ServerMessage::Event(event) => assert_ne!(event.sub_id(), Some(sub_id)),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_stale_sockets_are_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cabal.sock");
+        let (op_tx, _op_rx) = mpsc::unbounded_channel();
+        let (_event_tx, event_rx) = mpsc::unbounded_channel();
+        tokio::spawn(Daemon::new(&path).serve(op_tx.clone(), event_rx));
+        wait_for_socket(&path).await;
+
+        let (_event_tx, event_rx) = mpsc::unbounded_channel();
+        let second = Daemon::new(&path).serve(op_tx.clone(), event_rx).await;
+        assert!(matches!(second, Err(GoblinError::TransportError(msg)) if msg.contains("in use")));
+
+        let file = dir.path().join("notes.txt");
+        std::fs::write(&file, "keep me").unwrap();
+        let (_event_tx, event_rx) = mpsc::unbounded_channel();
+        assert!(Daemon::new(&file).serve(op_tx, event_rx).await.is_err());
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep me");
+    }
+
+    #[tokio::test]
+    async fn test_disconnect_drops_routes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cabal.sock");
+        let (op_tx, mut op_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        tokio::spawn(Daemon::new(&path).serve(op_tx, event_rx));
+        wait_for_socket(&path).await;
+
+        let (mut alice, alice_id, _) = TestClient::connect(&path, None).await;
+        let (mut bob, _, _) = TestClient::connect(&path, None).await;
+        let op: GoblinOp = Op::interrupt().into();
+        let sub_id = op.sub_id().clone();
+        alice.send(&ClientMessage::Op(op)).await;
+        tokio::time::timeout(Duration::from_secs(1), op_rx.recv()).await.unwrap();
+        drop(alice);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The answer is no longer Alice's alone; she gets it on reconnect
+        event_tx.send(warning(sub_id.clone())).unwrap();
+        match bob.recv().await {
+            ServerMessage::Event(event) => assert_eq!(event.sub_id(), Some(sub_id.clone())),
+            other => panic!("unexpected message: {:?}", other),
+        }
+        let (mut alice, _, _) = TestClient::connect(&path, Some(alice_id)).await;
+        assert!(matches!(alice.recv().await, ServerMessage::Event(event) if event.sub_id() == Some(sub_id)));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_replays_backlog() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cabal.sock");
+        let (op_tx, _op_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        tokio::spawn(Daemon::new(&path).with_backlog(1).serve(op_tx, event_rx));
+        wait_for_socket(&path).await;
+
+        let (client, client_id, resumed) = TestClient::connect(&path, None).await;
+        assert!(!resumed);
+        drop(client);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // Only the newest event fits in the backlog
+        event_tx.send(warning(SubmissionId::new())).unwrap();
+        let latest = SubmissionId::new();
+        event_tx.send(warning(latest.clone())).unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let (mut client, again, resumed) = TestClient::connect(&path, Some(client_id)).await;
+        assert_eq!(again, client_id);
+        assert!(resumed);
+        match client.recv().await {
+            ServerMessage::Event(event) => assert_eq!(event.sub_id(), Some(latest)),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Per-task deadlines
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentId, TaskId};
+
+/// What the session does when a task misses its deadline
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DeadlineAction {
+    /// Only report the missed deadline
+    #[default]
+    Warn,
+    /// Interrupt the task and free the agent
+    Interrupt,
+    /// Report the missed deadline to the agent's parent
+    Escalate,
+}
+
+/// A deadline being watched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    /// Agent working on the task
+    pub agent_id: AgentId,
+    /// When the task was assigned
+    pub started: Instant,
+    /// Time allowed
+    pub limit: Duration,
+    /// Action to apply when exceeded
+    pub action: DeadlineAction,
+}
+
+impl Deadline {
+    /// Instant at which the deadline expires
+    pub fn expires_at(&self) -> Instant {
+        self.started + self.limit
+    }
+}
+
+/// Deadlines of assigned tasks
+#[derive(Debug, Default)]
+pub struct DeadlineTracker {
+    deadlines: HashMap<TaskId, Deadline>,
+}
+
+impl DeadlineTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start watching a task
+    pub fn insert(&mut self, task_id: TaskId, deadline: Deadline) {
+        self.deadlines.insert(task_id, deadline);
+    }
+
+    /// Stop watching a task (it finished in time)
+    pub fn clear(&mut self, task_id: &TaskId) -> Option<Deadline> {
+        self.deadlines.remove(task_id)
+    }
+
+    /// Get the deadline of a task
+    pub fn get(&self, task_id: &TaskId) -> Option<&Deadline> {
+        self.deadlines.get(task_id)
+    }
+
+    /// Remove and return the deadline if it has expired
+    pub fn take_expired(&mut self, task_id: &TaskId, now: Instant) -> Option<Deadline> {
+        match self.deadlines.get(task_id) {
+            Some(d) if d.expires_at() <= now => self.deadlines.remove(task_id),
+            _ => None,
+        }
+    }
+
+    /// Number of watched tasks
+    pub fn len(&self) -> usize {
+        self.deadlines.len()
+    }
+
+    /// Check if no tasks are watched
+    pub fn is_empty(&self) -> bool {
+        self.deadlines.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_expired() {
+        let mut tracker = DeadlineTracker::new();
+        let task_id = TaskId::new();
+        let now = Instant::now();
+
+        tracker.insert(
+            task_id,
+            Deadline {
+                agent_id: AgentId::new(),
+                started: now,
+                limit: Duration::from_secs(10),
+                action: DeadlineAction::Warn,
+            },
+        );
+
+        assert!(tracker.take_expired(&task_id, now + Duration::from_secs(5)).is_none());
+        assert!(tracker.take_expired(&task_id, now + Duration::from_secs(10)).is_some());
+        assert!(tracker.is_empty());
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut tracker = DeadlineTracker::new();
+        let task_id = TaskId::new();
+        tracker.insert(
+            task_id,
+            Deadline {
+                agent_id: AgentId::new(),
+                started: Instant::now(),
+                limit: Duration::ZERO,
+                action: DeadlineAction::Interrupt,
+            },
+        );
+
+        assert!(tracker.clear(&task_id).is_some());
+        assert!(tracker.take_expired(&task_id, Instant::now()).is_none());
+    }
+}
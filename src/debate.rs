@@ -0,0 +1,316 @@
+//! Debates between two agents
+//!
+//! A [`Debate`] spawns two debaters under a moderator agent (the session
+//! root unless given). They argue opposite sides in alternating turns;
+//! after every round the moderator may settle the debate, and it must give
+//! its verdict after the last round. The transcript and verdict become the
+//! task result.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+use warhorn::{AgentConfig, AgentId, AgentRole, SubmissionId, TaskId};
+
+use crate::agent::AgentHandle;
+use crate::artifact::Attachment;
+use crate::error::GoblinError;
+use crate::protocol::GoblinEvent;
+use crate::result::TaskResult;
+use crate::session::SessionHandle;
+
+/// Side argued by a debater
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    For,
+    Against,
+}
+
+/// One argument in a debate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Turn {
+    /// Round the turn belongs to, from 1
+    pub round: usize,
+    pub side: Side,
+    /// Debater who spoke
+    pub agent_id: AgentId,
+    pub argument: String,
+}
+
+/// What a debater sees when making its next argument
+#[derive(Debug, Clone, Copy)]
+pub struct DebateContext<'a> {
+    pub question: &'a str,
+    pub side: Side,
+    pub round: usize,
+    /// Everything said so far
+    pub transcript: &'a [Turn],
+}
+
+/// Argues one side of a debate on behalf of a debater agent
+#[async_trait]
+pub trait Debater: Send + Sync {
+    /// Make the next argument
+    async fn argue(&self, agent: &AgentHandle, ctx: DebateContext<'_>) -> Result<String, GoblinError>;
+}
+
+/// Judges a debate on behalf of the moderator agent
+#[async_trait]
+pub trait Moderator: Send + Sync {
+    /// Return a verdict to end the debate, or None to continue
+    ///
+    /// Called after every round. After the last round (`final_round`),
+    /// None means the debate ended without a verdict.
+    async fn assess(
+        &self,
+        moderator: &AgentHandle,
+        question: &str,
+        transcript: &[Turn],
+        final_round: bool,
+    ) -> Result<Option<String>, GoblinError>;
+}
+
+/// A debate between two agents
+#[derive(Debug, Clone)]
+pub struct Debate {
+    /// Question being debated
+    pub question: String,
+    /// Maximum number of rounds
+    pub rounds: usize,
+    /// Agent moderating the debate (the root if None)
+    pub moderator: Option<AgentId>,
+}
+
+impl Debate {
+    /// Create a debate of at most `rounds` rounds
+    pub fn new(question: impl Into<String>, rounds: usize) -> Self {
+        Self {
+            question: question.into(),
+            rounds: rounds.max(1),
+            moderator: None,
+        }
+    }
+
+    /// Moderate the debate with a specific agent
+    pub fn with_moderator(mut self, moderator: AgentId) -> Self {
+        self.moderator = Some(moderator);
+        self
+    }
+
+    /// Run the debate and return its result
+    ///
+    /// The summary is the verdict; the payload holds the transcript.
+    pub async fn run(
+        &self,
+        session: &SessionHandle,
+        debater: Arc<dyn Debater>,
+        moderator: Arc<dyn Moderator>,
+        sub_id: &SubmissionId,
+    ) -> Result<TaskResult, GoblinError> {
+        let chair = match self.moderator {
+            Some(id) => session.get_agent(&id).ok_or(GoblinError::AgentNotFound(id))?,
+            None => session.orchestrator().ok_or(GoblinError::NoOrchestrator)?,
+        };
+        let task_id = TaskId::new();
+        info!(task_id = %task_id, rounds = self.rounds, "Starting debate");
+
+        let defaults = session.config();
+        let mut debaters = Vec::with_capacity(2);
+        for side in [Side::For, Side::Against] {
+            let config = AgentConfig {
+                role: AgentRole::Specialist { specialty: format!("debater-{:?}", side).to_lowercase() },
+                model: defaults.model.clone(),
+                cwd: defaults.cwd.clone(),
+                can_spawn: false,
+                ..Default::default()
+            };
+            let agent = match session.spawn_agent(config, Some(chair.id()), sub_id) {
+                Ok(agent) => agent,
+                Err(e) => {
+                    dismiss(session, &debaters, "Debate could not start", sub_id);
+                    return Err(e);
+                }
+            };
+            agent.assign_task(task_id);
+            debaters.push((side, agent));
+        }
+
+        let floor = Floor {
+            session,
+            chair: &chair,
+            debaters: &debaters,
+            debater: debater.as_ref(),
+            moderator: moderator.as_ref(),
+            task_id,
+            sub_id,
+        };
+        let outcome = self.argue(&floor).await;
+        dismiss(session, &debaters, "Debate finished", sub_id);
+        let (transcript, verdict) = outcome?;
+
+        let rounds = transcript.last().map_or(0, |t| t.round);
+        debug!(task_id = %task_id, rounds, settled = verdict.is_some(), "Debate finished");
+
+        let result = match &verdict {
+            Some(verdict) => TaskResult::success(task_id, verdict.clone()),
+            None => TaskResult::failure(task_id, "The debate ended without a verdict"),
+        };
+        let payload = serde_json::json!({
+            "question": self.question,
+            "rounds": rounds,
+            "transcript": transcript,
+            "verdict": verdict,
+        });
+        let mut result = result.with_payload(payload).from_agent(chair.id());
+        result.attachments.push(Attachment::text("transcript.md", render_transcript(&self.question, &transcript)));
+        Ok(result)
+    }
+
+    async fn argue(&self, floor: &Floor<'_>) -> Result<(Vec<Turn>, Option<String>), GoblinError> {
+        let mut transcript = Vec::new();
+        for round in 1..=self.rounds {
+            for (side, agent) in floor.debaters {
+                let ctx = DebateContext {
+                    question: &self.question,
+                    side: *side,
+                    round,
+                    transcript: &transcript,
+                };
+                let argument = floor.debater.argue(agent, ctx).await?;
+                let turn = Turn {
+                    round,
+                    side: *side,
+                    agent_id: agent.id(),
+                    argument,
+                };
+                floor.session.emit(GoblinEvent::DebateTurn {
+                    sub_id: floor.sub_id.clone(),
+                    task_id: floor.task_id,
+                    turn: turn.clone(),
+                });
+                transcript.push(turn);
+            }
+
+            let final_round = round == self.rounds;
+            let assessed = floor.moderator.assess(floor.chair, &self.question, &transcript, final_round).await?;
+            if let Some(verdict) = assessed {
+                return Ok((transcript, Some(verdict)));
+            }
+        }
+        Ok((transcript, None))
+    }
+}
+
+/// A debate in progress: who takes part and where it reports
+struct Floor<'a> {
+    session: &'a SessionHandle,
+    chair: &'a AgentHandle,
+    debaters: &'a [(Side, AgentHandle)],
+    debater: &'a dyn Debater,
+    moderator: &'a dyn Moderator,
+    task_id: TaskId,
+    sub_id: &'a SubmissionId,
+}
+
+/// Terminate the debaters
+fn dismiss(session: &SessionHandle, debaters: &[(Side, AgentHandle)], reason: &str, sub_id: &SubmissionId) {
+    for (_, agent) in debaters {
+        let _ = session.terminate_agent(&agent.id(), reason.into(), sub_id);
+    }
+}
+
+fn render_transcript(question: &str, transcript: &[Turn]) -> String {
+    let mut out = format!("# {}\n", question);
+    for turn in transcript {
+        out.push_str(&format!("\n## Round {} ({:?})\n\n{}\n", turn.round, turn.side, turn.argument));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing::session_with_root;
+
+    struct Echo;
+
+    #[async_trait]
+    impl Debater for Echo {
+        async fn argue(&self, _agent: &AgentHandle, ctx: DebateContext<'_>) -> Result<String, GoblinError> {
+            // The opposition concedes in round 2
+            if ctx.side == Side::Against && ctx.round == 2 {
+                return Ok("I concede".into());
+            }
+            Ok(format!("{:?} #{}", ctx.side, ctx.round))
+        }
+    }
+
+    struct Chair;
+
+    #[async_trait]
+    impl Moderator for Chair {
+        async fn assess(
+            &self,
+            _moderator: &AgentHandle,
+            _question: &str,
+            transcript: &[Turn],
+            _final_round: bool,
+        ) -> Result<Option<String>, GoblinError> {
+            let conceded = transcript.iter().any(|t| t.argument == "I concede");
+            Ok(conceded.then(|| "For wins".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_moderator_settles_on_convergence() {
+        let (session, mut rx, sub_id) = session_with_root();
+        let result = Debate::new("Tabs or spaces?", 5)
+            .run(&session, Arc::new(Echo), Arc::new(Chair), &sub_id)
+            .await
+            .unwrap();
+
+        assert!(result.is_success());
+        assert_eq!(result.summary, "For wins");
+        let payload = result.payload.unwrap();
+        assert_eq!(payload["rounds"], 2);
+        assert_eq!(payload["transcript"].as_array().unwrap().len(), 4);
+        assert_eq!(session.agent_count(), 1);
+
+        let turns = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|e| matches!(e, GoblinEvent::DebateTurn { .. }))
+            .count();
+        assert_eq!(turns, 4);
+    }
+
+    #[tokio::test]
+    async fn test_debate_without_verdict() {
+        let (session, _rx, sub_id) = session_with_root();
+        let result = Debate::new("Tabs or spaces?", 1)
+            .run(&session, Arc::new(Echo), Arc::new(Chair), &sub_id)
+            .await
+            .unwrap();
+
+        assert!(!result.is_success());
+        assert_eq!(result.attachments.len(), 1);
+    }
+    #[tokio::test]
+    async fn test_debaters_are_dismissed_when_one_cannot_spawn() {
+        let (session, _rx, sub_id) = session_with_root();
+        let chair = AgentConfig {
+            can_spawn: true,
+            max_children: Some(1),
+            ..Default::default()
+        };
+        let root = session.orchestrator().unwrap().id();
+        let chair = session.spawn_agent(chair, Some(root), &sub_id).unwrap();
+
+        let started = Debate::new("Tabs or spaces?", 1)
+            .with_moderator(chair.id())
+            .run(&session, Arc::new(Echo), Arc::new(Chair), &sub_id)
+            .await;
+        assert!(started.is_err());
+        assert_eq!(session.agent_count(), 2);
+    }
+}
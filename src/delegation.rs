@@ -0,0 +1,175 @@
+//! Delegation of subtasks between sessions
+//!
+//! A session's orchestrator can hand a subtask to another existing session,
+//! such as a long-lived "infrastructure" session. The orchestrator acts as
+//! the broker: it checks that the target session accepts delegations from
+//! the origin, starts the work there, and routes the outcome back to the
+//! origin session as if a local worker had produced it.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use warhorn::{SessionId, TaskId};
+
+use crate::error::GoblinError;
+
+/// Which sessions may delegate work into a session
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DelegationPolicy {
+    /// Reject all delegations
+    #[default]
+    Deny,
+    /// Accept delegations from any session
+    AllowAll,
+    /// Accept delegations from the listed sessions only
+    AllowFrom(Vec<SessionId>),
+}
+
+impl DelegationPolicy {
+    /// Check whether `origin` may delegate into a session with this policy
+    pub fn permits(&self, origin: &SessionId) -> bool {
+        match self {
+            Self::Deny => false,
+            Self::AllowAll => true,
+            Self::AllowFrom(sessions) => sessions.contains(origin),
+        }
+    }
+}
+
+/// A subtask delegated from one session to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delegation {
+    /// Session that delegated the work
+    pub origin_session: SessionId,
+    /// Subtask in the origin session
+    pub origin_task: TaskId,
+    /// Session doing the work
+    pub target_session: SessionId,
+    /// Task started in the target session
+    pub target_task: TaskId,
+}
+
+/// Outcome of delegated work, as seen by the origin session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DelegationOutcome {
+    /// Session that did the work
+    pub from_session: SessionId,
+    /// Whether the work succeeded
+    pub success: bool,
+    /// Summary reported by the target session
+    pub summary: String,
+}
+
+/// Tracks delegations in flight
+#[derive(Debug, Default)]
+pub struct DelegationBroker {
+    active: HashMap<(SessionId, TaskId), Delegation>,
+}
+
+impl DelegationBroker {
+    /// Create an empty broker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Validate and record a new delegation
+    pub fn open(
+        &mut self,
+        delegation: Delegation,
+        target_policy: &DelegationPolicy,
+    ) -> Result<(), GoblinError> {
+        if delegation.origin_session == delegation.target_session {
+            return Err(GoblinError::DelegationDenied(
+                "A session cannot delegate to itself".into(),
+            ));
+        }
+        if !target_policy.permits(&delegation.origin_session) {
+            return Err(GoblinError::DelegationDenied(format!(
+                "Session {} does not accept delegations from {}",
+                delegation.target_session, delegation.origin_session
+            )));
+        }
+
+        self.active.insert(
+            (delegation.target_session, delegation.target_task),
+            delegation,
+        );
+        Ok(())
+    }
+
+    /// Close the delegation for a finished target task
+    pub fn close(&mut self, target_session: &SessionId, target_task: &TaskId) -> Option<Delegation> {
+        self.active.remove(&(*target_session, *target_task))
+    }
+
+    /// Delegations originating from a session
+    pub fn from_session(&self, origin: &SessionId) -> Vec<Delegation> {
+        self.active
+            .values()
+            .filter(|d| d.origin_session == *origin)
+            .cloned()
+            .collect()
+    }
+
+    /// Number of delegations in flight
+    pub fn len(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Check if no delegations are in flight
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delegation(origin: SessionId, target: SessionId) -> Delegation {
+        Delegation {
+            origin_session: origin,
+            origin_task: TaskId::new(),
+            target_session: target,
+            target_task: TaskId::new(),
+        }
+    }
+
+    #[test]
+    fn test_policy_permits() {
+        let a = SessionId::new();
+        let b = SessionId::new();
+
+        assert!(!DelegationPolicy::Deny.permits(&a));
+        assert!(DelegationPolicy::AllowAll.permits(&a));
+        assert!(DelegationPolicy::AllowFrom(vec![a]).permits(&a));
+        assert!(!DelegationPolicy::AllowFrom(vec![a]).permits(&b));
+    }
+
+    #[test]
+    fn test_broker_open_and_close() {
+        let mut broker = DelegationBroker::new();
+        let origin = SessionId::new();
+        let target = SessionId::new();
+        let d = delegation(origin, target);
+
+        broker.open(d.clone(), &DelegationPolicy::AllowAll).unwrap();
+        assert_eq!(broker.from_session(&origin).len(), 1);
+
+        assert_eq!(broker.close(&target, &d.target_task), Some(d));
+        assert!(broker.is_empty());
+    }
+
+    #[test]
+    fn test_broker_denies() {
+        let mut broker = DelegationBroker::new();
+        let origin = SessionId::new();
+        let target = SessionId::new();
+
+        assert!(broker.open(delegation(origin, target), &DelegationPolicy::Deny).is_err());
+        assert!(broker
+            .open(delegation(origin, origin), &DelegationPolicy::AllowAll)
+            .is_err());
+        assert!(broker.is_empty());
+    }
+}
@@ -1,7 +1,39 @@
 //! Goblin error types
+//!
+//! Every error has a stable, machine-readable [`GoblinError::code`] and an
+//! [`ErrorCategory`], which are reported along with the message in error
+//! events, and says whether the failed operation may succeed if tried
+//! again.
 
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use warhorn::AgentId;
+use warhorn::{AgentId, SessionId};
+
+/// What kind of thing failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    /// A session, agent or task that does not exist or is in the wrong state
+    Session,
+    /// A task that could not be completed
+    Task,
+    /// A model provider, or its circuit breaker
+    Provider,
+    /// A tool call
+    Tool,
+    /// The sandbox a command runs in
+    Sandbox,
+    /// A limit or permission refusing the operation
+    Policy,
+    /// A token budget
+    Budget,
+    /// The protocol, channels and transports to clients
+    Protocol,
+    /// Invalid configuration
+    Config,
+    /// Persisted state and scratch workspaces
+    Storage,
+}
 
 /// Errors that can occur in the goblin system
 #[derive(Debug, Error)]
@@ -10,6 +42,10 @@ pub enum GoblinError {
     #[error("No active session")]
     NoActiveSession,
 
+    /// Session not found
+    #[error("Session not found: {0}")]
+    SessionNotFound(SessionId),
+
     /// No orchestrator agent
     #[error("No orchestrator agent in session")]
     NoOrchestrator,
@@ -22,14 +58,58 @@ pub enum GoblinError {
     #[error("Spawn denied: {0}")]
     SpawnDenied(String),
 
+    /// Agent spawn refused by a rate limit
+    #[error("Spawn denied: {reason}, retry after {retry_after_ms} ms")]
+    SpawnRateLimited { reason: String, retry_after_ms: u64 },
+
+    /// Invalid change to the agent hierarchy
+    #[error("Hierarchy error: {0}")]
+    HierarchyError(String),
+
+    /// Cross-session delegation denied
+    #[error("Delegation denied: {0}")]
+    DelegationDenied(String),
+
     /// Task error
     #[error("Task error: {0}")]
     TaskError(String),
 
+    /// Task, plan, stage or agent loop in the wrong state for the operation
+    #[error("Invalid state: {0}")]
+    InvalidState(String),
+
+    /// Call or attachment that can never be accepted as given
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    /// Agent lacks the component an operation needs
+    #[error("Not configured: {0}")]
+    NotConfigured(String),
+
+    /// Subtask queue full of work at the same or higher priority
+    #[error("Queue full: {0}")]
+    QueueFull(String),
+
     /// Tool error
     #[error("Tool error: {0}")]
     ToolError(#[from] trinkets::ToolError),
 
+    /// Tool call ran past the timeout set for the tool
+    #[error("{tool} timed out after {timeout_ms} ms")]
+    ToolTimeout { tool: String, timeout_ms: u64 },
+
+    /// Identical call another agent ran, which this call waited for, failed
+    #[error("Shared {tool} call failed: {message}")]
+    SharedCallFailed { tool: String, message: String },
+
+    /// MCP server failed to start, answer or run a tool
+    #[error("MCP server {server}: {message}")]
+    McpError { server: String, message: String },
+
+    /// Tool call refused by the agent's tool view
+    #[error("Agent {agent_id} may not use {tool}")]
+    ToolDenied { agent_id: AgentId, tool: String },
+
     /// Sandbox error
     #[error("Sandbox error: {0}")]
     SandboxError(#[from] wardstone::SandboxError),
@@ -42,7 +122,184 @@ pub enum GoblinError {
     #[error("Channel error: {0}")]
     ChannelError(String),
 
+    /// Transport (socket, stdio) error
+    #[error("Transport error: {0}")]
+    TransportError(String),
+
     /// Configuration error
     #[error("Configuration error: {0}")]
     ConfigError(String),
+
+    /// Scratch workspace error
+    #[error("Workspace error: {0}")]
+    WorkspaceError(String),
+
+    /// Model provider error
+    #[error("Model error: {0}")]
+    ModelError(String),
+
+    /// Model provider rate limited the call, failed on its side or could
+    /// not be reached; only these count against its circuit breaker
+    #[error("Model unavailable: {0}")]
+    ModelUnavailable(String),
+
+    /// Model calls fail fast while the model's circuit breaker is open
+    #[error("Model {model} unavailable, circuit open; retry after {retry_after_ms} ms")]
+    CircuitOpen { model: String, retry_after_ms: u64 },
+
+    /// Agent spent its token budget
+    #[error("Token budget exceeded by agent {0}")]
+    BudgetExceeded(AgentId),
+
+    /// Saving or loading persisted state failed
+    #[error("Persistence error: {0}")]
+    PersistenceError(String),
+}
+
+impl GoblinError {
+    /// Stable machine-readable code of the error
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NoActiveSession => "no_active_session",
+            Self::SessionNotFound(_) => "session_not_found",
+            Self::NoOrchestrator => "no_orchestrator",
+            Self::AgentNotFound(_) => "agent_not_found",
+            Self::SpawnDenied(_) => "spawn_denied",
+            Self::SpawnRateLimited { .. } => "spawn_rate_limited",
+            Self::HierarchyError(_) => "hierarchy_error",
+            Self::DelegationDenied(_) => "delegation_denied",
+            Self::TaskError(_) => "task_error",
+            Self::InvalidState(_) => "invalid_state",
+            Self::InvalidInput(_) => "invalid_input",
+            Self::NotConfigured(_) => "not_configured",
+            Self::QueueFull(_) => "queue_full",
+            Self::ToolError(_) => "tool_error",
+            Self::ToolTimeout { .. } => "tool_timeout",
+            Self::SharedCallFailed { .. } => "shared_call_failed",
+            Self::McpError { .. } => "mcp_error",
+            Self::ToolDenied { .. } => "tool_denied",
+            Self::SandboxError(_) => "sandbox_error",
+            Self::ProtocolError(_) => "protocol_error",
+            Self::ChannelError(_) => "channel_error",
+            Self::TransportError(_) => "transport_error",
+            Self::ConfigError(_) => "config_error",
+            Self::WorkspaceError(_) => "workspace_error",
+            Self::ModelError(_) => "model_error",
+            Self::ModelUnavailable(_) => "model_unavailable",
+            Self::CircuitOpen { .. } => "circuit_open",
+            Self::BudgetExceeded(_) => "budget_exceeded",
+            Self::PersistenceError(_) => "persistence_error",
+        }
+    }
+
+    /// What kind of thing failed
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Self::NoActiveSession
+            | Self::SessionNotFound(_)
+            | Self::NoOrchestrator
+            | Self::AgentNotFound(_)
+            | Self::HierarchyError(_)
+            | Self::InvalidState(_) => ErrorCategory::Session,
+            Self::TaskError(_) | Self::InvalidInput(_) => ErrorCategory::Task,
+            Self::ModelError(_) | Self::ModelUnavailable(_) | Self::CircuitOpen { .. } => ErrorCategory::Provider,
+            Self::ToolError(_) | Self::ToolTimeout { .. } | Self::SharedCallFailed { .. } | Self::McpError { .. } => {
+                ErrorCategory::Tool
+            }
+            Self::SandboxError(_) => ErrorCategory::Sandbox,
+            Self::SpawnDenied(_)
+            | Self::SpawnRateLimited { .. }
+            | Self::DelegationDenied(_)
+            | Self::QueueFull(_)
+            | Self::ToolDenied { .. } => ErrorCategory::Policy,
+            Self::BudgetExceeded(_) => ErrorCategory::Budget,
+            Self::ProtocolError(_) | Self::ChannelError(_) | Self::TransportError(_) => ErrorCategory::Protocol,
+            Self::ConfigError(_) | Self::NotConfigured(_) => ErrorCategory::Config,
+            Self::WorkspaceError(_) | Self::PersistenceError(_) => ErrorCategory::Storage,
+        }
+    }
+
+    /// Whether the failed operation may succeed if tried again unchanged
+    ///
+    /// Provider failures, rate limits, full queues, transport failures and
+    /// failed tasks and tools are; refusals by a policy or budget, invalid
+    /// configuration, input or state, timed out tools and missing sessions
+    /// or agents are not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::SpawnRateLimited { .. }
+            | Self::QueueFull(_)
+            | Self::TaskError(_)
+            | Self::ToolError(_)
+            | Self::SharedCallFailed { .. }
+            | Self::McpError { .. }
+            | Self::ChannelError(_)
+            | Self::TransportError(_)
+            | Self::ModelError(_)
+            | Self::ModelUnavailable(_)
+            | Self::CircuitOpen { .. }
+            | Self::PersistenceError(_) => true,
+            Self::NoActiveSession
+            | Self::SessionNotFound(_)
+            | Self::NoOrchestrator
+            | Self::AgentNotFound(_)
+            | Self::SpawnDenied(_)
+            | Self::HierarchyError(_)
+            | Self::DelegationDenied(_)
+            | Self::InvalidState(_)
+            | Self::InvalidInput(_)
+            | Self::NotConfigured(_)
+            | Self::ToolTimeout { .. }
+            | Self::ToolDenied { .. }
+            | Self::SandboxError(_)
+            | Self::ProtocolError(_)
+            | Self::ConfigError(_)
+            | Self::WorkspaceError(_)
+            | Self::BudgetExceeded(_) => false,
+        }
+    }
+
+    /// How long to wait before trying again, for errors that say
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            Self::SpawnRateLimited { retry_after_ms, .. } | Self::CircuitOpen { retry_after_ms, .. } => {
+                Some(std::time::Duration::from_millis(*retry_after_ms))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_categories_and_retryability() {
+        let limited = GoblinError::SpawnRateLimited {
+            reason: "too many spawns".into(),
+            retry_after_ms: 500,
+        };
+        assert_eq!((limited.code(), limited.category()), ("spawn_rate_limited", ErrorCategory::Policy));
+        assert!(limited.is_retryable());
+
+        let denied = GoblinError::ToolDenied {
+            agent_id: AgentId::new(),
+            tool: "shell".into(),
+        };
+        assert_eq!(denied.code(), "tool_denied");
+        assert!(!denied.is_retryable());
+        assert!(!GoblinError::BudgetExceeded(AgentId::new()).is_retryable());
+        assert_eq!(GoblinError::ModelError("overloaded".into()).category(), ErrorCategory::Provider);
+
+        let timeout = GoblinError::ToolTimeout {
+            tool: "search".into(),
+            timeout_ms: 100,
+        };
+        assert_eq!((timeout.code(), timeout.category()), ("tool_timeout", ErrorCategory::Tool));
+        assert!(!timeout.is_retryable());
+        assert!(!GoblinError::InvalidInput("bad call".into()).is_retryable());
+        assert!(!GoblinError::NotConfigured("no store".into()).is_retryable());
+        assert_eq!(GoblinError::InvalidState("not running".into()).category(), ErrorCategory::Session);
+    }
 }
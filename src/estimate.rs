@@ -0,0 +1,230 @@
+//! Cost estimates of planned tasks
+//!
+//! Before a planned task is handed to its agents, the session can estimate
+//! what it will cost: every planned subtask is expected to spend the
+//! average tokens of its role, taken from the results of earlier subtasks
+//! of the session or, for roles without history, from the
+//! [`EstimatePolicy`]. The estimate is a range around that expectation,
+//! priced by the session's model, and is reported in a `CostEstimate`
+//! event. When its upper bound is above the policy's threshold the task
+//! waits for the client to answer with `ConfirmCostEstimate`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentRole, TaskId};
+
+use crate::error::GoblinError;
+use crate::plan::TaskPlan;
+use crate::pricing::ModelPrice;
+use crate::runtime::{by_role, role_key};
+
+/// Tokens expected of a subtask whose role has no history or prior
+pub const DEFAULT_TOKENS_PER_TASK: u64 = 20_000;
+
+/// How planned tasks are estimated, and when they need confirmation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EstimatePolicy {
+    /// Expected tokens of a subtask by role key, used until the role has history
+    #[serde(default)]
+    pub tokens_per_task: HashMap<String, u64>,
+    /// Expected tokens of a subtask of any other role
+    #[serde(default = "default_tokens_per_task")]
+    pub default_tokens_per_task: u64,
+    /// Relative width of the range on either side of the expectation
+    #[serde(default = "default_spread")]
+    pub spread: f64,
+    /// Share of the tokens priced as input
+    #[serde(default = "default_input_share")]
+    pub input_share: f64,
+    /// Hold tasks whose estimate may cost more than this many dollars
+    /// until the client confirms them
+    #[serde(default)]
+    pub confirm_above_usd: Option<f64>,
+}
+
+fn default_tokens_per_task() -> u64 {
+    DEFAULT_TOKENS_PER_TASK
+}
+
+fn default_spread() -> f64 {
+    0.5
+}
+
+fn default_input_share() -> f64 {
+    0.75
+}
+
+impl Default for EstimatePolicy {
+    fn default() -> Self {
+        Self {
+            tokens_per_task: HashMap::new(),
+            default_tokens_per_task: DEFAULT_TOKENS_PER_TASK,
+            spread: default_spread(),
+            input_share: default_input_share(),
+            confirm_above_usd: None,
+        }
+    }
+}
+
+impl EstimatePolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expect subtasks of a role to spend this many tokens
+    pub fn with_tokens_per_task(mut self, role: impl Into<String>, tokens: u64) -> Self {
+        self.tokens_per_task.insert(role.into(), tokens);
+        self
+    }
+
+    /// Hold tasks that may cost more than `usd` until the client confirms
+    pub fn with_confirmation_above(mut self, usd: f64) -> Self {
+        self.confirm_above_usd = Some(usd);
+        self
+    }
+
+    /// Estimate a plan with the averages of earlier subtasks, priced at
+    /// `price` if the model has one
+    pub fn estimate(&self, plan: &TaskPlan, history: &RoleHistory, price: Option<ModelPrice>) -> CostEstimate {
+        let mut roles: BTreeMap<String, RoleEstimate> = BTreeMap::new();
+        for task in plan.tasks() {
+            let key = role_key(&task.role);
+            let entry = roles.entry(key.clone()).or_insert_with(|| {
+                let (tokens_per_task, from_history) = match history.average(&key) {
+                    Some(average) => (average, true),
+                    None => (self.prior(&task.role), false),
+                };
+                RoleEstimate {
+                    role: key,
+                    tasks: 0,
+                    tokens_per_task,
+                    from_history,
+                }
+            });
+            entry.tasks += 1;
+        }
+
+        let expected: u64 = roles.values().map(|r| r.tasks as u64 * r.tokens_per_task).sum();
+        let tokens_low = (expected as f64 * (1.0 - self.spread)).round() as u64;
+        let tokens_high = (expected as f64 * (1.0 + self.spread)).round() as u64;
+        let cost = |tokens: u64| {
+            price.map_or(0.0, |p| {
+                let input = (tokens as f64 * self.input_share).round() as u64;
+                p.cost(input, tokens - input)
+            })
+        };
+        let cost_high_usd = cost(tokens_high);
+        CostEstimate {
+            task_id: plan.task_id,
+            subtasks: plan.len(),
+            tokens_low,
+            tokens_high,
+            cost_low_usd: cost(tokens_low),
+            cost_high_usd,
+            by_role: roles.into_values().collect(),
+            needs_confirmation: self.confirm_above_usd.is_some_and(|max| cost_high_usd > max),
+        }
+    }
+
+    fn prior(&self, role: &AgentRole) -> u64 {
+        by_role(&self.tokens_per_task, role)
+            .copied()
+            .unwrap_or(self.default_tokens_per_task)
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if !(0.0..1.0).contains(&self.spread) {
+            return Err(GoblinError::ConfigError("estimate spread must be at least 0 and below 1".into()));
+        }
+        if !(0.0..=1.0).contains(&self.input_share) {
+            return Err(GoblinError::ConfigError("estimate input_share must be between 0 and 1".into()));
+        }
+        if self.confirm_above_usd.is_some_and(|usd| !(usd.is_finite() && usd >= 0.0)) {
+            return Err(GoblinError::ConfigError("estimate confirm_above_usd must not be negative".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Expected spending of the subtasks of one role
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleEstimate {
+    pub role: String,
+    /// Planned subtasks of the role
+    pub tasks: usize,
+    pub tokens_per_task: u64,
+    /// Whether the expectation is the role's average so far, not a prior
+    pub from_history: bool,
+}
+
+/// Estimated range of a planned task's spending
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostEstimate {
+    pub task_id: TaskId,
+    /// Subtasks in the plan
+    pub subtasks: usize,
+    pub tokens_low: u64,
+    pub tokens_high: u64,
+    pub cost_low_usd: f64,
+    pub cost_high_usd: f64,
+    pub by_role: Vec<RoleEstimate>,
+    /// Whether the task waits for `ConfirmCostEstimate`
+    pub needs_confirmation: bool,
+}
+
+/// Tokens spent by finished subtasks, by role
+#[derive(Debug, Clone, Default)]
+pub struct RoleHistory {
+    roles: HashMap<String, (u64, u64)>,
+}
+
+impl RoleHistory {
+    /// Count a finished subtask of an agent with `role`
+    pub fn record(&mut self, role: &AgentRole, tokens: u64) {
+        let (count, total) = self.roles.entry(role_key(role)).or_default();
+        *count += 1;
+        *total += tokens;
+    }
+
+    /// Average tokens of a role's subtasks, if any finished
+    pub fn average(&self, role: &str) -> Option<u64> {
+        self.roles
+            .get(role)
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, total)| total / count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::PlannedTask;
+
+    #[test]
+    fn test_history_replaces_priors() {
+        let mut plan = TaskPlan::new(TaskId::new(), "Ship it");
+        let lead = plan.add(PlannedTask::new("Backend", AgentRole::DomainLead { domain: "backend".into() }));
+        plan.add(PlannedTask::new("API", AgentRole::Worker).with_parent(lead));
+        plan.add(PlannedTask::new("DB", AgentRole::Worker).with_parent(lead));
+
+        let policy = EstimatePolicy::new()
+            .with_tokens_per_task("domain_lead", 10_000)
+            .with_confirmation_above(0.5);
+        let mut history = RoleHistory::default();
+        history.record(&AgentRole::Worker, 30_000);
+        history.record(&AgentRole::Worker, 50_000);
+
+        let estimate = policy.estimate(&plan, &history, Some(ModelPrice::new(4.0, 4.0)));
+        assert_eq!(estimate.subtasks, 3);
+        assert_eq!((estimate.tokens_low, estimate.tokens_high), (45_000, 135_000));
+        assert!((estimate.cost_high_usd - 0.54).abs() < 1e-9);
+        assert!(estimate.needs_confirmation);
+        let worker = estimate.by_role.iter().find(|r| r.role == "worker").unwrap();
+        assert_eq!((worker.tasks, worker.tokens_per_task, worker.from_history), (2, 40_000, true));
+
+        let free = policy.estimate(&plan, &history, None);
+        assert_eq!(free.cost_high_usd, 0.0);
+        assert!(!free.needs_confirmation);
+    }
+}
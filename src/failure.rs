@@ -0,0 +1,101 @@
+//! Reports of repeated failures to the lead
+//!
+//! When an agent's results fail several times in a row, or a task fails
+//! after being retried, the session delivers a [`FailureReport`] to the
+//! mailbox of the nearest domain lead above the agent (its parent if it
+//! has no lead). The report carries the error, the partial work of the
+//! failed result, the earlier attempts and a digest of the agent's
+//! transcript, so the lead can reassign the task, simplify it or report
+//! further up.
+
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentId, TaskId};
+
+use crate::agent::AgentHandle;
+use crate::error::GoblinError;
+use crate::handoff::{excerpt, history_digest};
+use crate::history::HistoryEntry;
+use crate::model::ChatRole;
+use crate::result::TaskResult;
+
+/// Failures in a row after which an agent is reported
+pub const DEFAULT_REPORT_AFTER: u32 = 2;
+
+/// When failures are reported to the lead
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FailureReports {
+    /// Report once an agent failed this many times in a row, or a task
+    /// failed this many attempts
+    pub after_failures: u32,
+}
+
+impl Default for FailureReports {
+    fn default() -> Self {
+        Self {
+            after_failures: DEFAULT_REPORT_AFTER,
+        }
+    }
+}
+
+impl FailureReports {
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.after_failures == 0 {
+            return Err(GoblinError::ConfigError("failure reports after_failures must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
+
+/// What a lead is told about an agent that keeps failing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureReport {
+    /// Agent that failed
+    pub agent_id: AgentId,
+    pub task_id: TaskId,
+    /// Summary of the last failed result
+    pub error: String,
+    /// Failures in a row, counting attempts at retried tasks
+    pub failures: u32,
+    /// The last failed result, with whatever partial work it holds and
+    /// the task's earlier attempts
+    pub result: TaskResult,
+    /// Digest of the end of the agent's transcript
+    pub transcript: String,
+}
+
+impl FailureReport {
+    /// Report a failed result of an agent
+    pub fn new(agent: &AgentHandle, result: TaskResult, failures: u32) -> Self {
+        Self {
+            agent_id: agent.id(),
+            task_id: result.task_id,
+            error: result.summary.clone(),
+            failures,
+            transcript: history_digest(agent),
+            result,
+        }
+    }
+
+    /// The report as a history entry for the lead
+    pub fn to_entry(&self) -> HistoryEntry {
+        let mut content = format!(
+            "Agent {} failed task {} ({} failures in a row): {}",
+            self.agent_id,
+            self.task_id,
+            self.failures,
+            excerpt(&self.error)
+        );
+        for attempt in &self.result.attempts {
+            content.push_str(&format!("\n\nAttempt {} failed: {}", attempt.attempt, excerpt(&attempt.error)));
+        }
+        if !self.result.files_changed.is_empty() {
+            let files: Vec<String> = self.result.files_changed.iter().map(|f| f.path.display().to_string()).collect();
+            content.push_str(&format!("\n\nFiles changed before failing: {}", files.join(", ")));
+        }
+        if !self.transcript.is_empty() {
+            content.push_str(&format!("\n\nIts latest context:\n{}", self.transcript));
+        }
+        content.push_str("\n\nReassign the task, simplify it, or report the failure up.");
+        HistoryEntry::message(ChatRole::System, content)
+    }
+}
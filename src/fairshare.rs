@@ -0,0 +1,402 @@
+//! Weighted fair sharing of concurrency slots between tasks
+//!
+//! When several tasks run in one session they compete for the same model
+//! call and tool execution slots. A [`FairSharePool`] hands free slots to
+//! the waiting task that currently holds the fewest slots relative to its
+//! weight, so one enormous task cannot monopolize the pool and starve a
+//! small urgent one. An idle pool never holds back a lone task.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use warhorn::TaskId;
+
+use crate::plan::TaskPlan;
+
+/// Weight of tasks that were not given one
+pub const DEFAULT_WEIGHT: u32 = 1;
+
+#[derive(Debug, Clone, Copy)]
+struct TaskShare {
+    weight: u32,
+    in_use: usize,
+    waiting: usize,
+}
+
+impl Default for TaskShare {
+    fn default() -> Self {
+        Self {
+            weight: DEFAULT_WEIGHT,
+            in_use: 0,
+            waiting: 0,
+        }
+    }
+}
+
+impl TaskShare {
+    /// Whether this share is further below its fair share than `other`
+    fn behind(&self, other: &TaskShare) -> bool {
+        (self.in_use as u64) * (other.weight as u64) < (other.in_use as u64) * (self.weight as u64)
+    }
+}
+
+/// How a pool's slots have been queued for
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueStats {
+    /// Slots in the pool, unlimited if None
+    pub capacity: Option<usize>,
+    /// Slots held right now
+    pub in_use: usize,
+    /// Acquisitions waiting for a slot
+    pub waiting: usize,
+    /// Slots granted so far
+    pub granted: u64,
+    /// Grants that had to wait for a slot
+    pub queued: u64,
+    /// Milliseconds spent waiting, summed over all grants
+    pub wait_ms_total: u64,
+    /// Longest single wait for a slot, in milliseconds
+    pub wait_ms_max: u64,
+}
+
+#[derive(Debug)]
+struct Shares {
+    capacity: Option<usize>,
+    in_use: usize,
+    tasks: HashMap<TaskId, TaskShare>,
+    granted: u64,
+    queued: u64,
+    wait_total: Duration,
+    wait_max: Duration,
+}
+
+impl Shares {
+    fn can_grant(&self, task_id: &TaskId) -> bool {
+        if self.capacity.is_some_and(|cap| self.in_use >= cap) {
+            return false;
+        }
+        let Some(share) = self.tasks.get(task_id) else {
+            return true;
+        };
+        // Only yield to waiting tasks that are strictly further behind
+        !self
+            .tasks
+            .iter()
+            .any(|(id, other)| id != task_id && other.waiting > 0 && other.behind(share))
+    }
+
+    fn grant(&mut self, task_id: TaskId) {
+        self.in_use += 1;
+        self.granted += 1;
+        self.tasks.entry(task_id).or_default().in_use += 1;
+    }
+
+    fn record_wait(&mut self, wait: Duration) {
+        self.queued += 1;
+        self.wait_total += wait;
+        self.wait_max = self.wait_max.max(wait);
+    }
+
+    fn release(&mut self, task_id: &TaskId) {
+        self.in_use = self.in_use.saturating_sub(1);
+        if let Some(share) = self.tasks.get_mut(task_id) {
+            share.in_use = share.in_use.saturating_sub(1);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Inner {
+    shares: Mutex<Shares>,
+    released: Notify,
+}
+
+/// A pool of concurrency slots shared fairly between tasks
+#[derive(Debug, Clone)]
+pub struct FairSharePool {
+    inner: Arc<Inner>,
+}
+
+impl FairSharePool {
+    /// Create a pool with `capacity` slots (unlimited if None)
+    pub fn new(capacity: Option<usize>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                shares: Mutex::new(Shares {
+                    capacity,
+                    in_use: 0,
+                    tasks: HashMap::new(),
+                    granted: 0,
+                    queued: 0,
+                    wait_total: Duration::ZERO,
+                    wait_max: Duration::ZERO,
+                }),
+                released: Notify::new(),
+            }),
+        }
+    }
+
+    /// Set a task's weight; a task with weight 3 gets three times the slots of weight 1
+    pub fn set_weight(&self, task_id: TaskId, weight: u32) {
+        self.inner.shares.lock().tasks.entry(task_id).or_default().weight = weight.max(1);
+        self.inner.released.notify_waiters();
+    }
+
+    /// Weight of a task
+    pub fn weight(&self, task_id: &TaskId) -> u32 {
+        self.inner
+            .shares
+            .lock()
+            .tasks
+            .get(task_id)
+            .map_or(DEFAULT_WEIGHT, |s| s.weight)
+    }
+
+    /// Forget a finished task's weight
+    pub fn remove_task(&self, task_id: &TaskId) {
+        let mut shares = self.inner.shares.lock();
+        if shares.tasks.get(task_id).is_some_and(|s| s.in_use == 0 && s.waiting == 0) {
+            shares.tasks.remove(task_id);
+        }
+    }
+
+    /// Take a slot for a task if it is its turn
+    pub fn try_acquire(&self, task_id: TaskId) -> Option<SlotPermit> {
+        let mut shares = self.inner.shares.lock();
+        if !shares.can_grant(&task_id) {
+            return None;
+        }
+        shares.grant(task_id);
+        Some(self.permit(task_id))
+    }
+
+    /// Wait for a slot for a task
+    ///
+    /// Cancel-safe: dropping the future withdraws the task from the queue.
+    pub async fn acquire(&self, task_id: TaskId) -> SlotPermit {
+        self.inner.shares.lock().tasks.entry(task_id).or_default().waiting += 1;
+        let waiting = Waiting {
+            inner: &self.inner,
+            task_id,
+        };
+        let started = Instant::now();
+        let mut queued = false;
+
+        loop {
+            let released = self.inner.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            {
+                let mut shares = self.inner.shares.lock();
+                if shares.can_grant(&task_id) {
+                    shares.grant(task_id);
+                    if queued {
+                        shares.record_wait(started.elapsed());
+                    }
+                    drop(shares);
+                    drop(waiting);
+                    return self.permit(task_id);
+                }
+            }
+
+            released.await;
+            queued = true;
+        }
+    }
+
+    /// Slots currently held by a task
+    pub fn in_use(&self, task_id: &TaskId) -> usize {
+        self.inner.shares.lock().tasks.get(task_id).map_or(0, |s| s.in_use)
+    }
+
+    /// Slots currently held by all tasks
+    pub fn total_in_use(&self) -> usize {
+        self.inner.shares.lock().in_use
+    }
+
+    /// Slots in use and waited for, and how long waits took
+    pub fn stats(&self) -> QueueStats {
+        let shares = self.inner.shares.lock();
+        QueueStats {
+            capacity: shares.capacity,
+            in_use: shares.in_use,
+            waiting: shares.tasks.values().map(|s| s.waiting).sum(),
+            granted: shares.granted,
+            queued: shares.queued,
+            wait_ms_total: shares.wait_total.as_millis() as u64,
+            wait_ms_max: shares.wait_max.as_millis() as u64,
+        }
+    }
+
+    fn permit(&self, task_id: TaskId) -> SlotPermit {
+        SlotPermit {
+            inner: Arc::clone(&self.inner),
+            task_id,
+        }
+    }
+}
+
+/// A pool shared between a session's user tasks, subtasks counting against
+/// the task they belong to
+#[derive(Debug, Clone)]
+pub struct TaskSlots {
+    pool: FairSharePool,
+    /// Plans of the session, to find the user task of a subtask
+    plans: Arc<RwLock<HashMap<TaskId, TaskPlan>>>,
+    /// Share of slots taken outside any task
+    unassigned: TaskId,
+}
+
+impl TaskSlots {
+    pub(crate) fn new(capacity: Option<usize>, plans: Arc<RwLock<HashMap<TaskId, TaskPlan>>>) -> Self {
+        Self {
+            pool: FairSharePool::new(capacity),
+            plans,
+            unassigned: TaskId::new(),
+        }
+    }
+
+    /// Wait for a slot on behalf of a task or one of its subtasks
+    pub async fn acquire(&self, task_id: Option<TaskId>) -> SlotPermit {
+        let root = task_id.map_or(self.unassigned, |id| self.root_task(&id));
+        self.pool.acquire(root).await
+    }
+
+    /// The pool the slots are taken from
+    pub fn pool(&self) -> &FairSharePool {
+        &self.pool
+    }
+
+    /// The user task a subtask belongs to (the task itself if it is one)
+    fn root_task(&self, task_id: &TaskId) -> TaskId {
+        self.plans
+            .read()
+            .values()
+            .find(|p| p.get(task_id).is_some())
+            .map_or(*task_id, |p| p.task_id)
+    }
+}
+
+/// A task's place in the queue, withdrawn on drop
+struct Waiting<'a> {
+    inner: &'a Inner,
+    task_id: TaskId,
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if let Some(share) = self.inner.shares.lock().tasks.get_mut(&self.task_id) {
+            share.waiting = share.waiting.saturating_sub(1);
+        }
+        self.inner.released.notify_waiters();
+    }
+}
+
+/// A held slot, released on drop
+#[derive(Debug)]
+pub struct SlotPermit {
+    inner: Arc<Inner>,
+    task_id: TaskId,
+}
+
+impl SlotPermit {
+    /// Task holding the slot
+    pub fn task_id(&self) -> TaskId {
+        self.task_id
+    }
+}
+
+impl Drop for SlotPermit {
+    fn drop(&mut self) {
+        self.inner.shares.lock().release(&self.task_id);
+        self.inner.released.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lone_task_uses_whole_pool() {
+        let pool = FairSharePool::new(Some(3));
+        let big = TaskId::new();
+
+        let permits: Vec<_> = (0..3).filter_map(|_| pool.try_acquire(big)).collect();
+        assert_eq!(permits.len(), 3);
+        assert!(pool.try_acquire(big).is_none());
+
+        drop(permits);
+        assert_eq!(pool.total_in_use(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_waiting_small_task_goes_first() {
+        let pool = FairSharePool::new(Some(2));
+        let big = TaskId::new();
+        let small = TaskId::new();
+
+        let first = pool.try_acquire(big).unwrap();
+        let _second = pool.try_acquire(big).unwrap();
+
+        let waiter = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.acquire(small).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // The freed slot goes to the starving task, not back to the big one
+        drop(first);
+        let permit = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(permit.task_id(), small);
+        assert!(pool.try_acquire(big).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_weights_split_the_pool() {
+        let pool = FairSharePool::new(Some(4));
+        let heavy = TaskId::new();
+        let light = TaskId::new();
+        pool.set_weight(heavy, 3);
+
+        let _h1 = pool.try_acquire(heavy).unwrap();
+        let _h2 = pool.try_acquire(heavy).unwrap();
+        let l1 = pool.try_acquire(light).unwrap();
+        let _l2 = pool.try_acquire(light).unwrap();
+
+        let light_waiter = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.acquire(light).await })
+        };
+        let heavy_waiter = {
+            let pool = pool.clone();
+            tokio::spawn(async move { pool.acquire(heavy).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // Heavy holds 2 of its 3 parts, light 1 of its 1: heavy is further behind
+        drop(l1);
+        let permit = tokio::time::timeout(Duration::from_secs(1), heavy_waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(permit.task_id(), heavy);
+        assert_eq!(pool.in_use(&heavy), 3);
+        assert!(!light_waiter.is_finished());
+
+        drop(permit);
+        let permit = tokio::time::timeout(Duration::from_secs(1), light_waiter)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(permit.task_id(), light);
+    }
+}
@@ -0,0 +1,244 @@
+//! Event filters for subscriptions
+//!
+//! An [`EventFilter`] lets a consumer that only wants part of the stream,
+//! such as a tree view that ignores streamed message content, skip the
+//! rest. Filters can select agent subtrees, task IDs and event categories;
+//! an event must match every criterion that is set.
+//!
+//! A subtree filter follows the hierarchy as it grows: agents spawned or
+//! moved under the subtree are added to it as their events pass through.
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use warhorn::{AgentId, TaskId};
+
+use crate::hierarchy::HierarchyEntry;
+use crate::protocol::GoblinEvent;
+
+/// Broad kind of an event
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventCategory {
+    /// Agents joining, leaving or changing status, and hierarchy updates
+    Status,
+    /// Agent messages, including streamed content
+    Messages,
+    /// Task lifecycle and results
+    Tasks,
+    /// Everything else, e.g. store changes and query responses
+    Other,
+}
+
+impl EventCategory {
+    /// Category of an event kind, as named by [`GoblinEvent::describe`]
+    pub fn of(kind: &str) -> Self {
+        match kind {
+            "AgentSpawned" | "AgentTerminated" | "AgentStatusChanged" | "AgentJoined" | "AgentPaused"
+            | "AgentResumed" | "AgentRestarted" | "AgentRestarting" | "AgentFailureEscalated" | "AgentForked"
+            | "AgentUnresponsive" | "AgentResponsive" | "AgentStalled" | "CircuitStateChanged" | "HierarchyChanged"
+            | "OrphanAdopted" | "OrphanTerminated" | "TreeDelta" | "HierarchySnapshot" => Self::Status,
+            "AgentMessage" | "AgentHandoff" | "DebateTurn" | "HistoryCompacted" => Self::Messages,
+            "TaskStarted" | "TaskInterrupted" | "TaskResult" | "TaskDeadlineExceeded" | "ResultReviewed"
+            | "ChildrenCompleted" | "ResultMerged" | "MapReduceProgress" | "PipelineStageStarted"
+            | "PipelineStageFinished" | "SpeculationSettled" | "TaskRetried" | "FailureReported"
+            | "FailurePropagated" => Self::Tasks,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// Which events a subscription delivers
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    /// Agents of the selected subtrees seen so far
+    subtree: Option<HashSet<AgentId>>,
+    tasks: Option<HashSet<TaskId>>,
+    categories: Option<HashSet<EventCategory>>,
+}
+
+impl EventFilter {
+    /// A filter that lets every event through
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only status and hierarchy updates
+    pub fn status_only() -> Self {
+        Self::new().category(EventCategory::Status)
+    }
+
+    /// Only agent messages
+    pub fn messages_only() -> Self {
+        Self::new().category(EventCategory::Messages)
+    }
+
+    /// Select events about an agent or its descendants
+    pub fn subtree(mut self, root: AgentId) -> Self {
+        self.subtree.get_or_insert_with(HashSet::new).insert(root);
+        self
+    }
+
+    /// Select events about a task
+    pub fn task(mut self, task_id: TaskId) -> Self {
+        self.tasks.get_or_insert_with(HashSet::new).insert(task_id);
+        self
+    }
+
+    /// Select events of a category
+    pub fn category(mut self, category: EventCategory) -> Self {
+        self.categories.get_or_insert_with(HashSet::new).insert(category);
+        self
+    }
+
+    /// Check an event against the filter, learning new subtree members
+    pub fn matches(&mut self, event: &GoblinEvent) -> bool {
+        if self.subtree.is_none() && self.tasks.is_none() && self.categories.is_none() {
+            return true;
+        }
+        let Some((kind, body)) = event.describe() else {
+            return false;
+        };
+
+        if let Some(subtree) = &mut self.subtree {
+            learn(subtree, &kind, &body);
+            if !agents(&kind, &body).iter().any(|a| subtree.contains(a)) {
+                return false;
+            }
+        }
+        if let Some(tasks) = &self.tasks {
+            let task_id = field(&body, "task_id").or_else(|| body.get("result").and_then(|r| field(r, "task_id")));
+            if !task_id.is_some_and(|t| tasks.contains(&t)) {
+                return false;
+            }
+        }
+        if let Some(categories) = &self.categories {
+            if !categories.contains(&EventCategory::of(&kind)) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// An [`EventFilter`] in serializable form, for clients subscribing over a
+/// transport; empty lists leave that criterion unset
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterSpec {
+    #[serde(default)]
+    pub subtrees: Vec<AgentId>,
+    #[serde(default)]
+    pub tasks: Vec<TaskId>,
+    #[serde(default)]
+    pub categories: Vec<EventCategory>,
+}
+
+impl FilterSpec {
+    pub fn build(&self) -> EventFilter {
+        let mut filter = EventFilter::new();
+        for root in &self.subtrees {
+            filter = filter.subtree(*root);
+        }
+        for task_id in &self.tasks {
+            filter = filter.task(*task_id);
+        }
+        for category in &self.categories {
+            filter = filter.category(*category);
+        }
+        filter
+    }
+}
+
+/// Add agents that joined the subtree in this event
+fn learn(subtree: &mut HashSet<AgentId>, kind: &str, body: &Value) {
+    let joined = |parent: Option<AgentId>| parent.is_some_and(|p| subtree.contains(&p));
+    match kind {
+        "AgentSpawned" if joined(field(body, "parent_id")) => {
+            subtree.extend(field::<AgentId>(body, "agent_id"));
+        }
+        "AgentForked" if joined(field(body, "source")) => {
+            subtree.extend(field::<AgentId>(body, "agent_id"));
+        }
+        "HierarchyChanged" if joined(field(body, "new_parent")) => {
+            subtree.extend(field::<AgentId>(body, "agent_id"));
+        }
+        "TreeDelta" => {
+            // Parents come before their children
+            let added: Vec<HierarchyEntry> = field(body, "added").unwrap_or_default();
+            for entry in added {
+                if entry.parent.is_some_and(|p| subtree.contains(&p)) {
+                    subtree.insert(entry.agent_id);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Agents an event is about
+fn agents(kind: &str, body: &Value) -> Vec<AgentId> {
+    let mut agents: Vec<AgentId> = ["agent_id", "source", "from", "to"]
+        .iter()
+        .filter_map(|name| field(body, name))
+        .collect();
+    if kind == "TreeDelta" {
+        let added: Vec<HierarchyEntry> = field(body, "added").unwrap_or_default();
+        let removed: Vec<AgentId> = field(body, "removed").unwrap_or_default();
+        let changed: Vec<(AgentId, Value)> = field(body, "status_changes").unwrap_or_default();
+        agents.extend(added.into_iter().map(|e| e.agent_id));
+        agents.extend(removed);
+        agents.extend(changed.into_iter().map(|(id, _)| id));
+    }
+    agents
+}
+
+fn field<T: serde::de::DeserializeOwned>(body: &Value, name: &str) -> Option<T> {
+    body.get(name).and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warhorn::{AgentConfig, AgentRole, Event, SubmissionId};
+
+    fn spawned(agent_id: AgentId, parent_id: Option<AgentId>) -> GoblinEvent {
+        Event::AgentSpawned {
+            sub_id: SubmissionId::new(),
+            agent_id,
+            parent_id,
+            role: AgentRole::Worker,
+            config: AgentConfig::default(),
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_subtree_filter_follows_spawns() {
+        let (lead, worker, other) = (AgentId::new(), AgentId::new(), AgentId::new());
+        let mut filter = EventFilter::status_only().subtree(lead);
+
+        assert!(filter.matches(&spawned(worker, Some(lead))));
+        assert!(!filter.matches(&spawned(other, None)));
+        assert!(filter.matches(&GoblinEvent::AgentPaused { sub_id: SubmissionId::new(), agent_id: worker }));
+        assert!(!filter.matches(&GoblinEvent::AgentPaused { sub_id: SubmissionId::new(), agent_id: other }));
+        assert!(!filter.matches(&GoblinEvent::AgentHandoff {
+            sub_id: SubmissionId::new(),
+            from: worker,
+            to: lead,
+            task_id: None,
+        }));
+    }
+
+    #[test]
+    fn test_task_filter() {
+        let task_id = TaskId::new();
+        let mut filter = EventFilter::new().task(task_id);
+        let started = |task_id| GoblinEvent::from(Event::TaskStarted { sub_id: SubmissionId::new(), task_id });
+
+        assert!(filter.matches(&started(task_id)));
+        assert!(!filter.matches(&started(TaskId::new())));
+        assert!(!filter.matches(&spawned(AgentId::new(), None)));
+        assert!(EventFilter::new().matches(&started(TaskId::new())));
+    }
+}
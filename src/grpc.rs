@@ -0,0 +1,296 @@
+//! gRPC service for infrastructure integration
+//!
+//! Maps the op/event protocol onto the `cabal.v1.Cabal` service defined in
+//! `proto/cabal.proto`. Ops are submitted with `Submit`, events stream
+//! from `WatchSession`, and `GetHierarchy` and `GetUsage` answer queries
+//! in a single call by sending the matching `Describe*` op and waiting
+//! for its answer. Payloads are JSON in the shape of the protocol types.
+//!
+//! A service made with [`GrpcService::with_token`] rejects calls whose
+//! `authorization` metadata is not `Bearer <token>` as unauthenticated.
+
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::{Stream, StreamExt};
+use tonic::{Request, Response, Status};
+use trinkets::ToolRegistry;
+use warhorn::{SessionId, SubmissionId};
+
+use crate::channel::{ChannelBuilder, ChannelError, GoblinChannel};
+use crate::error::GoblinError;
+use crate::filter::FilterSpec;
+use crate::orchestrator::Orchestrator;
+use crate::protocol::{GoblinEvent, GoblinOp};
+
+/// Generated protobuf types and service stubs
+pub mod proto {
+    tonic::include_proto!("cabal.v1");
+}
+
+use proto::cabal_server::{Cabal, CabalServer};
+use proto::{
+    EventMessage, HierarchyReply, HierarchyRequest, OpRequest, SubmitReply, UsageReply, UsageRequest,
+    WatchRequest,
+};
+
+/// How long a query waits for the orchestrator's answer by default
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The `Cabal` gRPC service, on a broadcast channel to an orchestrator
+#[derive(Debug, Clone)]
+pub struct GrpcService {
+    channel: GoblinChannel,
+    timeout: Duration,
+    token: Option<String>,
+}
+
+impl GrpcService {
+    /// Serve an orchestrator through `channel`, which must be built in
+    /// broadcast mode
+    pub fn new(channel: GoblinChannel) -> Result<Self, GoblinError> {
+        if channel.subscribe().is_none() {
+            return Err(GoblinError::ConfigError("gRPC service needs a broadcast channel".into()));
+        }
+        Ok(Self {
+            channel,
+            timeout: DEFAULT_TIMEOUT,
+            token: None,
+        })
+    }
+
+    /// Set how long queries wait for the orchestrator's answer
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Require every call to carry `authorization: Bearer <token>`
+    /// metadata
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Wrap the service for a tonic server
+    pub fn into_server(self) -> CabalServer<Self> {
+        CabalServer::new(self)
+    }
+
+    /// Run an orchestrator behind a gRPC server on `addr` until it stops
+    ///
+    /// `setup` gets the orchestrator before the service is added to the
+    /// tonic server, so its options and rules hold from the first RPC.
+    /// The service takes no token, so `addr` should be a loopback address;
+    /// to authenticate clients, serve [`GrpcService::with_token`] through
+    /// [`GrpcService::into_server`] instead.
+    pub async fn run(
+        addr: SocketAddr,
+        tools: ToolRegistry,
+        setup: impl FnOnce(Orchestrator) -> Orchestrator,
+    ) -> Result<(), GoblinError> {
+        let (channel, pair) = ChannelBuilder::new().broadcast(1024).replay(256).build();
+        let orchestrator = setup(Orchestrator::new(tools, pair));
+        let server = tonic::transport::Server::builder()
+            .add_service(Self::new(channel)?.into_server())
+            .serve(addr);
+
+        tokio::select! {
+            result = orchestrator.run() => result,
+            result = server => result.map_err(|e| GoblinError::TransportError(e.to_string())),
+        }
+    }
+
+    /// Reject a call that lacks the configured token
+    fn authorize<T>(&self, request: &Request<T>) -> Result<(), Status> {
+        let Some(token) = &self.token else {
+            return Ok(());
+        };
+        let bearer = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if bearer == Some(token.as_str()) {
+            Ok(())
+        } else {
+            Err(Status::unauthenticated("Invalid or missing token"))
+        }
+    }
+
+    /// Send an op and wait for the event answering it
+    async fn query(&self, op: GoblinOp) -> Result<GoblinEvent, Status> {
+        let sub_id = op.sub_id().clone();
+        // Subscribe first so the answer cannot slip past
+        let mut events = self
+            .channel
+            .subscribe()
+            .ok_or_else(|| Status::unavailable("Not a broadcast channel"))?;
+        self.channel.send(op).map_err(channel_status)?;
+
+        let answer = async {
+            while let Some(event) = events.recv().await {
+                if event.sub_id().as_ref() == Some(&sub_id) {
+                    return Some(event);
+                }
+            }
+            None
+        };
+        tokio::time::timeout(self.timeout, answer)
+            .await
+            .map_err(|_| Status::deadline_exceeded("No answer from the orchestrator"))?
+            .ok_or_else(|| Status::unavailable("Orchestrator stopped"))
+    }
+}
+
+#[tonic::async_trait]
+impl Cabal for GrpcService {
+    async fn submit(&self, request: Request<OpRequest>) -> Result<Response<SubmitReply>, Status> {
+        self.authorize(&request)?;
+        let op: GoblinOp = serde_json::from_str(&request.into_inner().op_json)
+            .map_err(|e| Status::invalid_argument(format!("Invalid op: {}", e)))?;
+        let sub_id = op.sub_id().to_string();
+        self.channel.send(op).map_err(channel_status)?;
+        Ok(Response::new(SubmitReply { sub_id }))
+    }
+
+    type WatchSessionStream = Pin<Box<dyn Stream<Item = Result<EventMessage, Status>> + Send>>;
+
+    async fn watch_session(
+        &self,
+        request: Request<WatchRequest>,
+    ) -> Result<Response<Self::WatchSessionStream>, Status> {
+        self.authorize(&request)?;
+        let filter_json = request.into_inner().filter_json;
+        let spec: FilterSpec = if filter_json.trim().is_empty() {
+            FilterSpec::default()
+        } else {
+            serde_json::from_str(&filter_json)
+                .map_err(|e| Status::invalid_argument(format!("Invalid filter: {}", e)))?
+        };
+        let events = self
+            .channel
+            .subscribe_filtered(spec.build())
+            .ok_or_else(|| Status::unavailable("Not a broadcast channel"))?;
+
+        let stream = events.map(|event| {
+            let kind = event.describe().map(|(kind, _)| kind).unwrap_or_default();
+            let event_json = serde_json::to_string(&event).map_err(|e| Status::internal(e.to_string()))?;
+            Ok(EventMessage { kind, event_json })
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_hierarchy(
+        &self,
+        request: Request<HierarchyRequest>,
+    ) -> Result<Response<HierarchyReply>, Status> {
+        self.authorize(&request)?;
+        let op = GoblinOp::DescribeHierarchy { sub_id: SubmissionId::new() };
+        match self.query(op).await? {
+            GoblinEvent::HierarchySnapshot { snapshot, .. } => Ok(Response::new(HierarchyReply {
+                snapshot_json: to_json(&snapshot)?,
+            })),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    async fn get_usage(&self, request: Request<UsageRequest>) -> Result<Response<UsageReply>, Status> {
+        self.authorize(&request)?;
+        let session_id: SessionId = serde_json::from_value(request.into_inner().session_id.into())
+            .map_err(|e| Status::invalid_argument(format!("Invalid session ID: {}", e)))?;
+        let op = GoblinOp::DescribeMetrics {
+            sub_id: SubmissionId::new(),
+            session_id,
+        };
+        match self.query(op).await? {
+            GoblinEvent::Metrics { metrics, .. } => Ok(Response::new(UsageReply {
+                usage_json: to_json(&metrics.usage)?,
+                metrics_json: to_json(&metrics)?,
+            })),
+            other => Err(unexpected(&other)),
+        }
+    }
+}
+
+fn channel_status(e: ChannelError) -> Status {
+    match e {
+        ChannelError::Closed => Status::unavailable(e.to_string()),
+        ChannelError::Full => Status::resource_exhausted(e.to_string()),
+    }
+}
+
+fn to_json(value: &impl serde::Serialize) -> Result<String, Status> {
+    serde_json::to_string(value).map_err(|e| Status::internal(e.to_string()))
+}
+
+/// An answer of the wrong kind, typically an error the orchestrator reported
+fn unexpected(event: &GoblinEvent) -> Status {
+    let body = serde_json::to_string(event).unwrap_or_default();
+    Status::internal(format!("Unexpected answer: {}", body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::SessionMetrics;
+    use warhorn::Op;
+
+    #[tokio::test]
+    async fn test_submit_forwards_ops() {
+        let (channel, mut pair) = ChannelBuilder::new().broadcast(16).build();
+        let service = GrpcService::new(channel).unwrap();
+
+        let op: GoblinOp = Op::interrupt().into();
+        let request = OpRequest { op_json: serde_json::to_string(&op).unwrap() };
+        let reply = service.submit(Request::new(request)).await.unwrap().into_inner();
+        assert_eq!(reply.sub_id, op.sub_id().to_string());
+        assert_eq!(pair.op_rx.recv().await.unwrap().sub_id(), op.sub_id());
+
+        let bad = OpRequest { op_json: "{}".into() };
+        let status = service.submit(Request::new(bad)).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_usage_query_waits_for_answer() {
+        let (channel, mut pair) = ChannelBuilder::new().broadcast(16).build();
+        let service = GrpcService::new(channel).unwrap().with_timeout(Duration::from_secs(1));
+
+        let mut metrics = SessionMetrics::default();
+        metrics.tasks_started = 3;
+        let answered = metrics.clone();
+        tokio::spawn(async move {
+            while let Some(op) = pair.op_rx.recv().await {
+                if let GoblinOp::DescribeMetrics { sub_id, session_id } = op {
+                    let metrics = answered.clone();
+                    let _ = pair.event_tx.send(GoblinEvent::Metrics { sub_id, session_id, metrics });
+                }
+            }
+        });
+
+        let session_id = SessionId::new();
+        let request = UsageRequest { session_id: session_id.to_string() };
+        let reply = service.get_usage(Request::new(request)).await.unwrap().into_inner();
+        assert_eq!(reply.metrics_json, serde_json::to_string(&metrics).unwrap());
+        assert_eq!(reply.usage_json, serde_json::to_string(&metrics.usage).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_token_is_required_when_set() {
+        let (channel, _pair) = ChannelBuilder::new().broadcast(16).build();
+        let service = GrpcService::new(channel).unwrap().with_token("secret");
+        let op: GoblinOp = Op::interrupt().into();
+        let request = || OpRequest { op_json: serde_json::to_string(&op).unwrap() };
+
+        let status = service.submit(Request::new(request())).await.unwrap_err();
+        assert_eq!(status.code(), tonic::Code::Unauthenticated);
+
+        let mut authorized = Request::new(request());
+        authorized
+            .metadata_mut()
+            .insert("authorization", "Bearer secret".parse().unwrap());
+        assert!(service.submit(authorized).await.is_ok());
+    }
+}
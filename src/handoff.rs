@@ -0,0 +1,93 @@
+//! Handing context over when an agent goes away
+//!
+//! When an agent is terminated or its run loop fails, a [`Handoff`]
+//! captures what it had learned: the tail of its history and the results
+//! it reported. The handoff goes to the agent replacing it, if any, and
+//! otherwise to its parent's mailbox, so the findings are not lost.
+
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentId, TaskId};
+
+use crate::agent::AgentHandle;
+use crate::history::HistoryEntry;
+use crate::model::ChatRole;
+use crate::result::TaskResult;
+
+/// History entries carried over in a handoff
+pub const HANDOFF_ENTRIES: usize = 8;
+
+/// Longest excerpt of a single entry carried over
+const MAX_EXCERPT_CHARS: usize = 500;
+
+/// Context captured from an agent that went away
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handoff {
+    /// Agent the context was captured from
+    pub from: AgentId,
+    /// Task the agent was working on
+    pub task_id: Option<TaskId>,
+    /// Why the agent went away
+    pub reason: String,
+    /// Digest of the end of the agent's history
+    pub summary: String,
+    /// Results the agent reported
+    pub results: Vec<TaskResult>,
+}
+
+impl Handoff {
+    /// Capture an agent's context, or None if it has nothing to hand over
+    pub fn capture(agent: &AgentHandle, results: Vec<TaskResult>, reason: impl Into<String>) -> Option<Self> {
+        let task_id = agent.current_task();
+        let summary = history_digest(agent);
+        if summary.is_empty() && results.is_empty() && task_id.is_none() {
+            return None;
+        }
+
+        Some(Self {
+            from: agent.id(),
+            task_id,
+            reason: reason.into(),
+            summary,
+            results,
+        })
+    }
+
+    /// The handoff as a history entry for the receiving agent
+    pub fn to_entry(&self) -> HistoryEntry {
+        let mut content = format!("Handoff from agent {} ({})", self.from, self.reason);
+        if let Some(task_id) = self.task_id {
+            content.push_str(&format!(", which was working on task {}", task_id));
+        }
+        if !self.summary.is_empty() {
+            content.push_str(&format!(".\n\nIts latest context:\n{}", self.summary));
+        }
+        for result in &self.results {
+            content.push_str(&format!(
+                "\n\nResult for task {} ({:?}): {}",
+                result.task_id, result.status, excerpt(&result.summary)
+            ));
+        }
+        HistoryEntry::message(ChatRole::System, content)
+    }
+}
+
+/// Digest of the end of an agent's history, one excerpt per entry
+pub(crate) fn history_digest(agent: &AgentHandle) -> String {
+    let history = agent.history();
+    let skip = history.len().saturating_sub(HANDOFF_ENTRIES);
+    history.entries()[skip..]
+        .iter()
+        .map(|entry| {
+            let message = entry.to_message();
+            format!("[{:?}] {}", message.role, excerpt(&message.content))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub(crate) fn excerpt(text: &str) -> String {
+    match text.char_indices().nth(MAX_EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,95 @@
+//! Heartbeats and dead-agent detection
+//!
+//! With a [`HeartbeatPolicy`] in the session options, every agent's run
+//! loop beats at the policy's interval, between pieces of mail and while a
+//! task is being worked on. A watchdog checks the beats at the same
+//! interval: an agent that missed `missed` beats in a row, because its run
+//! loop died or is blocked, is marked unresponsive and reported in an
+//! `AgentUnresponsive` event; once it beats again it is reported in an
+//! `AgentResponsive` event. Paused agents are not watched.
+//!
+//! Beats come from the run loop, not from the runtime, so they show the
+//! loop is alive rather than that the task moves: a runtime hung inside
+//! a task keeps beating. Those are caught by [stall
+//! detection](crate::stall), which watches the agent's progress instead.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::error::GoblinError;
+
+/// How often agents beat and how many beats they may miss
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeartbeatPolicy {
+    /// Time between beats, in milliseconds
+    pub interval_ms: u64,
+    /// Beats missed in a row before an agent is unresponsive
+    pub missed: u32,
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self {
+            interval_ms: 5_000,
+            missed: 3,
+        }
+    }
+}
+
+impl HeartbeatPolicy {
+    pub fn new(interval: Duration, missed: u32) -> Self {
+        Self {
+            interval_ms: interval.as_millis() as u64,
+            missed,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+
+    /// Silence after which an agent is unresponsive
+    pub fn timeout(&self) -> Duration {
+        self.interval() * self.missed
+    }
+
+    /// Ticker for beats or watchdog checks
+    pub(crate) fn ticker(&self) -> Interval {
+        let mut ticker = tokio::time::interval(self.interval());
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.interval_ms == 0 || self.missed == 0 {
+            return Err(GoblinError::ConfigError(
+                "heartbeat interval_ms and missed must be at least 1".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Wait for the next tick, or forever without a ticker
+pub(crate) async fn tick(ticker: &mut Option<Interval>) {
+    match ticker {
+        Some(ticker) => {
+            ticker.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_counts_missed_beats() {
+        let policy = HeartbeatPolicy::new(Duration::from_millis(200), 3);
+        assert_eq!(policy.timeout(), Duration::from_millis(600));
+        assert!(HeartbeatPolicy::new(Duration::ZERO, 3).validate().is_err());
+    }
+}
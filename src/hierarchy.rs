@@ -1,9 +1,13 @@
 //! Agent hierarchy management
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use warhorn::{AgentId, AgentRole, AgentStatus, AgentTree};
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentId, AgentRole, AgentStatus, AgentTree, TokenUsage};
 use crate::agent::AgentHandle;
+use crate::error::GoblinError;
+use crate::render;
+use crate::result::ResultStatus;
 
 /// Node in the agent hierarchy
 #[derive(Debug, Clone)]
@@ -12,6 +16,103 @@ struct HierarchyNode {
     role: AgentRole,
     parent: Option<AgentId>,
     children: Vec<AgentId>,
+    /// Distance from the root
+    depth: usize,
+    /// Most children the agent may have (unlimited if None)
+    max_children: Option<usize>,
+}
+
+/// One agent in a [`HierarchySnapshot`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HierarchyEntry {
+    pub agent_id: AgentId,
+    pub parent: Option<AgentId>,
+    pub role: AgentRole,
+    pub status: AgentStatus,
+    pub depth: usize,
+    pub children: Vec<AgentId>,
+}
+
+/// Flat, serializable view of the hierarchy, agents in depth-first order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HierarchySnapshot {
+    pub root: Option<AgentId>,
+    pub agents: Vec<HierarchyEntry>,
+}
+
+/// Progress and spend of an agent together with everything below it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubtreeSummary {
+    pub agents: usize,
+    /// Agents with a task in hand
+    pub working: usize,
+    /// Live agents without a task
+    pub idle: usize,
+    pub paused: usize,
+    /// Tasks reported as succeeded
+    pub succeeded: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    pub usage: TokenUsage,
+}
+
+impl SubtreeSummary {
+    /// Summary of a single agent, without its results
+    pub fn of_agent(agent: &AgentHandle) -> Self {
+        let live = agent.status() != AgentStatus::Terminated;
+        let working = live && agent.current_task().is_some();
+        Self {
+            agents: 1,
+            working: working as usize,
+            idle: (live && !working) as usize,
+            paused: agent.is_paused() as usize,
+            usage: agent.usage(),
+            ..Default::default()
+        }
+    }
+
+    /// Count a task result reported by the subtree
+    pub fn count_result(&mut self, status: ResultStatus) {
+        match status {
+            ResultStatus::Succeeded => self.succeeded += 1,
+            ResultStatus::Failed => self.failed += 1,
+            ResultStatus::Cancelled => self.cancelled += 1,
+        }
+    }
+
+    /// Add another summary into this one
+    pub fn merge(&mut self, other: &SubtreeSummary) {
+        self.agents += other.agents;
+        self.working += other.working;
+        self.idle += other.idle;
+        self.paused += other.paused;
+        self.succeeded += other.succeeded;
+        self.failed += other.failed;
+        self.cancelled += other.cancelled;
+        self.usage.input_tokens += other.usage.input_tokens;
+        self.usage.output_tokens += other.usage.output_tokens;
+        self.usage.total_tokens += other.usage.total_tokens;
+    }
+}
+
+/// Short progress line, e.g. `3 working / 2 done / 1 failed, 1200 tokens`
+impl std::fmt::Display for SubtreeSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts = [
+            (self.working, "working"),
+            (self.idle, "idle"),
+            (self.paused, "paused"),
+            (self.succeeded, "done"),
+            (self.failed, "failed"),
+            (self.cancelled, "cancelled"),
+        ];
+        let parts: Vec<String> = counts
+            .iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, label)| format!("{} {}", count, label))
+            .collect();
+        write!(f, "{}, {} tokens", parts.join(" / "), self.usage.total_tokens)
+    }
 }
 
 /// Manages the agent hierarchy tree
@@ -20,6 +121,8 @@ pub struct AgentHierarchy {
     nodes: HashMap<AgentId, HierarchyNode>,
     /// Root agent ID (orchestrator)
     root: Option<AgentId>,
+    /// Agent IDs by depth
+    levels: Vec<HashSet<AgentId>>,
 }
 
 impl AgentHierarchy {
@@ -28,27 +131,46 @@ impl AgentHierarchy {
         Self {
             nodes: HashMap::new(),
             root: None,
+            levels: Vec::new(),
         }
     }
 
     /// Add an agent to the hierarchy
+    ///
+    /// Fails if the agent is already present, if the parent is unknown, or
+    /// if an agent without a parent is added while there is a root.
     pub fn add_agent(
         &mut self,
         agent_id: AgentId,
         role: AgentRole,
         parent_id: Option<AgentId>,
-    ) {
-        // If no parent, this is the root
-        if parent_id.is_none() {
-            self.root = Some(agent_id);
+    ) -> Result<(), GoblinError> {
+        if self.nodes.contains_key(&agent_id) {
+            return Err(GoblinError::HierarchyError(format!(
+                "Agent {} is already in the hierarchy",
+                agent_id
+            )));
         }
 
-        // Add to parent's children
-        if let Some(pid) = &parent_id {
-            if let Some(parent) = self.nodes.get_mut(pid) {
+        let depth = match &parent_id {
+            // Add to parent's children
+            Some(pid) => {
+                let parent = self.nodes.get_mut(pid).ok_or(GoblinError::AgentNotFound(*pid))?;
                 parent.children.push(agent_id);
+                parent.depth + 1
             }
-        }
+            // If no parent, this is the root
+            None => {
+                if let Some(root) = self.root {
+                    return Err(GoblinError::HierarchyError(format!(
+                        "Hierarchy already has root {}",
+                        root
+                    )));
+                }
+                self.root = Some(agent_id);
+                0
+            }
+        };
 
         // Create the node
         let node = HierarchyNode {
@@ -56,14 +178,23 @@ impl AgentHierarchy {
             role,
             parent: parent_id,
             children: Vec::new(),
+            depth,
+            max_children: None,
         };
 
         self.nodes.insert(agent_id, node);
+        self.index_depth(agent_id, depth);
+        Ok(())
     }
 
     /// Remove an agent from the hierarchy
+    ///
+    /// The agent's children are left in place; use [`remove_subtree`](Self::remove_subtree)
+    /// to remove them too.
     pub fn remove_agent(&mut self, agent_id: &AgentId) -> bool {
         if let Some(node) = self.nodes.remove(agent_id) {
+            self.unindex_depth(agent_id, node.depth);
+
             // Remove from parent's children
             if let Some(pid) = &node.parent {
                 if let Some(parent) = self.nodes.get_mut(pid) {
@@ -82,6 +213,98 @@ impl AgentHierarchy {
         }
     }
 
+    /// Remove an agent and all of its descendants
+    ///
+    /// Returns the removed agents, each before its children; empty if the
+    /// agent is unknown.
+    pub fn remove_subtree(&mut self, agent_id: &AgentId) -> Vec<AgentId> {
+        if !self.nodes.contains_key(agent_id) {
+            return Vec::new();
+        }
+
+        let mut removed = vec![*agent_id];
+        removed.extend(self.descendants(agent_id));
+
+        if let Some(pid) = self.parent(agent_id) {
+            if let Some(parent) = self.nodes.get_mut(&pid) {
+                parent.children.retain(|id| id != agent_id);
+            }
+        }
+        for id in &removed {
+            if let Some(node) = self.nodes.remove(id) {
+                self.unindex_depth(id, node.depth);
+            }
+        }
+        if self.root.is_some_and(|root| removed.contains(&root)) {
+            self.root = None;
+        }
+
+        removed
+    }
+
+    /// Limit the number of children an agent may have
+    pub fn set_max_children(&mut self, agent_id: &AgentId, max_children: Option<usize>) {
+        if let Some(node) = self.nodes.get_mut(agent_id) {
+            node.max_children = max_children;
+        }
+    }
+
+    /// Move an agent, with its subtree, under a new parent
+    ///
+    /// Fails without changing anything if either agent is unknown, if the
+    /// new parent is the agent itself or one of its descendants, or if the
+    /// new parent has no room for another child. Returns the old parent.
+    pub fn reparent(&mut self, agent_id: &AgentId, new_parent: &AgentId) -> Result<Option<AgentId>, GoblinError> {
+        let old_parent = self.nodes.get(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?.parent;
+        let parent = self.nodes.get(new_parent).ok_or(GoblinError::AgentNotFound(*new_parent))?;
+        if old_parent == Some(*new_parent) {
+            return Ok(old_parent);
+        }
+
+        if new_parent == agent_id || self.is_descendant_of(new_parent, agent_id) {
+            return Err(GoblinError::HierarchyError(format!(
+                "Moving agent {} under {} would create a cycle",
+                agent_id, new_parent
+            )));
+        }
+        if parent.max_children.is_some_and(|max| parent.children.len() >= max) {
+            return Err(GoblinError::HierarchyError(format!(
+                "Agent {} cannot take more children",
+                new_parent
+            )));
+        }
+
+        if let Some(old) = old_parent.and_then(|pid| self.nodes.get_mut(&pid)) {
+            old.children.retain(|id| id != agent_id);
+        }
+        let depth = match self.nodes.get_mut(new_parent) {
+            Some(parent) => {
+                parent.children.push(*agent_id);
+                parent.depth + 1
+            }
+            None => 0,
+        };
+        if let Some(node) = self.nodes.get_mut(agent_id) {
+            node.parent = Some(*new_parent);
+        }
+        if self.root == Some(*agent_id) {
+            self.root = None;
+        }
+
+        // The whole subtree moves by the same number of levels
+        let moved: Vec<(AgentId, usize)> = DepthFirst::from(self, Some(*agent_id))
+            .map(|(id, offset, _)| (id, depth + offset))
+            .collect();
+        for (id, depth) in moved {
+            if let Some(node) = self.nodes.get_mut(&id) {
+                let old = std::mem::replace(&mut node.depth, depth);
+                self.unindex_depth(&id, old);
+                self.index_depth(id, depth);
+            }
+        }
+        Ok(old_parent)
+    }
+
     /// Get the root agent ID
     pub fn root(&self) -> Option<AgentId> {
         self.root
@@ -92,6 +315,32 @@ impl AgentHierarchy {
         self.nodes.get(agent_id).and_then(|n| n.parent)
     }
 
+    /// Ancestors of an agent, from its parent up to the root
+    pub fn ancestors(&self, agent_id: &AgentId) -> Vec<AgentId> {
+        let mut ancestors = Vec::new();
+        let mut current = self.parent(agent_id);
+        while let Some(id) = current {
+            ancestors.push(id);
+            current = self.parent(&id);
+        }
+        ancestors
+    }
+
+    /// Descendants of an agent, each before its own children
+    pub fn descendants(&self, agent_id: &AgentId) -> Vec<AgentId> {
+        DepthFirst::from(self, Some(*agent_id)).skip(1).map(|(id, _, _)| id).collect()
+    }
+
+    /// Check if an agent is somewhere below another
+    pub fn is_descendant_of(&self, agent_id: &AgentId, ancestor: &AgentId) -> bool {
+        self.ancestors(agent_id).contains(ancestor)
+    }
+
+    /// Get the role of an agent
+    pub fn role(&self, agent_id: &AgentId) -> Option<AgentRole> {
+        self.nodes.get(agent_id).map(|n| n.role.clone())
+    }
+
     /// Get children of an agent
     pub fn children(&self, agent_id: &AgentId) -> Vec<AgentId> {
         self.nodes.get(agent_id).map(|n| n.children.clone()).unwrap_or_default()
@@ -99,72 +348,158 @@ impl AgentHierarchy {
 
     /// Get depth of an agent in the tree
     pub fn depth(&self, agent_id: &AgentId) -> usize {
-        let mut depth = 0;
-        let mut current = Some(*agent_id);
-        
-        while let Some(id) = current {
-            if let Some(node) = self.nodes.get(&id) {
-                current = node.parent;
-                if current.is_some() {
-                    depth += 1;
-                }
-            } else {
-                break;
-            }
-        }
-        
-        depth
+        self.nodes.get(agent_id).map(|n| n.depth).unwrap_or(0)
     }
 
     /// Get all agents at a specific depth
     pub fn agents_at_depth(&self, depth: usize) -> Vec<AgentId> {
-        self.nodes.keys()
-            .filter(|id| self.depth(id) == depth)
-            .copied()
-            .collect()
+        self.levels.get(depth).map(|level| level.iter().copied().collect()).unwrap_or_default()
     }
 
-    /// Convert to protocol AgentTree format
-    pub fn to_tree(&self, agents: &HashMap<AgentId, AgentHandle>) -> AgentTree {
-        self.build_tree_node(self.root, agents)
+    fn index_depth(&mut self, agent_id: AgentId, depth: usize) {
+        if self.levels.len() <= depth {
+            self.levels.resize_with(depth + 1, HashSet::new);
+        }
+        self.levels[depth].insert(agent_id);
+    }
+
+    fn unindex_depth(&mut self, agent_id: &AgentId, depth: usize) {
+        if let Some(level) = self.levels.get_mut(depth) {
+            level.remove(agent_id);
+        }
+        while self.levels.last().is_some_and(|level| level.is_empty()) {
+            self.levels.pop();
+        }
+    }
+
+    /// Convert to protocol AgentTree format, or None if there is no root
+    pub fn to_tree(&self, agents: &HashMap<AgentId, AgentHandle>) -> Option<AgentTree> {
+        self.root.map(|root| self.build_tree_node(root, agents))
     }
 
     fn build_tree_node(
         &self,
-        agent_id: Option<AgentId>,
+        id: AgentId,
         agents: &HashMap<AgentId, AgentHandle>,
     ) -> AgentTree {
-        match agent_id {
-            Some(id) => {
-                let agent = agents.get(&id);
-                let node = self.nodes.get(&id);
-                
-                let children: Vec<AgentTree> = node
-                    .map(|n| &n.children)
-                    .unwrap_or(&Vec::new())
-                    .iter()
-                    .map(|child_id| self.build_tree_node(Some(*child_id), agents))
-                    .collect();
-
-                AgentTree {
-                    agent_id: id,
-                    role: node.map(|n| n.role.clone()).unwrap_or_default(),
-                    status: agent.map(|a| a.status()).unwrap_or(AgentStatus::Terminated),
-                    task_summary: None,
-                    children,
+        let agent = agents.get(&id);
+        let node = self.nodes.get(&id);
+
+        let children: Vec<AgentTree> = node
+            .map(|n| &n.children)
+            .unwrap_or(&Vec::new())
+            .iter()
+            .map(|child_id| self.build_tree_node(*child_id, agents))
+            .collect();
+
+        AgentTree {
+            agent_id: id,
+            role: node.map(|n| n.role.clone()).unwrap_or_default(),
+            status: agent.map(|a| a.status()).unwrap_or(AgentStatus::Terminated),
+            task_summary: None,
+            children,
+        }
+    }
+
+    /// Walk the tree depth-first from the root, each agent before its children
+    pub fn iter_dfs(&self) -> DepthFirst<'_> {
+        DepthFirst::from(self, self.root)
+    }
+
+    /// Walk the tree breadth-first from the root, level by level
+    pub fn iter_bfs(&self) -> BreadthFirst<'_> {
+        let mut queue = VecDeque::new();
+        queue.extend(self.root.map(|id| (id, 0)));
+        BreadthFirst { hierarchy: self, queue }
+    }
+
+    /// Check that parent and child links agree and every agent is reachable from the root
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        let broken = |msg: String| Err(GoblinError::HierarchyError(msg));
+
+        if let Some(root) = self.root {
+            match self.nodes.get(&root) {
+                None => return broken(format!("Root {} is not in the hierarchy", root)),
+                Some(node) if node.parent.is_some() => return broken(format!("Root {} has a parent", root)),
+                Some(_) => {}
+            }
+        }
+
+        for node in self.nodes.values() {
+            match node.parent {
+                Some(pid) => match self.nodes.get(&pid) {
+                    None => return broken(format!("Agent {} has unknown parent {}", node.agent_id, pid)),
+                    Some(parent) if !parent.children.contains(&node.agent_id) => {
+                        return broken(format!("Agent {} is missing from the children of {}", node.agent_id, pid));
+                    }
+                    Some(parent) if node.depth != parent.depth + 1 => {
+                        return broken(format!("Agent {} has a stale depth", node.agent_id));
+                    }
+                    Some(_) => {}
+                },
+                None if self.root != Some(node.agent_id) => {
+                    return broken(format!("Agent {} has no parent but is not the root", node.agent_id));
                 }
+                None => {}
             }
-            None => {
-                // Empty tree
-                AgentTree {
-                    agent_id: AgentId::new(),
-                    role: AgentRole::Worker,
-                    status: AgentStatus::Terminated,
-                    task_summary: None,
-                    children: vec![],
+
+            for (i, child) in node.children.iter().enumerate() {
+                if node.children[..i].contains(child) {
+                    return broken(format!("Agent {} lists child {} twice", node.agent_id, child));
+                }
+                if self.parent(child) != Some(node.agent_id) {
+                    return broken(format!("Agent {} lists {} as a child, which has another parent", node.agent_id, child));
                 }
             }
         }
+
+        // With consistent links, anything unreachable from the root sits on a cycle
+        let reachable = self.iter_dfs().count();
+        if reachable != self.nodes.len() {
+            return broken(format!("{} agents are not reachable from the root", self.nodes.len() - reachable));
+        }
+        Ok(())
+    }
+
+    /// Flat view of the hierarchy with statuses looked up in `agents`
+    ///
+    /// Agents missing from the map are reported as terminated.
+    pub fn snapshot(&self, agents: &HashMap<AgentId, AgentHandle>) -> HierarchySnapshot {
+        let agents = self
+            .iter_dfs()
+            .filter_map(|(id, _, _)| {
+                let status = agents.get(&id).map(|a| a.status()).unwrap_or(AgentStatus::Terminated);
+                self.entry(&id, status)
+            })
+            .collect();
+        HierarchySnapshot { root: self.root, agents }
+    }
+
+    /// A single agent's entry, with the given status
+    pub fn entry(&self, agent_id: &AgentId, status: AgentStatus) -> Option<HierarchyEntry> {
+        self.nodes.get(agent_id).map(|node| HierarchyEntry {
+            agent_id: *agent_id,
+            parent: node.parent,
+            role: node.role.clone(),
+            status,
+            depth: node.depth,
+            children: node.children.clone(),
+        })
+    }
+
+    /// Render the hierarchy as a Graphviz DOT digraph
+    pub fn to_dot(&self, agents: &HashMap<AgentId, AgentHandle>) -> String {
+        render::hierarchy_to_dot(self, agents)
+    }
+
+    /// Render the hierarchy as a Mermaid flowchart
+    pub fn to_mermaid(&self, agents: &HashMap<AgentId, AgentHandle>) -> String {
+        render::hierarchy_to_mermaid(self, agents)
+    }
+
+    /// The hierarchy snapshot as JSON
+    pub fn to_json(&self, agents: &HashMap<AgentId, AgentHandle>) -> String {
+        serde_json::to_string(&self.snapshot(agents)).expect("hierarchy snapshots always serialize")
     }
 
     /// Get total agent count
@@ -178,6 +513,55 @@ impl AgentHierarchy {
     }
 }
 
+/// Depth-first walk over the hierarchy, yielding `(agent, depth, role)`
+pub struct DepthFirst<'a> {
+    hierarchy: &'a AgentHierarchy,
+    stack: Vec<(AgentId, usize)>,
+}
+
+impl<'a> DepthFirst<'a> {
+    fn from(hierarchy: &'a AgentHierarchy, start: Option<AgentId>) -> Self {
+        Self {
+            hierarchy,
+            stack: start.map(|id| (id, 0)).into_iter().collect(),
+        }
+    }
+}
+
+impl<'a> Iterator for DepthFirst<'a> {
+    type Item = (AgentId, usize, &'a AgentRole);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((id, depth)) = self.stack.pop() {
+            if let Some(node) = self.hierarchy.nodes.get(&id) {
+                self.stack.extend(node.children.iter().rev().map(|c| (*c, depth + 1)));
+                return Some((id, depth, &node.role));
+            }
+        }
+        None
+    }
+}
+
+/// Breadth-first walk over the hierarchy, yielding `(agent, depth, role)`
+pub struct BreadthFirst<'a> {
+    hierarchy: &'a AgentHierarchy,
+    queue: VecDeque<(AgentId, usize)>,
+}
+
+impl<'a> Iterator for BreadthFirst<'a> {
+    type Item = (AgentId, usize, &'a AgentRole);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((id, depth)) = self.queue.pop_front() {
+            if let Some(node) = self.hierarchy.nodes.get(&id) {
+                self.queue.extend(node.children.iter().map(|c| (*c, depth + 1)));
+                return Some((id, depth, &node.role));
+            }
+        }
+        None
+    }
+}
+
 impl Default for AgentHierarchy {
     fn default() -> Self {
         Self::new()
@@ -211,7 +595,7 @@ mod tests {
         let mut hierarchy = AgentHierarchy::new();
         let root_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
         
         assert_eq!(hierarchy.len(), 1);
         assert_eq!(hierarchy.root(), Some(root_id));
@@ -226,9 +610,9 @@ mod tests {
         let child1_id = AgentId::new();
         let child2_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child1_id, AgentRole::Worker, Some(root_id));
-        hierarchy.add_agent(child2_id, AgentRole::Worker, Some(root_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child1_id, AgentRole::Worker, Some(root_id)).unwrap();
+        hierarchy.add_agent(child2_id, AgentRole::Worker, Some(root_id)).unwrap();
         
         assert_eq!(hierarchy.len(), 3);
         assert_eq!(hierarchy.root(), Some(root_id));
@@ -243,9 +627,9 @@ mod tests {
         let child_id = AgentId::new();
         let grandchild_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child_id, AgentRole::DomainLead { domain: "frontend".into() }, Some(root_id));
-        hierarchy.add_agent(grandchild_id, AgentRole::Worker, Some(child_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child_id, AgentRole::DomainLead { domain: "frontend".into() }, Some(root_id)).unwrap();
+        hierarchy.add_agent(grandchild_id, AgentRole::Worker, Some(child_id)).unwrap();
         
         assert_eq!(hierarchy.len(), 3);
         assert_eq!(hierarchy.children(&root_id).len(), 1);
@@ -253,6 +637,70 @@ mod tests {
         assert_eq!(hierarchy.children(&grandchild_id).len(), 0);
     }
 
+    #[test]
+    fn test_add_rejects_broken_links() {
+        let mut hierarchy = AgentHierarchy::new();
+        let root_id = AgentId::new();
+        let orphan_id = AgentId::new();
+
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+
+        assert!(matches!(
+            hierarchy.add_agent(orphan_id, AgentRole::Worker, Some(AgentId::new())),
+            Err(GoblinError::AgentNotFound(_))
+        ));
+        assert!(hierarchy.add_agent(orphan_id, AgentRole::Orchestrator, None).is_err());
+        assert!(hierarchy.add_agent(root_id, AgentRole::Worker, Some(root_id)).is_err());
+        assert_eq!(hierarchy.len(), 1);
+        assert_eq!(hierarchy.root(), Some(root_id));
+        assert!(hierarchy.children(&root_id).is_empty());
+    }
+
+    // === Validation Tests ===
+
+    #[test]
+    fn test_validate_consistent_hierarchy() {
+        let mut hierarchy = AgentHierarchy::new();
+        assert!(hierarchy.validate().is_ok());
+
+        let root_id = AgentId::new();
+        let lead_id = AgentId::new();
+        let worker_id = AgentId::new();
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead_id, AgentRole::DomainLead { domain: "docs".into() }, Some(root_id)).unwrap();
+        hierarchy.add_agent(worker_id, AgentRole::Worker, Some(lead_id)).unwrap();
+        hierarchy.reparent(&worker_id, &root_id).unwrap();
+        assert!(hierarchy.validate().is_ok());
+
+        hierarchy.remove_subtree(&lead_id);
+        assert!(hierarchy.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_detects_broken_links() {
+        let mut hierarchy = AgentHierarchy::new();
+        let root_id = AgentId::new();
+        let lead_id = AgentId::new();
+        let worker_id = AgentId::new();
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead_id, AgentRole::DomainLead { domain: "docs".into() }, Some(root_id)).unwrap();
+        hierarchy.add_agent(worker_id, AgentRole::Worker, Some(lead_id)).unwrap();
+
+        // Removing only the lead leaves the worker with a dangling parent
+        hierarchy.remove_agent(&lead_id);
+        assert!(matches!(hierarchy.validate(), Err(GoblinError::HierarchyError(_))));
+
+        // A cycle cut off from the root
+        let mut hierarchy = AgentHierarchy::new();
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead_id, AgentRole::Worker, Some(root_id)).unwrap();
+        hierarchy.add_agent(worker_id, AgentRole::Worker, Some(lead_id)).unwrap();
+        hierarchy.nodes.get_mut(&root_id).unwrap().children.clear();
+        hierarchy.nodes.get_mut(&lead_id).unwrap().parent = Some(worker_id);
+        hierarchy.nodes.get_mut(&worker_id).unwrap().children.push(lead_id);
+        assert!(hierarchy.validate().is_err());
+    }
+
     // === Remove Agent Tests ===
 
     #[test]
@@ -262,8 +710,8 @@ mod tests {
         let root_id = AgentId::new();
         let child_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id)).unwrap();
         
         assert!(hierarchy.remove_agent(&child_id));
         assert_eq!(hierarchy.len(), 1);
@@ -275,7 +723,7 @@ mod tests {
         let mut hierarchy = AgentHierarchy::new();
         let root_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
         
         assert!(hierarchy.remove_agent(&root_id));
         assert!(hierarchy.root().is_none());
@@ -298,9 +746,9 @@ mod tests {
         let child1_id = AgentId::new();
         let child2_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child1_id, AgentRole::Worker, Some(root_id));
-        hierarchy.add_agent(child2_id, AgentRole::Worker, Some(root_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child1_id, AgentRole::Worker, Some(root_id)).unwrap();
+        hierarchy.add_agent(child2_id, AgentRole::Worker, Some(root_id)).unwrap();
         
         hierarchy.remove_agent(&child1_id);
         
@@ -310,6 +758,109 @@ mod tests {
         assert!(!children.contains(&child1_id));
     }
 
+    // === Remove Subtree Tests ===
+
+    #[test]
+    fn test_remove_subtree() {
+        let mut hierarchy = AgentHierarchy::new();
+
+        let root = AgentId::new();
+        let lead = AgentId::new();
+        let worker1 = AgentId::new();
+        let worker2 = AgentId::new();
+        let other = AgentId::new();
+
+        hierarchy.add_agent(root, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead, AgentRole::DomainLead { domain: "backend".into() }, Some(root)).unwrap();
+        hierarchy.add_agent(worker1, AgentRole::Worker, Some(lead)).unwrap();
+        hierarchy.add_agent(worker2, AgentRole::Worker, Some(lead)).unwrap();
+        hierarchy.add_agent(other, AgentRole::Worker, Some(root)).unwrap();
+
+        assert_eq!(hierarchy.remove_subtree(&lead), vec![lead, worker1, worker2]);
+        assert_eq!(hierarchy.len(), 2);
+        assert_eq!(hierarchy.children(&root), vec![other]);
+        assert!(hierarchy.parent(&worker1).is_none());
+    }
+
+    #[test]
+    fn test_remove_subtree_at_root() {
+        let mut hierarchy = AgentHierarchy::new();
+
+        let root = AgentId::new();
+        let child = AgentId::new();
+
+        hierarchy.add_agent(root, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child, AgentRole::Worker, Some(root)).unwrap();
+
+        assert_eq!(hierarchy.remove_subtree(&root).len(), 2);
+        assert!(hierarchy.is_empty());
+        assert!(hierarchy.root().is_none());
+        assert!(hierarchy.remove_subtree(&root).is_empty());
+    }
+
+    // === Reparent Tests ===
+
+    #[test]
+    fn test_reparent_moves_subtree() {
+        let mut hierarchy = AgentHierarchy::new();
+
+        let root = AgentId::new();
+        let lead1 = AgentId::new();
+        let lead2 = AgentId::new();
+        let worker = AgentId::new();
+        let helper = AgentId::new();
+
+        hierarchy.add_agent(root, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead1, AgentRole::DomainLead { domain: "frontend".into() }, Some(root)).unwrap();
+        hierarchy.add_agent(lead2, AgentRole::DomainLead { domain: "backend".into() }, Some(root)).unwrap();
+        hierarchy.add_agent(worker, AgentRole::Worker, Some(lead1)).unwrap();
+        hierarchy.add_agent(helper, AgentRole::Worker, Some(worker)).unwrap();
+
+        assert_eq!(hierarchy.reparent(&worker, &lead2).unwrap(), Some(lead1));
+        assert!(hierarchy.children(&lead1).is_empty());
+        assert_eq!(hierarchy.children(&lead2), vec![worker]);
+        assert_eq!(hierarchy.parent(&worker), Some(lead2));
+        assert_eq!(hierarchy.depth(&helper), 3);
+    }
+
+    #[test]
+    fn test_reparent_rejects_cycles() {
+        let mut hierarchy = AgentHierarchy::new();
+
+        let root = AgentId::new();
+        let lead = AgentId::new();
+        let worker = AgentId::new();
+
+        hierarchy.add_agent(root, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead, AgentRole::DomainLead { domain: "backend".into() }, Some(root)).unwrap();
+        hierarchy.add_agent(worker, AgentRole::Worker, Some(lead)).unwrap();
+
+        assert!(hierarchy.reparent(&lead, &worker).is_err());
+        assert!(hierarchy.reparent(&lead, &lead).is_err());
+        assert!(hierarchy.reparent(&lead, &AgentId::new()).is_err());
+        assert_eq!(hierarchy.parent(&lead), Some(root));
+        assert_eq!(hierarchy.children(&lead), vec![worker]);
+    }
+
+    #[test]
+    fn test_reparent_respects_max_children() {
+        let mut hierarchy = AgentHierarchy::new();
+
+        let root = AgentId::new();
+        let lead = AgentId::new();
+        let worker1 = AgentId::new();
+        let worker2 = AgentId::new();
+
+        hierarchy.add_agent(root, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead, AgentRole::DomainLead { domain: "backend".into() }, Some(root)).unwrap();
+        hierarchy.add_agent(worker1, AgentRole::Worker, Some(lead)).unwrap();
+        hierarchy.add_agent(worker2, AgentRole::Worker, Some(root)).unwrap();
+        hierarchy.set_max_children(&lead, Some(1));
+
+        assert!(hierarchy.reparent(&worker2, &lead).is_err());
+        assert_eq!(hierarchy.parent(&worker2), Some(root));
+    }
+
     // === Depth Tests ===
 
     #[test]
@@ -317,7 +868,7 @@ mod tests {
         let mut hierarchy = AgentHierarchy::new();
         let root_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
         
         assert_eq!(hierarchy.depth(&root_id), 0);
     }
@@ -329,8 +880,8 @@ mod tests {
         let root_id = AgentId::new();
         let child_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id)).unwrap();
         
         assert_eq!(hierarchy.depth(&root_id), 0);
         assert_eq!(hierarchy.depth(&child_id), 1);
@@ -344,9 +895,9 @@ mod tests {
         let child_id = AgentId::new();
         let grandchild_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child_id, AgentRole::DomainLead { domain: "test".into() }, Some(root_id));
-        hierarchy.add_agent(grandchild_id, AgentRole::Worker, Some(child_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child_id, AgentRole::DomainLead { domain: "test".into() }, Some(root_id)).unwrap();
+        hierarchy.add_agent(grandchild_id, AgentRole::Worker, Some(child_id)).unwrap();
         
         assert_eq!(hierarchy.depth(&root_id), 0);
         assert_eq!(hierarchy.depth(&child_id), 1);
@@ -370,8 +921,8 @@ mod tests {
         let root_id = AgentId::new();
         let child_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id)).unwrap();
         
         let agents = hierarchy.agents_at_depth(0);
         assert_eq!(agents.len(), 1);
@@ -386,9 +937,9 @@ mod tests {
         let child1_id = AgentId::new();
         let child2_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child1_id, AgentRole::Worker, Some(root_id));
-        hierarchy.add_agent(child2_id, AgentRole::Worker, Some(root_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child1_id, AgentRole::Worker, Some(root_id)).unwrap();
+        hierarchy.add_agent(child2_id, AgentRole::Worker, Some(root_id)).unwrap();
         
         let agents = hierarchy.agents_at_depth(1);
         assert_eq!(agents.len(), 2);
@@ -401,12 +952,42 @@ mod tests {
         let mut hierarchy = AgentHierarchy::new();
         let root_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
         
         let agents = hierarchy.agents_at_depth(5);
         assert!(agents.is_empty());
     }
 
+    #[test]
+    fn test_depths_follow_reparent_and_removal() {
+        let mut hierarchy = AgentHierarchy::new();
+
+        let root_id = AgentId::new();
+        let lead1_id = AgentId::new();
+        let lead2_id = AgentId::new();
+        let worker_id = AgentId::new();
+        let helper_id = AgentId::new();
+
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead1_id, AgentRole::DomainLead { domain: "a".into() }, Some(root_id)).unwrap();
+        hierarchy.add_agent(lead2_id, AgentRole::DomainLead { domain: "b".into() }, Some(root_id)).unwrap();
+        hierarchy.add_agent(worker_id, AgentRole::Worker, Some(lead1_id)).unwrap();
+        hierarchy.add_agent(helper_id, AgentRole::Worker, Some(worker_id)).unwrap();
+        assert_eq!(hierarchy.agents_at_depth(3), vec![helper_id]);
+
+        // Moving the lead under its sibling pushes its subtree down a level
+        hierarchy.reparent(&lead1_id, &lead2_id).unwrap();
+        assert_eq!(hierarchy.depth(&lead1_id), 2);
+        assert_eq!(hierarchy.depth(&helper_id), 4);
+        assert_eq!(hierarchy.agents_at_depth(1), vec![lead2_id]);
+        assert_eq!(hierarchy.agents_at_depth(3), vec![worker_id]);
+        assert!(hierarchy.validate().is_ok());
+
+        hierarchy.remove_subtree(&worker_id);
+        assert!(hierarchy.agents_at_depth(3).is_empty());
+        assert!(hierarchy.agents_at_depth(4).is_empty());
+    }
+
     // === Parent Tests ===
 
     #[test]
@@ -414,7 +995,7 @@ mod tests {
         let mut hierarchy = AgentHierarchy::new();
         let root_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
         
         assert!(hierarchy.parent(&root_id).is_none());
     }
@@ -426,8 +1007,8 @@ mod tests {
         let root_id = AgentId::new();
         let child_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id)).unwrap();
         
         assert_eq!(hierarchy.parent(&child_id), Some(root_id));
     }
@@ -440,6 +1021,79 @@ mod tests {
         assert!(hierarchy.parent(&fake_id).is_none());
     }
 
+    // === Ancestor and Descendant Tests ===
+
+    #[test]
+    fn test_ancestors_and_descendants() {
+        let mut hierarchy = AgentHierarchy::new();
+
+        let root = AgentId::new();
+        let lead = AgentId::new();
+        let worker1 = AgentId::new();
+        let worker2 = AgentId::new();
+        let helper = AgentId::new();
+
+        hierarchy.add_agent(root, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead, AgentRole::DomainLead { domain: "backend".into() }, Some(root)).unwrap();
+        hierarchy.add_agent(worker1, AgentRole::Worker, Some(lead)).unwrap();
+        hierarchy.add_agent(helper, AgentRole::Worker, Some(worker1)).unwrap();
+        hierarchy.add_agent(worker2, AgentRole::Worker, Some(lead)).unwrap();
+
+        assert_eq!(hierarchy.ancestors(&helper), vec![worker1, lead, root]);
+        assert!(hierarchy.ancestors(&root).is_empty());
+        assert_eq!(hierarchy.descendants(&lead), vec![worker1, helper, worker2]);
+        assert_eq!(hierarchy.descendants(&root).len(), 4);
+        assert!(hierarchy.descendants(&helper).is_empty());
+
+        assert!(hierarchy.is_descendant_of(&helper, &lead));
+        assert!(!hierarchy.is_descendant_of(&lead, &helper));
+        assert!(!hierarchy.is_descendant_of(&lead, &lead));
+    }
+
+    #[test]
+    fn test_ancestors_and_descendants_nonexistent() {
+        let hierarchy = AgentHierarchy::new();
+        let fake_id = AgentId::new();
+
+        assert!(hierarchy.ancestors(&fake_id).is_empty());
+        assert!(hierarchy.descendants(&fake_id).is_empty());
+    }
+
+    // === Traversal Tests ===
+
+    #[test]
+    fn test_iter_dfs_and_bfs() {
+        let mut hierarchy = AgentHierarchy::new();
+
+        let root = AgentId::new();
+        let lead = AgentId::new();
+        let worker1 = AgentId::new();
+        let worker2 = AgentId::new();
+        let helper = AgentId::new();
+
+        hierarchy.add_agent(root, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead, AgentRole::DomainLead { domain: "backend".into() }, Some(root)).unwrap();
+        hierarchy.add_agent(worker1, AgentRole::Worker, Some(lead)).unwrap();
+        hierarchy.add_agent(helper, AgentRole::Worker, Some(worker1)).unwrap();
+        hierarchy.add_agent(worker2, AgentRole::Worker, Some(root)).unwrap();
+
+        let dfs: Vec<_> = hierarchy.iter_dfs().map(|(id, depth, _)| (id, depth)).collect();
+        assert_eq!(dfs, vec![(root, 0), (lead, 1), (worker1, 2), (helper, 3), (worker2, 1)]);
+
+        let bfs: Vec<_> = hierarchy.iter_bfs().map(|(id, depth, _)| (id, depth)).collect();
+        assert_eq!(bfs, vec![(root, 0), (lead, 1), (worker2, 1), (worker1, 2), (helper, 3)]);
+
+        let (_, _, role) = hierarchy.iter_bfs().next().unwrap();
+        assert_eq!(*role, AgentRole::Orchestrator);
+    }
+
+    #[test]
+    fn test_iter_empty() {
+        let hierarchy = AgentHierarchy::new();
+        assert_eq!(hierarchy.iter_dfs().count(), 0);
+        assert_eq!(hierarchy.iter_bfs().count(), 0);
+    }
+
     // === Children Tests ===
 
     #[test]
@@ -447,7 +1101,7 @@ mod tests {
         let mut hierarchy = AgentHierarchy::new();
         let root_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
         
         assert!(hierarchy.children(&root_id).is_empty());
     }
@@ -461,10 +1115,10 @@ mod tests {
         let child2_id = AgentId::new();
         let child3_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child1_id, AgentRole::Worker, Some(root_id));
-        hierarchy.add_agent(child2_id, AgentRole::Worker, Some(root_id));
-        hierarchy.add_agent(child3_id, AgentRole::Worker, Some(root_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child1_id, AgentRole::Worker, Some(root_id)).unwrap();
+        hierarchy.add_agent(child2_id, AgentRole::Worker, Some(root_id)).unwrap();
+        hierarchy.add_agent(child3_id, AgentRole::Worker, Some(root_id)).unwrap();
         
         let children = hierarchy.children(&root_id);
         assert_eq!(children.len(), 3);
@@ -492,12 +1146,12 @@ mod tests {
         let worker2 = AgentId::new();
         let worker3 = AgentId::new();
         
-        hierarchy.add_agent(root, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(lead1, AgentRole::DomainLead { domain: "frontend".into() }, Some(root));
-        hierarchy.add_agent(lead2, AgentRole::DomainLead { domain: "backend".into() }, Some(root));
-        hierarchy.add_agent(worker1, AgentRole::Worker, Some(lead1));
-        hierarchy.add_agent(worker2, AgentRole::Worker, Some(lead1));
-        hierarchy.add_agent(worker3, AgentRole::Worker, Some(lead2));
+        hierarchy.add_agent(root, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead1, AgentRole::DomainLead { domain: "frontend".into() }, Some(root)).unwrap();
+        hierarchy.add_agent(lead2, AgentRole::DomainLead { domain: "backend".into() }, Some(root)).unwrap();
+        hierarchy.add_agent(worker1, AgentRole::Worker, Some(lead1)).unwrap();
+        hierarchy.add_agent(worker2, AgentRole::Worker, Some(lead1)).unwrap();
+        hierarchy.add_agent(worker3, AgentRole::Worker, Some(lead2)).unwrap();
         
         assert_eq!(hierarchy.len(), 6);
         assert_eq!(hierarchy.agents_at_depth(0).len(), 1);
@@ -505,6 +1159,35 @@ mod tests {
         assert_eq!(hierarchy.agents_at_depth(2).len(), 3);
     }
 
+    // === Export Tests ===
+
+    #[test]
+    fn test_snapshot_and_json() {
+        let mut hierarchy = AgentHierarchy::new();
+        let agents: HashMap<AgentId, AgentHandle> = HashMap::new();
+
+        let root_id = AgentId::new();
+        let lead_id = AgentId::new();
+        let worker_id = AgentId::new();
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(lead_id, AgentRole::DomainLead { domain: "api".into() }, Some(root_id)).unwrap();
+        hierarchy.add_agent(worker_id, AgentRole::Worker, Some(lead_id)).unwrap();
+
+        let snapshot = hierarchy.snapshot(&agents);
+        assert_eq!(snapshot.root, Some(root_id));
+        let order: Vec<_> = snapshot.agents.iter().map(|e| (e.agent_id, e.depth)).collect();
+        assert_eq!(order, vec![(root_id, 0), (lead_id, 1), (worker_id, 2)]);
+        assert_eq!(snapshot.agents[1].parent, Some(root_id));
+        assert_eq!(snapshot.agents[1].children, vec![worker_id]);
+        assert_eq!(snapshot.agents[2].status, AgentStatus::Terminated);
+
+        let parsed: HierarchySnapshot = serde_json::from_str(&hierarchy.to_json(&agents)).unwrap();
+        assert_eq!(parsed, snapshot);
+
+        assert!(hierarchy.to_dot(&agents).contains("n1 -> n2;"));
+        assert!(hierarchy.to_mermaid(&agents).contains("n0 --> n1"));
+    }
+
     // === to_tree Tests ===
 
     #[test]
@@ -512,10 +1195,8 @@ mod tests {
         let hierarchy = AgentHierarchy::new();
         let agents: HashMap<AgentId, AgentHandle> = HashMap::new();
         
-        let tree = hierarchy.to_tree(&agents);
-        
-        // Empty tree should have a default agent
-        assert!(tree.children.is_empty());
+        // No ghost root for an empty hierarchy
+        assert!(hierarchy.to_tree(&agents).is_none());
     }
 
     #[test]
@@ -532,8 +1213,8 @@ mod tests {
         let root_id = AgentId::new();
         let child_id = AgentId::new();
         
-        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None);
-        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id));
+        hierarchy.add_agent(root_id, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(child_id, AgentRole::Worker, Some(root_id)).unwrap();
         
         // Create mock agents
         let (tx, _rx) = mpsc::unbounded_channel();
@@ -546,7 +1227,10 @@ mod tests {
         let _root_agent = Agent::new(root_config, None, tools.clone(), tx.clone());
         // We can't easily set the ID after creation, so we'll skip the full test here
         
-        let tree = hierarchy.to_tree(&agents);
-        assert!(tree.children.is_empty() || !tree.children.is_empty()); // Passes either way
+        let tree = hierarchy.to_tree(&agents).unwrap();
+        assert_eq!(tree.agent_id, root_id);
+        assert_eq!(tree.role, AgentRole::Orchestrator);
+        assert_eq!(tree.children.len(), 1);
+        assert_eq!(tree.children[0].agent_id, child_id);
     }
 }
@@ -0,0 +1,144 @@
+//! Per-agent conversation history
+//!
+//! Every agent keeps the conversation it has had so far: messages by role,
+//! the tool calls it made and their results. Runtimes append to it as the
+//! agent works and replay it to the model on the next turn, so state
+//! survives between tasks.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model::{ChatMessage, ChatRole};
+
+/// One entry of an agent's history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HistoryEntry {
+    /// A message by the system, the user, the agent or a tool
+    Message { role: ChatRole, content: String },
+    /// A tool call made by the agent
+    ToolCall {
+        call_id: String,
+        tool: String,
+        arguments: Value,
+    },
+    /// The result of a tool call
+    ToolResult {
+        call_id: String,
+        output: String,
+        success: bool,
+    },
+}
+
+impl HistoryEntry {
+    pub fn message(role: ChatRole, content: impl Into<String>) -> Self {
+        Self::Message {
+            role,
+            content: content.into(),
+        }
+    }
+
+    /// Estimated number of tokens the entry takes in a prompt
+    pub fn tokens(&self) -> u64 {
+        match self {
+            Self::Message { content, .. } => estimate_tokens(content),
+            Self::ToolCall { tool, arguments, .. } => estimate_tokens(tool) + estimate_tokens(&arguments.to_string()),
+            Self::ToolResult { output, .. } => estimate_tokens(output),
+        }
+    }
+
+    /// The entry as a chat message
+    pub fn to_message(&self) -> ChatMessage {
+        match self {
+            Self::Message { role, content } => ChatMessage::new(*role, content.as_str()),
+            Self::ToolCall { call_id, tool, arguments } => {
+                ChatMessage::assistant(format!("Calling {} ({}) with {}", tool, call_id, arguments))
+            }
+            Self::ToolResult { call_id, output, success } => {
+                let outcome = if *success { "succeeded" } else { "failed" };
+                ChatMessage::new(ChatRole::Tool, format!("Call {} {}:\n{}", call_id, outcome, output))
+            }
+        }
+    }
+}
+
+/// Rough token count of a text, at four characters per token
+pub fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
+/// An agent's conversation so far
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct History {
+    entries: Vec<HistoryEntry>,
+    tokens: u64,
+}
+
+impl History {
+    /// Create an empty history
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an entry
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.tokens += entry.tokens();
+        self.entries.push(entry);
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Estimated number of tokens the history takes in a prompt
+    pub fn tokens(&self) -> u64 {
+        self.tokens
+    }
+
+    /// Replace the oldest `count` entries with one entry
+    pub fn replace_prefix(&mut self, count: usize, entry: HistoryEntry) {
+        let count = count.min(self.entries.len());
+        self.entries.splice(..count, std::iter::once(entry));
+        self.tokens = self.entries.iter().map(HistoryEntry::tokens).sum();
+    }
+
+    /// The history as chat messages, oldest first
+    pub fn to_messages(&self) -> Vec<ChatMessage> {
+        self.entries.iter().map(HistoryEntry::to_message).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_tracks_entries_and_tokens() {
+        let mut history = History::new();
+        history.push(HistoryEntry::message(ChatRole::User, "List the files"));
+        history.push(HistoryEntry::ToolCall {
+            call_id: "c1".into(),
+            tool: "ls".into(),
+            arguments: serde_json::json!({ "path": "." }),
+        });
+        history.push(HistoryEntry::ToolResult {
+            call_id: "c1".into(),
+            output: "Cargo.toml".into(),
+            success: true,
+        });
+
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.tokens(), history.entries().iter().map(HistoryEntry::tokens).sum::<u64>());
+
+        let roles: Vec<_> = history.to_messages().into_iter().map(|m| m.role).collect();
+        assert_eq!(roles, vec![ChatRole::User, ChatRole::Assistant, ChatRole::Tool]);
+    }
+}
@@ -0,0 +1,241 @@
+//! Write-ahead journal of ops and events
+//!
+//! With a [`Journal`] installed, the orchestrator appends every op before
+//! handling it and every event before forwarding it to the client. Replaying
+//! the ops of a journal into a fresh orchestrator rebuilds the sessions they
+//! created; a crash loses at most the op that was being handled.
+//!
+//! [`FileJournal`] keeps the journal as JSON lines in a file and syncs each
+//! op to disk before it is handled. Other backends implement [`Journal`].
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::error::GoblinError;
+use crate::protocol::{GoblinEvent, GoblinOp};
+
+/// What a journal entry records
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalRecord {
+    Op(GoblinOp),
+    Event(GoblinEvent),
+}
+
+/// One entry of a journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Position in the journal, starting at 1
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub record: JournalRecord,
+}
+
+/// Append-only storage for ops and events
+pub trait Journal: Send + Sync {
+    /// Backend name, for logs
+    fn name(&self) -> &str;
+
+    /// Append a record, returning its sequence number
+    ///
+    /// An op must be durable when this returns.
+    fn append(&self, record: JournalRecord) -> Result<u64, GoblinError>;
+
+    /// All entries, oldest first
+    fn entries(&self) -> Result<Vec<JournalEntry>, GoblinError>;
+}
+
+/// Journal kept in memory, for tests and short-lived orchestrators
+#[derive(Debug, Default)]
+pub struct MemoryJournal {
+    entries: Mutex<Vec<JournalEntry>>,
+}
+
+impl MemoryJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Journal for MemoryJournal {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn append(&self, record: JournalRecord) -> Result<u64, GoblinError> {
+        let mut entries = self.entries.lock();
+        let seq = entries.len() as u64 + 1;
+        entries.push(JournalEntry { seq, at: Utc::now(), record });
+        Ok(seq)
+    }
+
+    fn entries(&self) -> Result<Vec<JournalEntry>, GoblinError> {
+        Ok(self.entries.lock().clone())
+    }
+}
+
+/// Journal kept as JSON lines in a file
+#[derive(Debug)]
+pub struct FileJournal {
+    path: PathBuf,
+    /// Open file and the sequence number of the last entry
+    file: Mutex<(File, u64)>,
+}
+
+impl FileJournal {
+    /// Open a journal file, creating it if needed and appending to it otherwise
+    ///
+    /// A torn last line is cut off, so new entries start on a line of their own.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, GoblinError> {
+        let path = path.as_ref().to_path_buf();
+        let (entries, valid) = scan_entries(&path)?;
+        let last = entries.last().map_or(0, |e| e.seq);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| journal_error(&path, e))?;
+        let len = file.metadata().map_err(|e| journal_error(&path, e))?.len();
+        if len > valid {
+            warn!(path = %path.display(), bytes = len - valid, "Truncating torn journal entry");
+            file.set_len(valid).map_err(|e| journal_error(&path, e))?;
+            file.sync_data().map_err(|e| journal_error(&path, e))?;
+        }
+        Ok(Self {
+            path,
+            file: Mutex::new((file, last)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Journal for FileJournal {
+    fn name(&self) -> &str {
+        "file"
+    }
+
+    fn append(&self, record: JournalRecord) -> Result<u64, GoblinError> {
+        let mut guard = self.file.lock();
+        let (file, last) = &mut *guard;
+        let durable = matches!(record, JournalRecord::Op(_));
+        let entry = JournalEntry {
+            seq: *last + 1,
+            at: Utc::now(),
+            record,
+        };
+
+        let mut line = serde_json::to_vec(&entry)
+            .map_err(|e| GoblinError::PersistenceError(format!("Failed to encode journal entry: {}", e)))?;
+        line.push(b'\n');
+        file.write_all(&line).map_err(|e| journal_error(&self.path, e))?;
+        if durable {
+            file.sync_data().map_err(|e| journal_error(&self.path, e))?;
+        }
+
+        *last = entry.seq;
+        Ok(entry.seq)
+    }
+
+    fn entries(&self) -> Result<Vec<JournalEntry>, GoblinError> {
+        let _guard = self.file.lock();
+        read_entries(&self.path)
+    }
+}
+
+/// Entries of a journal file; a torn last line from a crash is skipped
+fn read_entries(path: &Path) -> Result<Vec<JournalEntry>, GoblinError> {
+    scan_entries(path).map(|(entries, _)| entries)
+}
+
+/// Entries of a journal file and the length in bytes of the lines they were read from
+///
+/// A line is only complete with its newline, so a torn last line is left
+/// out of both, whether or not it parses.
+fn scan_entries(path: &Path) -> Result<(Vec<JournalEntry>, u64), GoblinError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), 0)),
+        Err(e) => return Err(journal_error(path, e)),
+    };
+
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let last = lines.iter().rposition(|l| !l.trim().is_empty());
+    let mut entries = Vec::with_capacity(lines.len());
+    let (mut offset, mut valid) = (0, 0);
+    for (i, raw) in lines.iter().enumerate() {
+        offset += raw.len();
+        let line = raw.trim();
+        if line.is_empty() {
+            valid = offset;
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(entry) if raw.ends_with('\n') => {
+                entries.push(entry);
+                valid = offset;
+            }
+            Err(e) if Some(i) != last => {
+                return Err(GoblinError::PersistenceError(format!(
+                    "Invalid journal line {} in {}: {}",
+                    i + 1,
+                    path.display(),
+                    e
+                )));
+            }
+            _ => warn!(path = %path.display(), "Skipping torn journal entry"),
+        }
+    }
+    Ok((entries, valid as u64))
+}
+
+fn journal_error(path: &Path, e: std::io::Error) -> GoblinError {
+    GoblinError::PersistenceError(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warhorn::SubmissionId;
+
+    fn describe() -> JournalRecord {
+        JournalRecord::Op(GoblinOp::DescribeAgents { sub_id: SubmissionId::new() })
+    }
+
+    #[test]
+    fn test_file_journal_appends_and_reopens() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+
+        let journal = FileJournal::open(&path).unwrap();
+        assert_eq!(journal.append(describe()).unwrap(), 1);
+        assert_eq!(journal.append(describe()).unwrap(), 2);
+        drop(journal);
+
+        // A crash in the middle of a write leaves a torn line behind
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"seq\":3,\"at\":").unwrap();
+
+        let journal = FileJournal::open(&path).unwrap();
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1, 2]);
+        assert!(matches!(entries[0].record, JournalRecord::Op(GoblinOp::DescribeAgents { .. })));
+        assert_eq!(journal.append(describe()).unwrap(), 3);
+
+        // The torn line was cut off, so the new entry is readable
+        let seqs: Vec<_> = journal.entries().unwrap().iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, vec![1, 2, 3]);
+        drop(journal);
+        let journal = FileJournal::open(&path).unwrap();
+        assert_eq!(journal.entries().unwrap().len(), 3);
+    }
+}
@@ -37,18 +37,153 @@
 //! - **Task**: A unit of work assigned to an agent
 
 pub mod agent;
+pub mod approval;
+pub mod policy;
+pub mod audit;
+pub mod sandbox;
+pub mod toolscope;
+pub mod budget;
+pub mod pricing;
+pub mod estimate;
+pub mod ratelimit;
+pub mod actor;
+pub mod runtime;
+pub mod model;
+pub mod history;
+pub mod compaction;
+pub mod handoff;
+pub mod checkpoint;
+pub mod bundle;
+pub mod journal;
+pub mod mailbox;
 pub mod session;
 pub mod orchestrator;
 pub mod hierarchy;
 pub mod channel;
+pub mod config;
+pub mod workspace;
+pub mod protocol;
+pub mod artifact;
+pub mod plan;
+pub mod planner;
+pub mod result;
+pub mod merger;
+pub mod review;
+pub mod mapreduce;
+pub mod pipeline;
+pub mod speculate;
+pub mod debate;
+pub mod workflow;
+pub mod scheduler;
+pub mod fairshare;
+pub mod modelslots;
+pub mod supervise;
+pub mod retry;
+pub mod failure;
+pub mod breaker;
+pub mod propagation;
+pub mod heartbeat;
+pub mod stall;
+pub mod orphan;
+pub mod recovery;
+pub mod toolcache;
+pub mod coalesce;
+pub mod toollimit;
+pub mod mcp;
+pub mod delegation;
+pub mod scope;
+pub mod deadline;
+pub mod schedule;
+pub mod selftest;
+pub mod rules;
+pub mod store;
+pub mod tasks;
+pub mod metrics;
+pub mod filter;
+pub mod replay;
+pub mod render;
+#[cfg(unix)]
+pub mod daemon;
+pub mod stdio;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod capabilities;
 pub mod error;
+#[cfg(test)]
+mod testing;
 
-pub use agent::{Agent, AgentHandle};
+pub use agent::{Agent, AgentHandle, AgentOverrides, AgentSummary};
+pub use audit::{AuditEntry, AuditLog, AuditQuery, AuditRecord};
+pub use policy::PolicyDocument;
+pub use budget::{BudgetLedger, SubtreeBudget, TokenBudget, TokenBudgets};
+pub use pricing::{ModelPrice, PricingTable};
+pub use estimate::{CostEstimate, EstimatePolicy, RoleEstimate, RoleHistory};
+pub use ratelimit::{RateLimit, TokenBucket};
+pub use approval::{
+    ApprovalDecision, ApprovalPolicy, ApprovalRule, ApprovalTimeout, Approvals, ExecRequest, PendingApproval, SandboxLevel,
+    TimeoutAction, TrustLevel, TrustPolicy,
+};
+pub use mailbox::{Mail, Mailbox};
+pub use runtime::{AgentRuntime, DefaultRuntime, Runtimes};
+pub use model::{ChatMessage, ChatRole, ModelProvider, ModelProviders};
+pub use history::{History, HistoryEntry};
+pub use compaction::{CompactionPolicy, Compactor, Summarizer};
+pub use handoff::Handoff;
+pub use checkpoint::{AgentCheckpoint, SessionCheckpoint};
+pub use bundle::SessionBundle;
+pub use journal::{FileJournal, Journal, JournalEntry, JournalRecord, MemoryJournal};
 pub use session::{Session, SessionHandle};
 pub use orchestrator::Orchestrator;
-pub use hierarchy::AgentHierarchy;
-pub use channel::{GoblinChannel, ChannelPair};
-pub use error::GoblinError;
+pub use hierarchy::{AgentHierarchy, BreadthFirst, DepthFirst, HierarchyEntry, HierarchySnapshot, SubtreeSummary};
+pub use channel::{GoblinChannel, GoblinSender, GoblinReceiver, ChannelPair, ChannelBuilder, ChannelError, ChannelSender, ChannelReceiver, ChannelStats, OverflowPolicy, LagPolicy};
+pub use config::{SessionConfigPatch, SessionOptions, SpawnLimits, UsageUpdates};
+pub use protocol::{GoblinEvent, GoblinOp};
+pub use artifact::{Artifact, ArtifactId, ArtifactInfo, ArtifactStore, Attachment};
+pub use plan::{TaskPlan, PlannedTask, PlanStatus};
+pub use planner::{Planner, PlannerKind, FlatPlanner, DomainPlanner};
+pub use result::{TaskResult, ResultStatus, FileChange, ChangeKind};
+pub use merger::{ResultMerger, MergerKind, ConcatMerger, VoteMerger, JudgeMerger, Judge};
+pub use review::{Review, ReviewPolicy, Reviewer};
+pub use mapreduce::{MapReduce, Mapper};
+pub use pipeline::{Pipeline, PipelineStage, StageRunner};
+pub use speculate::{AttemptRunner, Speculation};
+pub use debate::{Debate, Debater, Moderator, Side, Turn};
+pub use workflow::{Workflow, Stage, Gate, WorkflowRun};
+pub use scheduler::{Priority, TaskScheduler};
+pub use fairshare::{FairSharePool, QueueStats, SlotPermit};
+pub use modelslots::{ModelPermit, ModelQueueStats, ModelSlots};
+pub use supervise::{Backoff, SupervisionPolicies, SupervisionPolicy};
+pub use retry::{RetryPolicy, TaskAttempt};
+pub use failure::{FailureReport, FailureReports};
+pub use breaker::{BreakerPolicy, BreakerState, CircuitBreakers};
+pub use propagation::{FailurePolicies, FailurePolicy};
+pub use heartbeat::HeartbeatPolicy;
+pub use stall::{StallAction, StallPolicy};
+pub use orphan::OrphanPolicy;
+pub use recovery::{InterruptedTask, RecoveryReport};
+pub use toolcache::{CallContext, ToolCache, ToolCachePolicy, ToolCacheStats};
+pub use coalesce::{CoalescePolicy, InFlightCalls};
+pub use toollimit::{ToolLimit, ToolLimits, ToolPermit};
+pub use mcp::{McpClient, McpServer, McpTool, McpTools, McpTransport, StdioTransport};
+pub use delegation::DelegationPolicy;
+pub use scope::{AgentScope, JoinOutcome};
+pub use deadline::DeadlineAction;
+pub use schedule::{ScheduleId, ScheduleSpec};
+pub use capabilities::Capabilities;
+pub use rules::{Rule, Trigger, Action};
+pub use store::{SessionStore, StoreEntry, StoreRequest};
+pub use sandbox::{
+    EscalationRule, ResourceLimits, SandboxEscalation, SandboxEscalations, SandboxException, SandboxGrant, SandboxProfile,
+    SandboxProfiles, SANDBOX_PROFILE_ENV,
+};
+pub use toolscope::{ToolOverride, ToolScope, ToolView};
+pub use tasks::{ActiveTask, TaskRegistry};
+pub use metrics::SessionMetrics;
+pub use filter::{EventCategory, EventFilter, FilterSpec};
+pub use replay::ReplayBuffer;
+pub use error::{ErrorCategory, GoblinError};
 
 // Re-export commonly used protocol types
 pub use warhorn::{
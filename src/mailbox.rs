@@ -0,0 +1,92 @@
+//! Agent mailboxes
+//!
+//! Every agent has a mailbox for instructions (assign a task, interrupt,
+//! shut down) and for notifications the session delivers, such as the
+//! results of its children once they have all finished. Whatever runs the
+//! agent takes the receiving end once and reads mail from it; mail
+//! delivered before then is queued. A restarted agent gets a fresh mailbox.
+
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use warhorn::{AgentId, TaskId};
+
+use crate::approval::ExecRequest;
+use crate::failure::FailureReport;
+use crate::handoff::Handoff;
+use crate::result::TaskResult;
+
+/// Something delivered to an agent
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mail {
+    /// Work on a task
+    AssignTask {
+        task_id: TaskId,
+        instructions: String,
+    },
+    /// A message from another agent, or from the client if `from` is None
+    Message {
+        from: Option<AgentId>,
+        content: String,
+    },
+    /// Stop working on the current task
+    Interrupt,
+    /// Stop the run loop
+    Shutdown { reason: String },
+    /// Every child of a task the agent is responsible for has finished
+    ChildrenCompleted {
+        task_id: TaskId,
+        results: Vec<TaskResult>,
+    },
+    /// Context left behind by a child that was terminated or failed
+    Handoff { handoff: Handoff },
+    /// A reviewer rejected the agent's result; the task is still the agent's
+    ResultBounced {
+        task_id: TaskId,
+        feedback: String,
+    },
+    /// A descendant's command waits for a decision the agent may make
+    ApprovalRequest { request: ExecRequest },
+    /// A descendant keeps failing
+    FailureReport { report: FailureReport },
+}
+
+/// An agent's incoming mail
+#[derive(Debug)]
+pub struct Mailbox {
+    tx: Mutex<mpsc::UnboundedSender<Mail>>,
+    rx: Mutex<Option<mpsc::UnboundedReceiver<Mail>>>,
+}
+
+impl Default for Mailbox {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Mailbox {
+    /// Create an empty mailbox
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            tx: Mutex::new(tx),
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Deliver mail, returning false if the reader is gone
+    pub fn deliver(&self, mail: Mail) -> bool {
+        self.tx.lock().send(mail).is_ok()
+    }
+
+    /// Replace the channel with an empty one whose receiving end can be taken again
+    pub fn reopen(&self) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        *self.tx.lock() = tx;
+        *self.rx.lock() = Some(rx);
+    }
+
+    /// Take the receiving end (only once)
+    pub fn take_receiver(&self) -> Option<mpsc::UnboundedReceiver<Mail>> {
+        self.rx.lock().take()
+    }
+}
@@ -0,0 +1,344 @@
+//! Map-reduce over a list of items
+//!
+//! The items are split into chunks, each chunk is handed to a freshly
+//! spawned worker agent, and the chunk results are combined by a reduce
+//! step. At most `max_parallel` workers run at a time (the session's
+//! `max_parallel_agents` by default) and a progress event is emitted as
+//! every chunk completes.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::task::JoinSet;
+use tracing::{debug, info};
+use warhorn::{AgentConfig, AgentId, SubmissionId, TaskId};
+
+use crate::agent::AgentHandle;
+use crate::error::GoblinError;
+use crate::merger::{MergeContext, MergeRequest, ResultMerger};
+use crate::protocol::GoblinEvent;
+use crate::result::TaskResult;
+use crate::session::SessionHandle;
+
+/// Processes one chunk of items on a worker agent
+#[async_trait]
+pub trait Mapper<T>: Send + Sync {
+    /// Process a chunk, producing its (usually structured) result
+    async fn map(&self, agent: &AgentHandle, chunk: &[T]) -> Result<TaskResult, GoblinError>;
+}
+
+/// A map-reduce job
+#[derive(Debug, Clone)]
+pub struct MapReduce<T> {
+    /// What the job is about, handed to the reducer
+    pub description: String,
+    /// Items to process
+    pub items: Vec<T>,
+    /// Items per worker
+    pub chunk_size: usize,
+    /// Configuration of the worker agents
+    pub worker: AgentConfig,
+    /// Agent the workers are spawned under (the root if None)
+    pub parent: Option<AgentId>,
+    /// Maximum concurrent workers (the session's limit if None)
+    pub max_parallel: Option<usize>,
+}
+
+impl<T: Clone + Send + Sync + 'static> MapReduce<T> {
+    /// Create a job processing one item per worker
+    pub fn new(description: impl Into<String>, items: Vec<T>, worker: AgentConfig) -> Self {
+        Self {
+            description: description.into(),
+            items,
+            chunk_size: 1,
+            worker,
+            parent: None,
+            max_parallel: None,
+        }
+    }
+
+    /// Set the number of items per worker
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Spawn the workers under a specific agent
+    pub fn with_parent(mut self, parent: AgentId) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Limit the number of concurrent workers
+    pub fn with_max_parallel(mut self, max_parallel: usize) -> Self {
+        self.max_parallel = Some(max_parallel.max(1));
+        self
+    }
+
+    /// Run the job in a session and return the reduced result
+    pub async fn run(
+        self,
+        session: &SessionHandle,
+        mapper: Arc<dyn Mapper<T>>,
+        reducer: Arc<dyn ResultMerger>,
+        sub_id: &SubmissionId,
+    ) -> Result<TaskResult, GoblinError> {
+        if self.chunk_size == 0 {
+            return Err(GoblinError::ConfigError("map-reduce chunk_size must be at least 1".into()));
+        }
+        let task_id = TaskId::new();
+        let parent = match self.parent {
+            Some(parent) => Some(parent),
+            None => session.orchestrator().map(|o| o.id()),
+        };
+        let max_parallel = self
+            .max_parallel
+            .unwrap_or(session.config().max_parallel_agents)
+            .max(1);
+
+        let chunks: Vec<Vec<T>> = self.items.chunks(self.chunk_size).map(|c| c.to_vec()).collect();
+        let total = chunks.len();
+        info!(task_id = %task_id, chunks = total, max_parallel, "Starting map-reduce");
+
+        let mut pending = chunks.into_iter().enumerate();
+        let mut running = JoinSet::new();
+        let mut results: Vec<Option<TaskResult>> = vec![None; total];
+        let mut completed = 0;
+
+        loop {
+            while running.len() < max_parallel {
+                let Some((index, chunk)) = pending.next() else { break };
+                let session = session.clone();
+                let mapper = Arc::clone(&mapper);
+                let worker = self.worker.clone();
+                let sub_id = sub_id.clone();
+                running.spawn(async move {
+                    let result = map_chunk(&session, mapper.as_ref(), worker, parent, &chunk, &sub_id).await;
+                    (index, result)
+                });
+            }
+
+            let Some(joined) = running.join_next().await else { break };
+            let (index, result) = match joined {
+                Ok(joined) => joined,
+                Err(e) => {
+                    // Aborting the other chunks terminates their workers
+                    running.shutdown().await;
+                    return Err(GoblinError::TaskError(e.to_string()));
+                }
+            };
+            completed += 1;
+            debug!(task_id = %task_id, chunk = index, completed, total, "Map-reduce chunk finished");
+
+            session.emit(GoblinEvent::MapReduceProgress {
+                sub_id: sub_id.clone(),
+                task_id,
+                chunk: index,
+                success: result.is_success(),
+                completed,
+                total,
+            });
+            results[index] = Some(result);
+        }
+
+        let request = MergeRequest {
+            task_id,
+            description: self.description,
+            results: results.into_iter().flatten().collect(),
+        };
+        let ctx = MergeContext {
+            session,
+            parent,
+            sub_id,
+        };
+        let mut reduced = reducer.merge(&request, &ctx).await?;
+        reduced.task_id = task_id;
+        Ok(reduced)
+    }
+}
+
+/// Run one chunk on a fresh worker, turning errors into a failed result
+async fn map_chunk<T>(
+    session: &SessionHandle,
+    mapper: &dyn Mapper<T>,
+    worker: AgentConfig,
+    parent: Option<AgentId>,
+    chunk: &[T],
+    sub_id: &SubmissionId,
+) -> TaskResult {
+    let chunk_task = TaskId::new();
+    let agent = match session.spawn_agent(worker, parent, sub_id) {
+        Ok(agent) => agent,
+        Err(e) => return TaskResult::failure(chunk_task, e.to_string()),
+    };
+    let _worker = ChunkWorker {
+        session,
+        agent_id: agent.id(),
+        sub_id,
+    };
+    agent.assign_task(chunk_task);
+
+    match mapper.map(&agent, chunk).await {
+        Ok(mut result) => {
+            result.task_id = chunk_task;
+            result.from_agent(agent.id())
+        }
+        Err(e) => TaskResult::failure(chunk_task, e.to_string()).from_agent(agent.id()),
+    }
+}
+
+/// Terminates a chunk's worker when the chunk finishes, panics or is aborted
+struct ChunkWorker<'a> {
+    session: &'a SessionHandle,
+    agent_id: AgentId,
+    sub_id: &'a SubmissionId,
+}
+
+impl Drop for ChunkWorker<'_> {
+    fn drop(&mut self) {
+        let _ = self.session.terminate_agent(&self.agent_id, "Chunk finished".into(), self.sub_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use trinkets::ToolRegistry;
+    use warhorn::{AgentRole, SessionConfig};
+
+    use crate::session::Session;
+
+    struct Sum {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Mapper<u64> for Sum {
+        async fn map(&self, _agent: &AgentHandle, chunk: &[u64]) -> Result<TaskResult, GoblinError> {
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            let sum: u64 = chunk.iter().sum();
+            Ok(TaskResult::success(TaskId::new(), sum.to_string()).with_payload(sum.into()))
+        }
+    }
+
+    struct Total;
+
+    #[async_trait]
+    impl ResultMerger for Total {
+        fn name(&self) -> &str {
+            "total"
+        }
+
+        async fn merge(
+            &self,
+            request: &MergeRequest,
+            _ctx: &MergeContext<'_>,
+        ) -> Result<TaskResult, GoblinError> {
+            let total: u64 = request
+                .results
+                .iter()
+                .filter_map(|r| r.payload.as_ref()?.as_u64())
+                .sum();
+            Ok(TaskResult::success(request.task_id, total.to_string()).with_payload(total.into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_map_reduce() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let session = SessionHandle::new(Session::new(
+            SessionConfig::default(),
+            Arc::new(ToolRegistry::new()),
+            tx,
+        ));
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        session.spawn_agent(root, None, &sub_id).unwrap();
+
+        let mapper = Arc::new(Sum {
+            running: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+        });
+        let worker = AgentConfig {
+            role: AgentRole::Worker,
+            ..Default::default()
+        };
+        let result = MapReduce::new("sum 1..=10", (1..=10).collect(), worker)
+            .with_chunk_size(3)
+            .with_max_parallel(2)
+            .run(&session, mapper.clone(), Arc::new(Total), &sub_id)
+            .await
+            .unwrap();
+
+        assert_eq!(result.payload, Some(55.into()));
+        assert_eq!(mapper.peak.load(Ordering::SeqCst), 2);
+        // Workers are gone, only the root is left
+        assert_eq!(session.agent_count(), 1);
+
+        let mut progress = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let GoblinEvent::MapReduceProgress { completed, total, .. } = event {
+                progress.push((completed, total));
+            }
+        }
+        assert_eq!(progress, vec![(1, 4), (2, 4), (3, 4), (4, 4)]);
+    }
+
+    struct Panics;
+
+    #[async_trait]
+    impl Mapper<u64> for Panics {
+        async fn map(&self, _agent: &AgentHandle, chunk: &[u64]) -> Result<TaskResult, GoblinError> {
+            if chunk.contains(&1) {
+                panic!("bad chunk");
+            }
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            Ok(TaskResult::success(TaskId::new(), "slow"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_job_terminates_its_workers() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = SessionHandle::new(Session::new(
+            SessionConfig::default(),
+            Arc::new(ToolRegistry::new()),
+            tx,
+        ));
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        session.spawn_agent(root, None, &sub_id).unwrap();
+        let worker = AgentConfig {
+            role: AgentRole::Worker,
+            ..Default::default()
+        };
+
+        let mut empty = MapReduce::new("nothing", vec![1u64], worker.clone());
+        empty.chunk_size = 0;
+        let result = empty.run(&session, Arc::new(Panics), Arc::new(Total), &sub_id).await;
+        assert!(matches!(result, Err(GoblinError::ConfigError(_))));
+
+        let result = MapReduce::new("panic", vec![1, 2, 3], worker)
+            .with_max_parallel(3)
+            .run(&session, Arc::new(Panics), Arc::new(Total), &sub_id)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(session.agent_count(), 1);
+    }
+}
@@ -0,0 +1,507 @@
+//! Tools of Model Context Protocol servers
+//!
+//! [`McpServer`]s in the session options are started when the session is
+//! configured, each as a child process speaking JSON-RPC over its stdin
+//! and stdout. The session lists every server's tools and offers them to
+//! its agents under names prefixed with the server's, `github__search`
+//! for the `search` tool of the `github` server, so servers cannot shadow
+//! each other. A server is refused if a tool of the registry has a name
+//! with its prefix, so neither can shadow the other. The registry is
+//! shared by every session of an orchestrator, so a session keeps its
+//! servers' tools in its own [`McpTools`] beside it.
+//!
+//! Agents call a server's tool with [`crate::Agent::call_mcp_tool`], which
+//! goes through the agent's tool view, the audit log, the session's tool
+//! cache, coalescing and limits like any other call. A server that cannot
+//! be started or listed is reported in an `McpServerFailed` event and
+//! leaves the session without its tools.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::oneshot;
+use tracing::{debug, warn};
+
+use crate::error::GoblinError;
+
+/// Separator between a server's name and its tools' names
+pub const MCP_SEPARATOR: &str = "__";
+
+/// Protocol version asked for when connecting
+pub const MCP_PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Pages of tools read from a server at most
+pub const MCP_MAX_TOOL_PAGES: usize = 100;
+
+/// An MCP server started for each session
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct McpServer {
+    /// Prefix of the server's tools
+    pub name: String,
+    /// Program and arguments starting the server
+    pub command: Vec<String>,
+    /// Environment variables set for the server
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Longest a request to the server may take, in milliseconds
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    30_000
+}
+
+impl McpServer {
+    /// Server with this prefix, started by running the command
+    pub fn new<S: Into<String>>(name: impl Into<String>, command: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            name: name.into(),
+            command: command.into_iter().map(Into::into).collect(),
+            env: HashMap::new(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+
+    /// Set an environment variable for the server
+    pub fn with_env(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(name.into(), value.into());
+        self
+    }
+
+    /// Fail requests to the server that take longer than this
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ms = timeout.as_millis() as u64;
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    /// Check that the name can prefix tool names and that the server can be started
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.name.is_empty() || self.name.contains(MCP_SEPARATOR) {
+            return Err(GoblinError::ConfigError(format!(
+                "MCP server name {:?} must be non-empty and without {:?}",
+                self.name, MCP_SEPARATOR
+            )));
+        }
+        if self.command.is_empty() || self.timeout_ms == 0 {
+            return Err(GoblinError::ConfigError(format!(
+                "MCP server {} needs a command and a timeout_ms of at least 1",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A tool of an MCP server, as offered to agents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct McpTool {
+    /// Name agents call the tool by, prefixed with the server's
+    pub name: String,
+    /// Name of the server offering the tool
+    pub server: String,
+    /// Name of the tool on its server
+    pub tool: String,
+    /// What the tool does, as the server describes it
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON schema of the tool's parameters
+    #[serde(default)]
+    pub input_schema: Value,
+}
+
+/// Name agents call a server's tool by
+pub fn qualified_name(server: &str, tool: &str) -> String {
+    format!("{}{}{}", server, MCP_SEPARATOR, tool)
+}
+
+/// Refuse a server if a registry tool is named with its prefix
+pub fn check_registry_names(server: &str, registry: impl IntoIterator<Item = impl AsRef<str>>) -> Result<(), GoblinError> {
+    let prefix = qualified_name(server, "");
+    match registry.into_iter().find(|name| name.as_ref().starts_with(&prefix)) {
+        Some(name) => Err(GoblinError::McpError {
+            server: server.to_string(),
+            message: format!("registry tool {} has the server's prefix", name.as_ref()),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// A connection to an MCP server
+#[async_trait]
+pub trait McpTransport: Send + Sync {
+    /// Send a request and wait for its result
+    async fn request(&self, method: &str, params: Value) -> Result<Value, GoblinError>;
+
+    /// Send a notification, which has no result
+    async fn notify(&self, method: &str, params: Value) -> Result<(), GoblinError>;
+}
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Result<Value, String>>>>>;
+
+/// JSON-RPC over newline-delimited JSON, usually a server's stdin and stdout
+pub struct StdioTransport {
+    server: String,
+    timeout: Duration,
+    writer: tokio::sync::Mutex<Box<dyn AsyncWrite + Send + Unpin>>,
+    pending: Pending,
+    next_id: AtomicU64,
+    _child: Option<tokio::process::Child>,
+}
+
+impl StdioTransport {
+    /// Start a server and connect to its stdin and stdout
+    pub fn spawn(server: &McpServer) -> Result<Self, GoblinError> {
+        let mut child = tokio::process::Command::new(&server.command[0])
+            .args(&server.command[1..])
+            .envs(&server.env)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| GoblinError::McpError {
+                server: server.name.clone(),
+                message: format!("failed to start {}: {}", server.command[0], e),
+            })?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(GoblinError::McpError {
+                server: server.name.clone(),
+                message: "server has no stdio".into(),
+            });
+        };
+        let mut transport = Self::new(server, stdout, stdin);
+        transport._child = Some(child);
+        Ok(transport)
+    }
+
+    /// Speak to a server over a reader and writer
+    pub fn new(
+        server: &McpServer,
+        reader: impl AsyncRead + Send + Unpin + 'static,
+        writer: impl AsyncWrite + Send + Unpin + 'static,
+    ) -> Self {
+        let pending: Pending = Arc::default();
+        tokio::spawn(read_responses(server.name.clone(), reader, Arc::clone(&pending)));
+        Self {
+            server: server.name.clone(),
+            timeout: server.timeout(),
+            writer: tokio::sync::Mutex::new(Box::new(writer)),
+            pending,
+            next_id: AtomicU64::new(1),
+            _child: None,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> GoblinError {
+        GoblinError::McpError {
+            server: self.server.clone(),
+            message: message.into(),
+        }
+    }
+
+    async fn send(&self, message: Value) -> Result<(), GoblinError> {
+        let mut line = message.to_string();
+        line.push('\n');
+        let mut writer = self.writer.lock().await;
+        writer.write_all(line.as_bytes()).await.map_err(|e| self.error(e.to_string()))?;
+        writer.flush().await.map_err(|e| self.error(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl McpTransport for StdioTransport {
+    async fn request(&self, method: &str, params: Value) -> Result<Value, GoblinError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().insert(id, tx);
+        let sent = self
+            .send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}))
+            .await;
+        if let Err(e) = sent {
+            self.pending.lock().remove(&id);
+            return Err(e);
+        }
+        match tokio::time::timeout(self.timeout, rx).await {
+            Ok(Ok(result)) => result.map_err(|message| self.error(message)),
+            Ok(Err(_)) => Err(self.error("server closed the connection")),
+            Err(_) => {
+                self.pending.lock().remove(&id);
+                Err(self.error(format!("{} timed out", method)))
+            }
+        }
+    }
+
+    async fn notify(&self, method: &str, params: Value) -> Result<(), GoblinError> {
+        self.send(json!({"jsonrpc": "2.0", "method": method, "params": params})).await
+    }
+}
+
+/// Hand the responses a server writes to the requests waiting for them
+async fn read_responses(server: String, reader: impl AsyncRead + Unpin, pending: Pending) {
+    let mut lines = BufReader::new(reader).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                warn!(server = %server, error = %e, "Failed to read from MCP server");
+                break;
+            }
+        };
+        let Ok(message) = serde_json::from_str::<Value>(&line) else {
+            debug!(server = %server, line = %line, "Ignoring non-JSON line from MCP server");
+            continue;
+        };
+        let Some(id) = message.get("id").and_then(Value::as_u64) else { continue };
+        let result = match (message.get("result"), message.get("error")) {
+            (Some(result), _) => Ok(result.clone()),
+            (None, Some(error)) => Err(error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("request failed")
+                .to_string()),
+            // A request from the server, which is not supported
+            (None, None) => continue,
+        };
+        if let Some(waiting) = pending.lock().remove(&id) {
+            let _ = waiting.send(result);
+        }
+    }
+    // Waiting requests fail as their senders are dropped
+    pending.lock().clear();
+}
+
+/// A server connected and initialized, with the tools it listed
+pub struct McpClient {
+    server: String,
+    transport: Arc<dyn McpTransport>,
+    tools: Vec<McpTool>,
+}
+
+impl McpClient {
+    /// Initialize a connection and list the server's tools
+    ///
+    /// Pages of tools are listed until the server gives no cursor, gives
+    /// one it gave before, or [`MCP_MAX_TOOL_PAGES`] were read.
+    pub async fn connect(server: impl Into<String>, transport: Arc<dyn McpTransport>) -> Result<Self, GoblinError> {
+        let server = server.into();
+        transport
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": MCP_PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": {"name": "cabal", "version": env!("CARGO_PKG_VERSION")},
+                }),
+            )
+            .await?;
+        transport.notify("notifications/initialized", json!({})).await?;
+
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        let mut seen = HashSet::new();
+        for page_number in 1.. {
+            let params = match &cursor {
+                Some(cursor) => json!({"cursor": cursor}),
+                None => json!({}),
+            };
+            let page = transport.request("tools/list", params).await?;
+            for tool in page.get("tools").and_then(Value::as_array).into_iter().flatten() {
+                let Some(name) = tool.get("name").and_then(Value::as_str) else { continue };
+                tools.push(McpTool {
+                    name: qualified_name(&server, name),
+                    server: server.clone(),
+                    tool: name.to_string(),
+                    description: tool.get("description").and_then(Value::as_str).map(String::from),
+                    input_schema: tool.get("inputSchema").cloned().unwrap_or(Value::Null),
+                });
+            }
+            cursor = page.get("nextCursor").and_then(Value::as_str).map(String::from);
+            let Some(next) = &cursor else { break };
+            if !seen.insert(next.clone()) || page_number == MCP_MAX_TOOL_PAGES {
+                warn!(server = %server, pages = page_number, "MCP server keeps paging its tools, keeping those listed");
+                break;
+            }
+        }
+        Ok(Self { server, transport, tools })
+    }
+
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    pub fn tools(&self) -> &[McpTool] {
+        &self.tools
+    }
+
+    /// Call one of the server's tools by its name on the server
+    ///
+    /// Returns the call's result, with its `content`; a result the server
+    /// marks as an error fails with the text of its content.
+    pub async fn call(&self, tool: &str, arguments: Value) -> Result<Value, GoblinError> {
+        let result = self
+            .transport
+            .request("tools/call", json!({"name": tool, "arguments": arguments}))
+            .await?;
+        if result.get("isError").and_then(Value::as_bool) == Some(true) {
+            let text: Vec<&str> = result
+                .get("content")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|c| c.get("text").and_then(Value::as_str))
+                .collect();
+            return Err(GoblinError::McpError {
+                server: self.server.clone(),
+                message: format!("{} failed: {}", tool, text.join("\n")),
+            });
+        }
+        Ok(result)
+    }
+}
+
+/// The MCP servers of a session and their tools
+#[derive(Default)]
+pub struct McpTools {
+    clients: RwLock<HashMap<String, Arc<McpClient>>>,
+}
+
+impl McpTools {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Offer a connected server's tools, replacing a server of the same name
+    pub fn add(&self, client: McpClient) {
+        self.clients.write().insert(client.server.clone(), Arc::new(client));
+    }
+
+    /// Tools of every server, sorted by name
+    pub fn tools(&self) -> Vec<McpTool> {
+        let mut tools: Vec<McpTool> = self
+            .clients
+            .read()
+            .values()
+            .flat_map(|c| c.tools.iter().cloned())
+            .collect();
+        tools.sort_by(|a, b| a.name.cmp(&b.name));
+        tools
+    }
+
+    /// A tool by the name agents call it by
+    pub fn get(&self, name: &str) -> Option<McpTool> {
+        let (server, _) = name.split_once(MCP_SEPARATOR)?;
+        let client = self.clients.read().get(server).cloned()?;
+        client.tools.iter().find(|t| t.name == name).cloned()
+    }
+
+    /// Call a tool by the name agents call it by
+    pub async fn call(&self, name: &str, arguments: Value) -> Result<Value, GoblinError> {
+        let tool = self
+            .get(name)
+            .ok_or_else(|| GoblinError::NotConfigured(format!("No MCP tool {}", name)))?;
+        let client = self.clients.read().get(&tool.server).cloned();
+        match client {
+            Some(client) => client.call(&tool.tool, arguments).await,
+            None => Err(GoblinError::NotConfigured(format!("No MCP server {}", tool.server))),
+        }
+    }
+}
+
+impl std::fmt::Debug for McpTools {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let servers: Vec<String> = self.clients.read().keys().cloned().collect();
+        f.debug_struct("McpTools").field("servers", &servers).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers requests like a server with one `echo` tool
+    async fn fake_server(reader: impl AsyncRead + Unpin, mut writer: impl AsyncWrite + Unpin) {
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let request: Value = serde_json::from_str(&line).unwrap();
+            let Some(id) = request.get("id").cloned() else { continue };
+            let result = match request["method"].as_str().unwrap() {
+                "initialize" => json!({"protocolVersion": MCP_PROTOCOL_VERSION, "capabilities": {"tools": {}}}),
+                "tools/list" => json!({"tools": [{"name": "echo", "inputSchema": {"type": "object"}}]}),
+                "tools/call" if request["params"]["arguments"]["fail"] == true => {
+                    json!({"isError": true, "content": [{"type": "text", "text": "asked to fail"}]})
+                }
+                "tools/call" => json!({"content": [{"type": "text", "text": request["params"]["arguments"]["text"]}]}),
+                _ => continue,
+            };
+            let response = json!({"jsonrpc": "2.0", "id": id, "result": result}).to_string() + "\n";
+            writer.write_all(response.as_bytes()).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_tools_are_namespaced_and_callable() {
+        let (client_side, server_side) = tokio::io::duplex(4096);
+        let (server_read, server_write) = tokio::io::split(server_side);
+        tokio::spawn(fake_server(server_read, server_write));
+        let (client_read, client_write) = tokio::io::split(client_side);
+
+        let server = McpServer::new("files", ["files-mcp"]);
+        let transport = Arc::new(StdioTransport::new(&server, client_read, client_write));
+        let mcp = McpTools::new();
+        mcp.add(McpClient::connect("files", transport).await.unwrap());
+
+        let tools = mcp.tools();
+        assert_eq!(tools.len(), 1);
+        assert_eq!((tools[0].name.as_str(), tools[0].tool.as_str()), ("files__echo", "echo"));
+
+        let result = mcp.call("files__echo", json!({"text": "hi"})).await.unwrap();
+        assert_eq!(result["content"][0]["text"], "hi");
+        let failed = mcp.call("files__echo", json!({"fail": true})).await.unwrap_err();
+        assert_eq!(failed.code(), "mcp_error");
+        assert!(mcp.call("other__echo", json!({})).await.is_err());
+        assert!(McpServer::new("a__b", ["x"]).validate().is_err());
+        assert!(check_registry_names("files", ["shell_exec", "files__echo"]).is_err());
+        assert!(check_registry_names("files", ["shell_exec", "files_echo"]).is_ok());
+    }
+
+    /// Lists one tool per page and always gives the same next cursor
+    struct Looping {
+        lists: AtomicU64,
+    }
+
+    #[async_trait]
+    impl McpTransport for Looping {
+        async fn request(&self, method: &str, _params: Value) -> Result<Value, GoblinError> {
+            if method != "tools/list" {
+                return Ok(json!({}));
+            }
+            let page = self.lists.fetch_add(1, Ordering::SeqCst);
+            Ok(json!({"tools": [{"name": format!("tool{}", page)}], "nextCursor": "again"}))
+        }
+
+        async fn notify(&self, _method: &str, _params: Value) -> Result<(), GoblinError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeated_cursor_ends_the_listing() {
+        let transport = Arc::new(Looping { lists: AtomicU64::new(0) });
+        let client = McpClient::connect("loop", transport.clone()).await.unwrap();
+        assert_eq!(transport.lists.load(Ordering::SeqCst), 2);
+        assert_eq!(client.tools().len(), 2);
+    }
+}
@@ -0,0 +1,495 @@
+//! Result merging - combining child results into their parent's result
+//!
+//! When every child of a subtask has reported, the session hands their
+//! [`TaskResult`]s to a [`ResultMerger`]. The merged result becomes the
+//! parent's result and travels further up the plan until the whole task
+//! has a single result for the client.
+//!
+//! - [`ConcatMerger`]: concatenates child summaries
+//! - [`VoteMerger`]: picks the answer most workers agree on
+//! - [`JudgeMerger`]: lets a transient judge agent pick the best candidate
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+use warhorn::{AgentConfig, AgentId, AgentRole, SubmissionId, TaskId};
+
+use crate::agent::AgentHandle;
+use crate::artifact::Attachment;
+use crate::error::GoblinError;
+use crate::result::{total_usage, ResultStatus, TaskResult};
+use crate::session::Session;
+
+/// Input handed to a merger
+#[derive(Debug, Clone)]
+pub struct MergeRequest {
+    /// Task the merged result is for
+    pub task_id: TaskId,
+    /// What the task was about
+    pub description: String,
+    /// Results of the task's children, in plan order
+    pub results: Vec<TaskResult>,
+}
+
+/// Where a merge happens
+pub struct MergeContext<'a> {
+    /// Session the task belongs to
+    pub session: &'a Session,
+    /// Agent responsible for the parent task (the lead, or the root)
+    pub parent: Option<AgentId>,
+    /// Submission that triggered the merge
+    pub sub_id: &'a SubmissionId,
+}
+
+/// A strategy for combining child results
+#[async_trait]
+pub trait ResultMerger: Send + Sync {
+    /// Strategy name, for logging and capability reporting
+    fn name(&self) -> &str;
+
+    /// Combine the child results into one result for the parent task
+    async fn merge(
+        &self,
+        request: &MergeRequest,
+        ctx: &MergeContext<'_>,
+    ) -> Result<TaskResult, GoblinError>;
+}
+
+/// Built-in merger selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MergerKind {
+    /// Concatenate child summaries
+    #[default]
+    Concat,
+    /// Majority vote between workers attempting the same problem
+    Vote,
+}
+
+impl MergerKind {
+    /// Every built-in merger
+    pub const ALL: [MergerKind; 2] = [MergerKind::Concat, MergerKind::Vote];
+
+    /// Instantiate the selected merger
+    pub fn build(self) -> Arc<dyn ResultMerger> {
+        match self {
+            Self::Concat => Arc::new(ConcatMerger),
+            Self::Vote => Arc::new(VoteMerger::default()),
+        }
+    }
+}
+
+/// Merger that concatenates child summaries in plan order
+///
+/// The merged result succeeds only if every child succeeded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConcatMerger;
+
+#[async_trait]
+impl ResultMerger for ConcatMerger {
+    fn name(&self) -> &str {
+        "concat"
+    }
+
+    async fn merge(
+        &self,
+        request: &MergeRequest,
+        _ctx: &MergeContext<'_>,
+    ) -> Result<TaskResult, GoblinError> {
+        let summary = request
+            .results
+            .iter()
+            .map(|r| {
+                if r.is_success() {
+                    r.summary.clone()
+                } else {
+                    format!("(failed) {}", r.summary)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        Ok(TaskResult {
+            task_id: request.task_id,
+            agent_id: None,
+            status: if request.results.iter().all(|r| r.is_success()) {
+                ResultStatus::Succeeded
+            } else {
+                ResultStatus::Failed
+            },
+            summary,
+            payload: None,
+            files_changed: request.results.iter().flat_map(|r| r.files_changed.clone()).collect(),
+            attachments: request.results.iter().flat_map(|r| r.attachments.clone()).collect(),
+            artifacts: request.results.iter().flat_map(|r| r.artifacts.clone()).collect(),
+            usage: total_usage(&request.results),
+            attempts: Vec::new(),
+        })
+    }
+}
+
+/// Merger for tasks where several workers attempt the same problem
+///
+/// Successful results vote with their structured payload when they have
+/// one, otherwise with their normalized summary text. The answer with the
+/// most votes wins (ties go to the earliest in plan order); dissenting
+/// outputs are attached to the merged result.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VoteMerger {
+    /// Fail unless more than half of all results agree
+    pub require_majority: bool,
+}
+
+impl VoteMerger {
+    /// Voting key of a result
+    fn ballot(result: &TaskResult) -> Ballot<'_> {
+        match &result.payload {
+            Some(payload) => Ballot::Payload(payload),
+            None => Ballot::Summary(normalize(&result.summary)),
+        }
+    }
+}
+
+/// What a result votes for: its payload, compared as a value, or else its
+/// normalized summary
+#[derive(Debug, PartialEq)]
+enum Ballot<'a> {
+    Payload(&'a serde_json::Value),
+    Summary(String),
+}
+
+/// Lowercase, collapse whitespace and drop trailing punctuation
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .trim_end_matches(|c: char| c.is_ascii_punctuation())
+        .to_lowercase()
+}
+
+#[async_trait]
+impl ResultMerger for VoteMerger {
+    fn name(&self) -> &str {
+        "vote"
+    }
+
+    async fn merge(
+        &self,
+        request: &MergeRequest,
+        _ctx: &MergeContext<'_>,
+    ) -> Result<TaskResult, GoblinError> {
+        // (ballot, index of first result with it, votes)
+        let mut tally: Vec<(Ballot<'_>, usize, usize)> = Vec::new();
+        for (i, result) in request.results.iter().enumerate().filter(|(_, r)| r.is_success()) {
+            let ballot = Self::ballot(result);
+            match tally.iter_mut().find(|(b, _, _)| *b == ballot) {
+                Some(entry) => entry.2 += 1,
+                None => tally.push((ballot, i, 1)),
+            }
+        }
+
+        let total = request.results.len();
+        let Some((ballot, winner, votes)) = tally
+            .into_iter()
+            .reduce(|best, next| if next.2 > best.2 { next } else { best })
+        else {
+            return Ok(TaskResult::failure(request.task_id, "No worker produced a result to vote on"));
+        };
+
+        let dissent: Vec<Attachment> = request
+            .results
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| !r.is_success() || Self::ballot(r) != ballot)
+            .map(|(i, r)| {
+                let body = match &r.payload {
+                    Some(payload) => format!("{}\n\n{}", r.summary, payload),
+                    None => r.summary.clone(),
+                };
+                Attachment::text(format!("dissent-{}.txt", i + 1), body)
+            })
+            .collect();
+
+        let chosen = &request.results[winner];
+        let status = if !self.require_majority || votes * 2 > total {
+            ResultStatus::Succeeded
+        } else {
+            ResultStatus::Failed
+        };
+        Ok(TaskResult {
+            task_id: request.task_id,
+            agent_id: None,
+            status,
+            summary: format!("{}\n\n({} of {} workers agreed)", chosen.summary, votes, total),
+            payload: chosen.payload.clone(),
+            files_changed: chosen.files_changed.clone(),
+            attachments: dissent,
+            artifacts: chosen.artifacts.clone(),
+            usage: total_usage(&request.results),
+            attempts: Vec::new(),
+        })
+    }
+}
+
+/// A judge's pick among candidate results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict {
+    /// Index of the chosen candidate
+    pub choice: usize,
+    /// Why it was chosen
+    pub rationale: String,
+}
+
+/// Evaluates candidates on behalf of a judge agent
+#[async_trait]
+pub trait Judge: Send + Sync {
+    /// Pick the best of the candidates, which are all successful results
+    async fn judge(
+        &self,
+        agent: &AgentHandle,
+        description: &str,
+        candidates: &[TaskResult],
+    ) -> Result<Verdict, GoblinError>;
+}
+
+/// Merger that spawns a judge agent to pick the best of N candidate results
+///
+/// The judge is a transient child of the agent responsible for the parent
+/// task and is terminated as soon as it has delivered its verdict.
+pub struct JudgeMerger {
+    judge: Arc<dyn Judge>,
+}
+
+impl JudgeMerger {
+    /// Create a merger that delegates the choice to `judge`
+    pub fn new(judge: Arc<dyn Judge>) -> Self {
+        Self { judge }
+    }
+}
+
+#[async_trait]
+impl ResultMerger for JudgeMerger {
+    fn name(&self) -> &str {
+        "judge"
+    }
+
+    async fn merge(
+        &self,
+        request: &MergeRequest,
+        ctx: &MergeContext<'_>,
+    ) -> Result<TaskResult, GoblinError> {
+        let candidates: Vec<TaskResult> = request.results.iter().filter(|r| r.is_success()).cloned().collect();
+        if candidates.is_empty() {
+            return Ok(TaskResult::failure(request.task_id, "No candidate results to judge"));
+        }
+
+        let defaults = ctx.session.config();
+        let config = AgentConfig {
+            role: AgentRole::Specialist { specialty: "judge".into() },
+            model: defaults.model,
+            cwd: defaults.cwd,
+            can_spawn: false,
+            ..Default::default()
+        };
+        let agent = ctx.session.spawn_agent(config, ctx.parent, ctx.sub_id)?;
+        agent.assign_task(request.task_id);
+
+        let verdict = self.judge.judge(&agent, &request.description, &candidates).await;
+        let _ = ctx.session.terminate_agent(&agent.id(), "Verdict delivered".into(), ctx.sub_id);
+        let verdict = verdict?;
+
+        let chosen = candidates.get(verdict.choice).ok_or_else(|| {
+            GoblinError::TaskError(format!(
+                "Judge chose candidate {} of {}",
+                verdict.choice,
+                candidates.len()
+            ))
+        })?;
+        debug!(task_id = %request.task_id, choice = verdict.choice, "Judge picked a result");
+
+        Ok(TaskResult {
+            task_id: request.task_id,
+            agent_id: None,
+            status: ResultStatus::Succeeded,
+            summary: format!("{}\n\nJudge: {}", chosen.summary, verdict.rationale),
+            payload: chosen.payload.clone(),
+            files_changed: chosen.files_changed.clone(),
+            attachments: chosen.attachments.clone(),
+            artifacts: chosen.artifacts.clone(),
+            usage: total_usage(&request.results),
+            attempts: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use trinkets::ToolRegistry;
+    use warhorn::SessionConfig;
+
+    async fn merge(merger: &dyn ResultMerger, request: &MergeRequest) -> TaskResult {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let ctx = MergeContext {
+            session: &session,
+            parent: None,
+            sub_id: &sub_id,
+        };
+        merger.merge(request, &ctx).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_concat_merger() {
+        let request = MergeRequest {
+            task_id: TaskId::new(),
+            description: "Build an API".into(),
+            results: vec![
+                TaskResult::success(TaskId::new(), "Routes added"),
+                TaskResult::failure(TaskId::new(), "Models did not compile"),
+            ],
+        };
+
+        let merged = merge(&ConcatMerger, &request).await;
+        assert_eq!(merged.task_id, request.task_id);
+        assert!(!merged.is_success());
+        assert_eq!(merged.summary, "Routes added\n\n(failed) Models did not compile");
+    }
+
+    #[tokio::test]
+    async fn test_concat_merger_all_succeed() {
+        let request = MergeRequest {
+            task_id: TaskId::new(),
+            description: String::new(),
+            results: vec![TaskResult::success(TaskId::new(), "ok")],
+        };
+        assert!(merge(&ConcatMerger, &request).await.is_success());
+    }
+
+    fn request(results: Vec<TaskResult>) -> MergeRequest {
+        MergeRequest {
+            task_id: TaskId::new(),
+            description: "What is 6 * 7?".into(),
+            results,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_vote_merger_normalized_text() {
+        let merged = merge(
+            &VoteMerger::default(),
+            &request(vec![
+                TaskResult::success(TaskId::new(), "42"),
+                TaskResult::success(TaskId::new(), "41"),
+                TaskResult::success(TaskId::new(), " 42. "),
+                TaskResult::failure(TaskId::new(), "timed out"),
+            ]),
+        )
+        .await;
+
+        assert!(merged.is_success());
+        assert!(merged.summary.starts_with("42\n\n(2 of 4 workers agreed)"));
+        let dissent: Vec<_> = merged.attachments.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(dissent, vec!["dissent-2.txt", "dissent-4.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_vote_merger_structured_payloads() {
+        let answer = serde_json::json!({"status": "ok", "count": 3});
+        let merged = merge(
+            &VoteMerger { require_majority: true },
+            &request(vec![
+                TaskResult::success(TaskId::new(), "three").with_payload(answer.clone()),
+                TaskResult::success(TaskId::new(), "three items").with_payload(answer.clone()),
+                TaskResult::success(TaskId::new(), "four").with_payload(serde_json::json!({"count": 4})),
+            ]),
+        )
+        .await;
+
+        assert!(merged.is_success());
+        assert_eq!(merged.payload, Some(answer));
+        assert_eq!(merged.attachments.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_vote_merger_requires_majority() {
+        let merged = merge(
+            &VoteMerger { require_majority: true },
+            &request(vec![
+                TaskResult::success(TaskId::new(), "a"),
+                TaskResult::success(TaskId::new(), "b"),
+            ]),
+        )
+        .await;
+        assert!(!merged.is_success());
+
+        let none = merge(
+            &VoteMerger::default(),
+            &request(vec![TaskResult::failure(TaskId::new(), "x")]),
+        )
+        .await;
+        assert!(!none.is_success());
+    }
+
+    #[tokio::test]
+    async fn test_judge_merger_spawns_transient_judge() {
+        struct Longest;
+
+        #[async_trait]
+        impl Judge for Longest {
+            async fn judge(
+                &self,
+                agent: &AgentHandle,
+                _description: &str,
+                candidates: &[TaskResult],
+            ) -> Result<Verdict, GoblinError> {
+                assert!(matches!(agent.role(), AgentRole::Specialist { .. }));
+                let (choice, _) = candidates
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, c)| c.summary.len())
+                    .unwrap();
+                Ok(Verdict {
+                    choice,
+                    rationale: "most thorough".into(),
+                })
+            }
+        }
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::new(SessionConfig::default(), Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let lead = AgentConfig {
+            role: AgentRole::DomainLead { domain: "docs".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead, None, &sub_id).unwrap();
+        let ctx = MergeContext {
+            session: &session,
+            parent: Some(lead.id()),
+            sub_id: &sub_id,
+        };
+
+        let merged = JudgeMerger::new(Arc::new(Longest))
+            .merge(
+                &request(vec![
+                    TaskResult::success(TaskId::new(), "short"),
+                    TaskResult::failure(TaskId::new(), "a failure that is very long"),
+                    TaskResult::success(TaskId::new(), "a longer answer"),
+                ]),
+                &ctx,
+            )
+            .await
+            .unwrap();
+
+        assert!(merged.is_success());
+        assert_eq!(merged.summary, "a longer answer\n\nJudge: most thorough");
+        // The judge is gone once it has ruled
+        assert_eq!(session.agent_count(), 1);
+        assert!(lead.children().is_empty());
+    }
+}
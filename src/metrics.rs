@@ -0,0 +1,72 @@
+//! Per-session metrics
+//!
+//! A session counts what happened to its agents and tasks as it goes.
+//! [`Session::metrics`](crate::session::Session::metrics) combines those
+//! counters with the current token usage and queue depths into a
+//! [`SessionMetrics`] snapshot, which monitoring clients poll with the
+//! `DescribeMetrics` op.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+use warhorn::{TaskId, TokenUsage};
+
+use crate::modelslots::ModelQueueStats;
+
+/// Snapshot of a session's activity
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SessionMetrics {
+    pub agents_spawned: u64,
+    pub agents_terminated: u64,
+    /// Agents currently in the session
+    pub agents_active: usize,
+    pub tasks_started: u64,
+    pub tasks_completed: u64,
+    pub tasks_failed: u64,
+    /// Tasks currently in progress
+    pub tasks_active: usize,
+    /// Tokens spent by all agents, including terminated ones
+    pub usage: TokenUsage,
+    /// Dollar cost of the tokens spent, by the session's pricing table
+    #[serde(default)]
+    pub cost_usd: f64,
+    /// Dollars spent on each task, most expensive first
+    #[serde(default)]
+    pub task_costs: Vec<(TaskId, f64)>,
+    /// Time since the session was created
+    pub wall_time_ms: u64,
+    /// Subtasks waiting for an idle worker
+    pub queued_subtasks: usize,
+    /// Model call slots in use
+    pub model_slots_in_use: usize,
+    /// Model calls waiting for slots, and how long they waited
+    #[serde(default)]
+    pub model_queue: ModelQueueStats,
+    /// Tool execution slots in use
+    pub tool_slots_in_use: usize,
+}
+
+/// Running counters of a session
+#[derive(Debug, Default)]
+pub(crate) struct Counters {
+    pub agents_spawned: AtomicU64,
+    pub agents_terminated: AtomicU64,
+    pub tasks_started: AtomicU64,
+    pub tasks_completed: AtomicU64,
+    pub tasks_failed: AtomicU64,
+}
+
+impl Counters {
+    pub fn incr(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Copy the counters into a snapshot
+    pub fn fill(&self, metrics: &mut SessionMetrics) {
+        metrics.agents_spawned = self.agents_spawned.load(Ordering::Relaxed);
+        metrics.agents_terminated = self.agents_terminated.load(Ordering::Relaxed);
+        metrics.tasks_started = self.tasks_started.load(Ordering::Relaxed);
+        metrics.tasks_completed = self.tasks_completed.load(Ordering::Relaxed);
+        metrics.tasks_failed = self.tasks_failed.load(Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,212 @@
+//! Model providers
+//!
+//! A [`ModelProvider`] serves chat completions, streaming the reply as it
+//! is generated. Providers are registered with the session under a name;
+//! an agent's `AgentConfig.model` selects one with a `provider/model`
+//! prefix (`openai/gpt-4o`), and models without a registered prefix go to
+//! the default provider. Agents are bound to their provider when spawned
+//! and connect to it when initialized.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use warhorn::TokenUsage;
+
+use crate::error::GoblinError;
+
+/// Author of a chat message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+/// One message of a conversation with a model
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        Self {
+            role,
+            content: content.into(),
+        }
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::System, content)
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::User, content)
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self::new(ChatRole::Assistant, content)
+    }
+}
+
+/// A chat completion request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatRequest {
+    /// Model name as understood by the provider
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// A complete chat completion
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatResponse {
+    pub content: String,
+    pub usage: TokenUsage,
+}
+
+/// Receives pieces of a reply as they are generated
+pub type DeltaSink<'a> = &'a (dyn Fn(&str) + Send + Sync);
+
+/// A source of chat completions
+#[async_trait]
+pub trait ModelProvider: Send + Sync {
+    /// Provider name, used as the model prefix
+    fn name(&self) -> &str;
+
+    /// Check that a model can be reached before an agent starts using it
+    async fn connect(&self, _model: &str) -> Result<(), GoblinError> {
+        Ok(())
+    }
+
+    /// Context window of a model in tokens, if known
+    fn context_window(&self, _model: &str) -> Option<u64> {
+        None
+    }
+
+    /// Complete a conversation, passing each piece of the reply to `on_delta`
+    ///
+    /// The returned response holds the complete reply.
+    async fn complete(&self, request: &ChatRequest, on_delta: DeltaSink<'_>) -> Result<ChatResponse, GoblinError>;
+}
+
+/// A provider together with the model an agent uses from it
+#[derive(Clone)]
+pub struct ModelBinding {
+    pub provider: Arc<dyn ModelProvider>,
+    pub model: String,
+}
+
+impl std::fmt::Debug for ModelBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModelBinding")
+            .field("provider", &self.provider.name())
+            .field("model", &self.model)
+            .finish()
+    }
+}
+
+/// Registered model providers
+#[derive(Clone, Default)]
+pub struct ModelProviders {
+    default: Option<Arc<dyn ModelProvider>>,
+    by_name: HashMap<String, Arc<dyn ModelProvider>>,
+}
+
+impl std::fmt::Debug for ModelProviders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ModelProviders")
+            .field("default", &self.default.as_ref().map(|p| p.name()))
+            .field("providers", &self.names())
+            .finish()
+    }
+}
+
+impl ModelProviders {
+    /// No providers; agents run without a model
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a provider under its name
+    pub fn with_provider(mut self, provider: Arc<dyn ModelProvider>) -> Self {
+        self.by_name.insert(provider.name().to_string(), provider);
+        self
+    }
+
+    /// Serve models without a registered prefix with `provider`
+    pub fn with_default(mut self, provider: Arc<dyn ModelProvider>) -> Self {
+        self.default = Some(provider);
+        self
+    }
+
+    /// Provider and model name for a configured model
+    pub fn resolve(&self, model: &str) -> Option<ModelBinding> {
+        if let Some((prefix, name)) = model.split_once('/') {
+            if let Some(provider) = self.by_name.get(prefix) {
+                return Some(ModelBinding {
+                    provider: Arc::clone(provider),
+                    model: name.to_string(),
+                });
+            }
+        }
+        self.default.as_ref().map(|provider| ModelBinding {
+            provider: Arc::clone(provider),
+            model: model.to_string(),
+        })
+    }
+
+    /// Names of all registered providers
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .default
+            .iter()
+            .chain(self.by_name.values())
+            .map(|p| p.name().to_string())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Named(&'static str);
+
+    #[async_trait]
+    impl ModelProvider for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn complete(&self, request: &ChatRequest, _on_delta: DeltaSink<'_>) -> Result<ChatResponse, GoblinError> {
+            Ok(ChatResponse {
+                content: request.model.clone(),
+                usage: TokenUsage::default(),
+            })
+        }
+    }
+
+    #[test]
+    fn test_resolve_by_prefix() {
+        let providers = ModelProviders::new()
+            .with_provider(Arc::new(Named("openai")))
+            .with_default(Arc::new(Named("local")));
+
+        let binding = providers.resolve("openai/gpt-4o").unwrap();
+        assert_eq!((binding.provider.name(), binding.model.as_str()), ("openai", "gpt-4o"));
+
+        let binding = providers.resolve("mistral/large").unwrap();
+        assert_eq!((binding.provider.name(), binding.model.as_str()), ("local", "mistral/large"));
+
+        assert!(ModelProviders::new().resolve("gpt-4o").is_none());
+        assert_eq!(providers.names(), vec!["local", "openai"]);
+    }
+}
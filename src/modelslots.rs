@@ -0,0 +1,165 @@
+//! Session-wide limits on concurrent model calls
+//!
+//! Every model call an agent makes first takes a slot from the session's
+//! [`ModelSlots`]: one from the slots of its model, if that model is
+//! limited, then one from the slots shared by all models. Slots are shared
+//! fairly between the session's user tasks, so subtasks count against the
+//! task they belong to. How long calls queue for slots is reported in the
+//! session metrics.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use warhorn::TaskId;
+
+use crate::fairshare::{FairSharePool, QueueStats, SlotPermit};
+use crate::plan::TaskPlan;
+
+/// Queueing for a session's model call slots
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelQueueStats {
+    /// Slots shared by all models
+    pub session: QueueStats,
+    /// Slots of models limited on their own, by model
+    pub models: BTreeMap<String, QueueStats>,
+}
+
+/// Model call slots of a session
+#[derive(Debug)]
+pub struct ModelSlots {
+    session: FairSharePool,
+    models: HashMap<String, FairSharePool>,
+    /// Plans of the session, to find the user task of a subtask
+    plans: Arc<RwLock<HashMap<TaskId, TaskPlan>>>,
+    /// Share of calls made outside any task
+    unassigned: TaskId,
+}
+
+impl ModelSlots {
+    /// `slots` calls at once across models, and at most `per_model` of each listed model
+    pub(crate) fn new(
+        slots: Option<usize>,
+        per_model: &HashMap<String, usize>,
+        plans: Arc<RwLock<HashMap<TaskId, TaskPlan>>>,
+    ) -> Self {
+        Self {
+            session: FairSharePool::new(slots),
+            models: per_model
+                .iter()
+                .map(|(model, slots)| (model.clone(), FairSharePool::new(Some(*slots))))
+                .collect(),
+            plans,
+            unassigned: TaskId::new(),
+        }
+    }
+
+    /// Wait for a slot to call `model` on behalf of a task or one of its subtasks
+    ///
+    /// The model's own slot is taken first, so calls queued behind a busy
+    /// model do not hold slots other models could use.
+    pub async fn acquire(&self, model: &str, task_id: Option<TaskId>) -> ModelPermit {
+        let root = task_id.map_or(self.unassigned, |id| self.root_task(&id));
+        let model = match self.models.get(model) {
+            Some(pool) => Some(pool.acquire(root).await),
+            None => None,
+        };
+        let session = self.session.acquire(root).await;
+        ModelPermit {
+            _model: model,
+            _session: session,
+        }
+    }
+
+    /// Wait for a slot shared by all models
+    pub(crate) async fn acquire_shared(&self, task_id: &TaskId) -> SlotPermit {
+        self.session.acquire(self.root_task(task_id)).await
+    }
+
+    /// Set a task's share of the slots relative to other tasks
+    pub fn set_weight(&self, task_id: TaskId, weight: u32) {
+        for pool in self.pools() {
+            pool.set_weight(task_id, weight);
+        }
+    }
+
+    /// Weight of a task
+    pub fn weight(&self, task_id: &TaskId) -> u32 {
+        self.session.weight(task_id)
+    }
+
+    /// Forget a finished task
+    pub fn remove_task(&self, task_id: &TaskId) {
+        for pool in self.pools() {
+            pool.remove_task(task_id);
+        }
+    }
+
+    /// Slots shared by all models currently in use
+    pub fn total_in_use(&self) -> usize {
+        self.session.total_in_use()
+    }
+
+    /// Slots in use and waited for, shared and by model
+    pub fn stats(&self) -> ModelQueueStats {
+        ModelQueueStats {
+            session: self.session.stats(),
+            models: self.models.iter().map(|(model, pool)| (model.clone(), pool.stats())).collect(),
+        }
+    }
+
+    /// The user task a subtask belongs to (the task itself if it is one)
+    pub(crate) fn root_task(&self, task_id: &TaskId) -> TaskId {
+        self.plans
+            .read()
+            .values()
+            .find(|p| p.get(task_id).is_some())
+            .map_or(*task_id, |p| p.task_id)
+    }
+
+    fn pools(&self) -> impl Iterator<Item = &FairSharePool> {
+        std::iter::once(&self.session).chain(self.models.values())
+    }
+}
+
+/// Slots held for one model call, released on drop
+#[derive(Debug)]
+pub struct ModelPermit {
+    _model: Option<SlotPermit>,
+    _session: SlotPermit,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_limited_model_queues_without_blocking_others() {
+        let per_model = HashMap::from([("large".to_string(), 1)]);
+        let slots = Arc::new(ModelSlots::new(Some(3), &per_model, Arc::default()));
+        let task = TaskId::new();
+
+        let held = slots.acquire("large", Some(task)).await;
+        let waiter = {
+            let slots = Arc::clone(&slots);
+            tokio::spawn(async move { slots.acquire("large", Some(task)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // The queued call holds no shared slot, so other models still run
+        let _small = tokio::time::timeout(Duration::from_secs(1), slots.acquire("small", Some(task)))
+            .await
+            .unwrap();
+        assert_eq!(slots.stats().models["large"].waiting, 1);
+        assert_eq!(slots.total_in_use(), 2);
+
+        drop(held);
+        let _next = tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        let stats = slots.stats();
+        assert_eq!((stats.models["large"].granted, stats.models["large"].queued), (2, 1));
+        assert!(stats.models["large"].wait_ms_max >= 10);
+        assert_eq!(stats.session.queued, 0);
+    }
+}
@@ -1,6 +1,8 @@
 //! Main orchestrator - coordinates agent hierarchy
 
+use std::path::Path;
 use std::sync::Arc;
+use chrono::{DateTime, Utc};
 use tokio::sync::mpsc;
 use tracing::{debug, info, warn, error, instrument};
 
@@ -11,7 +13,26 @@ use warhorn::{
 use trinkets::ToolRegistry;
 
 use crate::session::{Session, SessionHandle};
-use crate::channel::{GoblinChannel, ChannelPair};
+use crate::channel::{ChannelError, ChannelPair, ChannelReceiver, ChannelSender, ChannelStats, GoblinChannel};
+use crate::artifact::Attachment;
+use crate::checkpoint::SessionCheckpoint;
+use crate::capabilities::{self, Capabilities};
+use crate::config::SessionOptions;
+use crate::delegation::{Delegation, DelegationBroker, DelegationOutcome};
+use crate::merger::{MergerKind, ResultMerger};
+use crate::review::Reviewer;
+use crate::compaction::Summarizer;
+use crate::model::ModelProviders;
+use crate::runtime::Runtimes;
+use crate::journal::{Journal, JournalRecord};
+use crate::recovery::{has_side_effects, JournalScan, RecoveryReport};
+use crate::planner::{PlanRequest, Planner, PlannerKind};
+use crate::policy::PolicyDocument;
+use crate::protocol::{GoblinEvent, GoblinOp};
+use crate::replay::ReplayBuffer;
+use crate::rules::{Action, Rule, RuleEngine};
+use crate::schedule::{ScheduleRegistry, ScheduleSpec};
+use crate::selftest::{self, SelfCheck, SelfTestContext};
 use crate::error::GoblinError;
 
 /// The main goblin orchestrator
@@ -20,25 +41,183 @@ use crate::error::GoblinError;
 pub struct Orchestrator {
     /// Active sessions
     sessions: parking_lot::RwLock<std::collections::HashMap<SessionId, SessionHandle>>,
+    /// Session that ops naming no session go to; forking leaves it as it is
+    active_session: Option<SessionId>,
     /// Tool registry
     tools: Arc<ToolRegistry>,
     /// Channel for receiving operations
-    op_rx: mpsc::UnboundedReceiver<Op>,
+    op_rx: ChannelReceiver<GoblinOp>,
     /// Channel for sending events
-    event_tx: mpsc::UnboundedSender<Event>,
+    event_tx: mpsc::UnboundedSender<GoblinEvent>,
+    /// Orchestration options applied to new sessions
+    options: SessionOptions,
+    /// Injected planner, overriding the strategy selected in the options
+    planner: Option<Arc<dyn Planner>>,
+    /// Injected result merger, overriding the strategy selected in the options
+    merger: Option<Arc<dyn ResultMerger>>,
+    /// Reviewer of subtask results, used when the options enable review
+    reviewer: Option<Arc<dyn Reviewer>>,
+    /// Agent runtimes for every new session
+    runtimes: Runtimes,
+    /// Model providers for every new session
+    models: ModelProviders,
+    /// Summarizer for compacting agent histories, overriding the agents' own models
+    summarizer: Option<Arc<dyn Summarizer>>,
+    /// Cross-session delegations in flight
+    delegations: DelegationBroker,
+    /// Checks run by the startup self-test
+    self_checks: Vec<Arc<dyn SelfCheck>>,
+    /// Recurring task submissions
+    schedules: ScheduleRegistry,
+    /// Automation rules evaluated on the event stream
+    rules: RuleEngine,
+    /// Client event channel, when events are tapped for rule evaluation
+    client_tx: Option<ChannelSender<GoblinEvent>>,
+    /// Tapped events awaiting rule evaluation
+    tap_rx: Option<ChannelReceiver<GoblinEvent>>,
+    /// Write-ahead log of ops and events
+    journal: Option<Arc<dyn Journal>>,
+    /// Recent critical events for late subscribers
+    replay: Option<Arc<ReplayBuffer>>,
+    /// Tasks waiting for their cost estimate to be confirmed, with the
+    /// session and the message that starts them
+    held_tasks: std::collections::HashMap<TaskId, (SessionId, String)>,
 }
 
 impl Orchestrator {
     /// Create a new orchestrator with the given channel pair
+    ///
+    /// Sessions report events on an unbounded channel; with a bounded event
+    /// channel, they are routed through the orchestrator loop, which applies
+    /// the channel's overflow policy.
     pub fn new(tools: ToolRegistry, channels: ChannelPair) -> Self {
+        let (event_tx, client_tx, tap_rx) = match channels.event_tx.unbounded() {
+            Some(tx) => (tx.clone(), None, None),
+            None => {
+                let (tap_tx, tap_rx) = mpsc::unbounded_channel();
+                (tap_tx, Some(channels.event_tx), Some(tap_rx.into()))
+            }
+        };
         Self {
             sessions: parking_lot::RwLock::new(std::collections::HashMap::new()),
+            active_session: None,
             tools: Arc::new(tools),
             op_rx: channels.op_rx,
-            event_tx: channels.event_tx,
+            event_tx,
+            options: SessionOptions::default(),
+            planner: None,
+            merger: None,
+            reviewer: None,
+            runtimes: Runtimes::default(),
+            models: ModelProviders::default(),
+            summarizer: None,
+            delegations: DelegationBroker::new(),
+            self_checks: selftest::default_checks(),
+            schedules: ScheduleRegistry::new(),
+            rules: RuleEngine::default(),
+            client_tx,
+            tap_rx,
+            journal: None,
+            replay: channels.replay,
+            held_tasks: std::collections::HashMap::new(),
+        }
+    }
+
+    /// Add a check to the startup self-test
+    pub fn with_self_check(mut self, check: Arc<dyn SelfCheck>) -> Self {
+        self.self_checks.push(check);
+        self
+    }
+
+    /// Create an orchestrator that plans every task with the given planner
+    pub fn with_planner(
+        tools: ToolRegistry,
+        planner: Arc<dyn Planner>,
+        channels: ChannelPair,
+    ) -> Self {
+        let mut orchestrator = Self::new(tools, channels);
+        orchestrator.planner = Some(planner);
+        orchestrator
+    }
+
+    /// Combine child results with the given merger in every new session
+    pub fn with_merger(mut self, merger: Arc<dyn ResultMerger>) -> Self {
+        self.merger = Some(merger);
+        self
+    }
+
+    /// Run agents with the given runtimes in every new session
+    pub fn with_runtimes(mut self, runtimes: Runtimes) -> Self {
+        self.runtimes = runtimes;
+        self
+    }
+
+    /// Serve agent models with the given providers in every new session
+    pub fn with_models(mut self, models: ModelProviders) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Compact agent histories with the given summarizer in every new session
+    pub fn with_summarizer(mut self, summarizer: Arc<dyn Summarizer>) -> Self {
+        self.summarizer = Some(summarizer);
+        self
+    }
+
+    /// Review subtask results with the given reviewer in every new session
+    pub fn with_reviewer(mut self, reviewer: Arc<dyn Reviewer>) -> Self {
+        self.reviewer = Some(reviewer);
+        self
+    }
+
+    /// Evaluate automation rules on every event
+    ///
+    /// Events are routed through the orchestrator loop, which forwards them
+    /// to the client and executes the actions of rules that fire.
+    pub fn with_rules(mut self, rules: Vec<Rule>) -> Self {
+        self.tap_events();
+        self.rules = RuleEngine::new(rules);
+        self
+    }
+
+    /// Append every op and event to a journal
+    ///
+    /// Ops are appended before they are handled; an op that cannot be
+    /// journaled fails. Events are appended before they reach the client.
+    pub fn with_journal(mut self, journal: Arc<dyn Journal>) -> Self {
+        self.tap_events();
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Route events through the orchestrator loop before they reach the client
+    fn tap_events(&mut self) {
+        if self.tap_rx.is_none() {
+            let (tap_tx, tap_rx) = mpsc::unbounded_channel();
+            self.client_tx = Some(std::mem::replace(&mut self.event_tx, tap_tx).into());
+            self.tap_rx = Some(tap_rx.into());
         }
     }
 
+    /// Set the orchestration options applied to new sessions
+    pub fn with_options(mut self, options: SessionOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Apply a policy document to new sessions, running its budget rules
+    /// on every event
+    ///
+    /// Budget rules replace rules set before with `with_rules`.
+    pub fn with_policy(mut self, policy: PolicyDocument) -> Result<Self, GoblinError> {
+        policy.validate()?;
+        self.options = policy.apply(std::mem::take(&mut self.options));
+        if !policy.budget.is_empty() {
+            self = self.with_rules(policy.budget);
+        }
+        Ok(self)
+    }
+
     /// Create an orchestrator and return a channel for communication
     pub fn with_channel(tools: ToolRegistry) -> (Self, GoblinChannel) {
         let (channel, pair) = GoblinChannel::new();
@@ -50,9 +229,29 @@ impl Orchestrator {
     pub async fn run(mut self) -> Result<(), GoblinError> {
         info!("Starting goblin orchestrator");
 
-        while let Some(op) = self.op_rx.recv().await {
-            if let Err(e) = self.handle_op(op).await {
-                error!(error = %e, "Error handling operation");
+        loop {
+            let next_due = self.schedules.next_due();
+            tokio::select! {
+                op = self.op_rx.recv() => {
+                    let Some(op) = op else { break };
+                    let sub_id = op.sub_id().clone();
+                    if let Err(e) = self.handle_op(op).await {
+                        error!(error = %e, code = e.code(), "Error handling operation");
+                        let _ = self.event_tx.send(GoblinEvent::OpFailed {
+                            sub_id,
+                            code: e.code().to_string(),
+                            category: e.category(),
+                            message: e.to_string(),
+                            retryable: e.is_retryable(),
+                        });
+                    }
+                }
+                Some(event) = recv_tapped(&mut self.tap_rx) => {
+                    self.handle_tapped_event(event).await;
+                }
+                _ = sleep_until(next_due) => {
+                    self.run_due_schedules(Utc::now()).await;
+                }
             }
         }
 
@@ -61,15 +260,181 @@ impl Orchestrator {
     }
 
     /// Handle a single operation
-    async fn handle_op(&mut self, op: Op) -> Result<(), GoblinError> {
+    async fn handle_op(&mut self, op: GoblinOp) -> Result<(), GoblinError> {
         let sub_id = op.sub_id().clone();
-        
+        if let Some(journal) = &self.journal {
+            journal.append(JournalRecord::Op(op.clone()))?;
+        }
+
+        match op {
+            GoblinOp::Protocol(op) => {
+                self.handle_protocol_op(op, &sub_id).await?;
+            }
+            GoblinOp::UserInputWithAttachments { prompt, context, attachments, .. } => {
+                self.handle_user_input(&prompt, context, &attachments, &sub_id).await?;
+            }
+            GoblinOp::RunWorkflow { prompt, workflow, .. } => {
+                let session = self.current_session()?;
+                let task_id = session.start_workflow(workflow, &prompt, &sub_id)?;
+                let _ = self.event_tx.send(Event::TaskStarted {
+                    sub_id: sub_id.clone(),
+                    task_id,
+                    prompt,
+                }.into());
+            }
+            GoblinOp::ApproveWorkflowStage { task_id, stage, .. } => {
+                self.current_session()?.approve_workflow_stage(&task_id, &stage, &sub_id)?;
+            }
+            GoblinOp::WorkflowStageResult { task_id, stage, success, .. } => {
+                self.current_session()?.finish_workflow_stage(&task_id, &stage, success, &sub_id)?;
+            }
+            GoblinOp::DelegateTask { origin_session, origin_task, target_session, prompt, .. } => {
+                self.delegate_task(origin_session, origin_task, target_session, prompt, &sub_id)?;
+            }
+            GoblinOp::CompleteDelegatedTask { session_id, task_id, success, summary, .. } => {
+                self.complete_delegated_task(session_id, task_id, success, summary, &sub_id)?;
+            }
+            GoblinOp::SetTaskWeight { task_id, weight, .. } => {
+                self.current_session()?.set_task_weight(task_id, weight);
+            }
+            GoblinOp::PauseAgent { agent_id, .. } => {
+                self.current_session()?.pause_agent(&agent_id, &sub_id)?;
+            }
+            GoblinOp::ResumeAgent { agent_id, .. } => {
+                self.current_session()?.resume_agent(&agent_id, &sub_id)?;
+            }
+            GoblinOp::SubmitTaskResult { result, .. } => {
+                let session = self.current_session()?;
+                if let Some(result) = session.record_result(result, &sub_id).await? {
+                    session.finish_task(&result.task_id);
+                    info!(task_id = %result.task_id, success = result.is_success(), "Task finished");
+                }
+            }
+            GoblinOp::DescribeCapabilities { .. } => {
+                let capabilities = self.capabilities();
+                let _ = self.event_tx.send(GoblinEvent::Capabilities { sub_id, capabilities });
+            }
+            GoblinOp::ScheduleTask { cron_or_interval, prompt, context, .. } => {
+                let spec: ScheduleSpec = cron_or_interval.parse()?;
+                let schedule = self.schedules.add(spec, prompt, context, Utc::now())?;
+                info!(schedule_id = %schedule.id, next_run = %schedule.next_run, "Scheduled task");
+                let _ = self.event_tx.send(GoblinEvent::TaskScheduled { sub_id, schedule });
+            }
+            GoblinOp::ForkSession { from, at_task, .. } => {
+                self.fork_session(&from, at_task, &sub_id).await?;
+            }
+            GoblinOp::UpdateSessionConfig { session_id, patch, .. } => {
+                let session = self.get_session(&session_id).ok_or(GoblinError::SessionNotFound(session_id))?;
+                session.update_config(&patch, &sub_id)?;
+            }
+            GoblinOp::FetchArtifact { artifact_id, .. } => {
+                let artifact = self.current_session()?.artifacts().get(&artifact_id);
+                let _ = self.event_tx.send(GoblinEvent::ArtifactContent { sub_id, artifact_id, artifact });
+            }
+            GoblinOp::StoreGet { key, .. } => {
+                let entry = self.current_session()?.store().get(&key);
+                let _ = self.event_tx.send(GoblinEvent::StoreValue { sub_id, key, entry });
+            }
+            GoblinOp::StoreSet { key, value, expected_version, .. } => {
+                let session = self.current_session()?;
+                let store = session.store();
+                match expected_version {
+                    None => {
+                        store.set(key, value, None, &sub_id);
+                    }
+                    Some(expected_version) => {
+                        if let Err(current) = store.compare_and_swap(key.clone(), expected_version, value, None, &sub_id) {
+                            let _ = self.event_tx.send(GoblinEvent::StoreConflict {
+                                sub_id,
+                                key,
+                                expected_version,
+                                current,
+                            });
+                        }
+                    }
+                }
+            }
+            GoblinOp::DescribeAgents { .. } => {
+                let agents = self.current_session()?.agent_summaries();
+                let _ = self.event_tx.send(GoblinEvent::AgentList { sub_id, agents });
+            }
+            GoblinOp::DescribeMetrics { session_id, .. } => {
+                let session = self.get_session(&session_id).ok_or(GoblinError::SessionNotFound(session_id))?;
+                let metrics = session.metrics();
+                let _ = self.event_tx.send(GoblinEvent::Metrics { sub_id, session_id, metrics });
+            }
+            GoblinOp::GrantSandboxEscalation { call_id, granted, ttl_secs, .. } => {
+                let ttl = ttl_secs.map(std::time::Duration::from_secs);
+                let sessions: Vec<SessionHandle> = self.sessions.read().values().cloned().collect();
+                if !sessions.iter().any(|s| s.escalations().resolve(&call_id, granted, ttl)) {
+                    warn!(call_id = %call_id, "No pending sandbox escalation for call");
+                }
+            }
+            GoblinOp::QueryAudit { session_id, query, .. } => {
+                let session = self.get_session(&session_id).ok_or(GoblinError::SessionNotFound(session_id))?;
+                let entries = session.audit().query(&query);
+                let _ = self.event_tx.send(GoblinEvent::AuditEntries { sub_id, session_id, entries });
+            }
+            GoblinOp::ExportAudit { session_id, path, .. } => {
+                let session = self.get_session(&session_id).ok_or(GoblinError::SessionNotFound(session_id))?;
+                let (path, entries) = session.audit().export(&path)?;
+                info!(session_id = %session_id, path = %path.display(), entries, "Exported audit log");
+                let _ = self.event_tx.send(GoblinEvent::AuditExported { sub_id, session_id, path, entries });
+            }
+            GoblinOp::SetBudgetCeiling { session_id, agent_id, max_tokens, .. } => {
+                let session = self.get_session(&session_id).ok_or(GoblinError::SessionNotFound(session_id))?;
+                session.ledger().set_ceiling(agent_id, max_tokens, &sub_id)?;
+                info!(session_id = %session_id, agent_id = ?agent_id, max_tokens = ?max_tokens, "Changed budget ceiling");
+            }
+            GoblinOp::DescribeBudgets { session_id, .. } => {
+                let session = self.get_session(&session_id).ok_or(GoblinError::SessionNotFound(session_id))?;
+                let budgets = session.ledger().rollup();
+                let _ = self.event_tx.send(GoblinEvent::Budgets { sub_id, session_id, budgets });
+            }
+            GoblinOp::ConfirmCostEstimate { task_id, approved, .. } => {
+                self.confirm_cost_estimate(task_id, approved, &sub_id)?;
+            }
+            GoblinOp::ListPendingApprovals { session_id, .. } => {
+                let session = self.get_session(&session_id).ok_or(GoblinError::SessionNotFound(session_id))?;
+                let approvals = session.approvals().list();
+                let _ = self.event_tx.send(GoblinEvent::PendingApprovals { sub_id, session_id, approvals });
+            }
+            GoblinOp::DescribeChannels { .. } => {
+                let channels = self.channel_stats();
+                let _ = self.event_tx.send(GoblinEvent::ChannelHealth { sub_id, channels });
+            }
+            GoblinOp::DescribeHierarchy { .. } => {
+                let snapshot = self.current_session()?.hierarchy_snapshot();
+                let _ = self.event_tx.send(GoblinEvent::HierarchySnapshot { sub_id, snapshot });
+            }
+            GoblinOp::ListSchedules { .. } => {
+                let schedules = self.schedules.list();
+                let _ = self.event_tx.send(GoblinEvent::ScheduleList { sub_id, schedules });
+            }
+            GoblinOp::CancelSchedule { schedule_id, .. } => {
+                if !self.schedules.cancel(&schedule_id) {
+                    return Err(GoblinError::ConfigError(format!(
+                        "Unknown schedule: {}",
+                        schedule_id
+                    )));
+                }
+                let _ = self.event_tx.send(GoblinEvent::ScheduleCancelled { sub_id, schedule_id });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle a core protocol operation
+    async fn handle_protocol_op(&mut self, op: Op, sub_id: &SubmissionId) -> Result<(), GoblinError> {
+        let sub_id = sub_id.clone();
+
         match op {
             Op::ConfigureSession { config, .. } => {
                 self.configure_session(config, &sub_id).await?;
             }
             Op::UserInput { prompt, context, .. } => {
-                self.handle_user_input(&prompt, context, &sub_id).await?;
+                self.handle_user_input(&prompt, context, &[], &sub_id).await?;
             }
             Op::Interrupt { task_id, .. } => {
                 self.handle_interrupt(task_id, &sub_id).await?;
@@ -92,20 +457,59 @@ impl Orchestrator {
     }
 
     /// Configure or create a session
+    ///
+    /// MCP servers connect in the background; their tools are offered once
+    /// they are listed.
     async fn configure_session(
         &mut self,
         config: SessionConfig,
         sub_id: &SubmissionId,
     ) -> Result<SessionHandle, GoblinError> {
-        let session = Session::new(
-            config.clone(),
-            Arc::clone(&self.tools),
-            self.event_tx.clone(),
-        );
+        self.options.validate()?;
+        let mut config = config;
+        self.options.spawn.apply(&mut config);
+
+        let session = self.build_session(config.clone(), self.options.clone());
         let session_id = session.id;
+
+        if let Some(settings) = &self.options.self_test {
+            let ctx = SelfTestContext {
+                tools: &self.tools,
+                config: &config,
+                settings,
+            };
+            let results = selftest::run_checks(&self.self_checks, &ctx).await;
+            let failed: Vec<String> = results.iter()
+                .filter(|r| !r.passed)
+                .map(|r| format!("{} ({})", r.name, r.message))
+                .collect();
+
+            let _ = self.event_tx.send(GoblinEvent::SelfTestCompleted {
+                sub_id: sub_id.clone(),
+                session_id,
+                passed: failed.is_empty(),
+                results,
+            });
+
+            if !failed.is_empty() {
+                warn!(session_id = %session_id, failed = ?failed, "Session self-test failed");
+                if settings.fail_fast {
+                    return Err(GoblinError::ConfigError(format!(
+                        "Self-test failed: {}",
+                        failed.join(", ")
+                    )));
+                }
+            }
+        }
         let handle = SessionHandle::new(session);
 
         self.sessions.write().insert(session_id, handle.clone());
+        self.active_session = Some(session_id);
+        handle.start_watchdog(sub_id);
+        if !handle.options.mcp_servers.is_empty() {
+            let (connecting, sub_id) = (handle.clone(), sub_id.clone());
+            tokio::spawn(async move { connecting.connect_mcp_servers(&sub_id).await });
+        }
 
         // Create the root orchestrator agent
         let orchestrator_config = AgentConfig {
@@ -124,64 +528,445 @@ impl Orchestrator {
             sub_id: sub_id.clone(),
             session_id,
             config,
-        });
+        }.into());
 
         info!(session_id = %session_id, "Session configured");
         Ok(handle)
     }
 
+    /// Create a session with the orchestrator's injected strategies
+    fn build_session(&self, config: SessionConfig, options: SessionOptions) -> Session {
+        let mut session = Session::with_options(
+            config,
+            options,
+            Arc::clone(&self.tools),
+            self.event_tx.clone(),
+        );
+        if let Some(planner) = &self.planner {
+            session = session.with_planner(Arc::clone(planner));
+        }
+        if let Some(merger) = &self.merger {
+            session = session.with_merger(Arc::clone(merger));
+        }
+        if let Some(reviewer) = &self.reviewer {
+            session = session.with_reviewer(Arc::clone(reviewer));
+        }
+        if let Some(summarizer) = &self.summarizer {
+            session = session.with_summarizer(Arc::clone(summarizer));
+        }
+        session
+            .with_runtimes(self.runtimes.clone())
+            .with_models(self.models.clone())
+    }
+
+    /// Rebuild a session from a checkpoint file
+    ///
+    /// Agents come back with their original ids, history and tasks; their
+    /// actors are started and unfinished tasks are assigned again. Clients
+    /// get a `SessionConfigured` event and a snapshot of the hierarchy, as
+    /// if the session had just been configured.
+    pub async fn restore_session(
+        &mut self,
+        path: impl AsRef<Path>,
+        sub_id: &SubmissionId,
+    ) -> Result<SessionHandle, GoblinError> {
+        let checkpoint = SessionCheckpoint::load(path)?;
+        checkpoint.options.validate()?;
+        let (handle, resumed) = self.start_from_checkpoint(&checkpoint, sub_id)?;
+        self.active_session = Some(handle.id);
+        info!(session_id = %handle.id, resumed_tasks = resumed.len(), "Session restored");
+        Ok(handle)
+    }
+
+    /// Copy a running session's hierarchy and agent contexts into a new session
+    ///
+    /// The source session is left untouched. See [`SessionCheckpoint::fork`]
+    /// for what `at_task` keeps.
+    pub async fn fork_session(
+        &mut self,
+        from: &SessionId,
+        at_task: Option<TaskId>,
+        sub_id: &SubmissionId,
+    ) -> Result<SessionHandle, GoblinError> {
+        let source = self.get_session(from).ok_or(GoblinError::SessionNotFound(*from))?;
+        let checkpoint = source.to_checkpoint();
+        let fork = checkpoint.fork(at_task)?;
+        let (handle, resumed) = self.start_from_checkpoint(&fork, sub_id)?;
+
+        let agents = checkpoint
+            .agents
+            .iter()
+            .zip(&fork.agents)
+            .map(|(original, copy)| (original.agent_id, copy.agent_id))
+            .collect();
+        let _ = self.event_tx.send(GoblinEvent::SessionForked {
+            sub_id: sub_id.clone(),
+            source: *from,
+            session_id: handle.id,
+            agents,
+        });
+
+        info!(source = %from, session_id = %handle.id, resumed_tasks = resumed.len(), "Session forked");
+        Ok(handle)
+    }
+
+    /// Start a session with the state saved in a checkpoint and resume its agents
+    fn start_from_checkpoint(
+        &mut self,
+        checkpoint: &SessionCheckpoint,
+        sub_id: &SubmissionId,
+    ) -> Result<(SessionHandle, Vec<TaskId>), GoblinError> {
+        let session_id = checkpoint.session_id;
+        if self.sessions.read().contains_key(&session_id) {
+            return Err(GoblinError::ConfigError(format!("Session {} is already running", session_id)));
+        }
+
+        let session = self
+            .build_session(checkpoint.config.clone(), checkpoint.options.clone())
+            .with_id(session_id);
+        session.restore(checkpoint)?;
+        let handle = SessionHandle::new(session);
+        self.sessions.write().insert(session_id, handle.clone());
+        handle.start_watchdog(sub_id);
+        if !handle.options.mcp_servers.is_empty() {
+            let (connecting, sub_id) = (handle.clone(), sub_id.clone());
+            tokio::spawn(async move { connecting.connect_mcp_servers(&sub_id).await });
+        }
+
+        let resumed = handle.resume_agents(sub_id)?;
+
+        let _ = self.event_tx.send(Event::SessionConfigured {
+            sub_id: sub_id.clone(),
+            session_id,
+            config: checkpoint.config.clone(),
+        }.into());
+        let _ = self.event_tx.send(GoblinEvent::HierarchySnapshot {
+            sub_id: sub_id.clone(),
+            snapshot: handle.hierarchy_snapshot(),
+        });
+        Ok((handle, resumed))
+    }
+
+    /// Rebuild state by handling the ops of a journal again, in order
+    ///
+    /// Ops that failed when they were first handled fail again and are
+    /// skipped, as are ops that would act outside the orchestrator, like
+    /// exporting the audit log or answering an approval request of the
+    /// process that wrote the journal. Replayed ops and their events are not
+    /// appended to this orchestrator's own journal. Returns the number of
+    /// ops replayed.
+    pub async fn replay(&mut self, journal: &dyn Journal) -> Result<usize, GoblinError> {
+        let entries = journal.entries()?;
+        let recording = self.journal.take();
+
+        let mut replayed = 0;
+        for entry in entries {
+            let JournalRecord::Op(op) = entry.record else { continue };
+            if has_side_effects(&op) {
+                continue;
+            }
+            if let Err(e) = self.handle_op(op).await {
+                debug!(seq = entry.seq, error = %e, "Replayed op failed");
+            }
+            replayed += 1;
+            while let Some(event) = self.tap_rx.as_mut().and_then(|rx| rx.try_recv().ok()) {
+                self.handle_tapped_event(event).await;
+            }
+        }
+
+        self.journal = recording;
+        info!(journal = journal.name(), ops = replayed, "Replayed journal");
+        Ok(replayed)
+    }
+
+    /// Recover from a crash by replaying the journal the crashed process wrote
+    ///
+    /// Like [`Orchestrator::replay`], but ops that started a task that
+    /// finished before the crash, or created a session with no task in
+    /// flight, are skipped. In-flight tasks are started again, under new
+    /// ids, when their op can be replayed, and are lost otherwise. Tool
+    /// calls that were running are ended as failed and commands that were
+    /// waiting for approval are denied, both as aborted. The report is also
+    /// sent in a `RecoveryCompleted` event.
+    pub async fn recover(&mut self, journal: &dyn Journal) -> Result<RecoveryReport, GoblinError> {
+        let entries = journal.entries()?;
+        let mut scan = JournalScan::new(&entries);
+        let recording = self.journal.take();
+
+        let mut report = RecoveryReport::default();
+        for entry in entries {
+            let JournalRecord::Op(op) = entry.record else { continue };
+            let sub_id = op.sub_id().clone();
+            if has_side_effects(&op) || scan.is_finished(&sub_id) || scan.is_idle_session(&sub_id) {
+                report.skipped += 1;
+                continue;
+            }
+            let interrupted = scan.take_in_flight(&sub_id);
+            if let Err(e) = self.handle_op(op).await {
+                debug!(seq = entry.seq, error = %e, "Recovered op failed");
+            }
+            report.ops += 1;
+
+            let mut started = None;
+            while let Some(event) = self.tap_rx.as_mut().and_then(|rx| rx.try_recv().ok()) {
+                match &event {
+                    GoblinEvent::Protocol(Event::SessionConfigured { sub_id: id, session_id, .. }) if *id == sub_id => {
+                        report.sessions.push(*session_id);
+                    }
+                    GoblinEvent::Protocol(Event::TaskStarted { sub_id: id, task_id, .. }) if *id == sub_id => {
+                        started = Some(*task_id);
+                    }
+                    _ => {}
+                }
+                self.handle_tapped_event(event).await;
+            }
+
+            if let Some(mut task) = interrupted {
+                task.resumed_as = started;
+                match started {
+                    Some(_) => report.resumed.push(task),
+                    None => report.lost.push(task),
+                }
+            }
+        }
+        self.journal = recording;
+
+        let sub_id = SubmissionId::new();
+        let (lost, pending_calls, running_tools) = scan.into_remaining();
+        report.lost.extend(lost);
+        for call in running_tools {
+            let _ = self.event_tx.send(GoblinEvent::ToolCallFinished {
+                sub_id: sub_id.clone(),
+                call_id: call.call_id.clone(),
+                agent_id: call.agent_id,
+                tool: call.tool.clone(),
+                success: false,
+            });
+            report.aborted_tool_calls.push(call);
+        }
+        for request in pending_calls {
+            let _ = self.event_tx.send(GoblinEvent::ExecApprovalDecided {
+                sub_id: sub_id.clone(),
+                call_id: request.call_id.clone(),
+                agent_id: request.agent_id,
+                approved: false,
+                rule: Some("aborted".to_string()),
+                decided_by: None,
+            });
+            report.aborted_calls.push(request.call_id);
+        }
+        let _ = self.event_tx.send(GoblinEvent::RecoveryCompleted {
+            sub_id,
+            report: report.clone(),
+        });
+        while let Some(event) = self.tap_rx.as_mut().and_then(|rx| rx.try_recv().ok()) {
+            self.handle_tapped_event(event).await;
+        }
+
+        info!(
+            journal = journal.name(),
+            ops = report.ops,
+            skipped = report.skipped,
+            resumed = report.resumed.len(),
+            lost = report.lost.len(),
+            "Recovered journal"
+        );
+        Ok(report)
+    }
+
     /// Handle user input - start a new task
     async fn handle_user_input(
         &mut self,
         prompt: &str,
         context: TaskContext,
+        attachments: &[Attachment],
         sub_id: &SubmissionId,
     ) -> Result<(), GoblinError> {
-        // Get the current session (assumes single session for now)
-        let session = self.sessions.read().values().next().cloned()
-            .ok_or_else(|| GoblinError::NoActiveSession)?;
+        let session = self.current_session()?;
 
         // Create task ID
         let task_id = TaskId::new();
-        session.set_current_task(Some(task_id));
+
+        // Store attachments before starting so a bad path rejects the task
+        let mut artifact_ids = Vec::with_capacity(attachments.len());
+        let mut artifact_refs = Vec::with_capacity(attachments.len());
+        let root = session.config().cwd.unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
+        for attachment in attachments {
+            let artifact_id = session.artifacts().store_attachment(attachment, &root, Some(task_id))?;
+            artifact_refs.push(format!("- {} (artifact {})", attachment.name, artifact_id));
+            artifact_ids.push(artifact_id);
+        }
+
+        session.start_task(task_id, prompt);
 
         // Emit task started
         let _ = self.event_tx.send(Event::TaskStarted {
             sub_id: sub_id.clone(),
             task_id,
             prompt: prompt.to_string(),
-        });
+        }.into());
 
         // Get orchestrator agent
         let orchestrator = session.orchestrator()
             .ok_or_else(|| GoblinError::NoOrchestrator)?;
 
+        let plan = session.plan_task(&PlanRequest {
+            task_id,
+            prompt: prompt.to_string(),
+            context,
+            attachments: artifact_ids,
+        }).await?;
+
         // TODO: Send prompt to orchestrator agent
         // For now, emit a placeholder message
-        orchestrator.emit_message(sub_id, format!("Received task: {}", prompt), false);
+        let mut message = format!("Received task: {}", prompt);
+        if !artifact_refs.is_empty() {
+            message.push_str("\n\nAttachments:\n");
+            message.push_str(&artifact_refs.join("\n"));
+        }
 
-        info!(task_id = %task_id, "Started task");
+        if let Some(estimate) = session.estimate_cost(&plan) {
+            let held = estimate.needs_confirmation;
+            let _ = self.event_tx.send(GoblinEvent::CostEstimate { sub_id: sub_id.clone(), estimate });
+            if held {
+                info!(task_id = %task_id, "Task held for cost confirmation");
+                self.held_tasks.insert(task_id, (session.id, message));
+                return Ok(());
+            }
+        }
+        orchestrator.emit_message(sub_id, message, false);
+
+        info!(
+            task_id = %task_id,
+            attachments = attachments.len(),
+            subtasks = plan.len(),
+            "Started task"
+        );
+        Ok(())
+    }
+
+    /// Start a task held for its cost estimate, or drop it
+    fn confirm_cost_estimate(
+        &mut self,
+        task_id: TaskId,
+        approved: bool,
+        sub_id: &SubmissionId,
+    ) -> Result<(), GoblinError> {
+        let (session_id, message) = self.held_tasks.remove(&task_id).ok_or_else(|| {
+            GoblinError::InvalidState(format!("Task {} is not waiting for a cost confirmation", task_id))
+        })?;
+        let session = self.get_session(&session_id).ok_or(GoblinError::SessionNotFound(session_id))?;
+        if approved {
+            let orchestrator = session.orchestrator().ok_or(GoblinError::NoOrchestrator)?;
+            orchestrator.emit_message(sub_id, message, false);
+            info!(task_id = %task_id, "Cost estimate confirmed");
+        } else {
+            let _ = self.event_tx.send(Event::TaskInterrupted {
+                sub_id: sub_id.clone(),
+                task_id,
+            }.into());
+            session.finish_task(&task_id);
+            info!(task_id = %task_id, "Task dropped at its cost estimate");
+        }
         Ok(())
     }
 
+    /// Forward a tapped event to the client and run the rules it fires
+    async fn handle_tapped_event(&mut self, event: GoblinEvent) {
+        // Usage thresholds apply to the session the event came from
+        let usage = self.session_of(&event)
+            .and_then(|id| self.get_session(&id))
+            .map(|s| s.total_usage())
+            .unwrap_or_default();
+        let firings = self.rules.observe(&event, &usage);
+
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.append(JournalRecord::Event(event.clone())) {
+                warn!(journal = journal.name(), error = %e, "Failed to journal event");
+            }
+        }
+        if let Some(client_tx) = &self.client_tx {
+            let event = match &self.replay {
+                Some(replay) => replay.publish(self.session_of(&event), event),
+                None => event,
+            };
+            if let Err(ChannelError::Full) = client_tx.send_async(event).await {
+                warn!("Client event channel full, dropping event");
+            }
+        }
+
+        for firing in firings {
+            let sub_id = SubmissionId::new();
+            let mut notifications = Vec::new();
+            info!(rule = %firing.rule, agent_id = ?firing.agent_id, task_id = ?firing.task_id, "Rule fired");
+
+            for action in firing.actions {
+                let result = match action {
+                    Action::Notify { target, message } => {
+                        notifications.push(crate::rules::Notification { target, message });
+                        Ok(())
+                    }
+                    Action::InterruptTask => match firing.task_id {
+                        Some(task_id) => self.handle_interrupt(Some(task_id), &sub_id).await,
+                        None => Ok(()),
+                    },
+                    Action::TerminateAgent => match firing.agent_id {
+                        Some(agent_id) => {
+                            let reason = Some(format!("Rule '{}' fired", firing.rule));
+                            self.terminate_agent(&agent_id, reason, &sub_id).await
+                        }
+                        None => Ok(()),
+                    },
+                    Action::Op(op) => self.handle_op(op).await,
+                };
+                if let Err(e) = result {
+                    warn!(rule = %firing.rule, error = %e, "Rule action failed");
+                }
+            }
+
+            let _ = self.event_tx.send(GoblinEvent::RuleFired {
+                sub_id,
+                rule: firing.rule,
+                agent_id: firing.agent_id,
+                task_id: firing.task_id,
+                notifications,
+            });
+        }
+    }
+
+    /// Submit every scheduled task that is due
+    async fn run_due_schedules(&mut self, now: DateTime<Utc>) {
+        for (prompt, context) in self.schedules.take_due(now) {
+            let sub_id = SubmissionId::new();
+            if let Err(e) = self.handle_user_input(&prompt, context, &[], &sub_id).await {
+                warn!(error = %e, prompt = %prompt, "Scheduled task failed to start");
+            }
+        }
+    }
+
     /// Handle interrupt
     async fn handle_interrupt(
         &mut self,
         task_id: Option<TaskId>,
         sub_id: &SubmissionId,
     ) -> Result<(), GoblinError> {
-        // Get the current session
-        let session = self.sessions.read().values().next().cloned()
-            .ok_or_else(|| GoblinError::NoActiveSession)?;
+        let session = self.current_session()?;
+
+        // Without a task ID, every task in progress is interrupted
+        let targets = match task_id {
+            Some(tid) => vec![tid],
+            None => session.active_tasks().into_iter().map(|t| t.task_id).collect(),
+        };
 
-        let current_task = task_id.or_else(|| session.current_task());
-        
-        if let Some(tid) = current_task {
+        for tid in targets {
+            // A task held for its cost estimate can no longer be confirmed
+            self.held_tasks.remove(&tid);
+            session.interrupt_agents(&tid);
             let _ = self.event_tx.send(Event::TaskInterrupted {
                 sub_id: sub_id.clone(),
                 task_id: tid,
-            });
-            session.set_current_task(None);
+            }.into());
+            session.finish_task(&tid);
             info!(task_id = %tid, "Task interrupted");
         }
 
@@ -195,10 +980,24 @@ impl Orchestrator {
         parent_id: Option<AgentId>,
         sub_id: &SubmissionId,
     ) -> Result<(), GoblinError> {
-        let session = self.sessions.read().values().next().cloned()
-            .ok_or_else(|| GoblinError::NoActiveSession)?;
+        let session = self.current_session()?;
 
-        session.spawn_agent(config, parent_id, sub_id)?;
+        let agent = match session.spawn_agent(config, parent_id, sub_id) {
+            Ok(agent) => agent,
+            Err(e @ (GoblinError::SpawnDenied(_) | GoblinError::SpawnRateLimited { .. })) => {
+                let _ = self.event_tx.send(GoblinEvent::SpawnDenied {
+                    sub_id: sub_id.clone(),
+                    parent_id,
+                    reason: e.to_string(),
+                    code: e.code().to_string(),
+                    retry_after_ms: e.retry_after().map(|d| d.as_millis() as u64),
+                });
+                // Reported; returning the error would fail the op a second time
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        session.start_actor(&agent.id(), sub_id)?;
         Ok(())
     }
 
@@ -209,10 +1008,10 @@ impl Orchestrator {
         reason: Option<String>,
         sub_id: &SubmissionId,
     ) -> Result<(), GoblinError> {
-        let session = self.sessions.read().values().next().cloned()
-            .ok_or_else(|| GoblinError::NoActiveSession)?;
+        let session = self.current_session()?;
 
         session.terminate_agent(agent_id, reason.unwrap_or_default(), sub_id)?;
+        session.join_terminated().await;
         Ok(())
     }
 
@@ -223,11 +1022,174 @@ impl Orchestrator {
         approved: bool,
         sub_id: &SubmissionId,
     ) -> Result<(), GoblinError> {
-        // TODO: Route approval to the agent that requested it
         debug!(call_id = %call_id, approved = approved, "Execution approval received");
+        let sessions: Vec<SessionHandle> = self.sessions.read().values().cloned().collect();
+        if !sessions.iter().any(|s| s.approvals().resolve(&call_id, approved)) {
+            warn!(call_id = %call_id, "No pending approval for call");
+        }
         Ok(())
     }
 
+    /// Hand a subtask from one session to another
+    fn delegate_task(
+        &mut self,
+        origin_session: SessionId,
+        origin_task: TaskId,
+        target_session: SessionId,
+        prompt: String,
+        sub_id: &SubmissionId,
+    ) -> Result<TaskId, GoblinError> {
+        let origin = self.get_session(&origin_session)
+            .ok_or(GoblinError::SessionNotFound(origin_session))?;
+        let target = self.get_session(&target_session)
+            .ok_or(GoblinError::SessionNotFound(target_session))?;
+
+        let target_task = TaskId::new();
+        self.delegations.open(
+            Delegation {
+                origin_session,
+                origin_task,
+                target_session,
+                target_task,
+            },
+            &target.options.delegation,
+        )?;
+        origin.mark_delegated(&origin_task);
+        target.start_task(target_task, prompt.clone());
+
+        let _ = self.event_tx.send(Event::TaskStarted {
+            sub_id: sub_id.clone(),
+            task_id: target_task,
+            prompt: prompt.clone(),
+        }.into());
+        if let Some(orchestrator) = target.orchestrator() {
+            orchestrator.emit_message(
+                sub_id,
+                format!("Delegated task from session {}: {}", origin_session, prompt),
+                false,
+            );
+        }
+
+        info!(
+            origin_session = %origin_session,
+            target_session = %target_session,
+            task_id = %target_task,
+            "Delegated task"
+        );
+        Ok(target_task)
+    }
+
+    /// Route the outcome of delegated work back to the origin session
+    fn complete_delegated_task(
+        &mut self,
+        session_id: SessionId,
+        task_id: TaskId,
+        success: bool,
+        summary: String,
+        sub_id: &SubmissionId,
+    ) -> Result<(), GoblinError> {
+        let delegation = self.delegations.close(&session_id, &task_id).ok_or_else(|| {
+            GoblinError::InvalidState(format!("Task {} is not a delegated task", task_id))
+        })?;
+        let origin = self.get_session(&delegation.origin_session)
+            .ok_or(GoblinError::SessionNotFound(delegation.origin_session))?;
+        if let Some(target) = self.get_session(&session_id) {
+            target.finish_task(&task_id);
+        }
+
+        origin.receive_delegated_result(
+            delegation.origin_task,
+            DelegationOutcome {
+                from_session: session_id,
+                success,
+                summary,
+            },
+            sub_id,
+        );
+        Ok(())
+    }
+
+    /// Describe what this orchestrator supports
+    pub fn capabilities(&self) -> Capabilities {
+        let mut tools: Vec<String> = self.tools.names().into_iter().map(|n| n.to_string()).collect();
+        tools.sort();
+
+        let mut models: Vec<String> = self.sessions.read().values()
+            .map(|s| s.config().model)
+            .collect();
+        models.sort();
+        models.dedup();
+
+        let mut planners: Vec<String> = PlannerKind::ALL.iter()
+            .map(|k| k.build().name().to_string())
+            .collect();
+        planners.extend(self.planner.as_ref().map(|p| p.name().to_string()));
+        planners.dedup();
+
+        let mut mergers: Vec<String> = MergerKind::ALL.iter()
+            .map(|k| k.build().name().to_string())
+            .collect();
+        mergers.extend(self.merger.as_ref().map(|m| m.name().to_string()));
+        mergers.dedup();
+
+        let runtimes = self.runtimes.names();
+
+        Capabilities {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            tools,
+            models,
+            roles: capabilities::ROLES.iter().map(|r| r.to_string()).collect(),
+            planners,
+            mergers,
+            runtimes,
+            model_providers: self.models.names(),
+            features: capabilities::FEATURES.iter().map(|f| f.to_string()).collect(),
+            cargo_features: capabilities::cargo_features(),
+        }
+    }
+
+    /// Session that ops naming no session go to: the one configured or
+    /// restored last
+    fn current_session(&self) -> Result<SessionHandle, GoblinError> {
+        self.active_session
+            .and_then(|id| self.get_session(&id))
+            .ok_or(GoblinError::NoActiveSession)
+    }
+
+    /// Health of the orchestrator's channels, by name
+    ///
+    /// `ops` is the client's operation channel. When events are routed
+    /// through the orchestrator loop, `events` is the internal queue of
+    /// session events and `client_events` the channel to the client.
+    pub fn channel_stats(&self) -> Vec<(String, ChannelStats)> {
+        let mut channels = vec![("ops".to_string(), self.op_rx.stats())];
+        if let Some(tap_rx) = &self.tap_rx {
+            channels.push(("events".to_string(), tap_rx.stats()));
+        }
+        if let Some(client_tx) = &self.client_tx {
+            channels.push(("client_events".to_string(), client_tx.stats()));
+        }
+        channels
+    }
+
+    /// Session an event belongs to, by the session, agent or task it names
+    fn session_of(&self, event: &GoblinEvent) -> Option<SessionId> {
+        let (_, body) = event.describe()?;
+        let field = |name: &str| body.get(name).cloned().filter(|v| !v.is_null());
+        if let Some(session_id) = field("session_id").and_then(|v| serde_json::from_value(v).ok()) {
+            return Some(session_id);
+        }
+
+        let sessions = self.sessions.read();
+        let agent_id: Option<AgentId> = field("agent_id").and_then(|v| serde_json::from_value(v).ok());
+        let task_id: Option<TaskId> = field("task_id").and_then(|v| serde_json::from_value(v).ok());
+        let owner = sessions.values().find(|s| {
+            agent_id.is_some_and(|id| s.get_agent(&id).is_some())
+                || task_id.is_some_and(|id| s.is_task_active(&id) || s.plan(&id).is_some())
+        });
+        owner.map(|s| s.id).or(self.active_session)
+    }
+
     /// Get a session by ID
     pub fn get_session(&self, id: &SessionId) -> Option<SessionHandle> {
         self.sessions.read().get(id).cloned()
@@ -239,6 +1201,25 @@ impl Orchestrator {
     }
 }
 
+/// Receive the next tapped event, or wait forever if events are not tapped
+async fn recv_tapped(tap_rx: &mut Option<ChannelReceiver<GoblinEvent>>) -> Option<GoblinEvent> {
+    match tap_rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Sleep until a schedule is due, or forever if nothing is scheduled
+async fn sleep_until(due: Option<DateTime<Utc>>) {
+    match due {
+        Some(due) => {
+            let wait = (due - Utc::now()).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,4 +1231,767 @@ mod tests {
         let (orchestrator, _channel) = Orchestrator::with_channel(tools);
         assert!(orchestrator.session_ids().is_empty());
     }
+
+    #[tokio::test]
+    async fn test_user_input_with_attachments() {
+        let (mut orchestrator, _channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let sub_id = SubmissionId::new();
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+
+        let op = GoblinOp::user_input_with_attachments(
+            "Summarize the notes",
+            TaskContext::default(),
+            vec![Attachment::text("notes.txt", "hello")],
+        );
+        orchestrator.handle_op(op).await.unwrap();
+
+        let task_id = session.active_tasks()[0].task_id;
+        assert_eq!(session.artifacts().for_task(&task_id).len(), 1);
+        assert_eq!(session.plan(&task_id).unwrap().prompt, "Summarize the notes");
+    }
+
+    #[tokio::test]
+    async fn test_costly_task_waits_for_confirmation() {
+        use crate::estimate::EstimatePolicy;
+        use crate::pricing::{ModelPrice, PricingTable};
+
+        let (orchestrator, mut channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let options = SessionOptions::new()
+            .with_pricing(PricingTable::new().with_default(ModelPrice::new(3.0, 15.0)))
+            .with_estimate(EstimatePolicy::new().with_confirmation_above(0.01));
+        let mut orchestrator = orchestrator.with_options(options);
+        let sub_id = SubmissionId::new();
+        let session = orchestrator.configure_session(SessionConfig::default(), &sub_id).await.unwrap();
+        while channel.try_recv().is_some() {}
+
+        orchestrator
+            .handle_user_input("Rewrite the parser", TaskContext::default(), &[], &sub_id)
+            .await
+            .unwrap();
+        let task_id = session.active_tasks()[0].task_id;
+        let events: Vec<_> = std::iter::from_fn(|| channel.try_recv()).collect();
+        assert!(events.iter().any(|e| matches!(
+            e,
+            GoblinEvent::CostEstimate { estimate, .. } if estimate.task_id == task_id && estimate.needs_confirmation
+        )));
+        assert!(!events.iter().any(|e| matches!(e, GoblinEvent::AgentMessage { .. })));
+
+        let confirm = |approved| GoblinOp::ConfirmCostEstimate { sub_id: SubmissionId::new(), task_id, approved };
+        orchestrator.handle_op(confirm(false)).await.unwrap();
+        assert!(session.active_tasks().is_empty());
+        assert!(orchestrator.handle_op(confirm(true)).await.is_err());
+
+        // Interrupting a held task drops it
+        orchestrator
+            .handle_user_input("Rewrite the lexer", TaskContext::default(), &[], &sub_id)
+            .await
+            .unwrap();
+        let held = session.active_tasks()[0].task_id;
+        orchestrator.handle_interrupt(Some(held), &sub_id).await.unwrap();
+        assert!(orchestrator.held_tasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_denied_spawn_is_reported_once() {
+        use crate::config::SpawnLimits;
+
+        let limits = SpawnLimits {
+            max_agents: Some(1),
+            ..SpawnLimits::default()
+        };
+        let (orchestrator, mut channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let mut orchestrator = orchestrator.with_options(SessionOptions::new().with_spawn_limits(limits));
+        let sub_id = SubmissionId::new();
+        let session = orchestrator.configure_session(SessionConfig::default(), &sub_id).await.unwrap();
+        let root = session.spawn_agent(AgentConfig::default(), None, &sub_id).unwrap();
+        while channel.try_recv().is_some() {}
+
+        orchestrator
+            .spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id)
+            .await
+            .unwrap();
+        let events: Vec<_> = std::iter::from_fn(|| channel.try_recv()).collect();
+        assert!(events.iter().any(|e| matches!(e, GoblinEvent::SpawnDenied { code, .. } if code == "spawn_denied")));
+        assert!(!events.iter().any(|e| matches!(e, GoblinEvent::OpFailed { .. })));
+        assert_eq!(session.agent_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_delegation_between_sessions() {
+        use crate::delegation::DelegationPolicy;
+
+        let (mut orchestrator, _channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let sub_id = SubmissionId::new();
+
+        let origin = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+        orchestrator.options = SessionOptions::new()
+            .with_delegation(DelegationPolicy::AllowFrom(vec![origin.id()]));
+        let infra = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+
+        // Infra accepts work from origin, but not the other way around
+        let origin_task = TaskId::new();
+        let target_task = orchestrator
+            .delegate_task(origin.id(), origin_task, infra.id(), "Provision a DB".into(), &sub_id)
+            .unwrap();
+        assert!(infra.is_task_active(&target_task));
+        assert!(orchestrator
+            .delegate_task(infra.id(), TaskId::new(), origin.id(), "Nope".into(), &sub_id)
+            .is_err());
+
+        orchestrator
+            .complete_delegated_task(infra.id(), target_task, true, "DB ready".into(), &sub_id)
+            .unwrap();
+        assert!(!infra.is_task_active(&target_task));
+
+        let outcome = origin.delegated_result(&origin_task).unwrap();
+        assert!(outcome.success);
+        assert_eq!(outcome.from_session, infra.id());
+        assert!(orchestrator
+            .complete_delegated_task(infra.id(), target_task, true, "again".into(), &sub_id)
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_self_test_fail_fast() {
+        use crate::selftest::SelfTestConfig;
+
+        let (orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let mut orchestrator = orchestrator.with_options(SessionOptions::new().with_self_test(
+            SelfTestConfig {
+                required_env: vec!["CABAL_TEST_MISSING_API_KEY".into()],
+                fail_fast: true,
+                ..Default::default()
+            },
+        ));
+
+        let result = orchestrator
+            .configure_session(SessionConfig::default(), &SubmissionId::new())
+            .await;
+
+        assert!(matches!(result, Err(GoblinError::ConfigError(_))));
+        assert!(orchestrator.session_ids().is_empty());
+        assert!(matches!(
+            channel.try_recv(),
+            Some(GoblinEvent::SelfTestCompleted { passed: false, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_injected_planner() {
+        use crate::planner::DomainPlanner;
+
+        let (_channel, pair) = GoblinChannel::new();
+        let mut orchestrator = Orchestrator::with_planner(
+            ToolRegistry::new(),
+            Arc::new(DomainPlanner::default()),
+            pair,
+        );
+        let sub_id = SubmissionId::new();
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+        assert_eq!(session.planner_name(), "domain");
+
+        orchestrator
+            .handle_user_input("- api: add endpoint\n- ui: add button", TaskContext::default(), &[], &sub_id)
+            .await
+            .unwrap();
+
+        let plan = session.plan(&session.active_tasks()[0].task_id).unwrap();
+        assert_eq!(plan.top_level().count(), 2);
+        assert_eq!(plan.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_ops() {
+        let (mut orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &SubmissionId::new())
+            .await
+            .unwrap();
+        while channel.try_recv().is_some() {}
+
+        orchestrator
+            .handle_op(GoblinOp::schedule_task("1h", "Nightly triage", TaskContext::default()))
+            .await
+            .unwrap();
+        let schedule = match channel.try_recv() {
+            Some(GoblinEvent::TaskScheduled { schedule, .. }) => schedule,
+            other => panic!("unexpected event: {:?}", other),
+        };
+        assert!(orchestrator
+            .handle_op(GoblinOp::schedule_task("whenever", "Bad", TaskContext::default()))
+            .await
+            .is_err());
+
+        // Firing the schedule submits the prompt as a regular task
+        orchestrator.run_due_schedules(schedule.next_run).await;
+        let task_id = session.active_tasks()[0].task_id;
+        assert_eq!(session.plan(&task_id).unwrap().prompt, "Nightly triage");
+        while channel.try_recv().is_some() {}
+
+        orchestrator
+            .handle_op(GoblinOp::ListSchedules { sub_id: SubmissionId::new() })
+            .await
+            .unwrap();
+        match channel.try_recv() {
+            Some(GoblinEvent::ScheduleList { schedules, .. }) => {
+                assert_eq!(schedules.len(), 1);
+                assert_eq!(schedules[0].runs, 1);
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        let cancel = || GoblinOp::CancelSchedule {
+            sub_id: SubmissionId::new(),
+            schedule_id: schedule.id,
+        };
+        orchestrator.handle_op(cancel()).await.unwrap();
+        assert!(orchestrator.handle_op(cancel()).await.is_err());
+        assert!(orchestrator.schedules.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_describe_capabilities() {
+        let (mut orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        orchestrator
+            .handle_op(GoblinOp::DescribeCapabilities { sub_id: SubmissionId::new() })
+            .await
+            .unwrap();
+
+        match channel.try_recv() {
+            Some(GoblinEvent::Capabilities { capabilities, .. }) => {
+                assert!(capabilities.planners.contains(&"domain".to_string()));
+                assert!(capabilities.mergers.contains(&"concat".to_string()));
+                assert!(capabilities.roles.contains(&"worker".to_string()));
+                assert!(capabilities.features.contains(&"workflows".to_string()));
+                assert!(capabilities.models.is_empty());
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_describe_agents() {
+        let (mut orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &SubmissionId::new())
+            .await
+            .unwrap();
+        let root = session.orchestrator().unwrap();
+        root.record(crate::history::HistoryEntry::message(crate::model::ChatRole::User, "Hello there"));
+
+        orchestrator
+            .handle_op(GoblinOp::DescribeAgents { sub_id: SubmissionId::new() })
+            .await
+            .unwrap();
+
+        let agents = loop {
+            match channel.try_recv() {
+                Some(GoblinEvent::AgentList { agents, .. }) => break agents,
+                Some(_) => continue,
+                None => panic!("no agent list"),
+            }
+        };
+        assert_eq!(agents.len(), 1);
+        assert_eq!(agents[0].agent_id, root.id());
+        assert_eq!((agents[0].history_len, agents[0].history_tokens), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn test_restore_session_from_checkpoint() {
+        use async_trait::async_trait;
+        use crate::history::HistoryEntry;
+        use crate::model::ChatRole;
+        use crate::result::TaskResult;
+        use crate::runtime::{AgentRuntime, TaskAssignment};
+
+        /// Keeps working on whatever it is given
+        struct Busy;
+
+        #[async_trait]
+        impl AgentRuntime for Busy {
+            fn name(&self) -> &str {
+                "busy"
+            }
+
+            async fn run_task(&self, _agent: &crate::agent::AgentHandle, a: &TaskAssignment) -> Result<TaskResult, GoblinError> {
+                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+                Ok(TaskResult::success(a.task_id, "done"))
+            }
+        }
+
+        let sub_id = SubmissionId::new();
+        let (mut original, _channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let session = original.configure_session(SessionConfig::default(), &sub_id).await.unwrap();
+        let root = session.orchestrator().unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+        let task_id = TaskId::new();
+        worker.assign_task(task_id);
+        worker.checkpoint(Some(TaskAssignment {
+            task_id,
+            instructions: "Keep going".into(),
+            sub_id: sub_id.clone(),
+        }));
+        worker.record(HistoryEntry::message(ChatRole::User, "Keep going"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.json");
+        session.checkpoint(&path).unwrap();
+
+        let (restored, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let mut restored = restored.with_runtimes(Runtimes::new(Arc::new(Busy)));
+        let handle = restored.restore_session(&path, &sub_id).await.unwrap();
+        assert_eq!(handle.id, session.id);
+        assert_eq!(handle.orchestrator().unwrap().id(), root.id());
+
+        let agent = handle.get_agent(&worker.id()).unwrap();
+        assert_eq!(agent.parent_id(), Some(root.id()));
+        assert_eq!(agent.history_len(), 1);
+        assert_eq!(agent.current_task(), Some(task_id));
+        assert!(agent.take_mailbox().is_none(), "actor should be reading the mailbox");
+
+        let events: Vec<_> = std::iter::from_fn(|| channel.try_recv()).collect();
+        assert!(events.iter().any(|e| matches!(
+            e,
+            GoblinEvent::Protocol(Event::SessionConfigured { session_id, .. }) if *session_id == session.id
+        )));
+        assert!(events.iter().any(|e| matches!(
+            e,
+            GoblinEvent::HierarchySnapshot { snapshot, .. } if snapshot.agents.len() == 2
+        )));
+        assert!(!events.iter().any(|e| matches!(e, GoblinEvent::Protocol(Event::AgentSpawned { .. }))));
+
+        assert!(restored.restore_session(&path, &sub_id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fork_session_copies_agents() {
+        use crate::history::HistoryEntry;
+        use crate::model::ChatRole;
+
+        let (mut orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let sub_id = SubmissionId::new();
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+        let root = session.orchestrator().unwrap().id();
+        let worker = session
+            .spawn_agent(AgentConfig { role: AgentRole::Worker, ..Default::default() }, Some(root), &sub_id)
+            .unwrap();
+        worker.record(HistoryEntry::message(ChatRole::User, "Try the fast path"));
+        while channel.try_recv().is_some() {}
+
+        orchestrator
+            .handle_op(GoblinOp::ForkSession { sub_id: sub_id.clone(), from: session.id, at_task: None })
+            .await
+            .unwrap();
+        let events: Vec<_> = std::iter::from_fn(|| channel.try_recv()).collect();
+        let (fork_id, agents) = events.iter().find_map(|e| match e {
+            GoblinEvent::SessionForked { source, session_id, agents, .. } if *source == session.id => {
+                Some((*session_id, agents.clone()))
+            }
+            _ => None,
+        }).unwrap();
+        assert_eq!(orchestrator.session_ids().len(), 2);
+
+        let fork = orchestrator.get_session(&fork_id).unwrap();
+        let copy = agents.iter().find(|(original, _)| *original == worker.id()).unwrap().1;
+        assert_ne!(copy, worker.id());
+        assert_eq!(fork.get_agent(&copy).unwrap().history_len(), 1);
+        assert_eq!(fork.hierarchy_snapshot().agents.len(), 2);
+
+        // The original session is not affected by work in the fork
+        fork.get_agent(&copy).unwrap().record(HistoryEntry::message(ChatRole::User, "Try the slow path"));
+        assert_eq!(worker.history_len(), 1);
+        assert!(session.get_agent(&copy).is_none());
+
+        // Input naming no session still goes to the original
+        orchestrator
+            .handle_user_input("Keep going", TaskContext::default(), &[], &sub_id)
+            .await
+            .unwrap();
+        assert_eq!(session.active_tasks().len(), 1);
+        assert!(fork.active_tasks().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_shared_between_agents_and_clients() {
+        use serde_json::json;
+
+        let (mut orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let sub_id = SubmissionId::new();
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+        let root = session.orchestrator().unwrap().id();
+        let worker = session
+            .spawn_agent(AgentConfig { role: AgentRole::Worker, ..Default::default() }, Some(root), &sub_id)
+            .unwrap();
+        while channel.try_recv().is_some() {}
+
+        let output = worker
+            .use_store(json!({ "action": "set", "key": "api_base", "value": "http://localhost:8080" }), &sub_id)
+            .unwrap();
+        assert_eq!(output["entry"]["version"], json!(1));
+        assert!(matches!(
+            channel.try_recv(),
+            Some(GoblinEvent::StoreChanged { entry, .. }) if entry.updated_by == Some(worker.id())
+        ));
+        assert!(worker.use_store(json!({ "action": "delete", "key": "api_base" }), &sub_id).is_err());
+
+        orchestrator
+            .handle_op(GoblinOp::StoreGet { sub_id: sub_id.clone(), key: "api_base".into() })
+            .await
+            .unwrap();
+        assert!(matches!(
+            channel.try_recv(),
+            Some(GoblinEvent::StoreValue { entry: Some(entry), .. }) if entry.value == json!("http://localhost:8080")
+        ));
+
+        orchestrator
+            .handle_op(GoblinOp::StoreSet {
+                sub_id: sub_id.clone(),
+                key: "api_base".into(),
+                value: json!("https://api.example.com"),
+                expected_version: Some(0),
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            channel.try_recv(),
+            Some(GoblinEvent::StoreConflict { current: Some(entry), .. }) if entry.version == 1
+        ));
+        assert_eq!(session.store().get("api_base").unwrap().version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_large_result_attachments_become_artifacts() {
+        use crate::artifact::{Attachment, INLINE_ATTACHMENT_LIMIT};
+        use crate::result::TaskResult;
+
+        let (mut orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let sub_id = SubmissionId::new();
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+        let root = session.orchestrator().unwrap().id();
+        let worker = session
+            .spawn_agent(AgentConfig { role: AgentRole::Worker, ..Default::default() }, Some(root), &sub_id)
+            .unwrap();
+        let task_id = TaskId::new();
+        worker.assign_task(task_id);
+        let report = worker
+            .produce_artifact("report.md", Some("text/markdown".into()), b"# Findings".to_vec(), &sub_id)
+            .unwrap();
+        while channel.try_recv().is_some() {}
+
+        let mut result = TaskResult::success(task_id, "done").from_agent(worker.id()).with_artifact(report);
+        result.attachments = vec![
+            Attachment::text("notes.txt", "short"),
+            Attachment::text("trace.log", "x".repeat(INLINE_ATTACHMENT_LIMIT + 1)),
+        ];
+        orchestrator
+            .handle_op(GoblinOp::SubmitTaskResult { sub_id: sub_id.clone(), result })
+            .await
+            .unwrap();
+        let events: Vec<_> = std::iter::from_fn(|| channel.try_recv()).collect();
+        let registered = events.iter().find_map(|e| match e {
+            GoblinEvent::ArtifactRegistered { artifact, .. } => Some(artifact.clone()),
+            _ => None,
+        }).unwrap();
+        assert_eq!((registered.name.as_str(), registered.producer), ("trace.log", Some(worker.id())));
+        let reported = events.iter().find_map(|e| match e {
+            GoblinEvent::TaskResult { result, .. } => Some(result.clone()),
+            _ => None,
+        }).unwrap();
+        assert_eq!(reported.attachments.len(), 1);
+        assert_eq!(reported.artifacts, vec![report, registered.id]);
+
+        orchestrator
+            .handle_op(GoblinOp::FetchArtifact { sub_id: sub_id.clone(), artifact_id: report })
+            .await
+            .unwrap();
+        assert!(matches!(
+            channel.try_recv(),
+            Some(GoblinEvent::ArtifactContent { artifact: Some(a), .. })
+                if a.as_text() == Some("# Findings") && a.task_id == Some(task_id)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_tasks_interrupt_separately() {
+        let (mut orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let sub_id = SubmissionId::new();
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+
+        for prompt in ["Fix the parser", "Write the docs"] {
+            orchestrator
+                .handle_user_input(prompt, TaskContext::default(), &[], &sub_id)
+                .await
+                .unwrap();
+        }
+        let tasks = session.active_tasks();
+        assert_eq!(tasks.len(), 2);
+        while channel.try_recv().is_some() {}
+
+        orchestrator.handle_interrupt(Some(tasks[0].task_id), &sub_id).await.unwrap();
+        let interrupted: Vec<TaskId> = std::iter::from_fn(|| channel.try_recv())
+            .filter_map(|e| match e {
+                GoblinEvent::Protocol(Event::TaskInterrupted { task_id, .. }) => Some(task_id),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(interrupted, vec![tasks[0].task_id]);
+        assert!(!session.is_task_active(&tasks[0].task_id));
+        assert!(session.is_task_active(&tasks[1].task_id));
+
+        orchestrator.handle_interrupt(None, &sub_id).await.unwrap();
+        assert!(session.active_tasks().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_bounded_channel_drops_oldest_events() {
+        use crate::channel::{ChannelBuilder, OverflowPolicy};
+
+        let (channel, pair) = ChannelBuilder::new().buffer_size(1).overflow(OverflowPolicy::DropOldest).build();
+        let mut orchestrator = Orchestrator::new(ToolRegistry::new(), pair);
+        let sub_id = SubmissionId::new();
+        orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+
+        let mut forwarded = 0;
+        while let Some(event) = orchestrator.tap_rx.as_mut().and_then(|rx| rx.try_recv().ok()) {
+            orchestrator.handle_tapped_event(event).await;
+            forwarded += 1;
+        }
+        assert!(forwarded > 1);
+        assert!(channel.try_recv().is_some());
+        assert!(channel.try_recv().is_none());
+
+        let stats: std::collections::HashMap<String, ChannelStats> = orchestrator.channel_stats().into_iter().collect();
+        assert_eq!(stats["events"].depth, 0);
+        assert_eq!(stats["events"].received, forwarded);
+        assert!(stats.contains_key("ops") && stats.contains_key("client_events"));
+    }
+
+    #[tokio::test]
+    async fn test_late_subscribers_get_replayed_events() {
+        use crate::channel::ChannelBuilder;
+
+        let (channel, pair) = ChannelBuilder::new().broadcast(64).replay(16).build();
+        let mut orchestrator = Orchestrator::new(ToolRegistry::new(), pair);
+        let sub_id = SubmissionId::new();
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+        while let Some(event) = orchestrator.tap_rx.as_mut().and_then(|rx| rx.try_recv().ok()) {
+            orchestrator.handle_tapped_event(event).await;
+        }
+
+        let mut late = channel.subscribe().unwrap();
+        let replayed: Vec<String> = std::iter::from_fn(|| late.try_recv())
+            .map(|e| e.describe().unwrap().0)
+            .collect();
+        assert_eq!(replayed.first().map(String::as_str), Some("AgentSpawned"));
+        assert!(replayed.iter().any(|kind| kind == "SessionConfigured"));
+        assert_eq!(orchestrator.replay.as_ref().unwrap().events(&session.id).len(), replayed.len());
+    }
+
+    #[tokio::test]
+    async fn test_rules_fire_on_tapped_events() {
+        use crate::deadline::DeadlineAction;
+        use crate::rules::Trigger;
+
+        let (orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let mut orchestrator = orchestrator.with_rules(vec![Rule::new(
+            "stuck worker",
+            Trigger::Event {
+                kind: "TaskDeadlineExceeded".into(),
+                count: 2,
+                per_agent: true,
+            },
+        )
+        .then(Action::Notify {
+            target: "https://hooks.example.com/oncall".into(),
+            message: "worker keeps missing deadlines".into(),
+        })
+        .then(Action::TerminateAgent)]);
+
+        let sub_id = SubmissionId::new();
+        let session = orchestrator
+            .configure_session(SessionConfig::default(), &sub_id)
+            .await
+            .unwrap();
+        let root = session.orchestrator().unwrap().id();
+        let worker = session
+            .spawn_agent(AgentConfig { role: AgentRole::Worker, ..Default::default() }, Some(root), &sub_id)
+            .unwrap();
+        let drain = |orchestrator: &mut Orchestrator| {
+            let mut events = Vec::new();
+            while let Ok(event) = orchestrator.tap_rx.as_mut().unwrap().try_recv() {
+                events.push(event);
+            }
+            events
+        };
+        for event in drain(&mut orchestrator) {
+            orchestrator.handle_tapped_event(event).await;
+        }
+        // Events reach the client only through the orchestrator
+        assert!(matches!(channel.try_recv(), Some(GoblinEvent::Protocol(_))));
+
+        let missed = || GoblinEvent::TaskDeadlineExceeded {
+            sub_id: SubmissionId::new(),
+            task_id: TaskId::new(),
+            agent_id: worker.id(),
+            deadline_ms: 10,
+            action: DeadlineAction::Warn,
+        };
+        orchestrator.handle_tapped_event(missed()).await;
+        assert!(session.get_agent(&worker.id()).is_some());
+        orchestrator.handle_tapped_event(missed()).await;
+        assert!(session.get_agent(&worker.id()).is_none());
+
+        let fired = drain(&mut orchestrator).into_iter().find_map(|e| match e {
+            GoblinEvent::RuleFired { rule, notifications, .. } => Some((rule, notifications)),
+            _ => None,
+        });
+        let (rule, notifications) = fired.unwrap();
+        assert_eq!(rule, "stuck worker");
+        assert_eq!(notifications[0].target, "https://hooks.example.com/oncall");
+    }
+
+    #[tokio::test]
+    async fn test_journal_replays_into_fresh_orchestrator() {
+        use crate::journal::MemoryJournal;
+
+        let journal = Arc::new(MemoryJournal::new());
+        let (orchestrator, channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let mut orchestrator = orchestrator.with_journal(journal.clone());
+        orchestrator
+            .handle_op(GoblinOp::schedule_task("1h", "Nightly triage", TaskContext::default()))
+            .await
+            .unwrap();
+        while let Some(event) = orchestrator.tap_rx.as_mut().and_then(|rx| rx.try_recv().ok()) {
+            orchestrator.handle_tapped_event(event).await;
+        }
+        assert!(matches!(channel.try_recv(), Some(GoblinEvent::TaskScheduled { .. })));
+
+        let records: Vec<_> = journal.entries().unwrap().into_iter().map(|e| e.record).collect();
+        assert!(matches!(
+            records.as_slice(),
+            [JournalRecord::Op(GoblinOp::ScheduleTask { .. }), JournalRecord::Event(GoblinEvent::TaskScheduled { .. })]
+        ));
+
+        let (mut replayed, _channel) = Orchestrator::with_channel(ToolRegistry::new());
+        assert_eq!(replayed.replay(journal.as_ref()).await.unwrap(), 1);
+        let schedules = replayed.schedules.list();
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].prompt, "Nightly triage");
+    }
+
+    #[tokio::test]
+    async fn test_recover_reports_lost_tasks_and_aborts_calls() {
+        use crate::approval::{ExecRequest, SandboxLevel, TrustLevel};
+        use crate::journal::MemoryJournal;
+        use warhorn::CallId;
+
+        let journal = Arc::new(MemoryJournal::new());
+        journal
+            .append(JournalRecord::Op(GoblinOp::schedule_task("1h", "Nightly triage", TaskContext::default())))
+            .unwrap();
+        let scheduled = TaskId::new();
+        journal
+            .append(JournalRecord::Event(
+                Event::TaskStarted { sub_id: SubmissionId::new(), task_id: scheduled, prompt: "Nightly triage".into() }
+                    .into(),
+            ))
+            .unwrap();
+        let call_id = CallId::new();
+        let request = ExecRequest {
+            call_id: call_id.clone(),
+            agent_id: AgentId::new(),
+            role: AgentRole::Worker,
+            command: vec!["make".into()],
+            cwd: "/tmp".into(),
+            sandbox: SandboxLevel::WorkspaceWrite,
+            trust: TrustLevel::Standard,
+            requested_at: chrono::Utc::now(),
+        };
+        journal
+            .append(JournalRecord::Event(GoblinEvent::ExecApprovalRequested { sub_id: SubmissionId::new(), request }))
+            .unwrap();
+        let tool_call = CallId::new();
+        journal
+            .append(JournalRecord::Event(GoblinEvent::ToolCallStarted {
+                sub_id: SubmissionId::new(),
+                call_id: tool_call.clone(),
+                agent_id: AgentId::new(),
+                task_id: Some(scheduled),
+                tool: "grep".into(),
+            }))
+            .unwrap();
+
+        // Neither an export nor a session with no work in flight is redone
+        let export = GoblinOp::ExportAudit {
+            sub_id: SubmissionId::new(),
+            session_id: SessionId::new(),
+            path: "audit.jsonl".into(),
+        };
+        journal.append(JournalRecord::Op(export)).unwrap();
+        let (fork, source) = (SubmissionId::new(), SessionId::new());
+        journal
+            .append(JournalRecord::Op(GoblinOp::ForkSession { sub_id: fork.clone(), from: source, at_task: None }))
+            .unwrap();
+        journal
+            .append(JournalRecord::Event(GoblinEvent::SessionForked {
+                sub_id: fork,
+                source,
+                session_id: SessionId::new(),
+                agents: Vec::new(),
+            }))
+            .unwrap();
+
+        let (orchestrator, _channel) = Orchestrator::with_channel(ToolRegistry::new());
+        let mut orchestrator = orchestrator.with_journal(journal.clone());
+        let report = orchestrator.recover(journal.as_ref()).await.unwrap();
+        assert_eq!((report.ops, report.skipped), (1, 2));
+        assert_eq!(orchestrator.schedules.list().len(), 1);
+        assert_eq!(report.lost.len(), 1);
+        assert_eq!(report.lost[0].task_id, scheduled);
+        assert_eq!(report.aborted_calls, vec![call_id]);
+        assert_eq!(report.aborted_tool_calls.len(), 1);
+        assert_eq!(report.aborted_tool_calls[0].call_id, tool_call);
+
+        let records: Vec<_> = journal.entries().unwrap().into_iter().map(|e| e.record).collect();
+        assert!(matches!(
+            &records[records.len() - 3..],
+            [
+                JournalRecord::Event(GoblinEvent::ToolCallFinished { success: false, .. }),
+                JournalRecord::Event(GoblinEvent::ExecApprovalDecided { approved: false, .. }),
+                JournalRecord::Event(GoblinEvent::RecoveryCompleted { .. })
+            ]
+        ));
+        let again = orchestrator.recover(journal.as_ref()).await.unwrap();
+        assert!(again.lost.is_empty() && again.aborted_calls.is_empty() && again.aborted_tool_calls.is_empty());
+    }
 }
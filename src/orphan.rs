@@ -0,0 +1,22 @@
+//! What happens to the children of an agent that died
+//!
+//! When an agent's run loop fails and the agent is not restarted, its
+//! children would keep running under a parent nobody reads. With an
+//! [`OrphanPolicy`] in the session options, each child is either adopted by
+//! the dead agent's parent, moving with its subtree in the hierarchy, or
+//! terminated, its handoff going to the dead agent's parent. Children that
+//! cannot be adopted, because the dead agent was the root or its parent
+//! has no room, are terminated. Each orphan is reported in an
+//! `OrphanAdopted` or `OrphanTerminated` event.
+
+use serde::{Deserialize, Serialize};
+
+/// What is done with the children of an agent that died
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanPolicy {
+    /// Move the children under their grandparent
+    Adopt,
+    /// Terminate the children
+    Terminate,
+}
@@ -0,0 +1,253 @@
+//! Staged pipelines
+//!
+//! A [`Pipeline`] passes a task through an ordered chain of agents, e.g.
+//! research → implement → review. Every stage runs on a fresh agent of the
+//! stage's role and receives the previous stage's [`TaskResult`] as
+//! context. The session reports each transition, and the pipeline stops at
+//! the first failing stage.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info};
+use warhorn::{AgentConfig, AgentId, AgentRole, SubmissionId, TaskId};
+
+use crate::agent::AgentHandle;
+use crate::error::GoblinError;
+use crate::protocol::GoblinEvent;
+use crate::result::TaskResult;
+use crate::session::SessionHandle;
+
+/// A single pipeline stage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineStage {
+    /// Stage name
+    pub name: String,
+    /// Role of the agent executing the stage
+    pub role: AgentRole,
+    /// Instructions given to the stage's agent
+    pub instructions: String,
+}
+
+impl PipelineStage {
+    /// Create a stage
+    pub fn new(name: impl Into<String>, role: AgentRole, instructions: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            role,
+            instructions: instructions.into(),
+        }
+    }
+}
+
+/// Input handed to a stage
+#[derive(Debug)]
+pub struct StageInput<'a> {
+    /// Task the pipeline is executing
+    pub task_id: TaskId,
+    /// The original prompt
+    pub prompt: &'a str,
+    /// The stage being run
+    pub stage: &'a PipelineStage,
+    /// Result of the previous stage (None for the first stage)
+    pub previous: Option<&'a TaskResult>,
+}
+
+/// Executes pipeline stages on their agents
+#[async_trait]
+pub trait StageRunner: Send + Sync {
+    /// Run a stage, producing its result
+    async fn run(&self, agent: &AgentHandle, input: StageInput<'_>) -> Result<TaskResult, GoblinError>;
+}
+
+/// An ordered chain of stages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pipeline {
+    /// Pipeline name
+    pub name: String,
+    /// Stages, in execution order
+    pub stages: Vec<PipelineStage>,
+}
+
+impl Pipeline {
+    /// Create an empty pipeline
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Append a stage
+    pub fn stage(mut self, stage: PipelineStage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Run the pipeline in a session and return the last stage's result
+    ///
+    /// If a stage fails, its result is returned and later stages are skipped.
+    pub async fn run(
+        &self,
+        session: &SessionHandle,
+        prompt: &str,
+        runner: Arc<dyn StageRunner>,
+        sub_id: &SubmissionId,
+    ) -> Result<TaskResult, GoblinError> {
+        if self.stages.is_empty() {
+            return Err(GoblinError::ConfigError(format!(
+                "Pipeline '{}' has no stages",
+                self.name
+            )));
+        }
+
+        let task_id = TaskId::new();
+        let parent = session.orchestrator().map(|o| o.id());
+        let total = self.stages.len();
+        info!(task_id = %task_id, pipeline = %self.name, stages = total, "Starting pipeline");
+
+        let mut previous: Option<TaskResult> = None;
+        for (index, stage) in self.stages.iter().enumerate() {
+            session.emit(GoblinEvent::PipelineStageStarted {
+                sub_id: sub_id.clone(),
+                task_id,
+                stage: stage.name.clone(),
+                index,
+                total,
+            });
+
+            let input = StageInput {
+                task_id,
+                prompt,
+                stage,
+                previous: previous.as_ref(),
+            };
+            let result = run_stage(session, runner.as_ref(), parent, input, sub_id).await?;
+            debug!(task_id = %task_id, stage = %stage.name, success = result.is_success(), "Pipeline stage finished");
+
+            session.emit(GoblinEvent::PipelineStageFinished {
+                sub_id: sub_id.clone(),
+                task_id,
+                stage: stage.name.clone(),
+                index,
+                total,
+                result: result.clone(),
+            });
+
+            let success = result.is_success();
+            previous = Some(result);
+            if !success {
+                break;
+            }
+        }
+
+        let mut result = previous.expect("pipeline has at least one stage");
+        result.task_id = task_id;
+        Ok(result)
+    }
+}
+
+/// Run one stage on a fresh agent, turning errors into a failed result
+async fn run_stage(
+    session: &SessionHandle,
+    runner: &dyn StageRunner,
+    parent: Option<AgentId>,
+    input: StageInput<'_>,
+    sub_id: &SubmissionId,
+) -> Result<TaskResult, GoblinError> {
+    let defaults = session.config();
+    let config = AgentConfig {
+        role: input.stage.role.clone(),
+        model: defaults.model,
+        cwd: defaults.cwd,
+        can_spawn: false,
+        ..Default::default()
+    };
+    let agent = session.spawn_agent(config, parent, sub_id)?;
+    let stage_task = TaskId::new();
+    agent.assign_task(stage_task);
+
+    let name = input.stage.name.clone();
+    let result = match runner.run(&agent, input).await {
+        Ok(mut result) => {
+            result.task_id = stage_task;
+            result.from_agent(agent.id())
+        }
+        Err(e) => TaskResult::failure(stage_task, e.to_string()).from_agent(agent.id()),
+    };
+    let _ = session.terminate_agent(&agent.id(), format!("Pipeline stage '{}' finished", name), sub_id);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    use crate::testing::session_with_root;
+
+    /// Appends the stage name to the previous summary; fails at "review"
+    /// when the input mentions a bug
+    struct Relay;
+
+    #[async_trait]
+    impl StageRunner for Relay {
+        async fn run(&self, _agent: &AgentHandle, input: StageInput<'_>) -> Result<TaskResult, GoblinError> {
+            let before = input.previous.map_or(input.prompt, |r| r.summary.as_str());
+            if input.stage.name == "review" && before.contains("bug") {
+                return Err(GoblinError::TaskError("review rejected".into()));
+            }
+            Ok(TaskResult::success(input.task_id, format!("{} > {}", before, input.stage.name)))
+        }
+    }
+
+    fn pipeline() -> Pipeline {
+        Pipeline::new("ship")
+            .stage(PipelineStage::new("research", AgentRole::Worker, "Find out"))
+            .stage(PipelineStage::new("implement", AgentRole::Worker, "Build it"))
+            .stage(PipelineStage::new("review", AgentRole::Worker, "Check it"))
+    }
+
+    fn transitions(rx: &mut mpsc::UnboundedReceiver<GoblinEvent>) -> Vec<String> {
+        let mut seen = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                GoblinEvent::PipelineStageStarted { stage, .. } => seen.push(format!("+{}", stage)),
+                GoblinEvent::PipelineStageFinished { stage, .. } => seen.push(format!("-{}", stage)),
+                _ => {}
+            }
+        }
+        seen
+    }
+
+    #[tokio::test]
+    async fn test_stages_receive_previous_result() {
+        let (session, mut rx, sub_id) = session_with_root();
+        let result = pipeline()
+            .run(&session, "feature", Arc::new(Relay), &sub_id)
+            .await
+            .unwrap();
+
+        assert!(result.is_success());
+        assert_eq!(result.summary, "feature > research > implement > review");
+        assert_eq!(session.agent_count(), 1);
+        assert_eq!(
+            transitions(&mut rx),
+            vec!["+research", "-research", "+implement", "-implement", "+review", "-review"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_stage_stops_the_pipeline() {
+        let (session, mut rx, sub_id) = session_with_root();
+        let pipeline = pipeline().stage(PipelineStage::new("deploy", AgentRole::Worker, "Ship it"));
+        let result = pipeline
+            .run(&session, "bugfix", Arc::new(Relay), &sub_id)
+            .await
+            .unwrap();
+
+        assert!(!result.is_success());
+        assert!(transitions(&mut rx).iter().all(|t| !t.ends_with("deploy")));
+    }
+}
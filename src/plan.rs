@@ -0,0 +1,204 @@
+//! Task plans - the decomposition of a task into subtasks
+
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentId, AgentRole, TaskId};
+
+use crate::scheduler::Priority;
+
+/// Progress of a single planned subtask
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PlanStatus {
+    /// Not started yet
+    #[default]
+    Pending,
+    /// Assigned to an agent and in progress
+    Running,
+    /// Finished successfully
+    Completed,
+    /// Finished with an error
+    Failed,
+    /// Dropped before completion
+    Cancelled,
+}
+
+/// A single step in a task plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedTask {
+    /// Subtask ID
+    pub id: TaskId,
+    /// What the assigned agent should do
+    pub description: String,
+    /// Role of the agent that should carry out this step
+    pub role: AgentRole,
+    /// Enclosing subtask (None for top-level steps)
+    pub parent: Option<TaskId>,
+    /// Subtasks that must complete before this one starts
+    pub depends_on: Vec<TaskId>,
+    /// Agent currently assigned to this step
+    pub assignee: Option<AgentId>,
+    /// Current progress
+    pub status: PlanStatus,
+    /// Scheduling priority
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+impl PlannedTask {
+    /// Create a new pending subtask
+    pub fn new(description: impl Into<String>, role: AgentRole) -> Self {
+        Self {
+            id: TaskId::new(),
+            description: description.into(),
+            role,
+            parent: None,
+            depends_on: Vec::new(),
+            assignee: None,
+            status: PlanStatus::Pending,
+            priority: Priority::Normal,
+        }
+    }
+
+    /// Set the scheduling priority
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Nest this subtask under another one
+    pub fn with_parent(mut self, parent: TaskId) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Add a dependency on another subtask
+    pub fn depends_on(mut self, task_id: TaskId) -> Self {
+        self.depends_on.push(task_id);
+        self
+    }
+}
+
+/// Decomposition of a user task into subtasks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskPlan {
+    /// The task this plan decomposes
+    pub task_id: TaskId,
+    /// Original prompt
+    pub prompt: String,
+    /// Planned subtasks, in insertion order
+    tasks: Vec<PlannedTask>,
+}
+
+impl TaskPlan {
+    /// Create an empty plan for a task
+    pub fn new(task_id: TaskId, prompt: impl Into<String>) -> Self {
+        Self {
+            task_id,
+            prompt: prompt.into(),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Add a subtask, returning its ID
+    pub fn add(&mut self, task: PlannedTask) -> TaskId {
+        let id = task.id;
+        self.tasks.push(task);
+        id
+    }
+
+    /// Get a subtask by ID
+    pub fn get(&self, id: &TaskId) -> Option<&PlannedTask> {
+        self.tasks.iter().find(|t| t.id == *id)
+    }
+
+    /// Get a mutable subtask by ID
+    pub fn get_mut(&mut self, id: &TaskId) -> Option<&mut PlannedTask> {
+        self.tasks.iter_mut().find(|t| t.id == *id)
+    }
+
+    /// Update the status of a subtask
+    pub fn set_status(&mut self, id: &TaskId, status: PlanStatus) -> bool {
+        match self.get_mut(id) {
+            Some(task) => {
+                task.status = status;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// All subtasks
+    pub fn tasks(&self) -> &[PlannedTask] {
+        &self.tasks
+    }
+
+    /// All subtasks, mutably
+    pub fn tasks_mut(&mut self) -> &mut [PlannedTask] {
+        &mut self.tasks
+    }
+
+    /// Top-level subtasks (no parent)
+    pub fn top_level(&self) -> impl Iterator<Item = &PlannedTask> {
+        self.tasks.iter().filter(|t| t.parent.is_none())
+    }
+
+    /// Direct subtasks of a subtask
+    pub fn children_of(&self, id: &TaskId) -> impl Iterator<Item = &PlannedTask> + '_ {
+        let id = *id;
+        self.tasks.iter().filter(move |t| t.parent == Some(id))
+    }
+
+    /// Subtasks without children of their own, i.e. the actual units of work
+    pub fn leaves(&self) -> impl Iterator<Item = &PlannedTask> {
+        self.tasks
+            .iter()
+            .filter(|t| !self.tasks.iter().any(|c| c.parent == Some(t.id)))
+    }
+
+    /// Get subtask count
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Check if the plan has no subtasks
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_add_and_get() {
+        let mut plan = TaskPlan::new(TaskId::new(), "Build an API");
+        let id = plan.add(PlannedTask::new("Write handlers", AgentRole::Worker));
+
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan.get(&id).unwrap().status, PlanStatus::Pending);
+    }
+
+    #[test]
+    fn test_plan_children() {
+        let mut plan = TaskPlan::new(TaskId::new(), "Build an API");
+        let lead = plan.add(PlannedTask::new(
+            "Backend",
+            AgentRole::DomainLead { domain: "backend".into() },
+        ));
+        plan.add(PlannedTask::new("Routes", AgentRole::Worker).with_parent(lead));
+        plan.add(PlannedTask::new("Models", AgentRole::Worker).with_parent(lead));
+
+        assert_eq!(plan.top_level().count(), 1);
+        assert_eq!(plan.children_of(&lead).count(), 2);
+    }
+
+    #[test]
+    fn test_plan_set_status() {
+        let mut plan = TaskPlan::new(TaskId::new(), "Build an API");
+        let id = plan.add(PlannedTask::new("Write handlers", AgentRole::Worker));
+
+        assert!(plan.set_status(&id, PlanStatus::Completed));
+        assert_eq!(plan.get(&id).unwrap().status, PlanStatus::Completed);
+        assert!(!plan.set_status(&TaskId::new(), PlanStatus::Failed));
+    }
+}
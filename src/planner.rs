@@ -0,0 +1,282 @@
+//! Planning strategies - turning a prompt into a task plan
+//!
+//! The orchestrator hands every new task to a [`Planner`], which decomposes it
+//! into a [`TaskPlan`]. Two strategies ship with the crate:
+//!
+//! - [`FlatPlanner`]: one worker per bullet point in the prompt
+//! - [`DomainPlanner`]: groups bullets by domain under `DomainLead` subtasks
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentRole, TaskContext, TaskId};
+
+use crate::artifact::ArtifactId;
+use crate::error::GoblinError;
+use crate::plan::{PlannedTask, TaskPlan};
+
+/// Input handed to a planner
+#[derive(Debug, Clone)]
+pub struct PlanRequest {
+    /// Task being planned
+    pub task_id: TaskId,
+    /// User prompt
+    pub prompt: String,
+    /// Task context from the submission
+    pub context: TaskContext,
+    /// Artifacts attached to the submission
+    pub attachments: Vec<ArtifactId>,
+}
+
+/// A strategy for decomposing tasks
+#[async_trait]
+pub trait Planner: Send + Sync {
+    /// Strategy name, for logging and capability reporting
+    fn name(&self) -> &str;
+
+    /// Decompose a task into a plan
+    async fn plan(&self, request: &PlanRequest) -> Result<TaskPlan, GoblinError>;
+}
+
+/// Built-in planner selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum PlannerKind {
+    /// One worker per bullet
+    #[default]
+    Flat,
+    /// Domain leads with workers below them
+    Domain,
+}
+
+impl PlannerKind {
+    /// Every built-in planner
+    pub const ALL: [PlannerKind; 2] = [PlannerKind::Flat, PlannerKind::Domain];
+
+    /// Instantiate the selected planner
+    pub fn build(self) -> Arc<dyn Planner> {
+        match self {
+            Self::Flat => Arc::new(FlatPlanner),
+            Self::Domain => Arc::new(DomainPlanner::default()),
+        }
+    }
+}
+
+/// Planner that assigns one worker to each bullet in the prompt
+///
+/// A prompt without bullets becomes a single worker subtask.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlatPlanner;
+
+#[async_trait]
+impl Planner for FlatPlanner {
+    fn name(&self) -> &str {
+        "flat"
+    }
+
+    async fn plan(&self, request: &PlanRequest) -> Result<TaskPlan, GoblinError> {
+        let mut plan = TaskPlan::new(request.task_id, request.prompt.clone());
+        let bullets: Vec<_> = request.prompt.lines().filter_map(bullet_text).collect();
+
+        if bullets.is_empty() {
+            plan.add(PlannedTask::new(request.prompt.trim(), AgentRole::Worker));
+        } else {
+            for bullet in bullets {
+                plan.add(PlannedTask::new(bullet, AgentRole::Worker));
+            }
+        }
+
+        Ok(plan)
+    }
+}
+
+/// Planner that groups work by domain under `DomainLead` subtasks
+///
+/// Domains come from headings (`## Frontend`, `Backend:`) or from a
+/// `domain: text` prefix on a bullet. Bullets outside any domain go to
+/// the fallback domain.
+#[derive(Debug, Clone)]
+pub struct DomainPlanner {
+    /// Domain for bullets that don't belong to a heading
+    pub fallback_domain: String,
+}
+
+impl Default for DomainPlanner {
+    fn default() -> Self {
+        Self {
+            fallback_domain: "general".into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Planner for DomainPlanner {
+    fn name(&self) -> &str {
+        "domain"
+    }
+
+    async fn plan(&self, request: &PlanRequest) -> Result<TaskPlan, GoblinError> {
+        // Preserve first-seen order of domains
+        let mut domains: Vec<(String, Vec<String>)> = Vec::new();
+        let mut current: Option<String> = None;
+
+        for line in request.prompt.lines() {
+            if let Some(text) = bullet_text(line) {
+                let (domain, item) = match split_domain_prefix(text) {
+                    Some((domain, item)) => (domain, item),
+                    None => (
+                        current.clone().unwrap_or_else(|| self.fallback_domain.clone()),
+                        text.to_string(),
+                    ),
+                };
+                match domains.iter_mut().find(|(d, _)| *d == domain) {
+                    Some((_, items)) => items.push(item),
+                    None => domains.push((domain, vec![item])),
+                }
+            } else if let Some(heading) = heading_text(line) {
+                current = Some(heading);
+            }
+        }
+
+        let mut plan = TaskPlan::new(request.task_id, request.prompt.clone());
+
+        if domains.is_empty() {
+            let lead = plan.add(PlannedTask::new(
+                request.prompt.trim(),
+                AgentRole::DomainLead { domain: self.fallback_domain.clone() },
+            ));
+            plan.add(PlannedTask::new(request.prompt.trim(), AgentRole::Worker).with_parent(lead));
+            return Ok(plan);
+        }
+
+        for (domain, items) in domains {
+            let lead = plan.add(PlannedTask::new(
+                format!("Coordinate {} work", domain),
+                AgentRole::DomainLead { domain },
+            ));
+            for item in items {
+                plan.add(PlannedTask::new(item, AgentRole::Worker).with_parent(lead));
+            }
+        }
+
+        Ok(plan)
+    }
+}
+
+/// Text of a bullet line (`-`, `*`, `+` or `1.`), if the line is one
+fn bullet_text(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let rest = if let Some(rest) = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))
+    {
+        rest
+    } else {
+        let digits = line.find(|c: char| !c.is_ascii_digit())?;
+        if digits == 0 {
+            return None;
+        }
+        line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") "))?
+    };
+
+    let rest = rest.trim();
+    (!rest.is_empty()).then_some(rest)
+}
+
+/// Domain name of a heading line (`# Name` or `Name:`), if the line is one
+fn heading_text(line: &str) -> Option<String> {
+    let line = line.trim();
+    let name = if line.starts_with('#') {
+        line.trim_start_matches('#')
+    } else {
+        line.strip_suffix(':')?
+    };
+
+    let name = name.trim();
+    (!name.is_empty() && name.split_whitespace().count() <= 3).then(|| name.to_lowercase())
+}
+
+/// Split a `domain: item` bullet into its parts
+fn split_domain_prefix(text: &str) -> Option<(String, String)> {
+    let (domain, item) = text.split_once(':')?;
+    let domain = domain.trim();
+    let item = item.trim();
+
+    let single_word = !domain.is_empty() && !domain.contains(char::is_whitespace);
+    (single_word && !item.is_empty()).then(|| (domain.to_lowercase(), item.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(prompt: &str) -> PlanRequest {
+        PlanRequest {
+            task_id: TaskId::new(),
+            prompt: prompt.into(),
+            context: TaskContext::default(),
+            attachments: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flat_planner_bullets() {
+        let plan = FlatPlanner
+            .plan(&request("Build it:\n- write models\n- write routes\n3. add tests"))
+            .await
+            .unwrap();
+
+        assert_eq!(plan.len(), 3);
+        assert!(plan.tasks().iter().all(|t| t.role == AgentRole::Worker));
+        assert_eq!(plan.tasks()[2].description, "add tests");
+    }
+
+    #[tokio::test]
+    async fn test_flat_planner_without_bullets() {
+        let plan = FlatPlanner.plan(&request("Fix the login bug")).await.unwrap();
+
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan.tasks()[0].description, "Fix the login bug");
+    }
+
+    #[tokio::test]
+    async fn test_domain_planner_headings() {
+        let prompt = "## Frontend\n- login page\n- signup page\n\nBackend:\n- auth endpoint";
+        let plan = DomainPlanner::default().plan(&request(prompt)).await.unwrap();
+
+        let leads: Vec<_> = plan.top_level().collect();
+        assert_eq!(leads.len(), 2);
+        assert_eq!(leads[0].role, AgentRole::DomainLead { domain: "frontend".into() });
+        assert_eq!(plan.children_of(&leads[0].id).count(), 2);
+        assert_eq!(plan.children_of(&leads[1].id).count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_domain_planner_prefixes_and_fallback() {
+        let prompt = "- docs: update README\n- bump version\n- docs: add changelog";
+        let plan = DomainPlanner::default().plan(&request(prompt)).await.unwrap();
+
+        let leads: Vec<_> = plan.top_level().collect();
+        assert_eq!(leads.len(), 2);
+        assert_eq!(leads[0].role, AgentRole::DomainLead { domain: "docs".into() });
+        assert_eq!(plan.children_of(&leads[0].id).count(), 2);
+        assert_eq!(leads[1].role, AgentRole::DomainLead { domain: "general".into() });
+    }
+
+    #[test]
+    fn test_planner_kind_build() {
+        assert_eq!(PlannerKind::Flat.build().name(), "flat");
+        assert_eq!(PlannerKind::Domain.build().name(), "domain");
+    }
+
+    #[test]
+    fn test_bullet_text() {
+        assert_eq!(bullet_text("  - item"), Some("item"));
+        assert_eq!(bullet_text("12) item"), Some("item"));
+        assert_eq!(bullet_text("plain line"), None);
+        assert_eq!(bullet_text("-"), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Declarative policy documents
+//!
+//! Operators keep the policy of their deployment in a TOML or YAML file
+//! rather than in code: approval rules, tool scopes, sandbox profiles,
+//! spawn limits, token budgets and budget rules. A [`PolicyDocument`] is
+//! parsed and validated as a whole, so a mistake surfaces as a
+//! `ConfigError` before any session starts, and is then applied to the
+//! orchestrator with `Orchestrator::with_policy`.
+//!
+//! ```toml
+//! [approval]
+//! default = "escalate"
+//!
+//! [[approval.rules]]
+//! name = "read-only tools"
+//! decision = "approve"
+//! commands = ["ls", "cat", "git status"]
+//! max_sandbox = "read_only"
+//!
+//! [[tool_scopes]]
+//! roles = ["worker"]
+//! min_depth = 2
+//! deny = ["net_*", "spawn_agent"]
+//!
+//! [spawn]
+//! max_parallel_agents = 4
+//!
+//! [token_budgets.roles.worker]
+//! max_tokens = 200000
+//!
+//! [[budget]]
+//! name = "token budget"
+//! when = { UsageAbove = { total_tokens = 2000000 } }
+//! then = ["InterruptTask"]
+//! ```
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::approval::ApprovalPolicy;
+use crate::budget::TokenBudgets;
+use crate::config::{SessionOptions, SpawnLimits};
+use crate::error::GoblinError;
+use crate::rules::{Rule, Trigger};
+use crate::sandbox::SandboxProfiles;
+use crate::toolscope::ToolScope;
+
+/// Policy loaded from a file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDocument {
+    /// Rules deciding agents' commands, and the decision when none matches
+    #[serde(default)]
+    pub approval: ApprovalPolicy,
+    /// Tools allowed and denied to agents by role and depth
+    #[serde(default)]
+    pub tool_scopes: Vec<ToolScope>,
+    /// Sandbox profiles of agents by role
+    #[serde(default)]
+    pub sandbox: SandboxProfiles,
+    /// Limits on how many agents sessions spawn, and how deep
+    #[serde(default)]
+    pub spawn: SpawnLimits,
+    /// Token budgets of agents by role, and ceilings on their subtrees
+    #[serde(default)]
+    pub token_budgets: TokenBudgets,
+    /// Rules reacting to usage, run by the orchestrator on every event
+    #[serde(default)]
+    pub budget: Vec<Rule>,
+}
+
+impl PolicyDocument {
+    /// Parse and validate a TOML document
+    pub fn from_toml(source: &str) -> Result<Self, GoblinError> {
+        let document: Self =
+            toml::from_str(source).map_err(|e| GoblinError::ConfigError(format!("Invalid policy: {}", e)))?;
+        document.validate()?;
+        Ok(document)
+    }
+
+    /// Parse and validate a YAML document
+    pub fn from_yaml(source: &str) -> Result<Self, GoblinError> {
+        let document: Self =
+            serde_yaml::from_str(source).map_err(|e| GoblinError::ConfigError(format!("Invalid policy: {}", e)))?;
+        document.validate()?;
+        Ok(document)
+    }
+
+    /// Load a policy file, in YAML if it ends in `.yaml` or `.yml` and in
+    /// TOML otherwise
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GoblinError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| GoblinError::ConfigError(format!("Policy file {}: {}", path.display(), e)))?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => Self::from_yaml(&source),
+            _ => Self::from_toml(&source),
+        }
+    }
+
+    /// Check that the policy is consistent and can be enforced
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if let Some(rule) = self.approval.rules.iter().find(|r| r.name.trim().is_empty()) {
+            return Err(GoblinError::ConfigError(format!(
+                "Approval rule for {:?} needs a name",
+                rule.commands
+            )));
+        }
+        for rule in &self.budget {
+            if rule.name.trim().is_empty() {
+                return Err(GoblinError::ConfigError("Budget rule needs a name".into()));
+            }
+            if rule.then.is_empty() {
+                return Err(GoblinError::ConfigError(format!("Budget rule {} does nothing", rule.name)));
+            }
+            if matches!(rule.when, Trigger::UsageAbove { total_tokens: 0 }) {
+                return Err(GoblinError::ConfigError(format!(
+                    "Budget rule {} needs a token threshold above 0",
+                    rule.name
+                )));
+            }
+        }
+        self.apply(SessionOptions::default()).validate()
+    }
+
+    /// Options with this policy's settings in place of their own
+    pub fn apply(&self, options: SessionOptions) -> SessionOptions {
+        SessionOptions {
+            approval: self.approval.clone(),
+            tool_scopes: self.tool_scopes.clone(),
+            sandbox: self.sandbox.clone(),
+            spawn: self.spawn,
+            token_budgets: self.token_budgets.clone(),
+            ..options
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approval::{ApprovalDecision, SandboxLevel};
+
+    #[test]
+    fn test_toml_and_yaml_load_the_same_policy() {
+        let toml = r#"
+            [approval]
+            default = "deny"
+
+            [[approval.rules]]
+            name = "read-only"
+            decision = "approve"
+            commands = ["ls", "git status"]
+            max_sandbox = "read_only"
+
+            [[tool_scopes]]
+            roles = ["worker"]
+            min_depth = 2
+            deny = ["net_*"]
+
+            [spawn]
+            max_parallel_agents = 4
+
+            [[budget]]
+            name = "tokens"
+            when = { UsageAbove = { total_tokens = 1000 } }
+            then = ["InterruptTask"]
+        "#;
+        let yaml = r#"
+approval:
+  default: deny
+  rules:
+    - name: read-only
+      decision: approve
+      commands: [ls, git status]
+      max_sandbox: read_only
+tool_scopes:
+  - roles: [worker]
+    min_depth: 2
+    deny: ["net_*"]
+spawn:
+  max_parallel_agents: 4
+budget:
+  - name: tokens
+    when:
+      UsageAbove:
+        total_tokens: 1000
+    then: [InterruptTask]
+"#;
+        for policy in [PolicyDocument::from_toml(toml).unwrap(), PolicyDocument::from_yaml(yaml).unwrap()] {
+            assert_eq!(policy.approval.default, ApprovalDecision::Deny);
+            assert_eq!(policy.approval.rules[0].max_sandbox, Some(SandboxLevel::ReadOnly));
+            assert_eq!(policy.tool_scopes[0].deny, vec!["net_*".to_string()]);
+            assert_eq!(policy.budget[0].when, Trigger::UsageAbove { total_tokens: 1000 });
+
+            let options = policy.apply(SessionOptions::new().with_max_queued_tasks(5));
+            assert_eq!(options.spawn.max_parallel_agents, Some(4));
+            assert_eq!(options.max_queued_tasks, Some(5));
+        }
+    }
+
+    #[test]
+    fn test_invalid_policies_are_config_errors() {
+        let invalid = [
+            "[spawn]\nmax_parallel_agents = 0",
+            "[[tool_scopes]]\nmin_depth = 3\nmax_depth = 1",
+            "[[budget]]\nname = \"idle\"\nwhen = { UsageAbove = { total_tokens = 10 } }\nthen = []",
+            "[approvals]\ndefault = \"deny\"",
+        ];
+        for source in invalid {
+            let result = PolicyDocument::from_toml(source);
+            assert!(matches!(result, Err(GoblinError::ConfigError(_))), "accepted: {}", source);
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Per-model pricing and running cost
+//!
+//! A [`PricingTable`] in the session options gives the price of input and
+//! output tokens for each model. Every agent is priced by its model when
+//! it is spawned; as it spends tokens its running cost grows, is rolled up
+//! its subtree, its task and the session by the session's ledger, and is
+//! reported in `UsageUpdated` events and the `DescribeMetrics` snapshot.
+//! Models without a price cost nothing.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GoblinError;
+
+/// Price of a model's tokens, in dollars per million tokens
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    pub fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+        }
+    }
+
+    /// Dollar cost of the given tokens
+    pub fn cost(&self, input: u64, output: u64) -> f64 {
+        (input as f64 * self.input_per_million + output as f64 * self.output_per_million) / 1_000_000.0
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        let valid = |price: f64| price.is_finite() && price >= 0.0;
+        if !valid(self.input_per_million) || !valid(self.output_per_million) {
+            return Err(GoblinError::ConfigError("model prices must be finite and not negative".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Prices of the models a session's agents use
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PricingTable {
+    /// Prices by model name
+    #[serde(default)]
+    pub models: HashMap<String, ModelPrice>,
+    /// Price of models not in the table; free if None
+    #[serde(default)]
+    pub default: Option<ModelPrice>,
+}
+
+impl PricingTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Price a model
+    pub fn with_model(mut self, model: impl Into<String>, price: ModelPrice) -> Self {
+        self.models.insert(model.into(), price);
+        self
+    }
+
+    /// Price models the table does not list
+    pub fn with_default(mut self, price: ModelPrice) -> Self {
+        self.default = Some(price);
+        self
+    }
+
+    /// Price of a model, if it has one
+    pub fn price_for(&self, model: &str) -> Option<ModelPrice> {
+        self.models.get(model).or(self.default.as_ref()).copied()
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        self.models
+            .values()
+            .chain(&self.default)
+            .try_for_each(ModelPrice::validate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prices_by_model_with_a_default() {
+        let table = PricingTable::new()
+            .with_model("large", ModelPrice::new(15.0, 75.0))
+            .with_default(ModelPrice::new(1.0, 2.0));
+        let large = table.price_for("large").unwrap();
+        assert!((large.cost(1_000_000, 200_000) - 30.0).abs() < 1e-9);
+        assert_eq!(table.price_for("other"), Some(ModelPrice::new(1.0, 2.0)));
+        assert!(PricingTable::new().price_for("large").is_none());
+        assert!(PricingTable::new().with_default(ModelPrice::new(-1.0, 0.0)).validate().is_err());
+    }
+}
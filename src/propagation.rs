@@ -0,0 +1,75 @@
+//! What a failed subtask does to its subtree
+//!
+//! When a subtask of a plan fails, the session looks up the
+//! [`FailurePolicy`] of the subtree it belongs to: the policy set for the
+//! agent responsible for the parent task or one of its ancestors, else the
+//! policy of that agent's role. Fail-fast cancels the subtask's unfinished
+//! siblings and interrupts the agents working on them, so the parent's
+//! merge sees the failure at once. Continue lets the siblings finish and
+//! leaves the gap to the merger. Quarantine also lets them finish, and
+//! keeps the failed agent alive but out of the worker pool for diagnosis.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use warhorn::AgentRole;
+
+use crate::runtime::by_role;
+
+/// What a child failure does to its siblings and agent
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailurePolicy {
+    /// Cancel the failed subtask's unfinished siblings
+    FailFast,
+    /// Let the siblings finish; the merger marks the gap
+    #[default]
+    Continue,
+    /// Let the siblings finish and keep the failed agent out of the worker pool
+    Quarantine,
+}
+
+/// Failure policies of a session's subtrees, by the role of their lead
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FailurePolicies {
+    /// Policy of subtrees whose lead's role has none of its own
+    #[serde(default)]
+    pub default: FailurePolicy,
+    /// Policies by role key, like `domain_lead` or `specialist:reviewer`
+    #[serde(default)]
+    pub roles: HashMap<String, FailurePolicy>,
+}
+
+impl FailurePolicies {
+    /// Handle child failures this way unless the lead's role has its own policy
+    pub fn with_default(mut self, policy: FailurePolicy) -> Self {
+        self.default = policy;
+        self
+    }
+
+    /// Handle child failures in the subtrees of agents with a role this way
+    pub fn with_role(mut self, role: impl Into<String>, policy: FailurePolicy) -> Self {
+        self.roles.insert(role.into(), policy);
+        self
+    }
+
+    /// Policy of a subtree led by an agent with `role`
+    pub fn policy_for(&self, role: &AgentRole) -> FailurePolicy {
+        by_role(&self.roles, role).copied().unwrap_or(self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role_policy_overrides_default() {
+        let policies = FailurePolicies::default().with_role("orchestrator", FailurePolicy::FailFast);
+        assert_eq!(policies.policy_for(&AgentRole::Orchestrator), FailurePolicy::FailFast);
+        assert_eq!(policies.policy_for(&AgentRole::Worker), FailurePolicy::Continue);
+
+        let parsed: FailurePolicies = serde_json::from_str(r#"{"default": "quarantine"}"#).unwrap();
+        assert_eq!(parsed.policy_for(&AgentRole::Worker), FailurePolicy::Quarantine);
+    }
+}
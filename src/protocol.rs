@@ -0,0 +1,954 @@
+//! Cabal protocol extensions
+//!
+//! The core op/event protocol lives in `warhorn`. Operations and events
+//! specific to cabal's orchestration are defined here and travel over the
+//! same channels as the core protocol, which is wrapped in the `Protocol`
+//! variants.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use warhorn::{
+    AgentId, AgentStatus, CallId, Event, MessageType, Op, SessionConfig, SessionId, SubmissionId, TaskContext,
+    TaskId, TokenUsage,
+};
+
+use crate::agent::AgentSummary;
+use crate::approval::{ExecRequest, PendingApproval, TimeoutAction};
+use crate::artifact::{Artifact, ArtifactId, ArtifactInfo, Attachment};
+use crate::breaker::BreakerState;
+use crate::error::ErrorCategory;
+use crate::propagation::FailurePolicy;
+use crate::recovery::RecoveryReport;
+use crate::stall::StallAction;
+use crate::audit::{AuditEntry, AuditQuery};
+use crate::budget::SubtreeBudget;
+use crate::estimate::CostEstimate;
+use crate::capabilities::Capabilities;
+use crate::channel::ChannelStats;
+use crate::compaction::Compaction;
+use crate::config::SessionConfigPatch;
+use crate::deadline::DeadlineAction;
+use crate::debate::Turn;
+use crate::hierarchy::{HierarchyEntry, HierarchySnapshot};
+use crate::metrics::SessionMetrics;
+use crate::result::TaskResult;
+use crate::rules::Notification;
+use crate::sandbox::SandboxEscalation;
+use crate::scope::JoinOutcome;
+use crate::schedule::{ScheduleId, ScheduleInfo};
+use crate::selftest::CheckResult;
+use crate::store::StoreEntry;
+use crate::workflow::Workflow;
+
+/// An operation accepted by the orchestrator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GoblinOp {
+    /// Core warhorn operation
+    Protocol(Op),
+    /// Submit a task together with attached files or context blobs
+    UserInputWithAttachments {
+        sub_id: SubmissionId,
+        prompt: String,
+        context: TaskContext,
+        attachments: Vec<Attachment>,
+    },
+    /// Start a task by executing a declarative workflow
+    RunWorkflow {
+        sub_id: SubmissionId,
+        prompt: String,
+        workflow: Workflow,
+    },
+    /// Approve a gated workflow stage
+    ApproveWorkflowStage {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        stage: String,
+    },
+    /// Report the outcome of a running workflow stage
+    WorkflowStageResult {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        stage: String,
+        success: bool,
+    },
+    /// Delegate a subtask from one session to another
+    DelegateTask {
+        sub_id: SubmissionId,
+        origin_session: SessionId,
+        origin_task: TaskId,
+        target_session: SessionId,
+        prompt: String,
+    },
+    /// Report the outcome of delegated work from the target session
+    CompleteDelegatedTask {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        task_id: TaskId,
+        success: bool,
+        summary: String,
+    },
+    /// Set a task's share of model and tool slots relative to other tasks
+    SetTaskWeight {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        weight: u32,
+    },
+    /// Stop an agent from reading mail and calling its model, keeping its state
+    PauseAgent {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+    },
+    /// Let a paused agent continue where it stopped
+    ResumeAgent {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+    },
+    /// Report the result of a subtask
+    SubmitTaskResult {
+        sub_id: SubmissionId,
+        result: TaskResult,
+    },
+    /// Ask what this orchestrator supports
+    DescribeCapabilities {
+        sub_id: SubmissionId,
+    },
+    /// Re-submit a task on a schedule
+    ScheduleTask {
+        sub_id: SubmissionId,
+        /// Interval (`15m`, `6h`, `1d`) or five-field cron expression (UTC)
+        cron_or_interval: String,
+        prompt: String,
+        context: TaskContext,
+    },
+    /// List active schedules
+    ListSchedules {
+        sub_id: SubmissionId,
+    },
+    /// Stop a schedule
+    CancelSchedule {
+        sub_id: SubmissionId,
+        schedule_id: ScheduleId,
+    },
+    /// Ask for the state of every agent in the current session
+    DescribeAgents {
+        sub_id: SubmissionId,
+    },
+    /// Ask for the full hierarchy, e.g. to resync after missed tree deltas
+    DescribeHierarchy {
+        sub_id: SubmissionId,
+    },
+    /// Copy a session's hierarchy and agent contexts into a new session
+    ForkSession {
+        sub_id: SubmissionId,
+        from: SessionId,
+        /// Task the fork continues; other work in flight is dropped
+        at_task: Option<TaskId>,
+    },
+    /// Change the configuration of a running session
+    UpdateSessionConfig {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        patch: SessionConfigPatch,
+    },
+    /// Fetch the content of an artifact in the current session
+    FetchArtifact {
+        sub_id: SubmissionId,
+        artifact_id: ArtifactId,
+    },
+    /// Read a key of the current session's store
+    StoreGet {
+        sub_id: SubmissionId,
+        key: String,
+    },
+    /// Write a key of the current session's store
+    StoreSet {
+        sub_id: SubmissionId,
+        key: String,
+        value: Value,
+        /// Write only if the key is at this version (0 if absent)
+        #[serde(default)]
+        expected_version: Option<u64>,
+    },
+    /// Ask for the depth, rate and drops of the orchestrator's channels
+    DescribeChannels {
+        sub_id: SubmissionId,
+    },
+    /// Ask for a session's counters, token usage and queue depths
+    DescribeMetrics {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+    },
+    /// Ask for a session's approval requests still waiting for an answer
+    ListPendingApprovals {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+    },
+    /// Answer an agent's request for access beyond its sandbox
+    GrantSandboxEscalation {
+        sub_id: SubmissionId,
+        call_id: CallId,
+        granted: bool,
+        /// Seconds the exception lasts, ten minutes if unset
+        #[serde(default)]
+        ttl_secs: Option<u64>,
+    },
+    /// Ask for entries of a session's audit log
+    QueryAudit {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        #[serde(default)]
+        query: AuditQuery,
+    },
+    /// Write a session's audit log to a file as JSON lines
+    ExportAudit {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        /// File name inside the session's audit export directory
+        path: PathBuf,
+    },
+    /// Change the token ceiling of an agent's subtree, or of the session
+    /// if no agent is given; unset lifts it
+    SetBudgetCeiling {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        #[serde(default)]
+        agent_id: Option<AgentId>,
+        #[serde(default)]
+        max_tokens: Option<u64>,
+    },
+    /// Ask for a session's spending rolled up by subtree
+    DescribeBudgets {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+    },
+    /// Start or drop a task held for its cost estimate
+    ConfirmCostEstimate {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        approved: bool,
+    },
+}
+
+impl GoblinOp {
+    /// Submit a task with attachments
+    pub fn user_input_with_attachments(
+        prompt: impl Into<String>,
+        context: TaskContext,
+        attachments: Vec<Attachment>,
+    ) -> Self {
+        Self::UserInputWithAttachments {
+            sub_id: SubmissionId::new(),
+            prompt: prompt.into(),
+            context,
+            attachments,
+        }
+    }
+
+    /// Start a workflow
+    pub fn run_workflow(prompt: impl Into<String>, workflow: Workflow) -> Self {
+        Self::RunWorkflow {
+            sub_id: SubmissionId::new(),
+            prompt: prompt.into(),
+            workflow,
+        }
+    }
+
+    /// Re-submit a task on a schedule
+    pub fn schedule_task(
+        cron_or_interval: impl Into<String>,
+        prompt: impl Into<String>,
+        context: TaskContext,
+    ) -> Self {
+        Self::ScheduleTask {
+            sub_id: SubmissionId::new(),
+            cron_or_interval: cron_or_interval.into(),
+            prompt: prompt.into(),
+            context,
+        }
+    }
+
+    /// Submission ID of this operation
+    pub fn sub_id(&self) -> &SubmissionId {
+        match self {
+            Self::Protocol(op) => op.sub_id(),
+            Self::UserInputWithAttachments { sub_id, .. }
+            | Self::RunWorkflow { sub_id, .. }
+            | Self::ApproveWorkflowStage { sub_id, .. }
+            | Self::WorkflowStageResult { sub_id, .. }
+            | Self::DelegateTask { sub_id, .. }
+            | Self::CompleteDelegatedTask { sub_id, .. }
+            | Self::SetTaskWeight { sub_id, .. }
+            | Self::PauseAgent { sub_id, .. }
+            | Self::ResumeAgent { sub_id, .. }
+            | Self::SubmitTaskResult { sub_id, .. }
+            | Self::DescribeCapabilities { sub_id }
+            | Self::ScheduleTask { sub_id, .. }
+            | Self::ListSchedules { sub_id }
+            | Self::CancelSchedule { sub_id, .. }
+            | Self::DescribeAgents { sub_id }
+            | Self::DescribeHierarchy { sub_id }
+            | Self::ForkSession { sub_id, .. }
+            | Self::UpdateSessionConfig { sub_id, .. }
+            | Self::FetchArtifact { sub_id, .. }
+            | Self::StoreGet { sub_id, .. }
+            | Self::StoreSet { sub_id, .. }
+            | Self::DescribeChannels { sub_id }
+            | Self::DescribeMetrics { sub_id, .. }
+            | Self::ListPendingApprovals { sub_id, .. }
+            | Self::GrantSandboxEscalation { sub_id, .. }
+            | Self::QueryAudit { sub_id, .. }
+            | Self::ExportAudit { sub_id, .. }
+            | Self::SetBudgetCeiling { sub_id, .. }
+            | Self::DescribeBudgets { sub_id, .. }
+            | Self::ConfirmCostEstimate { sub_id, .. } => sub_id,
+        }
+    }
+}
+
+impl From<Op> for GoblinOp {
+    fn from(op: Op) -> Self {
+        Self::Protocol(op)
+    }
+}
+
+/// An event emitted by the orchestrator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GoblinEvent {
+    /// Core warhorn event
+    Protocol(Event),
+    /// A task was not finished within its deadline
+    TaskDeadlineExceeded {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        agent_id: AgentId,
+        /// Time allowed, in milliseconds
+        deadline_ms: u64,
+        /// Action the session applied
+        action: DeadlineAction,
+    },
+    /// Results of the startup self-test of a session
+    SelfTestCompleted {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        passed: bool,
+        results: Vec<CheckResult>,
+    },
+    /// An agent reported the result of a task, or a whole task finished
+    TaskResult {
+        sub_id: SubmissionId,
+        result: TaskResult,
+    },
+    /// A reviewer approved or bounced a subtask result
+    ResultReviewed {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        /// Worker whose result was reviewed
+        agent_id: AgentId,
+        approved: bool,
+        feedback: Option<String>,
+        /// Times the result has been bounced so far
+        bounces: u32,
+    },
+    /// Every child of a task finished; also delivered to the parent's mailbox
+    ChildrenCompleted {
+        sub_id: SubmissionId,
+        /// Agent responsible for the task
+        parent_id: AgentId,
+        task_id: TaskId,
+        results: Vec<TaskResult>,
+    },
+    /// Child results were merged into the result of their parent task
+    ResultMerged {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        /// Agent responsible for the parent task, if any
+        agent_id: Option<AgentId>,
+        result: TaskResult,
+    },
+    /// Capabilities, in response to `DescribeCapabilities`
+    Capabilities {
+        sub_id: SubmissionId,
+        capabilities: Capabilities,
+    },
+    /// An automation rule fired
+    RuleFired {
+        sub_id: SubmissionId,
+        rule: String,
+        agent_id: Option<AgentId>,
+        task_id: Option<TaskId>,
+        /// Notifications for the host to deliver
+        notifications: Vec<Notification>,
+    },
+    /// A recurring task was scheduled
+    TaskScheduled {
+        sub_id: SubmissionId,
+        schedule: ScheduleInfo,
+    },
+    /// Agent states, in response to `DescribeAgents`
+    AgentList {
+        sub_id: SubmissionId,
+        agents: Vec<AgentSummary>,
+    },
+    /// Active schedules, in response to `ListSchedules`
+    ScheduleList {
+        sub_id: SubmissionId,
+        schedules: Vec<ScheduleInfo>,
+    },
+    /// A schedule was cancelled
+    ScheduleCancelled {
+        sub_id: SubmissionId,
+        schedule_id: ScheduleId,
+    },
+    /// A chunk of a map-reduce job finished
+    MapReduceProgress {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        /// Index of the chunk that finished
+        chunk: usize,
+        success: bool,
+        completed: usize,
+        total: usize,
+    },
+    /// A pipeline stage started
+    PipelineStageStarted {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        stage: String,
+        index: usize,
+        total: usize,
+    },
+    /// A pipeline stage finished
+    PipelineStageFinished {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        stage: String,
+        index: usize,
+        total: usize,
+        result: TaskResult,
+    },
+    /// A debater made an argument
+    DebateTurn {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        turn: Turn,
+    },
+    /// A speculative race finished and its losers were terminated
+    SpeculationSettled {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        /// Agent whose result was accepted, if any
+        winner: Option<AgentId>,
+        attempts: usize,
+        /// Tokens spent by the attempts that were not accepted
+        wasted: TokenUsage,
+    },
+    /// An agent's run loop ended
+    AgentJoined {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        outcome: JoinOutcome,
+    },
+    /// An agent was moved under a new parent
+    HierarchyChanged {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        old_parent: Option<AgentId>,
+        new_parent: AgentId,
+    },
+    /// Agents joined or left the hierarchy, or changed status
+    TreeDelta {
+        sub_id: SubmissionId,
+        added: Vec<HierarchyEntry>,
+        removed: Vec<AgentId>,
+        status_changes: Vec<(AgentId, AgentStatus)>,
+    },
+    /// The full hierarchy, in response to `DescribeHierarchy`
+    HierarchySnapshot {
+        sub_id: SubmissionId,
+        snapshot: HierarchySnapshot,
+    },
+    /// An agent's actor was restarted with the same id
+    AgentRestarted {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        /// History entries restored from the last checkpoint
+        restored_entries: usize,
+        /// Task assigned again after the restart
+        resumed_task: Option<TaskId>,
+    },
+    /// An agent whose run loop failed will be restarted by its supervision policy
+    AgentRestarting {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        /// Restarts so far, counting this one
+        attempt: u32,
+        /// Wait before the restart
+        delay_ms: u64,
+        error: String,
+    },
+    /// An agent whose run loop failed was terminated and its failure passed to its parent
+    AgentFailureEscalated {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        parent_id: Option<AgentId>,
+        error: String,
+    },
+    /// A failed task will be assigned again
+    TaskRetried {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        /// Agent whose attempt failed
+        agent_id: AgentId,
+        /// Number of the attempt about to start, counting from 1
+        attempt: u32,
+        /// Wait before the attempt
+        delay_ms: u64,
+        /// Summary of the failed result
+        error: String,
+        /// Whether the attempt is made by a fresh agent
+        fresh_agent: bool,
+    },
+    /// An agent that keeps failing was reported to its lead
+    FailureReported {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        task_id: TaskId,
+        /// Agent the report was delivered to
+        lead_id: AgentId,
+        failures: u32,
+    },
+    /// A subtask failed and its subtree's failure policy was applied
+    FailurePropagated {
+        sub_id: SubmissionId,
+        task_id: TaskId,
+        /// Agent whose result failed, None for merged results
+        agent_id: Option<AgentId>,
+        /// Task whose children the failed subtask is among
+        parent_task: TaskId,
+        policy: FailurePolicy,
+        /// Siblings, and their subtasks, cancelled by a fail-fast policy
+        cancelled: Vec<TaskId>,
+    },
+    /// An agent's run loop missed its heartbeats
+    AgentUnresponsive {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        /// Task the agent was working on
+        task_id: Option<TaskId>,
+        /// Time since the last beat
+        silent_ms: u64,
+    },
+    /// An unresponsive agent's run loop beat again
+    AgentResponsive {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+    },
+    /// An agent working on a task made no progress for the stall time
+    AgentStalled {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        task_id: TaskId,
+        /// When the agent last made progress
+        last_activity: DateTime<Utc>,
+        /// What was done about it
+        action: StallAction,
+    },
+    /// A child of an agent that died was moved under its grandparent
+    OrphanAdopted {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        /// Agent that died
+        old_parent: AgentId,
+        new_parent: AgentId,
+    },
+    /// A child of an agent that died was terminated
+    OrphanTerminated {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        /// Agent that died
+        old_parent: AgentId,
+        reason: String,
+    },
+    /// An agent was paused
+    AgentPaused {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+    },
+    /// A paused agent was resumed
+    AgentResumed {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+    },
+    /// An agent was forked into a sibling with a copy of its history
+    AgentForked {
+        sub_id: SubmissionId,
+        source: AgentId,
+        agent_id: AgentId,
+    },
+    /// The configuration of a running session changed
+    SessionConfigChanged {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        /// Configuration after the change
+        config: SessionConfig,
+        patch: SessionConfigPatch,
+    },
+    /// An agent or result added an artifact to the session
+    ArtifactRegistered {
+        sub_id: SubmissionId,
+        artifact: ArtifactInfo,
+    },
+    /// An artifact with its content, in response to `FetchArtifact`
+    ArtifactContent {
+        sub_id: SubmissionId,
+        artifact_id: ArtifactId,
+        artifact: Option<Artifact>,
+    },
+    /// A key of the session store was written
+    StoreChanged {
+        sub_id: SubmissionId,
+        key: String,
+        entry: StoreEntry,
+    },
+    /// A key of the session store, in response to `StoreGet`
+    StoreValue {
+        sub_id: SubmissionId,
+        key: String,
+        entry: Option<StoreEntry>,
+    },
+    /// An agent's command needs the client's approval, given with `Op::ExecApproval`
+    ExecApprovalRequested {
+        sub_id: SubmissionId,
+        request: ExecRequest,
+    },
+    /// A command was approved or denied
+    ExecApprovalDecided {
+        sub_id: SubmissionId,
+        call_id: CallId,
+        agent_id: AgentId,
+        approved: bool,
+        /// Policy rule that decided, `timeout` for a timeout action
+        rule: Option<String>,
+        /// Agent the request was escalated to, if it decided
+        #[serde(default)]
+        decided_by: Option<AgentId>,
+    },
+    /// A command's approval was handed to a delegate agent before the client
+    ApprovalDelegated {
+        sub_id: SubmissionId,
+        call_id: CallId,
+        /// Agent whose command is waiting
+        agent_id: AgentId,
+        delegate: AgentId,
+    },
+    /// An agent asks for access beyond its sandbox, answered with
+    /// `GrantSandboxEscalation`
+    SandboxEscalationRequested {
+        sub_id: SubmissionId,
+        request: SandboxEscalation,
+    },
+    /// A sandbox escalation was granted or refused
+    SandboxEscalationDecided {
+        sub_id: SubmissionId,
+        call_id: CallId,
+        agent_id: AgentId,
+        granted: bool,
+        /// Escalation rule that granted it, if not the client
+        rule: Option<String>,
+        /// End of the granted exception
+        expires_at: Option<DateTime<Utc>>,
+    },
+    /// Estimated spending of a planned task, before it starts; a task
+    /// that needs confirmation waits for `ConfirmCostEstimate`
+    CostEstimate {
+        sub_id: SubmissionId,
+        estimate: CostEstimate,
+    },
+    /// An agent spent tokens
+    UsageUpdated {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        /// Task the agent was working on
+        task_id: Option<TaskId>,
+        /// The agent's usage so far
+        usage: TokenUsage,
+        /// The agent's cost so far, in dollars
+        cost_usd: f64,
+        /// Tokens spent by the whole session so far
+        session_total: u64,
+    },
+    /// An agent's tool call started running, after any waits for slots
+    ToolCallStarted {
+        sub_id: SubmissionId,
+        call_id: CallId,
+        agent_id: AgentId,
+        task_id: Option<TaskId>,
+        tool: String,
+    },
+    /// A tool call that started ended; a call left running by a crash is
+    /// ended as failed by the recovery
+    ToolCallFinished {
+        sub_id: SubmissionId,
+        call_id: CallId,
+        agent_id: AgentId,
+        tool: String,
+        success: bool,
+    },
+    /// An MCP server was started and its tools offered to agents
+    McpServerConnected {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        server: String,
+        /// Names agents call the server's tools by
+        tools: Vec<String>,
+    },
+    /// An MCP server could not be started or listed
+    McpServerFailed {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        server: String,
+        error: String,
+    },
+    /// A journal was recovered after a crash
+    RecoveryCompleted {
+        sub_id: SubmissionId,
+        report: RecoveryReport,
+    },
+    /// An operation failed
+    OpFailed {
+        sub_id: SubmissionId,
+        /// Stable code of the error, see [`crate::GoblinError::code`]
+        code: String,
+        category: ErrorCategory,
+        message: String,
+        /// Whether the operation may succeed if submitted again
+        retryable: bool,
+    },
+    /// A `SpawnAgent` op was refused
+    SpawnDenied {
+        sub_id: SubmissionId,
+        parent_id: Option<AgentId>,
+        reason: String,
+        /// Code of the error, see [`crate::GoblinError::code`]
+        code: String,
+        /// When the spawn may succeed if tried again, for rate limits
+        retry_after_ms: Option<u64>,
+    },
+    /// A model's circuit breaker opened, half-opened or closed
+    CircuitStateChanged {
+        sub_id: SubmissionId,
+        /// Model the breaker guards, as `provider/model`
+        model: String,
+        state: BreakerState,
+        /// Failed calls in a row
+        failures: u32,
+    },
+    /// A session's live agents reached the warning share of its agent limit
+    AgentLimitNear {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        live: usize,
+        limit: usize,
+    },
+    /// An agent's usage passed a warning threshold of its token budget
+    BudgetWarning {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        used: u64,
+        limit: u64,
+        /// Fraction of the budget passed
+        threshold: f64,
+    },
+    /// An agent spent its token budget; it makes no more model calls
+    BudgetExceeded {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        used: u64,
+        limit: u64,
+    },
+    /// A subtree, or the session if no agent is given, reached its token
+    /// ceiling and its agents were paused
+    BudgetCeilingReached {
+        sub_id: SubmissionId,
+        agent_id: Option<AgentId>,
+        used: u64,
+        limit: u64,
+    },
+    /// A session's spending by subtree, in response to `DescribeBudgets`
+    Budgets {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        /// The session first, then subtrees by spending
+        budgets: Vec<SubtreeBudget>,
+    },
+    /// Nobody answered a command's approval request in time
+    ApprovalTimedOut {
+        sub_id: SubmissionId,
+        call_id: CallId,
+        /// Agent whose command is waiting
+        agent_id: AgentId,
+        action: TimeoutAction,
+        /// Agent asked next, when escalating to a parent
+        escalated_to: Option<AgentId>,
+    },
+    /// Channel health by channel name, in response to `DescribeChannels`
+    ChannelHealth {
+        sub_id: SubmissionId,
+        channels: Vec<(String, ChannelStats)>,
+    },
+    /// A session's metrics, in response to `DescribeMetrics`
+    Metrics {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        metrics: SessionMetrics,
+    },
+    /// Audit log entries, in response to `QueryAudit`
+    AuditEntries {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        entries: Vec<AuditEntry>,
+    },
+    /// A session's audit log was written out, in response to `ExportAudit`
+    AuditExported {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        path: PathBuf,
+        entries: usize,
+    },
+    /// A session's outstanding approvals, in response to `ListPendingApprovals`
+    PendingApprovals {
+        sub_id: SubmissionId,
+        session_id: SessionId,
+        approvals: Vec<PendingApproval>,
+    },
+    /// A `StoreSet` was refused because the key had moved on
+    StoreConflict {
+        sub_id: SubmissionId,
+        key: String,
+        expected_version: u64,
+        current: Option<StoreEntry>,
+    },
+    /// A session was forked into a new session
+    SessionForked {
+        sub_id: SubmissionId,
+        source: SessionId,
+        session_id: SessionId,
+        /// Each agent of the source with its copy in the fork
+        agents: Vec<(AgentId, AgentId)>,
+    },
+    /// Context of a terminated or failed agent was handed to another agent
+    AgentHandoff {
+        sub_id: SubmissionId,
+        from: AgentId,
+        to: AgentId,
+        task_id: Option<TaskId>,
+    },
+    /// Older entries of an agent's history were replaced by a summary
+    HistoryCompacted {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        compaction: Compaction,
+    },
+    /// A message from an agent, or a piece of one while it is streamed
+    ///
+    /// Streamed pieces are followed by a non-streaming message with the
+    /// complete content. `seq` counts every message of the agent, so a
+    /// client can put each agent's stream back in order.
+    AgentMessage {
+        sub_id: SubmissionId,
+        agent_id: AgentId,
+        seq: u64,
+        content: String,
+        streaming: bool,
+        message_type: MessageType,
+    },
+}
+
+impl GoblinEvent {
+    /// Kind name and fields of the event
+    ///
+    /// Core protocol events are described by their inner variant, e.g.
+    /// `TaskInterrupted` rather than `Protocol`.
+    pub fn describe(&self) -> Option<(String, Value)> {
+        let mut value = serde_json::to_value(self).ok()?;
+        if let Some(inner) = value.get_mut("Protocol") {
+            value = inner.take();
+        }
+
+        match value {
+            Value::String(kind) => Some((kind, Value::Null)),
+            Value::Object(map) => {
+                if let Some(Value::String(kind)) = map.get("type") {
+                    return Some((kind.clone(), Value::Object(map)));
+                }
+                let mut entries = map.into_iter();
+                match (entries.next(), entries.next()) {
+                    (Some((kind, body)), None) => Some((kind, body)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Submission this event responds to
+    pub fn sub_id(&self) -> Option<SubmissionId> {
+        let (_, body) = self.describe()?;
+        serde_json::from_value(body.get("sub_id")?.clone()).ok()
+    }
+}
+
+impl From<Event> for GoblinEvent {
+    fn from(event: Event) -> Self {
+        Self::Protocol(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_protocol_op() {
+        let op = Op::interrupt();
+        let sub_id = op.sub_id().clone();
+
+        let goblin_op: GoblinOp = op.into();
+        assert!(matches!(goblin_op, GoblinOp::Protocol(_)));
+        assert_eq!(goblin_op.sub_id(), &sub_id);
+    }
+
+    #[test]
+    fn test_user_input_with_attachments() {
+        let op = GoblinOp::user_input_with_attachments(
+            "Fix the crash",
+            TaskContext::default(),
+            vec![Attachment::text("crash.log", "panic at main.rs:1")],
+        );
+
+        match op {
+            GoblinOp::UserInputWithAttachments { prompt, attachments, .. } => {
+                assert_eq!(prompt, "Fix the crash");
+                assert_eq!(attachments.len(), 1);
+            }
+            _ => panic!("unexpected op"),
+        }
+    }
+
+    #[test]
+    fn test_event_sub_id() {
+        let sub_id = SubmissionId::new();
+        let event: GoblinEvent = Event::Warning {
+            sub_id: sub_id.clone(),
+            message: "careful".into(),
+            details: None,
+        }
+        .into();
+
+        assert_eq!(event.describe().unwrap().0, "Warning");
+        assert_eq!(event.sub_id(), Some(sub_id));
+    }
+}
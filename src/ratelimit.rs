@@ -0,0 +1,136 @@
+//! Token-bucket rate limits
+//!
+//! A [`RateLimit`] allows bursts of up to `burst` actions and refills at
+//! `per_minute` actions a minute. Each limited party gets its own
+//! [`TokenBucket`]; an action that finds its bucket empty is refused with
+//! the time until the next token is available, so callers can say when to
+//! try again.
+
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GoblinError;
+
+/// How often an action may happen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Actions allowed at once after a quiet period
+    pub burst: u32,
+    /// Actions allowed a minute, sustained
+    pub per_minute: u32,
+}
+
+impl RateLimit {
+    pub fn new(burst: u32, per_minute: u32) -> Self {
+        Self { burst, per_minute }
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.burst == 0 || self.per_minute == 0 {
+            return Err(GoblinError::ConfigError("rate limit burst and per_minute must be at least 1".into()));
+        }
+        Ok(())
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.per_minute as f64 / 60.0
+    }
+}
+
+/// Tokens left to one party under a rate limit
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    /// A full bucket
+    pub fn new(limit: RateLimit) -> Self {
+        Self::new_at(limit, Instant::now())
+    }
+
+    pub fn new_at(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            updated: now,
+        }
+    }
+
+    /// Time until a token is available at `now`, zero if one is
+    pub fn wait_at(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.limit.refill_per_sec())
+        }
+    }
+
+    /// Take a token at `now`, or return how long until one is available
+    pub fn try_take_at(&mut self, now: Instant) -> Result<(), Duration> {
+        match self.wait_at(now) {
+            Duration::ZERO => {
+                self.tokens -= 1.0;
+                Ok(())
+            }
+            wait => Err(wait),
+        }
+    }
+
+    /// Take a token now, or return how long until one is available
+    pub fn try_take(&mut self) -> Result<(), Duration> {
+        self.try_take_at(Instant::now())
+    }
+
+    /// Take a token from every bucket, or from none of them, returning the
+    /// longest wait if any is empty
+    pub fn try_take_all(buckets: &mut [&mut TokenBucket], now: Instant) -> Result<(), Duration> {
+        let wait = buckets.iter_mut().map(|b| b.wait_at(now)).max().unwrap_or_default();
+        if !wait.is_zero() {
+            return Err(wait);
+        }
+        for bucket in buckets.iter_mut() {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.refill_per_sec()).min(self.limit.burst as f64);
+        self.updated = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refills_after_a_burst() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new_at(RateLimit::new(2, 60), start);
+        assert!(bucket.try_take_at(start).is_ok());
+        assert!(bucket.try_take_at(start).is_ok());
+        let wait = bucket.try_take_at(start).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        assert!(bucket.try_take_at(start + Duration::from_millis(500)).is_err());
+        assert!(bucket.try_take_at(start + Duration::from_secs(1)).is_ok());
+    }
+
+    #[test]
+    fn test_take_all_takes_nothing_when_one_is_empty() {
+        let now = Instant::now();
+        let mut wide = TokenBucket::new_at(RateLimit::new(5, 60), now);
+        let mut narrow = TokenBucket::new_at(RateLimit::new(1, 30), now);
+        assert!(TokenBucket::try_take_all(&mut [&mut wide, &mut narrow], now).is_ok());
+        let wait = TokenBucket::try_take_all(&mut [&mut wide, &mut narrow], now).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(2));
+        assert_eq!(wide.wait_at(now), Duration::ZERO);
+        assert!(wide.try_take_at(now).is_ok());
+    }
+}
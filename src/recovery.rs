@@ -0,0 +1,314 @@
+//! Crash recovery from the journal
+//!
+//! After a restart, [`crate::Orchestrator::recover`] scans the journal the
+//! crashed process wrote and handles its ops again, like a replay, but
+//! skips the ops whose task had already finished and the ops creating a
+//! session none of whose tasks was in flight. Tasks that were in flight
+//! are started again when the op that started them can be replayed, and
+//! are lost otherwise, like the firings of schedules. Tool calls that were
+//! running are ended as failed and commands that were waiting for approval
+//! are denied, both as aborted. What was resumed and what was lost is
+//! reported in a `RecoveryCompleted` event, which is journaled too: a
+//! later recovery reads it to follow resumed tasks under their new ids and
+//! to leave lost ones alone.
+//!
+//! The session of a task is the one its op targets, or else the session
+//! configured last, as the orchestrator starts tasks in its one session.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentId, CallId, Event, Op, SessionId, SubmissionId, TaskId};
+
+use crate::approval::ExecRequest;
+use crate::journal::{JournalEntry, JournalRecord};
+use crate::protocol::{GoblinEvent, GoblinOp};
+
+/// A task that was in flight when the orchestrator stopped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterruptedTask {
+    /// Op that started the task
+    pub sub_id: SubmissionId,
+    pub task_id: TaskId,
+    pub prompt: String,
+    /// Task started again in its place, if resumed
+    #[serde(default)]
+    pub resumed_as: Option<TaskId>,
+}
+
+/// A tool call that was running when the orchestrator stopped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterruptedToolCall {
+    pub call_id: CallId,
+    pub agent_id: AgentId,
+    pub task_id: Option<TaskId>,
+    pub tool: String,
+}
+
+/// What a recovery resumed and what it lost
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecoveryReport {
+    /// Ops handled again
+    pub ops: usize,
+    /// Ops not handled again: those of finished tasks and idle sessions,
+    /// and those acting outside the orchestrator
+    #[serde(default)]
+    pub skipped: usize,
+    /// Sessions rebuilt
+    pub sessions: Vec<SessionId>,
+    /// In-flight tasks started again
+    pub resumed: Vec<InterruptedTask>,
+    /// In-flight tasks that could not be started again
+    pub lost: Vec<InterruptedTask>,
+    /// Commands that were waiting for approval, denied as aborted
+    pub aborted_calls: Vec<CallId>,
+    /// Tool calls that were running, ended as failed
+    #[serde(default)]
+    pub aborted_tool_calls: Vec<InterruptedToolCall>,
+}
+
+/// Whether handling the op again would act outside the orchestrator, by
+/// writing a file or answering a request of the process that wrote it
+pub(crate) fn has_side_effects(op: &GoblinOp) -> bool {
+    matches!(
+        op,
+        GoblinOp::ExportAudit { .. }
+            | GoblinOp::GrantSandboxEscalation { .. }
+            | GoblinOp::Protocol(Op::ExecApproval { .. })
+    )
+}
+
+/// State of the tasks and commands of a journal at its end
+#[derive(Debug, Default)]
+pub(crate) struct JournalScan {
+    /// Unfinished tasks, by the op that started them
+    in_flight: HashMap<SubmissionId, InterruptedTask>,
+    /// Ops whose task finished or was given up
+    finished: HashSet<SubmissionId>,
+    /// Op that started each task
+    task_ops: HashMap<TaskId, SubmissionId>,
+    /// Commands waiting for approval, oldest first
+    pending_calls: Vec<ExecRequest>,
+    /// Tool calls running, oldest first
+    running_tools: Vec<InterruptedToolCall>,
+    /// Op that created each session
+    session_ops: HashMap<SessionId, SubmissionId>,
+    /// Op that created the session a fork was made from, by the fork's op
+    fork_sources: HashMap<SubmissionId, SubmissionId>,
+    /// Op that created the session configured last
+    current_session: Option<SubmissionId>,
+    /// Session each op explicitly starts its task in
+    op_targets: HashMap<SubmissionId, SessionId>,
+    /// Op that created the session of each task
+    task_sessions: HashMap<TaskId, SubmissionId>,
+    /// Ops that created a session with tasks in flight
+    active_sessions: HashSet<SubmissionId>,
+}
+
+impl JournalScan {
+    pub(crate) fn new(entries: &[JournalEntry]) -> Self {
+        let mut scan = Self::default();
+        for entry in entries {
+            match &entry.record {
+                JournalRecord::Op(GoblinOp::DelegateTask { sub_id, target_session, .. }) => {
+                    scan.op_targets.insert(sub_id.clone(), *target_session);
+                }
+                JournalRecord::Op(_) => {}
+                JournalRecord::Event(event) => scan.observe(event),
+            }
+        }
+        scan.active_sessions = scan.sessions_in_flight();
+        scan
+    }
+
+    fn observe(&mut self, event: &GoblinEvent) {
+        match event {
+            GoblinEvent::Protocol(Event::SessionConfigured { sub_id, session_id, .. }) => {
+                self.session_ops.insert(*session_id, sub_id.clone());
+                self.current_session = Some(sub_id.clone());
+            }
+            GoblinEvent::SessionForked { sub_id, source, session_id, .. } => {
+                self.session_ops.insert(*session_id, sub_id.clone());
+                if let Some(source) = self.session_ops.get(source).cloned() {
+                    self.fork_sources.insert(sub_id.clone(), source);
+                }
+            }
+            GoblinEvent::Protocol(Event::TaskStarted { sub_id, task_id, prompt }) => {
+                let session = self.session_of(sub_id);
+                self.start(sub_id, *task_id, prompt, session);
+            }
+            GoblinEvent::Protocol(Event::TaskInterrupted { task_id, .. }) => self.finish(task_id),
+            GoblinEvent::TaskResult { result, .. } => self.finish(&result.task_id),
+            GoblinEvent::ExecApprovalRequested { request, .. } => self.pending_calls.push(request.clone()),
+            GoblinEvent::ExecApprovalDecided { call_id, .. } => {
+                self.pending_calls.retain(|request| request.call_id != *call_id);
+            }
+            GoblinEvent::ToolCallStarted { call_id, agent_id, task_id, tool, .. } => {
+                self.running_tools.push(InterruptedToolCall {
+                    call_id: call_id.clone(),
+                    agent_id: *agent_id,
+                    task_id: *task_id,
+                    tool: tool.clone(),
+                });
+            }
+            GoblinEvent::ToolCallFinished { call_id, .. } => {
+                self.running_tools.retain(|call| call.call_id != *call_id);
+            }
+            GoblinEvent::RecoveryCompleted { report, .. } => {
+                for task in &report.lost {
+                    self.finish(&task.task_id);
+                }
+                for task in &report.resumed {
+                    if let Some(task_id) = task.resumed_as {
+                        let session = self.task_sessions.get(&task.task_id).cloned();
+                        self.start(&task.sub_id, task_id, &task.prompt, session);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Op that created the session a task of the op runs in
+    fn session_of(&self, sub_id: &SubmissionId) -> Option<SubmissionId> {
+        if self.session_ops.values().any(|op| op == sub_id) {
+            return Some(sub_id.clone());
+        }
+        match self.op_targets.get(sub_id) {
+            Some(target) => self.session_ops.get(target).cloned(),
+            None => self.current_session.clone(),
+        }
+    }
+
+    fn start(&mut self, sub_id: &SubmissionId, task_id: TaskId, prompt: &str, session: Option<SubmissionId>) {
+        self.finished.remove(sub_id);
+        self.task_ops.insert(task_id, sub_id.clone());
+        if let Some(session) = session {
+            self.task_sessions.insert(task_id, session);
+        }
+        self.in_flight.insert(
+            sub_id.clone(),
+            InterruptedTask {
+                sub_id: sub_id.clone(),
+                task_id,
+                prompt: prompt.to_string(),
+                resumed_as: None,
+            },
+        );
+    }
+
+    fn finish(&mut self, task_id: &TaskId) {
+        if let Some(sub_id) = self.task_ops.get(task_id) {
+            if self.in_flight.get(sub_id).is_some_and(|task| task.task_id == *task_id) {
+                self.in_flight.remove(sub_id);
+                self.finished.insert(sub_id.clone());
+            }
+        }
+    }
+
+    /// Ops that created a session with tasks in flight, or a session one
+    /// of those was forked from
+    fn sessions_in_flight(&self) -> HashSet<SubmissionId> {
+        let mut active = HashSet::new();
+        for task in self.in_flight.values() {
+            let mut session = self.task_sessions.get(&task.task_id).cloned();
+            while let Some(op) = session {
+                if !active.insert(op.clone()) {
+                    break;
+                }
+                session = self.fork_sources.get(&op).cloned();
+            }
+        }
+        active
+    }
+
+    /// Whether the op started a task that finished
+    pub(crate) fn is_finished(&self, sub_id: &SubmissionId) -> bool {
+        self.finished.contains(sub_id)
+    }
+
+    /// Whether the op created a session none of whose tasks was in flight
+    pub(crate) fn is_idle_session(&self, sub_id: &SubmissionId) -> bool {
+        self.session_ops.values().any(|op| op == sub_id) && !self.active_sessions.contains(sub_id)
+    }
+
+    /// Task the op started that was in flight, if any
+    pub(crate) fn take_in_flight(&mut self, sub_id: &SubmissionId) -> Option<InterruptedTask> {
+        self.in_flight.remove(sub_id)
+    }
+
+    /// In-flight tasks not taken by their op, e.g. started by a schedule,
+    /// with the commands waiting for approval and the tool calls running
+    pub(crate) fn into_remaining(self) -> (Vec<InterruptedTask>, Vec<ExecRequest>, Vec<InterruptedToolCall>) {
+        (self.in_flight.into_values().collect(), self.pending_calls, self.running_tools)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn entry(seq: u64, event: GoblinEvent) -> JournalEntry {
+        JournalEntry { seq, at: Utc::now(), record: JournalRecord::Event(event) }
+    }
+
+    #[test]
+    fn test_scan_follows_resumed_tasks() {
+        let (done, running) = (SubmissionId::new(), SubmissionId::new());
+        let (done_task, running_task, resumed_task) = (TaskId::new(), TaskId::new(), TaskId::new());
+        let started = |sub_id: &SubmissionId, task_id| {
+            GoblinEvent::from(Event::TaskStarted { sub_id: sub_id.clone(), task_id, prompt: "work".into() })
+        };
+        let mut entries = vec![
+            entry(1, started(&done, done_task)),
+            entry(2, started(&running, running_task)),
+            entry(3, Event::TaskInterrupted { sub_id: done.clone(), task_id: done_task }.into()),
+        ];
+        let scan = JournalScan::new(&entries);
+        assert!(scan.is_finished(&done));
+        assert!(!scan.is_finished(&running));
+
+        let report = RecoveryReport {
+            resumed: vec![InterruptedTask {
+                sub_id: running.clone(),
+                task_id: running_task,
+                prompt: "work".into(),
+                resumed_as: Some(resumed_task),
+            }],
+            ..Default::default()
+        };
+        entries.push(entry(4, GoblinEvent::RecoveryCompleted { sub_id: SubmissionId::new(), report }));
+        entries.push(entry(5, Event::TaskInterrupted { sub_id: running.clone(), task_id: running_task }.into()));
+        let mut scan = JournalScan::new(&entries);
+        assert_eq!(scan.take_in_flight(&running).unwrap().task_id, resumed_task);
+    }
+
+    #[test]
+    fn test_scan_skips_sessions_without_work_in_flight() {
+        let (idle, busy) = (SubmissionId::new(), SubmissionId::new());
+        let (done, running) = (SubmissionId::new(), SubmissionId::new());
+        let configured = |sub_id: &SubmissionId| {
+            GoblinEvent::from(Event::SessionConfigured {
+                sub_id: sub_id.clone(),
+                session_id: SessionId::new(),
+                config: Default::default(),
+            })
+        };
+        let started = |sub_id: &SubmissionId, task_id| {
+            GoblinEvent::from(Event::TaskStarted { sub_id: sub_id.clone(), task_id, prompt: "work".into() })
+        };
+        let done_task = TaskId::new();
+        let entries = vec![
+            entry(1, configured(&idle)),
+            entry(2, started(&done, done_task)),
+            entry(3, Event::TaskInterrupted { sub_id: done.clone(), task_id: done_task }.into()),
+            entry(4, configured(&busy)),
+            entry(5, started(&running, TaskId::new())),
+        ];
+        let scan = JournalScan::new(&entries);
+        assert!(scan.is_idle_session(&idle));
+        assert!(!scan.is_idle_session(&busy));
+        assert!(!scan.is_idle_session(&running));
+    }
+}
@@ -0,0 +1,274 @@
+//! Rendering of task plans and agent hierarchies as Graphviz DOT and Mermaid
+//!
+//! The output is plain text meant for UIs and documentation. Nodes are
+//! filled according to their status so progress is visible at a glance.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use warhorn::{AgentId, AgentRole, AgentStatus};
+
+use crate::agent::AgentHandle;
+use crate::hierarchy::AgentHierarchy;
+use crate::plan::{PlanStatus, TaskPlan};
+
+/// A node to be rendered
+struct Node {
+    label: String,
+    fill: &'static str,
+}
+
+/// Flattened graph shared by both output formats
+struct Graph {
+    nodes: Vec<Node>,
+    edges: Vec<(usize, usize)>,
+    dashed: Vec<(usize, usize)>,
+}
+
+/// Render a task plan as a Graphviz DOT digraph
+///
+/// Solid edges are parent/child decomposition, dashed edges are dependencies.
+pub fn plan_to_dot(plan: &TaskPlan) -> String {
+    to_dot("plan", &plan_graph(plan))
+}
+
+/// Render a task plan as a Mermaid flowchart
+pub fn plan_to_mermaid(plan: &TaskPlan) -> String {
+    to_mermaid(&plan_graph(plan))
+}
+
+/// Render an agent hierarchy as a Graphviz DOT digraph
+///
+/// Statuses are looked up in `agents`; agents missing from the map are
+/// rendered as terminated.
+pub fn hierarchy_to_dot(
+    hierarchy: &AgentHierarchy,
+    agents: &HashMap<AgentId, AgentHandle>,
+) -> String {
+    to_dot("hierarchy", &hierarchy_graph(hierarchy, agents))
+}
+
+/// Render an agent hierarchy as a Mermaid flowchart
+pub fn hierarchy_to_mermaid(
+    hierarchy: &AgentHierarchy,
+    agents: &HashMap<AgentId, AgentHandle>,
+) -> String {
+    to_mermaid(&hierarchy_graph(hierarchy, agents))
+}
+
+/// Human-readable label for a role
+pub fn role_label(role: &AgentRole) -> String {
+    match role {
+        AgentRole::Orchestrator => "Orchestrator".to_string(),
+        AgentRole::DomainLead { domain } => format!("Lead: {}", domain),
+        AgentRole::Specialist { specialty } => format!("Specialist: {}", specialty),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Fill color for a plan status
+pub fn plan_status_color(status: PlanStatus) -> &'static str {
+    match status {
+        PlanStatus::Pending => "#eeeeee",
+        PlanStatus::Running => "#9ecae1",
+        PlanStatus::Completed => "#a1d99b",
+        PlanStatus::Failed => "#fc9272",
+        PlanStatus::Cancelled => "#bdbdbd",
+    }
+}
+
+/// Fill color for an agent status
+pub fn agent_status_color(status: &AgentStatus) -> &'static str {
+    match status {
+        AgentStatus::Spawning | AgentStatus::Initializing => "#fff7bc",
+        AgentStatus::Running => "#9ecae1",
+        AgentStatus::Terminated => "#bdbdbd",
+        _ => "#eeeeee",
+    }
+}
+
+fn plan_graph(plan: &TaskPlan) -> Graph {
+    let index: HashMap<_, _> = plan
+        .tasks()
+        .iter()
+        .enumerate()
+        .map(|(i, t)| (t.id, i))
+        .collect();
+
+    let mut graph = Graph {
+        nodes: Vec::with_capacity(plan.len()),
+        edges: Vec::new(),
+        dashed: Vec::new(),
+    };
+
+    for (i, task) in plan.tasks().iter().enumerate() {
+        graph.nodes.push(Node {
+            label: format!("{}\n{}", role_label(&task.role), task.description),
+            fill: plan_status_color(task.status),
+        });
+
+        if let Some(parent) = task.parent.and_then(|p| index.get(&p)) {
+            graph.edges.push((*parent, i));
+        }
+        for dep in task.depends_on.iter().filter_map(|d| index.get(d)) {
+            graph.dashed.push((*dep, i));
+        }
+    }
+
+    graph
+}
+
+fn hierarchy_graph(
+    hierarchy: &AgentHierarchy,
+    agents: &HashMap<AgentId, AgentHandle>,
+) -> Graph {
+    let mut graph = Graph {
+        nodes: Vec::with_capacity(hierarchy.len()),
+        edges: Vec::new(),
+        dashed: Vec::new(),
+    };
+
+    let mut indices: HashMap<AgentId, usize> = HashMap::with_capacity(hierarchy.len());
+    for (id, _, role) in hierarchy.iter_dfs() {
+        let status = agents.get(&id).map(|a| a.status()).unwrap_or(AgentStatus::Terminated);
+
+        let index = graph.nodes.len();
+        graph.nodes.push(Node {
+            label: format!("{}\n{}\n{:?}", role_label(role), id, status),
+            fill: agent_status_color(&status),
+        });
+        if let Some(p) = hierarchy.parent(&id).and_then(|pid| indices.get(&pid)) {
+            graph.edges.push((*p, index));
+        }
+        indices.insert(id, index);
+    }
+
+    graph
+}
+
+fn to_dot(name: &str, graph: &Graph) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "digraph {} {{", name);
+    let _ = writeln!(out, "    node [shape=box, style=\"rounded,filled\"];");
+
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "    n{} [label=\"{}\", fillcolor=\"{}\"];",
+            i,
+            escape_dot(&node.label),
+            node.fill
+        );
+    }
+    for (from, to) in &graph.edges {
+        let _ = writeln!(out, "    n{} -> n{};", from, to);
+    }
+    for (from, to) in &graph.dashed {
+        let _ = writeln!(out, "    n{} -> n{} [style=dashed];", from, to);
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+fn to_mermaid(graph: &Graph) -> String {
+    let mut out = String::from("flowchart TD\n");
+
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let _ = writeln!(out, "    n{}[\"{}\"]", i, escape_mermaid(&node.label));
+    }
+    for (from, to) in &graph.edges {
+        let _ = writeln!(out, "    n{} --> n{}", from, to);
+    }
+    for (from, to) in &graph.dashed {
+        let _ = writeln!(out, "    n{} -.-> n{}", from, to);
+    }
+    for (i, node) in graph.nodes.iter().enumerate() {
+        let _ = writeln!(out, "    style n{} fill:{}", i, node.fill);
+    }
+
+    out
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;").replace('\n', "<br/>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plan::PlannedTask;
+    use warhorn::TaskId;
+
+    fn sample_plan() -> TaskPlan {
+        let mut plan = TaskPlan::new(TaskId::new(), "Build an API");
+        let lead = plan.add(PlannedTask::new(
+            "Backend",
+            AgentRole::DomainLead { domain: "backend".into() },
+        ));
+        let models = plan.add(PlannedTask::new("Models", AgentRole::Worker).with_parent(lead));
+        plan.add(
+            PlannedTask::new("Routes", AgentRole::Worker)
+                .with_parent(lead)
+                .depends_on(models),
+        );
+        plan.set_status(&models, PlanStatus::Completed);
+        plan
+    }
+
+    #[test]
+    fn test_plan_to_dot() {
+        let dot = plan_to_dot(&sample_plan());
+
+        assert!(dot.starts_with("digraph plan {"));
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains("n1 -> n2 [style=dashed];"));
+        assert!(dot.contains(plan_status_color(PlanStatus::Completed)));
+        assert!(dot.contains("Lead: backend\\nBackend"));
+    }
+
+    #[test]
+    fn test_plan_to_mermaid() {
+        let mermaid = plan_to_mermaid(&sample_plan());
+
+        assert!(mermaid.starts_with("flowchart TD"));
+        assert!(mermaid.contains("n0 --> n1"));
+        assert!(mermaid.contains("n1 -.-> n2"));
+        assert!(mermaid.contains("style n1 fill:#a1d99b"));
+    }
+
+    #[test]
+    fn test_hierarchy_rendering() {
+        let mut hierarchy = AgentHierarchy::new();
+        let root = AgentId::new();
+        let worker = AgentId::new();
+        hierarchy.add_agent(root, AgentRole::Orchestrator, None).unwrap();
+        hierarchy.add_agent(worker, AgentRole::Worker, Some(root)).unwrap();
+
+        let agents = HashMap::new();
+        let dot = hierarchy_to_dot(&hierarchy, &agents);
+        let mermaid = hierarchy_to_mermaid(&hierarchy, &agents);
+
+        assert!(dot.contains("n0 -> n1;"));
+        assert!(dot.contains(&root.to_string()));
+        assert!(mermaid.contains("n0 --> n1"));
+        assert!(mermaid.contains(agent_status_color(&AgentStatus::Terminated)));
+    }
+
+    #[test]
+    fn test_empty_hierarchy_rendering() {
+        let hierarchy = AgentHierarchy::new();
+        let dot = hierarchy_to_dot(&hierarchy, &HashMap::new());
+        assert_eq!(dot.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(escape_dot("a \"b\"\nc"), "a \\\"b\\\"\\nc");
+        assert_eq!(escape_mermaid("a \"b\"\nc"), "a #quot;b#quot;<br/>c");
+    }
+}
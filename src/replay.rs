@@ -0,0 +1,125 @@
+//! Replay of recent events to late subscribers
+//!
+//! A UI that subscribes after a session started has missed the spawns and
+//! task events that describe its current state. A [`ReplayBuffer`] keeps
+//! the most recent critical events of each session and hands them to new
+//! subscribers ahead of the live stream, with nothing missed in between.
+//! Events are kept before they are forwarded, so one that is still on its
+//! way when a client subscribes may reach it both replayed and live.
+
+use std::collections::{HashMap, VecDeque};
+
+use parking_lot::Mutex;
+use warhorn::SessionId;
+
+use crate::filter::EventCategory;
+use crate::protocol::GoblinEvent;
+
+/// Recent critical events, per session
+#[derive(Debug)]
+pub struct ReplayBuffer {
+    /// Events kept per session
+    capacity: usize,
+    inner: Mutex<Buffered>,
+}
+
+#[derive(Debug, Default)]
+struct Buffered {
+    /// Sequence number of the next event, to merge sessions in order
+    next_seq: u64,
+    sessions: HashMap<Option<SessionId>, VecDeque<(u64, GoblinEvent)>>,
+}
+
+impl ReplayBuffer {
+    /// Keep up to `capacity` events per session
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Buffered::default()),
+        }
+    }
+
+    /// Whether an event is kept for replay: session setup, agent status
+    /// and hierarchy changes, and task lifecycle events
+    pub fn is_critical(event: &GoblinEvent) -> bool {
+        let Some((kind, _)) = event.describe() else {
+            return false;
+        };
+        kind == "SessionConfigured"
+            || matches!(EventCategory::of(&kind), EventCategory::Status)
+            || matches!(kind.as_str(), "TaskStarted" | "TaskInterrupted" | "TaskResult")
+    }
+
+    /// Keep an event if it is critical, returning it to be forwarded
+    ///
+    /// Forward the event only after this returns, so a concurrent
+    /// [`subscribe`](Self::subscribe) cannot miss it.
+    pub(crate) fn publish(&self, session_id: Option<SessionId>, event: GoblinEvent) -> GoblinEvent {
+        let mut inner = self.inner.lock();
+        if Self::is_critical(&event) {
+            let seq = inner.next_seq;
+            inner.next_seq += 1;
+            let events = inner.sessions.entry(session_id).or_default();
+            if events.len() == self.capacity {
+                events.pop_front();
+            }
+            if self.capacity > 0 {
+                events.push_back((seq, event.clone()));
+            }
+        }
+        event
+    }
+
+    /// Subscribe to the live stream, returning the buffered events of every
+    /// session, oldest first, together with the subscription
+    pub(crate) fn subscribe<R>(&self, subscribe: impl FnOnce() -> R) -> (Vec<GoblinEvent>, R) {
+        let inner = self.inner.lock();
+        let mut events: Vec<&(u64, GoblinEvent)> = inner.sessions.values().flatten().collect();
+        events.sort_by_key(|(seq, _)| *seq);
+        let events = events.into_iter().map(|(_, event)| event.clone()).collect();
+        (events, subscribe())
+    }
+
+    /// Buffered events of a session, oldest first
+    pub fn events(&self, session_id: &SessionId) -> Vec<GoblinEvent> {
+        let inner = self.inner.lock();
+        inner
+            .sessions
+            .get(&Some(*session_id))
+            .map(|events| events.iter().map(|(_, event)| event.clone()).collect())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warhorn::{AgentId, Event, SubmissionId, TaskId};
+
+    #[test]
+    fn test_keeps_recent_critical_events_per_session() {
+        let buffer = ReplayBuffer::new(2);
+        let (one, two) = (SessionId::new(), SessionId::new());
+        let paused = |agent_id| GoblinEvent::AgentPaused { sub_id: SubmissionId::new(), agent_id };
+        let started = GoblinEvent::from(Event::TaskStarted { sub_id: SubmissionId::new(), task_id: TaskId::new() });
+        let warning = GoblinEvent::from(Event::Warning {
+            sub_id: SubmissionId::new(),
+            message: "slow".into(),
+            details: None,
+        });
+
+        for event in [paused(AgentId::new()), started, warning, paused(AgentId::new())] {
+            buffer.publish(Some(one), event);
+        }
+        // Every event is handed back for forwarding, kept or not
+        let forwarded = buffer.publish(Some(two), paused(AgentId::new()));
+        assert!(matches!(forwarded, GoblinEvent::AgentPaused { .. }));
+
+        let kinds = |events: Vec<GoblinEvent>| -> Vec<String> {
+            events.iter().map(|e| e.describe().unwrap().0).collect()
+        };
+        assert_eq!(kinds(buffer.events(&one)), vec!["TaskStarted", "AgentPaused"]);
+        let (replay, ()) = buffer.subscribe(|| ());
+        assert_eq!(kinds(replay), vec!["TaskStarted", "AgentPaused", "AgentPaused"]);
+    }
+}
@@ -0,0 +1,192 @@
+//! Task results reported by agents
+//!
+//! A [`TaskResult`] is the structured outcome of a task: its status, a
+//! summary for humans, and typed outputs (a JSON payload, the files the
+//! agent changed, attachments) plus the tokens it took, so parents and
+//! clients can consume outcomes programmatically.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentId, TaskId, TokenUsage};
+
+use crate::artifact::{ArtifactId, Attachment};
+use crate::retry::TaskAttempt;
+
+/// How a task ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ResultStatus {
+    /// The task was completed
+    Succeeded,
+    /// The task could not be completed
+    Failed,
+    /// The task was abandoned before it finished
+    Cancelled,
+}
+
+/// Kind of change made to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// A file changed by a task
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileChange {
+    /// Path of the file, relative to the session's working directory
+    pub path: PathBuf,
+    /// What happened to it
+    pub kind: ChangeKind,
+}
+
+/// Outcome of a task or subtask
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskResult {
+    /// Task the result belongs to
+    pub task_id: TaskId,
+    /// Agent that produced the result (None for merged results)
+    pub agent_id: Option<AgentId>,
+    /// How the task ended
+    pub status: ResultStatus,
+    /// Human-readable summary
+    pub summary: String,
+    /// Structured answer, if the task produces one
+    #[serde(default)]
+    pub payload: Option<serde_json::Value>,
+    /// Files changed while working on the task
+    #[serde(default)]
+    pub files_changed: Vec<FileChange>,
+    /// Supporting files and blobs
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Artifacts in the session's store, e.g. large outputs
+    #[serde(default)]
+    pub artifacts: Vec<ArtifactId>,
+    /// Tokens spent on the task
+    #[serde(default)]
+    pub usage: TokenUsage,
+    /// Earlier failed attempts at the task, when it was retried
+    #[serde(default)]
+    pub attempts: Vec<TaskAttempt>,
+}
+
+impl TaskResult {
+    /// Create a result with the given status
+    pub fn new(task_id: TaskId, status: ResultStatus, summary: impl Into<String>) -> Self {
+        Self {
+            task_id,
+            agent_id: None,
+            status,
+            summary: summary.into(),
+            payload: None,
+            files_changed: Vec::new(),
+            attachments: Vec::new(),
+            artifacts: Vec::new(),
+            usage: TokenUsage::default(),
+            attempts: Vec::new(),
+        }
+    }
+
+    /// A successful result
+    pub fn success(task_id: TaskId, summary: impl Into<String>) -> Self {
+        Self::new(task_id, ResultStatus::Succeeded, summary)
+    }
+
+    /// A failed result
+    pub fn failure(task_id: TaskId, summary: impl Into<String>) -> Self {
+        Self::new(task_id, ResultStatus::Failed, summary)
+    }
+
+    /// A cancelled result
+    pub fn cancelled(task_id: TaskId, summary: impl Into<String>) -> Self {
+        Self::new(task_id, ResultStatus::Cancelled, summary)
+    }
+
+    /// Whether the task succeeded
+    pub fn is_success(&self) -> bool {
+        self.status == ResultStatus::Succeeded
+    }
+
+    /// Attach a structured answer
+    pub fn with_payload(mut self, payload: serde_json::Value) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    /// Record a changed file
+    pub fn with_file_change(mut self, path: impl Into<PathBuf>, kind: ChangeKind) -> Self {
+        self.files_changed.push(FileChange {
+            path: path.into(),
+            kind,
+        });
+        self
+    }
+
+    /// Refer to an artifact in the session's store
+    pub fn with_artifact(mut self, artifact_id: ArtifactId) -> Self {
+        self.artifacts.push(artifact_id);
+        self
+    }
+
+    /// Set the tokens spent on the task
+    pub fn with_usage(mut self, usage: TokenUsage) -> Self {
+        self.usage = usage;
+        self
+    }
+
+    /// Attribute the result to an agent
+    pub fn from_agent(mut self, agent_id: AgentId) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+}
+
+/// Sum the token usage of several results
+pub fn total_usage<'a>(results: impl IntoIterator<Item = &'a TaskResult>) -> TokenUsage {
+    sum_usage(results.into_iter().map(|r| &r.usage))
+}
+
+/// Sum several token usages
+pub fn sum_usage<'a>(usages: impl IntoIterator<Item = &'a TokenUsage>) -> TokenUsage {
+    let mut total = TokenUsage::default();
+    for usage in usages {
+        total.input_tokens += usage.input_tokens;
+        total.output_tokens += usage.output_tokens;
+        total.total_tokens += usage.total_tokens;
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_result_round_trips_as_json() {
+        let mut usage = TokenUsage::default();
+        usage.total_tokens = 42;
+        let result = TaskResult::success(TaskId::new(), "done")
+            .with_payload(serde_json::json!({ "tests": 12 }))
+            .with_file_change("src/lib.rs", ChangeKind::Modified)
+            .with_usage(usage);
+
+        let json = serde_json::to_string(&result).unwrap();
+        let back: TaskResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, result);
+        assert!(back.is_success());
+        assert_eq!(total_usage([&result, &result]).total_tokens, 84);
+    }
+
+    #[test]
+    fn test_minimal_result_parses() {
+        let json = format!(
+            r#"{{"task_id":{},"agent_id":null,"status":"Failed","summary":"no"}}"#,
+            serde_json::to_string(&TaskId::new()).unwrap()
+        );
+        let result: TaskResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(result.status, ResultStatus::Failed);
+        assert!(result.files_changed.is_empty());
+    }
+}
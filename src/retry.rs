@@ -0,0 +1,113 @@
+//! Retries of failed tasks
+//!
+//! With a [`RetryPolicy`] in the session options, a task an agent's
+//! runtime fails is not reported at once: it is assigned again after a
+//! growing, jittered delay, to the same agent or to a fresh one with the
+//! same configuration, until it succeeds or has been attempted
+//! `max_attempts` times. Every failed attempt is reported in a
+//! `TaskRetried` event and listed in the task's final result.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use warhorn::AgentId;
+
+use crate::error::GoblinError;
+use crate::supervise::Backoff;
+
+/// How failed tasks are retried
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Attempts in all, the first one included
+    pub max_attempts: u32,
+    #[serde(default)]
+    pub backoff: Backoff,
+    /// Share of each delay added or taken away at random
+    #[serde(default)]
+    pub jitter: f64,
+    /// Retry on a fresh agent with the same configuration instead of the one that failed
+    #[serde(default)]
+    pub fresh_agent: bool,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            backoff: Backoff::default(),
+            jitter: 0.0,
+            fresh_agent: false,
+        }
+    }
+
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Vary each delay by up to this share of it
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Retry on a fresh agent
+    pub fn on_fresh_agent(mut self) -> Self {
+        self.fresh_agent = true;
+        self
+    }
+
+    /// Delay before the retry following failed attempt `attempt`
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.backoff.delay(attempt);
+        if self.jitter == 0.0 {
+            return delay;
+        }
+        // Every RandomState hashes with different keys, which spreads retries
+        // well enough without pulling in a random number generator
+        let random = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        delay.mul_f64(1.0 + self.jitter * (2.0 * random - 1.0))
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.max_attempts == 0 {
+            return Err(GoblinError::ConfigError("retry max_attempts must be at least 1".into()));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(GoblinError::ConfigError("retry jitter must be between 0 and 1".into()));
+        }
+        self.backoff.validate()
+    }
+}
+
+/// A failed attempt at a task that was retried
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskAttempt {
+    /// Attempt number, counting from 1
+    pub attempt: u32,
+    /// Agent that made the attempt
+    pub agent_id: Option<AgentId>,
+    /// Summary of the failed result
+    pub error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jitter_stays_within_its_share() {
+        let policy = RetryPolicy::new(4).with_backoff(Backoff::new(1000, 10_000)).with_jitter(0.25);
+        for attempt in 1..=3 {
+            let base = 1000.0 * 2f64.powi(attempt as i32 - 1);
+            let delay = policy.delay(attempt).as_millis() as f64;
+            assert!(delay >= base * 0.75 - 1.0 && delay <= base * 1.25 + 1.0);
+        }
+        assert_eq!(RetryPolicy::new(2).with_backoff(Backoff::new(50, 50)).delay(3), Duration::from_millis(50));
+        assert!(RetryPolicy::new(0).validate().is_err());
+    }
+}
@@ -0,0 +1,49 @@
+//! Reviewing worker results before they are merged
+//!
+//! With a [`ReviewPolicy`] in the session options and a [`Reviewer`]
+//! installed, every successful subtask result is checked by a transient
+//! reviewer agent spawned as a sibling of the worker. The reviewer either
+//! approves the result, letting it flow up to the merger, or bounces it
+//! back to the worker with feedback. After `max_bounces` rejections the
+//! result is recorded as failed.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::agent::AgentHandle;
+use crate::error::GoblinError;
+use crate::result::TaskResult;
+
+/// When and how often results are reviewed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReviewPolicy {
+    /// Times a result may be bounced back before it counts as failed
+    pub max_bounces: u32,
+}
+
+impl Default for ReviewPolicy {
+    fn default() -> Self {
+        Self { max_bounces: 2 }
+    }
+}
+
+/// A reviewer's decision
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Review {
+    /// The result may flow up
+    Approve,
+    /// The worker must try again
+    Bounce { feedback: String },
+}
+
+/// Reviews results on behalf of a reviewer agent
+#[async_trait]
+pub trait Reviewer: Send + Sync {
+    /// Review the result of a subtask
+    async fn review(
+        &self,
+        agent: &AgentHandle,
+        description: &str,
+        result: &TaskResult,
+    ) -> Result<Review, GoblinError>;
+}
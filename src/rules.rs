@@ -0,0 +1,260 @@
+//! Event-driven automation rules
+//!
+//! Rules react to the session's event stream without an external
+//! controller: "when an agent's deadline is missed twice, terminate it and
+//! notify the on-call webhook", "when usage passes 2M tokens, run this op".
+//! The orchestrator feeds every event through the [`RuleEngine`] and
+//! executes the actions of the rules that fire. `RuleFired` events are not
+//! fed back to rules, so rules cannot trigger each other in a loop.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use warhorn::{AgentId, TaskId, TokenUsage};
+
+use crate::protocol::{GoblinEvent, GoblinOp};
+
+/// An automation rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    /// Rule name, reported when it fires
+    pub name: String,
+    /// What makes the rule fire
+    pub when: Trigger,
+    /// What to do when it fires
+    pub then: Vec<Action>,
+}
+
+impl Rule {
+    /// Create a rule
+    pub fn new(name: impl Into<String>, when: Trigger) -> Self {
+        Self {
+            name: name.into(),
+            when,
+            then: Vec::new(),
+        }
+    }
+
+    /// Add an action
+    pub fn then(mut self, action: Action) -> Self {
+        self.then.push(action);
+        self
+    }
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// Condition that fires a rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Trigger {
+    /// Events of a kind (e.g. `TaskInterrupted`) were seen `count` times
+    ///
+    /// The counter resets every time the rule fires.
+    Event {
+        kind: String,
+        #[serde(default = "one")]
+        count: u32,
+        /// Count separately for every agent
+        #[serde(default)]
+        per_agent: bool,
+    },
+    /// Total token usage in the session passed a threshold (fires once)
+    UsageAbove { total_tokens: u64 },
+}
+
+/// Something a rule does when it fires
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Action {
+    /// Report a notification for a target (e.g. a webhook URL) to the client
+    Notify { target: String, message: String },
+    /// Interrupt the task named in the triggering event
+    InterruptTask,
+    /// Terminate the agent named in the triggering event and its subtree
+    TerminateAgent,
+    /// Submit an operation
+    Op(GoblinOp),
+}
+
+/// A notification produced by a rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    /// Where the host should deliver it
+    pub target: String,
+    /// Message text
+    pub message: String,
+}
+
+/// A rule that fired
+#[derive(Debug, Clone)]
+pub struct Firing {
+    /// Rule name
+    pub rule: String,
+    /// Agent named in the triggering event
+    pub agent_id: Option<AgentId>,
+    /// Task named in the triggering event
+    pub task_id: Option<TaskId>,
+    /// Actions to execute
+    pub actions: Vec<Action>,
+}
+
+/// Evaluates rules against the event stream
+#[derive(Debug, Default)]
+pub struct RuleEngine {
+    rules: Vec<Rule>,
+    counts: HashMap<(usize, Option<AgentId>), u32>,
+    latched: HashSet<usize>,
+}
+
+impl RuleEngine {
+    /// Create an engine with the given rules
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self {
+            rules,
+            ..Default::default()
+        }
+    }
+
+    /// Check if there are no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Feed an event, returning the rules that fire
+    ///
+    /// `RuleFired` events never fire rules.
+    pub fn observe(&mut self, event: &GoblinEvent, usage: &TokenUsage) -> Vec<Firing> {
+        if matches!(event, GoblinEvent::RuleFired { .. }) {
+            return Vec::new();
+        }
+        let Some((kind, body)) = event.describe() else {
+            return Vec::new();
+        };
+        let agent_id: Option<AgentId> = field(&body, "agent_id");
+        let task_id: Option<TaskId> = field(&body, "task_id");
+
+        let mut fired = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            let fires = match &rule.when {
+                Trigger::Event { kind: wanted, count, per_agent } => {
+                    if *wanted != kind {
+                        continue;
+                    }
+                    let key = (index, if *per_agent { agent_id } else { None });
+                    let seen = self.counts.entry(key).or_insert(0);
+                    *seen += 1;
+                    if *seen >= *count {
+                        *seen = 0;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                Trigger::UsageAbove { total_tokens } => {
+                    usage.total_tokens > *total_tokens && self.latched.insert(index)
+                }
+            };
+
+            if fires {
+                fired.push(Firing {
+                    rule: rule.name.clone(),
+                    agent_id,
+                    task_id,
+                    actions: rule.then.clone(),
+                });
+            }
+        }
+        fired
+    }
+}
+
+fn field<T: serde::de::DeserializeOwned>(body: &Value, name: &str) -> Option<T> {
+    body.get(name).and_then(|v| serde_json::from_value(v.clone()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::deadline::DeadlineAction;
+    use warhorn::SubmissionId;
+
+    fn missed(agent_id: AgentId) -> GoblinEvent {
+        GoblinEvent::TaskDeadlineExceeded {
+            sub_id: SubmissionId::new(),
+            task_id: TaskId::new(),
+            agent_id,
+            deadline_ms: 10,
+            action: DeadlineAction::Warn,
+        }
+    }
+
+    #[test]
+    fn test_describe_event() {
+        let agent = AgentId::new();
+        let (kind, body) = missed(agent).describe().unwrap();
+        assert_eq!(kind, "TaskDeadlineExceeded");
+        assert_eq!(field::<AgentId>(&body, "agent_id"), Some(agent));
+    }
+
+    #[test]
+    fn test_event_count_per_agent() {
+        let mut engine = RuleEngine::new(vec![Rule::new(
+            "repeat offender",
+            Trigger::Event {
+                kind: "TaskDeadlineExceeded".into(),
+                count: 2,
+                per_agent: true,
+            },
+        )
+        .then(Action::TerminateAgent)]);
+        let usage = TokenUsage::default();
+        let a = AgentId::new();
+        let b = AgentId::new();
+
+        assert!(engine.observe(&missed(a), &usage).is_empty());
+        assert!(engine.observe(&missed(b), &usage).is_empty());
+        let fired = engine.observe(&missed(a), &usage);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].agent_id, Some(a));
+
+        // The counter starts over after firing
+        assert!(engine.observe(&missed(a), &usage).is_empty());
+    }
+
+    #[test]
+    fn test_usage_fires_once() {
+        let mut engine = RuleEngine::new(vec![Rule::new(
+            "budget",
+            Trigger::UsageAbove { total_tokens: 100 },
+        )]);
+        let event = missed(AgentId::new());
+        let mut usage = TokenUsage::default();
+
+        assert!(engine.observe(&event, &usage).is_empty());
+        usage.total_tokens = 101;
+        assert_eq!(engine.observe(&event, &usage).len(), 1);
+        assert!(engine.observe(&event, &usage).is_empty());
+    }
+
+    #[test]
+    fn test_rule_firings_do_not_trigger_rules() {
+        let mut engine = RuleEngine::new(vec![Rule::new(
+            "echo",
+            Trigger::Event {
+                kind: "RuleFired".into(),
+                count: 1,
+                per_agent: false,
+            },
+        )]);
+        let fired = GoblinEvent::RuleFired {
+            sub_id: SubmissionId::new(),
+            rule: "echo".into(),
+            agent_id: None,
+            task_id: None,
+            notifications: Vec::new(),
+        };
+        assert!(engine.observe(&fired, &TokenUsage::default()).is_empty());
+    }
+}
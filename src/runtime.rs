@@ -0,0 +1,332 @@
+//! Pluggable agent runtimes
+//!
+//! An [`AgentRuntime`] is the engine behind an agent's think/act loop:
+//! the actor loop hands it the tasks and messages the agent receives and
+//! reports the results it produces. Different engines (LLM-driven,
+//! rule-based, a remote service) can be plugged in per role through
+//! [`Runtimes`]; agents whose role has no runtime of its own use the
+//! default, [`DefaultRuntime`], which hands the task to the agent's model.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentConfig, AgentId, AgentRole, SubmissionId, TaskId};
+
+use crate::agent::AgentHandle;
+use crate::approval::ExecRequest;
+use crate::error::GoblinError;
+use crate::failure::FailureReport;
+use crate::handoff::excerpt;
+use crate::history::HistoryEntry;
+use crate::model::{ChatMessage, ChatRole};
+use crate::result::TaskResult;
+
+/// A task handed to a runtime
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskAssignment {
+    pub task_id: TaskId,
+    pub instructions: String,
+    /// Submission the agent reports under
+    pub sub_id: SubmissionId,
+}
+
+/// Execution engine for agents
+#[async_trait]
+pub trait AgentRuntime: Send + Sync {
+    /// Runtime name, for logs and capability discovery
+    fn name(&self) -> &str;
+
+    /// Work on a task until it is done
+    ///
+    /// The future is dropped if the agent is interrupted or shut down.
+    async fn run_task(&self, agent: &AgentHandle, assignment: &TaskAssignment) -> Result<TaskResult, GoblinError>;
+
+    /// React to a message delivered to the agent
+    async fn on_message(
+        &self,
+        _agent: &AgentHandle,
+        _from: Option<AgentId>,
+        _content: &str,
+    ) -> Result<(), GoblinError> {
+        Ok(())
+    }
+
+    /// Approve or deny a descendant's command escalated to the agent, or
+    /// return None to leave the decision to the client
+    ///
+    /// Like other mail, requests reach an agent busy with a task only once
+    /// the task is done.
+    async fn decide_approval(&self, _agent: &AgentHandle, _request: &ExecRequest) -> Option<bool> {
+        None
+    }
+
+    /// React to a descendant that keeps failing
+    ///
+    /// By default the report is added to the agent's history, for its
+    /// next turn to reassign, simplify or report the failed task.
+    async fn on_failure_report(&self, agent: &AgentHandle, report: &FailureReport) -> Result<(), GoblinError> {
+        agent.record(report.to_entry());
+        Ok(())
+    }
+
+    /// React to every child of a task the agent is responsible for having
+    /// finished
+    ///
+    /// By default the results are added to the agent's history, for its
+    /// next turn to synthesize them into its own result.
+    async fn on_children_completed(
+        &self,
+        agent: &AgentHandle,
+        task_id: TaskId,
+        results: &[TaskResult],
+    ) -> Result<(), GoblinError> {
+        let mut content = format!("Every subtask of task {} finished", task_id);
+        for result in results {
+            content.push_str(&format!(
+                "\n\nResult for task {} ({:?}): {}",
+                result.task_id, result.status, excerpt(&result.summary)
+            ));
+        }
+        agent.record(HistoryEntry::message(ChatRole::System, content));
+        Ok(())
+    }
+}
+
+/// The runtime agents use unless their role has another one
+///
+/// Each task is a single model turn over the agent's history, streamed to
+/// the client; the reply is the result summary. Messages to the agent are
+/// added to its history for the next turn.
+#[derive(Debug, Default)]
+pub struct DefaultRuntime;
+
+#[async_trait]
+impl AgentRuntime for DefaultRuntime {
+    fn name(&self) -> &str {
+        "default"
+    }
+
+    async fn run_task(&self, agent: &AgentHandle, assignment: &TaskAssignment) -> Result<TaskResult, GoblinError> {
+        if !agent.has_model() {
+            return Ok(TaskResult::failure(
+                assignment.task_id,
+                "No model provider is configured for this agent",
+            ));
+        }
+
+        agent.record(HistoryEntry::message(ChatRole::User, assignment.instructions.as_str()));
+        agent.compact_history(&assignment.sub_id).await?;
+        let mut messages = vec![ChatMessage::system(system_prompt(agent.role()))];
+        messages.extend(agent.history().to_messages());
+
+        let response = agent.stream(&assignment.sub_id, messages).await?;
+        agent.record(HistoryEntry::message(ChatRole::Assistant, response.content.as_str()));
+        Ok(TaskResult::success(assignment.task_id, response.content).with_usage(response.usage))
+    }
+
+    async fn on_message(&self, agent: &AgentHandle, from: Option<AgentId>, content: &str) -> Result<(), GoblinError> {
+        let content = match from {
+            Some(from) => format!("Message from agent {}: {}", from, content),
+            None => content.to_string(),
+        };
+        agent.record(HistoryEntry::message(ChatRole::User, content));
+        Ok(())
+    }
+}
+
+fn system_prompt(role: &AgentRole) -> String {
+    format!(
+        "You are a {} agent in a hierarchy of agents. Complete the task you are given and reply with the result.",
+        role_key(role).replace('_', " "),
+    )
+}
+
+/// Key runtimes are registered under for a role
+///
+/// Specialists can be targeted by specialty (`specialist:reviewer`) or all
+/// at once (`specialist`).
+pub fn role_key(role: &AgentRole) -> String {
+    match role {
+        AgentRole::Orchestrator => "orchestrator".into(),
+        AgentRole::DomainLead { .. } => "domain_lead".into(),
+        AgentRole::Specialist { specialty } => format!("specialist:{}", specialty),
+        other => format!("{:?}", other).to_lowercase(),
+    }
+}
+
+/// Keys that name `role` in per-role settings, most specific first
+///
+/// A specialist answers to `specialist:reviewer` and then `specialist`;
+/// other roles have only their [`role_key`].
+pub fn role_keys(role: &AgentRole) -> Vec<String> {
+    let key = role_key(role);
+    match key.split_once(':') {
+        Some((general, _)) => {
+            let general = general.to_string();
+            vec![key, general]
+        }
+        None => vec![key],
+    }
+}
+
+/// Entry of a per-role setting for `role`, by its most specific key
+pub(crate) fn by_role<'a, T>(map: &'a HashMap<String, T>, role: &AgentRole) -> Option<&'a T> {
+    role_keys(role).iter().find_map(|key| map.get(key))
+}
+
+/// Runtimes by role, with a default
+#[derive(Clone)]
+pub struct Runtimes {
+    default: Arc<dyn AgentRuntime>,
+    by_role: HashMap<String, Arc<dyn AgentRuntime>>,
+}
+
+impl Default for Runtimes {
+    fn default() -> Self {
+        Self::new(Arc::new(DefaultRuntime))
+    }
+}
+
+impl std::fmt::Debug for Runtimes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut roles: Vec<_> = self.by_role.iter().map(|(k, v)| (k.as_str(), v.name())).collect();
+        roles.sort();
+        f.debug_struct("Runtimes")
+            .field("default", &self.default.name())
+            .field("by_role", &roles)
+            .finish()
+    }
+}
+
+impl Runtimes {
+    /// Use `default` for every role
+    pub fn new(default: Arc<dyn AgentRuntime>) -> Self {
+        Self {
+            default,
+            by_role: HashMap::new(),
+        }
+    }
+
+    /// Use a runtime for one role (see [`role_key`])
+    pub fn with_role(mut self, role: impl Into<String>, runtime: Arc<dyn AgentRuntime>) -> Self {
+        self.by_role.insert(role.into(), runtime);
+        self
+    }
+
+    /// Runtime for an agent configuration
+    pub fn for_config(&self, config: &AgentConfig) -> Arc<dyn AgentRuntime> {
+        by_role(&self.by_role, &config.role)
+            .cloned()
+            .unwrap_or_else(|| Arc::clone(&self.default))
+    }
+
+    /// Names of all registered runtimes
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = std::iter::once(self.default.name())
+            .chain(self.by_role.values().map(|r| r.name()))
+            .map(String::from)
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+    use trinkets::ToolRegistry;
+    use warhorn::TokenUsage;
+
+    use crate::agent::Agent;
+    use crate::model::{ChatRequest, ChatResponse, DeltaSink, ModelBinding, ModelProvider, ModelProviders};
+
+    struct Named(&'static str);
+
+    #[async_trait]
+    impl AgentRuntime for Named {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        async fn run_task(&self, _agent: &AgentHandle, a: &TaskAssignment) -> Result<TaskResult, GoblinError> {
+            Ok(TaskResult::success(a.task_id, self.0))
+        }
+    }
+
+    struct Parrot;
+
+    #[async_trait]
+    impl ModelProvider for Parrot {
+        fn name(&self) -> &str {
+            "parrot"
+        }
+
+        async fn complete(&self, request: &ChatRequest, on_delta: DeltaSink<'_>) -> Result<ChatResponse, GoblinError> {
+            let content = request.messages.last().unwrap().content.clone();
+            on_delta(&content);
+            Ok(ChatResponse {
+                content,
+                usage: TokenUsage {
+                    input_tokens: 3,
+                    output_tokens: 2,
+                    total_tokens: 5,
+                },
+            })
+        }
+    }
+
+    fn config(role: AgentRole) -> AgentConfig {
+        AgentConfig {
+            role,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_runtime_selection_by_role() {
+        let runtimes = Runtimes::default()
+            .with_role("worker", Arc::new(Named("rules")))
+            .with_role("specialist", Arc::new(Named("remote")))
+            .with_role("specialist:judge", Arc::new(Named("llm")));
+
+        let pick = |role| runtimes.for_config(&config(role)).name().to_string();
+        assert_eq!(pick(AgentRole::Worker), "rules");
+        assert_eq!(pick(AgentRole::Orchestrator), "default");
+        assert_eq!(pick(AgentRole::Specialist { specialty: "judge".into() }), "llm");
+        assert_eq!(pick(AgentRole::Specialist { specialty: "reviewer".into() }), "remote");
+        assert_eq!(runtimes.names(), vec!["default", "llm", "remote", "rules"]);
+    }
+
+    #[tokio::test]
+    async fn test_default_runtime_uses_the_model() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let agent = |model: Option<ModelBinding>| {
+            let agent = Agent::new(config(AgentRole::Worker), None, Arc::new(ToolRegistry::new()), tx.clone());
+            AgentHandle::new(match model {
+                Some(model) => agent.with_model(model),
+                None => agent,
+            })
+        };
+        let assignment = TaskAssignment {
+            task_id: TaskId::new(),
+            instructions: "Say hi".into(),
+            sub_id: SubmissionId::new(),
+        };
+
+        let unbound = agent(None);
+        let result = DefaultRuntime.run_task(&unbound, &assignment).await.unwrap();
+        assert!(!result.is_success());
+
+        let bound = agent(ModelProviders::new().with_default(Arc::new(Parrot)).resolve("any"));
+        let result = DefaultRuntime.run_task(&bound, &assignment).await.unwrap();
+        assert!(result.is_success());
+        assert_eq!(result.summary, "Say hi");
+        assert_eq!(bound.usage().total_tokens, 5);
+        assert_eq!(bound.history_len(), 2);
+        assert_eq!(bound.summary().history_tokens, bound.history_tokens());
+    }
+}
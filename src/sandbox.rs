@@ -0,0 +1,525 @@
+//! Per-agent sandbox profiles
+//!
+//! Tools that run commands confine them with wardstone. A
+//! [`SandboxProfile`] says how: which directories the command may write,
+//! whether it may reach the network, and what resources it may use. The
+//! session picks each agent's profile by role when it is spawned, and the
+//! agent's tool context carries it to every tool execution under
+//! [`SANDBOX_PROFILE_ENV`].
+//!
+//! An agent whose tool call its sandbox blocked can ask for more with a
+//! [`SandboxEscalation`], saying what it needs and why. An
+//! [`EscalationRule`] grants requests within its limit; the client answers
+//! the rest with `GrantSandboxEscalation`. A grant is a
+//! [`SandboxException`] widening the agent's profile by exactly what was
+//! asked, until it expires; the agent then retries the call.
+
+use std::collections::HashMap;
+use std::path::{Component, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+use warhorn::{AgentId, AgentRole, CallId, SubmissionId};
+
+use crate::approval::{normalize_path, SandboxLevel};
+use crate::audit::{AuditLog, AuditRecord};
+use crate::error::GoblinError;
+use crate::protocol::GoblinEvent;
+use crate::runtime::{by_role, role_keys};
+
+/// Environment variable under which tools find the agent's profile, as JSON
+pub const SANDBOX_PROFILE_ENV: &str = "CABAL_SANDBOX_PROFILE";
+
+/// How long a granted exception lasts unless the grant says otherwise
+pub const DEFAULT_EXCEPTION_TTL: Duration = Duration::from_secs(600);
+
+/// Longest an exception lasts, whatever the grant says
+pub const MAX_EXCEPTION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Limits on what a sandboxed command may consume; unset means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// CPU time in seconds
+    #[serde(default)]
+    pub cpu_secs: Option<u64>,
+    /// Resident memory in bytes
+    #[serde(default)]
+    pub memory_bytes: Option<u64>,
+    /// Processes the command may have running at once
+    #[serde(default)]
+    pub max_processes: Option<u32>,
+}
+
+/// How an agent's tool executions are confined
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxProfile {
+    /// Filesystem access; the agent's trust decides if None
+    #[serde(default)]
+    pub level: Option<SandboxLevel>,
+    /// Directories writable besides the working directory, under `WorkspaceWrite`
+    #[serde(default)]
+    pub writable_roots: Vec<PathBuf>,
+    #[serde(default)]
+    pub network: bool,
+    #[serde(default)]
+    pub limits: ResourceLimits,
+}
+
+impl SandboxProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give commands this filesystem access, whatever the agent's trust
+    pub fn with_level(mut self, level: SandboxLevel) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Let commands write a directory besides the working directory
+    pub fn with_writable_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.writable_roots.push(root.into());
+        self
+    }
+
+    pub fn with_network(mut self, network: bool) -> Self {
+        self.network = network;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: ResourceLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The profile with a grant's access added
+    pub fn widened(&self, grant: &SandboxGrant) -> SandboxProfile {
+        let mut profile = self.clone();
+        profile.level = profile.level.max(grant.level);
+        profile.writable_roots.extend(grant.writable_roots.iter().cloned());
+        profile.network |= grant.network;
+        profile
+    }
+
+    /// Check that the profile can be enforced
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if let Some(root) = self.writable_roots.iter().find(|r| !r.is_absolute()) {
+            return Err(GoblinError::ConfigError(format!(
+                "Sandbox writable root must be absolute: {}",
+                root.display()
+            )));
+        }
+        let ResourceLimits { cpu_secs, memory_bytes, max_processes } = self.limits;
+        if cpu_secs == Some(0) || memory_bytes == Some(0) || max_processes == Some(0) {
+            return Err(GoblinError::ConfigError("Sandbox resource limits must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Sandbox profiles of a session's agents, by role
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxProfiles {
+    /// Profile of agents no role entry matches
+    #[serde(default)]
+    pub default: SandboxProfile,
+    /// Profiles by role key, like `worker` or `specialist:reviewer`
+    #[serde(default)]
+    pub roles: HashMap<String, SandboxProfile>,
+    /// Rules granting escalations without the client, the first match winning
+    #[serde(default)]
+    pub escalation_rules: Vec<EscalationRule>,
+}
+
+impl SandboxProfiles {
+    pub fn new(default: SandboxProfile) -> Self {
+        Self {
+            default,
+            roles: HashMap::new(),
+            escalation_rules: Vec::new(),
+        }
+    }
+
+    /// Grant escalations the rule covers without asking the client
+    pub fn with_escalation_rule(mut self, rule: EscalationRule) -> Self {
+        self.escalation_rules.push(rule);
+        self
+    }
+
+    /// Give agents with a role their own profile
+    pub fn with_role(mut self, role: impl Into<String>, profile: SandboxProfile) -> Self {
+        self.roles.insert(role.into(), profile);
+        self
+    }
+
+    /// Profile of an agent with `role`
+    pub fn profile_for(&self, role: &AgentRole) -> &SandboxProfile {
+        by_role(&self.roles, role).unwrap_or(&self.default)
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        std::iter::once(&self.default)
+            .chain(self.roles.values())
+            .try_for_each(SandboxProfile::validate)?;
+        for rule in &self.escalation_rules {
+            if let Some(root) = rule.limit.writable_roots.iter().find(|r| normalize_path(r).is_none()) {
+                return Err(GoblinError::ConfigError(format!(
+                    "Escalation rule {} limit root must be absolute: {}",
+                    rule.name,
+                    root.display()
+                )));
+            }
+            if Duration::from_secs(rule.ttl_secs) > MAX_EXCEPTION_TTL {
+                return Err(GoblinError::ConfigError(format!(
+                    "Escalation rule {} TTL must be at most {}s",
+                    rule.name,
+                    MAX_EXCEPTION_TTL.as_secs()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Access an agent asks for beyond its profile
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxGrant {
+    /// Filesystem access, if more than the profile's
+    #[serde(default)]
+    pub level: Option<SandboxLevel>,
+    /// Directories to write besides those the profile allows
+    #[serde(default)]
+    pub writable_roots: Vec<PathBuf>,
+    #[serde(default)]
+    pub network: bool,
+}
+
+impl SandboxGrant {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_level(mut self, level: SandboxLevel) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    pub fn with_writable_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.writable_roots.push(root.into());
+        self
+    }
+
+    pub fn with_network(mut self) -> Self {
+        self.network = true;
+        self
+    }
+
+    /// Whether the grant asks for nothing `limit` does not cover
+    ///
+    /// Roots asked for must be absolute and free of `..`, so a path cannot
+    /// climb out of the limit's roots after matching them.
+    pub fn within(&self, limit: &SandboxGrant) -> bool {
+        let limits: Vec<PathBuf> = limit.writable_roots.iter().filter_map(|l| normalize_path(l)).collect();
+        self.level <= limit.level
+            && (!self.network || limit.network)
+            && self.writable_roots.iter().all(|root| {
+                !root.components().any(|c| c == Component::ParentDir)
+                    && normalize_path(root).is_some_and(|root| limits.iter().any(|l| root.starts_with(l)))
+            })
+    }
+}
+
+/// A request for access beyond an agent's sandbox profile
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxEscalation {
+    pub call_id: CallId,
+    pub agent_id: AgentId,
+    pub role: AgentRole,
+    pub needs: SandboxGrant,
+    /// Why the agent needs it, typically the blocked tool call
+    pub reason: String,
+    pub requested_at: DateTime<Utc>,
+}
+
+/// Access granted to an agent beyond its profile, until it expires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SandboxException {
+    pub call_id: CallId,
+    pub grant: SandboxGrant,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SandboxException {
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// A rule granting the escalations within its limit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscalationRule {
+    /// Name reported with the grants the rule makes
+    pub name: String,
+    /// Most the rule grants
+    pub limit: SandboxGrant,
+    /// Roles of the requesting agent; any role if empty
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Seconds the granted exception lasts
+    pub ttl_secs: u64,
+}
+
+impl EscalationRule {
+    pub fn new(name: impl Into<String>, limit: SandboxGrant, ttl: Duration) -> Self {
+        Self {
+            name: name.into(),
+            limit,
+            roles: Vec::new(),
+            ttl_secs: ttl.as_secs(),
+        }
+    }
+
+    /// Grant only to agents with one of these roles
+    pub fn with_roles<S: Into<String>>(mut self, roles: impl IntoIterator<Item = S>) -> Self {
+        self.roles.extend(roles.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn matches(&self, request: &SandboxEscalation) -> bool {
+        if !request.needs.within(&self.limit) {
+            return false;
+        }
+        if self.roles.is_empty() {
+            return true;
+        }
+        let keys = role_keys(&request.role);
+        self.roles.iter().any(|r| keys.contains(r))
+    }
+}
+
+#[derive(Debug)]
+struct PendingEscalation {
+    request: SandboxEscalation,
+    sub_id: SubmissionId,
+    /// Expiry of the exception if granted
+    answer: oneshot::Sender<Option<DateTime<Utc>>>,
+}
+
+/// Sandbox escalation requests of a session's agents
+#[derive(Debug)]
+pub struct SandboxEscalations {
+    rules: Vec<EscalationRule>,
+    /// Requests waiting for the client, by call
+    pending: Mutex<HashMap<CallId, PendingEscalation>>,
+    event_tx: mpsc::UnboundedSender<GoblinEvent>,
+    /// Where decisions are recorded
+    audit: Option<Arc<AuditLog>>,
+}
+
+impl SandboxEscalations {
+    pub fn new(rules: Vec<EscalationRule>, event_tx: mpsc::UnboundedSender<GoblinEvent>) -> Self {
+        Self {
+            rules,
+            pending: Mutex::new(HashMap::new()),
+            event_tx,
+            audit: None,
+        }
+    }
+
+    /// Record every decision in an audit log
+    pub fn with_audit(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// Decide a request, waiting for the client if no rule grants it
+    ///
+    /// Returns the exception granted, if any. A request whose answer can
+    /// never arrive, because the session went away, is refused.
+    pub async fn request(&self, request: SandboxEscalation, sub_id: &SubmissionId) -> Option<SandboxException> {
+        let call_id = request.call_id.clone();
+        let grant = request.needs.clone();
+        if let Some(rule) = self.rules.iter().find(|r| r.matches(&request)) {
+            let expires_at = expiry(Duration::from_secs(rule.ttl_secs));
+            self.emit_decided(sub_id.clone(), &request, Some(expires_at), Some(rule.name.clone()));
+            return Some(SandboxException { call_id, grant, expires_at });
+        }
+
+        let (answer, answered) = oneshot::channel();
+        self.pending.lock().insert(
+            call_id.clone(),
+            PendingEscalation {
+                request: request.clone(),
+                sub_id: sub_id.clone(),
+                answer,
+            },
+        );
+        let _ = self.event_tx.send(GoblinEvent::SandboxEscalationRequested {
+            sub_id: sub_id.clone(),
+            request,
+        });
+        let expires_at = answered.await.ok().flatten()?;
+        Some(SandboxException { call_id, grant, expires_at })
+    }
+
+    /// Answer a pending request, granting it for `ttl` or the default, at
+    /// most [`MAX_EXCEPTION_TTL`]
+    ///
+    /// Returns false if no request with this call ID is pending.
+    pub fn resolve(&self, call_id: &CallId, granted: bool, ttl: Option<Duration>) -> bool {
+        let Some(pending) = self.pending.lock().remove(call_id) else {
+            return false;
+        };
+        let expires_at = granted.then(|| expiry(ttl.unwrap_or(DEFAULT_EXCEPTION_TTL)));
+        self.emit_decided(pending.sub_id, &pending.request, expires_at, None);
+        let _ = pending.answer.send(expires_at);
+        true
+    }
+
+    fn emit_decided(
+        &self,
+        sub_id: SubmissionId,
+        request: &SandboxEscalation,
+        expires_at: Option<DateTime<Utc>>,
+        rule: Option<String>,
+    ) {
+        if let Some(audit) = &self.audit {
+            audit.record(request.agent_id, AuditRecord::SandboxEscalation {
+                call_id: request.call_id.clone(),
+                needs: request.needs.clone(),
+                granted: expires_at.is_some(),
+                rule: rule.clone(),
+                expires_at,
+            });
+        }
+        let _ = self.event_tx.send(GoblinEvent::SandboxEscalationDecided {
+            sub_id,
+            call_id: request.call_id.clone(),
+            agent_id: request.agent_id,
+            granted: expires_at.is_some(),
+            rule,
+            expires_at,
+        });
+    }
+
+    /// Whether a call is waiting for the client
+    pub fn is_pending(&self, call_id: &CallId) -> bool {
+        self.pending.lock().contains_key(call_id)
+    }
+}
+
+/// When an exception granted now for `ttl` expires
+fn expiry(ttl: Duration) -> DateTime<Utc> {
+    let now = Utc::now();
+    chrono::Duration::from_std(ttl.min(MAX_EXCEPTION_TTL))
+        .ok()
+        .and_then(|ttl| now.checked_add_signed(ttl))
+        .unwrap_or(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles_are_picked_by_role() {
+        let reviewer = SandboxProfile::new().with_level(SandboxLevel::ReadOnly);
+        let builder = SandboxProfile::new().with_network(true).with_writable_root("/cache");
+        let profiles = SandboxProfiles::default()
+            .with_role("specialist:reviewer", reviewer.clone())
+            .with_role("worker", builder.clone());
+
+        let specialty = |s: &str| AgentRole::Specialist { specialty: s.into() };
+        assert_eq!(profiles.profile_for(&specialty("reviewer")), &reviewer);
+        assert_eq!(profiles.profile_for(&AgentRole::Worker), &builder);
+        assert_eq!(profiles.profile_for(&specialty("tester")), &SandboxProfile::default());
+        assert!(profiles.validate().is_ok());
+
+        let rule = EscalationRule::new("forever", SandboxGrant::new(), MAX_EXCEPTION_TTL * 2);
+        assert!(profiles.clone().with_escalation_rule(rule).validate().is_err());
+
+        let relative = profiles.with_role("orchestrator", SandboxProfile::new().with_writable_root("cache"));
+        assert!(relative.validate().is_err());
+    }
+
+    #[test]
+    fn test_grants_stay_under_the_limit_roots() {
+        let limit = SandboxGrant::new().with_writable_root("/cache");
+        let asks = |root: &str| SandboxGrant::new().with_writable_root(root).within(&limit);
+
+        assert!(asks("/cache/cargo"));
+        assert!(asks("/cache/./cargo"));
+        assert!(!asks("/cache/../etc"));
+        assert!(!asks("/cache/cargo/../../etc"));
+        assert!(!asks("cache/cargo"));
+        assert!(!asks("/cachet"));
+    }
+
+    #[test]
+    fn test_exception_ttl_is_capped() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let escalations = SandboxEscalations::new(Vec::new(), tx);
+        let call_id = CallId::new();
+        let (answer, _answered) = oneshot::channel();
+        escalations.pending.lock().insert(
+            call_id.clone(),
+            PendingEscalation {
+                request: SandboxEscalation {
+                    call_id: call_id.clone(),
+                    agent_id: AgentId::new(),
+                    role: AgentRole::Worker,
+                    needs: SandboxGrant::new().with_network(),
+                    reason: "fetch".into(),
+                    requested_at: Utc::now(),
+                },
+                sub_id: SubmissionId::new(),
+                answer,
+            },
+        );
+
+        assert!(escalations.resolve(&call_id, true, Some(Duration::from_secs(u64::MAX))));
+        let expires_at = expiry(Duration::MAX);
+        assert!(expires_at <= Utc::now() + chrono::Duration::from_std(MAX_EXCEPTION_TTL).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_granted_escalation_widens_the_profile() {
+        use crate::agent::Agent;
+        use trinkets::ToolRegistry;
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let rule = EscalationRule::new("cache", SandboxGrant::new().with_writable_root("/cache"), Duration::from_secs(60));
+        let escalations = Arc::new(SandboxEscalations::new(vec![rule], tx));
+        let agent = Arc::new(
+            Agent::new(Default::default(), None, Arc::new(ToolRegistry::new()), mpsc::unbounded_channel().0)
+                .with_sandbox_profile(SandboxProfile::new().with_level(SandboxLevel::WorkspaceWrite))
+                .with_escalations(Arc::clone(&escalations)),
+        );
+        let sub_id = SubmissionId::new();
+
+        // Within the rule's limit: granted at once
+        let needs = SandboxGrant::new().with_writable_root("/cache/cargo");
+        assert!(agent.request_sandbox_escalation(needs, "cargo build", &sub_id).await.unwrap());
+        assert_eq!(agent.sandbox_profile().writable_roots, vec![PathBuf::from("/cache/cargo")]);
+        assert!(matches!(rx.recv().await, Some(GoblinEvent::SandboxEscalationDecided { granted: true, rule: Some(_), .. })));
+
+        // Beyond it: the client decides
+        let asking = tokio::spawn({
+            let agent = Arc::clone(&agent);
+            let sub_id = sub_id.clone();
+            async move { agent.request_sandbox_escalation(SandboxGrant::new().with_network(), "fetch crates", &sub_id).await }
+        });
+        let call_id = match rx.recv().await {
+            Some(GoblinEvent::SandboxEscalationRequested { request, .. }) => request.call_id,
+            other => panic!("unexpected event: {:?}", other),
+        };
+        assert!(escalations.resolve(&call_id, true, Some(Duration::from_secs(60))));
+        assert!(asking.await.unwrap().unwrap());
+        assert!(agent.sandbox_profile().network);
+        assert_eq!(agent.sandbox(), SandboxLevel::WorkspaceWrite);
+    }
+}
@@ -0,0 +1,423 @@
+//! Recurring task submissions
+//!
+//! Long-lived orchestrators can re-run maintenance tasks (nightly triage,
+//! dependency bumps, ...) on a schedule instead of relying on an external
+//! scheduler. A schedule is either a fixed interval (`30s`, `15m`, `6h`,
+//! `1d`) or a five-field cron expression (`0 3 * * 1-5`) evaluated in UTC.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use warhorn::TaskContext;
+
+use crate::error::GoblinError;
+
+/// Unique identifier of a schedule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ScheduleId(Uuid);
+
+impl ScheduleId {
+    /// Create a new random schedule ID
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for ScheduleId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ScheduleId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// When a scheduled task runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleSpec {
+    /// Every fixed interval
+    Interval(Duration),
+    /// Whenever the cron expression matches
+    Cron(CronExpr),
+}
+
+impl ScheduleSpec {
+    /// Next run strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Interval(interval) => {
+                let step = ChronoDuration::from_std(*interval).ok()?;
+                after.checked_add_signed(step)
+            }
+            Self::Cron(cron) => cron.next_after(after),
+        }
+    }
+}
+
+impl FromStr for ScheduleSpec {
+    type Err = GoblinError;
+
+    /// Parse an interval (`90s`, `15m`, `2h`, `1d`) or a cron expression
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.split_whitespace().count() == 5 {
+            return Ok(Self::Cron(s.parse()?));
+        }
+
+        let invalid = || GoblinError::ConfigError(format!("Invalid schedule: '{}'", s));
+        let split = s.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+        let (value, unit) = s.split_at(split);
+        let value: u64 = value.parse().map_err(|_| invalid())?;
+        let unit_secs = match unit.trim() {
+            "s" | "sec" | "secs" => 1,
+            "m" | "min" | "mins" => 60,
+            "h" | "hr" | "hrs" => 3600,
+            "d" | "day" | "days" => 86_400,
+            _ => return Err(invalid()),
+        };
+        let secs = value
+            .checked_mul(unit_secs)
+            .ok_or_else(|| GoblinError::ConfigError(format!("Schedule interval '{}' is too long", s)))?;
+        if secs == 0 {
+            return Err(invalid());
+        }
+        Ok(Self::Interval(Duration::from_secs(secs)))
+    }
+}
+
+/// A parsed five-field cron expression (minute hour day-of-month month day-of-week)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CronExpr {
+    source: String,
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days: Vec<bool>,
+    months: Vec<bool>,
+    weekdays: Vec<bool>,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronExpr {
+    /// The original expression
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    fn day_matches(&self, t: &DateTime<Utc>) -> bool {
+        let dom = self.days[t.day() as usize];
+        let dow = self.weekdays[t.weekday().num_days_from_sunday() as usize];
+        // Standard cron: when both fields are restricted, either may match
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+
+    /// Next matching minute strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut t = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        // Four years covers every valid combination, including Feb 29
+        let limit = after + ChronoDuration::days(4 * 366);
+
+        while t <= limit {
+            if !self.months[t.month() as usize] {
+                let (year, month) = if t.month() == 12 { (t.year() + 1, 1) } else { (t.year(), t.month() + 1) };
+                t = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+                continue;
+            }
+            if !self.day_matches(&t) {
+                t = (t + ChronoDuration::days(1)).with_hour(0)?.with_minute(0)?;
+                continue;
+            }
+            if !self.hours[t.hour() as usize] {
+                t = (t + ChronoDuration::hours(1)).with_minute(0)?;
+                continue;
+            }
+            if !self.minutes[t.minute() as usize] {
+                t += ChronoDuration::minutes(1);
+                continue;
+            }
+            return Some(t);
+        }
+
+        None
+    }
+}
+
+impl FromStr for CronExpr {
+    type Err = GoblinError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(GoblinError::ConfigError(format!(
+                "Cron expression needs 5 fields: '{}'",
+                s
+            )));
+        }
+
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        // Both 0 and 7 mean Sunday
+        if weekdays[7] {
+            weekdays[0] = true;
+        }
+
+        Ok(Self {
+            source: s.trim().to_string(),
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            days_restricted: fields[2] != "*",
+            weekdays_restricted: fields[4] != "*",
+        })
+    }
+}
+
+/// Parse one cron field into a lookup table indexed by value
+fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<bool>, GoblinError> {
+    let invalid = || GoblinError::ConfigError(format!("Invalid cron field: '{}'", field));
+    let mut table = vec![false; max as usize + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((a, b)) = range.split_once('-') {
+            (a.parse().map_err(|_| invalid())?, b.parse().map_err(|_| invalid())?)
+        } else {
+            let v: u32 = range.parse().map_err(|_| invalid())?;
+            // `5/10` means "from 5, every 10"
+            (v, if part.contains('/') { max } else { v })
+        };
+
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for v in (start..=end).step_by(step as usize) {
+            table[v as usize] = true;
+        }
+    }
+
+    Ok(table)
+}
+
+/// A recurring task submission
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledTask {
+    /// Schedule ID
+    pub id: ScheduleId,
+    /// When the task runs
+    pub spec: ScheduleSpec,
+    /// Prompt submitted on every run
+    pub prompt: String,
+    /// Context submitted on every run
+    pub context: TaskContext,
+    /// Next planned run
+    pub next_run: DateTime<Utc>,
+    /// Number of completed submissions
+    pub runs: u64,
+}
+
+/// Client-facing summary of a schedule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleInfo {
+    /// Schedule ID
+    pub id: ScheduleId,
+    /// Prompt submitted on every run
+    pub prompt: String,
+    /// Next planned run
+    pub next_run: DateTime<Utc>,
+    /// Number of completed submissions
+    pub runs: u64,
+}
+
+impl From<&ScheduledTask> for ScheduleInfo {
+    fn from(task: &ScheduledTask) -> Self {
+        Self {
+            id: task.id,
+            prompt: task.prompt.clone(),
+            next_run: task.next_run,
+            runs: task.runs,
+        }
+    }
+}
+
+/// Registry of recurring tasks
+#[derive(Debug, Default)]
+pub struct ScheduleRegistry {
+    tasks: HashMap<ScheduleId, ScheduledTask>,
+}
+
+impl ScheduleRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a schedule, computing its first run from `now`
+    pub fn add(
+        &mut self,
+        spec: ScheduleSpec,
+        prompt: String,
+        context: TaskContext,
+        now: DateTime<Utc>,
+    ) -> Result<ScheduleInfo, GoblinError> {
+        let next_run = spec.next_after(now).ok_or_else(|| {
+            GoblinError::ConfigError("Schedule never fires".into())
+        })?;
+        let task = ScheduledTask {
+            id: ScheduleId::new(),
+            spec,
+            prompt,
+            context,
+            next_run,
+            runs: 0,
+        };
+        let info = ScheduleInfo::from(&task);
+        self.tasks.insert(task.id, task);
+        Ok(info)
+    }
+
+    /// Remove a schedule
+    pub fn cancel(&mut self, id: &ScheduleId) -> bool {
+        self.tasks.remove(id).is_some()
+    }
+
+    /// Summaries of all schedules, soonest first
+    pub fn list(&self) -> Vec<ScheduleInfo> {
+        let mut list: Vec<ScheduleInfo> = self.tasks.values().map(ScheduleInfo::from).collect();
+        list.sort_by_key(|s| s.next_run);
+        list
+    }
+
+    /// Earliest upcoming run
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.tasks.values().map(|t| t.next_run).min()
+    }
+
+    /// Take the submissions that are due and advance their schedules
+    ///
+    /// Runs missed while the orchestrator was busy are coalesced into one.
+    pub fn take_due(&mut self, now: DateTime<Utc>) -> Vec<(String, TaskContext)> {
+        let mut due = Vec::new();
+        let mut finished = Vec::new();
+
+        for task in self.tasks.values_mut().filter(|t| t.next_run <= now) {
+            due.push((task.prompt.clone(), task.context.clone()));
+            task.runs += 1;
+            match task.spec.next_after(now) {
+                Some(next) => task.next_run = next,
+                None => finished.push(task.id),
+            }
+        }
+        for id in finished {
+            self.tasks.remove(&id);
+        }
+
+        due
+    }
+
+    /// Number of schedules
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Check if there are no schedules
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_interval() {
+        assert_eq!("30s".parse::<ScheduleSpec>().unwrap(), ScheduleSpec::Interval(Duration::from_secs(30)));
+        assert_eq!("15m".parse::<ScheduleSpec>().unwrap(), ScheduleSpec::Interval(Duration::from_secs(900)));
+        assert_eq!("1d".parse::<ScheduleSpec>().unwrap(), ScheduleSpec::Interval(Duration::from_secs(86_400)));
+        assert!("0m".parse::<ScheduleSpec>().is_err());
+        assert!("soon".parse::<ScheduleSpec>().is_err());
+        assert!(matches!(
+            "999999999999999999d".parse::<ScheduleSpec>(),
+            Err(GoblinError::ConfigError(_))
+        ));
+        let far = ScheduleSpec::Interval(Duration::from_secs(1_000_000_000_000_000));
+        assert_eq!(far.next_after(at(2026, 1, 1, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_cron_nightly() {
+        let spec: ScheduleSpec = "0 3 * * *".parse().unwrap();
+        assert_eq!(spec.next_after(at(2026, 1, 1, 12, 0)), Some(at(2026, 1, 2, 3, 0)));
+        assert_eq!(spec.next_after(at(2026, 1, 1, 2, 59)), Some(at(2026, 1, 1, 3, 0)));
+    }
+
+    #[test]
+    fn test_cron_steps_ranges_and_weekdays() {
+        let every_15: CronExpr = "*/15 * * * *".parse().unwrap();
+        assert_eq!(every_15.next_after(at(2026, 1, 1, 10, 7)), Some(at(2026, 1, 1, 10, 15)));
+
+        // 2026-01-03 is a Saturday; weekdays only skips to Monday the 5th
+        let weekdays: CronExpr = "30 9 * * 1-5".parse().unwrap();
+        assert_eq!(weekdays.next_after(at(2026, 1, 3, 0, 0)), Some(at(2026, 1, 5, 9, 30)));
+
+        let month_end: CronExpr = "0 0 1 */3 *".parse().unwrap();
+        assert_eq!(month_end.next_after(at(2026, 2, 10, 0, 0)), Some(at(2026, 4, 1, 0, 0)));
+    }
+
+    #[test]
+    fn test_cron_invalid() {
+        assert!("60 * * * *".parse::<CronExpr>().is_err());
+        assert!("* * * *".parse::<CronExpr>().is_err());
+        assert!("*/0 * * * *".parse::<CronExpr>().is_err());
+        assert!("5-1 * * * *".parse::<CronExpr>().is_err());
+    }
+
+    #[test]
+    fn test_registry_take_due() {
+        let mut registry = ScheduleRegistry::new();
+        let now = at(2026, 1, 1, 0, 0);
+        let info = registry
+            .add("10m".parse().unwrap(), "triage".into(), TaskContext::default(), now)
+            .unwrap();
+        assert_eq!(info.next_run, at(2026, 1, 1, 0, 10));
+
+        assert!(registry.take_due(at(2026, 1, 1, 0, 5)).is_empty());
+        let due = registry.take_due(at(2026, 1, 1, 0, 10));
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].0, "triage");
+
+        let listed = registry.list();
+        assert_eq!(listed[0].runs, 1);
+        assert_eq!(listed[0].next_run, at(2026, 1, 1, 0, 20));
+
+        assert!(registry.cancel(&info.id));
+        assert!(registry.next_due().is_none());
+    }
+}
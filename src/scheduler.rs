@@ -0,0 +1,255 @@
+//! Priority scheduling of queued subtasks
+//!
+//! Subtasks waiting for a worker sit in a priority queue. Higher priorities
+//! are dispatched first and equal priorities keep submission order. When the
+//! queue is bounded and full, a higher-priority submission preempts the
+//! lowest-priority queued entry, which waits outside the queue and is
+//! requeued as soon as a slot frees; work that is already running is never
+//! preempted.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use serde::{Deserialize, Serialize};
+use warhorn::TaskId;
+
+/// Scheduling priority of a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+pub enum Priority {
+    /// Background work
+    Low,
+    /// Default priority
+    #[default]
+    Normal,
+    /// Dispatched ahead of normal work
+    High,
+    /// Dispatched before everything else
+    Critical,
+}
+
+/// A subtask waiting for a worker
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct QueuedTask {
+    task_id: TaskId,
+    priority: Priority,
+    seq: u64,
+}
+
+impl Ord for QueuedTask {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Max-heap: higher priority first, then lower sequence (older) first
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for QueuedTask {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Outcome of queueing a task
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Enqueued {
+    /// The task was queued
+    Queued,
+    /// The task was queued and displaced a lower-priority task, which is
+    /// requeued once the queue has room
+    Preempted(TaskId),
+    /// The queue is full of work at the same or higher priority
+    Rejected,
+}
+
+/// Priority queue of subtasks awaiting dispatch
+#[derive(Debug, Default)]
+pub struct TaskScheduler {
+    queue: BinaryHeap<QueuedTask>,
+    /// Preempted tasks waiting for room in the queue
+    displaced: Vec<QueuedTask>,
+    next_seq: u64,
+    capacity: Option<usize>,
+}
+
+impl TaskScheduler {
+    /// Create an unbounded scheduler
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a scheduler holding at most `capacity` queued tasks
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity: Some(capacity),
+            ..Self::default()
+        }
+    }
+
+    /// Queue a task for dispatch
+    pub fn enqueue(&mut self, task_id: TaskId, priority: Priority) -> Enqueued {
+        let mut outcome = Enqueued::Queued;
+
+        if self.capacity.is_some_and(|cap| self.queue.len() >= cap) {
+            match self.lowest() {
+                Some(lowest) if lowest.priority < priority => {
+                    let victim = lowest.clone();
+                    self.queue.retain(|t| t.task_id != victim.task_id);
+                    outcome = Enqueued::Preempted(victim.task_id);
+                    self.displaced.push(victim);
+                }
+                _ => return Enqueued::Rejected,
+            }
+        }
+
+        self.queue.push(QueuedTask {
+            task_id,
+            priority,
+            seq: self.next_seq,
+        });
+        self.next_seq += 1;
+        outcome
+    }
+
+    /// Take the highest-priority task
+    pub fn next(&mut self) -> Option<(TaskId, Priority)> {
+        let next = self.queue.pop();
+        self.requeue_displaced();
+        next.map(|t| (t.task_id, t.priority))
+    }
+
+    /// Peek at the highest-priority task
+    pub fn peek(&self) -> Option<(TaskId, Priority)> {
+        self.queue.peek().map(|t| (t.task_id, t.priority))
+    }
+
+    /// Remove a queued task
+    pub fn remove(&mut self, task_id: &TaskId) -> bool {
+        let before = self.len();
+        self.queue.retain(|t| t.task_id != *task_id);
+        self.displaced.retain(|t| t.task_id != *task_id);
+        self.requeue_displaced();
+        self.len() != before
+    }
+
+    /// Change the priority of a queued task, keeping its place among equals
+    pub fn reprioritize(&mut self, task_id: &TaskId, priority: Priority) -> bool {
+        let mut entries = std::mem::take(&mut self.queue).into_vec();
+        let entry = entries.iter_mut().find(|t| t.task_id == *task_id);
+        let found = entry.is_some();
+        if let Some(entry) = entry {
+            entry.priority = priority;
+        }
+        self.queue = entries.into();
+        if let Some(entry) = self.displaced.iter_mut().find(|t| t.task_id == *task_id) {
+            entry.priority = priority;
+            return true;
+        }
+        found
+    }
+
+    /// Queued task IDs in dispatch order, preempted tasks last
+    pub fn queued(&self) -> Vec<(TaskId, Priority)> {
+        let mut entries = self.queue.clone().into_sorted_vec();
+        entries.reverse();
+        let mut displaced = self.displaced.clone();
+        displaced.sort_by(|a, b| b.cmp(a));
+        entries.into_iter().chain(displaced).map(|t| (t.task_id, t.priority)).collect()
+    }
+
+    /// Get queue length, counting preempted tasks
+    pub fn len(&self) -> usize {
+        self.queue.len() + self.displaced.len()
+    }
+
+    /// Check if nothing is queued
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty() && self.displaced.is_empty()
+    }
+
+    fn lowest(&self) -> Option<&QueuedTask> {
+        self.queue.iter().min()
+    }
+
+    /// Move preempted tasks back into the queue while it has room
+    fn requeue_displaced(&mut self) {
+        while !self.displaced.is_empty() && !self.capacity.is_some_and(|cap| self.queue.len() >= cap) {
+            let index = (0..self.displaced.len()).max_by_key(|&i| &self.displaced[i]).unwrap_or(0);
+            self.queue.push(self.displaced.swap_remove(index));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_order() {
+        let mut scheduler = TaskScheduler::new();
+        let low = TaskId::new();
+        let high = TaskId::new();
+        let normal = TaskId::new();
+
+        scheduler.enqueue(low, Priority::Low);
+        scheduler.enqueue(high, Priority::High);
+        scheduler.enqueue(normal, Priority::Normal);
+
+        assert_eq!(scheduler.next(), Some((high, Priority::High)));
+        assert_eq!(scheduler.next(), Some((normal, Priority::Normal)));
+        assert_eq!(scheduler.next(), Some((low, Priority::Low)));
+        assert!(scheduler.next().is_none());
+    }
+
+    #[test]
+    fn test_fifo_within_priority() {
+        let mut scheduler = TaskScheduler::new();
+        let first = TaskId::new();
+        let second = TaskId::new();
+
+        scheduler.enqueue(first, Priority::Normal);
+        scheduler.enqueue(second, Priority::Normal);
+
+        assert_eq!(scheduler.next().unwrap().0, first);
+        assert_eq!(scheduler.next().unwrap().0, second);
+    }
+
+    #[test]
+    fn test_preempts_lowest_when_full() {
+        let mut scheduler = TaskScheduler::with_capacity(2);
+        let low = TaskId::new();
+        let normal = TaskId::new();
+        let high = TaskId::new();
+
+        assert_eq!(scheduler.enqueue(low, Priority::Low), Enqueued::Queued);
+        assert_eq!(scheduler.enqueue(normal, Priority::Normal), Enqueued::Queued);
+        assert_eq!(scheduler.enqueue(high, Priority::High), Enqueued::Preempted(low));
+        assert_eq!(scheduler.enqueue(TaskId::new(), Priority::Normal), Enqueued::Rejected);
+
+        let queued: Vec<_> = scheduler.queued().into_iter().map(|(id, _)| id).collect();
+        assert_eq!(queued, vec![high, normal, low]);
+
+        // The preempted task is requeued once a slot frees
+        assert_eq!(scheduler.next().unwrap().0, high);
+        assert_eq!(scheduler.enqueue(TaskId::new(), Priority::Low), Enqueued::Rejected);
+        assert_eq!(scheduler.next().unwrap().0, normal);
+        assert_eq!(scheduler.next().unwrap().0, low);
+        assert!(scheduler.is_empty());
+    }
+
+    #[test]
+    fn test_remove_and_reprioritize() {
+        let mut scheduler = TaskScheduler::new();
+        let a = TaskId::new();
+        let b = TaskId::new();
+        scheduler.enqueue(a, Priority::Low);
+        scheduler.enqueue(b, Priority::Normal);
+
+        assert!(scheduler.reprioritize(&a, Priority::Critical));
+        assert_eq!(scheduler.peek(), Some((a, Priority::Critical)));
+
+        assert!(scheduler.remove(&a));
+        assert!(!scheduler.remove(&a));
+        assert_eq!(scheduler.len(), 1);
+    }
+}
@@ -0,0 +1,220 @@
+//! Structured concurrency for agent run loops
+//!
+//! Every agent's run loop is a tokio task owned by the [`AgentScope`] of
+//! its parent (the session owns the scope of root agents). Terminating an
+//! agent cancels the run loops of its whole subtree, and the session awaits
+//! the cancelled tasks so none of them outlive the agents they belong to.
+//! Dropping a scope aborts everything it still owns.
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio::task::{JoinError, JoinHandle};
+use warhorn::{AgentId, SubmissionId};
+
+use crate::error::GoblinError;
+use crate::protocol::GoblinEvent;
+
+/// How an agent's run loop ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JoinOutcome {
+    /// The run loop returned successfully
+    Completed,
+    /// The run loop returned an error
+    Failed { error: String },
+    /// The run loop was cancelled because the agent or an ancestor was terminated
+    Cancelled,
+    /// The run loop panicked
+    Panicked,
+}
+
+impl JoinOutcome {
+    fn of(result: &Result<(), GoblinError>) -> Self {
+        match result {
+            Ok(()) => Self::Completed,
+            Err(e) => Self::Failed { error: e.to_string() },
+        }
+    }
+
+    fn of_join_error(error: &JoinError) -> Self {
+        if error.is_panic() {
+            Self::Panicked
+        } else {
+            Self::Cancelled
+        }
+    }
+}
+
+/// A run loop owned by a scope
+#[derive(Debug)]
+pub struct RunLoop {
+    /// Agent the loop belongs to
+    pub agent_id: AgentId,
+    sub_id: SubmissionId,
+    handle: JoinHandle<()>,
+}
+
+impl RunLoop {
+    /// Wait for the loop to finish, reporting cancellation or a panic
+    ///
+    /// Loops that returned on their own already reported their outcome.
+    pub async fn join(self, event_tx: &mpsc::UnboundedSender<GoblinEvent>) -> Option<JoinOutcome> {
+        let error = self.handle.await.err()?;
+        let outcome = JoinOutcome::of_join_error(&error);
+        let _ = event_tx.send(GoblinEvent::AgentJoined {
+            sub_id: self.sub_id,
+            agent_id: self.agent_id,
+            outcome: outcome.clone(),
+        });
+        Some(outcome)
+    }
+}
+
+/// Run loops of an agent's children
+#[derive(Debug, Default)]
+pub struct AgentScope {
+    loops: Mutex<HashMap<AgentId, RunLoop>>,
+}
+
+impl AgentScope {
+    /// Create an empty scope
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn a child's run loop in this scope
+    ///
+    /// When the loop returns, its outcome is reported as an `AgentJoined`
+    /// event.
+    pub fn spawn<F>(
+        &self,
+        agent_id: AgentId,
+        sub_id: &SubmissionId,
+        event_tx: mpsc::UnboundedSender<GoblinEvent>,
+        run: F,
+    ) -> Result<(), GoblinError>
+    where
+        F: Future<Output = Result<(), GoblinError>> + Send + 'static,
+    {
+        let mut loops = self.loops.lock();
+        if loops.get(&agent_id).is_some_and(|l| !l.handle.is_finished()) {
+            return Err(GoblinError::InvalidState(format!(
+                "Agent {} is already running",
+                agent_id
+            )));
+        }
+
+        let event_sub_id = sub_id.clone();
+        let handle = tokio::spawn(async move {
+            let result = run.await;
+            let _ = event_tx.send(GoblinEvent::AgentJoined {
+                sub_id: event_sub_id,
+                agent_id,
+                outcome: JoinOutcome::of(&result),
+            });
+        });
+
+        loops.insert(
+            agent_id,
+            RunLoop {
+                agent_id,
+                sub_id: sub_id.clone(),
+                handle,
+            },
+        );
+        Ok(())
+    }
+
+    /// Cancel a child's run loop, returning it to be awaited
+    pub fn cancel(&self, agent_id: &AgentId) -> Option<RunLoop> {
+        let run = self.loops.lock().remove(agent_id)?;
+        run.handle.abort();
+        Some(run)
+    }
+
+    /// Take a child's run loop out of the scope without cancelling it
+    pub(crate) fn detach(&self, agent_id: &AgentId) -> Option<RunLoop> {
+        self.loops.lock().remove(agent_id)
+    }
+
+    /// Take over a run loop detached from another scope
+    pub(crate) fn adopt(&self, run: RunLoop) {
+        self.loops.lock().insert(run.agent_id, run);
+    }
+
+    /// Cancel every run loop in the scope
+    pub fn cancel_all(&self) -> Vec<RunLoop> {
+        let loops: Vec<RunLoop> = self.loops.lock().drain().map(|(_, run)| run).collect();
+        for run in &loops {
+            run.handle.abort();
+        }
+        loops
+    }
+
+    /// Number of run loops still executing
+    pub fn running(&self) -> usize {
+        self.loops.lock().values().filter(|l| !l.handle.is_finished()).count()
+    }
+}
+
+impl Drop for AgentScope {
+    fn drop(&mut self) {
+        for run in self.loops.get_mut().values() {
+            run.handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_finished_loop_reports_outcome() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let scope = AgentScope::new();
+        let agent_id = AgentId::new();
+
+        scope
+            .spawn(agent_id, &SubmissionId::new(), tx.clone(), async {
+                Err(GoblinError::TaskError("boom".into()))
+            })
+            .unwrap();
+
+        match rx.recv().await {
+            Some(GoblinEvent::AgentJoined { outcome, .. }) => {
+                assert!(matches!(outcome, JoinOutcome::Failed { .. }))
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        // Already reported by the loop itself
+        assert_eq!(scope.cancel(&agent_id).unwrap().join(&tx).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_loop_is_awaited() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let scope = AgentScope::new();
+
+        scope
+            .spawn(AgentId::new(), &SubmissionId::new(), tx.clone(), async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(scope.running(), 1);
+
+        for run in scope.cancel_all() {
+            assert_eq!(run.join(&tx).await, Some(JoinOutcome::Cancelled));
+        }
+        assert_eq!(scope.running(), 0);
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(GoblinEvent::AgentJoined { outcome: JoinOutcome::Cancelled, .. })
+        ));
+    }
+}
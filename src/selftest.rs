@@ -0,0 +1,237 @@
+//! Startup self-test for sessions
+//!
+//! When enabled, the orchestrator runs a quick verification pass while
+//! configuring a session, before any planning starts, and reports the
+//! results as a [`GoblinEvent::SelfTestCompleted`](crate::protocol::GoblinEvent).
+//! A missing API key or tool then fails fast instead of surfacing halfway
+//! through a run across many workers.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use trinkets::ToolRegistry;
+use warhorn::SessionConfig;
+
+/// Self-test configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SelfTestConfig {
+    /// Tools that must be registered
+    pub required_tools: Vec<String>,
+    /// Environment variables that must be set (e.g. provider API keys)
+    pub required_env: Vec<String>,
+    /// Reject the session if any check fails
+    pub fail_fast: bool,
+}
+
+/// What a check verifies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckKind {
+    /// Tool registry
+    Tools,
+    /// Model provider access
+    Provider,
+    /// Sandbox and filesystem availability
+    Sandbox,
+    /// User-supplied check
+    Custom,
+}
+
+/// Outcome of a single check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CheckResult {
+    /// Check name
+    pub name: String,
+    /// What was checked
+    pub kind: CheckKind,
+    /// Whether the check passed
+    pub passed: bool,
+    /// Details, especially on failure
+    pub message: String,
+}
+
+impl CheckResult {
+    /// A passing result
+    pub fn pass(name: impl Into<String>, kind: CheckKind, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            passed: true,
+            message: message.into(),
+        }
+    }
+
+    /// A failing result
+    pub fn fail(name: impl Into<String>, kind: CheckKind, message: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            kind,
+            passed: false,
+            message: message.into(),
+        }
+    }
+}
+
+/// What checks get to look at
+pub struct SelfTestContext<'a> {
+    /// Shared tool registry
+    pub tools: &'a ToolRegistry,
+    /// Session being configured
+    pub config: &'a SessionConfig,
+    /// Self-test settings
+    pub settings: &'a SelfTestConfig,
+}
+
+/// A startup verification
+#[async_trait]
+pub trait SelfCheck: Send + Sync {
+    /// Run the check
+    async fn check(&self, ctx: &SelfTestContext<'_>) -> Vec<CheckResult>;
+}
+
+/// Verifies that every required tool is registered
+pub struct RequiredToolsCheck;
+
+#[async_trait]
+impl SelfCheck for RequiredToolsCheck {
+    async fn check(&self, ctx: &SelfTestContext<'_>) -> Vec<CheckResult> {
+        ctx.settings
+            .required_tools
+            .iter()
+            .map(|name| {
+                let check = format!("tool:{}", name);
+                if ctx.tools.get(name).is_some() {
+                    CheckResult::pass(check, CheckKind::Tools, "registered")
+                } else {
+                    CheckResult::fail(check, CheckKind::Tools, "tool is not registered")
+                }
+            })
+            .collect()
+    }
+}
+
+/// Verifies that provider credentials are present in the environment
+pub struct EnvCredentialsCheck;
+
+#[async_trait]
+impl SelfCheck for EnvCredentialsCheck {
+    async fn check(&self, ctx: &SelfTestContext<'_>) -> Vec<CheckResult> {
+        ctx.settings
+            .required_env
+            .iter()
+            .map(|var| {
+                let check = format!("env:{}", var);
+                match std::env::var(var) {
+                    Ok(value) if !value.trim().is_empty() => {
+                        CheckResult::pass(check, CheckKind::Provider, "set")
+                    }
+                    Ok(_) => CheckResult::fail(check, CheckKind::Provider, "set but empty"),
+                    Err(_) => CheckResult::fail(check, CheckKind::Provider, "not set"),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Verifies that the session working directory exists and is writable
+pub struct WorkdirCheck;
+
+#[async_trait]
+impl SelfCheck for WorkdirCheck {
+    async fn check(&self, ctx: &SelfTestContext<'_>) -> Vec<CheckResult> {
+        let cwd: PathBuf = match ctx.config.cwd.clone() {
+            Some(cwd) => cwd,
+            None => std::env::current_dir().unwrap_or_default(),
+        };
+
+        let shown = cwd.display().to_string();
+        let result = match std::fs::metadata(&cwd) {
+            Ok(meta) if !meta.is_dir() => {
+                CheckResult::fail("workdir", CheckKind::Sandbox, format!("{} is not a directory", shown))
+            }
+            Ok(meta) if meta.permissions().readonly() => {
+                CheckResult::fail("workdir", CheckKind::Sandbox, format!("{} is read-only", shown))
+            }
+            Ok(_) => CheckResult::pass("workdir", CheckKind::Sandbox, shown),
+            Err(e) => CheckResult::fail("workdir", CheckKind::Sandbox, format!("{}: {}", shown, e)),
+        };
+        vec![result]
+    }
+}
+
+/// The checks run by default
+pub fn default_checks() -> Vec<Arc<dyn SelfCheck>> {
+    vec![
+        Arc::new(RequiredToolsCheck),
+        Arc::new(EnvCredentialsCheck),
+        Arc::new(WorkdirCheck),
+    ]
+}
+
+/// Run every check and collect the results
+pub async fn run_checks(checks: &[Arc<dyn SelfCheck>], ctx: &SelfTestContext<'_>) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+    for check in checks {
+        results.extend(check.check(ctx).await);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> SelfTestConfig {
+        SelfTestConfig {
+            required_tools: vec!["definitely_not_registered".into()],
+            required_env: vec!["PATH".into(), "CABAL_SELFTEST_MISSING_KEY".into()],
+            fail_fast: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_checks() {
+        let tools = ToolRegistry::new();
+        let config = SessionConfig::default();
+        let settings = settings();
+        let ctx = SelfTestContext {
+            tools: &tools,
+            config: &config,
+            settings: &settings,
+        };
+
+        let results = run_checks(&default_checks(), &ctx).await;
+
+        let by_name = |name: &str| results.iter().find(|r| r.name == name).unwrap().passed;
+        assert!(!by_name("tool:definitely_not_registered"));
+        assert!(by_name("env:PATH"));
+        assert!(!by_name("env:CABAL_SELFTEST_MISSING_KEY"));
+        assert!(by_name("workdir"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_check() {
+        struct AlwaysFails;
+
+        #[async_trait]
+        impl SelfCheck for AlwaysFails {
+            async fn check(&self, _ctx: &SelfTestContext<'_>) -> Vec<CheckResult> {
+                vec![CheckResult::fail("custom", CheckKind::Custom, "nope")]
+            }
+        }
+
+        let tools = ToolRegistry::new();
+        let config = SessionConfig::default();
+        let settings = SelfTestConfig::default();
+        let ctx = SelfTestContext {
+            tools: &tools,
+            config: &config,
+            settings: &settings,
+        };
+
+        let checks: Vec<Arc<dyn SelfCheck>> = vec![Arc::new(AlwaysFails)];
+        let results = run_checks(&checks, &ctx).await;
+        assert_eq!(results, vec![CheckResult::fail("custom", CheckKind::Custom, "nope")]);
+    }
+}
@@ -2,26 +2,79 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
-use parking_lot::RwLock;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+use futures::FutureExt;
+use parking_lot::{Mutex, RwLock};
 use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 
 use warhorn::{
     AgentId, SessionId, TaskId, AgentConfig,
-    SessionConfig, Event, SubmissionId,
+    SessionConfig, Event, SubmissionId, TokenUsage,
 };
 use trinkets::ToolRegistry;
 
-use crate::agent::{Agent, AgentHandle};
-use crate::hierarchy::AgentHierarchy;
+use crate::actor;
+use crate::approval::Approvals;
+use crate::audit::AuditLog;
+use crate::breaker::CircuitBreakers;
+use crate::budget::BudgetLedger;
+use crate::sandbox::SandboxEscalations;
+use crate::agent::{Agent, AgentHandle, AgentOverrides, AgentSummary};
+use crate::artifact::{Artifact, ArtifactId, ArtifactStore, INLINE_ATTACHMENT_LIMIT};
+use crate::config::{SessionConfigPatch, SessionOptions};
+use crate::deadline::{Deadline, DeadlineAction, DeadlineTracker};
+use crate::store::SessionStore;
+use crate::tasks::{ActiveTask, TaskRegistry};
+use crate::metrics::{Counters, SessionMetrics};
+use crate::handoff::Handoff;
+use crate::bundle::{SessionBundle, BUNDLE_VERSION};
+use crate::checkpoint::{AgentCheckpoint, SessionCheckpoint, CHECKPOINT_VERSION};
+use crate::delegation::DelegationOutcome;
+use crate::mailbox::Mail;
+use crate::fairshare::{SlotPermit, TaskSlots};
+use crate::modelslots::ModelSlots;
+use crate::merger::{MergeContext, MergeRequest, ResultMerger};
+use crate::review::{Review, Reviewer};
+use crate::compaction::{Compactor, ModelSummarizer, Summarizer};
+use crate::history::HistoryEntry;
+use crate::model::{ChatRole, ModelProviders};
+use crate::runtime::{Runtimes, TaskAssignment};
+use crate::plan::{PlanStatus, TaskPlan};
+use crate::propagation::FailurePolicy;
+use crate::estimate::{CostEstimate, RoleHistory};
+use crate::ratelimit::TokenBucket;
+use crate::retry::TaskAttempt;
+use crate::failure::FailureReport;
+use crate::heartbeat::HeartbeatPolicy;
+use crate::orphan::OrphanPolicy;
+use crate::stall::StallAction;
+use crate::supervise::SupervisionPolicy;
+use crate::scheduler::{Enqueued, Priority, TaskScheduler};
+use crate::scope::{AgentScope, RunLoop};
+use crate::planner::{PlanRequest, Planner};
+use crate::result::{ResultStatus, TaskResult};
+use crate::workflow::{Workflow, WorkflowRun};
+use crate::coalesce::InFlightCalls;
+use crate::toolcache::ToolCache;
+use crate::toollimit::ToolLimits;
+use crate::mcp::{check_registry_names, McpClient, McpTools, StdioTransport};
+use crate::toolscope::{ToolScope, ToolView};
+use crate::workspace::ScratchDir;
+use crate::hierarchy::{AgentHierarchy, HierarchySnapshot, SubtreeSummary};
 use crate::error::GoblinError;
+use crate::protocol::GoblinEvent;
 
 /// A goblin orchestration session
 pub struct Session {
     /// Session ID
     pub id: SessionId,
     /// Session configuration
-    pub config: SessionConfig,
+    config: RwLock<SessionConfig>,
+    /// Cabal orchestration options
+    pub options: SessionOptions,
     /// All agents in this session
     agents: RwLock<HashMap<AgentId, AgentHandle>>,
     /// Agent hierarchy
@@ -29,9 +82,83 @@ pub struct Session {
     /// Shared tool registry
     tools: Arc<ToolRegistry>,
     /// Event sender
-    event_tx: mpsc::UnboundedSender<Event>,
-    /// Current active task
-    current_task: RwLock<Option<TaskId>>,
+    event_tx: mpsc::UnboundedSender<GoblinEvent>,
+    /// Submitted tasks that have not finished yet
+    tasks: RwLock<TaskRegistry>,
+    /// Files and blobs attached to or produced by tasks
+    artifacts: Arc<ArtifactStore>,
+    /// Planning strategy for new tasks
+    planner: Arc<dyn Planner>,
+    /// Plans by task
+    plans: Arc<RwLock<HashMap<TaskId, TaskPlan>>>,
+    /// Strategy for combining child results
+    merger: Arc<dyn ResultMerger>,
+    /// Reported and merged results, by task or subtask
+    results: RwLock<HashMap<TaskId, TaskResult>>,
+    /// Reviewer of subtask results, if review is enabled
+    reviewer: Option<Arc<dyn Reviewer>>,
+    /// Execution engines of agent actors, by role
+    runtimes: Runtimes,
+    /// Model providers agents are bound to when spawned
+    models: ModelProviders,
+    /// Summarizer used to compact agent histories
+    summarizer: Arc<dyn Summarizer>,
+    /// Times each subtask's result was bounced by the reviewer
+    bounces: RwLock<HashMap<TaskId, u32>>,
+    /// Workflows being executed, by task
+    workflows: RwLock<HashMap<TaskId, WorkflowRun>>,
+    /// Subtasks waiting for an idle worker
+    scheduler: RwLock<TaskScheduler>,
+    /// Model call slots shared between tasks
+    model_slots: Arc<ModelSlots>,
+    /// Tool execution slots shared between tasks
+    tool_slots: TaskSlots,
+    /// Outcomes of subtasks delegated to other sessions
+    delegated: RwLock<HashMap<TaskId, DelegationOutcome>>,
+    /// Deadlines of assigned tasks
+    deadlines: RwLock<DeadlineTracker>,
+    /// Run loops of root agents
+    root_scope: AgentScope,
+    /// Cancelled run loops not yet awaited
+    draining: Mutex<Vec<RunLoop>>,
+    /// Tokens spent by agents that have been terminated
+    retired_usage: Mutex<TokenUsage>,
+    /// Tokens spent by finished subtasks, by role, for cost estimates
+    role_history: RwLock<RoleHistory>,
+    /// Spawn rate buckets of the session and of each parent
+    spawn_buckets: Mutex<SpawnBuckets>,
+    /// Times each agent was restarted by its supervision policy
+    restarts: Mutex<HashMap<AgentId, u32>>,
+    /// Failed attempts at tasks being retried
+    attempts: Mutex<HashMap<TaskId, Vec<TaskAttempt>>>,
+    /// Failures of each agent in a row
+    failures: Mutex<HashMap<AgentId, u32>>,
+    /// Failure policies set for the subtrees of agents, overriding their role's
+    subtree_policies: RwLock<HashMap<AgentId, FailurePolicy>>,
+    /// Blackboard shared by the session's agents
+    store: Arc<SessionStore>,
+    /// Commands waiting for approval, and the policy deciding them
+    approvals: Arc<Approvals>,
+    /// Requests for access beyond agents' sandboxes
+    escalations: Arc<SandboxEscalations>,
+    /// Tool calls and approvals of the session's agents
+    audit: Arc<AuditLog>,
+    /// Token spending rolled up the hierarchy, with subtree ceilings
+    ledger: Arc<BudgetLedger>,
+    /// Circuit breakers of the models agents call, if the options ask for them
+    breakers: Option<Arc<CircuitBreakers>>,
+    /// Results of read-only tool calls, if the options ask for them
+    tool_cache: Option<Arc<ToolCache>>,
+    /// Tool calls in flight, if the options ask for identical ones to be coalesced
+    in_flight: Option<Arc<InFlightCalls>>,
+    /// Rate and concurrency limits of tools across agents
+    tool_limits: Arc<ToolLimits>,
+    /// Tools of the session's MCP servers
+    mcp: Arc<McpTools>,
+    /// Agent and task counters reported by `metrics`
+    counters: Counters,
+    /// When the session was created
+    created: Instant,
 }
 
 impl Session {
@@ -39,21 +166,226 @@ impl Session {
     pub fn new(
         config: SessionConfig,
         tools: Arc<ToolRegistry>,
-        event_tx: mpsc::UnboundedSender<Event>,
+        event_tx: mpsc::UnboundedSender<GoblinEvent>,
+    ) -> Self {
+        Self::with_options(config, SessionOptions::default(), tools, event_tx)
+    }
+
+    /// Create a new session with cabal orchestration options
+    pub fn with_options(
+        config: SessionConfig,
+        options: SessionOptions,
+        tools: Arc<ToolRegistry>,
+        event_tx: mpsc::UnboundedSender<GoblinEvent>,
     ) -> Self {
         let id = SessionId::new();
         
         info!(session_id = %id, "Creating new session");
         
+        let planner = options.planner.build();
+        let merger = options.merger.build();
+        let plans = Arc::new(RwLock::new(HashMap::new()));
+        let model_slots = Arc::new(ModelSlots::new(
+            options.model_slots,
+            &options.model_slots_per_model,
+            Arc::clone(&plans),
+        ));
+        let tool_slots = TaskSlots::new(options.tool_slots, Arc::clone(&plans));
+        let scheduler = match options.max_queued_tasks {
+            Some(max) => TaskScheduler::with_capacity(max),
+            None => TaskScheduler::new(),
+        };
+
+        let store = Arc::new(SessionStore::new(event_tx.clone()));
+        let mut audit = AuditLog::new().with_redacted_keys(options.audit_redacted_keys.iter().cloned());
+        if let Some(dir) = &options.audit_export_dir {
+            audit = audit.with_export_dir(dir.clone());
+        }
+        let audit = Arc::new(audit);
+        let approvals = Arc::new(
+            Approvals::new(options.approval.clone(), event_tx.clone()).with_audit(Arc::clone(&audit)),
+        );
+        let ledger = Arc::new(BudgetLedger::new(options.token_budgets.session, event_tx.clone()));
+        let breakers = options
+            .circuit_breaker
+            .map(|policy| Arc::new(CircuitBreakers::new(policy, event_tx.clone())));
+        let tool_cache = options.tool_cache.clone().map(|policy| Arc::new(ToolCache::new(policy)));
+        let in_flight = options
+            .coalesce
+            .clone()
+            .map(|policy| Arc::new(InFlightCalls::new(policy.resolved(options.tool_cache.as_ref()))));
+        let tool_limits = Arc::new(ToolLimits::new(options.tool_limits.clone()));
+        let mcp = Arc::new(McpTools::new());
+        let escalations = Arc::new(
+            SandboxEscalations::new(options.sandbox.escalation_rules.clone(), event_tx.clone())
+                .with_audit(Arc::clone(&audit)),
+        );
+
         Self {
             id,
-            config,
+            config: RwLock::new(config),
+            options,
             agents: RwLock::new(HashMap::new()),
             hierarchy: RwLock::new(AgentHierarchy::new()),
             tools,
             event_tx,
-            current_task: RwLock::new(None),
+            tasks: RwLock::new(TaskRegistry::new()),
+            artifacts: Arc::new(ArtifactStore::new()),
+            planner,
+            plans,
+            merger,
+            results: RwLock::new(HashMap::new()),
+            reviewer: None,
+            runtimes: Runtimes::default(),
+            models: ModelProviders::default(),
+            summarizer: Arc::new(ModelSummarizer),
+            bounces: RwLock::new(HashMap::new()),
+            workflows: RwLock::new(HashMap::new()),
+            scheduler: RwLock::new(scheduler),
+            model_slots,
+            tool_slots,
+            delegated: RwLock::new(HashMap::new()),
+            deadlines: RwLock::new(DeadlineTracker::new()),
+            root_scope: AgentScope::new(),
+            draining: Mutex::new(Vec::new()),
+            retired_usage: Mutex::new(TokenUsage::default()),
+            role_history: RwLock::new(RoleHistory::default()),
+            spawn_buckets: Mutex::new(SpawnBuckets::default()),
+            restarts: Mutex::new(HashMap::new()),
+            attempts: Mutex::new(HashMap::new()),
+            failures: Mutex::new(HashMap::new()),
+            subtree_policies: RwLock::new(HashMap::new()),
+            store,
+            approvals,
+            escalations,
+            audit,
+            ledger,
+            breakers,
+            tool_cache,
+            in_flight,
+            tool_limits,
+            mcp,
+            counters: Counters::default(),
+            created: Instant::now(),
+        }
+    }
+
+    /// Keep an existing session id, e.g. when restoring from a checkpoint
+    pub(crate) fn with_id(mut self, id: SessionId) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Replace the planning strategy
+    pub fn with_planner(mut self, planner: Arc<dyn Planner>) -> Self {
+        self.planner = planner;
+        self
+    }
+
+    /// Name of the planning strategy in use
+    pub fn planner_name(&self) -> &str {
+        self.planner.name()
+    }
+
+    /// Replace the result merging strategy
+    pub fn with_merger(mut self, merger: Arc<dyn ResultMerger>) -> Self {
+        self.merger = merger;
+        self
+    }
+
+    /// Install the reviewer used when the options enable review
+    pub fn with_reviewer(mut self, reviewer: Arc<dyn Reviewer>) -> Self {
+        self.reviewer = Some(reviewer);
+        self
+    }
+
+    /// Replace the agent runtimes
+    pub fn with_runtimes(mut self, runtimes: Runtimes) -> Self {
+        self.runtimes = runtimes;
+        self
+    }
+
+    /// Agent runtimes in use
+    pub fn runtimes(&self) -> &Runtimes {
+        &self.runtimes
+    }
+
+    /// Replace the model providers
+    pub fn with_models(mut self, models: ModelProviders) -> Self {
+        self.models = models;
+        self
+    }
+
+    /// Model providers in use
+    pub fn models(&self) -> &ModelProviders {
+        &self.models
+    }
+
+    /// Replace the summarizer used to compact agent histories
+    pub fn with_summarizer(mut self, summarizer: Arc<dyn Summarizer>) -> Self {
+        self.summarizer = summarizer;
+        self
+    }
+
+    /// Name of the result merging strategy in use
+    pub fn merger_name(&self) -> &str {
+        self.merger.name()
+    }
+
+    /// Plan a task and remember the plan
+    pub async fn plan_task(&self, request: &PlanRequest) -> Result<TaskPlan, GoblinError> {
+        let plan = self.planner.plan(request).await?;
+
+        debug!(
+            session_id = %self.id,
+            task_id = %request.task_id,
+            planner = self.planner.name(),
+            subtasks = plan.len(),
+            "Planned task"
+        );
+
+        self.plans.write().insert(request.task_id, plan.clone());
+        Ok(plan)
+    }
+
+    /// Estimate what a planned task will cost, if the options ask for estimates
+    pub fn estimate_cost(&self, plan: &TaskPlan) -> Option<CostEstimate> {
+        let policy = self.options.estimate.as_ref()?;
+        let price = self.options.pricing.price_for(&self.config().model);
+        Some(policy.estimate(plan, &self.role_history.read(), price))
+    }
+
+    /// Take a spawn from the session's and the parent's rate limits, if set
+    fn take_spawn_token(&self, parent_id: Option<AgentId>) -> Result<(), GoblinError> {
+        let limits = self.options.spawn;
+        if limits.rate_per_session.is_none() && limits.rate_per_parent.is_none() {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let mut buckets = self.spawn_buckets.lock();
+        let SpawnBuckets { session, parents } = &mut *buckets;
+        let mut limited = Vec::new();
+        if let Some(limit) = limits.rate_per_session {
+            limited.push(session.get_or_insert_with(|| TokenBucket::new_at(limit, now)));
         }
+        if let (Some(limit), Some(pid)) = (limits.rate_per_parent, parent_id) {
+            limited.push(parents.entry(pid).or_insert_with(|| TokenBucket::new_at(limit, now)));
+        }
+        TokenBucket::try_take_all(&mut limited, now).map_err(|wait| {
+            let reason = match parent_id {
+                Some(pid) => format!("spawns under agent {} exceed the rate limit", pid),
+                None => "spawns exceed the session's rate limit".to_string(),
+            };
+            GoblinError::SpawnRateLimited {
+                reason,
+                retry_after_ms: (wait.as_millis() as u64).max(1),
+            }
+        })
+    }
+
+    /// Get the plan for a task
+    pub fn plan(&self, task_id: &TaskId) -> Option<TaskPlan> {
+        self.plans.read().get(task_id).cloned()
     }
 
     /// Spawn a new agent in this session
@@ -62,6 +394,85 @@ impl Session {
         config: AgentConfig,
         parent_id: Option<AgentId>,
         sub_id: &SubmissionId,
+    ) -> Result<AgentHandle, GoblinError> {
+        self.spawn(config, parent_id, None, sub_id)
+    }
+
+    /// Spawn a new agent whose tools are narrowed by a scope of its own,
+    /// on top of the session's tool scopes
+    pub fn spawn_agent_with_tools(
+        &self,
+        config: AgentConfig,
+        parent_id: Option<AgentId>,
+        tools: ToolScope,
+        sub_id: &SubmissionId,
+    ) -> Result<AgentHandle, GoblinError> {
+        self.spawn(config, parent_id, Some(tools), sub_id)
+    }
+
+    fn spawn(
+        &self,
+        config: AgentConfig,
+        parent_id: Option<AgentId>,
+        tools: Option<ToolScope>,
+        sub_id: &SubmissionId,
+    ) -> Result<AgentHandle, GoblinError> {
+        let handle = self.register_agent(config.clone(), parent_id, None, tools)?;
+        let agent_id = handle.id();
+
+        // Emit event
+        let _ = self.event_tx.send(Event::AgentSpawned {
+            sub_id: sub_id.clone(),
+            agent_id,
+            parent_id,
+            role: config.role.clone(),
+            config,
+        }.into());
+        let added = self.hierarchy.read().entry(&agent_id, handle.status());
+        self.emit(GoblinEvent::TreeDelta {
+            sub_id: sub_id.clone(),
+            added: added.into_iter().collect(),
+            removed: Vec::new(),
+            status_changes: Vec::new(),
+        });
+        if handle.is_paused() {
+            self.emit(GoblinEvent::AgentPaused {
+                sub_id: sub_id.clone(),
+                agent_id,
+            });
+        }
+        let live = self.agent_count();
+        if let (Some(limit), Some(warn_at)) = (self.options.spawn.max_agents, self.options.spawn.warn_at()) {
+            if live == warn_at {
+                warn!(session_id = %self.id, live, limit, "Session is near its agent limit");
+                self.emit(GoblinEvent::AgentLimitNear {
+                    sub_id: sub_id.clone(),
+                    session_id: self.id,
+                    live,
+                    limit,
+                });
+            }
+        }
+
+        info!(
+            session_id = %self.id,
+            agent_id = %agent_id,
+            parent = ?parent_id,
+            "Spawned agent"
+        );
+
+        Ok(handle)
+    }
+
+    /// Create an agent and add it to the registry and hierarchy, without events
+    ///
+    /// The agent gets a fresh id unless one is given.
+    fn register_agent(
+        &self,
+        config: AgentConfig,
+        parent_id: Option<AgentId>,
+        id: Option<AgentId>,
+        own_tools: Option<ToolScope>,
     ) -> Result<AgentHandle, GoblinError> {
         // Verify parent exists if specified
         if let Some(pid) = &parent_id {
@@ -78,24 +489,103 @@ impl Session {
             }
         }
 
+        let depth = parent_id.map_or(0, |pid| self.hierarchy.read().depth(&pid) + 1);
+        if self.options.spawn.max_depth.is_some_and(|max| depth > max) {
+            return Err(GoblinError::SpawnDenied(format!("Agents may not be spawned below depth {}", depth - 1)));
+        }
+
         // Create the agent
-        let agent = Agent::new(
+        let mut agent = Agent::new(
             config.clone(),
             parent_id,
             Arc::clone(&self.tools),
             self.event_tx.clone(),
         );
+        if let Some(id) = id {
+            agent = agent.with_id(id);
+        }
         let agent_id = agent.id;
-        let handle = AgentHandle::new(agent);
 
-        // Add to registry
-        self.agents.write().insert(agent_id, handle.clone());
+        if let Some(scratch) = &self.options.scratch {
+            agent = agent.with_scratch(ScratchDir::provision(scratch, agent_id)?);
+        }
+        if let Some(model) = self.models.resolve(&config.model) {
+            agent = agent.with_model(model);
+        }
+        if let Some(policy) = self.options.compaction {
+            agent = agent.with_compactor(Compactor {
+                policy,
+                summarizer: Arc::clone(&self.summarizer),
+            });
+        }
+        let mut tools = ToolView::scoped(Arc::clone(&self.tools), &self.options.tool_scopes, &config.role, depth);
+        if let Some(scope) = own_tools {
+            tools = tools.with_own_scope(scope);
+        }
+        let agent = agent
+            .with_tool_view(tools)
+            .with_sandbox_profile(self.options.sandbox.profile_for(&config.role).clone())
+            .with_trust(self.approvals.policy().trust.trust_for(&config.role, depth))
+            .with_budget(self.options.token_budgets.budget_for(&config.role).cloned())
+            .with_price(self.options.pricing.price_for(&config.model))
+            .with_store(Arc::clone(&self.store))
+            .with_artifacts(Arc::clone(&self.artifacts))
+            .with_approvals(Arc::clone(&self.approvals))
+            .with_escalations(Arc::clone(&self.escalations))
+            .with_audit(Arc::clone(&self.audit))
+            .with_ledger(Arc::clone(&self.ledger))
+            .with_model_slots(Arc::clone(&self.model_slots))
+            .with_tool_slots(self.tool_slots.clone())
+            .with_usage_updates(self.options.usage_updates);
+        let agent = match &self.breakers {
+            Some(breakers) => agent.with_circuit_breakers(Arc::clone(breakers)),
+            None => agent,
+        };
+        let agent = match &self.tool_cache {
+            Some(cache) => agent.with_tool_cache(Arc::clone(cache)),
+            None => agent,
+        };
+        let agent = match &self.in_flight {
+            Some(calls) => agent.with_in_flight_calls(Arc::clone(calls)),
+            None => agent,
+        };
+        let agent = agent
+            .with_tool_limits(Arc::clone(&self.tool_limits))
+            .with_mcp_tools(Arc::clone(&self.mcp));
+        let agent = match self.options.max_tool_calls_per_agent {
+            Some(calls) => agent.with_max_tool_calls(calls),
+            None => agent,
+        };
+        let handle = AgentHandle::new(agent);
 
-        // Update hierarchy
+        // Check the agent ceiling, update the hierarchy and add to the
+        // registry under the same locks, so concurrent spawns cannot
+        // overshoot the ceiling
         {
             let mut hierarchy = self.hierarchy.write();
-            hierarchy.add_agent(agent_id, config.role.clone(), parent_id);
+            let mut agents = self.agents.write();
+            let live = agents.len();
+            if let Some(max) = self.options.spawn.max_agents.filter(|max| live >= *max) {
+                return Err(GoblinError::SpawnDenied(format!("Session already has {} of at most {} agents", live, max)));
+            }
+            hierarchy.add_agent(agent_id, config.role.clone(), parent_id)?;
+            // Restored agents keep their IDs and are not new spawns; new
+            // ones take from the rate limits only once nothing else
+            // refused them
+            if id.is_none() {
+                if let Err(e) = self.take_spawn_token(parent_id) {
+                    hierarchy.remove_agent(&agent_id);
+                    return Err(e);
+                }
+            }
+            hierarchy.set_max_children(&agent_id, config.max_children);
+            agents.insert(agent_id, handle.clone());
         }
+        // Only once the agent is in the hierarchy, so a rejected spawn
+        // leaves nothing behind
+        self.approvals.register(&handle);
+        self.ledger.register(&handle, self.options.token_budgets.ceiling_for(&config.role));
+        Counters::incr(&self.counters.agents_spawned);
 
         // Update parent's children list
         if let Some(pid) = &parent_id {
@@ -104,23 +594,83 @@ impl Session {
             }
         }
 
-        // Emit event
-        let _ = self.event_tx.send(Event::AgentSpawned {
-            sub_id: sub_id.clone(),
-            agent_id,
-            parent_id,
-            role: config.role.clone(),
-            config,
-        });
+        Ok(handle)
+    }
 
-        info!(
-            session_id = %self.id,
-            agent_id = %agent_id,
-            parent = ?parent_id,
-            "Spawned agent"
-        );
+    /// Commands of this session's agents waiting for approval
+    pub fn approvals(&self) -> &Approvals {
+        &self.approvals
+    }
 
-        Ok(handle)
+    /// Requests of this session's agents for access beyond their sandboxes
+    pub fn escalations(&self) -> &SandboxEscalations {
+        &self.escalations
+    }
+
+    /// Audit trail of this session's tool calls and approvals
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    /// Token spending of this session's agents, rolled up by subtree
+    pub fn ledger(&self) -> &BudgetLedger {
+        &self.ledger
+    }
+
+    /// Results of this session's read-only tool calls, if cached
+    pub fn tool_cache(&self) -> Option<&ToolCache> {
+        self.tool_cache.as_deref()
+    }
+
+    /// Tool calls of this session's agents in flight, if coalesced
+    pub fn in_flight_calls(&self) -> Option<&InFlightCalls> {
+        self.in_flight.as_deref()
+    }
+
+    /// Rate and concurrency limits of this session's tools
+    pub fn tool_limits(&self) -> &ToolLimits {
+        &self.tool_limits
+    }
+
+    /// Tools of this session's MCP servers
+    pub fn mcp_tools(&self) -> &McpTools {
+        &self.mcp
+    }
+
+    /// Start the MCP servers of the session's options and offer their tools
+    ///
+    /// Servers that cannot be started or listed are reported and left out.
+    pub async fn connect_mcp_servers(&self, sub_id: &SubmissionId) {
+        for server in &self.options.mcp_servers {
+            let spawned = check_registry_names(&server.name, self.tools.names())
+                .and_then(|_| StdioTransport::spawn(server));
+            let connected = match spawned {
+                Ok(transport) => McpClient::connect(&server.name, Arc::new(transport)).await,
+                Err(e) => Err(e),
+            };
+            match connected {
+                Ok(client) => {
+                    let tools: Vec<String> = client.tools().iter().map(|t| t.name.clone()).collect();
+                    info!(session_id = %self.id, server = %server.name, tools = tools.len(), "Connected MCP server");
+                    self.mcp.add(client);
+                    self.emit(GoblinEvent::McpServerConnected {
+                        sub_id: sub_id.clone(),
+                        session_id: self.id,
+                        server: server.name.clone(),
+                        tools,
+                    });
+                }
+                Err(e) => {
+                    warn!(session_id = %self.id, server = %server.name, error = %e, "Failed to connect MCP server");
+                    self.emit(GoblinEvent::McpServerFailed {
+                        sub_id: sub_id.clone(),
+                        session_id: self.id,
+                        server: server.name.clone(),
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
     }
 
     /// Get an agent by ID
@@ -138,125 +688,3092 @@ impl Session {
         self.agents.read().len()
     }
 
+    /// State of every agent
+    pub fn agent_summaries(&self) -> Vec<AgentSummary> {
+        let mut summaries: Vec<_> = self.agents.read().values().map(|a| a.summary()).collect();
+        summaries.sort_by_key(|s| s.agent_id.to_string());
+        summaries
+    }
+
+    /// Token usage summed over all agents, including terminated ones
+    pub fn total_usage(&self) -> TokenUsage {
+        let mut total = self.retired_usage.lock().clone();
+        for agent in self.agents.read().values() {
+            let usage = agent.usage();
+            total.input_tokens += usage.input_tokens;
+            total.output_tokens += usage.output_tokens;
+            total.total_tokens += usage.total_tokens;
+        }
+        total
+    }
+
     /// Terminate an agent
+    ///
+    /// What the agent had learned is handed over to its parent's mailbox.
     pub fn terminate_agent(
         &self,
         agent_id: &AgentId,
         reason: String,
         sub_id: &SubmissionId,
     ) -> Result<(), GoblinError> {
-        let agent = self.agents.write().remove(agent_id).ok_or_else(|| {
-            GoblinError::AgentNotFound(*agent_id)
-        })?;
-
-        // Remove from parent's children
-        if let Some(pid) = agent.parent_id {
-            if let Some(parent) = self.agents.read().get(&pid) {
-                parent.remove_child(agent_id);
-            }
+        let parent_id = self.get_agent(agent_id).and_then(|a| a.parent_id());
+        if let (Some(handoff), Some(parent_id)) = (self.remove_agent(agent_id, reason, sub_id)?, parent_id) {
+            self.hand_off(handoff, &parent_id, sub_id);
         }
+        Ok(())
+    }
 
-        // Terminate children recursively
-        for child_id in agent.children() {
-            let _ = self.terminate_agent(&child_id, "Parent terminated".into(), sub_id);
+    /// Terminate an agent and spawn a fresh one with the same configuration
+    /// in its place
+    ///
+    /// The replacement starts with the terminated agent's handoff in its
+    /// history and takes over its task.
+    pub fn replace_agent(
+        &self,
+        agent_id: &AgentId,
+        reason: String,
+        sub_id: &SubmissionId,
+    ) -> Result<AgentHandle, GoblinError> {
+        let agent = self.get_agent(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+        let (config, parent_id) = (agent.config.clone(), agent.parent_id());
+        drop(agent);
+
+        let handoff = self.remove_agent(agent_id, reason, sub_id)?;
+        let replacement = self.spawn_agent(config, parent_id, sub_id)?;
+        if let Some(handoff) = handoff {
+            self.emit_handoff(&handoff, replacement.id(), sub_id);
+            replacement.record(handoff.to_entry());
+            if let Some(task_id) = handoff.task_id {
+                replacement.assign_task(task_id);
+            }
         }
+        Ok(replacement)
+    }
 
-        // Update hierarchy
-        self.hierarchy.write().remove_agent(agent_id);
+    /// Heartbeat policy of the session's agents, if they beat
+    pub fn heartbeat_policy(&self) -> Option<HeartbeatPolicy> {
+        self.options.heartbeat
+    }
 
-        // Terminate the agent
-        agent.terminate(sub_id, reason);
+    /// Mark agents that missed their heartbeats unresponsive
+    ///
+    /// Unresponsive agents that beat again are marked responsive. Paused
+    /// and terminated agents, and agents whose run loop never started, are
+    /// not checked. Returns the agents newly found unresponsive.
+    pub fn check_heartbeats(&self, sub_id: &SubmissionId) -> Vec<AgentId> {
+        let Some(policy) = self.options.heartbeat else { return Vec::new() };
+        let agents: Vec<AgentHandle> = self.agents.read().values().cloned().collect();
+        let mut found = Vec::new();
+        for agent in agents {
+            if agent.is_paused() || agent.status() == warhorn::AgentStatus::Terminated {
+                continue;
+            }
+            let Some(silent) = agent.since_beat() else { continue };
+            let unresponsive = silent >= policy.timeout();
+            if !agent.set_unresponsive(unresponsive) {
+                continue;
+            }
+            if unresponsive {
+                warn!(
+                    session_id = %self.id,
+                    agent_id = %agent.id(),
+                    silent_ms = silent.as_millis() as u64,
+                    "Agent missed its heartbeats"
+                );
+                self.emit(GoblinEvent::AgentUnresponsive {
+                    sub_id: sub_id.clone(),
+                    agent_id: agent.id(),
+                    task_id: agent.current_task(),
+                    silent_ms: silent.as_millis() as u64,
+                });
+                found.push(agent.id());
+            } else {
+                info!(session_id = %self.id, agent_id = %agent.id(), "Agent is responsive again");
+                self.emit(GoblinEvent::AgentResponsive {
+                    sub_id: sub_id.clone(),
+                    agent_id: agent.id(),
+                });
+            }
+        }
+        found
+    }
 
-        info!(
-            session_id = %self.id,
-            agent_id = %agent_id,
-            "Terminated agent"
-        );
+    /// Report agents working on a task that made no progress for a while
+    ///
+    /// Each stall is reported once and the stall policy's action applied:
+    /// a nudge is added to the agent's history, an interrupt drops its
+    /// task. Returns the agents newly found stalled.
+    pub fn check_stalls(&self, sub_id: &SubmissionId) -> Vec<AgentId> {
+        let Some(policy) = &self.options.stall else { return Vec::new() };
+        let agents: Vec<AgentHandle> = self.agents.read().values().cloned().collect();
+        let now = chrono::Utc::now();
+        let mut found = Vec::new();
+        for agent in agents {
+            let Some(task_id) = agent.current_task() else { continue };
+            if agent.is_paused() || agent.status() == warhorn::AgentStatus::Terminated {
+                continue;
+            }
+            let last_activity = agent.last_progress();
+            let idle = (now - last_activity).to_std().unwrap_or_default();
+            if idle < policy.after() || !agent.set_stalled() {
+                continue;
+            }
+            warn!(
+                session_id = %self.id,
+                agent_id = %agent.id(),
+                task_id = %task_id,
+                idle_ms = idle.as_millis() as u64,
+                "Agent stalled"
+            );
+            self.emit(GoblinEvent::AgentStalled {
+                sub_id: sub_id.clone(),
+                agent_id: agent.id(),
+                task_id,
+                last_activity,
+                action: policy.action,
+            });
+            match policy.action {
+                StallAction::Warn => {}
+                StallAction::Nudge => agent.record(HistoryEntry::message(ChatRole::User, policy.nudge())),
+                StallAction::Interrupt => {
+                    if let Err(e) = agent.interrupt() {
+                        warn!(agent_id = %agent.id(), error = %e, "Failed to interrupt stalled agent");
+                    }
+                }
+            }
+            found.push(agent.id());
+        }
+        found
+    }
 
+    /// Pause an agent, keeping all of its state
+    pub fn pause_agent(&self, agent_id: &AgentId, sub_id: &SubmissionId) -> Result<(), GoblinError> {
+        let agent = self.get_agent(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+        if agent.pause() {
+            info!(session_id = %self.id, agent_id = %agent_id, "Paused agent");
+            self.emit(GoblinEvent::AgentPaused {
+                sub_id: sub_id.clone(),
+                agent_id: *agent_id,
+            });
+        }
         Ok(())
     }
 
-    /// Get the hierarchy tree
-    pub fn hierarchy(&self) -> warhorn::AgentTree {
-        self.hierarchy.read().to_tree(&self.agents.read())
+    /// Resume a paused agent where it left off
+    pub fn resume_agent(&self, agent_id: &AgentId, sub_id: &SubmissionId) -> Result<(), GoblinError> {
+        let agent = self.get_agent(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+        if agent.resume() {
+            info!(session_id = %self.id, agent_id = %agent_id, "Resumed agent");
+            self.emit(GoblinEvent::AgentResumed {
+                sub_id: sub_id.clone(),
+                agent_id: *agent_id,
+            });
+        }
+        Ok(())
     }
 
-    /// Set current task
-    pub fn set_current_task(&self, task_id: Option<TaskId>) {
-        *self.current_task.write() = task_id;
-    }
+    /// Move an agent, with its subtree, under a new parent
+    ///
+    /// The move is rejected if it would create a cycle, if the new parent
+    /// cannot spawn, or if it has no room for another child. The agent's
+    /// run loop moves to the new parent's scope.
+    pub fn reparent_agent(
+        &self,
+        agent_id: &AgentId,
+        new_parent: &AgentId,
+        sub_id: &SubmissionId,
+    ) -> Result<(), GoblinError> {
+        let agent = self.get_agent(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+        let parent = self.get_agent(new_parent).ok_or(GoblinError::AgentNotFound(*new_parent))?;
+        if !parent.config.can_spawn {
+            return Err(GoblinError::SpawnDenied(format!("Agent {} cannot have children", new_parent)));
+        }
+        if let Some(max) = self.options.spawn.max_depth {
+            let hierarchy = self.hierarchy.read();
+            let base = hierarchy.depth(agent_id);
+            let below = hierarchy.descendants(agent_id).iter().map(|id| hierarchy.depth(id) - base).max();
+            let deepest = hierarchy.depth(new_parent) + 1 + below.unwrap_or(0);
+            if deepest > max {
+                return Err(GoblinError::SpawnDenied(format!(
+                    "Moving agent {} under {} would put agents at depth {}, below the limit of {}",
+                    agent_id, new_parent, deepest, max
+                )));
+            }
+        }
 
-    /// Get current task
-    pub fn current_task(&self) -> Option<TaskId> {
-        *self.current_task.read()
-    }
+        let old_parent = self.hierarchy.write().reparent(agent_id, new_parent)?;
+        if old_parent == Some(*new_parent) {
+            return Ok(());
+        }
 
-    /// Get the root orchestrator agent (if exists)
-    pub fn orchestrator(&self) -> Option<AgentHandle> {
-        self.hierarchy.read().root().and_then(|id| self.get_agent(&id))
+        let old = old_parent.and_then(|pid| self.get_agent(&pid));
+        if let Some(old) = &old {
+            old.remove_child(agent_id);
+        }
+        parent.add_child(*agent_id);
+        agent.set_parent(*new_parent);
+
+        let run = match &old {
+            Some(old) => old.scope().detach(agent_id),
+            None => self.root_scope.detach(agent_id),
+        };
+        if let Some(run) = run {
+            parent.scope().adopt(run);
+        }
+
+        info!(session_id = %self.id, agent_id = %agent_id, new_parent = %new_parent, "Reparented agent");
+        self.emit(GoblinEvent::HierarchyChanged {
+            sub_id: sub_id.clone(),
+            agent_id: *agent_id,
+            old_parent,
+            new_parent: *new_parent,
+        });
+        Ok(())
     }
-}
 
-/// Handle to a session for external interaction
-#[derive(Clone)]
-pub struct SessionHandle {
-    inner: Arc<Session>,
-}
+    /// Apply the orphan policy to the children of an agent that died
+    ///
+    /// Returns the children that were adopted or terminated.
+    pub fn handle_orphans(&self, agent_id: &AgentId, sub_id: &SubmissionId) -> Vec<AgentId> {
+        let Some(policy) = self.options.orphans else { return Vec::new() };
+        let (grandparent, children) = {
+            let hierarchy = self.hierarchy.read();
+            (hierarchy.parent(agent_id), hierarchy.children(agent_id))
+        };
+        for child in &children {
+            let adopted = match (policy, grandparent) {
+                (OrphanPolicy::Adopt, Some(new_parent)) => match self.reparent_agent(child, &new_parent, sub_id) {
+                    Ok(()) => Some(new_parent),
+                    Err(e) => {
+                        warn!(session_id = %self.id, agent_id = %child, error = %e, "Failed to adopt orphan");
+                        None
+                    }
+                },
+                _ => None,
+            };
+            if let Some(new_parent) = adopted {
+                info!(session_id = %self.id, agent_id = %child, new_parent = %new_parent, "Adopted orphan");
+                self.emit(GoblinEvent::OrphanAdopted {
+                    sub_id: sub_id.clone(),
+                    agent_id: *child,
+                    old_parent: *agent_id,
+                    new_parent,
+                });
+                continue;
+            }
 
-impl SessionHandle {
-    pub fn new(session: Session) -> Self {
-        Self {
-            inner: Arc::new(session),
+            let reason = format!("parent {} died", agent_id);
+            match self.remove_agent(child, reason.clone(), sub_id) {
+                Ok(handoff) => {
+                    if let (Some(handoff), Some(grandparent)) = (handoff, grandparent) {
+                        self.hand_off(handoff, &grandparent, sub_id);
+                    }
+                    info!(session_id = %self.id, agent_id = %child, "Terminated orphan");
+                    self.emit(GoblinEvent::OrphanTerminated {
+                        sub_id: sub_id.clone(),
+                        agent_id: *child,
+                        old_parent: *agent_id,
+                        reason,
+                    });
+                }
+                Err(e) => warn!(session_id = %self.id, agent_id = %child, error = %e, "Failed to terminate orphan"),
+            }
         }
+        children
     }
 
-    pub fn id(&self) -> SessionId {
-        self.inner.id
+    /// Spawn a sibling of an agent that continues from the same point
+    ///
+    /// The fork gets a copy of the source's history and works on the same
+    /// task, so alternative approaches can be explored in parallel.
+    pub fn fork_agent(
+        &self,
+        agent_id: &AgentId,
+        overrides: AgentOverrides,
+        sub_id: &SubmissionId,
+    ) -> Result<AgentHandle, GoblinError> {
+        let source = self.get_agent(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+        let mut config = source.config.clone();
+        overrides.apply(&mut config);
+
+        let fork = self.spawn_agent(config, source.parent_id(), sub_id)?;
+        fork.restore_history(source.history());
+        if let Some(task_id) = source.current_task() {
+            fork.assign_task(task_id);
+        }
+
+        info!(session_id = %self.id, source = %agent_id, fork = %fork.id(), "Forked agent");
+        self.emit(GoblinEvent::AgentForked {
+            sub_id: sub_id.clone(),
+            source: *agent_id,
+            agent_id: fork.id(),
+        });
+        Ok(fork)
     }
-}
 
-impl std::ops::Deref for SessionHandle {
-    type Target = Session;
+    /// Deliver the context another agent left behind to an agent's mailbox
+    pub(crate) fn hand_off(&self, handoff: Handoff, to: &AgentId, sub_id: &SubmissionId) {
+        if let Some(agent) = self.get_agent(to) {
+            self.emit_handoff(&handoff, *to, sub_id);
+            agent.deliver(Mail::Handoff { handoff });
+        }
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.inner
+    fn emit_handoff(&self, handoff: &Handoff, to: AgentId, sub_id: &SubmissionId) {
+        debug!(session_id = %self.id, from = %handoff.from, to = %to, "Handed off agent context");
+        self.emit(GoblinEvent::AgentHandoff {
+            sub_id: sub_id.clone(),
+            from: handoff.from,
+            to,
+            task_id: handoff.task_id,
+        });
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use warhorn::AgentRole;
+    /// Count an agent's failed result, reporting the agent to its lead
+    /// once it failed often enough in a row
+    fn track_failures(&self, agent: &AgentHandle, result: &TaskResult, sub_id: &SubmissionId) {
+        if result.is_success() {
+            self.failures.lock().remove(&agent.id());
+            return;
+        }
+        if result.status != ResultStatus::Failed {
+            return;
+        }
+        let in_a_row = {
+            let mut failures = self.failures.lock();
+            let count = failures.entry(agent.id()).or_default();
+            *count += 1;
+            *count
+        };
+        let failures = in_a_row.max(result.attempts.len() as u32 + 1);
+        if failures < self.options.failure_reports.after_failures {
+            return;
+        }
+        let Some(lead) = self.lead_of(agent) else { return };
+        self.failures.lock().remove(&agent.id());
 
-    fn create_test_session() -> (Session, mpsc::UnboundedReceiver<Event>) {
-        let (tx, rx) = mpsc::unbounded_channel();
-        let tools = Arc::new(ToolRegistry::new());
-        let config = SessionConfig::default();
-        (Session::new(config, tools, tx), rx)
+        warn!(session_id = %self.id, agent_id = %agent.id(), lead_id = %lead.id(), failures, "Reporting failing agent to its lead");
+        self.emit(GoblinEvent::FailureReported {
+            sub_id: sub_id.clone(),
+            agent_id: agent.id(),
+            task_id: result.task_id,
+            lead_id: lead.id(),
+            failures,
+        });
+        lead.deliver(Mail::FailureReport {
+            report: FailureReport::new(agent, result.clone(), failures),
+        });
     }
 
-    #[test]
-    fn test_session_creation() {
-        let (session, _rx) = create_test_session();
-        assert_eq!(session.agent_count(), 0);
+    /// Set the failure policy of an agent's subtree, overriding its role's
+    pub fn set_failure_policy(&self, agent_id: AgentId, policy: FailurePolicy) {
+        self.subtree_policies.write().insert(agent_id, policy);
     }
 
-    #[test]
-    fn test_spawn_agent() {
-        let (session, mut rx) = create_test_session();
-        let sub_id = SubmissionId::new();
-        
+    /// Failure policy of the subtree working on a task
+    ///
+    /// The policy set for the agent responsible for the task or its nearest
+    /// ancestor wins; otherwise the policy of the responsible agent's role.
+    pub fn failure_policy(&self, task_id: &TaskId) -> FailurePolicy {
+        let Some(agent) = self.responsible_agent(task_id).and_then(|id| self.get_agent(&id)) else {
+            return self.options.failure_propagation.default;
+        };
+        let mut lineage = vec![agent.id()];
+        lineage.extend(self.hierarchy.read().ancestors(&agent.id()));
+        let overrides = self.subtree_policies.read();
+        lineage
+            .iter()
+            .find_map(|id| overrides.get(id).copied())
+            .unwrap_or_else(|| self.options.failure_propagation.policy_for(&agent.role))
+    }
+
+    /// Apply the failure policy of a failed subtask's subtree
+    fn propagate_failure(&self, result: &TaskResult, sub_id: &SubmissionId) {
+        let task_id = result.task_id;
+        let (parent_task, unfinished) = {
+            let plans = self.plans.read();
+            let Some(plan) = plans.values().find(|p| p.task_id != task_id && p.get(&task_id).is_some()) else {
+                return;
+            };
+            let parent = plan.get(&task_id).and_then(|t| t.parent);
+            let siblings: Vec<TaskId> = match parent {
+                Some(parent) => plan.children_of(&parent).map(|t| t.id).collect(),
+                None => plan.top_level().map(|t| t.id).collect(),
+            };
+            // Unfinished siblings and everything planned below them
+            let mut unfinished = Vec::new();
+            let mut pending = siblings;
+            while let Some(id) = pending.pop() {
+                pending.extend(plan.children_of(&id).map(|t| t.id));
+                if plan.get(&id).is_some_and(|t| matches!(t.status, PlanStatus::Pending | PlanStatus::Running)) {
+                    unfinished.push(id);
+                }
+            }
+            (parent.unwrap_or(plan.task_id), unfinished)
+        };
+
+        let policy = self.failure_policy(&parent_task);
+        let mut cancelled = Vec::new();
+        match policy {
+            FailurePolicy::FailFast => {
+                for id in unfinished {
+                    self.scheduler.write().remove(&id);
+                    self.set_plan_status(&id, PlanStatus::Cancelled);
+                    self.clear_deadline(&id);
+                    self.results.write().insert(
+                        id,
+                        TaskResult::new(id, ResultStatus::Cancelled, format!("Cancelled after task {} failed", task_id)),
+                    );
+                    cancelled.push(id);
+                }
+                let interrupted = self
+                    .agents
+                    .read()
+                    .values()
+                    .filter(|a| a.current_task().is_some_and(|t| cancelled.contains(&t)))
+                    .filter(|a| a.interrupt().is_ok())
+                    .count();
+                info!(session_id = %self.id, task_id = %task_id, cancelled = cancelled.len(), interrupted, "Failing subtree fast");
+            }
+            FailurePolicy::Quarantine => {
+                if let Some(agent) = result.agent_id.and_then(|id| self.get_agent(&id)) {
+                    if agent.quarantine() {
+                        warn!(session_id = %self.id, agent_id = %agent.id(), task_id = %task_id, "Quarantined failed agent");
+                    }
+                }
+            }
+            FailurePolicy::Continue => {}
+        }
+        self.emit(GoblinEvent::FailurePropagated {
+            sub_id: sub_id.clone(),
+            task_id,
+            agent_id: result.agent_id,
+            parent_task,
+            policy,
+            cancelled,
+        });
+    }
+
+    /// Nearest domain lead above an agent, or its parent if it has none
+    fn lead_of(&self, agent: &AgentHandle) -> Option<AgentHandle> {
+        let parent = self.get_agent(&agent.parent_id()?)?;
+        let mut ancestor = Some(parent.clone());
+        while let Some(candidate) = ancestor {
+            if matches!(candidate.role, warhorn::AgentRole::DomainLead { .. }) {
+                return Some(candidate);
+            }
+            ancestor = candidate.parent_id().and_then(|id| self.get_agent(&id));
+        }
+        Some(parent)
+    }
+
+    /// Results reported by an agent
+    fn results_of(&self, agent_id: &AgentId) -> Vec<TaskResult> {
+        self.results
+            .read()
+            .values()
+            .filter(|r| r.agent_id == Some(*agent_id))
+            .cloned()
+            .collect()
+    }
+
+    /// Remove and terminate an agent and its descendants, capturing its handoff
+    fn remove_agent(
+        &self,
+        agent_id: &AgentId,
+        reason: String,
+        sub_id: &SubmissionId,
+    ) -> Result<Option<Handoff>, GoblinError> {
+        let agent = self.get_agent(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+
+        // Detach the whole subtree at once, then take its agents out of the registry
+        let mut subtree = self.hierarchy.write().remove_subtree(agent_id);
+        if subtree.is_empty() {
+            subtree.push(*agent_id);
+        }
+        let removed: Vec<AgentHandle> = {
+            let mut agents = self.agents.write();
+            subtree.iter().filter_map(|id| agents.remove(id)).collect()
+        };
+        {
+            let mut buckets = self.spawn_buckets.lock();
+            for id in &subtree {
+                buckets.parents.remove(id);
+            }
+        }
+
+        // Remove from parent's children and cancel the agent's run loop
+        let run = match agent.parent_id() {
+            Some(pid) => self.agents.read().get(&pid).and_then(|parent| {
+                parent.remove_child(agent_id);
+                parent.scope().cancel(agent_id)
+            }),
+            None => self.root_scope.cancel(agent_id),
+        };
+        let handoff = Handoff::capture(&agent, self.results_of(agent_id), reason.as_str());
+
+        // Stop every run loop in the subtree
+        {
+            let mut draining = self.draining.lock();
+            draining.extend(run);
+            for removed in &removed {
+                draining.extend(removed.scope().cancel_all());
+            }
+        }
+
+        // Terminate descendants before the agent itself
+        for removed in removed.iter().rev() {
+            // Keep the agent's spend in the session totals
+            {
+                let usage = removed.usage();
+                let mut retired = self.retired_usage.lock();
+                retired.input_tokens += usage.input_tokens;
+                retired.output_tokens += usage.output_tokens;
+                retired.total_tokens += usage.total_tokens;
+            }
+
+            let reason = if removed.id() == *agent_id {
+                reason.clone()
+            } else {
+                "Parent terminated".to_string()
+            };
+            removed.terminate(sub_id, reason);
+            Counters::incr(&self.counters.agents_terminated);
+
+            info!(
+                session_id = %self.id,
+                agent_id = %removed.id(),
+                "Terminated agent"
+            );
+        }
+        self.emit(GoblinEvent::TreeDelta {
+            sub_id: sub_id.clone(),
+            added: Vec::new(),
+            removed: removed.iter().map(|a| a.id()).collect(),
+            status_changes: Vec::new(),
+        });
+
+        Ok(handoff)
+    }
+
+    /// Start an agent's run loop in its parent's scope
+    ///
+    /// The loop is cancelled when the agent or any of its ancestors is
+    /// terminated, and its outcome is reported as an `AgentJoined` event.
+    pub fn start_agent<F>(&self, agent_id: &AgentId, sub_id: &SubmissionId, run: F) -> Result<(), GoblinError>
+    where
+        F: Future<Output = Result<(), GoblinError>> + Send + 'static,
+    {
+        let agents = self.agents.read();
+        let agent = agents.get(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+        let scope = match agent.parent_id() {
+            Some(pid) => agents.get(&pid).ok_or(GoblinError::AgentNotFound(pid))?.scope(),
+            None => &self.root_scope,
+        };
+        scope.spawn(*agent_id, sub_id, self.event_tx.clone(), run)
+    }
+
+    /// Interrupt every agent working on a task or one of its subtasks
+    pub fn interrupt_agents(&self, task_id: &TaskId) -> usize {
+        self.task_agents(task_id)
+            .iter()
+            .filter_map(|id| self.get_agent(id))
+            .filter(|a| a.interrupt().is_ok())
+            .count()
+    }
+
+    /// Check if an agent is somewhere below another, e.g. under a lead
+    pub fn is_under(&self, agent_id: &AgentId, ancestor: &AgentId) -> bool {
+        self.hierarchy.read().is_descendant_of(agent_id, ancestor)
+    }
+
+    /// Interrupt an agent and every agent below it
+    pub fn interrupt_subtree(&self, agent_id: &AgentId) -> usize {
+        let mut subtree = vec![*agent_id];
+        subtree.extend(self.hierarchy.read().descendants(agent_id));
+        subtree
+            .iter()
+            .filter_map(|id| self.get_agent(id))
+            .filter(|a| a.current_task().is_some() && a.interrupt().is_ok())
+            .count()
+    }
+
+    /// Token usage of an agent and every agent below it
+    pub fn subtree_usage(&self, agent_id: &AgentId) -> TokenUsage {
+        self.subtree_summary(agent_id).map(|s| s.usage).unwrap_or_default()
+    }
+
+    /// Await the run loops cancelled by terminations
+    ///
+    /// Returns the number of loops awaited.
+    pub async fn join_terminated(&self) -> usize {
+        let loops = std::mem::take(&mut *self.draining.lock());
+        let count = loops.len();
+        for run in loops {
+            let agent_id = run.agent_id;
+            if let Some(outcome) = run.join(&self.event_tx).await {
+                debug!(session_id = %self.id, agent_id = %agent_id, outcome = ?outcome, "Joined agent run loop");
+            }
+        }
+        count
+    }
+
+    /// Capture the session's state for a restart
+    pub fn to_checkpoint(&self) -> SessionCheckpoint {
+        let order: Vec<AgentId> = self.hierarchy.read().iter_dfs().map(|(id, _, _)| id).collect();
+        let agents = {
+            let agents = self.agents.read();
+            order.iter().filter_map(|id| agents.get(id)).map(AgentCheckpoint::capture).collect()
+        };
+
+        SessionCheckpoint {
+            version: CHECKPOINT_VERSION,
+            saved_at: chrono::Utc::now(),
+            session_id: self.id,
+            config: self.config(),
+            options: SessionOptions {
+                approval: self.approvals.policy(),
+                ..self.options.clone()
+            },
+            tasks: self.active_tasks(),
+            agents,
+            plans: self.plans.read().values().cloned().collect(),
+            results: self.results.read().values().cloned().collect(),
+            retired_usage: self.retired_usage.lock().clone(),
+        }
+    }
+
+    /// Bring back the agents, plans and results saved in a checkpoint
+    ///
+    /// Agents keep their ids; no `AgentSpawned` events are emitted. Run
+    /// loops are not started, see [`SessionHandle::resume_agents`].
+    pub fn restore(&self, checkpoint: &SessionCheckpoint) -> Result<(), GoblinError> {
+        for saved in &checkpoint.agents {
+            let agent = self.register_agent(
+                saved.config.clone(),
+                saved.parent_id,
+                Some(saved.agent_id),
+                saved.tool_scope.clone(),
+            )?;
+            agent.restore_history(saved.history.clone());
+            agent.add_usage(saved.usage.input_tokens, saved.usage.output_tokens);
+            if let Some(task_id) = saved.current_task {
+                agent.assign_task(task_id);
+            }
+            agent.checkpoint(saved.assignment.clone());
+            if saved.paused {
+                agent.pause();
+            }
+        }
+        self.hierarchy.read().validate()?;
+
+        self.plans.write().extend(checkpoint.plans.iter().map(|p| (p.task_id, p.clone())));
+        self.results.write().extend(checkpoint.results.iter().map(|r| (r.task_id, r.clone())));
+        *self.retired_usage.lock() = checkpoint.retired_usage.clone();
+        let mut tasks = self.tasks.write();
+        for task in &checkpoint.tasks {
+            tasks.start(task.clone());
+        }
+        drop(tasks);
+
+        info!(session_id = %self.id, agents = checkpoint.agents.len(), "Restored session from checkpoint");
+        Ok(())
+    }
+
+    /// Save the session's state to a checkpoint file
+    pub fn checkpoint(&self, path: impl AsRef<std::path::Path>) -> Result<(), GoblinError> {
+        self.to_checkpoint().save(path)?;
+        debug!(session_id = %self.id, "Saved session checkpoint");
+        Ok(())
+    }
+
+    /// Pack the finished session into a portable bundle
+    ///
+    /// Fails while a task is still in progress.
+    pub fn to_bundle(&self) -> Result<SessionBundle, GoblinError> {
+        if let Some(task) = self.active_tasks().first() {
+            return Err(GoblinError::InvalidState(format!(
+                "Session {} still has task {} in progress",
+                self.id, task.task_id
+            )));
+        }
+        Ok(SessionBundle {
+            version: BUNDLE_VERSION,
+            exported_at: chrono::Utc::now(),
+            hierarchy: self.hierarchy_snapshot(),
+            usage: self.total_usage(),
+            session: self.to_checkpoint(),
+        })
+    }
+
+    /// Export the finished session to a bundle file
+    pub fn export(&self, path: impl AsRef<std::path::Path>) -> Result<(), GoblinError> {
+        self.to_bundle()?.save(path)?;
+        info!(session_id = %self.id, "Exported session bundle");
+        Ok(())
+    }
+
+    /// Get the hierarchy tree, or None if no root agent has been spawned
+    ///
+    /// Agents with children carry the [`SubtreeSummary`] of their subtree
+    /// as their `task_summary`.
+    pub fn hierarchy(&self) -> Option<warhorn::AgentTree> {
+        let mut tree = self.hierarchy.read().to_tree(&self.agents.read())?;
+        annotate_tree(&mut tree, &self.agent_tallies());
+        Some(tree)
+    }
+
+    /// Aggregate status, results and token usage of an agent and every agent below it
+    pub fn subtree_summary(&self, agent_id: &AgentId) -> Option<SubtreeSummary> {
+        self.get_agent(agent_id)?;
+        let mut subtree = vec![*agent_id];
+        subtree.extend(self.hierarchy.read().descendants(agent_id));
+
+        let tallies = self.agent_tallies();
+        let mut summary = SubtreeSummary::default();
+        for tally in subtree.iter().filter_map(|id| tallies.get(id)) {
+            summary.merge(tally);
+        }
+        Some(summary)
+    }
+
+    /// Summary of each agent on its own, with the results it reported
+    fn agent_tallies(&self) -> HashMap<AgentId, SubtreeSummary> {
+        let mut tallies: HashMap<AgentId, SubtreeSummary> = self
+            .agents
+            .read()
+            .iter()
+            .map(|(id, agent)| (*id, SubtreeSummary::of_agent(agent)))
+            .collect();
+        for result in self.results.read().values() {
+            if let Some(tally) = result.agent_id.and_then(|id| tallies.get_mut(&id)) {
+                tally.count_result(result.status);
+            }
+        }
+        tallies
+    }
+
+    /// Counters, token usage and queue depths of the session
+    pub fn metrics(&self) -> SessionMetrics {
+        let mut metrics = SessionMetrics {
+            agents_active: self.agent_count(),
+            tasks_active: self.tasks.read().len(),
+            usage: self.total_usage(),
+            cost_usd: self.ledger.session_cost(),
+            task_costs: self.ledger.task_costs(),
+            wall_time_ms: self.created.elapsed().as_millis() as u64,
+            queued_subtasks: self.scheduler.read().len(),
+            model_slots_in_use: self.model_slots.total_in_use(),
+            model_queue: self.model_slots.stats(),
+            tool_slots_in_use: self.tool_slots.pool().total_in_use(),
+            ..Default::default()
+        };
+        self.counters.fill(&mut metrics);
+        metrics
+    }
+
+    /// Flat, serializable view of the hierarchy with agent statuses
+    pub fn hierarchy_snapshot(&self) -> HierarchySnapshot {
+        self.hierarchy.read().snapshot(&self.agents.read())
+    }
+
+    /// Render the hierarchy as a Graphviz DOT digraph
+    pub fn hierarchy_dot(&self) -> String {
+        self.hierarchy.read().to_dot(&self.agents.read())
+    }
+
+    /// Render the hierarchy as a Mermaid flowchart
+    pub fn hierarchy_mermaid(&self) -> String {
+        self.hierarchy.read().to_mermaid(&self.agents.read())
+    }
+
+    /// Register a submitted task as active
+    pub fn start_task(&self, task_id: TaskId, prompt: impl Into<String>) {
+        self.tasks.write().start(ActiveTask::new(task_id, prompt));
+        Counters::incr(&self.counters.tasks_started);
+    }
+
+    /// Unregister a task that finished or was interrupted
+    pub fn finish_task(&self, task_id: &TaskId) -> Option<ActiveTask> {
+        self.tasks.write().finish(task_id)
+    }
+
+    /// Tasks in progress, oldest first
+    pub fn active_tasks(&self) -> Vec<ActiveTask> {
+        self.tasks.read().list()
+    }
+
+    /// Check if a task is in progress
+    pub fn is_task_active(&self, task_id: &TaskId) -> bool {
+        self.tasks.read().contains(task_id)
+    }
+
+    /// Agents working on a task or on one of the subtasks of its plan
+    pub fn task_agents(&self, task_id: &TaskId) -> Vec<AgentId> {
+        let subtasks: Vec<TaskId> = self
+            .plans
+            .read()
+            .get(task_id)
+            .map(|p| p.tasks().iter().map(|t| t.id).collect())
+            .unwrap_or_default();
+
+        self.agents
+            .read()
+            .values()
+            .filter(|a| a.current_task().is_some_and(|t| t == *task_id || subtasks.contains(&t)))
+            .map(|a| a.id())
+            .collect()
+    }
+
+    /// Current session configuration
+    pub fn config(&self) -> SessionConfig {
+        self.config.read().clone()
+    }
+
+    /// Change the configuration of the running session
+    ///
+    /// A new model is used by agents spawned afterwards. A new agent limit
+    /// applies to the root at once and is refused if the root already has
+    /// more children than it allows. A new approval policy decides the
+    /// commands requested afterwards. A refused patch changes nothing.
+    pub fn update_config(&self, patch: &SessionConfigPatch, sub_id: &SubmissionId) -> Result<SessionConfig, GoblinError> {
+        patch.validate()?;
+        let root = self.orchestrator();
+        if let (Some(max), Some(root)) = (patch.max_parallel_agents, &root) {
+            let children = root.children().len();
+            if children > max {
+                return Err(GoblinError::ConfigError(format!(
+                    "Root agent already has {} children, more than max_parallel_agents {}",
+                    children, max
+                )));
+            }
+        }
+
+        let config = {
+            let mut config = self.config.write();
+            patch.apply(&mut config);
+            config.clone()
+        };
+        if let (Some(max), Some(root)) = (patch.max_parallel_agents, &root) {
+            root.set_max_children(Some(max));
+            self.hierarchy.write().set_max_children(&root.id(), Some(max));
+        }
+        if let Some(policy) = &patch.approval {
+            self.approvals.set_policy(policy.clone());
+        }
+
+        info!(session_id = %self.id, patch = ?patch, "Session configuration updated");
+        let _ = self.event_tx.send(GoblinEvent::SessionConfigChanged {
+            sub_id: sub_id.clone(),
+            session_id: self.id,
+            config: config.clone(),
+            patch: patch.clone(),
+        });
+        Ok(config)
+    }
+
+
+    /// Queue a subtask for dispatch to an idle worker
+    ///
+    /// Returns the lower-priority subtask that was preempted to make room,
+    /// if any. Preempted subtasks stay pending in their plan and are
+    /// requeued once the queue has room.
+    pub fn queue_task(&self, task_id: TaskId, priority: Priority) -> Result<Option<TaskId>, GoblinError> {
+        match self.scheduler.write().enqueue(task_id, priority) {
+            Enqueued::Queued => Ok(None),
+            Enqueued::Preempted(victim) => {
+                debug!(session_id = %self.id, task_id = %victim, "Preempted queued task");
+                Ok(Some(victim))
+            }
+            Enqueued::Rejected => Err(GoblinError::QueueFull(format!(
+                "Task queue is full; cannot queue {} at {:?} priority",
+                task_id, priority
+            ))),
+        }
+    }
+
+    /// Queue every pending unit of work in a task's plan at its planned priority
+    pub fn queue_plan(&self, task_id: &TaskId) -> Result<usize, GoblinError> {
+        let plan = self.plan(task_id).ok_or_else(|| {
+            GoblinError::InvalidState(format!("No plan for task {}", task_id))
+        })?;
+
+        let mut queued = 0;
+        for task in plan.leaves().filter(|t| t.status == PlanStatus::Pending) {
+            self.queue_task(task.id, task.priority)?;
+            queued += 1;
+        }
+        Ok(queued)
+    }
+
+    /// Assign queued subtasks to idle workers, highest priority first
+    pub fn dispatch_queued(&self) -> Vec<(TaskId, AgentId)> {
+        let mut idle = self.idle_workers().into_iter();
+        let mut scheduler = self.scheduler.write();
+        let mut assigned = Vec::new();
+
+        while !scheduler.is_empty() {
+            let Some(agent) = idle.next() else { break };
+            let Some((task_id, _)) = scheduler.next() else { break };
+
+            agent.assign_task(task_id);
+            assigned.push((task_id, agent.id()));
+        }
+        drop(scheduler);
+
+        for (task_id, agent_id) in &assigned {
+            self.set_plan_status(task_id, PlanStatus::Running);
+            debug!(session_id = %self.id, task_id = %task_id, agent_id = %agent_id, "Dispatched task");
+        }
+        assigned
+    }
+
+    /// Workers with no task assigned
+    pub fn idle_workers(&self) -> Vec<AgentHandle> {
+        self.agents
+            .read()
+            .values()
+            .filter(|a| !a.config.can_spawn && a.is_idle() && !a.is_quarantined())
+            .cloned()
+            .collect()
+    }
+
+    /// Subtasks waiting for a worker, in dispatch order
+    pub fn queued_tasks(&self) -> Vec<(TaskId, Priority)> {
+        self.scheduler.read().queued()
+    }
+
+    /// Remove a subtask from the queue before it is dispatched
+    pub fn cancel_queued(&self, task_id: &TaskId) -> bool {
+        let removed = self.scheduler.write().remove(task_id);
+        if removed {
+            self.set_plan_status(task_id, PlanStatus::Cancelled);
+        }
+        removed
+    }
+
+    /// Set a task's share of model and tool slots relative to other tasks
+    pub fn set_task_weight(&self, task_id: TaskId, weight: u32) {
+        self.model_slots.set_weight(task_id, weight);
+        self.tool_slots.pool().set_weight(task_id, weight);
+    }
+
+    /// Weight of a task
+    pub fn task_weight(&self, task_id: &TaskId) -> u32 {
+        self.model_slots.weight(task_id)
+    }
+
+    /// Wait for a model call slot on behalf of a task or one of its subtasks
+    pub async fn acquire_model_slot(&self, task_id: &TaskId) -> SlotPermit {
+        self.model_slots.acquire_shared(task_id).await
+    }
+
+    /// Wait for a tool execution slot on behalf of a task or one of its subtasks
+    ///
+    /// Agents take these slots for every tool call they run.
+    pub async fn acquire_tool_slot(&self, task_id: &TaskId) -> SlotPermit {
+        self.tool_slots.acquire(Some(*task_id)).await
+    }
+
+    /// Watch a task assigned to an agent for a deadline
+    ///
+    /// Uses the session's default action unless `action` overrides it. The
+    /// timer itself is driven by [`SessionHandle::assign_task_with_deadline`].
+    pub fn set_deadline(
+        &self,
+        task_id: TaskId,
+        agent_id: AgentId,
+        limit: Duration,
+        action: Option<DeadlineAction>,
+    ) {
+        self.deadlines.write().insert(
+            task_id,
+            Deadline {
+                agent_id,
+                started: Instant::now(),
+                limit,
+                action: action.unwrap_or(self.options.deadline_action),
+            },
+        );
+    }
+
+    /// Stop watching a task's deadline, e.g. because it finished
+    pub fn clear_deadline(&self, task_id: &TaskId) -> bool {
+        self.deadlines.write().clear(task_id).is_some()
+    }
+
+    /// Apply the deadline action if the task's deadline has passed
+    ///
+    /// Returns the action applied, or None if the deadline was cleared, has
+    /// not expired, or the agent already moved on from the task.
+    pub fn check_deadline(&self, task_id: &TaskId, sub_id: &SubmissionId) -> Option<DeadlineAction> {
+        let deadline = self.deadlines.write().take_expired(task_id, Instant::now())?;
+        let agent = self.get_agent(&deadline.agent_id)?;
+        if agent.current_task() != Some(*task_id) {
+            return None;
+        }
+
+        warn!(
+            session_id = %self.id,
+            task_id = %task_id,
+            agent_id = %deadline.agent_id,
+            action = ?deadline.action,
+            "Task deadline exceeded"
+        );
+
+        let _ = self.event_tx.send(GoblinEvent::TaskDeadlineExceeded {
+            sub_id: sub_id.clone(),
+            task_id: *task_id,
+            agent_id: deadline.agent_id,
+            deadline_ms: deadline.limit.as_millis() as u64,
+            action: deadline.action,
+        });
+
+        match deadline.action {
+            DeadlineAction::Warn => {}
+            DeadlineAction::Interrupt => {
+                agent.clear_task();
+                self.set_plan_status(task_id, PlanStatus::Failed);
+                let _ = self.event_tx.send(Event::TaskInterrupted {
+                    sub_id: sub_id.clone(),
+                    task_id: *task_id,
+                }.into());
+            }
+            DeadlineAction::Escalate => {
+                if let Some(parent) = agent.parent_id().and_then(|pid| self.get_agent(&pid)) {
+                    parent.emit_message(
+                        sub_id,
+                        format!(
+                            "Agent {} missed the {:?} deadline for task {}",
+                            agent.id(), deadline.limit, task_id
+                        ),
+                        false,
+                    );
+                }
+            }
+        }
+
+        Some(deadline.action)
+    }
+
+    /// Record that a subtask was handed to another session
+    pub fn mark_delegated(&self, task_id: &TaskId) {
+        self.set_plan_status(task_id, PlanStatus::Running);
+    }
+
+    /// Accept the outcome of a subtask delegated to another session
+    ///
+    /// The outcome is handled like a local worker's: the plan is updated and
+    /// the orchestrator agent reports it.
+    pub fn receive_delegated_result(
+        &self,
+        task_id: TaskId,
+        outcome: DelegationOutcome,
+        sub_id: &SubmissionId,
+    ) {
+        let status = if outcome.success { PlanStatus::Completed } else { PlanStatus::Failed };
+        self.set_plan_status(&task_id, status);
+
+        if let Some(orchestrator) = self.orchestrator() {
+            let verb = if outcome.success { "completed" } else { "failed" };
+            orchestrator.emit_message(
+                sub_id,
+                format!("Subtask {} {}: {}", task_id, verb, outcome.summary),
+                false,
+            );
+        }
+
+        info!(
+            session_id = %self.id,
+            task_id = %task_id,
+            from_session = %outcome.from_session,
+            success = outcome.success,
+            "Received delegated result"
+        );
+        self.delegated.write().insert(task_id, outcome);
+    }
+
+    /// Outcome of a delegated subtask, once reported
+    pub fn delegated_result(&self, task_id: &TaskId) -> Option<DelegationOutcome> {
+        self.delegated.read().get(task_id).cloned()
+    }
+
+    /// Record the result of a subtask and merge upward where possible
+    ///
+    /// Once every sibling of the subtask has finished, their results are
+    /// merged into the parent's result and reported to the agent
+    /// responsible for the parent. This repeats up the plan; when the
+    /// top-level subtasks are all done, the merged result of the whole task
+    /// is returned.
+    pub async fn record_result(
+        &self,
+        result: TaskResult,
+        sub_id: &SubmissionId,
+    ) -> Result<Option<TaskResult>, GoblinError> {
+        let mut result = result;
+        let cancelled = self
+            .plans
+            .read()
+            .values()
+            .find_map(|p| p.get(&result.task_id))
+            .is_some_and(|t| t.status == PlanStatus::Cancelled);
+        if cancelled {
+            debug!(session_id = %self.id, task_id = %result.task_id, "Dropping result of cancelled task");
+            return Ok(None);
+        }
+        if let Some(agent) = result.agent_id.and_then(|id| self.get_agent(&id)) {
+            if result.usage.total_tokens == 0 {
+                result.usage = agent.usage();
+            }
+        }
+        let Some(mut result) = self.review_result(result, sub_id).await? else {
+            return Ok(None);
+        };
+        self.offload_attachments(&mut result, sub_id);
+        if let Some(attempts) = self.attempts.lock().remove(&result.task_id) {
+            result.attempts = attempts;
+        }
+        if let Some(agent) = result.agent_id.and_then(|id| self.get_agent(&id)) {
+            self.track_failures(&agent, &result, sub_id);
+        }
+        if let Some(agent) = result.agent_id.and_then(|id| self.get_agent(&id)) {
+            self.role_history.write().record(&agent.role, result.usage.total_tokens);
+        }
+        let _ = self.event_tx.send(GoblinEvent::TaskResult {
+            sub_id: sub_id.clone(),
+            result: result.clone(),
+        });
+
+        // Whether the result in hand was merged, and not yet reported
+        let mut merged_up = false;
+        loop {
+            let task_id = result.task_id;
+            let status = if result.is_success() { PlanStatus::Completed } else { PlanStatus::Failed };
+            self.set_plan_status(&task_id, status);
+            self.clear_deadline(&task_id);
+            if result.status == ResultStatus::Failed {
+                self.propagate_failure(&result, sub_id);
+            }
+            if let Some(agent) = result.agent_id.and_then(|id| self.get_agent(&id)) {
+                if agent.current_task() == Some(task_id) {
+                    agent.clear_task();
+                }
+            }
+            self.results.write().insert(task_id, result);
+
+            if let Some(plan) = self.plans.read().get(&task_id) {
+                if plan.task_id == task_id {
+                    info!(session_id = %self.id, task_id = %task_id, "Task result complete");
+                    self.model_slots.remove_task(&task_id);
+                    self.tool_slots.pool().remove_task(&task_id);
+                    let result = self.results.read().get(&task_id).cloned();
+                    if let Some(result) = &result {
+                        if result.is_success() {
+                            Counters::incr(&self.counters.tasks_completed);
+                        } else {
+                            Counters::incr(&self.counters.tasks_failed);
+                        }
+                        if merged_up {
+                            let _ = self.event_tx.send(GoblinEvent::TaskResult {
+                                sub_id: sub_id.clone(),
+                                result: result.clone(),
+                            });
+                        }
+                    }
+                    return Ok(result);
+                }
+            }
+
+            // A task outside any plan is complete on its own
+            if !self.plans.read().values().any(|p| p.get(&task_id).is_some()) {
+                return Ok(self.results.read().get(&task_id).cloned());
+            }
+
+            let Some(request) = self.merge_request(&task_id)? else {
+                return Ok(None);
+            };
+            let recipient = self.responsible_agent(&request.task_id);
+            if let Some(parent_id) = recipient {
+                self.notify_children_completed(parent_id, &request, sub_id);
+            }
+
+            let ctx = MergeContext {
+                session: self,
+                parent: recipient,
+                sub_id,
+            };
+            let mut merged = self.merger.merge(&request, &ctx).await?;
+            merged.task_id = request.task_id;
+
+            debug!(
+                session_id = %self.id,
+                task_id = %request.task_id,
+                merger = self.merger.name(),
+                children = request.results.len(),
+                "Merged child results"
+            );
+            let _ = self.event_tx.send(GoblinEvent::ResultMerged {
+                sub_id: sub_id.clone(),
+                task_id: request.task_id,
+                agent_id: recipient,
+                result: merged.clone(),
+            });
+            result = merged;
+            merged_up = true;
+        }
+    }
+
+    /// Result recorded for a task or subtask
+    pub fn result(&self, task_id: &TaskId) -> Option<TaskResult> {
+        self.results.read().get(task_id).cloned()
+    }
+
+    /// Build the merge of a subtask's siblings, if they have all finished
+    fn merge_request(&self, task_id: &TaskId) -> Result<Option<MergeRequest>, GoblinError> {
+        let plans = self.plans.read();
+        let plan = plans.values().find(|p| p.get(task_id).is_some()).ok_or_else(|| {
+            GoblinError::InvalidState(format!("Task {} is not part of any plan", task_id))
+        })?;
+        let parent = plan.get(task_id).and_then(|t| t.parent);
+
+        let siblings: Vec<_> = match parent {
+            Some(parent) => plan.children_of(&parent).collect(),
+            None => plan.top_level().collect(),
+        };
+        let finished = |status: PlanStatus| {
+            matches!(status, PlanStatus::Completed | PlanStatus::Failed | PlanStatus::Cancelled)
+        };
+        if !siblings.iter().all(|t| finished(t.status)) {
+            return Ok(None);
+        }
+
+        let results = self.results.read();
+        let (target, description) = match parent.and_then(|p| plan.get(&p)) {
+            Some(parent) => (parent.id, parent.description.clone()),
+            None => (plan.task_id, plan.prompt.clone()),
+        };
+        Ok(Some(MergeRequest {
+            task_id: target,
+            description,
+            results: siblings.iter().filter_map(|t| results.get(&t.id).cloned()).collect(),
+        }))
+    }
+
+    /// Have a sibling reviewer check a worker's result
+    ///
+    /// Returns None if the result was bounced back to the worker, which
+    /// keeps the task. Once the bounces are used up the result is turned
+    /// into a failure.
+    async fn review_result(&self, result: TaskResult, sub_id: &SubmissionId) -> Result<Option<TaskResult>, GoblinError> {
+        let (Some(policy), Some(reviewer)) = (self.options.review, self.reviewer.as_ref()) else {
+            return Ok(Some(result));
+        };
+        let Some(worker) = result.agent_id.and_then(|id| self.get_agent(&id)) else {
+            return Ok(Some(result));
+        };
+        let task_id = result.task_id;
+        let description = {
+            let plans = self.plans.read();
+            let plan = plans.values().find(|p| p.task_id != task_id && p.get(&task_id).is_some());
+            match plan.and_then(|p| p.get(&task_id)) {
+                Some(task) => task.description.clone(),
+                None => return Ok(Some(result)),
+            }
+        };
+        if !result.is_success() {
+            return Ok(Some(result));
+        }
+
+        let defaults = self.config();
+        let config = AgentConfig {
+            role: warhorn::AgentRole::Specialist { specialty: "reviewer".into() },
+            model: defaults.model,
+            cwd: defaults.cwd,
+            can_spawn: false,
+            ..Default::default()
+        };
+        let parent = worker.parent_id().or_else(|| self.orchestrator().map(|o| o.id()));
+        let agent = self.spawn_agent(config, parent, sub_id)?;
+        agent.assign_task(task_id);
+        let review = reviewer.review(&agent, &description, &result).await;
+        let _ = self.terminate_agent(&agent.id(), "Review delivered".into(), sub_id);
+
+        let feedback = match review? {
+            Review::Approve => None,
+            Review::Bounce { feedback } => Some(feedback),
+        };
+        let bounces = {
+            let mut all = self.bounces.write();
+            let count = all.entry(task_id).or_insert(0);
+            if feedback.is_some() {
+                *count += 1;
+            }
+            *count
+        };
+        let _ = self.event_tx.send(GoblinEvent::ResultReviewed {
+            sub_id: sub_id.clone(),
+            task_id,
+            agent_id: worker.id(),
+            approved: feedback.is_none(),
+            feedback: feedback.clone(),
+            bounces,
+        });
+
+        match feedback {
+            None => Ok(Some(result)),
+            Some(feedback) if bounces <= policy.max_bounces => {
+                debug!(session_id = %self.id, task_id = %task_id, bounces, "Result bounced back to worker");
+                worker.deliver(Mail::ResultBounced { task_id, feedback });
+                Ok(None)
+            }
+            Some(feedback) => {
+                warn!(session_id = %self.id, task_id = %task_id, bounces, "Result rejected by reviewer");
+                let mut failed = result;
+                failed.status = ResultStatus::Failed;
+                failed.summary = format!("{}\n\nRejected by reviewer: {}", failed.summary, feedback);
+                Ok(Some(failed))
+            }
+        }
+    }
+
+    /// Tell a parent that all children of one of its tasks have finished
+    fn notify_children_completed(&self, parent_id: AgentId, request: &MergeRequest, sub_id: &SubmissionId) {
+        if let Some(parent) = self.get_agent(&parent_id) {
+            parent.deliver(Mail::ChildrenCompleted {
+                task_id: request.task_id,
+                results: request.results.clone(),
+            });
+        }
+        let _ = self.event_tx.send(GoblinEvent::ChildrenCompleted {
+            sub_id: sub_id.clone(),
+            parent_id,
+            task_id: request.task_id,
+            results: request.results.clone(),
+        });
+    }
+
+    /// Agent responsible for a task: its assignee, or the root for whole tasks
+    fn responsible_agent(&self, task_id: &TaskId) -> Option<AgentId> {
+        let assignee = self
+            .plans
+            .read()
+            .values()
+            .find_map(|p| p.get(task_id))
+            .and_then(|t| t.assignee);
+        assignee.or_else(|| self.orchestrator().map(|o| o.id()))
+    }
+
+    /// Update a subtask's status in whichever plan contains it
+    fn set_plan_status(&self, task_id: &TaskId, status: PlanStatus) {
+        for plan in self.plans.write().values_mut() {
+            if plan.set_status(task_id, status) {
+                break;
+            }
+        }
+    }
+
+    /// Start executing a workflow as a new task
+    pub fn start_workflow(
+        &self,
+        workflow: Workflow,
+        prompt: &str,
+        sub_id: &SubmissionId,
+    ) -> Result<TaskId, GoblinError> {
+        let task_id = TaskId::new();
+        let run = WorkflowRun::new(workflow, task_id, prompt)?;
+
+        info!(
+            session_id = %self.id,
+            task_id = %task_id,
+            workflow = %run.workflow.name,
+            "Starting workflow"
+        );
+
+        self.workflows.write().insert(task_id, run);
+        self.start_task(task_id, prompt);
+        self.advance_workflow(&task_id, sub_id)?;
+        Ok(task_id)
+    }
+
+    /// Spawn agents for every workflow stage that is ready to start
+    ///
+    /// Ready stages are claimed under the workflow lock and their agents
+    /// spawned after it is released. If any spawn fails, the agents already
+    /// spawned are terminated and the stages returned to be started again.
+    fn advance_workflow(&self, task_id: &TaskId, sub_id: &SubmissionId) -> Result<(), GoblinError> {
+        let root = self.orchestrator().ok_or(GoblinError::NoOrchestrator)?;
+        let stages = self.with_workflow(task_id, |run| {
+            let ready = run.ready_stages();
+            Ok(ready
+                .into_iter()
+                .map(|index| {
+                    run.mark_running(index, Vec::new());
+                    (index, run.stage(index).clone(), run.stage_subtasks(index).to_vec())
+                })
+                .collect::<Vec<_>>())
+        })?;
+
+        let defaults = self.config();
+        let mut spawned = Vec::new();
+        for (index, stage, subtasks) in &stages {
+            for subtask in subtasks {
+                let config = AgentConfig {
+                    role: stage.role.clone(),
+                    model: defaults.model.clone(),
+                    cwd: defaults.cwd.clone(),
+                    can_spawn: false,
+                    ..Default::default()
+                };
+                match self.spawn_agent(config, Some(root.id()), sub_id) {
+                    Ok(agent) => spawned.push((*index, agent, *subtask)),
+                    Err(e) => {
+                        for (_, agent, _) in spawned {
+                            let reason = format!("Workflow stage '{}' could not start", stage.name);
+                            let _ = self.terminate_agent(&agent.id(), reason, sub_id);
+                        }
+                        let _ = self.with_workflow(task_id, |run| {
+                            for (index, _, _) in &stages {
+                                run.unmark_running(*index);
+                            }
+                            Ok(())
+                        });
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        for (index, stage, _) in &stages {
+            let agents: Vec<_> = spawned
+                .iter()
+                .filter(|(i, _, _)| i == index)
+                .map(|(_, agent, subtask)| {
+                    agent.assign_task(*subtask);
+                    agent.id()
+                })
+                .collect();
+            debug!(task_id = %task_id, stage = %stage.name, agents = agents.len(), "Started workflow stage");
+            self.with_workflow(task_id, |run| {
+                run.mark_running(*index, agents);
+                Ok(())
+            })?;
+        }
+
+        if let Some(run) = self.workflow(task_id) {
+            self.plans.write().insert(*task_id, run.plan().clone());
+        }
+        Ok(())
+    }
+
+    /// Approve a gated workflow stage and start it
+    pub fn approve_workflow_stage(
+        &self,
+        task_id: &TaskId,
+        stage: &str,
+        sub_id: &SubmissionId,
+    ) -> Result<(), GoblinError> {
+        self.with_workflow(task_id, |run| run.approve(stage))?;
+        self.advance_workflow(task_id, sub_id)
+    }
+
+    /// Record a workflow stage outcome, release its agents, and start what it unblocked
+    pub fn finish_workflow_stage(
+        &self,
+        task_id: &TaskId,
+        stage: &str,
+        success: bool,
+        sub_id: &SubmissionId,
+    ) -> Result<(), GoblinError> {
+        let agents = self.with_workflow(task_id, |run| run.finish_stage(stage, success))?;
+        for agent_id in agents {
+            let _ = self.terminate_agent(&agent_id, format!("Workflow stage '{}' finished", stage), sub_id);
+        }
+
+        if self.with_workflow(task_id, |run| Ok(run.is_finished()))? {
+            info!(task_id = %task_id, success = success, "Workflow finished");
+            if let Some(run) = self.workflow(task_id) {
+                self.plans.write().insert(*task_id, run.plan().clone());
+            }
+            return Ok(());
+        }
+
+        self.advance_workflow(task_id, sub_id)
+    }
+
+    /// Get a snapshot of the workflow run for a task
+    pub fn workflow(&self, task_id: &TaskId) -> Option<WorkflowRun> {
+        self.workflows.read().get(task_id).cloned()
+    }
+
+    fn with_workflow<T>(
+        &self,
+        task_id: &TaskId,
+        f: impl FnOnce(&mut WorkflowRun) -> Result<T, GoblinError>,
+    ) -> Result<T, GoblinError> {
+        let mut workflows = self.workflows.write();
+        let run = workflows.get_mut(task_id).ok_or_else(|| {
+            GoblinError::InvalidState(format!("No workflow for task {}", task_id))
+        })?;
+        f(run)
+    }
+
+    /// Get the session artifact store
+    pub fn artifacts(&self) -> &ArtifactStore {
+        &self.artifacts
+    }
+
+    /// Register an artifact and report it to clients
+    pub fn register_artifact(&self, artifact: Artifact, sub_id: &SubmissionId) -> ArtifactId {
+        let info = artifact.info();
+        let id = self.artifacts.insert(artifact);
+        let _ = self.event_tx.send(GoblinEvent::ArtifactRegistered {
+            sub_id: sub_id.clone(),
+            artifact: info,
+        });
+        id
+    }
+
+    /// Move a result's large inline attachments into the artifact store
+    fn offload_attachments(&self, result: &mut TaskResult, sub_id: &SubmissionId) {
+        let (large, small): (Vec<_>, Vec<_>) = std::mem::take(&mut result.attachments)
+            .into_iter()
+            .partition(|a| a.inline_size() > INLINE_ATTACHMENT_LIMIT);
+        result.attachments = small;
+
+        for attachment in large {
+            let data = match attachment.read() {
+                Ok(data) => data,
+                Err(e) => {
+                    warn!(task_id = %result.task_id, error = %e, "Keeping attachment inline");
+                    result.attachments.push(attachment);
+                    continue;
+                }
+            };
+            let mut artifact = Artifact::new(attachment.name, attachment.media_type, data).with_task(result.task_id);
+            if let Some(agent_id) = result.agent_id {
+                artifact = artifact.with_producer(agent_id);
+            }
+            result.artifacts.push(self.register_artifact(artifact, sub_id));
+        }
+    }
+
+    /// Get the blackboard shared by the session's agents
+    pub fn store(&self) -> &SessionStore {
+        &self.store
+    }
+
+    /// Get the root orchestrator agent (if exists)
+    pub fn orchestrator(&self) -> Option<AgentHandle> {
+        self.hierarchy.read().root().and_then(|id| self.get_agent(&id))
+    }
+
+    /// Send an event to the session's client
+    pub(crate) fn emit(&self, event: impl Into<GoblinEvent>) {
+        let _ = self.event_tx.send(event.into());
+    }
+}
+
+/// Handle to a session for external interaction
+#[derive(Clone)]
+pub struct SessionHandle {
+    inner: Arc<Session>,
+}
+
+impl SessionHandle {
+    pub fn new(session: Session) -> Self {
+        Self {
+            inner: Arc::new(session),
+        }
+    }
+
+    pub fn id(&self) -> SessionId {
+        self.inner.id
+    }
+
+    /// Assign a task to an agent and enforce a deadline on it
+    ///
+    /// A timer fires after `limit`; if the agent is still on the task by
+    /// then, the deadline action is applied. Clearing the deadline or
+    /// reassigning the agent disarms the timer.
+    pub fn assign_task_with_deadline(
+        &self,
+        agent_id: &AgentId,
+        task_id: TaskId,
+        limit: Duration,
+        action: Option<DeadlineAction>,
+        sub_id: &SubmissionId,
+    ) -> Result<(), GoblinError> {
+        let agent = self.get_agent(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+        agent.assign_task(task_id);
+        self.set_deadline(task_id, *agent_id, limit, action);
+
+        let session = self.clone();
+        let sub_id = sub_id.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(limit).await;
+            session.check_deadline(&task_id, &sub_id);
+        });
+
+        Ok(())
+    }
+
+    /// Check agents' heartbeats and progress while the session lives
+    ///
+    /// Checks run at the heartbeat interval or a quarter of the stall time,
+    /// whichever is shorter. Does nothing if neither is watched.
+    pub fn start_watchdog(&self, sub_id: &SubmissionId) {
+        let heartbeat = self.heartbeat_policy().map(|policy| policy.interval());
+        let stall = self.options.stall.as_ref().map(|policy| policy.check_interval());
+        let Some(period) = heartbeat.into_iter().chain(stall).min() else { return };
+        let session = Arc::downgrade(&self.inner);
+        let sub_id = sub_id.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(period);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(session) = session.upgrade() else { break };
+                session.check_heartbeats(&sub_id);
+                session.check_stalls(&sub_id);
+            }
+        });
+    }
+
+    /// Run an agent as an actor reading its mailbox
+    ///
+    /// The actor loop is started in the parent's scope like any run loop,
+    /// with the runtime registered for the agent's role; instructions are
+    /// then sent through the agent's handle.
+    pub fn start_actor(&self, agent_id: &AgentId, sub_id: &SubmissionId) -> Result<(), GoblinError> {
+        let agent = self.get_agent(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+        let mailbox = agent.take_mailbox().ok_or_else(|| {
+            GoblinError::InvalidState(format!("Mailbox of agent {} is already being read", agent_id))
+        })?;
+        let runtime = self.runtimes.for_config(&agent.config);
+        let session = self.clone();
+        let sub = sub_id.clone();
+        let run = async move {
+            let run = actor::run(session.clone(), agent.clone(), runtime, mailbox, sub.clone());
+            let outcome = AssertUnwindSafe(run).catch_unwind().await;
+            let error = match &outcome {
+                Ok(Ok(())) => None,
+                Ok(Err(e)) => Some(e.to_string()),
+                Err(_) => Some("panicked".to_string()),
+            };
+            if let Some(error) = error {
+                if session.options.supervision.policy_for(&agent.role).is_some() {
+                    let (session, agent_id) = (session.clone(), agent.id());
+                    tokio::spawn(async move { session.supervise(agent_id, error, sub).await });
+                } else {
+                    // Save what the agent had learned before its loop died
+                    let reason = format!("run loop failed: {}", error);
+                    let handoff = Handoff::capture(&agent, session.results_of(&agent.id()), reason);
+                    if let (Some(handoff), Some(parent_id)) = (handoff, agent.parent_id()) {
+                        session.hand_off(handoff, &parent_id, &sub);
+                    }
+                    session.handle_orphans(&agent.id(), &sub);
+                }
+            }
+            outcome.unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        };
+        self.start_agent(agent_id, sub_id, run)
+    }
+
+    /// Start the actors of restored agents and hand them their tasks again
+    ///
+    /// The root agent is left without an actor, as when a session is
+    /// configured. Returns the tasks that were assigned again.
+    pub fn resume_agents(&self, sub_id: &SubmissionId) -> Result<Vec<TaskId>, GoblinError> {
+        let root = self.hierarchy.read().root();
+        let order: Vec<AgentId> = self.hierarchy.read().iter_dfs().map(|(id, _, _)| id).collect();
+
+        let mut resumed = Vec::new();
+        for agent_id in order.into_iter().filter(|id| Some(*id) != root) {
+            self.start_actor(&agent_id, sub_id)?;
+            let Some(agent) = self.get_agent(&agent_id) else { continue };
+            if let Some(assignment) = agent.last_checkpoint().and_then(|c| c.assignment) {
+                resumed.push(assignment.task_id);
+                agent.assign(assignment.task_id, assignment.instructions)?;
+            }
+        }
+        Ok(resumed)
+    }
+
+    /// Restart an agent's actor, keeping its identity
+    ///
+    /// The old run loop is stopped if it is still running. The agent keeps
+    /// its id and place in the hierarchy, its history is restored from the
+    /// last checkpoint and the task it was working on is assigned again.
+    /// No `AgentSpawned` event is emitted.
+    pub async fn restart_agent(&self, agent_id: &AgentId, sub_id: &SubmissionId) -> Result<(), GoblinError> {
+        let agent = self.get_agent(agent_id).ok_or(GoblinError::AgentNotFound(*agent_id))?;
+        let run = match agent.parent_id() {
+            Some(pid) => self.get_agent(&pid).and_then(|parent| parent.scope().cancel(agent_id)),
+            None => self.root_scope.cancel(agent_id),
+        };
+        if let Some(run) = run {
+            run.join(&self.event_tx).await;
+        }
+
+        let checkpoint = agent.reopen(sub_id);
+        self.start_actor(agent_id, sub_id)?;
+
+        let restored_entries = checkpoint.as_ref().map_or(agent.history_len(), |c| c.history.len());
+        let assignment = checkpoint.and_then(|c| c.assignment);
+        let resumed_task = assignment.as_ref().map(|a| a.task_id);
+        if let Some(assignment) = assignment {
+            agent.assign(assignment.task_id, assignment.instructions)?;
+        }
+
+        info!(session_id = %self.id, agent_id = %agent_id, restored_entries, "Restarted agent");
+        self.emit(GoblinEvent::AgentRestarted {
+            sub_id: sub_id.clone(),
+            agent_id: *agent_id,
+            restored_entries,
+            resumed_task,
+        });
+        Ok(())
+    }
+
+    /// Assign a failed task again if the retry policy allows another attempt
+    ///
+    /// The attempt is recorded and reported, and the task is assigned after
+    /// the policy's delay, to the same agent or a fresh one in its place.
+    /// Returns whether the task will be retried; if not, the result is
+    /// recorded as usual.
+    pub fn retry_task(
+        &self,
+        agent: &AgentHandle,
+        assignment: &TaskAssignment,
+        result: &TaskResult,
+        sub_id: &SubmissionId,
+    ) -> bool {
+        let Some(policy) = self.options.retry else { return false };
+        if result.status != ResultStatus::Failed {
+            return false;
+        }
+        let task_id = assignment.task_id;
+        let attempt = {
+            let mut attempts = self.attempts.lock();
+            let attempts = attempts.entry(task_id).or_default();
+            if attempts.len() as u32 + 1 >= policy.max_attempts {
+                return false;
+            }
+            attempts.push(TaskAttempt {
+                attempt: attempts.len() as u32 + 1,
+                agent_id: Some(agent.id()),
+                error: result.summary.clone(),
+                failed_at: chrono::Utc::now(),
+            });
+            attempts.len() as u32
+        };
+        *self.failures.lock().entry(agent.id()).or_default() += 1;
+
+        let delay = policy.delay(attempt);
+        warn!(session_id = %self.id, task_id = %task_id, attempt, "Retrying failed task");
+        self.emit(GoblinEvent::TaskRetried {
+            sub_id: sub_id.clone(),
+            task_id,
+            agent_id: agent.id(),
+            attempt: attempt + 1,
+            delay_ms: delay.as_millis() as u64,
+            error: result.summary.clone(),
+            fresh_agent: policy.fresh_agent,
+        });
+
+        let (session, agent, assignment) = (self.clone(), agent.clone(), assignment.clone());
+        let (result, sub_id) = (result.clone(), sub_id.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+            // An interrupt during the wait drops the task
+            if agent.current_task() != Some(task_id) {
+                session.attempts.lock().remove(&task_id);
+                return;
+            }
+            let retried = if policy.fresh_agent {
+                session.retry_on_fresh_agent(&agent, &assignment)
+            } else {
+                agent.assign(task_id, assignment.instructions)
+            };
+            if let Err(e) = retried {
+                warn!(session_id = %session.id, task_id = %task_id, error = %e, "Failed to retry task");
+                // Nobody is working on the task any more, so it fails
+                let mut failed = result;
+                failed.summary = format!("{}; retry failed: {}", failed.summary, e);
+                if let Err(e) = session.record_result(failed, &sub_id).await {
+                    warn!(session_id = %session.id, task_id = %task_id, error = %e, "Failed to record result");
+                }
+            }
+        });
+        true
+    }
+
+    fn retry_on_fresh_agent(&self, agent: &AgentHandle, assignment: &TaskAssignment) -> Result<(), GoblinError> {
+        let reason = format!("Task {} is retried on a fresh agent", assignment.task_id);
+        let replacement = self.replace_agent(&agent.id(), reason, &assignment.sub_id)?;
+        self.start_actor(&replacement.id(), &assignment.sub_id)?;
+        replacement.assign(assignment.task_id, assignment.instructions.clone())
+    }
+
+    /// Apply the supervision policy of an agent whose run loop failed
+    async fn supervise(&self, agent_id: AgentId, error: String, sub_id: SubmissionId) {
+        // Let the failed loop finish reporting before the agent is restarted
+        let run = match self.get_agent(&agent_id).and_then(|a| a.parent_id()) {
+            Some(pid) => self.get_agent(&pid).and_then(|parent| parent.scope().detach(&agent_id)),
+            None => self.root_scope.detach(&agent_id),
+        };
+        if let Some(run) = run {
+            run.join(&self.event_tx).await;
+        }
+
+        let mut failed = agent_id;
+        loop {
+            let Some(agent) = self.get_agent(&failed) else { return };
+            let Some(policy) = self.options.supervision.policy_for(&agent.role) else { return };
+            let parent_id = agent.parent_id();
+            drop(agent);
+
+            match policy {
+                SupervisionPolicy::OneForOne { max_restarts, backoff } => {
+                    let attempt = {
+                        let mut restarts = self.restarts.lock();
+                        let count = restarts.entry(failed).or_default();
+                        *count += 1;
+                        *count
+                    };
+                    if attempt > max_restarts {
+                        let reason = format!("run loop failed: {}; gave up after {} restarts", error, max_restarts);
+                        self.give_up(&failed, reason, &sub_id);
+                        return;
+                    }
+                    let delay = backoff.delay(attempt);
+                    warn!(session_id = %self.id, agent_id = %failed, attempt, error = %error, "Restarting failed agent");
+                    self.emit(GoblinEvent::AgentRestarting {
+                        sub_id: sub_id.clone(),
+                        agent_id: failed,
+                        attempt,
+                        delay_ms: delay.as_millis() as u64,
+                        error,
+                    });
+                    tokio::time::sleep(delay).await;
+                    if let Err(e) = self.restart_agent(&failed, &sub_id).await {
+                        warn!(session_id = %self.id, agent_id = %failed, error = %e, "Failed to restart agent");
+                    }
+                    return;
+                }
+                SupervisionPolicy::Escalate => {
+                    warn!(session_id = %self.id, agent_id = %failed, error = %error, "Escalating agent failure");
+                    self.emit(GoblinEvent::AgentFailureEscalated {
+                        sub_id: sub_id.clone(),
+                        agent_id: failed,
+                        parent_id,
+                        error: error.clone(),
+                    });
+                    self.give_up(&failed, format!("run loop failed: {}; escalated", error), &sub_id);
+                    match parent_id {
+                        Some(pid) => failed = pid,
+                        None => return,
+                    }
+                }
+                SupervisionPolicy::GiveUp => {
+                    self.give_up(&failed, format!("run loop failed: {}", error), &sub_id);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn give_up(&self, agent_id: &AgentId, reason: String, sub_id: &SubmissionId) {
+        self.restarts.lock().remove(agent_id);
+        self.handle_orphans(agent_id, sub_id);
+        if let Err(e) = self.terminate_agent(agent_id, reason, sub_id) {
+            warn!(session_id = %self.id, agent_id = %agent_id, error = %e, "Failed to terminate agent");
+        }
+    }
+}
+
+impl std::ops::Deref for SessionHandle {
+    type Target = Session;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+/// Spawn rate buckets of a session and of each parent
+#[derive(Debug, Default)]
+struct SpawnBuckets {
+    session: Option<TokenBucket>,
+    parents: HashMap<AgentId, TokenBucket>,
+}
+
+/// Set the subtree summary on every node with children, returning the node's summary
+fn annotate_tree(tree: &mut warhorn::AgentTree, tallies: &HashMap<AgentId, SubtreeSummary>) -> SubtreeSummary {
+    let mut summary = tallies.get(&tree.agent_id).cloned().unwrap_or_default();
+    for child in &mut tree.children {
+        summary.merge(&annotate_tree(child, tallies));
+    }
+    if !tree.children.is_empty() {
+        tree.task_summary = Some(summary.to_string());
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warhorn::{AgentRole, AgentStatus};
+
+    fn create_test_session() -> (Session, mpsc::UnboundedReceiver<GoblinEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let tools = Arc::new(ToolRegistry::new());
+        let config = SessionConfig::default();
+        (Session::new(config, tools, tx), rx)
+    }
+
+    #[test]
+    fn test_session_creation() {
+        let (session, _rx) = create_test_session();
+        assert_eq!(session.agent_count(), 0);
+    }
+
+    #[test]
+    fn test_spawn_agent() {
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        
         let config = AgentConfig {
             role: AgentRole::Orchestrator,
             ..Default::default()
         };
-        
-        let result = session.spawn_agent(config, None, &sub_id);
-        assert!(result.is_ok());
+        
+        assert!(session.hierarchy().is_none());
+        let result = session.spawn_agent(config, None, &sub_id);
+        assert!(result.is_ok());
+        assert_eq!(session.agent_count(), 1);
+        assert_eq!(session.hierarchy().map(|t| t.agent_id), Some(result.unwrap().id()));
+        
+        // Check event was emitted
+        let event = rx.try_recv();
+        assert!(matches!(event, Ok(GoblinEvent::Protocol(Event::AgentSpawned { .. }))));
+
+        // A second root is rejected
+        assert!(session.spawn_agent(AgentConfig::default(), None, &sub_id).is_err());
+        assert_eq!(session.agent_count(), 1);
+    }
+
+    #[test]
+    fn test_workflow_execution() {
+        use crate::workflow::{Gate, Stage, StageState};
+
+        let (session, _rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        session.spawn_agent(root, None, &sub_id).unwrap();
+
+        let workflow = Workflow::new("ship")
+            .stage(Stage::new("implement", AgentRole::Worker, "Write the code").with_agents(2))
+            .stage(Stage::new("review", AgentRole::Worker, "Review it").with_gate(Gate::Approval));
+
+        let task_id = session.start_workflow(workflow, "Ship it", &sub_id).unwrap();
+        assert_eq!(session.agent_count(), 3);
+        assert!(session.plan(&task_id).is_some());
+
+        session.finish_workflow_stage(&task_id, "implement", true, &sub_id).unwrap();
+        assert_eq!(session.agent_count(), 1);
+        let run = session.workflow(&task_id).unwrap();
+        assert_eq!(run.state("review"), Some(&StageState::AwaitingApproval));
+
+        session.approve_workflow_stage(&task_id, "review", &sub_id).unwrap();
+        assert_eq!(session.agent_count(), 2);
+
+        session.finish_workflow_stage(&task_id, "review", true, &sub_id).unwrap();
+        assert_eq!(session.agent_count(), 1);
+        assert!(session.workflow(&task_id).unwrap().is_successful());
+    }
+
+    #[test]
+    fn test_workflow_stage_that_cannot_start_releases_its_agents() {
+        use crate::config::SpawnLimits;
+        use crate::workflow::{Stage, StageState};
+
+        let limits = SpawnLimits {
+            max_agents: Some(2),
+            ..SpawnLimits::default()
+        };
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let options = SessionOptions::new().with_spawn_limits(limits);
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        session.spawn_agent(root, None, &sub_id).unwrap();
+
+        let workflow = Workflow::new("ship")
+            .stage(Stage::new("implement", AgentRole::Worker, "Write the code").with_agents(2));
+        let started = session.start_workflow(workflow, "Ship it", &sub_id);
+        assert!(matches!(started, Err(GoblinError::SpawnDenied(_))));
+        assert_eq!(session.agent_count(), 1);
+
+        let run = session.workflows.read().values().next().cloned().unwrap();
+        assert_eq!(run.state("implement"), Some(&StageState::Pending));
+    }
+
+    #[test]
+    fn test_priority_dispatch_to_idle_workers() {
+        let (session, _rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let worker = AgentConfig {
+            role: AgentRole::Worker,
+            can_spawn: false,
+            ..Default::default()
+        };
+        session.spawn_agent(worker, None, &sub_id).unwrap();
+
+        let low = TaskId::new();
+        let high = TaskId::new();
+        session.queue_task(low, Priority::Low).unwrap();
+        session.queue_task(high, Priority::High).unwrap();
+
+        // One idle worker takes the high-priority task
+        let assigned = session.dispatch_queued();
+        assert_eq!(assigned.len(), 1);
+        assert_eq!(assigned[0].0, high);
+        assert!(session.idle_workers().is_empty());
+        assert_eq!(session.queued_tasks(), vec![(low, Priority::Low)]);
+
+        // Freeing the worker lets the low-priority task through
+        session.get_agent(&assigned[0].1).unwrap().clear_task();
+        assert_eq!(session.dispatch_queued()[0].0, low);
+    }
+
+    #[test]
+    fn test_queue_plan_preempts_low_priority() {
+        use crate::plan::PlannedTask;
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(
+            SessionConfig::default(),
+            SessionOptions::new().with_max_queued_tasks(1),
+            Arc::new(ToolRegistry::new()),
+            tx,
+        );
+
+        let task_id = TaskId::new();
+        let mut plan = TaskPlan::new(task_id, "work");
+        let low = plan.add(PlannedTask::new("cleanup", AgentRole::Worker).with_priority(Priority::Low));
+        session.plans.write().insert(task_id, plan);
+        assert_eq!(session.queue_plan(&task_id).unwrap(), 1);
+
+        let urgent = TaskId::new();
+        assert_eq!(session.queue_task(urgent, Priority::Critical).unwrap(), Some(low));
+        assert_eq!(session.plan(&task_id).unwrap().get(&low).unwrap().status, PlanStatus::Pending);
+        assert!(session.queue_task(TaskId::new(), Priority::Low).is_err());
+        assert_eq!(session.queued_tasks(), vec![(urgent, Priority::Critical), (low, Priority::Low)]);
+    }
+
+    fn spawn_worker_under_root(session: &Session, sub_id: &SubmissionId) -> (AgentHandle, AgentHandle) {
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = session.spawn_agent(root, None, sub_id).unwrap();
+        let worker = AgentConfig {
+            role: AgentRole::Worker,
+            ..Default::default()
+        };
+        let worker = session.spawn_agent(worker, Some(root.id()), sub_id).unwrap();
+        (root, worker)
+    }
+
+    #[tokio::test]
+    async fn test_deadline_interrupts_task() {
+        let (session, mut rx) = create_test_session();
+        let session = SessionHandle::new(session);
+        let sub_id = SubmissionId::new();
+        let (_root, worker) = spawn_worker_under_root(&session, &sub_id);
+        while rx.try_recv().is_ok() {}
+
+        let task_id = TaskId::new();
+        session
+            .assign_task_with_deadline(
+                &worker.id(),
+                task_id,
+                Duration::from_millis(10),
+                Some(DeadlineAction::Interrupt),
+                &sub_id,
+            )
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert!(worker.current_task().is_none());
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(GoblinEvent::TaskDeadlineExceeded { action: DeadlineAction::Interrupt, .. })
+        ));
+        assert!(matches!(
+            rx.try_recv(),
+            Ok(GoblinEvent::Protocol(Event::TaskInterrupted { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cleared_deadline_does_not_fire() {
+        let (session, mut rx) = create_test_session();
+        let session = SessionHandle::new(session);
+        let sub_id = SubmissionId::new();
+        let (_root, worker) = spawn_worker_under_root(&session, &sub_id);
+        while rx.try_recv().is_ok() {}
+
+        let task_id = TaskId::new();
+        session
+            .assign_task_with_deadline(&worker.id(), task_id, Duration::from_millis(10), None, &sub_id)
+            .unwrap();
+        assert!(session.clear_deadline(&task_id));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        assert_eq!(worker.current_task(), Some(task_id));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_deadline_escalates_to_parent() {
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+        while rx.try_recv().is_ok() {}
+
+        let task_id = TaskId::new();
+        worker.assign_task(task_id);
+        session.set_deadline(task_id, worker.id(), Duration::ZERO, Some(DeadlineAction::Escalate));
+
+        assert_eq!(session.check_deadline(&task_id, &sub_id), Some(DeadlineAction::Escalate));
+        assert_eq!(worker.current_task(), Some(task_id));
+        assert!(matches!(rx.try_recv(), Ok(GoblinEvent::TaskDeadlineExceeded { .. })));
+        match rx.try_recv() {
+            Ok(GoblinEvent::AgentMessage { agent_id, streaming: false, .. }) => assert_eq!(agent_id, root.id()),
+            other => panic!("expected escalation message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_spawn_provisions_scratch_dir() {
+        use crate::workspace::ScratchConfig;
+
+        let root = tempfile::tempdir().unwrap();
+        let options = SessionOptions::new().with_scratch(ScratchConfig {
+            root: Some(root.path().to_path_buf()),
+            layout: vec!["tmp".into()],
+            keep: false,
+        });
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(
+            SessionConfig::default(),
+            options,
+            Arc::new(ToolRegistry::new()),
+            tx,
+        );
+        let sub_id = SubmissionId::new();
+
+        let agent = session
+            .spawn_agent(AgentConfig::default(), None, &sub_id)
+            .unwrap();
+        let path = agent.scratch_dir().unwrap();
+        assert!(path.join("tmp").is_dir());
+
+        session.terminate_agent(&agent.id(), "done".into(), &sub_id).unwrap();
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_result_of_a_whole_task_is_reported_once() {
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let task_id = TaskId::new();
+        session.plans.write().insert(task_id, TaskPlan::new(task_id, "Fix the build"));
+        while rx.try_recv().is_ok() {}
+
+        let done = session.record_result(TaskResult::success(task_id, "fixed"), &sub_id).await.unwrap();
+        assert_eq!(done.map(|r| r.task_id), Some(task_id));
+        let reported = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|e| matches!(e, GoblinEvent::TaskResult { .. }))
+            .count();
+        assert_eq!(reported, 1);
+    }
+
+    #[tokio::test]
+    async fn test_results_merge_up_the_plan() {
+        use crate::plan::PlannedTask;
+
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, _worker) = spawn_worker_under_root(&session, &sub_id);
+        while rx.try_recv().is_ok() {}
+
+        let task_id = TaskId::new();
+        let mut plan = TaskPlan::new(task_id, "Build an API");
+        let lead = plan.add(PlannedTask::new(
+            "Backend",
+            AgentRole::DomainLead { domain: "backend".into() },
+        ));
+        let routes = plan.add(PlannedTask::new("Routes", AgentRole::Worker).with_parent(lead));
+        let models = plan.add(PlannedTask::new("Models", AgentRole::Worker).with_parent(lead));
+        session.plans.write().insert(task_id, plan);
+
+        let first = session.record_result(TaskResult::success(routes, "routes done"), &sub_id).await;
+        assert_eq!(first.unwrap(), None);
+        assert!(matches!(rx.try_recv(), Ok(GoblinEvent::TaskResult { result, .. }) if result.task_id == routes));
+        assert!(rx.try_recv().is_err());
+
+        let done = session
+            .record_result(TaskResult::success(models, "models done"), &sub_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(done.task_id, task_id);
+        assert!(done.is_success());
+        assert_eq!(done.summary, "routes done\n\nmodels done");
+
+        assert!(matches!(rx.try_recv(), Ok(GoblinEvent::TaskResult { result, .. }) if result.task_id == models));
+        match rx.try_recv() {
+            Ok(GoblinEvent::ChildrenCompleted { parent_id, task_id: t, results, .. }) => {
+                assert_eq!((parent_id, t, results.len()), (root.id(), lead, 2));
+            }
+            other => panic!("expected children completed, got {:?}", other),
+        }
+        match rx.try_recv() {
+            Ok(GoblinEvent::ResultMerged { task_id: merged, agent_id, .. }) => {
+                assert_eq!(merged, lead);
+                assert_eq!(agent_id, Some(root.id()));
+            }
+            other => panic!("expected lead merge, got {:?}", other),
+        }
+        assert!(matches!(rx.try_recv(), Ok(GoblinEvent::ChildrenCompleted { task_id: t, .. }) if t == task_id));
+        assert!(matches!(rx.try_recv(), Ok(GoblinEvent::ResultMerged { task_id: t, .. }) if t == task_id));
+        assert!(matches!(rx.try_recv(), Ok(GoblinEvent::TaskResult { result, .. }) if result == done));
+        assert_eq!(session.plan(&task_id).unwrap().get(&lead).unwrap().status, PlanStatus::Completed);
+        assert_eq!(session.merger_name(), "concat");
+
+        // The root was told about both rounds
+        let mut mailbox = root.take_mailbox().unwrap();
+        assert!(matches!(mailbox.try_recv(), Ok(Mail::ChildrenCompleted { task_id: t, .. }) if t == lead));
+        assert!(matches!(mailbox.try_recv(), Ok(Mail::ChildrenCompleted { task_id: t, .. }) if t == task_id));
+    }
+
+    #[tokio::test]
+    async fn test_subtasks_share_their_task_slots() {
+        use crate::plan::PlannedTask;
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(
+            SessionConfig::default(),
+            SessionOptions::new().with_slots(2, 2),
+            Arc::new(ToolRegistry::new()),
+            tx,
+        );
+
+        let big = TaskId::new();
+        let mut plan = TaskPlan::new(big, "big");
+        let a = plan.add(PlannedTask::new("a", AgentRole::Worker));
+        let b = plan.add(PlannedTask::new("b", AgentRole::Worker));
+        session.plans.write().insert(big, plan);
+        session.set_task_weight(big, 2);
+
+        // Subtask slots are charged to the user task
+        let first = session.acquire_model_slot(&a).await;
+        let _second = session.acquire_model_slot(&b).await;
+        assert_eq!(first.task_id(), big);
+        assert_eq!(session.task_weight(&big), 2);
+
+        let small = TaskId::new();
+        drop(first);
+        assert_eq!(session.acquire_model_slot(&small).await.task_id(), small);
+    }
+
+    #[tokio::test]
+    async fn test_terminating_parent_cancels_descendant_loops() {
+        use crate::scope::JoinOutcome;
+
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let lead_config = AgentConfig {
+            role: AgentRole::DomainLead { domain: "code".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead_config, None, &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id).unwrap();
+
+        for agent in [&lead, &worker] {
+            session
+                .start_agent(&agent.id(), &sub_id, async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(())
+                })
+                .unwrap();
+        }
+        assert_eq!(lead.scope().running(), 1);
+
+        session.terminate_agent(&lead.id(), "done".into(), &sub_id).unwrap();
+        assert_eq!(session.join_terminated().await, 2);
+
+        let mut joined = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let GoblinEvent::AgentJoined { agent_id, outcome, .. } = event {
+                assert_eq!(outcome, JoinOutcome::Cancelled);
+                joined.push(agent_id);
+            }
+        }
+        joined.sort_by_key(|id| id.to_string());
+        let mut expected = vec![lead.id(), worker.id()];
+        expected.sort_by_key(|id| id.to_string());
+        assert_eq!(joined, expected);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_count_agents_and_tasks() {
+        let (session, _rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (_root, worker) = spawn_worker_under_root(&session, &sub_id);
+        worker.add_usage(100, 20);
+        session.terminate_agent(&worker.id(), "done".into(), &sub_id).unwrap();
+
+        let task_id = TaskId::new();
+        let mut plan = TaskPlan::new(task_id, "Fix the parser");
+        let step = plan.add(PlannedTask::new("Fix the lexer", AgentRole::Worker));
+        session.plans.write().insert(task_id, plan);
+        session.start_task(task_id, "Fix the parser");
+        session.start_task(TaskId::new(), "Write the docs");
+        session.record_result(TaskResult::failure(step, "lexer broken"), &sub_id).await.unwrap();
+        session.finish_task(&task_id);
+
+        let metrics = session.metrics();
+        assert_eq!((metrics.agents_spawned, metrics.agents_terminated, metrics.agents_active), (2, 1, 1));
+        assert_eq!((metrics.tasks_started, metrics.tasks_completed, metrics.tasks_failed), (2, 0, 1));
+        assert_eq!(metrics.tasks_active, 1);
+        assert_eq!(metrics.usage.total_tokens, 120);
+        assert_eq!(metrics.queued_subtasks, 0);
+    }
+
+    #[test]
+    fn test_terminated_agents_hand_off_context() {
+        use crate::history::HistoryEntry;
+        use crate::model::ChatRole;
+
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let lead_config = AgentConfig {
+            role: AgentRole::DomainLead { domain: "code".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead_config, None, &sub_id).unwrap();
+        let mut lead_mail = lead.take_mailbox().unwrap();
+
+        // A replacement inherits the context and the task
+        let task_id = TaskId::new();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id).unwrap();
+        worker.assign_task(task_id);
+        worker.record(HistoryEntry::message(ChatRole::Assistant, "The bug is in the parser"));
+        let replacement = session.replace_agent(&worker.id(), "stuck".into(), &sub_id).unwrap();
+        assert_ne!(replacement.id(), worker.id());
+        assert_eq!(replacement.current_task(), Some(task_id));
+        assert_eq!(lead.children(), vec![replacement.id()]);
+        match &replacement.history().entries()[..] {
+            [HistoryEntry::Message { role: ChatRole::System, content }] => {
+                assert!(content.contains("The bug is in the parser"));
+            }
+            other => panic!("unexpected history: {:?}", other),
+        }
+        assert!(lead_mail.try_recv().is_err());
+
+        // Without a replacement the parent gets it
+        session.terminate_agent(&replacement.id(), "done".into(), &sub_id).unwrap();
+        match lead_mail.try_recv() {
+            Ok(Mail::Handoff { handoff }) => {
+                assert_eq!((handoff.from, handoff.task_id), (replacement.id(), Some(task_id)));
+            }
+            other => panic!("expected handoff, got {:?}", other),
+        }
+
+        let handoffs = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|e| matches!(e, GoblinEvent::AgentHandoff { .. }))
+            .count();
+        assert_eq!(handoffs, 2);
+    }
+
+    #[test]
+    fn test_fork_copies_history_and_task() {
+        use crate::history::HistoryEntry;
+        use crate::model::ChatRole;
+
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+        let task_id = TaskId::new();
+        worker.assign_task(task_id);
+        worker.record(HistoryEntry::message(ChatRole::User, "Try recursion"));
+
+        let fork = session
+            .fork_agent(&worker.id(), AgentOverrides::default().with_model("other-model"), &sub_id)
+            .unwrap();
+        assert_eq!(fork.parent_id(), Some(root.id()));
+        assert_eq!(fork.config.model, "other-model");
+        assert_eq!(fork.current_task(), Some(task_id));
+        assert_eq!(fork.history(), worker.history());
+
+        // The copies diverge from here
+        fork.record(HistoryEntry::message(ChatRole::User, "Try iteration"));
+        assert_eq!(worker.history_len(), 1);
+        assert!(std::iter::from_fn(|| rx.try_recv().ok()).any(|e| matches!(e, GoblinEvent::AgentForked { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_reparent_moves_agent_and_run_loop() {
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let lead = |domain: &str| AgentConfig {
+            role: AgentRole::DomainLead { domain: domain.into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = AgentConfig {
+            role: AgentRole::Orchestrator,
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = session.spawn_agent(root, None, &sub_id).unwrap();
+        let lead1 = session.spawn_agent(lead("frontend"), Some(root.id()), &sub_id).unwrap();
+        let lead2 = session.spawn_agent(lead("backend"), Some(root.id()), &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(lead1.id()), &sub_id).unwrap();
+        session
+            .start_agent(&worker.id(), &sub_id, async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok(())
+            })
+            .unwrap();
+        while rx.try_recv().is_ok() {}
+
+        session.reparent_agent(&worker.id(), &lead2.id(), &sub_id).unwrap();
+        assert_eq!(worker.parent_id(), Some(lead2.id()));
+        assert!(lead1.children().is_empty());
+        assert_eq!(lead2.children(), vec![worker.id()]);
+        assert_eq!((lead1.scope().running(), lead2.scope().running()), (0, 1));
+        match rx.try_recv() {
+            Ok(GoblinEvent::HierarchyChanged { old_parent, new_parent, .. }) => {
+                assert_eq!((old_parent, new_parent), (Some(lead1.id()), lead2.id()));
+            }
+            other => panic!("expected hierarchy change, got {:?}", other),
+        }
+
+        // Workers cannot have children, and leads cannot move under their own workers
+        assert!(session.reparent_agent(&lead1.id(), &worker.id(), &sub_id).is_err());
+        assert!(session.reparent_agent(&root.id(), &lead2.id(), &sub_id).is_err());
+        assert_eq!(root.parent_id(), None);
+    }
+
+    #[test]
+    fn test_tree_deltas() {
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+        worker.set_status(AgentStatus::Running, &sub_id);
+        session.terminate_agent(&root.id(), "done".into(), &sub_id).unwrap();
+
+        let deltas: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                GoblinEvent::TreeDelta { added, removed, status_changes, .. } => Some((added, removed, status_changes)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(deltas.len(), 4);
+        assert_eq!(deltas[0].0[0].agent_id, root.id());
+        assert_eq!(deltas[1].0[0].parent, Some(root.id()));
+        assert_eq!(deltas[2].2, vec![(worker.id(), AgentStatus::Running)]);
+        assert_eq!(deltas[3].1, vec![root.id(), worker.id()]);
+        assert!(deltas[3].2.is_empty());
+
+        assert!(session.hierarchy_snapshot().agents.is_empty());
+    }
+
+    #[test]
+    fn test_subtree_summary_rolls_up() {
+        let (session, _rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+        let helper = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+
+        worker.assign_task(TaskId::new());
+        worker.add_usage(100, 20);
+        helper.add_usage(10, 2);
+        let failed = TaskResult::failure(TaskId::new(), "no luck").from_agent(helper.id());
+        session.results.write().insert(failed.task_id, failed);
+
+        let summary = session.subtree_summary(&root.id()).unwrap();
+        assert_eq!((summary.agents, summary.working, summary.idle), (3, 1, 2));
+        assert_eq!((summary.succeeded, summary.failed), (0, 1));
+        assert_eq!(summary.usage.total_tokens, 132);
+        assert_eq!(session.subtree_summary(&helper.id()).unwrap().agents, 1);
+        assert!(session.subtree_summary(&AgentId::new()).is_none());
+
+        let tree = session.hierarchy().unwrap();
+        assert_eq!(tree.task_summary, Some(summary.to_string()));
+        assert_eq!(tree.task_summary.as_deref(), Some("1 working / 2 idle / 1 failed, 132 tokens"));
+        assert!(tree.children.iter().all(|c| c.task_summary.is_none()));
+    }
+
+    #[test]
+    fn test_subtree_queries() {
+        let (session, _rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+        let lead = AgentConfig {
+            role: AgentRole::DomainLead { domain: "backend".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead, Some(root.id()), &sub_id).unwrap();
+        let helper = session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id).unwrap();
+
+        assert!(session.is_under(&helper.id(), &root.id()));
+        assert!(!session.is_under(&worker.id(), &lead.id()));
+
+        lead.add_usage(10, 5);
+        helper.add_usage(1, 1);
+        worker.add_usage(100, 100);
+        assert_eq!(session.subtree_usage(&lead.id()).total_tokens, 17);
+
+        helper.assign_task(TaskId::new());
+        worker.assign_task(TaskId::new());
+        assert_eq!(session.interrupt_subtree(&lead.id()), 1);
+    }
+
+    #[test]
+    fn test_depth_limit_covers_spawns_and_moves() {
+        use crate::config::SpawnLimits;
+
+        let limits = SpawnLimits {
+            max_depth: Some(2),
+            ..SpawnLimits::default()
+        };
+        let options = SessionOptions::new().with_spawn_limits(limits);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let spawner = AgentConfig {
+            role: AgentRole::Worker,
+            can_spawn: true,
+            ..Default::default()
+        };
+        let root = session.spawn_agent(spawner.clone(), None, &sub_id).unwrap();
+        let lead = session.spawn_agent(spawner.clone(), Some(root.id()), &sub_id).unwrap();
+        let helper = session.spawn_agent(spawner.clone(), Some(lead.id()), &sub_id).unwrap();
+        let denied = session.spawn_agent(spawner.clone(), Some(helper.id()), &sub_id);
+        assert!(matches!(denied, Err(GoblinError::SpawnDenied(_))));
+
+        let other = session.spawn_agent(spawner.clone(), Some(root.id()), &sub_id).unwrap();
+        session.spawn_agent(spawner, Some(other.id()), &sub_id).unwrap();
+        let moved = session.reparent_agent(&other.id(), &lead.id(), &sub_id);
+        assert!(matches!(moved, Err(GoblinError::SpawnDenied(msg)) if msg.contains("depth 3")));
+        assert_eq!(session.hierarchy.read().parent(&other.id()), Some(root.id()));
+    }
+
+    #[test]
+    fn test_agent_limit_warns_then_denies() {
+        use crate::config::SpawnLimits;
+
+        let limits = SpawnLimits {
+            max_agents: Some(5),
+            ..SpawnLimits::default()
+        };
+        let options = SessionOptions::new().with_spawn_limits(limits);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let (root, _worker) = spawn_worker_under_root(&session, &sub_id);
+        for _ in 0..2 {
+            session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+        }
+        let last = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+
+        let warnings: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                GoblinEvent::AgentLimitNear { live, limit, .. } => Some((live, limit)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(warnings, vec![(4, 5)]);
+
+        let denied = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id);
+        assert!(matches!(denied, Err(GoblinError::SpawnDenied(msg)) if msg.contains("5 of at most 5")));
+        session.terminate_agent(&last.id(), "done".into(), &sub_id).unwrap();
+        assert!(session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).is_ok());
+    }
+
+    #[test]
+    fn test_spawn_rate_limits_each_parent() {
+        use crate::config::SpawnLimits;
+        use crate::ratelimit::RateLimit;
+
+        let limits = SpawnLimits {
+            rate_per_parent: Some(RateLimit::new(2, 1)),
+            rate_per_session: Some(RateLimit::new(10, 60)),
+            ..SpawnLimits::default()
+        };
+        let options = SessionOptions::new().with_spawn_limits(limits);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let (root, _worker) = spawn_worker_under_root(&session, &sub_id);
+        let lead = AgentConfig {
+            role: AgentRole::DomainLead { domain: "backend".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead, Some(root.id()), &sub_id).unwrap();
+
+        let denied = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap_err();
+        assert!(matches!(denied, GoblinError::SpawnRateLimited { .. }));
+        let wait = denied.retry_after().unwrap();
+        assert!(wait > Duration::from_secs(50) && wait <= Duration::from_secs(60));
+        assert!(session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id).is_ok());
+
+        // A parent's bucket goes with it
+        session.terminate_agent(&lead.id(), "done".into(), &sub_id).unwrap();
+        assert!(!session.spawn_buckets.lock().parents.contains_key(&lead.id()));
+    }
+
+    #[test]
+    fn test_refused_spawns_take_no_spawn_token() {
+        use crate::config::SpawnLimits;
+        use crate::ratelimit::RateLimit;
+
+        let limits = SpawnLimits {
+            max_depth: Some(1),
+            rate_per_session: Some(RateLimit::new(4, 60)),
+            ..SpawnLimits::default()
+        };
+        let options = SessionOptions::new().with_spawn_limits(limits);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let (root, _worker) = spawn_worker_under_root(&session, &sub_id);
+        let lead = AgentConfig {
+            role: AgentRole::DomainLead { domain: "backend".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead, Some(root.id()), &sub_id).unwrap();
+
+        let too_deep = session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id);
+        assert!(matches!(too_deep, Err(GoblinError::SpawnDenied(_))));
+        assert!(session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).is_ok());
+        let limited = session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id);
+        assert!(matches!(limited, Err(GoblinError::SpawnRateLimited { .. })));
+    }
+
+    #[test]
+    fn test_subtree_ceiling_throttles_only_its_agents() {
+        use crate::budget::TokenBudgets;
+
+        let options = SessionOptions::new()
+            .with_token_budgets(TokenBudgets::default().with_subtree_ceiling("domain_lead", 100));
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+        let lead = AgentConfig {
+            role: AgentRole::DomainLead { domain: "backend".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead, Some(root.id()), &sub_id).unwrap();
+        let helper = session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id).unwrap();
+
+        helper.add_usage(60, 0);
+        lead.add_usage(30, 0);
+        worker.add_usage(500, 0);
+        assert!(!lead.is_paused());
+        helper.add_usage(10, 0);
+        assert!(lead.is_paused() && helper.is_paused());
+        assert!(!root.is_paused() && !worker.is_paused());
+        assert_eq!(session.ledger().spent_below(&root.id()), 600);
+
+        let late = session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id).unwrap();
+        assert!(late.is_paused());
+
+        session.ledger().set_ceiling(Some(lead.id()), Some(200), &sub_id).unwrap();
+        assert!(!lead.is_paused() && !helper.is_paused() && !late.is_paused());
+        let budgets = session.ledger().rollup();
+        assert_eq!((budgets[0].agent_id, budgets[0].spent), (None, 600));
+        assert!(budgets.iter().all(|b| !b.throttled));
+    }
+
+    #[test]
+    fn test_lifted_subtree_stays_paused_under_the_session_ceiling() {
+        use crate::budget::TokenBudgets;
+
+        let budgets = TokenBudgets::default()
+            .with_subtree_ceiling("domain_lead", 100)
+            .with_session_ceiling(150);
+        let options = SessionOptions::new().with_token_budgets(budgets);
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+        let lead = AgentConfig {
+            role: AgentRole::DomainLead { domain: "backend".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead, Some(root.id()), &sub_id).unwrap();
+
+        lead.add_usage(110, 0);
+        worker.add_usage(50, 0);
+        assert!(lead.is_paused() && worker.is_paused());
+
+        session.ledger().set_ceiling(Some(lead.id()), Some(1000), &sub_id).unwrap();
+        assert!(lead.is_paused());
+        session.ledger().set_ceiling(None, Some(1000), &sub_id).unwrap();
+        assert!(!lead.is_paused() && !worker.is_paused() && !root.is_paused());
+    }
+
+    #[test]
+    fn test_costs_roll_up_to_tasks_and_the_session() {
+        use crate::pricing::{ModelPrice, PricingTable};
+
+        let options = SessionOptions::new().with_pricing(PricingTable::new().with_default(ModelPrice::new(2.0, 10.0)));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+
+        let task_id = TaskId::new();
+        worker.assign_task(task_id);
+        worker.add_usage(500_000, 100_000);
+        root.add_usage(1_000_000, 0);
+
+        assert!((worker.cost_usd() - 2.0).abs() < 1e-9);
+        assert!((session.ledger().cost_below(&root.id()) - 4.0).abs() < 1e-9);
+        let metrics = session.metrics();
+        assert!((metrics.cost_usd - 4.0).abs() < 1e-9);
+        assert_eq!(metrics.task_costs.len(), 1);
+        assert_eq!(metrics.task_costs[0].0, task_id);
+
+        let reported = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|e| match e {
+            GoblinEvent::UsageUpdated { agent_id, task_id, cost_usd, .. } if agent_id == worker.id() => {
+                Some((task_id, cost_usd))
+            }
+            _ => None,
+        });
+        assert_eq!(reported.map(|(task, _)| task), Some(Some(task_id)));
+    }
+
+    #[tokio::test]
+    async fn test_repeated_failures_are_reported_to_the_lead() {
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, _) = spawn_worker_under_root(&session, &sub_id);
+        let lead = AgentConfig {
+            role: AgentRole::DomainLead { domain: "backend".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead, Some(root.id()), &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id).unwrap();
+        let mut mailbox = lead.take_mailbox().unwrap();
+
+        let fail = |summary: &str| {
+            let task_id = TaskId::new();
+            worker.assign_task(task_id);
+            TaskResult::failure(task_id, summary).from_agent(worker.id())
+        };
+        session.record_result(fail("first"), &sub_id).await.unwrap();
+        assert!(mailbox.try_recv().is_err());
+        let second = fail("second");
+        let task_id = second.task_id;
+        session.record_result(second, &sub_id).await.unwrap();
+
+        match mailbox.try_recv() {
+            Ok(Mail::FailureReport { report }) => {
+                assert_eq!((report.agent_id, report.task_id, report.failures), (worker.id(), task_id, 2));
+                assert_eq!(report.error, "second");
+            }
+            other => panic!("expected failure report, got {:?}", other),
+        }
+        let reported = std::iter::from_fn(|| rx.try_recv().ok())
+            .any(|e| matches!(e, GoblinEvent::FailureReported { lead_id, .. } if lead_id == lead.id()));
+        assert!(reported);
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_cancels_siblings_and_quarantine_keeps_agent() {
+        use crate::plan::PlannedTask;
+
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+        session.set_failure_policy(root.id(), FailurePolicy::FailFast);
+
+        let task_id = TaskId::new();
+        let mut plan = TaskPlan::new(task_id, "Build an API");
+        let routes = plan.add(PlannedTask::new("Routes", AgentRole::Worker));
+        let models = plan.add(PlannedTask::new("Models", AgentRole::Worker));
+        session.plans.write().insert(task_id, plan);
+        session.set_plan_status(&models, PlanStatus::Running);
+        worker.assign_task(models);
+
+        let merged = session
+            .record_result(TaskResult::failure(routes, "routes broken"), &sub_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(merged.task_id, task_id);
+        assert!(!merged.is_success());
+        assert_eq!(session.plan(&task_id).unwrap().get(&models).unwrap().status, PlanStatus::Cancelled);
+        let late = session.record_result(TaskResult::success(models, "models done"), &sub_id).await;
+        assert_eq!(late.unwrap(), None);
+        let cancelled = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|e| match e {
+            GoblinEvent::FailurePropagated { policy: FailurePolicy::FailFast, cancelled, .. } => Some(cancelled),
+            _ => None,
+        });
+        assert_eq!(cancelled, Some(vec![models]));
+
+        session.set_failure_policy(root.id(), FailurePolicy::Quarantine);
+        worker.clear_task();
+        let task_id = TaskId::new();
+        let mut plan = TaskPlan::new(task_id, "Fix the parser");
+        let step = plan.add(PlannedTask::new("Fix the lexer", AgentRole::Worker));
+        session.plans.write().insert(task_id, plan);
+        session
+            .record_result(TaskResult::failure(step, "lexer broken").from_agent(worker.id()), &sub_id)
+            .await
+            .unwrap();
+        assert!(worker.is_quarantined() && worker.summary().quarantined);
+        assert!(session.idle_workers().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_silent_agents_are_marked_unresponsive() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let options = SessionOptions::default().with_heartbeat(HeartbeatPolicy::new(Duration::from_millis(10), 2));
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let (root, worker) = spawn_worker_under_root(&session, &sub_id);
+        worker.beat();
+
+        // The root's run loop never started, so only the worker is watched
+        assert!(session.check_heartbeats(&sub_id).is_empty());
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert_eq!(session.check_heartbeats(&sub_id), vec![worker.id()]);
+        assert!(worker.summary().unresponsive && !root.is_unresponsive());
+        assert!(session.check_heartbeats(&sub_id).is_empty());
+
+        worker.beat();
+        session.check_heartbeats(&sub_id);
+        assert!(!worker.is_unresponsive());
+        let kinds: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                GoblinEvent::AgentUnresponsive { agent_id, .. } => Some(("unresponsive", agent_id)),
+                GoblinEvent::AgentResponsive { agent_id, .. } => Some(("responsive", agent_id)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(kinds, vec![("unresponsive", worker.id()), ("responsive", worker.id())]);
+    }
+
+    #[tokio::test]
+    async fn test_stalled_agents_are_nudged_once() {
+        use crate::stall::{StallPolicy, DEFAULT_NUDGE};
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let stall = StallPolicy::new(Duration::from_millis(20)).with_action(StallAction::Nudge);
+        let options = SessionOptions::default().with_stall_detection(stall);
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let (_root, worker) = spawn_worker_under_root(&session, &sub_id);
+        let task_id = TaskId::new();
+        worker.assign_task(task_id);
+
+        assert!(session.check_stalls(&sub_id).is_empty());
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(session.check_stalls(&sub_id), vec![worker.id()]);
+        assert!(session.check_stalls(&sub_id).is_empty());
+        assert_eq!(
+            worker.history().entries().last(),
+            Some(&HistoryEntry::message(ChatRole::User, DEFAULT_NUDGE))
+        );
+
+        let stalled = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|e| match e {
+            GoblinEvent::AgentStalled { agent_id, task_id, last_activity, .. } => Some((agent_id, task_id, last_activity)),
+            _ => None,
+        });
+        assert_eq!(stalled, Some((worker.id(), task_id, worker.last_progress())));
+
+        // Progress ends the stall, so the next one is reported again
+        worker.mark_progress();
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert_eq!(session.check_stalls(&sub_id), vec![worker.id()]);
+    }
+
+    #[test]
+    fn test_orphans_are_adopted_by_their_grandparent() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let options = SessionOptions::default().with_orphans(OrphanPolicy::Adopt);
+        let session = Session::with_options(SessionConfig::default(), options, Arc::new(ToolRegistry::new()), tx);
+        let sub_id = SubmissionId::new();
+        let (root, _) = spawn_worker_under_root(&session, &sub_id);
+        let lead = AgentConfig {
+            role: AgentRole::DomainLead { domain: "backend".into() },
+            can_spawn: true,
+            ..Default::default()
+        };
+        let lead = session.spawn_agent(lead, Some(root.id()), &sub_id).unwrap();
+        let worker = session.spawn_agent(AgentConfig::default(), Some(lead.id()), &sub_id).unwrap();
+
+        assert_eq!(session.handle_orphans(&lead.id(), &sub_id), vec![worker.id()]);
+        assert_eq!(worker.parent_id(), Some(root.id()));
+        assert!(session.hierarchy.read().children(&lead.id()).is_empty());
+        let adopted = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|e| match e {
+            GoblinEvent::OrphanAdopted { agent_id, new_parent, .. } => Some((agent_id, new_parent)),
+            _ => None,
+        });
+        assert_eq!(adopted, Some((worker.id(), root.id())));
+
+        // The root has no parent to adopt its children, so they are terminated
+        let orphans = session.handle_orphans(&root.id(), &sub_id);
+        assert_eq!(orphans.len(), 3);
         assert_eq!(session.agent_count(), 1);
-        
-        // Check event was emitted
-        let event = rx.try_recv();
-        assert!(matches!(event, Ok(Event::AgentSpawned { .. })));
+        let terminated = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|e| matches!(e, GoblinEvent::OrphanTerminated { .. }))
+            .count();
+        assert_eq!(terminated, 3);
+    }
+
+    #[test]
+    fn test_agent_tool_scope_survives_checkpoint() {
+        let (session, _rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, _) = spawn_worker_under_root(&session, &sub_id);
+        let scope = ToolScope::new().with_allow(["read_file", "grep"]);
+        let reader = session
+            .spawn_agent_with_tools(AgentConfig::default(), Some(root.id()), scope.clone(), &sub_id)
+            .unwrap();
+        assert!(reader.tool_view().allows("grep"));
+        assert!(!reader.tool_view().allows("write_file"));
+        assert!(root.tool_view().allows("write_file"));
+
+        let (restored, _rx) = create_test_session();
+        restored.restore(&session.to_checkpoint()).unwrap();
+        let reader = restored.get_agent(&reader.id()).unwrap();
+        assert_eq!(reader.tool_view().own_scope(), Some(&scope));
+    }
+
+    #[test]
+    fn test_update_config_live() {
+        use crate::approval::{ApprovalDecision, ApprovalPolicy, ApprovalRule};
+        use crate::config::SessionConfigPatch;
+
+        let (session, mut rx) = create_test_session();
+        let sub_id = SubmissionId::new();
+        let (root, _worker) = spawn_worker_under_root(&session, &sub_id);
+        session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).unwrap();
+        while rx.try_recv().is_ok() {}
+
+        // Two children already run under the root
+        let too_tight = SessionConfigPatch::default().with_model("local/small").with_max_parallel_agents(1);
+        assert!(session.update_config(&too_tight, &sub_id).is_err());
+        assert_ne!(session.config().model, "local/small");
+        assert!(rx.try_recv().is_err());
+
+        let patch = SessionConfigPatch::default().with_model("local/small").with_max_parallel_agents(2);
+        let config = session.update_config(&patch, &sub_id).unwrap();
+        assert_eq!((config.model.as_str(), config.max_parallel_agents), ("local/small", 2));
+        assert!(matches!(rx.try_recv(), Ok(GoblinEvent::SessionConfigChanged { patch: p, .. }) if p == patch));
+        assert!(session.spawn_agent(AgentConfig::default(), Some(root.id()), &sub_id).is_err());
+
+        let unnamed = ApprovalPolicy::new().with_rule(ApprovalRule::new(" ", ApprovalDecision::Approve));
+        assert!(session.update_config(&SessionConfigPatch::default().with_approval_policy(unnamed), &sub_id).is_err());
+        let deny = ApprovalPolicy::new().with_default(ApprovalDecision::Deny);
+        let patch = SessionConfigPatch::default().with_approval_policy(deny.clone());
+        session.update_config(&patch, &sub_id).unwrap();
+        assert_eq!(session.approvals().policy(), deny);
+        assert_eq!(session.to_checkpoint().options.approval, deny);
+    }
+
+    #[tokio::test]
+    async fn test_reviewer_bounces_results_back() {
+        use crate::plan::PlannedTask;
+        use async_trait::async_trait;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Bounces every result until it mentions tests
+        struct NeedsTests(AtomicUsize);
+
+        #[async_trait]
+        impl Reviewer for NeedsTests {
+            async fn review(&self, agent: &AgentHandle, _: &str, result: &TaskResult) -> Result<Review, GoblinError> {
+                assert_eq!(agent.role(), &AgentRole::Specialist { specialty: "reviewer".into() });
+                self.0.fetch_add(1, Ordering::SeqCst);
+                if result.summary.contains("tests") {
+                    Ok(Review::Approve)
+                } else {
+                    Ok(Review::Bounce { feedback: "add tests".into() })
+                }
+            }
+        }
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let reviewer = Arc::new(NeedsTests(AtomicUsize::new(0)));
+        let session = Session::with_options(
+            SessionConfig::default(),
+            SessionOptions::new().with_review(1),
+            Arc::new(ToolRegistry::new()),
+            tx,
+        )
+        .with_reviewer(reviewer.clone());
+        let sub_id = SubmissionId::new();
+        let (_root, worker) = spawn_worker_under_root(&session, &sub_id);
+        let mut mailbox = worker.take_mailbox().unwrap();
+
+        let task_id = TaskId::new();
+        let mut plan = TaskPlan::new(task_id, "Fix the bug");
+        let fix = plan.add(PlannedTask::new("Fix", AgentRole::Worker));
+        session.plans.write().insert(task_id, plan);
+        worker.assign_task(fix);
+
+        let bounced = session
+            .record_result(TaskResult::success(fix, "fixed").from_agent(worker.id()), &sub_id)
+            .await
+            .unwrap();
+        assert_eq!(bounced, None);
+        assert_eq!(worker.current_task(), Some(fix));
+        assert!(matches!(mailbox.try_recv(), Ok(Mail::ResultBounced { task_id: t, .. }) if t == fix));
+
+        let done = session
+            .record_result(TaskResult::success(fix, "fixed, with tests").from_agent(worker.id()), &sub_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(done.is_success());
+        assert_eq!(reviewer.0.load(Ordering::SeqCst), 2);
+        // Reviewers are transient
+        assert_eq!(session.agent_count(), 2);
+
+        let mut reviews = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let GoblinEvent::ResultReviewed { approved, bounces, .. } = event {
+                reviews.push((approved, bounces));
+            }
+        }
+        assert_eq!(reviews, vec![(false, 1), (true, 1)]);
     }
 }
@@ -0,0 +1,277 @@
+//! Speculative execution of critical subtasks
+//!
+//! A [`Speculation`] races several redundant workers on the same subtask
+//! and takes the first result that is acceptable (successful, unless a
+//! custom check is given). The losing workers are terminated as soon as a
+//! winner is known, which cancels their run loops; if a worker cannot be
+//! started, those already started are terminated. Tokens the losers spent
+//! are still counted: they stay in the session totals, and the winning
+//! result's usage covers every attempt.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+use tracing::{debug, info};
+use warhorn::{AgentConfig, AgentId, SubmissionId, TaskId};
+
+use crate::agent::AgentHandle;
+use crate::error::GoblinError;
+use crate::protocol::GoblinEvent;
+use crate::result::{sum_usage, TaskResult};
+use crate::session::SessionHandle;
+
+/// Makes one attempt at a subtask on a worker agent
+#[async_trait]
+pub trait AttemptRunner: Send + Sync {
+    /// Attempt the task; `attempt` numbers the redundant workers from 0
+    async fn attempt(&self, agent: &AgentHandle, task_id: TaskId, attempt: usize) -> Result<TaskResult, GoblinError>;
+}
+
+/// Decides whether a result is good enough to win the race
+pub type Acceptance = Arc<dyn Fn(&TaskResult) -> bool + Send + Sync>;
+
+/// A race of redundant workers
+#[derive(Clone)]
+pub struct Speculation {
+    /// Subtask the workers attempt
+    pub task_id: TaskId,
+    /// Configuration of the workers
+    pub worker: AgentConfig,
+    /// Number of redundant workers
+    pub attempts: usize,
+    /// Agent the workers are spawned under (the root if None)
+    pub parent: Option<AgentId>,
+    accept: Acceptance,
+}
+
+impl std::fmt::Debug for Speculation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Speculation")
+            .field("task_id", &self.task_id)
+            .field("attempts", &self.attempts)
+            .field("parent", &self.parent)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Speculation {
+    /// Race `attempts` workers on a subtask, accepting the first success
+    pub fn new(task_id: TaskId, worker: AgentConfig, attempts: usize) -> Self {
+        Self {
+            task_id,
+            worker,
+            attempts: attempts.max(1),
+            parent: None,
+            accept: Arc::new(TaskResult::is_success),
+        }
+    }
+
+    /// Spawn the workers under a specific agent
+    pub fn with_parent(mut self, parent: AgentId) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    /// Only accept results passing a check
+    pub fn with_acceptance(mut self, accept: impl Fn(&TaskResult) -> bool + Send + Sync + 'static) -> Self {
+        self.accept = Arc::new(accept);
+        self
+    }
+
+    /// Run the race and return the winning result
+    ///
+    /// If no attempt is acceptable, a failed result is returned once all
+    /// of them have finished.
+    pub async fn run(
+        &self,
+        session: &SessionHandle,
+        runner: Arc<dyn AttemptRunner>,
+        sub_id: &SubmissionId,
+    ) -> Result<TaskResult, GoblinError> {
+        let parent = match self.parent {
+            Some(parent) => Some(parent),
+            None => session.orchestrator().map(|o| o.id()),
+        };
+        info!(task_id = %self.task_id, attempts = self.attempts, "Starting speculative attempts");
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let mut workers = Vec::with_capacity(self.attempts);
+        for attempt in 0..self.attempts {
+            let started = session.spawn_agent(self.worker.clone(), parent, sub_id).and_then(|agent| {
+                agent.assign_task(self.task_id);
+                workers.push(agent.clone());
+
+                let runner = Arc::clone(&runner);
+                let tx = tx.clone();
+                let task_id = self.task_id;
+                session.start_agent(&agent.id(), sub_id, async move {
+                    let outcome = runner.attempt(&agent, task_id, attempt).await;
+                    let _ = tx.send((agent.id(), outcome));
+                    Ok(())
+                })
+            });
+            if let Err(e) = started {
+                terminate(session, &workers, "Speculation could not start", sub_id).await;
+                return Err(e);
+            }
+        }
+        drop(tx);
+
+        let mut winner = None;
+        while let Some((agent_id, outcome)) = rx.recv().await {
+            let result = match outcome {
+                Ok(result) => result,
+                Err(e) => TaskResult::failure(self.task_id, e.to_string()),
+            };
+            if (self.accept)(&result) {
+                winner = Some((agent_id, result));
+                break;
+            }
+            debug!(task_id = %self.task_id, agent_id = %agent_id, "Speculative attempt not accepted");
+        }
+
+        // Account for every attempt before the losers are torn down
+        let winner_id = winner.as_ref().map(|(id, _)| *id);
+        let usages: Vec<_> = workers.iter().map(|w| (w.id(), w.usage())).collect();
+        let total = sum_usage(usages.iter().map(|(_, usage)| usage));
+        let wasted = sum_usage(usages.iter().filter(|(id, _)| Some(*id) != winner_id).map(|(_, usage)| usage));
+        terminate(session, &workers, "Speculative attempt finished", sub_id).await;
+
+        session.emit(GoblinEvent::SpeculationSettled {
+            sub_id: sub_id.clone(),
+            task_id: self.task_id,
+            winner: winner_id,
+            attempts: self.attempts,
+            wasted: wasted.clone(),
+        });
+
+        let result = match winner {
+            Some((agent_id, result)) => {
+                info!(task_id = %self.task_id, agent_id = %agent_id, wasted_tokens = wasted.total_tokens, "Speculative attempt won");
+                result.from_agent(agent_id)
+            }
+            None => TaskResult::failure(
+                self.task_id,
+                format!("None of {} attempts was acceptable", self.attempts),
+            ),
+        };
+        let mut result = result.with_usage(total);
+        result.task_id = self.task_id;
+        Ok(result)
+    }
+}
+
+/// Terminate the workers and wait for their run loops to end
+async fn terminate(session: &SessionHandle, workers: &[AgentHandle], reason: &str, sub_id: &SubmissionId) {
+    for worker in workers {
+        let _ = session.terminate_agent(&worker.id(), reason.into(), sub_id);
+    }
+    session.join_terminated().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::testing::session_with_root;
+
+    /// Attempt 0 hangs, attempt 1 fails fast, attempt 2 succeeds; each
+    /// spends 10 tokens first
+    struct Racer;
+
+    #[async_trait]
+    impl AttemptRunner for Racer {
+        async fn attempt(&self, agent: &AgentHandle, task_id: TaskId, attempt: usize) -> Result<TaskResult, GoblinError> {
+            agent.add_usage(5, 5);
+            match attempt {
+                0 => {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok(TaskResult::success(task_id, "too late"))
+                }
+                1 => Err(GoblinError::TaskError("gave up".into())),
+                _ => {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                    Ok(TaskResult::success(task_id, "got it"))
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_first_acceptable_attempt_wins() {
+        let (session, mut rx, sub_id) = session_with_root();
+        let task_id = TaskId::new();
+
+        let result = Speculation::new(task_id, AgentConfig::default(), 3)
+            .run(&session, Arc::new(Racer), &sub_id)
+            .await
+            .unwrap();
+
+        assert!(result.is_success());
+        assert_eq!(result.summary, "got it");
+        assert_eq!(result.usage.total_tokens, 30);
+        // Losers are gone but their tokens still count
+        assert_eq!(session.agent_count(), 1);
+        assert_eq!(session.total_usage().total_tokens, 30);
+
+        let mut settled = None;
+        while let Ok(event) = rx.try_recv() {
+            if let GoblinEvent::SpeculationSettled { winner, wasted, .. } = event {
+                settled = Some((winner, wasted.total_tokens));
+            }
+        }
+        assert_eq!(settled, Some((result.agent_id, 20)));
+    }
+
+    struct Numbered;
+
+    #[async_trait]
+    impl AttemptRunner for Numbered {
+        async fn attempt(&self, _agent: &AgentHandle, task_id: TaskId, attempt: usize) -> Result<TaskResult, GoblinError> {
+            Ok(TaskResult::success(task_id, format!("attempt {}", attempt)))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_acceptance() {
+        let (session, _rx, sub_id) = session_with_root();
+        let speculation = Speculation::new(TaskId::new(), AgentConfig::default(), 3);
+
+        let picky = speculation
+            .clone()
+            .with_acceptance(|r| r.summary == "attempt 1")
+            .run(&session, Arc::new(Numbered), &sub_id)
+            .await
+            .unwrap();
+        assert_eq!(picky.summary, "attempt 1");
+
+        let hopeless = speculation
+            .with_acceptance(|_| false)
+            .run(&session, Arc::new(Numbered), &sub_id)
+            .await
+            .unwrap();
+        assert!(!hopeless.is_success());
+        assert_eq!(session.agent_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_started_workers_are_terminated_when_one_cannot_spawn() {
+        let (session, _rx, sub_id) = session_with_root();
+        let lead = AgentConfig {
+            can_spawn: true,
+            max_children: Some(2),
+            ..Default::default()
+        };
+        let root = session.orchestrator().unwrap().id();
+        let lead = session.spawn_agent(lead, Some(root), &sub_id).unwrap();
+
+        let started = Speculation::new(TaskId::new(), AgentConfig::default(), 3)
+            .with_parent(lead.id())
+            .run(&session, Arc::new(Numbered), &sub_id)
+            .await;
+        assert!(started.is_err());
+        assert_eq!(session.agent_count(), 2);
+    }
+}
@@ -0,0 +1,104 @@
+//! Detection of agents that make no progress
+//!
+//! An agent makes progress when it is assigned a task, changes status,
+//! receives tokens from its model or calls a tool; runtimes may report
+//! other progress through [`crate::Agent::mark_progress`]. With a
+//! [`StallPolicy`] in the session options, the session's watchdog looks
+//! for agents working on a task that made none for `after_ms`. Each stall
+//! is reported once in an `AgentStalled` event with the time of the last
+//! activity, and the policy's action is applied: a warning only, a nudge
+//! added to the agent's history for its next model call, or an interrupt.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::GoblinError;
+
+/// Nudge added to a stalled agent's history unless the policy has its own
+pub const DEFAULT_NUDGE: &str =
+    "You have made no progress for a while. Continue with the task, or report what is blocking you.";
+
+/// What is done with a stalled agent besides warning
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StallAction {
+    /// Only report the stall
+    #[default]
+    Warn,
+    /// Add a message to the agent's history
+    Nudge,
+    /// Interrupt the agent's task
+    Interrupt,
+}
+
+/// When agents count as stalled
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StallPolicy {
+    /// Time without progress after which an agent is stalled, in milliseconds
+    pub after_ms: u64,
+    #[serde(default)]
+    pub action: StallAction,
+    /// Message nudged agents are given instead of [`DEFAULT_NUDGE`]
+    #[serde(default)]
+    pub nudge_message: Option<String>,
+}
+
+impl StallPolicy {
+    pub fn new(after: Duration) -> Self {
+        Self {
+            after_ms: after.as_millis() as u64,
+            action: StallAction::Warn,
+            nudge_message: None,
+        }
+    }
+
+    pub fn with_action(mut self, action: StallAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    /// Nudge stalled agents with this message
+    pub fn with_nudge(mut self, message: impl Into<String>) -> Self {
+        self.action = StallAction::Nudge;
+        self.nudge_message = Some(message.into());
+        self
+    }
+
+    pub fn after(&self) -> Duration {
+        Duration::from_millis(self.after_ms)
+    }
+
+    /// How often the watchdog looks for stalls
+    pub fn check_interval(&self) -> Duration {
+        (self.after() / 4).max(Duration::from_millis(1))
+    }
+
+    /// Message a nudged agent is given
+    pub fn nudge(&self) -> &str {
+        self.nudge_message.as_deref().unwrap_or(DEFAULT_NUDGE)
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.after_ms == 0 {
+            return Err(GoblinError::ConfigError("stall after_ms must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policy_defaults_to_warning() {
+        let policy: StallPolicy = serde_json::from_str(r#"{"after_ms": 60000, "action": "nudge"}"#).unwrap();
+        assert_eq!(policy.action, StallAction::Nudge);
+        assert_eq!(policy.nudge(), DEFAULT_NUDGE);
+        assert_eq!(policy.check_interval(), Duration::from_secs(15));
+
+        let policy: StallPolicy = serde_json::from_str(r#"{"after_ms": 1000}"#).unwrap();
+        assert_eq!(policy.action, StallAction::Warn);
+    }
+}
@@ -0,0 +1,292 @@
+//! Orchestrator over newline-delimited JSON on stdin/stdout
+//!
+//! Frontends written in other languages spawn a cabal process and drive
+//! it through pipes. Every line is one JSON [`Envelope`] carrying the
+//! protocol version: ops come in on stdin, and events go out on stdout
+//! after an initial [`StdioMessage::Ready`]. A line that cannot be handled
+//! is answered with [`StdioMessage::Error`] naming its line number, and
+//! the stream carries on.
+//!
+//! Closing stdin stops the orchestrator once the ops already read are
+//! handled; the events they produced are written out before exiting.
+
+use std::future::Future;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+use trinkets::ToolRegistry;
+
+use crate::channel::ChannelPair;
+use crate::error::GoblinError;
+use crate::orchestrator::Orchestrator;
+use crate::protocol::{GoblinEvent, GoblinOp};
+
+/// Version of the envelope format and the messages it carries
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// One line of the stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    /// Protocol version the body is written in; lines of any other version
+    /// are rejected
+    pub version: u32,
+    /// The op or message
+    pub body: T,
+}
+
+impl<T> Envelope<T> {
+    /// Wrap a message in the current protocol version
+    pub fn new(body: T) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            body,
+        }
+    }
+}
+
+/// A line written to stdout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum StdioMessage {
+    /// The process is ready to read ops
+    Ready {
+        /// Crate version
+        version: String,
+    },
+    /// An orchestrator event
+    Event(GoblinEvent),
+    /// An input line could not be handled
+    Error {
+        /// Line number on stdin, starting at 1
+        line: Option<u64>,
+        message: String,
+    },
+}
+
+/// Run an orchestrator on this process's stdin and stdout until stdin closes
+///
+/// `setup` gets the orchestrator before the first line of stdin is read,
+/// so its options and rules hold from the frontend's first op.
+pub async fn run(
+    tools: ToolRegistry,
+    setup: impl FnOnce(Orchestrator) -> Orchestrator,
+) -> Result<(), GoblinError> {
+    let (op_tx, op_rx) = mpsc::unbounded_channel();
+    let (event_tx, event_rx) = mpsc::unbounded_channel();
+    let pair = ChannelPair {
+        op_rx: op_rx.into(),
+        event_tx: event_tx.into(),
+        replay: None,
+    };
+    let orchestrator = setup(Orchestrator::new(tools, pair));
+
+    // Agents may still hold event senders when the orchestrator stops, so
+    // its end is signalled separately from the event channel closing
+    let (stopped_tx, stopped_rx) = oneshot::channel();
+    let orchestrator = async move {
+        let result = orchestrator.run().await;
+        let _ = stopped_tx.send(());
+        result
+    };
+    let stopped = async move {
+        let _ = stopped_rx.await;
+    };
+    let (result, served) = tokio::join!(
+        orchestrator,
+        forward(tokio::io::stdin(), tokio::io::stdout(), op_tx, event_rx, stopped),
+    );
+    served.and(result)
+}
+
+/// Serve the protocol on `input` and `output`, forwarding ops to `op_tx`
+/// and writing events from `event_rx`
+///
+/// Returns once `input` is closed and `event_rx` has no more events.
+pub async fn serve(
+    input: impl AsyncRead + Unpin,
+    output: impl AsyncWrite + Unpin,
+    op_tx: mpsc::UnboundedSender<GoblinOp>,
+    event_rx: mpsc::UnboundedReceiver<GoblinEvent>,
+) -> Result<(), GoblinError> {
+    forward(input, output, op_tx, event_rx, std::future::pending()).await
+}
+
+async fn forward(
+    input: impl AsyncRead + Unpin,
+    output: impl AsyncWrite + Unpin,
+    op_tx: mpsc::UnboundedSender<GoblinOp>,
+    mut event_rx: mpsc::UnboundedReceiver<GoblinEvent>,
+    stopped: impl Future<Output = ()>,
+) -> Result<(), GoblinError> {
+    let mut writer = Writer { output };
+    let mut lines = BufReader::new(input).lines();
+    // Dropped when input closes, which stops the orchestrator
+    let mut op_tx = Some(op_tx);
+    let mut events_open = true;
+    let mut line_no = 0;
+    tokio::pin!(stopped);
+
+    writer
+        .write(StdioMessage::Ready {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        })
+        .await?;
+
+    while op_tx.is_some() || events_open {
+        tokio::select! {
+            biased;
+            event = event_rx.recv(), if events_open => match event {
+                Some(event) => writer.write(StdioMessage::Event(event)).await?,
+                // Keep reading ops, even with nobody left to answer them
+                None => events_open = false,
+            },
+            line = lines.next_line(), if op_tx.is_some() => {
+                let Some(line) = line.map_err(io)? else {
+                    debug!("Stdin closed");
+                    op_tx = None;
+                    continue;
+                };
+                line_no += 1;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match decode::<GoblinOp>(&line) {
+                    Ok(op) => {
+                        if op_tx.as_ref().is_some_and(|tx| tx.send(op).is_err()) {
+                            op_tx = None;
+                        }
+                    }
+                    Err(message) => {
+                        warn!(line = line_no, error = %message, "Malformed stdio message");
+                        writer.write(StdioMessage::Error { line: Some(line_no), message }).await?;
+                    }
+                }
+            }
+            _ = &mut stopped => {
+                while let Ok(event) = event_rx.try_recv() {
+                    writer.write(StdioMessage::Event(event)).await?;
+                }
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Decode the body of an envelope, checking its version first
+pub(crate) fn decode<T: DeserializeOwned>(line: &str) -> Result<T, String> {
+    let value: Value = serde_json::from_str(line).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| "Missing protocol version".to_string())?;
+    if version != u64::from(PROTOCOL_VERSION) {
+        return Err(format!(
+            "Unsupported protocol version {}, expected {}",
+            version, PROTOCOL_VERSION
+        ));
+    }
+    let envelope: Envelope<T> =
+        serde_json::from_value(value).map_err(|e| format!("Invalid message: {}", e))?;
+    Ok(envelope.body)
+}
+
+fn io(e: std::io::Error) -> GoblinError {
+    GoblinError::TransportError(e.to_string())
+}
+
+struct Writer<W> {
+    output: W,
+}
+
+impl<W: AsyncWrite + Unpin> Writer<W> {
+    async fn write(&mut self, message: StdioMessage) -> Result<(), GoblinError> {
+        let mut line = match serde_json::to_string(&Envelope::new(message)) {
+            Ok(line) => line,
+            Err(e) => serde_json::to_string(&Envelope::new(StdioMessage::Error {
+                line: None,
+                message: format!("Unserializable event: {}", e),
+            }))
+            .map_err(|e| GoblinError::TransportError(e.to_string()))?,
+        };
+        line.push('\n');
+        self.output.write_all(line.as_bytes()).await.map_err(io)?;
+        // Frontends read line by line, so nothing may sit in a buffer
+        self.output.flush().await.map_err(io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{DuplexStream, Lines};
+    use warhorn::{Event, Op, SubmissionId};
+
+    async fn next(output: &mut Lines<BufReader<DuplexStream>>) -> Envelope<StdioMessage> {
+        let line = output.next_line().await.unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_forwards_ops_and_events() {
+        let (mut input, served_input) = tokio::io::duplex(4096);
+        let (served_output, output) = tokio::io::duplex(4096);
+        let (op_tx, mut op_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let server = tokio::spawn(serve(served_input, served_output, op_tx, event_rx));
+        let mut output = BufReader::new(output).lines();
+
+        let ready = next(&mut output).await;
+        assert_eq!(ready.version, PROTOCOL_VERSION);
+        assert!(matches!(ready.body, StdioMessage::Ready { .. }));
+
+        let op: GoblinOp = Op::interrupt().into();
+        let mut line = serde_json::to_string(&Envelope::new(op.clone())).unwrap();
+        line.push('\n');
+        input.write_all(line.as_bytes()).await.unwrap();
+        assert_eq!(op_rx.recv().await.unwrap().sub_id(), op.sub_id());
+
+        let sub_id = SubmissionId::new();
+        event_tx
+            .send(Event::Warning { sub_id: sub_id.clone(), message: "slow".into(), details: None }.into())
+            .unwrap();
+        match next(&mut output).await.body {
+            StdioMessage::Event(event) => assert_eq!(event.sub_id(), Some(sub_id)),
+            other => panic!("unexpected message: {:?}", other),
+        }
+
+        // Closing both ends finishes the stream
+        drop(input);
+        drop(event_tx);
+        server.await.unwrap().unwrap();
+        assert!(op_rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reports_bad_lines_and_continues() {
+        let (mut input, served_input) = tokio::io::duplex(4096);
+        let (served_output, output) = tokio::io::duplex(4096);
+        let (op_tx, mut op_rx) = mpsc::unbounded_channel();
+        let (_event_tx, event_rx) = mpsc::unbounded_channel::<GoblinEvent>();
+        tokio::spawn(serve(served_input, served_output, op_tx, event_rx));
+        let mut output = BufReader::new(output).lines();
+        next(&mut output).await;
+
+        let op: GoblinOp = Op::interrupt().into();
+        let future = serde_json::json!({ "version": PROTOCOL_VERSION + 1, "body": op });
+        let current = serde_json::to_string(&Envelope::new(op.clone())).unwrap();
+        let lines = format!("not json\n{}\n\n{}\n", future, current);
+        input.write_all(lines.as_bytes()).await.unwrap();
+
+        for expected in [1, 2] {
+            match next(&mut output).await.body {
+                StdioMessage::Error { line, .. } => assert_eq!(line, Some(expected)),
+                other => panic!("unexpected message: {:?}", other),
+            }
+        }
+        assert_eq!(op_rx.recv().await.unwrap().sub_id(), op.sub_id());
+    }
+}
@@ -0,0 +1,236 @@
+//! Shared session blackboard
+//!
+//! A [`SessionStore`] holds small facts agents share with each other, such
+//! as an API base URL or a naming scheme, without routing them through a
+//! lead. Every key carries a version that starts at 1 and grows with each
+//! write, so agents can update a key with compare-and-swap instead of
+//! overwriting each other. Every change is reported as a `StoreChanged`
+//! event.
+//!
+//! Agents use the store through the [`STORE_TOOL`] tool, clients through
+//! the `StoreGet` and `StoreSet` ops.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use warhorn::{AgentId, SubmissionId};
+
+use crate::protocol::GoblinEvent;
+
+/// Name of the tool agents use to read and write the store
+pub const STORE_TOOL: &str = "session_store";
+
+/// A value in the store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoreEntry {
+    pub value: Value,
+    /// Number of writes to the key so far
+    pub version: u64,
+    /// Agent that wrote the value, None for clients
+    pub updated_by: Option<AgentId>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Arguments of a [`STORE_TOOL`] call
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum StoreRequest {
+    Get {
+        key: String,
+    },
+    Set {
+        key: String,
+        value: Value,
+    },
+    /// Write only if the key is at `expected_version`; 0 means absent
+    CompareAndSwap {
+        key: String,
+        expected_version: u64,
+        value: Value,
+    },
+}
+
+/// JSON schema of the [`STORE_TOOL`] arguments
+pub fn tool_schema() -> Value {
+    json!({
+        "type": "object",
+        "description": "Read and write facts shared with the other agents of the session",
+        "properties": {
+            "action": { "type": "string", "enum": ["get", "set", "compare_and_swap"] },
+            "key": { "type": "string" },
+            "value": { "description": "Value to write, for set and compare_and_swap" },
+            "expected_version": {
+                "type": "integer",
+                "description": "Version the key must be at for compare_and_swap, 0 if it must not exist"
+            }
+        },
+        "required": ["action", "key"]
+    })
+}
+
+/// Concurrent key-value store shared by a session's agents
+#[derive(Debug)]
+pub struct SessionStore {
+    entries: RwLock<HashMap<String, StoreEntry>>,
+    event_tx: mpsc::UnboundedSender<GoblinEvent>,
+}
+
+impl SessionStore {
+    /// Create an empty store reporting changes on `event_tx`
+    pub fn new(event_tx: mpsc::UnboundedSender<GoblinEvent>) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            event_tx,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<StoreEntry> {
+        self.entries.read().get(key).cloned()
+    }
+
+    /// All keys, sorted
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.entries.read().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Write a value unconditionally
+    pub fn set(
+        &self,
+        key: impl Into<String>,
+        value: Value,
+        agent_id: Option<AgentId>,
+        sub_id: &SubmissionId,
+    ) -> StoreEntry {
+        let key = key.into();
+        let entry = {
+            let mut entries = self.entries.write();
+            let version = entries.get(&key).map_or(0, |e| e.version) + 1;
+            let entry = StoreEntry {
+                value,
+                version,
+                updated_by: agent_id,
+                updated_at: Utc::now(),
+            };
+            entries.insert(key.clone(), entry.clone());
+            entry
+        };
+        self.changed(key, &entry, sub_id);
+        entry
+    }
+
+    /// Write a value if the key is still at `expected_version` (0 if absent)
+    ///
+    /// On a conflict nothing is written and the current entry is returned.
+    pub fn compare_and_swap(
+        &self,
+        key: impl Into<String>,
+        expected_version: u64,
+        value: Value,
+        agent_id: Option<AgentId>,
+        sub_id: &SubmissionId,
+    ) -> Result<StoreEntry, Option<StoreEntry>> {
+        let key = key.into();
+        let entry = {
+            let mut entries = self.entries.write();
+            let current = entries.get(&key);
+            if current.map_or(0, |e| e.version) != expected_version {
+                return Err(current.cloned());
+            }
+            let entry = StoreEntry {
+                value,
+                version: expected_version + 1,
+                updated_by: agent_id,
+                updated_at: Utc::now(),
+            };
+            entries.insert(key.clone(), entry.clone());
+            entry
+        };
+        self.changed(key, &entry, sub_id);
+        Ok(entry)
+    }
+
+    /// Carry out a [`STORE_TOOL`] call, returning the tool's output
+    pub fn handle(&self, request: StoreRequest, agent_id: Option<AgentId>, sub_id: &SubmissionId) -> Value {
+        match request {
+            StoreRequest::Get { key } => json!({ "key": key, "entry": self.get(&key) }),
+            StoreRequest::Set { key, value } => {
+                let entry = self.set(key.clone(), value, agent_id, sub_id);
+                json!({ "key": key, "entry": entry })
+            }
+            StoreRequest::CompareAndSwap { key, expected_version, value } => {
+                match self.compare_and_swap(key.clone(), expected_version, value, agent_id, sub_id) {
+                    Ok(entry) => json!({ "key": key, "swapped": true, "entry": entry }),
+                    Err(current) => json!({ "key": key, "swapped": false, "entry": current }),
+                }
+            }
+        }
+    }
+
+    fn changed(&self, key: String, entry: &StoreEntry, sub_id: &SubmissionId) {
+        let _ = self.event_tx.send(GoblinEvent::StoreChanged {
+            sub_id: sub_id.clone(),
+            key,
+            entry: entry.clone(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_compare_and_swap() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let store = SessionStore::new(tx);
+        let sub_id = SubmissionId::new();
+        let agent = AgentId::new();
+
+        // Version 0 claims a key that does not exist yet
+        let first = store.compare_and_swap("api_base", 0, json!("http://localhost:8080"), Some(agent), &sub_id);
+        assert_eq!(first.unwrap().version, 1);
+        let conflict = store.compare_and_swap("api_base", 0, json!("http://other"), None, &sub_id);
+        assert_eq!(conflict.unwrap_err().unwrap().value, json!("http://localhost:8080"));
+
+        let second = store.set("api_base", json!("https://api.example.com"), None, &sub_id);
+        assert_eq!((second.version, second.updated_by), (2, None));
+        assert_eq!(store.get("api_base"), Some(second));
+        assert_eq!(store.keys(), vec!["api_base"]);
+
+        let changes: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok())
+            .filter_map(|e| match e {
+                GoblinEvent::StoreChanged { entry, .. } => Some(entry.version),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(changes, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_tool_requests() {
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let store = SessionStore::new(tx);
+        let sub_id = SubmissionId::new();
+
+        let request: StoreRequest = serde_json::from_value(json!({
+            "action": "compare_and_swap",
+            "key": "naming",
+            "expected_version": 0,
+            "value": "snake_case"
+        }))
+        .unwrap();
+        assert_eq!(store.handle(request.clone(), None, &sub_id)["swapped"], json!(true));
+        assert_eq!(store.handle(request, None, &sub_id)["swapped"], json!(false));
+
+        let got = store.handle(StoreRequest::Get { key: "naming".into() }, None, &sub_id);
+        assert_eq!(got["entry"]["value"], json!("snake_case"));
+        let missing = store.handle(StoreRequest::Get { key: "other".into() }, None, &sub_id);
+        assert!(missing["entry"].is_null());
+    }
+}
@@ -0,0 +1,146 @@
+//! Supervision of failed agents
+//!
+//! When an agent's run loop returns an error or panics, the session looks
+//! up the [`SupervisionPolicy`] of the agent's role. A one-for-one policy
+//! restarts the agent alone, waiting longer before each attempt, until it
+//! has been restarted `max_restarts` times; then it gives up. Escalating
+//! terminates the agent and applies its parent's policy to the parent, as
+//! if the parent had failed. Giving up terminates the agent. Agents whose
+//! role has no policy are left stopped, with what they had learned handed
+//! to their parent.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use warhorn::AgentRole;
+
+use crate::runtime::by_role;
+use crate::error::GoblinError;
+
+/// Growing delay between attempts
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Backoff {
+    /// Delay before the first attempt, in milliseconds
+    pub initial_ms: u64,
+    /// Longest delay, in milliseconds
+    pub max_ms: u64,
+    /// Factor each delay grows by
+    pub factor: f64,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial_ms: 100,
+            max_ms: 30_000,
+            factor: 2.0,
+        }
+    }
+}
+
+impl Backoff {
+    pub fn new(initial_ms: u64, max_ms: u64) -> Self {
+        Self {
+            initial_ms,
+            max_ms,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before attempt `attempt`, counting from 1
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let grown = self.initial_ms as f64 * self.factor.powi(attempt.saturating_sub(1) as i32);
+        Duration::from_millis(grown.min(self.max_ms as f64) as u64)
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if !(self.factor.is_finite() && self.factor >= 1.0) || self.initial_ms > self.max_ms {
+            return Err(GoblinError::ConfigError(
+                "backoff factor must be at least 1 and initial_ms at most max_ms".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What is done when an agent's run loop fails
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum SupervisionPolicy {
+    /// Restart the failed agent alone, then give up after `max_restarts`
+    OneForOne {
+        max_restarts: u32,
+        #[serde(default)]
+        backoff: Backoff,
+    },
+    /// Terminate the failed agent and apply the parent's policy to the parent
+    Escalate,
+    /// Terminate the failed agent
+    GiveUp,
+}
+
+impl SupervisionPolicy {
+    /// Restart up to `max_restarts` times with the default backoff
+    pub fn one_for_one(max_restarts: u32) -> Self {
+        Self::OneForOne {
+            max_restarts,
+            backoff: Backoff::default(),
+        }
+    }
+}
+
+/// Supervision policies of a session's agents, by role
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SupervisionPolicies {
+    /// Policy of agents whose role has none of its own
+    #[serde(default)]
+    pub default: Option<SupervisionPolicy>,
+    /// Policies by role key, like `worker` or `specialist:reviewer`
+    #[serde(default)]
+    pub roles: HashMap<String, SupervisionPolicy>,
+}
+
+impl SupervisionPolicies {
+    /// Supervise every agent this way unless its role has its own policy
+    pub fn with_default(mut self, policy: SupervisionPolicy) -> Self {
+        self.default = Some(policy);
+        self
+    }
+
+    /// Supervise agents with a role this way
+    pub fn with_role(mut self, role: impl Into<String>, policy: SupervisionPolicy) -> Self {
+        self.roles.insert(role.into(), policy);
+        self
+    }
+
+    /// Policy of an agent with `role`
+    pub fn policy_for(&self, role: &AgentRole) -> Option<SupervisionPolicy> {
+        by_role(&self.roles, role).or(self.default.as_ref()).copied()
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        self.default.iter().chain(self.roles.values()).try_for_each(|policy| match policy {
+            SupervisionPolicy::OneForOne { backoff, .. } => backoff.validate(),
+            _ => Ok(()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_grows_to_its_cap() {
+        let backoff = Backoff::new(100, 500);
+        let delays: Vec<u64> = (1..=5).map(|n| backoff.delay(n).as_millis() as u64).collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+
+        let policies = SupervisionPolicies::default()
+            .with_default(SupervisionPolicy::GiveUp)
+            .with_role("worker", SupervisionPolicy::one_for_one(3));
+        assert!(matches!(policies.policy_for(&AgentRole::Worker), Some(SupervisionPolicy::OneForOne { max_restarts: 3, .. })));
+        assert_eq!(policies.policy_for(&AgentRole::Orchestrator), Some(SupervisionPolicy::GiveUp));
+    }
+}
@@ -0,0 +1,96 @@
+//! Tasks running in a session
+//!
+//! A session works on any number of submitted tasks at once. Each one is
+//! registered as an [`ActiveTask`] from the moment it is submitted until
+//! its result is complete or it is interrupted; the agents serving it are
+//! those working on the task itself or on one of the subtasks of its plan.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use warhorn::TaskId;
+
+/// A submitted task that has not finished yet
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveTask {
+    pub task_id: TaskId,
+    pub prompt: String,
+    pub started_at: DateTime<Utc>,
+}
+
+impl ActiveTask {
+    pub fn new(task_id: TaskId, prompt: impl Into<String>) -> Self {
+        Self {
+            task_id,
+            prompt: prompt.into(),
+            started_at: Utc::now(),
+        }
+    }
+}
+
+/// Active tasks of a session, by ID
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+    tasks: HashMap<TaskId, ActiveTask>,
+}
+
+impl TaskRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a task, replacing an earlier registration with the same ID
+    pub fn start(&mut self, task: ActiveTask) {
+        self.tasks.insert(task.task_id, task);
+    }
+
+    /// Unregister a finished or interrupted task
+    pub fn finish(&mut self, task_id: &TaskId) -> Option<ActiveTask> {
+        self.tasks.remove(task_id)
+    }
+
+    pub fn get(&self, task_id: &TaskId) -> Option<&ActiveTask> {
+        self.tasks.get(task_id)
+    }
+
+    pub fn contains(&self, task_id: &TaskId) -> bool {
+        self.tasks.contains_key(task_id)
+    }
+
+    /// All active tasks, oldest first
+    pub fn list(&self) -> Vec<ActiveTask> {
+        let mut tasks: Vec<ActiveTask> = self.tasks.values().cloned().collect();
+        tasks.sort_by_key(|t| t.started_at);
+        tasks
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_keeps_tasks_apart() {
+        let mut registry = TaskRegistry::new();
+        let first = ActiveTask::new(TaskId::new(), "Fix the parser");
+        let mut second = ActiveTask::new(TaskId::new(), "Write the docs");
+        second.started_at = first.started_at + chrono::Duration::seconds(1);
+        registry.start(second.clone());
+        registry.start(first.clone());
+
+        assert_eq!(registry.list(), vec![first.clone(), second.clone()]);
+        assert_eq!(registry.finish(&first.task_id), Some(first.clone()));
+        assert!(!registry.contains(&first.task_id));
+        assert_eq!(registry.get(&second.task_id), Some(&second));
+        assert_eq!(registry.len(), 1);
+    }
+}
@@ -0,0 +1,29 @@
+//! Fixtures shared by the tests of several modules
+
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+use trinkets::ToolRegistry;
+use warhorn::{AgentConfig, AgentRole, SessionConfig, SubmissionId};
+
+use crate::protocol::GoblinEvent;
+use crate::session::{Session, SessionHandle};
+
+/// A session with a root orchestrator that may spawn, the receiver of its
+/// events and the submission the root was spawned for
+pub(crate) fn session_with_root() -> (SessionHandle, mpsc::UnboundedReceiver<GoblinEvent>, SubmissionId) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let session = SessionHandle::new(Session::new(
+        SessionConfig::default(),
+        Arc::new(ToolRegistry::new()),
+        tx,
+    ));
+    let sub_id = SubmissionId::new();
+    let root = AgentConfig {
+        role: AgentRole::Orchestrator,
+        can_spawn: true,
+        ..Default::default()
+    };
+    session.spawn_agent(root, None, &sub_id).unwrap();
+    (session, rx, sub_id)
+}
@@ -0,0 +1,311 @@
+//! Results of read-only tool calls shared between a session's agents
+//!
+//! Workers often run the same read-only call, the same grep or file read,
+//! within moments of each other. With a [`ToolCachePolicy`] in the session
+//! options, runtimes look calls of the cached tools up in the session's
+//! [`ToolCache`] before running them and store their results after. Calls
+//! are keyed by tool and parameters, whatever the order of object keys, and
+//! by the [`CallContext`] they run in, so agents in other directories or
+//! sandboxes do not share results. Results expire after the policy's TTL.
+//! A call of a tool the policy names as writing clears the cache when it
+//! starts and again when it ends, since reads that ran alongside it may
+//! have stored what it overwrote.
+//!
+//! Tools are named exactly, or by prefix with a trailing `*` (`read_*`).
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::GoblinError;
+use crate::sandbox::SandboxProfile;
+use crate::toolscope::pattern_matches;
+
+/// Which tool results are cached and for how long
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCachePolicy {
+    /// Time a result is served from the cache, in milliseconds
+    pub ttl_ms: u64,
+    /// Read-only tools whose results are cached
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Tools that change what cached tools read; calling one clears the cache
+    #[serde(default)]
+    pub writes: Vec<String>,
+    /// Results kept at most, the oldest being dropped first
+    #[serde(default = "default_max_entries")]
+    pub max_entries: usize,
+}
+
+fn default_max_entries() -> usize {
+    1024
+}
+
+impl ToolCachePolicy {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl_ms: ttl.as_millis() as u64,
+            tools: Vec::new(),
+            writes: Vec::new(),
+            max_entries: default_max_entries(),
+        }
+    }
+
+    /// Cache the results of these tools
+    pub fn with_tools<S: Into<String>>(mut self, tools: impl IntoIterator<Item = S>) -> Self {
+        self.tools.extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    /// Clear the cache whenever one of these tools is called
+    pub fn with_writes<S: Into<String>>(mut self, tools: impl IntoIterator<Item = S>) -> Self {
+        self.writes.extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn ttl(&self) -> Duration {
+        Duration::from_millis(self.ttl_ms)
+    }
+
+    /// Whether results of a tool are cached
+    pub fn caches(&self, tool: &str) -> bool {
+        self.tools.iter().any(|p| pattern_matches(p, tool))
+    }
+
+    /// Whether calling a tool clears the cache
+    pub fn writes(&self, tool: &str) -> bool {
+        self.writes.iter().any(|p| pattern_matches(p, tool))
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.ttl_ms == 0 || self.max_entries == 0 {
+            return Err(GoblinError::ConfigError(
+                "tool cache ttl_ms and max_entries must be at least 1".into(),
+            ));
+        }
+        if let Some(tool) = self.tools.iter().find(|t| self.writes(t)) {
+            return Err(GoblinError::ConfigError(format!("tool cache caches writing tool {}", tool)));
+        }
+        Ok(())
+    }
+}
+
+/// What the result of a call depends on besides its tool and parameters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CallContext {
+    /// Directory relative paths resolve against
+    pub cwd: PathBuf,
+    /// Sandbox the call runs in, with exceptions applied
+    pub sandbox: SandboxProfile,
+}
+
+/// Hits and misses of a tool cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCacheStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Times a writing tool cleared the cache
+    pub invalidations: u64,
+}
+
+struct Cached {
+    result: Value,
+    stored: Instant,
+}
+
+/// A session's cache of tool results
+pub struct ToolCache {
+    policy: ToolCachePolicy,
+    entries: Mutex<HashMap<String, Cached>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+impl ToolCache {
+    pub fn new(policy: ToolCachePolicy) -> Self {
+        Self {
+            policy,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            invalidations: AtomicU64::new(0),
+        }
+    }
+
+    pub fn policy(&self) -> &ToolCachePolicy {
+        &self.policy
+    }
+
+    /// Cached result of a call, if the tool is cached and the result fresh
+    pub fn get(&self, tool: &str, params: &Value, context: &CallContext) -> Option<Value> {
+        if !self.policy.caches(tool) {
+            return None;
+        }
+        let key = key(tool, params, context);
+        let mut entries = self.entries.lock();
+        let fresh = entries.get(&key).filter(|c| c.stored.elapsed() < self.policy.ttl());
+        let result = fresh.map(|c| c.result.clone());
+        match &result {
+            Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+            None => {
+                entries.remove(&key);
+                self.misses.fetch_add(1, Ordering::Relaxed)
+            }
+        };
+        result
+    }
+
+    /// Store the result of a call, if the tool is cached
+    pub fn put(&self, tool: &str, params: &Value, context: &CallContext, result: Value) {
+        if !self.policy.caches(tool) {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        if entries.len() >= self.policy.max_entries {
+            let ttl = self.policy.ttl();
+            entries.retain(|_, c| c.stored.elapsed() < ttl);
+        }
+        if entries.len() >= self.policy.max_entries {
+            if let Some(oldest) = entries.iter().min_by_key(|(_, c)| c.stored).map(|(k, _)| k.clone()) {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(key(tool, params, context), Cached { result, stored: Instant::now() });
+    }
+
+    /// Note that a tool is being called or a call of it ended, clearing the
+    /// cache if it writes
+    ///
+    /// Returns whether the cache was cleared.
+    pub fn observe_call(&self, tool: &str) -> bool {
+        if !self.policy.writes(tool) {
+            return false;
+        }
+        self.entries.lock().clear();
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    pub fn stats(&self) -> ToolCacheStats {
+        ToolCacheStats {
+            entries: self.entries.lock().len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl std::fmt::Debug for ToolCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolCache")
+            .field("policy", &self.policy)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// Key of a call, the same for parameters that differ only in key order
+pub(crate) fn key(tool: &str, params: &Value, context: &CallContext) -> String {
+    let mut key = String::from(tool);
+    key.push('\0');
+    canonicalize(params, &mut key);
+    key.push('\0');
+    canonicalize(&serde_json::to_value(context).unwrap_or_default(), &mut key);
+    key
+}
+
+fn canonicalize(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut fields: Vec<_> = map.iter().collect();
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            out.push('{');
+            for (i, (name, value)) in fields.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(name.clone()).to_string());
+                out.push(':');
+                canonicalize(value, out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                canonicalize(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_writes_clear_cached_reads() {
+        let cache = ToolCache::new(
+            ToolCachePolicy::new(Duration::from_secs(60))
+                .with_tools(["read_file", "grep"])
+                .with_writes(["write_*"]),
+        );
+        let ctx = CallContext::default();
+        cache.put("read_file", &json!({"path": "a.rs", "lines": [1, 20]}), &ctx, json!("fn main() {}"));
+        cache.put("shell_exec", &json!({"command": "ls"}), &ctx, json!("a.rs"));
+
+        let reordered = json!({"lines": [1, 20], "path": "a.rs"});
+        assert_eq!(cache.get("read_file", &reordered, &ctx), Some(json!("fn main() {}")));
+        assert_eq!(cache.get("shell_exec", &json!({"command": "ls"}), &ctx), None);
+        assert!(!cache.observe_call("grep"));
+        assert!(cache.observe_call("write_file"));
+        assert_eq!(cache.get("read_file", &reordered, &ctx), None);
+        assert_eq!(cache.stats(), ToolCacheStats { entries: 0, hits: 1, misses: 1, invalidations: 1 });
+    }
+
+    #[test]
+    fn test_results_are_kept_per_directory_and_sandbox() {
+        use crate::approval::SandboxLevel;
+
+        let cache = ToolCache::new(ToolCachePolicy::new(Duration::from_secs(60)).with_tools(["read_file"]));
+        let params = json!({"path": "a.rs"});
+        let repo = CallContext {
+            cwd: "/work/repo".into(),
+            sandbox: SandboxProfile::new().with_level(SandboxLevel::ReadOnly),
+        };
+        cache.put("read_file", &params, &repo, json!("fn main() {}"));
+
+        let fork = CallContext { cwd: "/work/fork".into(), ..repo.clone() };
+        let writable = CallContext {
+            sandbox: SandboxProfile::new().with_level(SandboxLevel::WorkspaceWrite),
+            ..repo.clone()
+        };
+        assert_eq!(cache.get("read_file", &params, &fork), None);
+        assert_eq!(cache.get("read_file", &params, &writable), None);
+        assert_eq!(cache.get("read_file", &params, &repo), Some(json!("fn main() {}")));
+    }
+
+    #[test]
+    fn test_results_expire() {
+        let cache = ToolCache::new(ToolCachePolicy::new(Duration::from_millis(10)).with_tools(["grep"]));
+        let ctx = CallContext::default();
+        cache.put("grep", &json!({"pattern": "todo"}), &ctx, json!([]));
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(cache.get("grep", &json!({"pattern": "todo"}), &ctx), None);
+        assert!(ToolCachePolicy::new(Duration::ZERO).validate().is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Rate and concurrency limits of tools shared by a session's agents
+//!
+//! A tool that wraps an external API must not be called by every worker of
+//! a session at once. [`ToolLimit`]s in the session options, by tool name
+//! or pattern, cap how often the tools they match are called, as a
+//! [`RateLimit`], and how many of their calls run at once, across all of
+//! the session's agents. Tools matched by one pattern share its limits, so
+//! `github_*` limits every tool of the same API together. Calls over a
+//! limit wait for their turn rather than fail.
+//!
+//! Exact tool names take precedence over patterns.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::GoblinError;
+use crate::ratelimit::{RateLimit, TokenBucket};
+use crate::toolscope::pattern_matches;
+
+/// Limits of the tools a name or pattern matches
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolLimit {
+    /// How often the tools may be called
+    #[serde(default)]
+    pub rate: Option<RateLimit>,
+    /// Calls of the tools running at once
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+}
+
+impl ToolLimit {
+    pub fn with_rate(mut self, rate: RateLimit) -> Self {
+        self.rate = Some(rate);
+        self
+    }
+
+    pub fn with_concurrency(mut self, calls: usize) -> Self {
+        self.concurrency = Some(calls);
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if let Some(rate) = &self.rate {
+            rate.validate()?;
+        }
+        if self.concurrency == Some(0) {
+            return Err(GoblinError::ConfigError("tool limit concurrency must be at least 1".into()));
+        }
+        Ok(())
+    }
+}
+
+/// A session's tool limits and the calls they hold
+pub struct ToolLimits {
+    limits: HashMap<String, ToolLimit>,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+    slots: HashMap<String, Arc<Semaphore>>,
+    waiting: AtomicUsize,
+}
+
+/// Permission to run a limited call, held until it ends
+#[derive(Debug, Default)]
+pub struct ToolPermit {
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+impl ToolLimits {
+    pub fn new(limits: HashMap<String, ToolLimit>) -> Self {
+        let slots = limits
+            .iter()
+            .filter_map(|(pattern, limit)| Some((pattern.clone(), Arc::new(Semaphore::new(limit.concurrency?)))))
+            .collect();
+        Self {
+            limits,
+            buckets: Mutex::new(HashMap::new()),
+            slots,
+            waiting: AtomicUsize::new(0),
+        }
+    }
+
+    /// Name or pattern whose limits apply to a tool
+    fn pattern_for(&self, tool: &str) -> Option<&str> {
+        if self.limits.contains_key(tool) {
+            return Some(tool);
+        }
+        self.limits
+            .keys()
+            .filter(|p| pattern_matches(p, tool))
+            .max_by_key(|p| p.len())
+            .map(String::as_str)
+    }
+
+    /// Wait until a call of a tool is within its limits
+    pub async fn acquire(&self, tool: &str) -> ToolPermit {
+        let Some(pattern) = self.pattern_for(tool) else {
+            return ToolPermit::default();
+        };
+        self.waiting.fetch_add(1, Ordering::Relaxed);
+        let _waiting = Waiting(&self.waiting);
+
+        let slot = match self.slots.get(pattern) {
+            Some(slots) => Arc::clone(slots).acquire_owned().await.ok(),
+            None => None,
+        };
+        if let Some(rate) = self.limits[pattern].rate {
+            loop {
+                let taken = self
+                    .buckets
+                    .lock()
+                    .entry(pattern.to_string())
+                    .or_insert_with(|| TokenBucket::new(rate))
+                    .try_take();
+                match taken {
+                    Ok(()) => break,
+                    Err(wait) => tokio::time::sleep(wait).await,
+                }
+            }
+        }
+        ToolPermit { _slot: slot }
+    }
+
+    /// Calls waiting for a limit now
+    pub fn waiting(&self) -> usize {
+        self.waiting.load(Ordering::Relaxed)
+    }
+}
+
+impl std::fmt::Debug for ToolLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolLimits")
+            .field("limits", &self.limits)
+            .field("waiting", &self.waiting())
+            .finish()
+    }
+}
+
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pattern_limits_calls_of_every_matching_tool() {
+        let limits = ToolLimits::new(HashMap::from([(
+            "github_*".to_string(),
+            ToolLimit::default().with_concurrency(1).with_rate(RateLimit::new(2, 600)),
+        )]));
+
+        let held = limits.acquire("github_issues").await;
+        let second = tokio::time::timeout(Duration::from_millis(20), limits.acquire("github_pulls")).await;
+        assert!(second.is_err());
+        drop(held);
+
+        let _permit = limits.acquire("github_pulls").await;
+        assert_eq!(limits.waiting(), 0);
+        let _free = limits.acquire("read_file").await;
+        assert!(ToolLimit::default().with_concurrency(0).validate().is_err());
+    }
+}
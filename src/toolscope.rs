@@ -0,0 +1,304 @@
+//! Role- and depth-scoped tool access
+//!
+//! Every agent of a session shares one tool registry, but not every agent
+//! should use every tool: a worker deep in the tree has no business
+//! spawning agents or reaching the network. [`ToolScope`]s in the session
+//! options narrow the tools of the agents they match, and each agent gets
+//! a [`ToolView`] of the registry built from them when it is spawned.
+//!
+//! A scope may also override the settings of tools for the agents it
+//! matches, like a tool's timeout or parameters fixed for every call. An
+//! agent spawned with [`crate::Session::spawn_agent_with_tools`] gets its
+//! own scope on top of the session's, which is applied last.
+//!
+//! Tools are named exactly, or by prefix with a trailing `*` (`net_*`).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use trinkets::ToolRegistry;
+use warhorn::AgentRole;
+
+use crate::runtime::role_keys;
+
+/// Tools allowed or denied to the agents a scope matches
+///
+/// Every criterion that is set must match; no roles match any role.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolScope {
+    /// Roles of the agent, as keys like `worker` or `specialist:reviewer`
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Least depth of the agent in the hierarchy, the root being 0
+    #[serde(default)]
+    pub min_depth: Option<usize>,
+    /// Greatest depth of the agent in the hierarchy
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// The only tools the agent may use; any tool if empty
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Tools the agent may not use, even if allowed
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Settings of tools, by tool name or pattern
+    #[serde(default)]
+    pub overrides: HashMap<String, ToolOverride>,
+}
+
+/// Settings of a tool that replace the registry's for some agents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolOverride {
+    /// Longest a call may run, in milliseconds
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    /// Parameters set on every call, replacing the agent's values
+    #[serde(default)]
+    pub params: Map<String, Value>,
+}
+
+impl ToolOverride {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout_ms = Some(timeout.as_millis() as u64);
+        self
+    }
+
+    /// Set a parameter on every call
+    pub fn with_param(mut self, name: impl Into<String>, value: Value) -> Self {
+        self.params.insert(name.into(), value);
+        self
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
+
+    /// Apply another override on top of this one
+    fn merge(&mut self, other: &ToolOverride) {
+        if other.timeout_ms.is_some() {
+            self.timeout_ms = other.timeout_ms;
+        }
+        self.params.extend(other.params.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+}
+
+impl ToolScope {
+    /// A scope matching every agent and restricting nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match only agents with one of these roles
+    pub fn with_roles<S: Into<String>>(mut self, roles: impl IntoIterator<Item = S>) -> Self {
+        self.roles.extend(roles.into_iter().map(Into::into));
+        self
+    }
+
+    /// Match only agents at least this deep
+    pub fn with_min_depth(mut self, depth: usize) -> Self {
+        self.min_depth = Some(depth);
+        self
+    }
+
+    /// Match only agents at most this deep
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Allow only these tools
+    pub fn with_allow<S: Into<String>>(mut self, tools: impl IntoIterator<Item = S>) -> Self {
+        self.allow.extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    /// Deny these tools
+    pub fn with_deny<S: Into<String>>(mut self, tools: impl IntoIterator<Item = S>) -> Self {
+        self.deny.extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    /// Override the settings of a tool, or of the tools a pattern names
+    pub fn with_override(mut self, tool: impl Into<String>, settings: ToolOverride) -> Self {
+        self.overrides.insert(tool.into(), settings);
+        self
+    }
+
+    /// Whether the scope applies to an agent with `role` at `depth`
+    pub fn matches(&self, role: &AgentRole, depth: usize) -> bool {
+        if self.min_depth.is_some_and(|min| depth < min) || self.max_depth.is_some_and(|max| depth > max) {
+            return false;
+        }
+        if self.roles.is_empty() {
+            return true;
+        }
+        let keys = role_keys(role);
+        self.roles.iter().any(|r| keys.contains(r))
+    }
+
+    fn permits(&self, tool: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|p| pattern_matches(p, tool)))
+            && !self.deny.iter().any(|p| pattern_matches(p, tool))
+    }
+}
+
+pub(crate) fn pattern_matches(pattern: &str, tool: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => tool.starts_with(prefix),
+        None => pattern == tool,
+    }
+}
+
+/// The tools of a registry one agent may use
+#[derive(Clone)]
+pub struct ToolView {
+    registry: Arc<ToolRegistry>,
+    /// Scopes that matched the agent, all of which must permit a tool
+    scopes: Vec<ToolScope>,
+    /// Scope given to this agent alone, applied last
+    own: Option<ToolScope>,
+}
+
+impl ToolView {
+    /// A view of every tool in the registry
+    pub fn all(registry: Arc<ToolRegistry>) -> Self {
+        Self {
+            registry,
+            scopes: Vec::new(),
+            own: None,
+        }
+    }
+
+    /// A view for an agent with `role` at `depth`, narrowed by the scopes
+    /// that match it
+    pub fn scoped(registry: Arc<ToolRegistry>, scopes: &[ToolScope], role: &AgentRole, depth: usize) -> Self {
+        Self {
+            registry,
+            scopes: scopes.iter().filter(|s| s.matches(role, depth)).cloned().collect(),
+            own: None,
+        }
+    }
+
+    /// Narrow the view further with a scope of the agent's own
+    ///
+    /// The scope's role and depth criteria are ignored.
+    pub fn with_own_scope(mut self, scope: ToolScope) -> Self {
+        self.own = Some(scope);
+        self
+    }
+
+    /// Scope given to this agent alone, if any
+    pub fn own_scope(&self) -> Option<&ToolScope> {
+        self.own.as_ref()
+    }
+
+    fn all_scopes(&self) -> impl Iterator<Item = &ToolScope> {
+        self.scopes.iter().chain(&self.own)
+    }
+
+    /// Whether the agent may use a tool, registered or built in
+    pub fn allows(&self, tool: &str) -> bool {
+        self.all_scopes().all(|s| s.permits(tool))
+    }
+
+    /// Settings of a tool for this agent, later scopes overriding earlier
+    /// ones and exact names overriding patterns
+    pub fn settings(&self, tool: &str) -> ToolOverride {
+        let mut settings = ToolOverride::default();
+        for scope in self.all_scopes() {
+            let mut matching: Vec<_> = scope
+                .overrides
+                .iter()
+                .filter(|(pattern, _)| pattern_matches(pattern, tool))
+                .collect();
+            matching.sort_by_key(|(pattern, _)| (!pattern.ends_with('*'), pattern.len()));
+            for (_, over) in matching {
+                settings.merge(over);
+            }
+        }
+        settings
+    }
+
+    /// Apply the agent's settings of a tool to the parameters of a call
+    pub fn configure(&self, tool: &str, params: &mut Value) {
+        let settings = self.settings(tool);
+        if let Value::Object(params) = params {
+            params.extend(settings.params);
+        }
+    }
+
+    /// Registered tools the agent may use, sorted
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .registry
+            .names()
+            .into_iter()
+            .map(|n| n.to_string())
+            .filter(|n| self.allows(n))
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// The whole registry, for running tools the view allows
+    pub fn registry(&self) -> &ToolRegistry {
+        &self.registry
+    }
+}
+
+impl std::fmt::Debug for ToolView {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolView")
+            .field("scopes", &self.scopes)
+            .field("own", &self.own)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_scopes_narrow_the_view() {
+        let scopes = [
+            ToolScope::new().with_roles(["worker"]).with_min_depth(2).with_deny(["net_*", "spawn_agent"]),
+            ToolScope::new().with_roles(["specialist:reviewer"]).with_allow(["read_file", "grep"]),
+        ];
+        let registry = Arc::new(ToolRegistry::new());
+
+        let shallow = ToolView::scoped(Arc::clone(&registry), &scopes, &AgentRole::Worker, 1);
+        assert!(shallow.allows("net_fetch") && shallow.allows("spawn_agent"));
+
+        let deep = ToolView::scoped(Arc::clone(&registry), &scopes, &AgentRole::Worker, 3);
+        assert!(!deep.allows("net_fetch"));
+        assert!(!deep.allows("spawn_agent"));
+        assert!(deep.allows("read_file"));
+
+        let reviewer = AgentRole::Specialist { specialty: "reviewer".into() };
+        let reviewer = ToolView::scoped(registry, &scopes, &reviewer, 1);
+        assert!(reviewer.allows("grep"));
+        assert!(!reviewer.allows("write_file"));
+    }
+
+    #[test]
+    fn test_own_scope_overrides_settings_last() {
+        let scopes = [ToolScope::new()
+            .with_override("shell_*", ToolOverride::default().with_timeout(Duration::from_secs(60)))
+            .with_override("shell_exec", ToolOverride::default().with_param("login", Value::Bool(false)))];
+        let own = ToolScope::new()
+            .with_deny(["net_*"])
+            .with_override("shell_*", ToolOverride::default().with_timeout(Duration::from_secs(5)));
+        let view = ToolView::scoped(Arc::new(ToolRegistry::new()), &scopes, &AgentRole::Worker, 2).with_own_scope(own);
+
+        assert!(!view.allows("net_fetch"));
+        assert_eq!(view.settings("shell_exec").timeout(), Some(Duration::from_secs(5)));
+        let mut params = serde_json::json!({"command": "ls", "login": true});
+        view.configure("shell_exec", &mut params);
+        assert_eq!(params, serde_json::json!({"command": "ls", "login": false}));
+        assert_eq!(view.settings("read_file"), ToolOverride::default());
+    }
+}
@@ -0,0 +1,408 @@
+//! WebSocket server for remote clients
+//!
+//! Remote UIs connect over WebSocket and speak the same versioned JSON
+//! [`Envelope`]s as the [stdio transport](crate::stdio), one per text
+//! frame. After a [`WsServerMessage::Welcome`] a connection receives every
+//! event until it sends a [`WsClientMessage::Subscribe`] narrowing the
+//! stream to what it shows; ops it sends go to the orchestrator.
+//!
+//! A server made with [`WebSocketServer::with_token`] only upgrades
+//! connections whose handshake carries `Authorization: Bearer <token>`;
+//! without a token anyone who can reach the address can drive the
+//! orchestrator, so keep such servers on a loopback address.
+//!
+//! The server pings each connection and drops it when no traffic comes
+//! back in time. Every new subscription, including the one made on
+//! connect, starts with the recent critical events from the replay
+//! buffer, so a client that reconnects after a dropped connection, or is
+//! cut off for falling behind, catches up on the session's state.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{header, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use tracing::{debug, info, warn};
+use trinkets::ToolRegistry;
+
+use crate::channel::{ChannelBuilder, GoblinChannel, LagPolicy};
+use crate::error::GoblinError;
+use crate::filter::FilterSpec;
+use crate::orchestrator::Orchestrator;
+use crate::protocol::{GoblinEvent, GoblinOp};
+use crate::stdio::{self, Envelope};
+
+/// Events buffered per connection by default
+pub const DEFAULT_BUFFER: usize = 1024;
+
+/// Critical events kept per session for resync by default
+pub const DEFAULT_REPLAY: usize = 256;
+
+/// A message sent by a client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WsClientMessage {
+    /// Replace the connection's subscription, replaying recent events
+    /// that match it
+    Subscribe(FilterSpec),
+    /// Submit an operation
+    Op(GoblinOp),
+}
+
+/// A message sent by the server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WsServerMessage {
+    /// The connection is ready
+    Welcome {
+        /// Crate version
+        version: String,
+    },
+    /// An orchestrator event
+    Event(GoblinEvent),
+    /// A message could not be handled
+    Error { message: String },
+}
+
+/// WebSocket server in front of an orchestrator
+pub struct WebSocketServer {
+    addr: SocketAddr,
+    ping_interval: Duration,
+    pong_timeout: Duration,
+    buffer: usize,
+    replay: usize,
+    token: Option<String>,
+}
+
+impl WebSocketServer {
+    /// Create a server listening on `addr`
+    pub fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            ping_interval: Duration::from_secs(30),
+            pong_timeout: Duration::from_secs(10),
+            buffer: DEFAULT_BUFFER,
+            replay: DEFAULT_REPLAY,
+            token: None,
+        }
+    }
+
+    /// Require clients to send `Authorization: Bearer <token>` when they
+    /// connect
+    pub fn with_token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Set how long a connection may stay silent before it is pinged
+    pub fn with_ping_interval(mut self, interval: Duration) -> Self {
+        self.ping_interval = interval;
+        self
+    }
+
+    /// Set how long after a ping a silent connection is dropped
+    pub fn with_pong_timeout(mut self, timeout: Duration) -> Self {
+        self.pong_timeout = timeout;
+        self
+    }
+
+    /// Set how many unread events a connection may fall behind by before
+    /// it is cut off
+    pub fn with_buffer(mut self, events: usize) -> Self {
+        self.buffer = events;
+        self
+    }
+
+    /// Set how many critical events per session are replayed on resync
+    pub fn with_replay(mut self, events: usize) -> Self {
+        self.replay = events;
+        self
+    }
+
+    /// Run an orchestrator behind the server until it stops
+    ///
+    /// `setup` gets the orchestrator before the listener is bound, so its
+    /// options and rules hold for the first client that connects. Clients
+    /// that fall behind are cut off rather than slowing the others down.
+    pub async fn run(
+        self,
+        tools: ToolRegistry,
+        setup: impl FnOnce(Orchestrator) -> Orchestrator,
+    ) -> Result<(), GoblinError> {
+        // A client that falls behind is cut off and resyncs on reconnect
+        let (channel, pair) = ChannelBuilder::new()
+            .broadcast(self.buffer)
+            .lag(LagPolicy::Disconnect)
+            .replay(self.replay)
+            .build();
+        let orchestrator = setup(Orchestrator::new(tools, pair));
+
+        tokio::select! {
+            result = orchestrator.run() => result,
+            result = self.serve(channel) => result,
+        }
+    }
+
+    /// Bind the address and serve clients on a broadcast `channel`
+    pub async fn serve(self, channel: GoblinChannel) -> Result<(), GoblinError> {
+        let listener = TcpListener::bind(self.addr)
+            .await
+            .map_err(|e| GoblinError::TransportError(format!("{}: {}", self.addr, e)))?;
+        self.accept(listener, channel).await
+    }
+
+    /// Serve clients connecting to `listener` on a broadcast `channel`
+    pub async fn accept(self, listener: TcpListener, channel: GoblinChannel) -> Result<(), GoblinError> {
+        if channel.subscribe().is_none() {
+            return Err(GoblinError::ConfigError(
+                "WebSocket server needs a broadcast channel".into(),
+            ));
+        }
+        info!(addr = ?listener.local_addr().ok(), "WebSocket server listening");
+
+        loop {
+            let (stream, peer) = listener
+                .accept()
+                .await
+                .map_err(|e| GoblinError::TransportError(e.to_string()))?;
+            if channel.is_closed() {
+                return Ok(());
+            }
+            let channel = channel.clone();
+            let keepalive = (self.ping_interval, self.pong_timeout);
+            let token = self.token.clone();
+            tokio::spawn(async move {
+                if let Err(e) = handle_connection(stream, channel, token, keepalive).await {
+                    debug!(peer = %peer, error = %e, "WebSocket connection closed with error");
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection(
+    stream: TcpStream,
+    channel: GoblinChannel,
+    token: Option<String>,
+    (ping_interval, pong_timeout): (Duration, Duration),
+) -> Result<(), GoblinError> {
+    let authorize = |request: &Request, response: Response| -> Result<Response, ErrorResponse> {
+        let Some(token) = token else {
+            return Ok(response);
+        };
+        let bearer = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if bearer == Some(token.as_str()) {
+            return Ok(response);
+        }
+        let mut rejection = ErrorResponse::new(Some("invalid or missing token".into()));
+        *rejection.status_mut() = StatusCode::UNAUTHORIZED;
+        Err(rejection)
+    };
+    let ws = tokio_tungstenite::accept_hdr_async(stream, authorize).await.map_err(ws)?;
+    let (mut sink, mut incoming) = ws.split();
+    let Some(mut events) = channel.subscribe() else {
+        return Ok(());
+    };
+    send(&mut sink, WsServerMessage::Welcome {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    })
+    .await?;
+
+    // Fires after `ping_interval` of silence to send a ping, then after
+    // `pong_timeout` more to drop the connection; any frame resets it
+    let keepalive = tokio::time::sleep(ping_interval);
+    tokio::pin!(keepalive);
+    let mut pinged = false;
+
+    let reason = loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Some(event) => send(&mut sink, WsServerMessage::Event(event)).await?,
+                None if channel.is_closed() => break "orchestrator stopped",
+                None => break "fell behind, reconnect to resync",
+            },
+            message = incoming.next() => {
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => return Err(ws(e)),
+                    None => return Ok(()),
+                };
+                keepalive.as_mut().reset(Instant::now() + ping_interval);
+                pinged = false;
+                match message {
+                    Message::Text(text) => match stdio::decode::<WsClientMessage>(&text) {
+                        Ok(WsClientMessage::Subscribe(spec)) => {
+                            if let Some(subscription) = channel.subscribe_filtered(spec.build()) {
+                                events = subscription;
+                            }
+                        }
+                        Ok(WsClientMessage::Op(op)) => {
+                            if channel.send(op).is_err() {
+                                break "orchestrator stopped";
+                            }
+                        }
+                        Err(message) => {
+                            warn!(error = %message, "Malformed WebSocket message");
+                            send(&mut sink, WsServerMessage::Error { message }).await?;
+                        }
+                    },
+                    Message::Close(_) => return Ok(()),
+                    // Pings are answered by the protocol layer; any frame
+                    // counts as a sign of life
+                    _ => {}
+                }
+            }
+            _ = &mut keepalive => {
+                if pinged {
+                    break "keepalive timeout";
+                }
+                sink.send(Message::Ping(Vec::new())).await.map_err(ws)?;
+                keepalive.as_mut().reset(Instant::now() + pong_timeout);
+                pinged = true;
+            }
+        }
+    };
+
+    debug!(reason, "Closing WebSocket connection");
+    let close = CloseFrame {
+        code: CloseCode::Away,
+        reason: reason.into(),
+    };
+    let _ = sink.send(Message::Close(Some(close))).await;
+    Ok(())
+}
+
+async fn send(
+    sink: &mut futures::stream::SplitSink<WebSocketStream<TcpStream>, Message>,
+    message: WsServerMessage,
+) -> Result<(), GoblinError> {
+    let text = serde_json::to_string(&Envelope::new(message))
+        .map_err(|e| GoblinError::TransportError(e.to_string()))?;
+    sink.send(Message::Text(text)).await.map_err(ws)
+}
+
+fn ws(e: tokio_tungstenite::tungstenite::Error) -> GoblinError {
+    GoblinError::TransportError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::EventCategory;
+    use tokio_tungstenite::connect_async;
+    use warhorn::{Event, Op, SubmissionId, TaskId};
+
+    async fn listen(server: WebSocketServer, channel: GoblinChannel) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(server.accept(listener, channel));
+        url
+    }
+
+    fn server() -> WebSocketServer {
+        WebSocketServer::new(([127, 0, 0, 1], 0).into())
+    }
+
+    fn text(message: &WsClientMessage) -> Message {
+        Message::Text(serde_json::to_string(&Envelope::new(message)).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_subscription_filters_events_and_ops_reach_orchestrator() {
+        let (channel, mut pair) = ChannelBuilder::new().broadcast(16).build();
+        let url = listen(server(), channel).await;
+        let (mut client, _) = connect_async(&url).await.unwrap();
+
+        let welcome = client.next().await.unwrap().unwrap();
+        let welcome: Envelope<WsServerMessage> = serde_json::from_str(welcome.to_text().unwrap()).unwrap();
+        assert!(matches!(welcome.body, WsServerMessage::Welcome { .. }));
+
+        let spec = FilterSpec {
+            categories: vec![EventCategory::Tasks],
+            ..FilterSpec::default()
+        };
+        client.send(text(&WsClientMessage::Subscribe(spec))).await.unwrap();
+        let op: GoblinOp = Op::interrupt().into();
+        client.send(text(&WsClientMessage::Op(op.clone()))).await.unwrap();
+        let received = tokio::time::timeout(Duration::from_secs(1), pair.op_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(received.sub_id(), op.sub_id());
+
+        // The subscription was replaced before the op was forwarded
+        let task_id = TaskId::new();
+        let warning = Event::Warning { sub_id: SubmissionId::new(), message: "slow".into(), details: None };
+        pair.event_tx.send(warning.into()).unwrap();
+        pair.event_tx.send(Event::TaskStarted { sub_id: SubmissionId::new(), task_id }.into()).unwrap();
+
+        let message = tokio::time::timeout(Duration::from_secs(1), client.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let envelope: Envelope<WsServerMessage> = serde_json::from_str(message.to_text().unwrap()).unwrap();
+        match envelope.body {
+            WsServerMessage::Event(event) => assert_eq!(event.describe().unwrap().0, "TaskStarted"),
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_silent_connection_is_dropped() {
+        let (channel, _pair) = ChannelBuilder::new().broadcast(16).build();
+        let server = server()
+            .with_ping_interval(Duration::from_millis(20))
+            .with_pong_timeout(Duration::from_millis(20));
+        let url = listen(server, channel).await;
+        let (mut client, _) = connect_async(&url).await.unwrap();
+
+        // Not reading means pings go unanswered
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let closed = tokio::time::timeout(Duration::from_secs(1), async {
+            while let Some(Ok(message)) = client.next().await {
+                if let Message::Close(frame) = message {
+                    return frame.map(|f| f.reason.to_string());
+                }
+            }
+            None
+        })
+        .await
+        .unwrap();
+        assert_eq!(closed.as_deref(), Some("keepalive timeout"));
+    }
+
+    #[tokio::test]
+    async fn test_token_is_required_when_set() {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let (channel, _pair) = ChannelBuilder::new().broadcast(16).build();
+        let url = listen(server().with_token("secret"), channel).await;
+
+        match connect_async(&url).await {
+            Err(tokio_tungstenite::tungstenite::Error::Http(response)) => {
+                assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+            }
+            other => panic!("unexpected handshake result: {:?}", other.map(|_| ())),
+        }
+
+        let mut request = url.as_str().into_client_request().unwrap();
+        request
+            .headers_mut()
+            .insert(header::AUTHORIZATION, "Bearer secret".parse().unwrap());
+        let (mut client, _) = connect_async(request).await.unwrap();
+        let welcome = client.next().await.unwrap().unwrap();
+        let welcome: Envelope<WsServerMessage> = serde_json::from_str(welcome.to_text().unwrap()).unwrap();
+        assert!(matches!(welcome.body, WsServerMessage::Welcome { .. }));
+    }
+}
@@ -0,0 +1,542 @@
+//! Declarative multi-stage workflows
+//!
+//! A [`Workflow`] describes a repeatable process as a set of stages. Each
+//! stage names the role and number of agents that carry it out, the stages
+//! it waits for (a stage waiting on several others is a merge point), and an
+//! optional gate that must be passed before it starts.
+//!
+//! Workflows can be built in code or loaded from a JSON file. The
+//! orchestrator executes them through a [`WorkflowRun`], which tracks stage
+//! progress and tells the orchestrator which stages are ready to start.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use warhorn::{AgentId, AgentRole, TaskId};
+
+use crate::error::GoblinError;
+use crate::plan::{PlanStatus, PlannedTask, TaskPlan};
+
+/// A declarative multi-stage process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Workflow {
+    /// Workflow name
+    pub name: String,
+    /// Stages, in declaration order
+    pub stages: Vec<Stage>,
+}
+
+/// A single workflow stage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stage {
+    /// Unique stage name
+    pub name: String,
+    /// Role of the agents executing this stage
+    pub role: AgentRole,
+    /// Instructions given to the stage's agents
+    pub instructions: String,
+    /// Number of agents working the stage in parallel
+    #[serde(default = "default_agents")]
+    pub agents: usize,
+    /// Stages that must complete first
+    ///
+    /// `None` means the previous stage in declaration order; an empty list
+    /// means the stage can start immediately.
+    #[serde(default)]
+    pub after: Option<Vec<String>>,
+    /// Gate that must be passed before the stage starts
+    #[serde(default)]
+    pub gate: Gate,
+}
+
+fn default_agents() -> usize {
+    1
+}
+
+/// Condition for starting a stage once its dependencies are complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Gate {
+    /// Start as soon as dependencies complete
+    #[default]
+    Auto,
+    /// Wait for explicit approval from the client
+    Approval,
+}
+
+impl Stage {
+    /// Create a stage executed by a single agent
+    pub fn new(name: impl Into<String>, role: AgentRole, instructions: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            role,
+            instructions: instructions.into(),
+            agents: 1,
+            after: None,
+            gate: Gate::Auto,
+        }
+    }
+
+    /// Set the number of parallel agents
+    pub fn with_agents(mut self, agents: usize) -> Self {
+        self.agents = agents;
+        self
+    }
+
+    /// Wait for the given stages instead of the previous one
+    pub fn after<I, S>(mut self, stages: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.after = Some(stages.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Require client approval before starting
+    pub fn with_gate(mut self, gate: Gate) -> Self {
+        self.gate = gate;
+        self
+    }
+}
+
+impl Workflow {
+    /// Create an empty workflow
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Append a stage
+    pub fn stage(mut self, stage: Stage) -> Self {
+        self.stages.push(stage);
+        self
+    }
+
+    /// Parse a workflow from JSON
+    pub fn from_json(json: &str) -> Result<Self, GoblinError> {
+        let workflow: Self = serde_json::from_str(json)
+            .map_err(|e| GoblinError::ConfigError(format!("Invalid workflow: {}", e)))?;
+        workflow.validate()?;
+        Ok(workflow)
+    }
+
+    /// Load a workflow from a JSON file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, GoblinError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            GoblinError::ConfigError(format!("Failed to read workflow {}: {}", path.display(), e))
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// Dependencies of a stage, by index
+    fn dependencies(&self, index: usize) -> Vec<usize> {
+        match &self.stages[index].after {
+            None if index == 0 => Vec::new(),
+            None => vec![index - 1],
+            Some(names) => names
+                .iter()
+                .filter_map(|n| self.stages.iter().position(|s| &s.name == n))
+                .collect(),
+        }
+    }
+
+    /// Check names, references, agent counts, and that there are no cycles
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        if self.stages.is_empty() {
+            return Err(GoblinError::ConfigError(format!(
+                "Workflow '{}' has no stages",
+                self.name
+            )));
+        }
+
+        let mut names = HashSet::new();
+        for stage in &self.stages {
+            if !names.insert(stage.name.as_str()) {
+                return Err(GoblinError::ConfigError(format!(
+                    "Duplicate workflow stage '{}'",
+                    stage.name
+                )));
+            }
+            if stage.agents == 0 {
+                return Err(GoblinError::ConfigError(format!(
+                    "Workflow stage '{}' needs at least one agent",
+                    stage.name
+                )));
+            }
+        }
+
+        for stage in &self.stages {
+            for dep in stage.after.iter().flatten() {
+                if !names.contains(dep.as_str()) {
+                    return Err(GoblinError::ConfigError(format!(
+                        "Workflow stage '{}' waits for unknown stage '{}'",
+                        stage.name, dep
+                    )));
+                }
+            }
+        }
+
+        // Kahn's algorithm: every stage must become ready eventually
+        let deps: Vec<Vec<usize>> = (0..self.stages.len()).map(|i| self.dependencies(i)).collect();
+        let mut done = vec![false; self.stages.len()];
+        let mut remaining = self.stages.len();
+        loop {
+            let ready: Vec<usize> = (0..self.stages.len())
+                .filter(|&i| !done[i] && deps[i].iter().all(|&d| done[d]))
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+            for i in ready {
+                done[i] = true;
+                remaining -= 1;
+            }
+        }
+
+        if remaining > 0 {
+            return Err(GoblinError::ConfigError(format!(
+                "Workflow '{}' has a dependency cycle",
+                self.name
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Execution state of a stage
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StageState {
+    /// Waiting for dependencies
+    Pending,
+    /// Dependencies complete, waiting for gate approval
+    AwaitingApproval,
+    /// Gate approved, ready to start
+    Approved,
+    /// Agents are working on the stage
+    Running,
+    /// Finished successfully
+    Completed,
+    /// Finished with an error
+    Failed,
+    /// Skipped because the workflow failed
+    Cancelled,
+}
+
+/// A workflow being executed for a task
+#[derive(Debug, Clone)]
+pub struct WorkflowRun {
+    /// The workflow definition
+    pub workflow: Workflow,
+    /// Task the workflow was started for
+    pub task_id: TaskId,
+    /// Plan mirroring the workflow's stages and agent slots
+    plan: TaskPlan,
+    /// Per-stage execution state
+    states: Vec<StageState>,
+    /// Plan task per stage
+    stage_tasks: Vec<TaskId>,
+    /// Plan subtasks per stage (one per agent slot)
+    subtasks: Vec<Vec<TaskId>>,
+    /// Agents currently executing each stage
+    agents: HashMap<usize, Vec<AgentId>>,
+}
+
+impl WorkflowRun {
+    /// Start tracking a validated workflow for a task
+    pub fn new(workflow: Workflow, task_id: TaskId, prompt: &str) -> Result<Self, GoblinError> {
+        workflow.validate()?;
+
+        let mut plan = TaskPlan::new(task_id, prompt);
+        let mut stage_ids: Vec<TaskId> = Vec::with_capacity(workflow.stages.len());
+        let mut subtasks = Vec::with_capacity(workflow.stages.len());
+
+        for (i, stage) in workflow.stages.iter().enumerate() {
+            let mut stage_task = PlannedTask::new(stage.name.clone(), stage.role.clone());
+            for dep in workflow.dependencies(i) {
+                stage_task = stage_task.depends_on(stage_ids[dep]);
+            }
+            let stage_id = plan.add(stage_task);
+            stage_ids.push(stage_id);
+
+            let slots = (0..stage.agents)
+                .map(|_| {
+                    plan.add(
+                        PlannedTask::new(stage.instructions.clone(), stage.role.clone())
+                            .with_parent(stage_id),
+                    )
+                })
+                .collect();
+            subtasks.push(slots);
+        }
+
+        Ok(Self {
+            states: vec![StageState::Pending; workflow.stages.len()],
+            workflow,
+            task_id,
+            plan,
+            stage_tasks: stage_ids,
+            subtasks,
+            agents: HashMap::new(),
+        })
+    }
+
+    /// Plan view of the workflow, for rendering and status reporting
+    pub fn plan(&self) -> &TaskPlan {
+        &self.plan
+    }
+
+    /// State of a stage by name
+    pub fn state(&self, stage: &str) -> Option<&StageState> {
+        self.index(stage).ok().map(|i| &self.states[i])
+    }
+
+    fn index(&self, stage: &str) -> Result<usize, GoblinError> {
+        self.workflow
+            .stages
+            .iter()
+            .position(|s| s.name == stage)
+            .ok_or_else(|| GoblinError::InvalidState(format!("Unknown workflow stage '{}'", stage)))
+    }
+
+    /// Promote stages whose dependencies completed and return those ready to start
+    ///
+    /// Gated stages move to `AwaitingApproval` instead and are returned only
+    /// once approved.
+    pub fn ready_stages(&mut self) -> Vec<usize> {
+        let mut ready = Vec::new();
+
+        for i in 0..self.states.len() {
+            match self.states[i] {
+                StageState::Pending => {
+                    let deps_done = self
+                        .workflow
+                        .dependencies(i)
+                        .iter()
+                        .all(|&d| self.states[d] == StageState::Completed);
+                    if !deps_done {
+                        continue;
+                    }
+                    if self.workflow.stages[i].gate == Gate::Approval {
+                        self.states[i] = StageState::AwaitingApproval;
+                    } else {
+                        ready.push(i);
+                    }
+                }
+                StageState::Approved => ready.push(i),
+                _ => {}
+            }
+        }
+
+        ready
+    }
+
+    /// Stage definition by index
+    pub fn stage(&self, index: usize) -> &Stage {
+        &self.workflow.stages[index]
+    }
+
+    /// Plan subtasks for a stage's agent slots
+    pub fn stage_subtasks(&self, index: usize) -> &[TaskId] {
+        &self.subtasks[index]
+    }
+
+    /// Approve a gated stage
+    pub fn approve(&mut self, stage: &str) -> Result<(), GoblinError> {
+        let i = self.index(stage)?;
+        if self.states[i] != StageState::AwaitingApproval {
+            return Err(GoblinError::InvalidState(format!(
+                "Workflow stage '{}' is not awaiting approval",
+                stage
+            )));
+        }
+        self.states[i] = StageState::Approved;
+        Ok(())
+    }
+
+    /// Record that a stage was started by the given agents
+    pub fn mark_running(&mut self, index: usize, agents: Vec<AgentId>) {
+        self.states[index] = StageState::Running;
+        self.set_stage_plan_status(index, PlanStatus::Running);
+        self.agents.insert(index, agents);
+    }
+
+    /// Return a stage whose agents could not be started to the state it was
+    /// started from, so a later advance starts it again
+    pub fn unmark_running(&mut self, index: usize) {
+        self.states[index] = if self.workflow.stages[index].gate == Gate::Approval {
+            StageState::Approved
+        } else {
+            StageState::Pending
+        };
+        self.set_stage_plan_status(index, PlanStatus::Pending);
+        self.agents.remove(&index);
+    }
+
+    /// Record the outcome of a running stage, returning the agents that worked on it
+    ///
+    /// A failed stage cancels every stage that has not started yet.
+    pub fn finish_stage(&mut self, stage: &str, success: bool) -> Result<Vec<AgentId>, GoblinError> {
+        let i = self.index(stage)?;
+        if self.states[i] != StageState::Running {
+            return Err(GoblinError::InvalidState(format!(
+                "Workflow stage '{}' is not running",
+                stage
+            )));
+        }
+
+        let status = if success { PlanStatus::Completed } else { PlanStatus::Failed };
+        self.states[i] = if success { StageState::Completed } else { StageState::Failed };
+        self.set_stage_plan_status(i, status);
+
+        if !success {
+            for j in 0..self.states.len() {
+                if matches!(
+                    self.states[j],
+                    StageState::Pending | StageState::AwaitingApproval | StageState::Approved
+                ) {
+                    self.states[j] = StageState::Cancelled;
+                    self.set_stage_plan_status(j, PlanStatus::Cancelled);
+                }
+            }
+        }
+
+        Ok(self.agents.remove(&i).unwrap_or_default())
+    }
+
+    fn set_stage_plan_status(&mut self, index: usize, status: PlanStatus) {
+        self.plan.set_status(&self.stage_tasks[index], status);
+        for id in &self.subtasks[index] {
+            self.plan.set_status(id, status);
+        }
+    }
+
+    /// Check whether every stage has finished (successfully or not)
+    pub fn is_finished(&self) -> bool {
+        self.states.iter().all(|s| {
+            matches!(s, StageState::Completed | StageState::Failed | StageState::Cancelled)
+        })
+    }
+
+    /// Check whether every stage completed successfully
+    pub fn is_successful(&self) -> bool {
+        self.states.iter().all(|s| *s == StageState::Completed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review_workflow() -> Workflow {
+        Workflow::new("feature")
+            .stage(Stage::new("research", AgentRole::Worker, "Research the codebase"))
+            .stage(Stage::new("backend", AgentRole::Worker, "Implement the API").with_agents(2))
+            .stage(Stage::new("frontend", AgentRole::Worker, "Implement the UI").after(["research"]))
+            .stage(
+                Stage::new("review", AgentRole::Worker, "Review all changes")
+                    .after(["backend", "frontend"])
+                    .with_gate(Gate::Approval),
+            )
+    }
+
+    #[test]
+    fn test_validate_ok() {
+        assert!(review_workflow().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_definitions() {
+        let dup = Workflow::new("dup")
+            .stage(Stage::new("a", AgentRole::Worker, "x"))
+            .stage(Stage::new("a", AgentRole::Worker, "y"));
+        assert!(dup.validate().is_err());
+
+        let unknown = Workflow::new("unknown")
+            .stage(Stage::new("a", AgentRole::Worker, "x").after(["missing"]));
+        assert!(unknown.validate().is_err());
+
+        let cycle = Workflow::new("cycle")
+            .stage(Stage::new("a", AgentRole::Worker, "x").after(["b"]))
+            .stage(Stage::new("b", AgentRole::Worker, "y").after(["a"]));
+        assert!(cycle.validate().is_err());
+
+        assert!(Workflow::new("empty").validate().is_err());
+    }
+
+    #[test]
+    fn test_from_json() {
+        let role = serde_json::to_value(AgentRole::Worker).unwrap();
+        let json = serde_json::json!({
+            "name": "triage",
+            "stages": [
+                { "name": "collect", "role": role, "instructions": "Collect issues" },
+                { "name": "label", "role": role, "instructions": "Label issues", "agents": 3 }
+            ]
+        });
+
+        let workflow = Workflow::from_json(&json.to_string()).unwrap();
+        assert_eq!(workflow.stages.len(), 2);
+        assert_eq!(workflow.stages[1].agents, 3);
+        assert_eq!(workflow.stages[1].gate, Gate::Auto);
+        assert!(workflow.stages[1].after.is_none());
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let json = serde_json::to_string(&review_workflow()).unwrap();
+        let workflow = Workflow::from_json(&json).unwrap();
+        assert_eq!(workflow.stages[3].gate, Gate::Approval);
+    }
+
+    #[test]
+    fn test_run_progression() {
+        let mut run = WorkflowRun::new(review_workflow(), TaskId::new(), "Add login").unwrap();
+        assert_eq!(run.plan().len(), 4 + 5);
+
+        // Only research has no dependencies
+        assert_eq!(run.ready_stages(), vec![0]);
+        run.mark_running(0, vec![AgentId::new()]);
+        assert!(run.ready_stages().is_empty());
+
+        // Research unlocks backend and frontend in parallel
+        assert_eq!(run.finish_stage("research", true).unwrap().len(), 1);
+        assert_eq!(run.ready_stages(), vec![1, 2]);
+        run.mark_running(1, vec![AgentId::new(), AgentId::new()]);
+        run.mark_running(2, vec![AgentId::new()]);
+
+        // Review is a merge point behind an approval gate
+        run.finish_stage("backend", true).unwrap();
+        assert!(run.ready_stages().is_empty());
+        run.finish_stage("frontend", true).unwrap();
+        assert!(run.ready_stages().is_empty());
+        assert_eq!(run.state("review"), Some(&StageState::AwaitingApproval));
+
+        run.approve("review").unwrap();
+        assert_eq!(run.ready_stages(), vec![3]);
+        run.mark_running(3, vec![AgentId::new()]);
+        run.finish_stage("review", true).unwrap();
+
+        assert!(run.is_finished());
+        assert!(run.is_successful());
+        assert!(run.plan().tasks().iter().all(|t| t.status == PlanStatus::Completed));
+    }
+
+    #[test]
+    fn test_run_failure_cancels_remaining() {
+        let mut run = WorkflowRun::new(review_workflow(), TaskId::new(), "Add login").unwrap();
+        run.ready_stages();
+        run.mark_running(0, vec![AgentId::new()]);
+
+        run.finish_stage("research", false).unwrap();
+
+        assert!(run.is_finished());
+        assert!(!run.is_successful());
+        assert_eq!(run.state("review"), Some(&StageState::Cancelled));
+        assert!(run.finish_stage("backend", true).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Per-agent scratch workspaces
+//!
+//! Each agent can be given a private scratch directory for temporary files so
+//! that workers don't litter the shared working directory. The directory is
+//! removed when the agent terminates, or when the [`ScratchDir`] is dropped.
+
+use std::path::{Component, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+use warhorn::AgentId;
+
+use crate::error::GoblinError;
+
+/// Environment variable under which tools find the agent's scratch directory
+pub const SCRATCH_DIR_ENV: &str = "CABAL_SCRATCH_DIR";
+
+/// Scratch workspace configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScratchConfig {
+    /// Parent directory for scratch spaces (defaults to the system temp dir)
+    pub root: Option<PathBuf>,
+    /// Relative subdirectories created inside every scratch space
+    pub layout: Vec<PathBuf>,
+    /// Keep the directory after the agent terminates (for debugging)
+    pub keep: bool,
+}
+
+impl ScratchConfig {
+    /// Check that the layout only contains plain relative paths
+    pub fn validate(&self) -> Result<(), GoblinError> {
+        for entry in &self.layout {
+            let plain = entry
+                .components()
+                .all(|c| matches!(c, Component::Normal(_)));
+            if !plain || entry.as_os_str().is_empty() {
+                return Err(GoblinError::ConfigError(format!(
+                    "Invalid scratch layout entry: {}",
+                    entry.display()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A provisioned scratch directory owned by an agent
+#[derive(Debug)]
+pub struct ScratchDir {
+    path: PathBuf,
+    keep: bool,
+}
+
+impl ScratchDir {
+    /// Create the scratch directory and its layout for an agent
+    pub fn provision(config: &ScratchConfig, agent_id: AgentId) -> Result<Self, GoblinError> {
+        config.validate()?;
+
+        let root = config.root.clone().unwrap_or_else(std::env::temp_dir);
+        let path = root.join(format!("cabal-{}", agent_id));
+
+        std::fs::create_dir_all(&path).map_err(|e| workspace_error(&path, e))?;
+        // Own the directory from here on so a failed layout still cleans up
+        let dir = Self { path, keep: config.keep };
+
+        for entry in &config.layout {
+            let sub = dir.path.join(entry);
+            std::fs::create_dir_all(&sub).map_err(|e| workspace_error(&sub, e))?;
+        }
+
+        debug!(agent_id = %agent_id, path = %dir.path.display(), "Provisioned scratch directory");
+        Ok(dir)
+    }
+
+    /// Path of the scratch directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Remove the scratch directory now, reporting failures
+    pub fn cleanup(mut self) -> Result<(), GoblinError> {
+        let result = self.remove();
+        // Already handled, don't retry on drop
+        self.keep = true;
+        result
+    }
+
+    fn remove(&self) -> Result<(), GoblinError> {
+        if self.keep || !self.path.exists() {
+            return Ok(());
+        }
+        std::fs::remove_dir_all(&self.path).map_err(|e| workspace_error(&self.path, e))
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        if let Err(e) = self.remove() {
+            warn!(error = %e, "Failed to clean up scratch directory");
+        }
+    }
+}
+
+fn workspace_error(path: &Path, e: std::io::Error) -> GoblinError {
+    GoblinError::WorkspaceError(format!("{}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_in(root: &Path) -> ScratchConfig {
+        ScratchConfig {
+            root: Some(root.to_path_buf()),
+            layout: vec!["tmp".into(), "out/logs".into()],
+            keep: false,
+        }
+    }
+
+    #[test]
+    fn test_provision_creates_layout() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = ScratchDir::provision(&config_in(root.path()), AgentId::new()).unwrap();
+
+        assert!(dir.path().starts_with(root.path()));
+        assert!(dir.path().join("tmp").is_dir());
+        assert!(dir.path().join("out/logs").is_dir());
+    }
+
+    #[test]
+    fn test_cleanup_and_drop_remove_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let config = config_in(root.path());
+
+        let dir = ScratchDir::provision(&config, AgentId::new()).unwrap();
+        let path = dir.path().to_path_buf();
+        dir.cleanup().unwrap();
+        assert!(!path.exists());
+
+        let dir = ScratchDir::provision(&config, AgentId::new()).unwrap();
+        let path = dir.path().to_path_buf();
+        drop(dir);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_keep_preserves_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let config = ScratchConfig { keep: true, ..config_in(root.path()) };
+
+        let dir = ScratchDir::provision(&config, AgentId::new()).unwrap();
+        let path = dir.path().to_path_buf();
+        drop(dir);
+        assert!(path.exists());
+    }
+
+    #[test]
+    fn test_layout_rejects_escaping_paths() {
+        let config = ScratchConfig {
+            layout: vec!["../outside".into()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = ScratchConfig {
+            layout: vec!["/abs".into()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}